@@ -0,0 +1,51 @@
+//! Benchmarks of the scanning of large metadata headers.
+//!
+//! Run with `cargo bench --bench scan`.
+
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use nom_exif::*;
+
+/// Returns `path` with `count` APP1 segments of 64 KiB inserted after SOI,
+/// like the XMP previews some editors embed.
+fn with_leading_segments(path: &str, count: usize, payload: &[u8]) -> Vec<u8> {
+    let data = std::fs::read(path).unwrap();
+    let mut segment = vec![0xff, 0xe1];
+    segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    segment.extend_from_slice(payload);
+
+    let mut out = data[..2].to_vec();
+    for _ in 0..count {
+        out.extend_from_slice(&segment);
+    }
+    out.extend_from_slice(&data[2..]);
+    out
+}
+
+/// An XMP packet of about 64 KiB, whose capture time comes last.
+fn large_xmp() -> Vec<u8> {
+    let mut xmp = b"http://ns.adobe.com/xap/1.0/\0".to_vec();
+    xmp.extend_from_slice(br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF><rdf:Description "#);
+    while xmp.len() < 65000 {
+        xmp.extend_from_slice(br#"xmp:Label="a label which is not a date" "#);
+    }
+    xmp.extend_from_slice(br#"xmp:CreateDate="2021-05-01T10:00:00+02:00"/></rdf:RDF></x:xmpmeta>"#);
+    xmp
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let padding = vec![0x20; 65000];
+    let jpeg = with_leading_segments("./testdata/exif.jpg", 64, &padding);
+    c.bench_function("parse_exif with 4 MiB of leading APP1", |b| {
+        b.iter(|| parse_exif(Cursor::new(&jpeg), None).unwrap().unwrap())
+    });
+
+    let jpeg = with_leading_segments("./testdata/no-exif.jpg", 1, &large_xmp());
+    c.bench_function("best_capture_time from a 64 KiB XMP packet", |b| {
+        b.iter(|| best_capture_time(Cursor::new(&jpeg)).unwrap().unwrap())
+    });
+}
+
+criterion_group!(benches, bench_scan);
+criterion_main!(benches);
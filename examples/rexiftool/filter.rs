@@ -0,0 +1,67 @@
+/// Tag filters, in the style of ExifTool: `-DateTimeOriginal` only prints
+/// the given tags, `--Make` doesn't print them. Names are case-insensitive,
+/// and may contain `*` wildcards, e.g. `-GPS*`.
+#[derive(Debug, Default)]
+pub struct TagFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl TagFilter {
+    /// Takes the tag filters out of the command line arguments: arguments
+    /// with one or two dashes followed by an uppercase letter, which aren't
+    /// options.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> (TagFilter, Vec<String>) {
+        let mut filter = TagFilter::default();
+        let mut rest = Vec::new();
+        for arg in args {
+            if let Some(name) = arg.strip_prefix("--").filter(|x| is_tag(x)) {
+                filter.exclude.push(name.to_ascii_lowercase());
+            } else if let Some(name) = arg.strip_prefix('-').filter(|x| is_tag(x)) {
+                filter.include.push(name.to_ascii_lowercase());
+            } else {
+                rest.push(arg);
+            }
+        }
+        (filter, rest)
+    }
+
+    /// Whether the tag or key `name` is printed. Keys of videos also match
+    /// by their last component, e.g. `-Make` matches
+    /// `com.apple.quicktime.make`.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        let short = name.rsplit('.').next().unwrap_or(&name);
+        let any = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|x| glob_match(x, &name) || glob_match(x, short))
+        };
+        (self.include.is_empty() || any(&self.include)) && !any(&self.exclude)
+    }
+}
+
+fn is_tag(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// Matches `name` with `pattern`, where `*` matches any characters.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(mut name) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = rest.split('*').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return name.ends_with(part);
+        }
+        match name.find(part) {
+            Some(pos) => name = &name[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,235 @@
+use std::{
+    error::Error,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use nom_exif::{EntryValue, MediaInfo, MediaParser};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Registry};
+
+mod filter;
+
+use filter::TagFilter;
+
+/// Prints the metadata of images and videos.
+///
+/// Tags can be selected in the style of ExifTool: `-TAG` only prints TAG,
+/// `--TAG` doesn't print it. Tag names are case-insensitive and may
+/// contain `*` wildcards, e.g. `-GPS*`.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about)]
+struct Cli {
+    /// Files, or directories with `-r`.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    #[arg(short, long)]
+    json: bool,
+
+    /// Walk directories recursively.
+    #[arg(short, long)]
+    recursive: bool,
+
+    #[arg(long)]
+    debug: bool,
+}
+
+#[cfg(feature = "json_dump")]
+const FEATURE_JSON_DUMP_ON: bool = true;
+#[cfg(not(feature = "json_dump"))]
+const FEATURE_JSON_DUMP_ON: bool = false;
+
+type Values = Vec<(String, EntryValue)>;
+type Results = Box<dyn Iterator<Item = (PathBuf, nom_exif::Result<Option<MediaInfo>>)>>;
+
+fn main() -> ExitCode {
+    let (filter, args) = TagFilter::from_args(std::env::args());
+    let cli = Cli::parse_from(args);
+
+    tracing_run(&cli, &filter)
+}
+
+#[tracing::instrument(skip(filter))]
+fn tracing_run(cli: &Cli, filter: &TagFilter) -> ExitCode {
+    if cli.debug {
+        init_tracing().expect("init tracing failed");
+    }
+
+    match run(cli, filter) {
+        Ok(0) => ExitCode::SUCCESS,
+        // The errors have been printed
+        Ok(_) => ExitCode::FAILURE,
+        Err(err) => {
+            tracing::error!(?err);
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints the metadata of all the files, and returns the number of files
+/// which couldn't be parsed.
+///
+/// The files of directories are printed as soon as they are parsed, in no
+/// particular order, so that their metadata isn't all kept in memory.
+fn run(cli: &Cli, filter: &TagFilter) -> Result<usize, Box<dyn Error>> {
+    if cli.json && !FEATURE_JSON_DUMP_ON {
+        let msg = "-j/--json option requires the feature `json_dump`.";
+        return Err(msg.into());
+    }
+
+    // Only print the names of the files if there are several
+    let several = cli.files.len() > 1 || cli.files.iter().any(|x| x.is_dir());
+
+    let parser = MediaParser::default();
+    let mut failures = 0;
+    let mut printed = 0;
+    for path in &cli.files {
+        let results: Results = if path.is_dir() {
+            parse_dir(path, cli.recursive)?
+        } else {
+            let res = File::open(path)
+                .map_err(nom_exif::Error::from)
+                .and_then(|f| parser.parse_media(f));
+            Box::new(std::iter::once((path.clone(), res)))
+        };
+        for (path, res) in results {
+            match res {
+                Ok(info) => {
+                    let values = values(info, filter);
+                    if cli.json {
+                        #[cfg(feature = "json_dump")]
+                        print_json(&path, values, several, printed)?;
+                    } else {
+                        print_text(&path, values, several);
+                    }
+                    printed += 1;
+                }
+                Err(e) => {
+                    eprintln!("{}: {e}", path.display());
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    if cli.json && several {
+        // Closes the array of objects
+        println!("{}", if printed == 0 { "[]" } else { "]" });
+    }
+
+    Ok(failures)
+}
+
+/// Prints the values of the file at `path`, after its name if there are
+/// several files.
+fn print_text(path: &Path, values: Option<Values>, several: bool) {
+    if several {
+        println!("======== {}", path.display());
+    }
+    match values {
+        Some(values) => values.iter().for_each(|x| {
+            println!("{:<40}=> {}", x.0, x.1);
+        }),
+        None => println!("Exif data not found in {}.", path.display()),
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn parse_dir(path: &Path, recursive: bool) -> Result<Results, Box<dyn Error>> {
+    use nom_exif::batch::{self, BatchOptions};
+
+    let options = BatchOptions {
+        recursive,
+        ..Default::default()
+    };
+    Ok(Box::new(batch::parse_dir(path, options)))
+}
+
+#[cfg(not(feature = "rayon"))]
+fn parse_dir(path: &Path, _recursive: bool) -> Result<Results, Box<dyn Error>> {
+    let msg = format!(
+        "{} is a directory, parsing directories requires the feature `rayon`.",
+        path.display()
+    );
+    Err(msg.into())
+}
+
+/// Returns the values of the tags selected by `filter`, or `None` if an
+/// image has no Exif data.
+fn values(info: Option<MediaInfo>, filter: &TagFilter) -> Option<Values> {
+    let values = match info? {
+        MediaInfo::Exif(iter) => iter
+            .filter(|x| {
+                let name = x.tag().map_or_else(
+                    || format!("0x{:04x}", x.tag_code()),
+                    |x| x.info().name.to_owned(),
+                );
+                filter.matches(&name)
+            })
+            .filter_map(|x| {
+                let v = x.take_value()?;
+                Some((
+                    x.tag()
+                        .map(|x| x.to_string())
+                        .unwrap_or_else(|| format!("0x{:04x}", x.tag_code())),
+                    v,
+                ))
+            })
+            .collect(),
+        MediaInfo::Track(iter) => iter
+            .filter(|x| filter.matches(&x.0))
+            .filter_map(|(k, v)| Some((k, v.ok()?)))
+            .collect(),
+        _ => return None,
+    };
+    Some(values)
+}
+
+/// Prints the object of a file, or an element of an array of objects with
+/// a `SourceFile` key if there are several files, which is closed by the
+/// caller.
+#[cfg(feature = "json_dump")]
+fn print_json(
+    path: &Path,
+    values: Option<Values>,
+    several: bool,
+    index: usize,
+) -> serde_json::Result<()> {
+    use serde_json::{Map, Value};
+
+    let mut object = Map::new();
+    if several {
+        let path = path.display().to_string();
+        object.insert("SourceFile".into(), Value::String(path));
+    }
+    for (k, v) in values.unwrap_or_default() {
+        object.insert(k, serde_json::to_value(v)?);
+    }
+
+    let json = serde_json::to_string_pretty(&Value::Object(object))?;
+    match (several, index) {
+        (false, _) => println!("{json}"),
+        (true, 0) => println!("[\n{json}"),
+        (true, _) => println!(",\n{json}"),
+    }
+    Ok(())
+}
+
+fn init_tracing() -> io::Result<()> {
+    let stdout_log = tracing_subscriber::fmt::layer().pretty();
+    let subscriber = Registry::default().with(stdout_log);
+
+    let file = File::create("debug.log")?;
+    let debug_log = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(file);
+    let subscriber = subscriber.with(debug_log);
+
+    subscriber.init();
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+/*
+ * C API of nom-exif, built with the `capi` feature flag. See the
+ * documentation of the `capi` module for the details of each function.
+ */
+
+#ifndef NOMEXIF_H
+#define NOMEXIF_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The entries of a parsed file, freed with nomexif_entries_free(). */
+typedef struct NomExifEntries NomExifEntries;
+
+typedef enum NomExifValueType {
+    NOMEXIF_VALUE_NONE = 0,
+    NOMEXIF_VALUE_TEXT = 1,
+    NOMEXIF_VALUE_INTEGER = 2,
+    NOMEXIF_VALUE_FLOAT = 3,
+    NOMEXIF_VALUE_RATIONAL = 4,
+    NOMEXIF_VALUE_TIME = 5,
+    NOMEXIF_VALUE_OTHER = 6,
+} NomExifValueType;
+
+/* Returns NULL on error, see nomexif_last_error(). */
+NomExifEntries *nomexif_parse_file(const char *path);
+const char *nomexif_last_error(void);
+void nomexif_entries_free(NomExifEntries *entries);
+
+size_t nomexif_entries_len(const NomExifEntries *entries);
+
+/* The strings are owned by `entries`. */
+const char *nomexif_entry_key(const NomExifEntries *entries, size_t index);
+uint16_t nomexif_entry_tag(const NomExifEntries *entries, size_t index);
+NomExifValueType nomexif_entry_type(const NomExifEntries *entries, size_t index);
+const char *nomexif_entry_text(const NomExifEntries *entries, size_t index);
+
+/* Return false if the value doesn't have the requested type. */
+bool nomexif_entry_i64(const NomExifEntries *entries, size_t index, int64_t *out);
+bool nomexif_entry_f64(const NomExifEntries *entries, size_t index, double *out);
+bool nomexif_entry_rational(const NomExifEntries *entries, size_t index,
+                            int64_t *numerator, int64_t *denominator);
+bool nomexif_entry_time(const NomExifEntries *entries, size_t index,
+                        int64_t *timestamp, int32_t *offset);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NOMEXIF_H */
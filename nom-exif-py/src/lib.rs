@@ -0,0 +1,159 @@
+//! Python bindings of nom-exif, built with
+//! [maturin](https://www.maturin.rs/):
+//!
+//! ```python
+//! import nom_exif
+//!
+//! meta = nom_exif.parse("./testdata/exif.jpg")
+//! print(meta["Make"], meta["DateTimeOriginal"])
+//! ```
+
+use std::{fs::File, path::PathBuf};
+
+use nom_exif::{EntryValue, Exif, MediaInfo};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyDict},
+    IntoPyObjectExt,
+};
+
+/// Parses the image or video at `path`, and returns its metadata as a dict:
+/// the Exif entries of an image, keyed by tag name (e.g. `"Make"`), or the
+/// metadata items of a video (e.g. `"com.apple.quicktime.make"`).
+///
+/// Texts are `str`, integers `int`, rationals `float`, times
+/// `datetime.datetime` and undefined data `bytes`. The entries whose values
+/// couldn't be parsed are left out. Raises `OSError` if the file can't be
+/// read, and `ValueError` if it can't be parsed.
+#[pyfunction]
+fn parse(py: Python<'_>, path: PathBuf) -> PyResult<Bound<'_, PyDict>> {
+    let f = File::open(path)?;
+    let info = py
+        .detach(|| nom_exif::parse_media(f))
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    let dict = PyDict::new(py);
+    match info {
+        Some(MediaInfo::Exif(iter)) => {
+            for (tag, value) in Exif::from(iter) {
+                let key = tag.info().name;
+                // The first one wins, as with `Exif::get`
+                if !dict.contains(key)? {
+                    dict.set_item(key, to_py(py, value)?)?;
+                }
+            }
+        }
+        Some(MediaInfo::Track(iter)) => {
+            for (key, value) in iter {
+                if let Ok(value) = value {
+                    dict.set_item(key, to_py(py, value)?)?;
+                }
+            }
+        }
+        _ => (),
+    }
+    Ok(dict)
+}
+
+fn to_py(py: Python<'_>, value: EntryValue) -> PyResult<Bound<'_, PyAny>> {
+    use EntryValue as V;
+    match value {
+        V::Text(s) => s.into_bound_py_any(py),
+        V::U64(v) => v.into_bound_py_any(py),
+        V::F32(v) => v.into_bound_py_any(py),
+        V::F64(v) => v.into_bound_py_any(py),
+        V::Time(t) => t.into_bound_py_any(py),
+        V::Undefined(data) => PyBytes::new(py, &data).into_bound_py_any(py),
+        V::URationalArray(v) => v
+            .iter()
+            .map(|x| x.as_float())
+            .collect::<Vec<_>>()
+            .into_bound_py_any(py),
+        V::IRationalArray(v) => v
+            .iter()
+            .map(|x| x.as_float())
+            .collect::<Vec<_>>()
+            .into_bound_py_any(py),
+        V::URational(_) | V::IRational(_) => value.to_f64().into_bound_py_any(py),
+        _ => value.to_i64().into_bound_py_any(py),
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "nom_exif")]
+fn nom_exif_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse, m)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom_exif::{IRational, URational};
+    use pyo3::types::{PyDateTime, PyFloat, PyInt, PyList, PyString};
+
+    fn parse_sample<'py>(py: Python<'py>, name: &str) -> Bound<'py, PyDict> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../testdata")
+            .join(name);
+        parse(py, path).unwrap()
+    }
+
+    #[test]
+    fn parse_image() {
+        Python::initialize();
+        Python::attach(|py| {
+            let dict = parse_sample(py, "exif.jpg");
+            let get = |key: &str| dict.get_item(key).unwrap().unwrap();
+
+            assert_eq!(get("Make").extract::<String>().unwrap(), "vivo");
+            assert!(get("ImageWidth").is_instance_of::<PyInt>());
+            assert!(get("ExposureTime").is_instance_of::<PyFloat>());
+            assert!(get("DateTimeOriginal").is_instance_of::<PyDateTime>());
+
+            let latitude = get("GPSLatitude");
+            let latitude = latitude.cast::<PyList>().unwrap();
+            assert_eq!(latitude.len(), 3);
+            assert!(latitude.iter().all(|x| x.is_instance_of::<PyFloat>()));
+            assert!(get("GPSLatitudeRef").is_instance_of::<PyString>());
+        });
+    }
+
+    #[test]
+    fn parse_video() {
+        Python::initialize();
+        Python::attach(|py| {
+            let dict = parse_sample(py, "meta.mov");
+            let get = |key: &str| dict.get_item(key).unwrap().unwrap();
+
+            assert_eq!(
+                get("com.apple.quicktime.make").extract::<String>().unwrap(),
+                "Apple"
+            );
+            assert!(get("com.apple.quicktime.creationdate").is_instance_of::<PyDateTime>());
+            assert!(get("duration").is_instance_of::<PyInt>());
+            let location = get("com.apple.quicktime.location.ISO6709");
+            assert!(location.extract::<String>().unwrap().starts_with('+'));
+
+            let err = parse(py, PathBuf::from("../testdata/missing.jpg")).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyOSError>(py));
+        });
+    }
+
+    #[test]
+    fn value_to_py() {
+        Python::initialize();
+        Python::attach(|py| {
+            let value = to_py(py, EntryValue::URational(URational::new(1, 4))).unwrap();
+            assert_eq!(value.extract::<f64>().unwrap(), 0.25);
+            let value = to_py(py, EntryValue::IRational(IRational::new(-1, 2))).unwrap();
+            assert_eq!(value.extract::<f64>().unwrap(), -0.5);
+            let value = to_py(py, EntryValue::URationalArray(vec![URational::new(3, 2)])).unwrap();
+            assert_eq!(value.extract::<Vec<f64>>().unwrap(), [1.5]);
+            let value = to_py(py, EntryValue::U16(7)).unwrap();
+            assert_eq!(value.extract::<i64>().unwrap(), 7);
+            let value = to_py(py, EntryValue::Undefined(vec![1, 2])).unwrap();
+            assert_eq!(value.cast::<PyBytes>().unwrap().as_bytes(), [1, 2]);
+        });
+    }
+}
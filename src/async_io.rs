@@ -0,0 +1,77 @@
+//! The async readers of the supported runtimes: tokio (`async` feature
+//! flag), and the runtimes based on futures-io (`futures-io` feature flag),
+//! e.g. smol or async-std.
+
+use std::io;
+
+/// An async reader, which the async parsing functions are written for.
+pub(crate) trait AsyncSource {
+    /// Appends up to `n` bytes to `buf`, and returns how many were read,
+    /// which is only less than `n` at the end of the file.
+    async fn read_to_buf(&mut self, buf: &mut Vec<u8>, n: usize) -> io::Result<usize>;
+}
+
+/// An async reader which can skip data by seeking.
+pub(crate) trait AsyncSeekSource: AsyncSource {
+    async fn skip(&mut self, n: u64) -> io::Result<()>;
+
+    /// Seeks to the offset `pos` of the file.
+    async fn seek_to(&mut self, pos: u64) -> io::Result<()>;
+}
+
+/// A tokio reader.
+#[cfg(feature = "async")]
+pub(crate) struct TokioSource<R>(pub R);
+
+#[cfg(feature = "async")]
+impl<R: tokio::io::AsyncRead + Unpin> AsyncSource for TokioSource<R> {
+    async fn read_to_buf(&mut self, buf: &mut Vec<u8>, n: usize) -> io::Result<usize> {
+        use tokio::io::AsyncReadExt;
+        (&mut self.0).take(n as u64).read_to_end(buf).await
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin> AsyncSeekSource for TokioSource<R> {
+    async fn skip(&mut self, n: u64) -> io::Result<()> {
+        use tokio::io::AsyncSeekExt;
+        self.0.seek(io::SeekFrom::Current(n as i64)).await?;
+        Ok(())
+    }
+
+    async fn seek_to(&mut self, pos: u64) -> io::Result<()> {
+        use tokio::io::AsyncSeekExt;
+        self.0.seek(io::SeekFrom::Start(pos)).await?;
+        Ok(())
+    }
+}
+
+/// A futures-io reader.
+#[cfg(feature = "futures-io")]
+pub(crate) struct FuturesSource<R>(pub R);
+
+#[cfg(feature = "futures-io")]
+impl<R: futures_util::io::AsyncRead + Unpin> AsyncSource for FuturesSource<R> {
+    async fn read_to_buf(&mut self, buf: &mut Vec<u8>, n: usize) -> io::Result<usize> {
+        use futures_util::io::AsyncReadExt;
+        (&mut self.0).take(n as u64).read_to_end(buf).await
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R> AsyncSeekSource for FuturesSource<R>
+where
+    R: futures_util::io::AsyncRead + futures_util::io::AsyncSeek + Unpin,
+{
+    async fn skip(&mut self, n: u64) -> io::Result<()> {
+        use futures_util::io::AsyncSeekExt;
+        self.0.seek(io::SeekFrom::Current(n as i64)).await?;
+        Ok(())
+    }
+
+    async fn seek_to(&mut self, pos: u64) -> io::Result<()> {
+        use futures_util::io::AsyncSeekExt;
+        self.0.seek(io::SeekFrom::Start(pos)).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+//! Parses all the media files of a directory tree in parallel, using
+//! [rayon](https://crates.io/crates/rayon). Enabled by the `rayon` feature
+//! flag.
+
+use std::{
+    fs::{self, File, ReadDir},
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use rayon::iter::{ParallelBridge, ParallelIterator};
+
+use crate::{MediaInfo, MediaParser};
+
+/// Number of parsed files which may wait to be consumed, so memory usage
+/// stays bounded when the consumer is slower than the parsing.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Options for [`parse_dir`].
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// The parser used for every file, shared by the worker threads.
+    /// Default: a parser which pools 64 buffers (see
+    /// [`MediaParserBuilder::buffer_pool_size`](crate::MediaParserBuilder::buffer_pool_size)).
+    pub parser: MediaParser,
+
+    /// Walk sub-directories as well. Symbolic links to directories aren't
+    /// followed. Default: `true`.
+    pub recursive: bool,
+
+    /// Only the files with one of these extensions (case-insensitive) are
+    /// parsed, all files if empty. Default: the extensions of the supported
+    /// formats, `jpg`, `jpeg`, `heic`, `heif`, `mov` and `mp4`.
+    pub extensions: Vec<String>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            parser: MediaParser::builder().buffer_pool_size(64).build(),
+            recursive: true,
+            extensions: ["jpg", "jpeg", "heic", "heif", "mov", "mp4"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// Walks the directory tree at `path`, parses its files in parallel (see
+/// [`parse_media`](crate::parse_media)), and returns the results as they
+/// are ready, in no particular order.
+///
+/// Directories which can't be read are returned with their error as well.
+/// Dropping the iterator stops the parsing.
+///
+/// The files are parsed on rayon's global thread pool, so the iterator
+/// shouldn't be consumed from one of its threads.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::{batch::*, *};
+///
+/// let mut makes = parse_dir("./testdata", BatchOptions::default())
+///     .filter_map(|(_, res)| res.ok().flatten()?.into_exif())
+///     .filter_map(|iter| Exif::from(iter).get(ExifTag::Make).map(|x| x.to_string()))
+///     .collect::<Vec<_>>();
+/// makes.sort();
+/// makes.dedup();
+/// assert_eq!(makes, ["Apple", "SAMSUNG", "vivo"]);
+/// ```
+pub fn parse_dir<P: AsRef<Path>>(path: P, options: BatchOptions) -> BatchIter {
+    let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+    let walk = Walk::new(path.as_ref().to_path_buf(), options.recursive);
+
+    rayon::spawn(move || {
+        let BatchOptions {
+            parser, extensions, ..
+        } = options;
+        // Stops at the first failed send, i.e. once the receiver is dropped
+        let _ = walk
+            .filter(|x| match x {
+                Ok(path) => has_extension(path, &extensions),
+                Err(_) => true,
+            })
+            .par_bridge()
+            .try_for_each_with(tx, |tx, x| {
+                let res = match x {
+                    Ok(path) => {
+                        let res = File::open(&path)
+                            .map_err(crate::Error::from)
+                            .and_then(|f| parser.parse_media(f));
+                        (path, res)
+                    }
+                    Err((path, e)) => (path, Err(e.into())),
+                };
+                tx.send(res).map_err(|_| ())
+            });
+    });
+
+    BatchIter { rx }
+}
+
+/// Results of [`parse_dir`]: the path of each file (or of each directory
+/// which can't be read), and what [`parse_media`](crate::parse_media)
+/// returned for it.
+#[derive(Debug)]
+pub struct BatchIter {
+    rx: Receiver<(PathBuf, crate::Result<Option<MediaInfo>>)>,
+}
+
+impl Iterator for BatchIter {
+    type Item = (PathBuf, crate::Result<Option<MediaInfo>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    if extensions.is_empty() {
+        return true;
+    }
+    let Some(ext) = path.extension().and_then(|x| x.to_str()) else {
+        return false;
+    };
+    extensions.iter().any(|x| x.eq_ignore_ascii_case(ext))
+}
+
+/// Iterates over the files of a directory tree, depth-first.
+struct Walk {
+    dirs: Vec<PathBuf>,
+    current: Option<(PathBuf, ReadDir)>,
+    recursive: bool,
+}
+
+impl Walk {
+    fn new(root: PathBuf, recursive: bool) -> Self {
+        Self {
+            dirs: vec![root],
+            current: None,
+            recursive,
+        }
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<PathBuf, (PathBuf, io::Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((dir, entries)) = &mut self.current {
+                match entries.next() {
+                    Some(Ok(entry)) => {
+                        let path = entry.path();
+                        match entry.file_type() {
+                            Ok(ft) if ft.is_dir() => {
+                                if self.recursive {
+                                    self.dirs.push(path);
+                                }
+                            }
+                            // Follow links to files only
+                            Ok(ft) if ft.is_symlink() => {
+                                if fs::metadata(&path).is_ok_and(|x| x.is_file()) {
+                                    return Some(Ok(path));
+                                }
+                            }
+                            Ok(_) => return Some(Ok(path)),
+                            Err(e) => return Some(Err((path, e))),
+                        }
+                    }
+                    Some(Err(e)) => return Some(Err((dir.clone(), e))),
+                    None => self.current = None,
+                }
+                continue;
+            }
+
+            let dir = self.dirs.pop()?;
+            match fs::read_dir(&dir) {
+                Ok(entries) => self.current = Some((dir, entries)),
+                Err(e) => return Some(Err((dir, e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_parse_dir() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut results = parse_dir("./testdata", BatchOptions::default()).collect::<Vec<_>>();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let names = results
+            .iter()
+            .map(|x| x.0.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"exif.jpg"), "{names:?}");
+        assert!(names.contains(&"meta.mov"), "{names:?}");
+        assert!(!names.contains(&"exif.png"), "{names:?}");
+
+        for (path, res) in results {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            match name {
+                "exif.jpg" | "exif.heic" => {
+                    assert!(res.unwrap().unwrap().into_exif().is_some())
+                }
+                "meta.mov" | "meta.mp4" => {
+                    assert!(res.unwrap().unwrap().into_track().is_some())
+                }
+                "no-exif.jpg" => assert!(res.unwrap().is_none()),
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn batch_options() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let options = BatchOptions {
+            extensions: vec!["PNG".to_owned()],
+            ..Default::default()
+        };
+        let results = parse_dir("./testdata", options).collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_err());
+
+        // Missing directory
+        let results = parse_dir("./testdata/missing", BatchOptions::default()).collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_err());
+
+        // Dropping the iterator early
+        let mut iter = parse_dir("./testdata", BatchOptions::default());
+        assert!(iter.next().is_some());
+        drop(iter);
+    }
+}
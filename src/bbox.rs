@@ -1,37 +1,76 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
+#[cfg(feature = "std")]
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+
+use crate::prelude::*;
+#[cfg(feature = "std")]
+use nom::Needed;
 use nom::{
     bytes::{complete, streaming},
     combinator::{fail, map_res},
     error::context,
-    number, AsChar, IResult, Needed,
+    number, AsChar, IResult,
 };
 
 mod idat;
 mod iinf;
 mod iloc;
+#[cfg(feature = "std")]
 mod ilst;
+#[cfg(feature = "std")]
 mod keys;
+#[cfg(feature = "std")]
+mod mdhd;
 mod meta;
+#[cfg(feature = "std")]
 mod mvhd;
+#[cfg(feature = "std")]
 mod tkhd;
-pub use ilst::IlstBox;
+#[cfg(feature = "std")]
+mod trak;
+#[cfg(feature = "std")]
+pub use iinf::IinfBox;
+#[cfg(feature = "std")]
+pub use iloc::IlocBox;
+#[cfg(feature = "std")]
+pub use ilst::RawIlstItem;
+#[cfg(feature = "std")]
 pub use keys::KeysBox;
+#[cfg(feature = "std")]
+pub use mdhd::MdhdBox;
 pub use meta::MetaBox;
+#[cfg(feature = "std")]
 pub use mvhd::MvhdBox;
-pub use tkhd::parse_video_tkhd_in_moov;
+#[cfg(feature = "std")]
+pub use tkhd::TkhdBox;
+#[cfg(feature = "std")]
+pub use trak::{parse_traks_in_moov, TrakBox};
 
 const MAX_BODY_LEN: usize = 2000 * 1024 * 1024;
 
+/// Converts a time of a header atom, in seconds since midnight, January 1,
+/// 1904 (UTC). Returns `None` if it's not set (0).
+#[cfg(feature = "std")]
+pub fn time_since_1904(seconds: u64) -> Option<DateTime<FixedOffset>> {
+    if seconds == 0 {
+        return None;
+    }
+    let t = NaiveDate::from_ymd_opt(1904, 1, 1)?.and_hms_opt(0, 0, 0)?
+        + Duration::try_seconds(seconds.try_into().ok()?)?;
+    Some(t.and_utc().fixed_offset())
+}
+
+#[allow(unused)]
 #[derive(Debug, PartialEq)]
 pub enum Error {
     UnsupportedConstructionMethod(u8),
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {}
 
 impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::UnsupportedConstructionMethod(x) => {
                 write!(f, "unsupported construction method ({x})")
@@ -86,6 +125,7 @@ impl BoxHeader {
         ))
     }
 
+    #[cfg(feature = "std")]
     pub fn body_size(&self) -> u64 {
         self.box_size - self.header_size as u64
     }
@@ -169,10 +209,12 @@ impl<'a> BoxHolder<'a> {
     }
 }
 
+#[cfg(feature = "std")]
 type BoxResult<'a> = IResult<&'a [u8], Option<BoxHolder<'a>>>;
 
 /// Parses every top level box while `predicate` returns true, then returns the
 /// last parsed box.
+#[cfg(feature = "std")]
 pub fn travel_while<'a, F>(input: &'a [u8], mut predicate: F) -> BoxResult<'a>
 where
     F: FnMut(&BoxHolder<'a>) -> bool,
@@ -194,6 +236,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 pub fn travel_header<'a, F>(input: &'a [u8], mut predicate: F) -> IResult<&'a [u8], BoxHeader>
 where
     F: FnMut(&BoxHeader, &'a [u8]) -> bool,
@@ -530,7 +573,7 @@ mod tests {
         // gps info
         assert_eq!(
             "+27.2939+112.6932/",
-            std::str::from_utf8(&bbox.body_data()[4..]).unwrap() // Safe-slice in test_case
+            core::str::from_utf8(&bbox.body_data()[4..]).unwrap() // Safe-slice in test_case
         );
     }
 
@@ -1,4 +1,4 @@
-use std::ops::Range;
+use core::ops::Range;
 
 use nom::{bytes::streaming, IResult};
 
@@ -12,7 +12,7 @@ pub struct IdatBox<'a> {
 
 #[allow(unused)]
 impl<'a> IdatBox<'a> {
-    pub fn parse(input: &'a [u8]) -> IResult<&'a [u8], IdatBox> {
+    pub fn parse(input: &'a [u8]) -> IResult<&'a [u8], IdatBox<'a>> {
         let (remain, header) = BoxHeader::parse(input)?;
         let (remain, data) = streaming::take(header.box_size - header.header_size as u64)(remain)?;
 
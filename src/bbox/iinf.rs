@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use nom::{
     bytes::streaming,
     combinator::{cond, fail, map_res},
@@ -12,11 +10,12 @@ use nom::{
 use crate::bbox::FullBoxHeader;
 
 use super::{parse_cstr, ParseBody, ParseBox};
+use crate::prelude::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IinfBox {
     header: FullBoxHeader,
-    entries: HashMap<String, InfeBox>,
+    entries: Vec<InfeBox>,
 }
 
 impl ParseBody<IinfBox> for IinfBox {
@@ -32,18 +31,22 @@ impl ParseBody<IinfBox> for IinfBox {
         let (remain, entries) =
             many_m_n(item_count as usize, item_count as usize, InfeBox::parse_box)(remain)?;
 
-        let entries = entries
-            .into_iter()
-            .map(|e| (e.key().to_owned(), e))
-            .collect::<HashMap<_, _>>();
-
         Ok((remain, IinfBox { header, entries }))
     }
 }
 
 impl IinfBox {
     pub fn get_infe(&self, item_type: &'static str) -> Option<&InfeBox> {
-        self.entries.get(item_type)
+        self.entries.iter().find(|x| x.key() == item_type)
+    }
+
+    /// Returns all `mime` items with the specified `content_type`.
+    #[cfg(feature = "std")]
+    pub fn mime_items<'a>(&'a self, content_type: &'a str) -> impl Iterator<Item = &'a InfeBox> {
+        self.entries.iter().filter(move |x| {
+            x.item_type.as_deref() == Some("mime")
+                && x.content_type.as_deref() == Some(content_type)
+        })
     }
 }
 
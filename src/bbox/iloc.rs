@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use alloc::collections::BTreeMap;
 
 use nom::{
     combinator::{cond, fail, map_res},
@@ -11,6 +11,7 @@ use nom::{
 use crate::bbox::FullBoxHeader;
 
 use super::{Error, ParseBody};
+use crate::prelude::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IlocBox {
@@ -19,7 +20,7 @@ pub struct IlocBox {
     length_size: u8,      // 4 bits
     base_offset_size: u8, // 4 bits
     index_size: u8,       // 4 bits, version 1/2, reserved in version 0
-    items: HashMap<u32, ItemLocation>,
+    items: BTreeMap<u32, ItemLocation>,
 }
 
 const MAX_ILOC_EXTENTS_PER_ITEM: u16 = 32;
@@ -124,6 +125,136 @@ impl IlocBox {
                 })
             })
     }
+
+    #[cfg(feature = "std")]
+    pub fn item_location(&self, id: u32) -> Option<&ItemLocation> {
+        self.items.get(&id)
+    }
+
+    /// Points item `id` to a single extent located at `offset` (absolute,
+    /// i.e. base offset is reset to 0).
+    #[cfg(feature = "std")]
+    pub fn set_item_location(
+        &mut self,
+        id: u32,
+        construction_method: u8,
+        offset: u64,
+        length: u64,
+    ) {
+        let Some(item) = self.items.get_mut(&id) else {
+            return;
+        };
+        if item.construction_method.is_some() || construction_method != 0 {
+            item.construction_method = Some(construction_method);
+        }
+        item.base_offset = 0;
+        item.extents = vec![ItemLocationExtent {
+            index: 0,
+            offset,
+            length,
+        }];
+    }
+
+    /// Shifts all file offsets (construction method 0) which are greater than
+    /// or equal to `start` by `delta`.
+    #[cfg(feature = "std")]
+    pub fn shift_file_offsets(&mut self, start: u64, delta: i64) {
+        for item in self.items.values_mut() {
+            if item.construction_method.unwrap_or(0) != 0 || item.data_ref_index != 0 {
+                continue;
+            }
+            if item.base_offset > 0 && item.base_offset >= start {
+                item.base_offset = item.base_offset.saturating_add_signed(delta);
+                continue;
+            }
+            for extent in item.extents.iter_mut() {
+                if item.base_offset + extent.offset >= start {
+                    extent.offset = extent.offset.saturating_add_signed(delta);
+                }
+            }
+        }
+    }
+
+    /// Serializes the whole box (including the header).
+    ///
+    /// Field sizes are enlarged if some values don't fit in the original
+    /// sizes.
+    #[cfg(feature = "std")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let version = self.header.version;
+        let mut items = self.items.values().collect::<Vec<_>>();
+        items.sort_by_key(|x| x.id);
+
+        let fit = |size: u8, v: u64| -> u8 {
+            if v > u32::MAX as u64 {
+                8
+            } else if v > 0 && size == 0 {
+                4
+            } else {
+                size
+            }
+        };
+        let (mut offset_size, mut length_size, mut base_offset_size, mut index_size) = (
+            self.offset_size,
+            self.length_size,
+            self.base_offset_size,
+            self.index_size,
+        );
+        for item in items.iter() {
+            base_offset_size = fit(base_offset_size, item.base_offset);
+            for extent in item.extents.iter() {
+                offset_size = fit(offset_size, extent.offset);
+                length_size = fit(length_size, extent.length);
+                index_size = fit(index_size, extent.index);
+            }
+        }
+
+        let mut body = Vec::new();
+        body.push(version);
+        body.extend_from_slice(&self.header.flags.to_be_bytes()[1..]);
+        body.push((offset_size << 4) | length_size);
+        body.push((base_offset_size << 4) | index_size);
+        if version < 2 {
+            body.extend_from_slice(&(items.len() as u16).to_be_bytes());
+        } else {
+            body.extend_from_slice(&(items.len() as u32).to_be_bytes());
+        }
+
+        for item in items {
+            if version < 2 {
+                body.extend_from_slice(&(item.id as u16).to_be_bytes());
+            } else {
+                body.extend_from_slice(&item.id.to_be_bytes());
+            }
+            if version >= 1 {
+                let method = item.construction_method.unwrap_or(0) as u16;
+                body.extend_from_slice(&method.to_be_bytes());
+            }
+            body.extend_from_slice(&item.data_ref_index.to_be_bytes());
+            put_sized(&mut body, base_offset_size, item.base_offset);
+            body.extend_from_slice(&(item.extents.len() as u16).to_be_bytes());
+            for extent in item.extents.iter() {
+                put_sized(&mut body, index_size, extent.index);
+                put_sized(&mut body, offset_size, extent.offset);
+                put_sized(&mut body, length_size, extent.length);
+            }
+        }
+
+        let mut data = Vec::with_capacity(body.len() + 8);
+        data.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+        data.extend_from_slice(b"iloc");
+        data.extend_from_slice(&body);
+        data
+    }
+}
+
+#[cfg(feature = "std")]
+fn put_sized(out: &mut Vec<u8>, size: u8, v: u64) {
+    match size {
+        4 => out.extend_from_slice(&(v as u32).to_be_bytes()),
+        8 => out.extend_from_slice(&v.to_be_bytes()),
+        _ => (),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -155,6 +286,22 @@ pub struct ItemLocation {
     extents: Vec<ItemLocationExtent>,
 }
 
+impl ItemLocation {
+    #[cfg(feature = "std")]
+    pub fn construction_method(&self) -> u8 {
+        self.construction_method.unwrap_or(0)
+    }
+
+    /// Returns `(offset, length)` of each extent, base offset included.
+    #[cfg(feature = "std")]
+    pub fn extents(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.extents
+            .iter()
+            .map(|x| (self.base_offset + x.offset, x.length))
+    }
+}
+
+#[allow(unused)]
 #[allow(clippy::enum_variant_names)]
 enum ConstructionMethod {
     FileOffset = 0,
@@ -164,7 +311,7 @@ enum ConstructionMethod {
 
 impl TryFrom<u8> for ConstructionMethod {
     type Error = Error;
-    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: u8) -> core::result::Result<Self, Self::Error> {
         match value {
             0 => Ok(Self::FileOffset),
             1 => Ok(Self::IdatOffset),
@@ -173,3 +320,29 @@ impl TryFrom<u8> for ConstructionMethod {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bbox::{find_box, ParseBox},
+        testkit::read_sample,
+    };
+
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("exif.heic")]
+    #[test_case("no-exif.heic")]
+    fn iloc_round_trip(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample(path).unwrap();
+        let (_, meta) = find_box(&buf, "meta").unwrap();
+        let meta = meta.unwrap();
+        let (_, iloc) = find_box(&meta.body_data()[4..], "iloc").unwrap();
+        let iloc = iloc.unwrap();
+
+        let (_, parsed) = IlocBox::parse_box(iloc.data).unwrap();
+        assert_eq!(parsed.to_bytes(), iloc.data);
+    }
+}
@@ -10,6 +10,7 @@ use nom::sequence::tuple;
 use crate::EntryValue;
 
 use super::BoxHeader;
+use crate::prelude::*;
 
 /// Represents an [item list atom][1].
 ///
@@ -18,12 +19,14 @@ use super::BoxHeader;
 /// atom-path: moov/meta/ilst
 ///
 /// [1]: https://developer.apple.com/documentation/quicktime-file-format/metadata_item_list_atom
+#[allow(unused)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct IlstBox {
     header: BoxHeader,
     pub items: Vec<IlstItem>,
 }
 
+#[allow(unused)]
 impl IlstBox {
     pub fn parse_box(input: &[u8]) -> nom::IResult<&[u8], IlstBox> {
         let (remain, header) = BoxHeader::parse(input)?;
@@ -81,6 +84,41 @@ impl IlstItem {
     }
 }
 
+/// An ilst item whose value is not decoded yet, so that decoding errors can be
+/// reported per item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawIlstItem<'a> {
+    /// 1-based index of the key in the keys atom
+    pub index: u32,
+    pub type_code: u32,
+    pub data: &'a [u8],
+}
+
+impl<'a> RawIlstItem<'a> {
+    pub fn parse(input: &'a [u8]) -> nom::IResult<&'a [u8], RawIlstItem<'a>> {
+        let (remain, (size, index, data_len, _, _, type_code, _)) =
+            tuple((be_u32, be_u32, be_u32, tag("data"), u8, be_u24, be_u32))(input)?;
+
+        if size < 24 || data_len < 16 || size - 24 != data_len - 16 {
+            context("invalid ilst item", fail::<_, (), _>)(remain)?;
+        }
+
+        let (remain, data) = take(data_len - 16)(remain)?;
+        Ok((
+            remain,
+            RawIlstItem {
+                index,
+                type_code,
+                data,
+            },
+        ))
+    }
+
+    pub fn value(&self) -> crate::Result<EntryValue> {
+        parse_value(self.type_code, self.data)
+    }
+}
+
 /// Parse ilst item data to value, see [Well-known
 /// types](https://developer.apple.com/documentation/quicktime-file-format/well-known_types)
 #[tracing::instrument(skip(data))]
@@ -4,6 +4,7 @@ use nom::multi::many_m_n;
 use nom::number::complete::be_u32;
 
 use crate::bbox::{FullBoxHeader, ParseBody};
+use crate::prelude::*;
 
 /// Represents a [keys atom][1].
 ///
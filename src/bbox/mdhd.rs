@@ -0,0 +1,135 @@
+use chrono::{DateTime, Duration, FixedOffset};
+use nom::{
+    combinator::map,
+    number::complete::{be_u16, be_u32, be_u64},
+    sequence::tuple,
+};
+
+use super::{time_since_1904, FullBoxHeader, ParseBody};
+use crate::prelude::*;
+
+/// Represents a [media header atom][1].
+///
+/// mdhd is a fullbox which contains version & flags, the times and duration
+/// are 64 bits wide in version 1.
+///
+/// atom-path: moov/trak/mdia/mdhd
+///
+/// [1]: https://developer.apple.com/documentation/quicktime-file-format/media_header_atom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MdhdBox {
+    header: FullBoxHeader,
+
+    /// seconds since midnight, January 1, 1904
+    creation_time: u64,
+
+    /// seconds since midnight, January 1, 1904
+    modification_time: u64,
+
+    /// The number of time units that pass per second in the media's time
+    /// coordinate system.
+    pub time_scale: u32,
+
+    /// Duration of the media in time scale units.
+    pub duration: u64,
+
+    /// ISO 639-2/T language code, packed as three 5 bits values.
+    language: u16,
+}
+
+impl MdhdBox {
+    /// Returns the duration of the media, `None` if the time scale is 0.
+    pub fn duration(&self) -> Option<Duration> {
+        if self.time_scale == 0 {
+            return None;
+        }
+        let ms = self.duration as u128 * 1000 / self.time_scale as u128;
+        Some(Duration::milliseconds(ms.try_into().ok()?))
+    }
+
+    /// Returns the creation time (UTC), `None` if it's not set.
+    pub fn creation_time(&self) -> Option<DateTime<FixedOffset>> {
+        time_since_1904(self.creation_time)
+    }
+
+    /// Returns the modification time (UTC), `None` if it's not set.
+    pub fn modification_time(&self) -> Option<DateTime<FixedOffset>> {
+        time_since_1904(self.modification_time)
+    }
+
+    /// Returns the ISO 639-2/T language code, e.g. `"eng"`. `None` for
+    /// undetermined languages (`"und"`) and Macintosh language codes.
+    pub fn language(&self) -> Option<String> {
+        if self.language < 0x400 || self.language == 0x7fff {
+            return None;
+        }
+        let code = [10, 5, 0]
+            .iter()
+            .map(|shift| (((self.language >> shift) & 0x1f) as u8 + 0x60) as char)
+            .collect::<String>();
+        (code != "und" && code.chars().all(|c| c.is_ascii_lowercase())).then_some(code)
+    }
+}
+
+impl ParseBody<MdhdBox> for MdhdBox {
+    fn parse_body(body: &[u8], header: FullBoxHeader) -> nom::IResult<&[u8], MdhdBox> {
+        let (remain, (creation_time, modification_time, time_scale, duration)) =
+            if header.version == 1 {
+                tuple((be_u64, be_u64, be_u32, be_u64))(body)?
+            } else {
+                map(tuple((be_u32, be_u32, be_u32, be_u32)), |(c, m, t, d)| {
+                    (c.into(), m.into(), t, d.into())
+                })(body)?
+            };
+        let (remain, language) = be_u16(remain)?;
+
+        Ok((
+            remain,
+            MdhdBox {
+                header,
+                creation_time,
+                modification_time,
+                time_scale,
+                duration,
+                language,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bbox::ParseBox;
+
+    fn mdhd(language: u16) -> MdhdBox {
+        let mut data = vec![0, 0, 0, 32, b'm', b'd', b'h', b'd', 0, 0, 0, 0];
+        // creation time (2024-01-01), modification time, time scale, duration
+        data.extend_from_slice(&3786912000u32.to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&600u32.to_be_bytes());
+        data.extend_from_slice(&1500u32.to_be_bytes());
+        data.extend_from_slice(&language.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        MdhdBox::parse_box(&data).unwrap().1
+    }
+
+    #[test]
+    fn mdhd_box() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // "eng"
+        let b = mdhd(0x15c7);
+        assert_eq!(b.language().as_deref(), Some("eng"));
+        assert_eq!(b.duration(), Some(Duration::milliseconds(2500)));
+        assert_eq!(
+            b.creation_time().unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(b.modification_time(), None);
+
+        // "und", and a Macintosh language code
+        assert_eq!(mdhd(0x55c4).language(), None);
+        assert_eq!(mdhd(0).language(), None);
+    }
+}
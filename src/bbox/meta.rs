@@ -1,10 +1,11 @@
-use std::collections::HashMap;
+use alloc::collections::BTreeMap;
 
 use nom::{combinator::fail, multi::many0, IResult, Needed};
 
 use crate::bbox::FullBoxHeader;
 
 use super::{iinf::IinfBox, iloc::IlocBox, BoxHolder, ParseBody, ParseBox};
+use crate::prelude::*;
 
 /// Representing the `meta` box in a HEIF/HEIC file.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -29,7 +30,7 @@ impl ParseBody<MetaBox> for MetaBox {
         let boxes = boxes
             .into_iter()
             .map(|b| (b.header.box_type.to_owned(), b))
-            .collect::<HashMap<_, _>>();
+            .collect::<BTreeMap<_, _>>();
 
         // parse iinf box
         let iinf = boxes
@@ -65,6 +66,15 @@ impl ParseBody<MetaBox> for MetaBox {
 }
 
 impl MetaBox {
+    /// Returns true if there is a `mime` item with the specified
+    /// `content_type`.
+    #[cfg(feature = "std")]
+    pub fn has_mime_item(&self, content_type: &str) -> bool {
+        self.iinf
+            .as_ref()
+            .is_some_and(|iinf| iinf.mime_items(content_type).next().is_some())
+    }
+
     #[tracing::instrument(skip_all)]
     pub fn exif_data<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], Option<&'a [u8]>> {
         self.iinf
@@ -98,6 +108,7 @@ impl MetaBox {
     }
 }
 
+#[allow(unused)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ItemLocationExtent {
     index: u64,
@@ -105,6 +116,7 @@ struct ItemLocationExtent {
     length: u64,
 }
 
+#[allow(unused)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ItemLocation {
     id: u32,
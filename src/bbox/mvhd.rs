@@ -1,4 +1,6 @@
-use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+#[cfg(feature = "std")]
+use chrono::Local;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use nom::{bytes::complete::take, number::complete::be_u32, sequence::tuple};
 
 use super::{FullBoxHeader, ParseBody};
@@ -39,6 +41,11 @@ impl MvhdBox {
         ((self.duration as f64) / (self.time_scale as f64) * 1000_f64) as u32
     }
 
+    /// Number of time units per second of the movie, e.g. of edit lists.
+    pub fn time_scale(&self) -> u32 {
+        self.time_scale
+    }
+
     fn creation_time_naive(&self) -> NaiveDateTime {
         NaiveDate::from_ymd_opt(1904, 1, 1)
             .unwrap()
@@ -52,6 +59,7 @@ impl MvhdBox {
     }
 
     #[allow(dead_code)]
+    #[cfg(feature = "std")]
     pub fn creation_time_local(&self) -> DateTime<Local> {
         Local.from_utc_datetime(&self.creation_time_naive())
     }
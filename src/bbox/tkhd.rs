@@ -1,14 +1,18 @@
+use chrono::{DateTime, FixedOffset};
 use nom::{
-    bytes::complete::take,
-    number::complete::{be_u16, be_u32, be_u64},
+    combinator::map,
+    multi::count,
+    number::complete::{be_i32, be_u16, be_u32, be_u64},
     sequence::tuple,
 };
 
-use super::{find_box, travel_while, BoxHolder, FullBoxHeader, ParseBody, ParseBox};
+use super::{time_since_1904, FullBoxHeader, ParseBody};
+use crate::prelude::*;
 
 /// Represents a [movie header atom][1].
 ///
-/// tkhd is a fullbox which contains version & flags.
+/// tkhd is a fullbox which contains version & flags, the times and duration
+/// are 64 bits wide in version 1.
 ///
 /// atom-path: moov/trak/tkhd
 ///
@@ -18,63 +22,51 @@ pub struct TkhdBox {
     header: FullBoxHeader,
 
     /// seconds since midnight, January 1, 1904
-    creation_time: u32,
+    creation_time: u64,
 
     /// seconds since midnight, January 1, 1904
-    modification_time: u32,
+    modification_time: u64,
 
-    track_id: u32,
+    pub track_id: u32,
     // reserved: u32,
-    duration: u32,
+    duration: u64,
     // reserved2: u64,
     layer: u16,
     alt_group: u16,
     volume: u16,
     // reserved3: u16,
+    /// Transformation matrix `[a, b, u, c, d, v, x, y, w]`, in 16.16 fixed
+    /// point (2.30 for `u`, `v` and `w`).
+    matrix: Vec<i32>,
 
-    // matrix: [u8; 36],
     pub width: u32,
     pub height: u32,
 }
 
 impl ParseBody<TkhdBox> for TkhdBox {
     fn parse_body(body: &[u8], header: FullBoxHeader) -> nom::IResult<&[u8], TkhdBox> {
-        let (
-            remain,
-            (
-                creation_time,
-                modification_time,
-                track_id,
-                _,
-                duration,
-                _,
-                layer,
-                alt_group,
-                volume,
-                _,
-                _,
-                width,
-                _,
-                height,
-                _,
-            ),
-        ) = tuple((
-            be_u32,
-            be_u32,
-            be_u32,
-            be_u32,
-            be_u32,
-            be_u64,
-            be_u16,
-            be_u16,
-            be_u16,
-            be_u16,
-            take(36usize),
-            be_u16,
-            be_u16,
-            be_u16,
-            be_u16,
-        ))(body)?;
+        let (remain, (creation_time, modification_time, track_id, _, duration)) =
+            if header.version == 1 {
+                tuple((be_u64, be_u64, be_u32, be_u32, be_u64))(body)?
+            } else {
+                map(
+                    tuple((be_u32, be_u32, be_u32, be_u32, be_u32)),
+                    |(c, m, id, r, d)| (c.into(), m.into(), id, r, d.into()),
+                )(body)?
+            };
+        let (remain, (_, layer, alt_group, volume, _, matrix, width, _, height, _)) =
+            tuple((
+                be_u64,
+                be_u16,
+                be_u16,
+                be_u16,
+                be_u16,
+                count(be_i32, 9),
+                be_u16,
+                be_u16,
+                be_u16,
+                be_u16,
+            ))(remain)?;
 
         Ok((
             remain,
@@ -87,6 +79,7 @@ impl ParseBody<TkhdBox> for TkhdBox {
                 layer,
                 alt_group,
                 volume,
+                matrix,
                 width: width as u32,
                 height: height as u32,
             },
@@ -94,71 +87,117 @@ impl ParseBody<TkhdBox> for TkhdBox {
     }
 }
 
-/// Try to find a video track's tkhd in moov body. atom-path: "moov/trak/tkhd".
-pub fn parse_video_tkhd_in_moov(input: &[u8]) -> crate::Result<Option<TkhdBox>> {
-    let Some(bbox) = find_video_track(input)? else {
-        return Ok(None);
-    };
-    let (_, Some(bbox)) = find_box(bbox.body_data(), "tkhd")? else {
-        return Ok(None);
-    };
-    let (_, tkhd) = TkhdBox::parse_box(bbox.data).map_err(|_| "parse tkhd failed")?;
-    Ok(Some(tkhd))
-}
+impl TkhdBox {
+    /// Returns the creation time (UTC), `None` if it's not set.
+    pub fn creation_time(&self) -> Option<DateTime<FixedOffset>> {
+        time_since_1904(self.creation_time)
+    }
 
-fn find_video_track(input: &[u8]) -> crate::Result<Option<BoxHolder>> {
-    let (_, bbox) = travel_while(input, |b| {
-        // find video track
-        if b.box_type() != "trak" {
-            true
-        } else {
-            // got a 'trak', to check if it's a 'vide' trak
-
-            let found = find_box(b.body_data(), "mdia/hdlr");
-            let Ok(bbox) = found else {
-                return true;
-            };
-            let Some(hdlr) = bbox.1 else {
-                return true;
-            };
+    /// Returns the modification time (UTC), `None` if it's not set.
+    pub fn modification_time(&self) -> Option<DateTime<FixedOffset>> {
+        time_since_1904(self.modification_time)
+    }
 
-            // component subtype
-            if hdlr.body_data().len() < 4 {
-                return true;
-            }
-            let subtype = &hdlr.body_data()[8..12]; // Safe-slice
-            if subtype == b"vide" {
-                // found it!
-                false
-            } else {
-                true
-            }
+    /// Returns the clockwise rotation of the track in degrees (`0`, `90`,
+    /// `180` or `270` for common videos), as applied by the matrix. For a
+    /// flipped track (see [`Self::is_flipped`]), it's the rotation applied
+    /// after the horizontal flip.
+    pub fn rotation(&self) -> u32 {
+        let (mut a, mut b) = (self.matrix[0] as f64, self.matrix[1] as f64);
+        if self.is_flipped() {
+            // The matrix is rotation × flip, where the flip negates `a` & `b`
+            (a, b) = (-a, -b);
         }
-    })
-    .map_err(|e| format!("find vide trak failed: {e:?}"))?;
+        let degrees = b.atan2(a).to_degrees().round() as i32;
+        degrees.rem_euclid(360) as u32
+    }
 
-    Ok(bbox)
+    /// Returns true if the matrix mirrors the track, i.e. its determinant
+    /// is negative. Mirroring is reported as a horizontal flip, a vertical
+    /// one being a horizontal flip rotated by 180°.
+    pub fn is_flipped(&self) -> bool {
+        let [a, b, _, c, d, ..] = self.matrix[..] else {
+            return false;
+        };
+        i64::from(a) * i64::from(d) < i64::from(b) * i64::from(c)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{bbox::travel_while, testkit::read_sample};
-
     use super::*;
+    use crate::{
+        bbox::{parse_traks_in_moov, travel_while, ParseBox},
+        testkit::read_sample,
+    };
+
     use test_case::test_case;
 
-    #[test_case("meta.mov", 720, 1280)]
-    #[test_case("meta.mp4", 1920, 1080)]
-    fn tkhd_box(path: &str, width: u32, height: u32) {
+    #[test_case("meta.mov", 720, 1280, 0)]
+    #[test_case("meta.mp4", 1920, 1080, 90)]
+    #[test_case("embedded-in-heic.mov", 1920, 1440, 90)]
+    fn tkhd_box(path: &str, width: u32, height: u32, rotation: u32) {
         let _ = tracing_subscriber::fmt().with_test_writer().try_init();
 
         let buf = read_sample(path).unwrap();
 
         let (_, bbox) = travel_while(&buf, |b| b.box_type() != "moov").unwrap();
         let bbox = bbox.unwrap();
-        let tkhd = parse_video_tkhd_in_moov(bbox.body_data()).unwrap().unwrap();
+        let traks = parse_traks_in_moov(bbox.body_data());
+        let trak = traks.iter().find(|x| x.handler_type == "vide").unwrap();
+        let tkhd = trak.tkhd.as_ref().unwrap();
 
         assert_eq!(tkhd.width, width);
         assert_eq!(tkhd.height, height);
+        assert_eq!(tkhd.rotation(), rotation);
+        assert!(!tkhd.is_flipped());
+    }
+
+    #[test]
+    fn tkhd_version_1() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut data = vec![0, 0, 0, 104, b't', b'k', b'h', b'd', 1, 0, 0, 7];
+        // creation time (2024-01-01), modification time, track ID, reserved,
+        // duration
+        data.extend(3786912000u64.to_be_bytes());
+        data.extend(3786915600u64.to_be_bytes());
+        data.extend([0, 0, 0, 2, 0, 0, 0, 0]);
+        data.extend(600u64.to_be_bytes());
+        data.extend([0; 16]);
+        for x in [1 << 16, 0, 0, 0, 1 << 16, 0, 0, 0, 1 << 30] {
+            data.extend(i32::to_be_bytes(x));
+        }
+        data.extend([0x07, 0x80, 0, 0, 0x04, 0x38, 0, 0]);
+
+        let (_, tkhd) = TkhdBox::parse_box(&data).unwrap();
+        assert_eq!(tkhd.track_id, 2);
+        assert_eq!((tkhd.width, tkhd.height), (1920, 1080));
+        assert_eq!(
+            tkhd.creation_time().unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            tkhd.modification_time().unwrap().to_rfc3339(),
+            "2024-01-01T01:00:00+00:00"
+        );
+        assert_eq!(tkhd.rotation(), 0);
+    }
+
+    #[test_case([-1, 0, 0, 1], 0; "horizontal flip")]
+    #[test_case([1, 0, 0, -1], 180; "vertical flip")]
+    #[test_case([0, 1, 1, 0], 270; "flip and rotation")]
+    fn tkhd_flip(matrix: [i32; 4], rotation: u32) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample("meta.mov").unwrap();
+        let (_, bbox) = travel_while(&buf, |b| b.box_type() != "moov").unwrap();
+        let traks = parse_traks_in_moov(bbox.unwrap().body_data());
+        let mut tkhd = traks[0].tkhd.clone().unwrap();
+
+        let [a, b, c, d] = matrix.map(|x| x << 16);
+        tkhd.matrix = vec![a, b, 0, c, d, 0, 0, 0, 1 << 30];
+        assert!(tkhd.is_flipped());
+        assert_eq!(tkhd.rotation(), rotation);
     }
 }
@@ -0,0 +1,515 @@
+use nom::{
+    bytes::complete::take,
+    number::complete::{be_f64, be_u16, be_u32, be_u64, be_u8},
+    sequence::tuple,
+    IResult,
+};
+
+use crate::{prelude::*, CodecConfig, ColorInfo, ContentLightLevel, DolbyVision, MasteringDisplay};
+
+use super::{find_box, BoxHolder, MdhdBox, ParseBox, TkhdBox};
+
+/// Represents a [track atom][1], with the information of its children which
+/// describes the track: the track header, the handler type, the media header
+/// and the sample table.
+///
+/// atom-path: moov/trak
+///
+/// [1]: https://developer.apple.com/documentation/quicktime-file-format/track_atom
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrakBox {
+    pub tkhd: Option<TkhdBox>,
+
+    /// Component subtype of `mdia/hdlr`, e.g. `vide` or `soun`.
+    pub handler_type: String,
+
+    pub mdhd: Option<MdhdBox>,
+
+    /// Total duration of the edits of the edit list (`edts/elst`), in
+    /// `mvhd` time scale units, i.e. the duration of the track in the movie
+    /// once the edits (e.g. trims) are applied.
+    pub edit_duration: Option<u64>,
+
+    /// Data format of the first sample description (`stsd`), e.g. `avc1`,
+    /// `hvc1` or `mp4a`.
+    pub codec: Option<String>,
+
+    /// Channel count of the first sound sample description.
+    pub audio_channels: Option<u16>,
+
+    /// Sample rate (Hz) of the first sound sample description.
+    pub audio_sample_rate: Option<u32>,
+
+    /// Color description (`colr`, `mdcv` & `clli`) of the first video
+    /// sample description.
+    pub color: Option<ColorInfo>,
+
+    /// Decoder configuration (`avcC`, `hvcC` or `av1C`) of the first video
+    /// sample description.
+    pub codec_config: Option<CodecConfig>,
+
+    /// Number of samples, and their total duration in `mdhd` time scale
+    /// units (`stts`).
+    pub sample_count: u64,
+    pub sample_duration: u64,
+
+    /// Total size of the samples in bytes (`stsz`).
+    pub sample_bytes: u64,
+}
+
+impl TrakBox {
+    /// Parses the children of a `trak` box. Children which are missing or
+    /// malformed are left empty.
+    pub fn parse(trak: &BoxHolder) -> TrakBox {
+        let body = trak.body_data();
+        let tkhd = find_box(body, "tkhd")
+            .ok()
+            .and_then(|(_, b)| b)
+            .and_then(|b| TkhdBox::parse_box(b.data).ok())
+            .map(|(_, tkhd)| tkhd);
+        let mdhd = find_box(body, "mdia/mdhd")
+            .ok()
+            .and_then(|(_, b)| b)
+            .and_then(|b| MdhdBox::parse_box(b.data).ok())
+            .map(|(_, mdhd)| mdhd);
+        let handler_type = find_box(body, "mdia/hdlr")
+            .ok()
+            .and_then(|(_, b)| b)
+            .and_then(|b| b.body_data().get(8..12).map(|x| x.to_vec()))
+            .map(|x| x.iter().map(|b| *b as char).collect())
+            .unwrap_or_default();
+
+        let edit_duration = find_box(body, "edts/elst")
+            .ok()
+            .and_then(|(_, b)| b)
+            .and_then(|b| parse_elst(b.body_data()).ok())
+            .map(|(_, x)| x);
+
+        let mut trak = TrakBox {
+            tkhd,
+            handler_type,
+            mdhd,
+            edit_duration,
+            codec: None,
+            audio_channels: None,
+            audio_sample_rate: None,
+            color: None,
+            codec_config: None,
+            sample_count: 0,
+            sample_duration: 0,
+            sample_bytes: 0,
+        };
+
+        let Ok((_, Some(stbl))) = find_box(body, "mdia/minf/stbl") else {
+            return trak;
+        };
+        let stbl = stbl.body_data();
+        if let Ok((_, Some(stsd))) = find_box(stbl, "stsd") {
+            // Ignore malformed sample descriptions
+            let _ = trak.parse_stsd(stsd.body_data());
+        }
+        if let Some((count, duration)) = find_box(stbl, "stts")
+            .ok()
+            .and_then(|(_, b)| b)
+            .and_then(|b| parse_stts(b.body_data()).ok())
+            .map(|(_, x)| x)
+        {
+            (trak.sample_count, trak.sample_duration) = (count, duration);
+        }
+        if let Some(bytes) = find_box(stbl, "stsz")
+            .ok()
+            .and_then(|(_, b)| b)
+            .and_then(|b| parse_stsz(b.body_data()).ok())
+            .map(|(_, x)| x)
+        {
+            trak.sample_bytes = bytes;
+        }
+
+        trak
+    }
+
+    /// Returns the number of frames (samples) per second.
+    pub fn frame_rate(&self) -> Option<f64> {
+        let time_scale = self.mdhd.as_ref()?.time_scale;
+        if self.sample_count == 0 || self.sample_duration == 0 {
+            return None;
+        }
+        Some(self.sample_count as f64 * time_scale as f64 / self.sample_duration as f64)
+    }
+
+    /// Parses the first entry of the sample description atom (`stsd` body,
+    /// including version & flags).
+    fn parse_stsd<'a>(&mut self, body: &'a [u8]) -> IResult<&'a [u8], ()> {
+        // version & flags, entry count
+        let (remain, _) = take(8usize)(body)?;
+        let (_, entry) = BoxHolder::parse(remain)?;
+        self.codec = Some(entry.box_type().to_owned());
+        if self.handler_type == "vide" {
+            // Video sample description: the fixed fields are followed by
+            // extension boxes, e.g. `avcC` or `colr`
+            let (children, _) = take(78usize)(entry.body_data())?;
+            self.color = parse_color(children);
+            self.codec_config = parse_codec_config(children);
+            return Ok((remain, ()));
+        }
+        if self.handler_type != "soun" {
+            return Ok((remain, ()));
+        }
+
+        // Sound sample description: reserved (6), data reference index (2),
+        // version (2), revision level (2), vendor (4), then the version 0/1
+        // fields
+        let (data, _) = take(8usize)(entry.body_data())?;
+        let (data, version) = be_u16(data)?;
+        let (data, _) = take(6usize)(data)?;
+        let (data, channels) = be_u16(data)?;
+        let (data, _) = take(6usize)(data)?;
+        let (data, sample_rate) = be_u32(data)?;
+        if version == 2 {
+            // size of struct only, then the actual values
+            let (data, _) = take(4usize)(data)?;
+            let (data, sample_rate) = be_f64(data)?;
+            let (_, channels) = be_u32(data)?;
+            self.audio_sample_rate = Some(sample_rate as u32);
+            self.audio_channels = Some(channels as u16);
+        } else {
+            // 16.16 fixed point
+            self.audio_sample_rate = Some(sample_rate >> 16);
+            self.audio_channels = Some(channels);
+        }
+        Ok((remain, ()))
+    }
+}
+
+/// Returns the `trak` boxes of a `moov` body. Parsing stops at the first
+/// malformed box.
+pub fn parse_traks_in_moov(input: &[u8]) -> Vec<TrakBox> {
+    let mut traks = Vec::new();
+    let mut remain = input;
+    while !remain.is_empty() {
+        let Ok((rem, bbox)) = BoxHolder::parse(remain) else {
+            break;
+        };
+        remain = rem;
+        if bbox.box_type() == "trak" {
+            traks.push(TrakBox::parse(&bbox));
+        }
+    }
+    traks
+}
+
+/// Returns the color description of the extension boxes of a video sample
+/// description, including the Dolby Vision configuration, or `None` if there
+/// isn't any. ICC profiles are ignored.
+fn parse_color(children: &[u8]) -> Option<ColorInfo> {
+    let mut color = ColorInfo::default();
+    let mut found = false;
+    let mut remain = children;
+    while let Ok((rem, bbox)) = BoxHolder::parse(remain) {
+        remain = rem;
+        let body = bbox.body_data();
+        match bbox.box_type() {
+            "colr" => found |= parse_colr(body, &mut color).is_ok(),
+            "mdcv" => {
+                if let Ok((_, display)) = parse_mdcv(body) {
+                    color.mastering_display = Some(display);
+                    found = true;
+                }
+            }
+            "clli" => {
+                if let Ok((_, level)) = parse_clli(body) {
+                    color.content_light = Some(level);
+                    found = true;
+                }
+            }
+            "dvcC" | "dvvC" | "dvwC" => {
+                color.dolby_vision = DolbyVision::parse(body);
+                found |= color.dolby_vision.is_some();
+            }
+            _ => (),
+        }
+    }
+    found.then_some(color)
+}
+
+/// Returns the first decoder configuration of the extension boxes of a
+/// video sample description which is recognized.
+fn parse_codec_config(children: &[u8]) -> Option<CodecConfig> {
+    let mut remain = children;
+    while let Ok((rem, bbox)) = BoxHolder::parse(remain) {
+        remain = rem;
+        if let Some(config) = CodecConfig::parse(bbox.box_type(), bbox.body_data()) {
+            return Some(config);
+        }
+    }
+    None
+}
+
+/// Parses a `colr` box body with an `nclx` (ISO) or `nclc` (QuickTime)
+/// color type.
+fn parse_colr<'a>(body: &'a [u8], color: &mut ColorInfo) -> IResult<&'a [u8], ()> {
+    let (remain, color_type) = take(4usize)(body)?;
+    let nclx = match color_type {
+        b"nclx" => true,
+        b"nclc" => false,
+        _ => return nom::combinator::fail(remain),
+    };
+    let (mut remain, (primaries, transfer, matrix)) = tuple((be_u16, be_u16, be_u16))(remain)?;
+    (color.primaries, color.transfer, color.matrix) =
+        (Some(primaries), Some(transfer), Some(matrix));
+    if nclx {
+        let (rem, flags) = take(1usize)(remain)?;
+        remain = rem;
+        color.full_range = Some(flags[0] & 0x80 != 0);
+    }
+    Ok((remain, ()))
+}
+
+/// Parses a `mdcv` box body, whose primaries are in G, B, R order.
+fn parse_mdcv(body: &[u8]) -> IResult<&[u8], MasteringDisplay> {
+    let xy = || tuple((be_u16, be_u16));
+    let (remain, (green, blue, red, white_point)) = tuple((xy(), xy(), xy(), xy()))(body)?;
+    let (remain, (max_luminance, min_luminance)) = tuple((be_u32, be_u32))(remain)?;
+    Ok((
+        remain,
+        MasteringDisplay {
+            red,
+            green,
+            blue,
+            white_point,
+            max_luminance,
+            min_luminance,
+        },
+    ))
+}
+
+/// Parses a `clli` box body.
+fn parse_clli(body: &[u8]) -> IResult<&[u8], ContentLightLevel> {
+    let (remain, (max_cll, max_fall)) = tuple((be_u16, be_u16))(body)?;
+    Ok((remain, ContentLightLevel { max_cll, max_fall }))
+}
+
+/// Returns the total duration of the edits of an edit list atom (`elst`
+/// body, including version & flags), empty edits (delays) included.
+fn parse_elst(body: &[u8]) -> IResult<&[u8], u64> {
+    let (mut remain, (version, _, entry_count)) = tuple((be_u8, take(3usize), be_u32))(body)?;
+    let mut duration = 0u64;
+    for _ in 0..entry_count {
+        // segment duration, media time, media rate
+        let (rem, segment) = if version == 1 {
+            let (rem, (segment, _, _)) = tuple((be_u64, be_u64, be_u32))(remain)?;
+            (rem, segment)
+        } else {
+            let (rem, (segment, _, _)) = tuple((be_u32, be_u32, be_u32))(remain)?;
+            (rem, segment as u64)
+        };
+        remain = rem;
+        duration = duration.saturating_add(segment);
+    }
+    Ok((remain, duration))
+}
+
+/// Returns the sample count and total duration of a time-to-sample atom
+/// (`stts` body, including version & flags).
+fn parse_stts(body: &[u8]) -> IResult<&[u8], (u64, u64)> {
+    let (mut remain, (_, entry_count)) = tuple((be_u32, be_u32))(body)?;
+    let (mut count, mut duration) = (0u64, 0u64);
+    for _ in 0..entry_count {
+        let (rem, (n, delta)) = tuple((be_u32, be_u32))(remain)?;
+        remain = rem;
+        count = count.saturating_add(n as u64);
+        duration = duration.saturating_add(n as u64 * delta as u64);
+    }
+    Ok((remain, (count, duration)))
+}
+
+/// Returns the total size of the samples of a sample size atom (`stsz`
+/// body, including version & flags).
+fn parse_stsz(body: &[u8]) -> IResult<&[u8], u64> {
+    let (mut remain, (_, sample_size, sample_count)) = tuple((be_u32, be_u32, be_u32))(body)?;
+    if sample_size != 0 {
+        return Ok((remain, sample_size as u64 * sample_count as u64));
+    }
+
+    let mut total = 0u64;
+    for _ in 0..sample_count {
+        let (rem, size) = be_u32(remain)?;
+        remain = rem;
+        total += size as u64;
+    }
+    Ok((remain, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bbox::travel_while, testkit::read_sample};
+
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("meta.mov", "vide", "avc1", 30, 60.0)]
+    #[test_case("meta.mp4", "vide", "avc1", 32, 30.0)]
+    #[test_case("embedded-in-heic.mov", "vide", "hvc1", 60, 21.47)]
+    fn trak_box(path: &str, handler: &str, codec: &str, samples: u64, frame_rate: f64) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample(path).unwrap();
+        let (_, bbox) = travel_while(&buf, |b| b.box_type() != "moov").unwrap();
+        let traks = parse_traks_in_moov(bbox.unwrap().body_data());
+
+        let trak = &traks[0];
+        assert_eq!(trak.handler_type, handler);
+        assert_eq!(trak.codec.as_deref(), Some(codec));
+        assert_eq!(trak.sample_count, samples);
+        assert!((trak.frame_rate().unwrap() - frame_rate).abs() < 0.01);
+        assert!(trak.sample_bytes > 0);
+        assert_eq!(trak.audio_channels, None);
+    }
+
+    #[test_case("meta.mov", 1, 44100)]
+    #[test_case("meta.mp4", 2, 48000)]
+    fn audio_trak(path: &str, channels: u16, sample_rate: u32) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample(path).unwrap();
+        let (_, bbox) = travel_while(&buf, |b| b.box_type() != "moov").unwrap();
+        let traks = parse_traks_in_moov(bbox.unwrap().body_data());
+
+        let trak = traks.iter().find(|x| x.handler_type == "soun").unwrap();
+        assert_eq!(trak.codec.as_deref(), Some("mp4a"));
+        assert_eq!(trak.audio_channels, Some(channels));
+        assert_eq!(trak.audio_sample_rate, Some(sample_rate));
+    }
+
+    #[test_case("meta.mov", 12, 1, 6, None)]
+    #[test_case("meta.mp4", 1, 1, 1, Some(false))]
+    fn trak_color(
+        path: &str,
+        primaries: u16,
+        transfer: u16,
+        matrix: u16,
+        full_range: Option<bool>,
+    ) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample(path).unwrap();
+        let (_, bbox) = travel_while(&buf, |b| b.box_type() != "moov").unwrap();
+        let traks = parse_traks_in_moov(bbox.unwrap().body_data());
+
+        let color = traks[0].color.as_ref().unwrap();
+        assert_eq!(color.primaries, Some(primaries));
+        assert_eq!(color.transfer, Some(transfer));
+        assert_eq!(color.matrix, Some(matrix));
+        assert_eq!(color.full_range, full_range);
+        assert_eq!(color.mastering_display, None);
+        assert!(traks[1].color.is_none());
+    }
+
+    #[test_case("meta.mov", 77, 32)]
+    #[test_case("meta.mp4", 100, 41)]
+    #[test_case("embedded-in-heic.mov", 1, 150)]
+    fn trak_codec_config(path: &str, profile: u8, level: u8) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample(path).unwrap();
+        let (_, bbox) = travel_while(&buf, |b| b.box_type() != "moov").unwrap();
+        let traks = parse_traks_in_moov(bbox.unwrap().body_data());
+
+        let config = traks[0].codec_config.as_ref().unwrap();
+        assert_eq!((config.profile, config.level), (profile, level));
+        assert_eq!(
+            config.chroma_subsampling,
+            Some(crate::ChromaSubsampling::Yuv420)
+        );
+        assert_eq!(config.bit_depth, Some(8));
+        assert!(traks[1].codec_config.is_none());
+    }
+
+    #[test]
+    fn hdr_color() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // HDR10: BT.2020 primaries, PQ, 1000 cd/m² mastering display
+        let mut children = b"\0\0\0\x13colrnclx\0\x09\0\x10\0\x09\x80".to_vec();
+        children.extend(b"\0\0\0\x20mdcv");
+        for x in [8500u16, 39850, 6550, 2300, 35400, 14600, 15635, 16450] {
+            children.extend(x.to_be_bytes());
+        }
+        children.extend(10_000_000u32.to_be_bytes());
+        children.extend(50u32.to_be_bytes());
+        children.extend(b"\0\0\0\x0cclli\x03\xe8\x01\x90");
+
+        let color = parse_color(&children).unwrap();
+        assert_eq!(
+            color,
+            ColorInfo {
+                primaries: Some(9),
+                transfer: Some(16),
+                matrix: Some(9),
+                full_range: Some(true),
+                mastering_display: Some(MasteringDisplay {
+                    red: (35400, 14600),
+                    green: (8500, 39850),
+                    blue: (6550, 2300),
+                    white_point: (15635, 16450),
+                    max_luminance: 10_000_000,
+                    min_luminance: 50,
+                }),
+                content_light: Some(ContentLightLevel {
+                    max_cll: 1000,
+                    max_fall: 400,
+                }),
+                ..Default::default()
+            }
+        );
+        assert_eq!(color.hdr_format(), Some(crate::HdrFormat::Hdr10));
+
+        // Dolby Vision profile 8.1, level 6, with an HDR10 compatible base
+        // layer
+        children.extend(b"\0\0\0\x20dvvC\x01\0\x10\x35\x10\0");
+        children.extend([0; 18]);
+        let color = parse_color(&children).unwrap();
+        assert_eq!(
+            color.dolby_vision,
+            Some(DolbyVision {
+                profile: 8,
+                level: 6,
+                enhancement_layer: false,
+                compatibility_id: 1,
+            })
+        );
+        assert_eq!(color.hdr_format(), Some(crate::HdrFormat::DolbyVision));
+
+        // ICC profiles only
+        assert_eq!(parse_color(b"\0\0\0\x0ccolrprof"), None);
+    }
+
+    #[test]
+    fn stts_overflow() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut body = vec![0, 0, 0, 0];
+        body.extend(3u32.to_be_bytes());
+        for _ in 0..3 {
+            body.extend(u32::MAX.to_be_bytes());
+            body.extend(u32::MAX.to_be_bytes());
+        }
+        let (_, (count, duration)) = parse_stts(&body).unwrap();
+        assert_eq!(count, 3 * u32::MAX as u64);
+        assert_eq!(duration, u64::MAX);
+    }
+
+    #[test]
+    fn elst_overflow() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut body = vec![1, 0, 0, 0];
+        body.extend(2u32.to_be_bytes());
+        for _ in 0..2 {
+            body.extend(u64::MAX.to_be_bytes());
+            body.extend([0; 12]);
+        }
+        let (_, duration) = parse_elst(&body).unwrap();
+        assert_eq!(duration, u64::MAX);
+    }
+}
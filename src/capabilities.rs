@@ -0,0 +1,279 @@
+use std::{
+    fmt,
+    io::{Read, Seek},
+    ops::{BitOr, BitOrAssign},
+};
+
+use nom::{IResult, Needed};
+
+use crate::{
+    error::convert_parse_error, exif::input_to_iter, file::FileFormat, heif, jpeg,
+    media::MIN_GROW_SIZE, mov::LOCATION_KEY, ExifTag, IfdKind, MediaParser,
+};
+
+/// The kinds of metadata a media file contains, as returned by
+/// [`capabilities`]. Flags can be combined with `|`.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./testdata/exif.heic").unwrap();
+/// let caps = capabilities(f).unwrap();
+/// assert!(caps.contains(Capabilities::EXIF | Capabilities::XMP));
+/// assert!(!caps.contains(Capabilities::TRACK));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    /// Exif data (JPEG APP1 segment, HEIF `Exif` item).
+    pub const EXIF: Self = Self(1);
+
+    /// An XMP packet (JPEG APP1 segment, HEIF `mime` item).
+    pub const XMP: Self = Self(1 << 1);
+
+    /// Photoshop image resources, which hold IPTC data (JPEG APP13 segment).
+    pub const IPTC: Self = Self(1 << 2);
+
+    /// QuickTime/MP4 metadata, see [`parse_metadata_iter`](crate::parse_metadata_iter).
+    pub const TRACK: Self = Self(1 << 3);
+
+    /// The Exif data has a GPS IFD, or the video has a location entry. The
+    /// location itself isn't parsed, it may still be incomplete.
+    pub const GPS: Self = Self(1 << 4);
+
+    /// The Exif data has a thumbnail (`ThumbnailOffset` in IFD1).
+    pub const THUMBNAIL: Self = Self(1 << 5);
+
+    const NAMES: [(Self, &'static str); 6] = [
+        (Self::EXIF, "EXIF"),
+        (Self::XMP, "XMP"),
+        (Self::IPTC, "IPTC"),
+        (Self::TRACK, "TRACK"),
+        (Self::GPS, "GPS"),
+        (Self::THUMBNAIL, "THUMBNAIL"),
+    ];
+
+    /// Returns an empty set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the raw bits of this set.
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if no flag is set.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if all flags of `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Capabilities {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        write!(f, "Capabilities({})", names.join(" | "))
+    }
+}
+
+/// Detects the file format of `reader`, and which kinds of metadata it
+/// contains, without parsing the metadata itself: only the JPEG segments
+/// before the image data, the HEIF `meta` box (and Exif item), or the
+/// QuickTime/MP4 `moov` atom are read. Callers can use this to decide what
+/// to parse, e.g. skip files without Exif data or location.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// let caps = capabilities(f).unwrap();
+/// assert!(caps.contains(Capabilities::EXIF | Capabilities::GPS));
+/// assert!(caps.contains(Capabilities::THUMBNAIL));
+///
+/// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+/// assert_eq!(
+///     capabilities(f).unwrap(),
+///     Capabilities::TRACK | Capabilities::GPS
+/// );
+/// ```
+pub fn capabilities<R: Read + Seek>(reader: R) -> crate::Result<Capabilities> {
+    MediaParser::default().capabilities(reader)
+}
+
+pub(crate) fn read_capabilities<R: Read + Seek>(
+    reader: R,
+    parser: &MediaParser,
+) -> crate::Result<Capabilities> {
+    let (ff, reader) = FileFormat::sniff(reader)?;
+
+    match ff {
+        FileFormat::Jpeg => read_until(reader, parser, jpeg::scan_capabilities),
+        FileFormat::Heif => read_until(reader, parser, heif::scan_capabilities),
+        FileFormat::QuickTime | FileFormat::MP4 | FileFormat::Matroska => {
+            let mut iter = parser.parse_track_seekable(reader, ff)?;
+            let mut caps = Capabilities::TRACK;
+            if iter.any(|(key, _)| key == LOCATION_KEY) {
+                caps |= Capabilities::GPS;
+            }
+            Ok(caps)
+        }
+    }
+}
+
+/// Returns the kinds of metadata found in the Exif `data` (TIFF header and
+/// IFDs). The values of the entries aren't parsed.
+pub(crate) fn exif_capabilities(data: &[u8]) -> Capabilities {
+    let Ok(iter) = input_to_iter(data) else {
+        return Capabilities::empty();
+    };
+
+    let mut caps = Capabilities::empty();
+    for entry in iter {
+        match entry.ifd_kind() {
+            IfdKind::Gps => caps |= Capabilities::GPS,
+            IfdKind::Ifd1 if entry.tag() == Some(ExifTag::ThumbnailOffset) => {
+                caps |= Capabilities::THUMBNAIL
+            }
+            _ => (),
+        }
+    }
+    caps
+}
+
+/// Reads `reader` into a buffer until `parse` succeeds, growing the buffer
+/// within the limits of `parser`.
+fn read_until<R: Read, T>(
+    mut reader: R,
+    parser: &MediaParser,
+    parse: impl Fn(&[u8]) -> IResult<&[u8], T>,
+) -> crate::Result<T> {
+    let init_size = parser.init_read_size();
+    let mut buf = Vec::with_capacity(init_size);
+    parser.stats.start(false);
+    let n = reader
+        .by_ref()
+        .take(init_size as u64)
+        .read_to_end(buf.as_mut())?;
+    parser.stats.read(n, buf.len());
+
+    loop {
+        let needed = match parse(&buf[..]) {
+            Ok((_, res)) => break Ok(res),
+            Err(nom::Err::Incomplete(Needed::Size(n))) => Some(n.get()),
+            Err(nom::Err::Incomplete(Needed::Unknown)) => None,
+            Err(err) => break Err(convert_parse_error(err, "scan metadata failed")),
+        };
+
+        let to_read = parser.grow_size(buf.len(), needed.unwrap_or(MIN_GROW_SIZE))?;
+        parser.reserve_buffer(&mut buf, to_read);
+        let n = reader
+            .by_ref()
+            .take(to_read as u64)
+            .read_to_end(buf.as_mut())?;
+        parser.stats.read(n, buf.len());
+        if n == 0 {
+            break Err(crate::Error::Truncated { needed });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testkit::*, MetadataEdits};
+    use test_case::test_case;
+
+    const EXIF: Capabilities = Capabilities::EXIF;
+    const XMP: Capabilities = Capabilities::XMP;
+    const TRACK: Capabilities = Capabilities::TRACK;
+    const GPS: Capabilities = Capabilities::GPS;
+    const THUMBNAIL: Capabilities = Capabilities::THUMBNAIL;
+
+    #[test_case("exif.jpg", EXIF | GPS | THUMBNAIL)]
+    #[test_case("no-exif.jpg", Capabilities::empty())]
+    #[test_case("exif.heic", EXIF | XMP | GPS)]
+    #[test_case("no-exif.heic", EXIF)] // An Exif item without GPS IFD
+    #[test_case("meta.mov", TRACK | GPS)]
+    #[test_case("meta.mp4", TRACK | GPS)]
+    fn sample_capabilities(path: &str, expect: Capabilities) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let f = open_sample(path).unwrap();
+        assert_eq!(capabilities(f).unwrap(), expect);
+    }
+
+    #[test]
+    fn jpeg_xmp_iptc() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample("no-exif.jpg").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.set_xmp(r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#);
+        let mut out = Vec::new();
+        crate::write_metadata(&buf[..], &mut out, &edits).unwrap();
+        assert_eq!(
+            capabilities(std::io::Cursor::new(&out)).unwrap(),
+            Capabilities::XMP
+        );
+
+        // Insert an APP13 segment right after SOI
+        let payload = b"Photoshop 3.0\08BIM\x04\x04\0\0\0\0\0\0";
+        let mut data = vec![0xff, 0xd8, 0xff, 0xed];
+        data.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        data.extend_from_slice(payload);
+        data.extend_from_slice(&out[2..]);
+        assert_eq!(
+            capabilities(std::io::Cursor::new(&data)).unwrap(),
+            Capabilities::XMP | Capabilities::IPTC
+        );
+    }
+
+    #[test]
+    fn capabilities_limits() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // The Exif segment of exif.jpg is larger than the limit
+        let parser = MediaParser::builder().max_metadata_size(1024).build();
+        let err = parser
+            .capabilities(open_sample("exif.jpg").unwrap())
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::LimitExceeded { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn capabilities_debug() {
+        assert_eq!(format!("{:?}", EXIF | GPS), "Capabilities(EXIF | GPS)");
+        assert_eq!(format!("{:?}", Capabilities::empty()), "Capabilities()");
+        assert!(Capabilities::empty().is_empty());
+        assert!((EXIF | GPS).contains(GPS));
+        assert!(!(EXIF | GPS).contains(XMP | GPS));
+    }
+}
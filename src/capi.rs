@@ -0,0 +1,451 @@
+//! A C API, for embedding the parser in C, C++ or Swift applications.
+//! Enabled by the `capi` feature flag, see `include/nomexif.h` for the
+//! declarations.
+//!
+//! Build the crate as a `cdylib` or `staticlib` to link it, e.g. with
+//! `cargo rustc --release --features capi --crate-type cdylib`.
+//!
+//! # Usage
+//!
+//! ```c
+//! NomExifEntries *entries = nomexif_parse_file("photo.jpg");
+//! if (entries == NULL) {
+//!     fprintf(stderr, "%s\n", nomexif_last_error());
+//!     return 1;
+//! }
+//! for (size_t i = 0; i < nomexif_entries_len(entries); i++) {
+//!     printf("%s => %s\n", nomexif_entry_key(entries, i), nomexif_entry_text(entries, i));
+//! }
+//! nomexif_entries_free(entries);
+//! ```
+//!
+//! The strings returned by the entry accessors are owned by the
+//! `NomExifEntries`, and valid until it's freed.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    fs::File,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::{EntryValue, Exif, MediaInfo};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The entries of a file, returned by [`nomexif_parse_file`]: the Exif
+/// entries of an image, or the metadata items of a video. The entries whose
+/// values couldn't be parsed are left out.
+#[derive(Debug)]
+pub struct NomExifEntries {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: CString,
+    tag: u16,
+    value: EntryValue,
+    text: CString,
+}
+
+/// The type of an entry value, see [`nomexif_entry_type`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NomExifValueType {
+    /// The entry doesn't exist.
+    None = 0,
+    Text = 1,
+    Integer = 2,
+    Float = 3,
+    Rational = 4,
+    Time = 5,
+    /// Undefined data, or an array of rationals.
+    Other = 6,
+}
+
+impl NomExifEntries {
+    fn new(info: Option<MediaInfo>) -> Self {
+        let entries = match info {
+            Some(MediaInfo::Exif(iter)) => Exif::from(iter)
+                .into_iter()
+                .map(|(tag, value)| Entry::new(tag.info().name.to_owned(), tag.code(), value))
+                .collect(),
+            Some(MediaInfo::Track(iter)) => iter
+                .filter_map(|(key, value)| Some(Entry::new(key, 0, value.ok()?)))
+                .collect(),
+            None => Vec::new(),
+        };
+        Self { entries }
+    }
+}
+
+impl Entry {
+    fn new(key: String, tag: u16, value: EntryValue) -> Self {
+        Self {
+            key: c_string(key),
+            tag,
+            text: c_string(value.to_string()),
+            value,
+        }
+    }
+
+    fn value_type(&self) -> NomExifValueType {
+        use EntryValue as V;
+        match self.value {
+            V::Text(_) => NomExifValueType::Text,
+            V::U8(_) | V::U16(_) | V::U32(_) | V::U64(_) => NomExifValueType::Integer,
+            V::I8(_) | V::I16(_) | V::I32(_) | V::I64(_) => NomExifValueType::Integer,
+            V::F32(_) | V::F64(_) => NomExifValueType::Float,
+            V::URational(_) | V::IRational(_) => NomExifValueType::Rational,
+            V::Time(_) => NomExifValueType::Time,
+            V::Undefined(_) | V::URationalArray(_) | V::IRationalArray(_) => {
+                NomExifValueType::Other
+            }
+        }
+    }
+}
+
+/// Removes the NUL bytes which C strings can't hold.
+fn c_string(s: String) -> CString {
+    CString::new(s).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).expect("NUL bytes have been removed")
+    })
+}
+
+fn set_last_error(msg: String) {
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(c_string(msg)));
+}
+
+/// Returns the entry at `index`, or `None` if `entries` is NULL or `index`
+/// is out of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed.
+unsafe fn entry<'a>(entries: *const NomExifEntries, index: usize) -> Option<&'a Entry> {
+    entries.as_ref()?.entries.get(index)
+}
+
+/// Parses the image or video at `path` (a NUL-terminated UTF-8 string).
+///
+/// Returns NULL if the file can't be opened or parsed, see
+/// [`nomexif_last_error`]. A file without metadata has no entries. The
+/// result must be freed with [`nomexif_entries_free`].
+///
+/// # Safety
+///
+/// `path` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_parse_file(path: *const c_char) -> *mut NomExifEntries {
+    if path.is_null() {
+        set_last_error("path is NULL".to_owned());
+        return ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        set_last_error("path isn't valid UTF-8".to_owned());
+        return ptr::null_mut();
+    };
+
+    // Unwinding into C is undefined behavior
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        let info = crate::parse_media(File::open(path)?)?;
+        crate::Result::Ok(NomExifEntries::new(info))
+    }));
+    match res {
+        Ok(Ok(entries)) => Box::into_raw(Box::new(entries)),
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("parsing panicked".to_owned());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the message of the last error of [`nomexif_parse_file`] on this
+/// thread, or NULL. It's valid until the next call of
+/// [`nomexif_parse_file`] on this thread.
+#[no_mangle]
+pub extern "C" fn nomexif_last_error() -> *const c_char {
+    LAST_ERROR.with(|x| x.borrow().as_ref().map_or(ptr::null(), |x| x.as_ptr()))
+}
+
+/// Frees entries returned by [`nomexif_parse_file`], does nothing if
+/// `entries` is NULL.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// already freed.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entries_free(entries: *mut NomExifEntries) {
+    if !entries.is_null() {
+        drop(Box::from_raw(entries));
+    }
+}
+
+/// Returns the number of entries, 0 if `entries` is NULL.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entries_len(entries: *const NomExifEntries) -> usize {
+    entries.as_ref().map_or(0, |x| x.entries.len())
+}
+
+/// Returns the key of an entry: the tag name of an Exif entry (e.g.
+/// `"Make"`), or the key of a video metadata item (e.g.
+/// `"com.apple.quicktime.make"`). NULL if `index` is out of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_key(
+    entries: *const NomExifEntries,
+    index: usize,
+) -> *const c_char {
+    entry(entries, index).map_or(ptr::null(), |x| x.key.as_ptr())
+}
+
+/// Returns the Exif tag code of an entry (e.g. `0x010f` for `Make`), 0 for
+/// video metadata items or if `index` is out of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_tag(entries: *const NomExifEntries, index: usize) -> u16 {
+    entry(entries, index).map_or(0, |x| x.tag)
+}
+
+/// Returns the type of the value of an entry, `None` if `index` is out of
+/// range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_type(
+    entries: *const NomExifEntries,
+    index: usize,
+) -> NomExifValueType {
+    entry(entries, index).map_or(NomExifValueType::None, Entry::value_type)
+}
+
+/// Returns the value of an entry as text, as printed by `rexiftool`. NULL
+/// if `index` is out of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_text(
+    entries: *const NomExifEntries,
+    index: usize,
+) -> *const c_char {
+    entry(entries, index).map_or(ptr::null(), |x| x.text.as_ptr())
+}
+
+/// Stores the value of an integer entry in `out`, see
+/// [`EntryValue::to_i64`]. Returns false if the value isn't an integer
+/// which fits, or if `index` is out of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed. `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_i64(
+    entries: *const NomExifEntries,
+    index: usize,
+    out: *mut i64,
+) -> bool {
+    match entry(entries, index).and_then(|x| x.value.to_i64()) {
+        Some(v) => {
+            out.write(v);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stores the value of a numeric entry (including rationals) in `out`, see
+/// [`EntryValue::to_f64`]. Returns false if the value isn't numeric, or if
+/// `index` is out of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed. `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_f64(
+    entries: *const NomExifEntries,
+    index: usize,
+    out: *mut f64,
+) -> bool {
+    match entry(entries, index).and_then(|x| x.value.to_f64()) {
+        Some(v) => {
+            out.write(v);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stores the numerator and the denominator of a rational entry in
+/// `numerator` and `denominator`, see [`EntryValue::to_rational`]. Returns
+/// false if the value isn't a rational or an integer, or if `index` is out
+/// of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed. `numerator` and `denominator` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_rational(
+    entries: *const NomExifEntries,
+    index: usize,
+    numerator: *mut i64,
+    denominator: *mut i64,
+) -> bool {
+    match entry(entries, index).and_then(|x| x.value.to_rational()) {
+        Some((n, d)) => {
+            numerator.write(n);
+            denominator.write(d);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stores the value of a time entry in `timestamp` (seconds since the Unix
+/// epoch) and `offset` (its UTC offset in seconds). Returns false if the
+/// value isn't a time, or if `index` is out of range.
+///
+/// # Safety
+///
+/// `entries` must be NULL, or returned by [`nomexif_parse_file`] and not
+/// freed. `timestamp` and `offset` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nomexif_entry_time(
+    entries: *const NomExifEntries,
+    index: usize,
+    timestamp: *mut i64,
+    offset: *mut i32,
+) -> bool {
+    match entry(entries, index).and_then(|x| x.value.as_time()) {
+        Some(t) => {
+            timestamp.write(t.timestamp());
+            offset.write(t.offset().local_minus_utc());
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn parse(path: &str) -> *mut NomExifEntries {
+        let path = CString::new(format!("./testdata/{path}")).unwrap();
+        unsafe { nomexif_parse_file(path.as_ptr()) }
+    }
+
+    unsafe fn find(entries: *const NomExifEntries, key: &str) -> Option<usize> {
+        (0..nomexif_entries_len(entries))
+            .find(|&i| CStr::from_ptr(nomexif_entry_key(entries, i)).to_str() == Ok(key))
+    }
+
+    #[test_case("exif.jpg", "Make", "vivo")]
+    #[test_case("exif.heic", "Model", "iPhone 12 Pro")]
+    #[test_case("meta.mov", "com.apple.quicktime.make", "Apple")]
+    fn capi_text(path: &str, key: &str, text: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let entries = parse(path);
+        assert!(!entries.is_null());
+        unsafe {
+            let i = find(entries, key).unwrap();
+            assert_eq!(nomexif_entry_type(entries, i), NomExifValueType::Text);
+            let value = CStr::from_ptr(nomexif_entry_text(entries, i));
+            assert_eq!(value.to_str().unwrap(), text);
+
+            let len = nomexif_entries_len(entries);
+            assert!(nomexif_entry_key(entries, len).is_null());
+            assert_eq!(nomexif_entry_type(entries, len), NomExifValueType::None);
+            nomexif_entries_free(entries);
+        }
+    }
+
+    #[test]
+    fn capi_values() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let entries = parse("exif.jpg");
+        unsafe {
+            let i = find(entries, "ISOSpeedRatings").unwrap();
+            assert_eq!(nomexif_entry_tag(entries, i), 0x8827);
+            assert_eq!(nomexif_entry_type(entries, i), NomExifValueType::Integer);
+            let mut iso = 0;
+            assert!(nomexif_entry_i64(entries, i, &mut iso));
+            assert_eq!(iso, 454);
+
+            let i = find(entries, "FNumber").unwrap();
+            assert_eq!(nomexif_entry_type(entries, i), NomExifValueType::Rational);
+            let (mut n, mut d) = (0, 0);
+            assert!(nomexif_entry_rational(entries, i, &mut n, &mut d));
+            assert_eq!((n, d), (175, 100));
+            let mut f = 0.0;
+            assert!(nomexif_entry_f64(entries, i, &mut f));
+            assert_eq!(f, 1.75);
+            assert!(!nomexif_entry_i64(entries, i, &mut iso));
+
+            let i = find(entries, "DateTimeOriginal").unwrap();
+            let (mut timestamp, mut offset) = (0, 0);
+            assert!(nomexif_entry_time(entries, i, &mut timestamp, &mut offset));
+            assert_eq!(offset, 8 * 3600);
+            nomexif_entries_free(entries);
+        }
+    }
+
+    #[test]
+    fn capi_errors() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        assert!(parse("missing.jpg").is_null());
+        assert!(!nomexif_last_error().is_null());
+        assert!(unsafe { nomexif_parse_file(ptr::null()) }.is_null());
+        let msg = unsafe { CStr::from_ptr(nomexif_last_error()) };
+        assert_eq!(msg.to_str().unwrap(), "path is NULL");
+
+        unsafe {
+            assert_eq!(nomexif_entries_len(ptr::null()), 0);
+            nomexif_entries_free(ptr::null_mut());
+        }
+
+        let entries = parse("no-exif.jpg");
+        assert!(!entries.is_null());
+        unsafe {
+            assert_eq!(nomexif_entries_len(entries), 0);
+            nomexif_entries_free(entries);
+        }
+    }
+}
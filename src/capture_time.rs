@@ -0,0 +1,227 @@
+use std::io::{Read, Seek};
+
+use chrono::{
+    offset::LocalResult, DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone as _,
+};
+use memchr::memmem;
+
+use crate::{file::FileFormat, read_xmp, Exif, ExifTag, MediaParser};
+
+/// Where the time returned by [`best_capture_time`] comes from. Sources are
+/// tried in the order of the variants, from the most to the least reliable.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureTimeSource {
+    /// An Exif date/time tag of an image: `DateTimeOriginal`, `CreateDate`
+    /// or `ModifyDate`, see [`Exif::datetime_original`].
+    Exif(ExifTag),
+
+    /// An XMP property of an image, e.g. `"exif:DateTimeOriginal"`,
+    /// `"photoshop:DateCreated"` or `"xmp:CreateDate"`.
+    Xmp(&'static str),
+
+    /// The `com.apple.quicktime.creationdate` item of a QuickTime/MP4 file,
+    /// e.g. the video of a live photo.
+    QuickTimeCreationDate,
+
+    /// The creation time of the movie header (`moov/mvhd`) of a
+    /// QuickTime/MP4 file, in UTC. Many cameras set it to the time the
+    /// recording ended, or leave it in local time.
+    MovieHeader,
+}
+
+/// A capture time, along with where it was found, see [`best_capture_time`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureTimeInfo {
+    pub time: DateTime<FixedOffset>,
+    pub source: CaptureTimeSource,
+}
+
+impl CaptureTimeInfo {
+    /// Returns [`Self::time`] as a `time::OffsetDateTime`.
+    #[cfg(feature = "time")]
+    pub fn offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        crate::values::to_offset_datetime(&self.time)
+    }
+}
+
+/// XMP properties holding the capture time, by priority.
+const XMP_PROPERTIES: [&str; 3] = [
+    "exif:DateTimeOriginal",
+    "photoshop:DateCreated",
+    "xmp:CreateDate",
+];
+
+/// Returns the time when the media in `reader` was captured, and where it
+/// was found (see [`CaptureTimeSource`]), or `None` if it has no capture
+/// time at all:
+///
+/// - For images (JPEG, HEIF/HEIC): the Exif date/time tags (see
+///   [`Exif::datetime_original`]), then the XMP packet, which is where
+///   some editors and HEIF writers store the creation date of images
+///   without Exif data. Reading the XMP packet reads the whole file.
+///
+/// - For videos (QuickTime, MP4), e.g. the video of a live photo: the
+///   `com.apple.quicktime.creationdate` item, then the creation time of the
+///   movie header.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// let info = best_capture_time(f).unwrap().unwrap();
+/// assert_eq!(info.source, CaptureTimeSource::Exif(ExifTag::DateTimeOriginal));
+/// assert_eq!(info.time.to_rfc3339(), "2023-07-09T20:36:33.616+08:00");
+///
+/// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+/// let info = best_capture_time(f).unwrap().unwrap();
+/// assert_eq!(info.source, CaptureTimeSource::QuickTimeCreationDate);
+/// assert_eq!(info.time.to_rfc3339(), "2019-02-12T15:27:12+08:00");
+/// ```
+pub fn best_capture_time<R: Read + Seek>(reader: R) -> crate::Result<Option<CaptureTimeInfo>> {
+    MediaParser::default().best_capture_time(reader)
+}
+
+pub(crate) fn read_capture_time<R: Read + Seek>(
+    reader: R,
+    parser: &MediaParser,
+) -> crate::Result<Option<CaptureTimeInfo>> {
+    let (ff, mut reader) = FileFormat::sniff(reader)?;
+
+    let res = match ff {
+        FileFormat::Jpeg | FileFormat::Heif => {
+            if let Some(iter) = parser.parse_exif(reader.by_ref(), Some(ff))? {
+                let exif: Exif = iter.into();
+                if let Some((tag, time)) = exif.datetime_original_with_tag() {
+                    return Ok(Some(CaptureTimeInfo {
+                        time,
+                        source: CaptureTimeSource::Exif(tag),
+                    }));
+                }
+            }
+            reader.rewind()?;
+            read_xmp(reader)?.and_then(|xmp| xmp_capture_time(&xmp))
+        }
+        FileFormat::QuickTime | FileFormat::MP4 | FileFormat::Matroska => {
+            parser.parse_track_seekable(reader, ff)?.into_capture_time()
+        }
+    };
+    Ok(res.map(|(time, source)| CaptureTimeInfo { time, source }))
+}
+
+/// Returns the first capture time property found in an XMP packet, either
+/// as an attribute or as an element.
+fn xmp_capture_time(xmp: &str) -> Option<(DateTime<FixedOffset>, CaptureTimeSource)> {
+    XMP_PROPERTIES.iter().find_map(|&name| {
+        let value = xmp_property(xmp, name)?;
+        let time = parse_xmp_date(value.trim())?;
+        Some((time, CaptureTimeSource::Xmp(name)))
+    })
+}
+
+/// Returns the value of the first occurrence of property `name` in an XMP
+/// packet, either as an attribute (`name="value"`) or as an element
+/// (`<name>value</name>`). Packets may be large, so they're searched with
+/// `memmem` (SIMD accelerated) rather than a regex.
+fn xmp_property<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    memmem::find_iter(xmp.as_bytes(), name.as_bytes()).find_map(|pos| {
+        // Safe-slice: `name` is ASCII, so it ends at a char boundary
+        let rest = &xmp[pos + name.len()..];
+        if let Some(rest) = rest.strip_prefix('>') {
+            return rest.find('<').map(|end| &rest[..end]);
+        }
+        let rest = rest.trim_start().strip_prefix('=')?;
+        let rest = rest.trim_start().strip_prefix('"')?;
+        rest.find('"').map(|end| &rest[..end])
+    })
+}
+
+/// Parses an XMP date, e.g. `2023-07-09T20:36:33+08:00`. Dates without a
+/// time zone are in local time, like Exif dates without offset tags.
+fn parse_xmp_date(s: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Some(t);
+    }
+    // Seconds are optional
+    let utc = s.strip_suffix('Z').map(|x| format!("{x}+00:00"));
+    if let Ok(t) = DateTime::parse_from_str(utc.as_deref().unwrap_or(s), "%Y-%m-%dT%H:%M%:z") {
+        return Some(t);
+    }
+
+    let t = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+            date.and_hms_opt(0, 0, 0)
+        })?;
+    match Local.from_local_datetime(&t) {
+        LocalResult::Single(t) => Some(t.fixed_offset()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testkit::*, write_metadata, MetadataEdits};
+    use std::io::Cursor;
+    use test_case::test_case;
+
+    #[test_case("exif.jpg", Some(CaptureTimeSource::Exif(ExifTag::DateTimeOriginal)))]
+    #[test_case("exif.heic", Some(CaptureTimeSource::Exif(ExifTag::DateTimeOriginal)))]
+    #[test_case("no-exif.jpg", None)]
+    #[test_case("meta.mov", Some(CaptureTimeSource::QuickTimeCreationDate))]
+    #[test_case("embedded-in-heic.mov", Some(CaptureTimeSource::QuickTimeCreationDate))]
+    #[test_case("meta.mp4", Some(CaptureTimeSource::MovieHeader))]
+    fn sample_capture_time(path: &str, source: Option<CaptureTimeSource>) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let info = best_capture_time(open_sample(path).unwrap()).unwrap();
+        assert_eq!(info.map(|x| x.source), source);
+    }
+
+    #[test]
+    fn xmp_fallback() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut edits = MetadataEdits::new();
+        edits.set_xmp(concat!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF><rdf:Description "#,
+            r#"xmp:CreateDate="2021-05-01T10:00:00+02:00">"#,
+            r#"<photoshop:DateCreated>2021-05-01T09:59:58.5+02:00</photoshop:DateCreated>"#,
+            r#"</rdf:Description></rdf:RDF></x:xmpmeta>"#,
+        ));
+        let mut out = Vec::new();
+        write_metadata(&read_sample("no-exif.jpg").unwrap()[..], &mut out, &edits).unwrap();
+
+        let info = best_capture_time(Cursor::new(&out)).unwrap().unwrap();
+        assert_eq!(info.source, CaptureTimeSource::Xmp("photoshop:DateCreated"));
+        assert_eq!(info.time.to_rfc3339(), "2021-05-01T09:59:58.500+02:00");
+    }
+
+    #[test]
+    fn xmp_dates() {
+        let t = parse_xmp_date("2021-05-01T10:00Z").unwrap();
+        assert_eq!(t.to_rfc3339(), "2021-05-01T10:00:00+00:00");
+
+        let t = parse_xmp_date("2021-05-01T10:00:00").unwrap();
+        assert_eq!(t.naive_local().to_string(), "2021-05-01 10:00:00");
+
+        let t = parse_xmp_date("2021-05-01").unwrap();
+        assert_eq!(t.naive_local().to_string(), "2021-05-01 00:00:00");
+
+        assert!(parse_xmp_date("yesterday").is_none());
+    }
+
+    #[test_case(r#"<a xmp:CreateDate = "2021" b="1">"#, Some("2021"))]
+    #[test_case(r#"<xmp:CreateDate>2021</xmp:CreateDate>"#, Some("2021"))]
+    #[test_case(r#"<a xmp:CreateDateTime="x" xmp:CreateDate="2021"/>"#, Some("2021"))]
+    #[test_case(r#"<a xmp:CreateDate="2021"#, None)]
+    #[test_case(r#"<a xmp:ModifyDate="2021"/>"#, None)]
+    fn xmp_properties(xmp: &str, value: Option<&str>) {
+        assert_eq!(xmp_property(xmp, "xmp:CreateDate"), value);
+    }
+}
@@ -0,0 +1,170 @@
+/// The decoder configuration of an H.264, HEVC or AV1 video track, as
+/// returned in [`TrackMeta::codec_config`](crate::TrackMeta::codec_config),
+/// e.g. to check that a device can play a video before sending it, or to
+/// decide whether to transcode it.
+///
+/// It comes from the `avcC`, `hvcC` or `av1C` box of QuickTime/MP4 files, or
+/// the `CodecPrivate` element of Matroska files.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecConfig {
+    /// Profile, as coded: `profile_idc` for H.264 (e.g. 100 for High),
+    /// `general_profile_idc` for HEVC (e.g. 2 for Main 10), `seq_profile` for
+    /// AV1 (e.g. 0 for Main).
+    pub profile: u8,
+
+    /// Level, as coded: ten times the level for H.264 (e.g. 31 for 3.1),
+    /// thirty times the level for HEVC (e.g. 93 for 3.1), `seq_level_idx`
+    /// for AV1 (e.g. 8 for 4.0).
+    pub level: u8,
+
+    /// `None` if the configuration doesn't tell, e.g. for H.264 High 10
+    /// profile records without the optional extension.
+    pub chroma_subsampling: Option<ChromaSubsampling>,
+
+    /// Bits per luma sample.
+    pub bit_depth: Option<u8>,
+}
+
+/// The chroma subsampling of a video, see [`CodecConfig`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChromaSubsampling {
+    /// No chroma (4:0:0).
+    Monochrome,
+    Yuv420,
+    Yuv422,
+    Yuv444,
+}
+
+impl ChromaSubsampling {
+    /// Converts a `chroma_format_idc` of H.264 or HEVC.
+    fn from_idc(idc: u8) -> ChromaSubsampling {
+        match idc & 0b11 {
+            0 => ChromaSubsampling::Monochrome,
+            1 => ChromaSubsampling::Yuv420,
+            2 => ChromaSubsampling::Yuv422,
+            _ => ChromaSubsampling::Yuv444,
+        }
+    }
+}
+
+impl CodecConfig {
+    /// Parses the body of an `avcC`, `hvcC` or `av1C` box, given its type.
+    pub(crate) fn parse(box_type: &str, data: &[u8]) -> Option<CodecConfig> {
+        match box_type {
+            "avcC" => parse_avcc(data),
+            "hvcC" => parse_hvcc(data),
+            "av1C" => parse_av1c(data),
+            _ => None,
+        }
+    }
+}
+
+/// Parses an `AVCDecoderConfigurationRecord`. Chroma format and bit depth
+/// are only stored by records of the High profiles other than High, after
+/// the parameter sets.
+fn parse_avcc(data: &[u8]) -> Option<CodecConfig> {
+    let (profile, level) = (*data.get(1)?, *data.get(3)?);
+    let mut config = CodecConfig {
+        profile,
+        level,
+        chroma_subsampling: None,
+        bit_depth: None,
+    };
+    // Baseline, Main, Extended and High profiles are 4:2:0 8 bits only
+    if matches!(profile, 66 | 77 | 88 | 100) {
+        config.chroma_subsampling = Some(ChromaSubsampling::Yuv420);
+        config.bit_depth = Some(8);
+        return Some(config);
+    }
+
+    // Skip the SPS & PPS, each with a 16-bit length
+    let skip = |mut pos: usize, count: u8| {
+        for _ in 0..count {
+            let len = data.get(pos..pos + 2)?;
+            pos += 2 + u16::from_be_bytes([len[0], len[1]]) as usize;
+        }
+        Some(pos)
+    };
+    let extension = skip(6, *data.get(5)? & 0x1f)
+        .and_then(|pos| skip(pos + 1, *data.get(pos)?))
+        .and_then(|pos| data.get(pos..pos + 2));
+    if let Some(extension) = extension {
+        config.chroma_subsampling = Some(ChromaSubsampling::from_idc(extension[0]));
+        config.bit_depth = Some((extension[1] & 0b111) + 8);
+    }
+    Some(config)
+}
+
+/// Parses an `HEVCDecoderConfigurationRecord`.
+fn parse_hvcc(data: &[u8]) -> Option<CodecConfig> {
+    let data = data.get(..19)?;
+    Some(CodecConfig {
+        profile: data[1] & 0x1f,
+        level: data[12],
+        chroma_subsampling: Some(ChromaSubsampling::from_idc(data[16])),
+        bit_depth: Some((data[17] & 0b111) + 8),
+    })
+}
+
+/// Parses an `AV1CodecConfigurationRecord`.
+fn parse_av1c(data: &[u8]) -> Option<CodecConfig> {
+    let data = data.get(..3)?;
+    let flag = |bit: u8| data[2] & 1 << bit != 0;
+    let bit_depth = match (flag(6), flag(5)) {
+        (true, true) => 12,
+        (true, false) => 10,
+        _ => 8,
+    };
+    let chroma_subsampling = match (flag(4), flag(3), flag(2)) {
+        (true, ..) => ChromaSubsampling::Monochrome,
+        (_, true, true) => ChromaSubsampling::Yuv420,
+        (_, true, false) => ChromaSubsampling::Yuv422,
+        _ => ChromaSubsampling::Yuv444,
+    };
+    Some(CodecConfig {
+        profile: data[1] >> 5,
+        level: data[1] & 0x1f,
+        chroma_subsampling: Some(chroma_subsampling),
+        bit_depth: Some(bit_depth),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    // High 4:2:2 with the extension, after 1 SPS & 1 PPS
+    #[test_case("avcC", &[1, 122, 0, 40, 0xff, 0xe1, 0, 2, 0x67, 0x7a, 1, 0, 1, 0x68, 0xfe, 0xfa],
+        122, 40, Some(ChromaSubsampling::Yuv422), Some(10))]
+    // High 10 without the extension
+    #[test_case("avcC", &[1, 110, 0, 51, 0xff, 0xe0, 0], 110, 51, None, None)]
+    // High profile, level 5.1, 4:4:4 12 bits
+    #[test_case("av1C", &[0x81, 0x2d, 0x60, 0], 1, 13, Some(ChromaSubsampling::Yuv444), Some(12))]
+    // Main profile, level 4.0, 4:2:0 10 bits
+    #[test_case("av1C", &[0x81, 0x08, 0x4c, 0], 0, 8, Some(ChromaSubsampling::Yuv420), Some(10))]
+    fn codec_config(
+        box_type: &str,
+        data: &[u8],
+        profile: u8,
+        level: u8,
+        chroma_subsampling: Option<ChromaSubsampling>,
+        bit_depth: Option<u8>,
+    ) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let config = CodecConfig::parse(box_type, data).unwrap();
+        assert_eq!(
+            config,
+            CodecConfig {
+                profile,
+                level,
+                chroma_subsampling,
+                bit_depth,
+            }
+        );
+        assert_eq!(CodecConfig::parse("avcC", &data[..2]), None);
+    }
+}
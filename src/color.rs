@@ -0,0 +1,143 @@
+/// The color description of a video track, as returned in
+/// [`TrackMeta::color`](crate::TrackMeta::color): the `colr`, `mdcv` and
+/// `clli` boxes of QuickTime/MP4 files, or the `Colour` element of Matroska
+/// files.
+///
+/// Primaries, transfer characteristics and matrix coefficients are the code
+/// points of ITU-T H.273 (ISO/IEC 23091-2), e.g. 1 for BT.709, 9 for BT.2020
+/// primaries, 16 for the PQ (SMPTE ST 2084) transfer function and 18 for HLG.
+/// See [`ColorInfo::hdr_format`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorInfo {
+    pub primaries: Option<u16>,
+    pub transfer: Option<u16>,
+    pub matrix: Option<u16>,
+
+    /// Whether the samples use the full range of values (e.g. 0-255 for 8
+    /// bits), rather than the limited "video" range. Unknown for QuickTime
+    /// `nclc` descriptions.
+    pub full_range: Option<bool>,
+
+    pub mastering_display: Option<MasteringDisplay>,
+    pub content_light: Option<ContentLightLevel>,
+
+    /// Dolby Vision configuration (`dvcC`, `dvvC` or `dvwC` box, or the
+    /// equivalent Matroska block addition mapping).
+    pub dolby_vision: Option<DolbyVision>,
+
+    /// Whether HDR10+ dynamic metadata is signaled. Only Matroska files
+    /// declare it (as ITU-T T.35 block additions): in QuickTime/MP4 files it
+    /// is only carried in the SEI messages of the samples, which aren't read.
+    pub hdr10_plus: bool,
+}
+
+/// The color volume of the display on which the video was mastered (SMPTE
+/// ST 2086), e.g. of HDR10 videos.
+///
+/// Chromaticity coordinates are in units of 0.00002, and luminances in units
+/// of 0.0001 cd/m², as stored in `mdcv` boxes, e.g. `(34000, 16000)` for the
+/// red primary of BT.2020 and `10_000_000` for 1000 cd/m².
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MasteringDisplay {
+    /// `(x, y)` coordinates of the red, green and blue primaries.
+    pub red: (u16, u16),
+    pub green: (u16, u16),
+    pub blue: (u16, u16),
+    pub white_point: (u16, u16),
+
+    pub max_luminance: u32,
+    pub min_luminance: u32,
+}
+
+/// The content light level of a video (CTA-861.3), in cd/m².
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentLightLevel {
+    /// Maximum light level of a pixel (MaxCLL).
+    pub max_cll: u16,
+
+    /// Maximum average light level of a frame (MaxFALL).
+    pub max_fall: u16,
+}
+
+/// The Dolby Vision decoder configuration of a video track.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DolbyVision {
+    /// Profile, e.g. 5 (no SDR/HDR10 compatible base layer) or 8.
+    pub profile: u8,
+    pub level: u8,
+
+    /// Whether there is an enhancement layer.
+    pub enhancement_layer: bool,
+
+    /// Compatibility of the base layer (e.g. 1 for HDR10, 4 for HLG), which
+    /// is the ".1" of profile 8.1.
+    pub compatibility_id: u8,
+}
+
+impl DolbyVision {
+    /// Parses a `DOVIDecoderConfigurationRecord`, the body of `dvcC`, `dvvC`
+    /// and `dvwC` boxes.
+    pub(crate) fn parse(data: &[u8]) -> Option<DolbyVision> {
+        let bits = u32::from_be_bytes(data.get(2..6)?.try_into().ok()?);
+        Some(DolbyVision {
+            profile: (bits >> 25) as u8,
+            level: (bits >> 19) as u8 & 0x3f,
+            enhancement_layer: bits & 1 << 17 != 0,
+            compatibility_id: (bits >> 12) as u8 & 0xf,
+        })
+    }
+}
+
+/// The HDR format of a video, see [`ColorInfo::hdr_format`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HdrFormat {
+    /// PQ transfer function, with static metadata if any, e.g. HDR10 or
+    /// Dolby Vision profile 8.1 base layers.
+    Hdr10,
+
+    /// Hybrid log-gamma transfer function.
+    Hlg,
+
+    /// HDR10 with dynamic metadata.
+    Hdr10Plus,
+
+    /// Dolby Vision, whatever its base layer is compatible with.
+    DolbyVision,
+}
+
+impl ColorInfo {
+    /// Returns the HDR format of the video, e.g. for media servers to badge
+    /// it, or `None` for SDR (or unknown) videos.
+    ///
+    /// Dolby Vision takes precedence over HDR10+, which takes precedence over
+    /// the format given by the transfer characteristics.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/meta.mp4").unwrap();
+    /// let tracks = parse_metadata_iter(f).unwrap().tracks();
+    /// let color = tracks[0].color.as_ref().unwrap();
+    ///
+    /// assert_eq!(color.primaries, Some(1));
+    /// assert_eq!(color.hdr_format(), None);
+    /// ```
+    pub fn hdr_format(&self) -> Option<HdrFormat> {
+        if self.dolby_vision.is_some() {
+            return Some(HdrFormat::DolbyVision);
+        }
+        match self.transfer? {
+            16 if self.hdr10_plus => Some(HdrFormat::Hdr10Plus),
+            16 => Some(HdrFormat::Hdr10),
+            18 => Some(HdrFormat::Hlg),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use crate::{EntryValue, Exif, ExifTag, MetadataIter};
+
+/// A difference between the metadata of two files, as returned by [`diff`]
+/// (where `K` is an [`ExifTag`]) or [`diff_tracks`] (where `K` is the key of
+/// a QuickTime/MP4 entry).
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagChange<K = ExifTag> {
+    /// The tag is only in the new metadata.
+    Added(K, EntryValue),
+
+    /// The tag is only in the old metadata.
+    Removed(K, EntryValue),
+
+    /// The tag is in both, with different values.
+    Changed {
+        key: K,
+        old: EntryValue,
+        new: EntryValue,
+    },
+}
+
+impl<K> TagChange<K> {
+    /// Returns the tag or key which has changed.
+    pub fn key(&self) -> &K {
+        match self {
+            TagChange::Added(key, _) | TagChange::Removed(key, _) => key,
+            TagChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// Compares the Exif data of two images, e.g. to check that an editing or
+/// transcoding pipeline preserves the metadata. The changes are sorted by
+/// tag code.
+///
+/// The entries of [`Exif::entries`] are compared, i.e. the recognized tags
+/// of the main image and its sub-IFDs. GPS tags are compared one by one.
+/// Offsets (e.g. [`ExifTag::ExifOffset`]) are left out, since they change
+/// whenever a file is rewritten.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let mut edits = MetadataEdits::new();
+/// edits.set_exif(ExifTag::Artist, "Jane Doe");
+/// let mut output = Vec::new();
+/// let input = std::fs::read("./testdata/exif.jpg").unwrap();
+/// write_metadata(&input[..], &mut output, &edits).unwrap();
+///
+/// let old: Exif = parse_exif(&input[..], None).unwrap().unwrap().into();
+/// let new: Exif = parse_exif(&output[..], None).unwrap().unwrap().into();
+/// assert_eq!(
+///     diff(&old, &new),
+///     [TagChange::Added(ExifTag::Artist, "Jane Doe".into())]
+/// );
+/// ```
+pub fn diff(old: &Exif, new: &Exif) -> Vec<TagChange> {
+    const OFFSETS: [ExifTag; 5] = [
+        ExifTag::ExifOffset,
+        ExifTag::GPSInfo,
+        ExifTag::InteropOffset,
+        ExifTag::StripOffsets,
+        ExifTag::ThumbnailOffset,
+    ];
+    let entries = |exif: &Exif| {
+        exif.entries()
+            .into_iter()
+            .filter(|(tag, _)| !OFFSETS.contains(tag))
+            .collect()
+    };
+    changes(entries(old), entries(new))
+}
+
+/// Like [`diff`], for the metadata of two videos: compares all their
+/// entries, sorted by key. Entries which couldn't be parsed are left out.
+pub fn diff_tracks(old: &MetadataIter, new: &MetadataIter) -> Vec<TagChange<String>> {
+    fn entries(iter: &MetadataIter) -> BTreeMap<String, EntryValue> {
+        let mut map = BTreeMap::new();
+        for (k, v) in iter.clone() {
+            if let Ok(v) = v {
+                // Keep the first value of a repeated key, like `get` does
+                map.entry(k).or_insert(v);
+            }
+        }
+        map
+    }
+    changes(entries(old), entries(new))
+}
+
+fn changes<K: Ord>(
+    old: BTreeMap<K, EntryValue>,
+    mut new: BTreeMap<K, EntryValue>,
+) -> Vec<TagChange<K>> {
+    let mut changes = Vec::new();
+    for (key, old) in old {
+        match new.remove(&key) {
+            None => changes.push(TagChange::Removed(key, old)),
+            Some(new) if new != old => changes.push(TagChange::Changed { key, old, new }),
+            Some(_) => (),
+        }
+    }
+    changes.extend(new.into_iter().map(|(k, v)| TagChange::Added(k, v)));
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, parse_media, testkit::*, write_metadata, MetadataEdits};
+
+    #[test]
+    fn diff_exif() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample("exif.jpg").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits
+            .set_exif(ExifTag::Make, "Apple")
+            .set_exif(ExifTag::Artist, "Jane Doe")
+            .remove_exif(ExifTag::Model);
+        let mut output = Vec::new();
+        write_metadata(&input[..], &mut output, &edits).unwrap();
+
+        let old: Exif = parse_exif(&input[..], None).unwrap().unwrap().into();
+        let new: Exif = parse_exif(&output[..], None).unwrap().unwrap().into();
+        assert_eq!(
+            diff(&old, &new),
+            [
+                TagChange::Changed {
+                    key: ExifTag::Make,
+                    old: "vivo".into(),
+                    new: "Apple".into()
+                },
+                TagChange::Removed(ExifTag::Model, "vivo X90 Pro+".into()),
+                TagChange::Added(ExifTag::Artist, "Jane Doe".into()),
+            ]
+        );
+        assert_eq!(diff(&old, &old), []);
+    }
+
+    #[test]
+    fn diff_track() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample("meta.mov").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.set_quicktime("com.apple.quicktime.make", "Canon");
+        let mut output = Vec::new();
+        write_metadata(&input[..], &mut output, &edits).unwrap();
+
+        let track = |data: &[u8]| {
+            parse_media(std::io::Cursor::new(data))
+                .unwrap()
+                .unwrap()
+                .into_track()
+                .unwrap()
+        };
+        let (old, new) = (track(&input), track(&output));
+        assert_eq!(
+            diff_tracks(&old, &new),
+            [TagChange::Changed {
+                key: "com.apple.quicktime.make".to_owned(),
+                old: "Apple".into(),
+                new: "Canon".into()
+            }]
+        );
+        assert_eq!(diff_tracks(&old, &old), []);
+    }
+}
@@ -0,0 +1,80 @@
+//! Parsing of EBML, the binary format of Matroska (MKV) and WebM files: the
+//! elements of a buffer, and their values.
+
+#[cfg(feature = "std")]
+use crate::prelude::*;
+
+pub(crate) const EBML_ID: u32 = 0x1A45_DFA3;
+pub(crate) const DOC_TYPE_ID: u32 = 0x4282;
+
+/// Returns the `DocType` of the EBML header at the start of `input`, e.g.
+/// `b"webm"`, or `None` if `input` doesn't start with an EBML header.
+pub(crate) fn doc_type(input: &[u8]) -> Option<&[u8]> {
+    let (EBML_ID, header) = children(input).next()? else {
+        return None;
+    };
+    let (_, doc_type) = children(header).find(|x| x.0 == DOC_TYPE_ID)?;
+    let len = doc_type
+        .iter()
+        .position(|x| *x == 0)
+        .unwrap_or(doc_type.len());
+    Some(&doc_type[..len])
+}
+
+/// Iterates over the `(id, body)` of the elements in `data`, until the end
+/// of `data` or a malformed element.
+pub(crate) fn children(mut data: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    core::iter::from_fn(move || {
+        let (id, n) = vint(data, true)?;
+        let (size, m) = vint(&data[n..], false)?;
+        let body = data.get(n + m..)?;
+        let size = usize::try_from(size).ok()?.min(body.len());
+        let (body, rest) = body.split_at(size);
+        data = rest;
+        Some((id as u32, body))
+    })
+}
+
+/// Parses the variable-length integer at the start of `data`, along with
+/// its length. IDs keep their length marker, sizes don't.
+pub(crate) fn vint(data: &[u8], is_id: bool) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > if is_id { 4 } else { 8 } {
+        return None;
+    }
+    let bytes = data.get(..len)?;
+    let mut value = if is_id {
+        u64::from(first)
+    } else {
+        u64::from(first) & (0xff >> len)
+    };
+    for b in &bytes[1..] {
+        value = value << 8 | u64::from(*b);
+    }
+    Some((value, len))
+}
+
+/// Parses an unsigned integer element of up to 8 bytes.
+#[cfg(feature = "std")]
+pub(crate) fn uint(data: &[u8]) -> Option<u64> {
+    (data.len() <= 8).then(|| data.iter().fold(0, |n, b| n << 8 | u64::from(*b)))
+}
+
+/// Parses a float element of 4 or 8 bytes.
+#[cfg(feature = "std")]
+pub(crate) fn float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?).into()),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// Parses a string element, which may be padded with NULs.
+#[cfg(feature = "std")]
+pub(crate) fn text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches('\0')
+        .to_owned()
+}
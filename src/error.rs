@@ -1,9 +1,29 @@
-use std::{io, string::FromUtf8Error};
+use alloc::string::FromUtf8Error;
+use core::fmt::Display;
+#[cfg(feature = "std")]
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
 use thiserror::Error;
 
-type FallbackError = Box<dyn std::error::Error + Send + Sync>;
+#[cfg(feature = "std")]
+use crate::event::{EventSink, ParseEvent};
+use crate::{prelude::*, FileFormat, IfdKind};
 
+type FallbackError = Box<dyn core::error::Error + Send + Sync>;
+
+/// The errors of this crate.
+///
+/// Besides the generic [`Error::ParseFailed`], there are structured variants
+/// which tell apart files which aren't supported at all
+/// ([`Error::UnsupportedFormat`]), damaged files ([`Error::Truncated`],
+/// [`Error::CorruptBox`]) and files which exceed the limits of a
+/// [`MediaParser`](crate::MediaParser) ([`Error::LimitExceeded`]).
+///
+/// New variants may be added in minor versions.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum Error {
     /// `NotFound` has been deprecated, please don't check this error in your
     /// code (use "_" to ommit it if you are using match statement).
@@ -22,10 +42,267 @@ pub enum Error {
 
     #[error("parsed entry result has been taken")]
     EntryHasBeenTaken,
+
+    /// The file format is not supported (by this operation). `detected` is
+    /// the name of the detected format (e.g. `"QuickTime"`), or `None`
+    /// if the format isn't recognized at all.
+    #[error("unsupported file format{}", detected.as_ref().map(|x| format!(": {x}")).unwrap_or_default())]
+    UnsupportedFormat { detected: Option<String> },
+
+    /// The file ends before its metadata does. `needed` is the number of
+    /// missing bytes, if known.
+    #[error("file is truncated{}", needed.map(|x| format!("; {x} more bytes needed")).unwrap_or_default())]
+    Truncated { needed: Option<usize> },
+
+    /// A box (atom) of a HEIF/HEIC, QuickTime or MP4 file is malformed.
+    /// `path` is the path of the box (e.g. `"moov/meta/ilst"`), and `offset`
+    /// is the position in the file where the malformed data was found.
+    #[error("corrupt box {path} at offset {offset}")]
+    CorruptBox { path: String, offset: u64 },
+
+    /// A limit of the [`MediaParser`](crate::MediaParser) has been exceeded.
+    #[error("limit exceeded; {limit}")]
+    LimitExceeded { limit: Limit },
+
+    /// The reader can't seek, and skipping the next `remaining` bytes, at
+    /// `offset` in the file, would read and discard more than
+    /// [`MediaParserBuilder::max_discarded_bytes`](crate::MediaParserBuilder::max_discarded_bytes).
+    /// Parsing can be resumed from `offset + remaining` (e.g. with an HTTP
+    /// range request), see
+    /// [`MediaParser::resume_metadata_unseekable`](crate::MediaParser::resume_metadata_unseekable).
+    #[error("{remaining} bytes need to be skipped at offset {offset}")]
+    SkipRequired {
+        format: FileFormat,
+        offset: u64,
+        remaining: u64,
+    },
+
+    /// Parsing has been stopped, because the deadline of the
+    /// [`MediaParser`](crate::MediaParser) has passed (`timed_out`), or its
+    /// cancel check has returned `true`, see
+    /// [`MediaParserBuilder::deadline`](crate::MediaParserBuilder::deadline)
+    /// and
+    /// [`MediaParserBuilder::cancel_check`](crate::MediaParserBuilder::cancel_check).
+    #[error("{}", if *timed_out { "parsing timed out" } else { "parsing cancelled" })]
+    Cancelled { timed_out: bool },
+}
+
+/// The limit which has been exceeded, see [`Error::LimitExceeded`]. Each
+/// variant holds the configured maximum, see
+/// [`MediaParserBuilder`](crate::MediaParserBuilder).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// Maximum metadata size in bytes.
+    MetadataSize(usize),
+    /// Maximum number of entries.
+    Entries(usize),
+    /// Maximum string length in bytes.
+    StringLength(usize),
+    /// Maximum number of skipped bytes.
+    SkippedBytes(u64),
+    /// Maximum nesting depth of boxes or EBML elements.
+    NestingDepth(usize),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Limit::MetadataSize(max) => write!(f, "metadata is larger than {max} bytes"),
+            Limit::Entries(max) => write!(f, "more than {max} entries"),
+            Limit::StringLength(max) => write!(f, "string is longer than {max} bytes"),
+            Limit::SkippedBytes(max) => write!(f, "more than {max} bytes skipped"),
+            Limit::NestingDepth(max) => write!(f, "nested more than {max} levels deep"),
+        }
+    }
+}
+
+/// Something which has been skipped by a lenient parse (see
+/// [`MediaParser::parse_exif_lenient`](crate::MediaParser::parse_exif_lenient)
+/// and
+/// [`MediaParser::parse_metadata_lenient`](crate::MediaParser::parse_metadata_lenient)),
+/// or a recoverable anomaly which has been worked around (see
+/// [`MediaParser::take_warnings`](crate::MediaParser::take_warnings)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The file ends before its metadata does, only the available part has
+    /// been parsed. `needed` is the number of missing bytes, if known.
+    Truncated { needed: Option<usize> },
+
+    /// An IFD couldn't be parsed, so its entries are missing. `offset` is its
+    /// position in the Exif (TIFF) data.
+    SkippedIfd {
+        kind: IfdKind,
+        offset: usize,
+        reason: String,
+    },
+
+    /// An entry couldn't be parsed. `name` is the tag (e.g.
+    /// `"Make(0x010f)"`) or the QuickTime key.
+    SkippedEntry { name: String, reason: String },
+
+    /// A box (atom) is malformed, so the entries in it are missing, see
+    /// [`Error::CorruptBox`].
+    SkippedBox { path: String, offset: u64 },
+
+    /// The entry count of an IFD is larger than what fits in the data, only
+    /// the `available` entries have been parsed.
+    EntryCount {
+        kind: IfdKind,
+        declared: u16,
+        available: u16,
+    },
+
+    /// An offset points outside of the Exif (TIFF) data, and has been
+    /// ignored. `name` is the tag (e.g. `"SubIFDs(0x014a)"`), or `"next IFD"`
+    /// for the link to the following IFD.
+    OffsetOutOfRange { name: String, offset: usize },
+
+    /// An ASCII entry has non-ASCII (but valid UTF-8) characters, which
+    /// have been kept.
+    NonAsciiText { name: String },
+}
+
+impl Warning {
+    /// Converts the error of entry `name`, which is skipped.
+    #[cfg(feature = "std")]
+    pub(crate) fn skipped(name: String, e: Error) -> Warning {
+        match e {
+            CorruptBox { path, offset } => Warning::SkippedBox { path, offset },
+            e => Warning::SkippedEntry {
+                name,
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Warning::Truncated { needed } => {
+                write!(f, "file is truncated")?;
+                if let Some(n) = needed {
+                    write!(f, "; {n} more bytes needed")?;
+                }
+                Ok(())
+            }
+            Warning::SkippedIfd {
+                kind,
+                offset,
+                reason,
+            } => write!(f, "skipped {kind:?} at offset {offset}; {reason}"),
+            Warning::SkippedEntry { name, reason } => write!(f, "skipped {name}; {reason}"),
+            Warning::SkippedBox { path, offset } => {
+                write!(f, "skipped corrupt box {path} at offset {offset}")
+            }
+            Warning::EntryCount {
+                kind,
+                declared,
+                available,
+            } => write!(
+                f,
+                "{kind:?} has {declared} entries, but only {available} fit in the data"
+            ),
+            Warning::OffsetOutOfRange { name, offset } => {
+                write!(f, "{name} points to offset {offset}, which is out of range")
+            }
+            Warning::NonAsciiText { name } => write!(f, "{name} has non-ASCII characters"),
+        }
+    }
+}
+
+/// Collects the [`Warning`]s of a [`MediaParser`](crate::MediaParser),
+/// while its iterators are parsing entries lazily, and passes them to its
+/// event callback, if any. Clones share the same list; a default one doesn't
+/// collect anything.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Diagnostics(Option<Arc<DiagnosticsInner>>);
+
+/// Without `std`, there is no [`MediaParser`](crate::MediaParser) to take
+/// the warnings from, so they're only logged.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Diagnostics {}
+
+#[cfg(not(feature = "std"))]
+impl Diagnostics {
+    pub fn push(&self, warning: Warning) {
+        tracing::debug!(%warning, "Recoverable anomaly.");
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+struct DiagnosticsInner {
+    warnings: Mutex<Vec<Warning>>,
+    events: Option<EventSink>,
+}
+
+#[cfg(feature = "std")]
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics(Some(Default::default()))
+    }
+
+    /// Returns a new list, which passes the warnings to the same event
+    /// callback.
+    pub fn renew(&self) -> Diagnostics {
+        let events = self.0.as_ref().and_then(|x| x.events.clone());
+        Diagnostics(Some(Arc::new(DiagnosticsInner {
+            warnings: Default::default(),
+            events,
+        })))
+    }
+
+    /// Returns a new list, which passes the warnings to `events`.
+    pub fn with_events(events: EventSink) -> Diagnostics {
+        Diagnostics(Some(Arc::new(DiagnosticsInner {
+            warnings: Default::default(),
+            events: Some(events),
+        })))
+    }
+
+    /// Records `warning`, unless it has been recorded already (e.g. when
+    /// the same IFD is parsed by a cloned iterator again).
+    pub fn push(&self, warning: Warning) {
+        let Some(inner) = &self.0 else {
+            return;
+        };
+        let mut list = inner.warnings.lock().unwrap_or_else(|e| e.into_inner());
+        if list.contains(&warning) {
+            return;
+        }
+        list.push(warning.clone());
+        drop(list);
+        match &inner.events {
+            Some(events) => events.emit(ParseEvent::Fallback(warning)),
+            None => tracing::debug!(%warning, "Recoverable anomaly."),
+        }
+    }
+
+    /// Passes `event` to the event callback, if any.
+    pub fn emit(&self, event: impl FnOnce() -> ParseEvent) {
+        if let Some(events) = self.0.as_ref().and_then(|x| x.events.as_ref()) {
+            events.emit(event());
+        }
+    }
+
+    pub fn take(&self) -> Vec<Warning> {
+        match &self.0 {
+            Some(inner) => {
+                core::mem::take(&mut *inner.warnings.lock().unwrap_or_else(|e| e.into_inner()))
+            }
+            None => Vec::new(),
+        }
+    }
 }
 
 use Error::*;
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(value: io::Error) -> Self {
         ParseFailed(value.into())
@@ -58,7 +335,14 @@ impl From<nom::Err<nom::error::Error<&[u8]>>> for crate::Error {
 
 pub(crate) fn convert_parse_error(e: nom::Err<nom::error::Error<&[u8]>>, message: &str) -> Error {
     let s = match e {
-        nom::Err::Incomplete(_) => format!("{e}; {message}"),
+        nom::Err::Incomplete(needed) => {
+            return Truncated {
+                needed: match needed {
+                    nom::Needed::Unknown => None,
+                    nom::Needed::Size(n) => Some(n.get()),
+                },
+            }
+        }
         nom::Err::Error(e) => format!("{}; {message}", e.code.description()),
         nom::Err::Failure(e) => format!("{}; {message}", e.code.description()),
     };
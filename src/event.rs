@@ -0,0 +1,53 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::Warning;
+
+/// A step of the parsing of a file, passed to the callback set with
+/// [`MediaParserBuilder::on_event`](crate::MediaParserBuilder::on_event),
+/// e.g. to profile a batch job, or to find out why a file is slow to parse.
+///
+/// Offsets are positions in the file, in bytes.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent {
+    /// The parsing of a file has started.
+    Started,
+
+    /// A top-level box (atom) of a QuickTime/MP4 file has been found, e.g.
+    /// `ftyp`, `mdat` or `moov`. `size` includes the header.
+    BoxVisited {
+        box_type: String,
+        offset: u64,
+        size: u64,
+    },
+
+    /// A segment of a JPEG file which doesn't hold the Exif data has been
+    /// skipped, e.g. an ICC profile in `APP2` (marker `0xE2`). `size` is the
+    /// size of its payload.
+    SegmentSkipped { marker: u8, offset: u64, size: u64 },
+
+    /// A recoverable anomaly, which has been worked around, see
+    /// [`MediaParser::take_warnings`](crate::MediaParser::take_warnings).
+    Fallback(Warning),
+}
+
+/// The callback of [`ParseEvent`]s.
+#[derive(Clone)]
+pub(crate) struct EventSink(Arc<dyn Fn(&ParseEvent) + Send + Sync>);
+
+impl EventSink {
+    pub fn new<F: Fn(&ParseEvent) + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn emit(&self, event: ParseEvent) {
+        tracing::debug!(?event, "Parse event.");
+        (self.0)(&event);
+    }
+}
+
+impl Debug for EventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventSink")
+    }
+}
@@ -1,19 +1,50 @@
+pub use composite::CompositeTag;
 pub use exif_iter::{ExifIter, ParsedExifEntry};
 pub use gps::{GPSInfo, LatLng};
+pub use ifd::{DuplicatePolicy, IfdKind};
+pub use lens::LensInfo;
 pub use parser::Exif;
-pub use tags::ExifTag;
+#[cfg(feature = "stream")]
+pub use stream::ExifStream;
+pub use tag_info::{TagInfo, TagType};
+pub use tags::{ExifTag, Orientation};
+#[cfg(feature = "std")]
+pub use validate::Finding;
 
 pub(crate) mod ifd;
-pub(crate) use io::read_exif;
-pub(crate) use parser::{check_exif_header, input_to_exif, input_to_iter};
+#[cfg(feature = "std")]
+pub(crate) use exif_iter::ExifLimits;
+pub(crate) use io::exif_range;
+#[cfg(any(feature = "async", feature = "futures-io"))]
+pub(crate) use io::read_exif_async;
+#[cfg(feature = "std")]
+pub(crate) use io::{read_exif, read_exif_lenient};
+pub(crate) use parser::{check_exif_header, input_to_iter};
+#[cfg(feature = "std")]
+pub(crate) use parser::{input_to_exif, input_to_iter_with_diagnostics};
+#[cfg(feature = "kamadak-exif")]
+pub(crate) use tags::ExifTagCode;
 
+mod composite;
+mod display;
 mod exif_iter;
 mod gps;
 mod io;
+mod lens;
+#[cfg(feature = "lens-db")]
+mod lens_db;
 mod parser;
+#[cfg(feature = "stream")]
+mod stream;
+mod tag_info;
 mod tags;
+#[cfg(feature = "std")]
+mod validate;
 
-use crate::file::FileFormat;
+#[cfg(feature = "std")]
+use crate::MediaParser;
+use crate::{file::FileFormat, input::Input};
+#[cfg(feature = "std")]
 use std::io::Read;
 
 /// Read exif data from `reader`, and build an [`ExifIter`] for it.
@@ -35,11 +66,90 @@ use std::io::Read;
 /// - An `Ok<Some<ExifIter>>` if Exif data is found and parsed successfully.
 /// - An `Ok<None>` if Exif data is not found.
 /// - An `Err` if Exif data is found but parsing failed.
+#[cfg(feature = "std")]
 pub fn parse_exif<T: Read>(
     reader: T,
     format: Option<FileFormat>,
 ) -> crate::Result<Option<ExifIter<'static>>> {
-    read_exif(reader, format)?.map(input_to_iter).transpose()
+    MediaParser::default().parse_exif(reader, format)
+}
+
+/// Parse exif data from `data`, which holds the whole file in memory, e.g. an
+/// upload received by a server.
+///
+/// Unlike [`parse_exif`], `data` is not copied into an internal buffer, the
+/// returned [`ExifIter`] borrows it. See [`parse_exif_bytes`] (requires the
+/// `bytes` feature) for an owned, but still zero-copy, alternative.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let data = std::fs::read("./testdata/exif.jpg").unwrap();
+/// let exif: Exif = parse_exif_slice(&data, None).unwrap().unwrap().into();
+/// assert_eq!(exif.get(ExifTag::Model).unwrap().to_string(), "vivo X90 Pro+");
+/// ```
+pub fn parse_exif_slice(
+    data: &[u8],
+    format: Option<FileFormat>,
+) -> crate::Result<Option<ExifIter<'_>>> {
+    exif_range(data, format)?
+        .map(|range| input_to_iter(Input::from_slice_range(data, range)))
+        .transpose()
+}
+
+/// Parse exif data from `data`, which holds the whole file in memory.
+///
+/// `data` is not copied, the returned [`ExifIter`] keeps a reference to it,
+/// so it can outlive the caller's buffer (unlike [`parse_exif_slice`]).
+#[cfg(feature = "bytes")]
+pub fn parse_exif_bytes(
+    data: bytes::Bytes,
+    format: Option<FileFormat>,
+) -> crate::Result<Option<ExifIter<'static>>> {
+    exif_range(&data, format)?
+        .map(|range| input_to_iter(Input::from_bytes_range(data, range)))
+        .transpose()
+}
+
+/// Memory-map the file at `path`, and parse exif data directly from the
+/// mapping, which avoids read syscalls and copying the file into a buffer.
+/// Useful for indexing large photo libraries.
+///
+/// Only the pages which contain the exif data (and the headers before it)
+/// are actually read from disk. The returned [`ExifIter`] keeps the mapping
+/// alive. See also [`parse_media_mmap`](crate::parse_media_mmap).
+///
+/// # Safety
+///
+/// The file must not be modified (e.g. truncated), by this or another
+/// process, while the returned [`ExifIter`] is alive: the mapping would
+/// change under the parser, which is undefined behavior, and the process
+/// may be killed by `SIGBUS` on some platforms. See
+/// [`memmap2::Mmap`](https://docs.rs/memmap2/latest/memmap2/struct.Mmap.html).
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// // SAFETY: the test data isn't modified
+/// let iter = unsafe { parse_exif_mmap("./testdata/exif.heic", None) };
+/// let exif: Exif = iter.unwrap().unwrap().into();
+/// assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "Apple");
+/// ```
+#[cfg(feature = "mmap")]
+pub unsafe fn parse_exif_mmap<P: AsRef<std::path::Path>>(
+    path: P,
+    format: Option<FileFormat>,
+) -> crate::Result<Option<ExifIter<'static>>> {
+    let file = std::fs::File::open(path)?;
+    // SAFETY: guaranteed by the caller
+    let data = unsafe { memmap2::Mmap::map(&file)? };
+    exif_range(&data, format)?
+        .map(|range| input_to_iter(Input::from_mmap_range(data, range)))
+        .transpose()
 }
 
 #[cfg(feature = "async")]
@@ -51,16 +161,44 @@ pub async fn parse_exif_async<T: AsyncRead + Unpin>(
     reader: T,
     format: Option<FileFormat>,
 ) -> crate::Result<Option<ExifIter<'static>>> {
-    use io::read_exif_async;
-    read_exif_async(reader, format)
-        .await?
-        .map(input_to_iter)
-        .transpose()
+    MediaParser::default()
+        .parse_exif_async(reader, format)
+        .await
+}
+
+/// Like [`parse_exif_async`], but returns the entries as an [`ExifStream`].
+/// Requires the `stream` feature flag.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let f = tokio::fs::File::open("./testdata/exif.jpg").await.unwrap();
+/// let mut stream = parse_exif_stream(f, None);
+/// while let Some(entry) = stream.next().await {
+///     let entry = entry.unwrap();
+///     if entry.tag() == Some(ExifTag::Model) {
+///         assert_eq!(entry.take_value().unwrap().to_string(), "vivo X90 Pro+");
+///     }
+/// }
+/// # });
+/// ```
+#[cfg(all(feature = "async", feature = "stream"))]
+pub fn parse_exif_stream<T: AsyncRead + Unpin + Send + 'static>(
+    reader: T,
+    format: Option<FileFormat>,
+) -> ExifStream {
+    ExifStream::parsing(Box::pin(parse_exif_async(reader, format)))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::testkit::open_sample;
+    use crate::{
+        testkit::{open_sample, read_sample},
+        EntryValue,
+    };
     use test_case::test_case;
 
     use super::*;
@@ -90,4 +228,181 @@ mod tests {
             "Make(0x010f) => Apple, Model(0x0110) => iPhone 12 Pro"
         );
     }
+
+    fn entries(iter: ExifIter) -> Vec<(usize, u16, Option<EntryValue>)> {
+        iter.map(|x| (x.ifd_index(), x.tag_code(), x.take_value()))
+            .collect()
+    }
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn slice(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample(path).unwrap();
+        let iter = parse_exif_slice(&data, None).unwrap().unwrap();
+        let expected = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(entries(iter), entries(expected));
+    }
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn from_bytes(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = crate::read_exif_data(open_sample(path).unwrap())
+            .unwrap()
+            .unwrap();
+        let expected = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let iter = ExifIter::from_bytes(&data).unwrap();
+        assert_eq!(entries(iter.clone()), entries(expected.clone()));
+
+        for entry in iter.clone() {
+            assert_eq!(iter.raw_value_of(&entry).unwrap(), entry.raw_value());
+        }
+        // Entries of another iterator, or owned data
+        let entry = expected.clone().next().unwrap();
+        assert!(iter.raw_value_of(&entry).is_none());
+        assert!(expected.raw_value_of(&entry).is_none());
+
+        let mut prefixed = b"Exif\0\0".to_vec();
+        prefixed.extend_from_slice(&data);
+        let iter = ExifIter::from_bytes(&prefixed).unwrap();
+        assert_eq!(entries(iter), entries(expected));
+
+        ExifIter::from_bytes(&[]).unwrap_err();
+        ExifIter::from_bytes(&data[4..]).unwrap_err();
+    }
+
+    #[test]
+    fn gps_next_ifd() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // IFD0 with a GPSInfo entry, then an empty GPS IFD linking to itself
+        let data = b"II*\0\x08\0\0\0\x01\0\x25\x88\x04\0\x01\0\0\0\x1a\0\0\0\0\0\0\0\
+                     \0\0\x1a\0\0\0";
+        let iter = ExifIter::from_bytes(data).unwrap();
+        assert!(iter.parse_gps_info().unwrap().is_none());
+        assert_eq!(iter.count(), 1);
+    }
+
+    #[test]
+    fn ifd_chain_loop() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // IFD0 and IFD1 with an ImageWidth entry each, IFD1 linking to itself
+        let data = b"II*\0\x08\0\0\0\x01\0\0\x01\x03\0\x01\0\0\0\x01\0\0\0\x1a\0\0\0\
+                     \x01\0\0\x01\x03\0\x01\0\0\0\x02\0\0\0\x1a\0\0\0";
+        let iter = ExifIter::from_bytes(data).unwrap();
+        assert_eq!(
+            entries(iter.clone()),
+            [
+                (0, 0x0100, Some(EntryValue::U16(1))),
+                (1, 0x0100, Some(EntryValue::U16(2))),
+            ]
+        );
+        let exif: Exif = iter.into();
+        assert_eq!(
+            exif.get_all(ExifTag::ImageWidth),
+            [(0, &EntryValue::U16(1)), (1, &EntryValue::U16(2))]
+        );
+    }
+
+    #[test_case("no-exif.jpg")]
+    fn slice_none(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample(path).unwrap();
+        assert!(parse_exif_slice(&data, None).unwrap().is_none());
+        parse_exif_slice(&data, Some(FileFormat::Heif)).unwrap_err();
+        parse_exif_slice(&[], None).unwrap_err();
+
+        // Truncated in the middle of the Exif segment
+        let data = read_sample("exif.jpg").unwrap();
+        parse_exif_slice(&data[..0x100], None).unwrap_err();
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn bytes_input(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = bytes::Bytes::from(read_sample(path).unwrap());
+        let iter = parse_exif_bytes(data.clone(), None).unwrap().unwrap();
+        let expected = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        drop(data);
+        assert_eq!(entries(iter), entries(expected));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn mmap(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // SAFETY: the test data isn't modified
+        let iter = unsafe { parse_exif_mmap(format!("./testdata/{path}"), None) }
+            .unwrap()
+            .unwrap();
+        let expected = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entries(iter), entries(expected));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_error() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // SAFETY: the test data isn't modified
+        unsafe {
+            assert!(parse_exif_mmap("./testdata/no-exif.jpg", None)
+                .unwrap()
+                .is_none());
+            parse_exif_mmap("./testdata/missing.jpg", None).unwrap_err();
+            parse_exif_mmap("./testdata/meta.mov", None).unwrap_err();
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_exif(open_sample("exif.jpg").unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let entry = iter
+            .clone()
+            .find(|x| x.tag() == Some(ExifTag::Make))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            r#"{"ifd":0,"tag":"Make(0x010f)","value":"vivo","error":null}"#
+        );
+        // The value is not taken
+        assert_eq!(entry.take_value(), Some("vivo".into()));
+
+        let exif: Exif = iter.into();
+        let json = serde_json::to_value(&exif).unwrap();
+        assert_eq!(json["ifds"][0]["Make(0x010f)"], "vivo");
+        assert_eq!(
+            json["ifds"][0]["DateTimeOriginal(0x9003)"],
+            "2023-07-09T20:36:33+08:00"
+        );
+        assert_eq!(json["gps_info"]["latitude_ref"], "N");
+        assert_eq!(
+            json["gps_info"]["latitude"],
+            serde_json::json!([[22, 1], [31, 1], [5208, 100]])
+        );
+    }
 }
@@ -0,0 +1,230 @@
+//! Composite tags, which are computed from several Exif tags (like the
+//! composite tags of exiftool).
+
+use core::fmt;
+
+use crate::{prelude::*, EntryValue, ExifTag};
+
+use super::Exif;
+
+/// Diagonal of a 35 mm film frame (36 x 24 mm), in millimeters.
+const FULL_FRAME_DIAGONAL: f64 = 43.266_615;
+
+/// A tag computed from several Exif tags, see [`Exif::composites`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CompositeTag {
+    /// Image dimensions as text, e.g. `"4032x3024"`.
+    ImageSize,
+
+    /// Image size in megapixels.
+    Megapixels,
+
+    /// Ratio of the diagonal of a 35 mm film frame to the diagonal of the
+    /// sensor (crop factor).
+    ScaleFactor35efl,
+
+    /// 35 mm equivalent focal length, in millimeters.
+    FocalLength35efl,
+
+    /// Horizontal field of view, in degrees, computed from the 35 mm
+    /// equivalent focal length.
+    FieldOfView,
+
+    /// Light value (exposure value normalized to ISO 100), computed from
+    /// the aperture, exposure time and ISO speed.
+    LightValue,
+
+    /// GPS position in decimal degrees, e.g. `"43.29013, 84.22713"`.
+    GPSPosition,
+}
+
+impl CompositeTag {
+    /// All composite tags, in the order [`Exif::composites`] returns them.
+    pub const ALL: [CompositeTag; 7] = [
+        CompositeTag::ImageSize,
+        CompositeTag::Megapixels,
+        CompositeTag::ScaleFactor35efl,
+        CompositeTag::FocalLength35efl,
+        CompositeTag::FieldOfView,
+        CompositeTag::LightValue,
+        CompositeTag::GPSPosition,
+    ];
+}
+
+impl fmt::Display for CompositeTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl Exif {
+    /// Computes the composite tags which the Exif data has enough
+    /// information for, see [`CompositeTag`].
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.heic").unwrap();
+    /// let exif: Exif = parse_exif(f, None).unwrap().unwrap().into();
+    ///
+    /// let composites = exif.composites();
+    /// assert_eq!(composites[0], (CompositeTag::ImageSize, "4032x3024".into()));
+    /// assert_eq!(
+    ///     exif.composite(CompositeTag::FocalLength35efl),
+    ///     Some(EntryValue::F64(26.0))
+    /// );
+    /// ```
+    pub fn composites(&self) -> Vec<(CompositeTag, EntryValue)> {
+        CompositeTag::ALL
+            .into_iter()
+            .filter_map(|tag| self.composite(tag).map(|v| (tag, v)))
+            .collect()
+    }
+
+    /// Computes the composite `tag`, `None` if the tags it's computed from
+    /// are missing or invalid.
+    pub fn composite(&self, tag: CompositeTag) -> Option<EntryValue> {
+        match tag {
+            CompositeTag::ImageSize => {
+                let (width, height) = self.image_size()?;
+                Some(format!("{width}x{height}").into())
+            }
+            CompositeTag::Megapixels => {
+                let (width, height) = self.image_size()?;
+                Some((width as f64 * height as f64 / 1e6).into())
+            }
+            CompositeTag::ScaleFactor35efl => self.scale_factor_35efl().map(Into::into),
+            CompositeTag::FocalLength35efl => self.focal_length_35efl().map(Into::into),
+            CompositeTag::FieldOfView => {
+                let f = self.focal_length_35efl()?;
+                Some((2.0 * (18.0 / f).atan()).to_degrees().into())
+            }
+            CompositeTag::LightValue => {
+                let aperture = self.positive_f64(ExifTag::FNumber)?;
+                let time = self.positive_f64(ExifTag::ExposureTime)?;
+                let iso = self.positive_f64(ExifTag::ISOSpeedRatings)?;
+                let lv = 2.0 * aperture.log2() - time.log2() - (iso / 100.0).log2();
+                Some(lv.into())
+            }
+            CompositeTag::GPSPosition => {
+                let gps = self.get_gps_info().ok()??;
+                let (latitude, longitude, _) = gps.to_decimal();
+                Some(format!("{latitude:.5}, {longitude:.5}").into())
+            }
+        }
+    }
+
+    /// Returns the dimensions of the main image.
+    pub(crate) fn image_size(&self) -> Option<(u32, u32)> {
+        [
+            (ExifTag::ExifImageWidth, ExifTag::ExifImageHeight),
+            (ExifTag::ImageWidth, ExifTag::ImageHeight),
+        ]
+        .into_iter()
+        .find_map(|(w, h)| {
+            let size = (self.get_u32(w)?, self.get_u32(h)?);
+            (size.0 > 0 && size.1 > 0).then_some(size)
+        })
+    }
+
+    fn scale_factor_35efl(&self) -> Option<f64> {
+        let focal_length = self.positive_f64(ExifTag::FocalLength);
+        let focal_length_35 = self.positive_f64(ExifTag::FocalLengthIn35mmFilm);
+        if let (Some(f), Some(f35)) = (focal_length, focal_length_35) {
+            return Some(f35 / f);
+        }
+
+        // Compute the sensor size from the focal plane resolution
+        let unit = match self.get_u32(ExifTag::FocalPlaneResolutionUnit).unwrap_or(2) {
+            2 => 25.4,
+            3 => 10.0,
+            4 => 1.0,
+            5 => 0.001,
+            _ => return None,
+        };
+        let (width, height) = self.image_size()?;
+        let x_res = self.positive_f64(ExifTag::FocalPlaneXResolution)?;
+        let y_res = self.positive_f64(ExifTag::FocalPlaneYResolution)?;
+        let sensor_width = width as f64 / x_res * unit;
+        let sensor_height = height as f64 / y_res * unit;
+        Some(FULL_FRAME_DIAGONAL / sensor_width.hypot(sensor_height))
+    }
+
+    fn focal_length_35efl(&self) -> Option<f64> {
+        if let Some(f35) = self.positive_f64(ExifTag::FocalLengthIn35mmFilm) {
+            return Some(f35);
+        }
+        Some(self.positive_f64(ExifTag::FocalLength)? * self.scale_factor_35efl()?)
+    }
+
+    fn positive_f64(&self, tag: ExifTag) -> Option<f64> {
+        self.get_f64(tag).filter(|x| x.is_finite() && *x > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exif::input_to_exif, parse_exif, testkit::open_sample, ExifBuilder};
+
+    fn assert_close(value: Option<EntryValue>, expect: f64) {
+        let value = value.and_then(|x| x.to_f64()).unwrap();
+        assert!((value - expect).abs() < 0.01, "{value} != {expect}");
+    }
+
+    #[test]
+    fn composites() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif: Exif = parse_exif(open_sample("exif.jpg").unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(
+            exif.composite(CompositeTag::ImageSize),
+            Some("3072x4096".into())
+        );
+        assert_close(exif.composite(CompositeTag::Megapixels), 12.58);
+        assert_close(exif.composite(CompositeTag::ScaleFactor35efl), 2.65);
+        assert_close(exif.composite(CompositeTag::FocalLength35efl), 23.0);
+        assert_close(exif.composite(CompositeTag::FieldOfView), 76.09);
+        // f/1.75, 1/100 s, ISO 454
+        assert_close(exif.composite(CompositeTag::LightValue), 6.07);
+        assert_eq!(
+            exif.composite(CompositeTag::GPSPosition),
+            Some("22.53113, 114.02148".into())
+        );
+        assert_eq!(
+            exif.composites()
+                .into_iter()
+                .map(|x| x.0)
+                .collect::<Vec<_>>(),
+            CompositeTag::ALL
+        );
+    }
+
+    #[test]
+    fn composites_focal_plane() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // A 36 x 24 mm sensor, without FocalLengthIn35mmFilm
+        let mut builder = ExifBuilder::new();
+        builder
+            .set(ExifTag::ExifImageWidth, 6000u32)
+            .set(ExifTag::ExifImageHeight, 4000u32)
+            .set(ExifTag::FocalPlaneResolutionUnit, 4u16)
+            .set(ExifTag::FocalPlaneXResolution, (6000u32, 36u32))
+            .set(ExifTag::FocalPlaneYResolution, (4000u32, 24u32))
+            .set(ExifTag::FocalLength, (50u32, 1u32));
+        let exif = input_to_exif(builder.build().unwrap()).unwrap();
+
+        assert_close(exif.composite(CompositeTag::ScaleFactor35efl), 1.0);
+        assert_close(exif.composite(CompositeTag::FocalLength35efl), 50.0);
+        assert_close(exif.composite(CompositeTag::FieldOfView), 39.6);
+        assert_eq!(exif.composite(CompositeTag::LightValue), None);
+        assert_eq!(exif.composite(CompositeTag::GPSPosition), None);
+    }
+}
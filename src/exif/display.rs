@@ -0,0 +1,183 @@
+//! Human-readable rendering of entry values, e.g. `1/250` for exposure times
+//! or `f/2.8` for apertures.
+
+use crate::{prelude::*, EntryValue, ExifTag};
+
+impl ExifTag {
+    /// Renders a `value` of this tag the way photographers expect, e.g.:
+    ///
+    /// - `ExposureTime`: `1/250`, or `2` for long exposures (in seconds)
+    /// - `FNumber`, `ApertureValue`, `MaxApertureValue`: `f/2.8`
+    /// - `FocalLength`, `FocalLengthIn35mmFilm`: `26 mm`
+    /// - `Flash`: the bitfield decoded, e.g. `Auto, Fired, Red-eye reduction`
+    /// - `MeteringMode`, `ExposureProgram`: the name of the mode, e.g.
+    ///   `Center-weighted average`
+    ///
+    /// Values of other tags, or values which don't have the expected type,
+    /// are rendered with [`EntryValue`]'s `Display` implementation.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let value = EntryValue::URational((1, 250).into());
+    /// assert_eq!(ExifTag::ExposureTime.display_value(&value), "1/250");
+    ///
+    /// let value = EntryValue::URational((28, 10).into());
+    /// assert_eq!(ExifTag::FNumber.display_value(&value), "f/2.8");
+    ///
+    /// let value = EntryValue::U16(0x19);
+    /// assert_eq!(ExifTag::Flash.display_value(&value), "Auto, Fired");
+    /// ```
+    pub fn display_value(self, value: &EntryValue) -> String {
+        self.try_display_value(value)
+            .unwrap_or_else(|| value.to_string())
+    }
+
+    fn try_display_value(self, value: &EntryValue) -> Option<String> {
+        match self {
+            ExifTag::ExposureTime => exposure_time(value.to_f64()?),
+            ExifTag::FNumber => Some(format!("f/{:.1}", value.to_f64()?)),
+            ExifTag::ApertureValue | ExifTag::MaxApertureValue => {
+                // APEX value
+                let f = 2_f64.powf(value.to_f64()? / 2.0);
+                f.is_finite().then(|| format!("f/{f:.1}"))
+            }
+            ExifTag::FocalLength | ExifTag::FocalLengthIn35mmFilm => {
+                Some(format!("{} mm", trim_float(value.to_f64()?)))
+            }
+            ExifTag::Flash => Some(flash(value.to_u32()?)),
+            ExifTag::MeteringMode => {
+                let name = match value.to_u32()? {
+                    0 => "Unknown",
+                    1 => "Average",
+                    2 => "Center-weighted average",
+                    3 => "Spot",
+                    4 => "Multi-spot",
+                    5 => "Multi-segment",
+                    6 => "Partial",
+                    255 => "Other",
+                    v => return Some(format!("Unknown ({v})")),
+                };
+                Some(name.to_owned())
+            }
+            ExifTag::ExposureProgram => {
+                let name = match value.to_u32()? {
+                    0 => "Not defined",
+                    1 => "Manual",
+                    2 => "Program AE",
+                    3 => "Aperture-priority AE",
+                    4 => "Shutter speed priority AE",
+                    5 => "Creative (Slow speed)",
+                    6 => "Action (High speed)",
+                    7 => "Portrait",
+                    8 => "Landscape",
+                    v => return Some(format!("Unknown ({v})")),
+                };
+                Some(name.to_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Renders an exposure time in seconds: fractions of a second as `1/n`.
+fn exposure_time(secs: f64) -> Option<String> {
+    if !secs.is_finite() || secs <= 0.0 {
+        return None;
+    }
+    if secs < 0.25 {
+        Some(format!("1/{}", (1.0 / secs).round()))
+    } else {
+        Some(trim_float((secs * 10.0).round() / 10.0))
+    }
+}
+
+/// Renders `v` with at most one decimal, without a trailing `.0`.
+fn trim_float(v: f64) -> String {
+    let s = format!("{v:.1}");
+    match s.strip_suffix(".0") {
+        Some(s) => s.to_owned(),
+        None => s,
+    }
+}
+
+/// Decodes the `Flash` bitfield.
+fn flash(v: u32) -> String {
+    if v & 0x20 != 0 {
+        return "No flash function".to_owned();
+    }
+
+    let mut parts = Vec::new();
+    match (v >> 3) & 0x3 {
+        1 => parts.push("On"),
+        2 => parts.push("Off"),
+        3 => parts.push("Auto"),
+        _ => (),
+    }
+    parts.push(if v & 0x1 != 0 {
+        "Fired"
+    } else {
+        "Did not fire"
+    });
+    match (v >> 1) & 0x3 {
+        2 => parts.push("Return not detected"),
+        3 => parts.push("Return detected"),
+        _ => (),
+    }
+    if v & 0x40 != 0 {
+        parts.push("Red-eye reduction");
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, testkit::open_sample};
+    use test_case::test_case;
+
+    #[test_case(ExifTag::ExposureTime, (1, 250).into(), "1/250")]
+    #[test_case(ExifTag::ExposureTime, (10, 2500).into(), "1/250")]
+    #[test_case(ExifTag::ExposureTime, (1, 2).into(), "0.5")]
+    #[test_case(ExifTag::ExposureTime, (30, 1).into(), "30")]
+    #[test_case(ExifTag::ExposureTime, (1, 0).into(), "1/0 (inf)")]
+    #[test_case(ExifTag::FNumber, (28, 10).into(), "f/2.8")]
+    #[test_case(ExifTag::FNumber, (8, 1).into(), "f/8.0")]
+    #[test_case(ExifTag::ApertureValue, (297, 100).into(), "f/2.8")]
+    #[test_case(ExifTag::FocalLength, (26, 1).into(), "26 mm")]
+    #[test_case(ExifTag::FocalLength, (4200, 1000).into(), "4.2 mm")]
+    #[test_case(ExifTag::FocalLengthIn35mmFilm, 26u16.into(), "26 mm")]
+    #[test_case(ExifTag::Flash, 0u16.into(), "Did not fire")]
+    #[test_case(ExifTag::Flash, 0x10u16.into(), "Off, Did not fire")]
+    #[test_case(ExifTag::Flash, 0x0fu16.into(), "On, Fired, Return detected")]
+    #[test_case(ExifTag::Flash, 0x59u16.into(), "Auto, Fired, Red-eye reduction")]
+    #[test_case(ExifTag::Flash, 0x20u16.into(), "No flash function")]
+    #[test_case(ExifTag::MeteringMode, 2u16.into(), "Center-weighted average")]
+    #[test_case(ExifTag::MeteringMode, 9u16.into(), "Unknown (9)")]
+    #[test_case(ExifTag::ExposureProgram, 3u16.into(), "Aperture-priority AE")]
+    #[test_case(ExifTag::Make, "vivo".into(), "vivo")]
+    #[test_case(ExifTag::Flash, "on".into(), "on")]
+    fn display_value(tag: ExifTag, value: EntryValue, expect: &str) {
+        assert_eq!(tag.display_value(&value), expect);
+    }
+
+    #[test]
+    fn entry_display_value() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_exif(open_sample("exif.jpg").unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let values = iter
+            .filter(|x| {
+                x.tag().is_some_and(|t| {
+                    [ExifTag::ExposureTime, ExifTag::FNumber, ExifTag::Flash].contains(&t)
+                })
+            })
+            .map(|x| x.display_value().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["f/1.8", "1/100", "Off, Did not fire"]);
+    }
+}
@@ -1,4 +1,9 @@
-use std::{cell::RefCell, fmt::Debug, sync::Arc};
+use alloc::{collections::BTreeSet, sync::Arc};
+#[cfg(not(feature = "std"))]
+use core::cell::{RefCell as Mutex, RefMut as MutexGuard};
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
 
 use nom::{
     number::{complete, Endianness},
@@ -6,13 +11,15 @@ use nom::{
 };
 
 use crate::{
-    input::{AssociatedInput, Input},
+    error::Diagnostics,
+    input::{AssociatedInput, Input, InputData},
+    prelude::*,
     slice::SliceChecked,
     values::{DataFormat, EntryData, EntryError, IRational, URational},
-    EntryValue, ExifTag,
+    EntryValue, ExifTag, Limit, Warning,
 };
 
-use super::{tags::ExifTagCode, GPSInfo};
+use super::{tags::ExifTagCode, GPSInfo, IfdKind};
 
 /// An iterator version of [`Exif`](crate::Exif). Use [`ParsedExifEntry`] as
 /// iterator items.
@@ -22,7 +29,9 @@ use super::{tags::ExifTagCode, GPSInfo};
 ///
 /// Note that a new cloned `ExifIter` which is cloned by `clone()` method
 /// (*NOT* `cloned()`) will always be reset to the first entry, no matter what
-/// the original iterator state was.
+/// the original iterator state was. Use [`ExifIter::rewind`] to iterate over
+/// the same `ExifIter` again, or [`ExifIter::nth_ifd`] to iterate over a
+/// single IFD (e.g. only the thumbnail's one).
 #[derive(Debug)]
 pub struct ExifIter<'a> {
     // Use Arc to make sure we won't clone the owned data.
@@ -30,24 +39,80 @@ pub struct ExifIter<'a> {
     endian: Endianness,
     tz: Option<String>,
     ifd0: Option<ImageFileDirectoryIter>,
+    limits: ExifLimits,
+    /// Position of the input buffer in the file, when only a part of the
+    /// file has been read into it.
+    file_offset: usize,
+    /// Whether the IFDs following `ifd0` (IFD1, etc.) are iterated over.
+    follow_next: bool,
 
     // Iterating status
     ifds: Vec<ImageFileDirectoryIter>,
+    entries: usize,
+    /// Offsets of the IFDs of the main IFD chain (IFD0, IFD1, etc.) visited
+    /// so far, to stop at a loop in the chain.
+    chain: BTreeSet<usize>,
+    /// IFDs which couldn't be parsed, see [`crate::Warning::SkippedIfd`].
+    skipped_ifds: Vec<Warning>,
+    pub(crate) diagnostics: Diagnostics,
+}
+
+/// Limits of an [`ExifIter`], see [`MediaParserBuilder`](crate::MediaParserBuilder).
+#[derive(Debug, Clone)]
+pub(crate) struct ExifLimits {
+    pub max_ifd_depth: usize,
+    pub max_entries: usize,
+    pub max_string_length: usize,
+    /// Checked before each entry, since decoding doesn't read anything.
+    #[cfg(feature = "std")]
+    pub cancellation: crate::media::Cancellation,
+}
+
+impl Default for ExifLimits {
+    fn default() -> Self {
+        Self {
+            max_ifd_depth: MAX_IFD_DEPTH,
+            max_entries: usize::MAX,
+            max_string_length: usize::MAX,
+            #[cfg(feature = "std")]
+            cancellation: Default::default(),
+        }
+    }
+}
+
+impl ExifLimits {
+    /// Returns an error if the entry count exceeds the maximum, or if
+    /// parsing has been cancelled.
+    fn check(&self, entries: usize) -> Option<EntryError> {
+        if entries > self.max_entries {
+            return Some(EntryError::LimitExceeded(Limit::Entries(self.max_entries)));
+        }
+        #[cfg(feature = "std")]
+        if let Some(timed_out) = self.cancellation.cancelled() {
+            return Some(EntryError::Cancelled { timed_out });
+        }
+        None
+    }
 }
 
 impl Clone for ExifIter<'_> {
     fn clone(&self) -> Self {
-        let mut ifds = Vec::new();
-        if let Some(ref ifd0) = self.ifd0 {
-            ifds.push(ifd0.clone());
-        }
-        Self {
+        let mut iter = Self {
             input: self.input.clone(),
             endian: self.endian,
             tz: self.tz.clone(),
             ifd0: self.ifd0.clone(),
-            ifds,
-        }
+            limits: self.limits.clone(),
+            file_offset: self.file_offset,
+            follow_next: self.follow_next,
+            ifds: Vec::new(),
+            entries: 0,
+            chain: BTreeSet::new(),
+            skipped_ifds: Vec::new(),
+            diagnostics: self.diagnostics.clone(),
+        };
+        iter.rewind();
+        iter
     }
 }
 
@@ -58,17 +123,167 @@ impl<'a> ExifIter<'a> {
         tz: Option<String>,
         ifd0: Option<ImageFileDirectoryIter>,
     ) -> ExifIter<'a> {
-        let mut ifds = Vec::new();
-        if let Some(ref ifd0) = ifd0 {
-            ifds.push(ifd0.clone());
-        }
-        ExifIter {
+        let mut iter = ExifIter {
             input: Arc::new(input.into()),
             endian,
             tz,
             ifd0,
-            ifds,
+            limits: ExifLimits::default(),
+            file_offset: 0,
+            follow_next: true,
+            ifds: Vec::new(),
+            entries: 0,
+            chain: BTreeSet::new(),
+            skipped_ifds: Vec::new(),
+            diagnostics: Diagnostics::default(),
+        };
+        iter.rewind();
+        iter
+    }
+
+    /// Parses the Exif (TIFF) data in `data`, e.g. as returned by
+    /// [`read_exif_data`](crate::read_exif_data) or stored in a database.
+    /// A leading `Exif\0\0` identifier, as in JPEG APP1 segments, is
+    /// skipped.
+    ///
+    /// `data` is neither copied nor kept in an internal buffer: the iterator
+    /// borrows it for `'a`, and so do the slices returned by
+    /// [`Self::raw_value_of`]. Entry offsets are relative to `data`.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let data = read_exif_data(f).unwrap().unwrap();
+    ///
+    /// let mut iter = ExifIter::from_bytes(&data).unwrap();
+    /// let model = iter.find(|x| x.tag() == Some(ExifTag::Model)).unwrap();
+    /// assert_eq!(model.take_value().unwrap().to_string(), "vivo X90 Pro+");
+    /// ```
+    pub fn from_bytes(data: &'a [u8]) -> crate::Result<ExifIter<'a>> {
+        let start = if data.starts_with(EXIF_IDENT) {
+            EXIF_IDENT.len()
+        } else {
+            0
+        };
+        super::input_to_iter(Input::from_slice_range(data, start..data.len()))
+    }
+
+    /// Returns the bytes of the value of `entry` (see
+    /// [`ParsedExifEntry::raw_value`]), borrowed from the caller's buffer
+    /// rather than from `entry`, so they live as long as the buffer.
+    ///
+    /// Returns `None` if the iterator doesn't borrow its data (i.e. it
+    /// hasn't been created by [`Self::from_bytes`] or
+    /// [`parse_exif_slice`](crate::parse_exif_slice)), or `entry` doesn't
+    /// come from it.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let data = std::fs::read("./testdata/exif.jpg").unwrap();
+    /// let iter = parse_exif_slice(&data, None).unwrap().unwrap();
+    ///
+    /// let model = iter.clone().find(|x| x.tag() == Some(ExifTag::Model)).unwrap();
+    /// let raw: &[u8] = iter.raw_value_of(&model).unwrap();
+    /// drop(iter);
+    /// assert_eq!(raw, b"vivo X90 Pro+\0");
+    /// ```
+    pub fn raw_value_of(&self, entry: &ParsedExifEntry) -> Option<&'a [u8]> {
+        let InputData::Borrowed(data) = self.input.data else {
+            return None;
+        };
+        let start = entry.raw.value_offset.checked_sub(self.file_offset)?;
+        let value = data.get(start..start + entry.raw.bytes.len())?;
+        (value == entry.raw.bytes).then_some(value)
+    }
+
+    /// Sets the position of the input buffer in the file, so that the
+    /// offsets of the entries are file offsets.
+    #[cfg(feature = "std")]
+    pub(crate) fn set_file_offset(&mut self, offset: usize) {
+        self.file_offset = offset;
+    }
+
+    pub(crate) fn set_limits(&mut self, limits: ExifLimits) {
+        for ifd in self.ifd0.iter_mut().chain(self.ifds.iter_mut()) {
+            ifd.max_string_length = limits.max_string_length;
         }
+        self.limits = limits;
+    }
+
+    /// Resets the iterator to the first entry, so the entries can be
+    /// iterated over again without re-parsing the file. Entry values are
+    /// parsed again though, since they're parsed lazily.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut iter = parse_exif(f, None).unwrap().unwrap();
+    /// let count = iter.by_ref().count();
+    ///
+    /// iter.rewind();
+    /// assert_eq!(iter.count(), count);
+    /// ```
+    pub fn rewind(&mut self) {
+        self.ifds = self.ifd0.iter().cloned().collect();
+        self.entries = 0;
+        self.chain = self.ifd0.iter().map(|x| x.offset()).collect();
+        self.skipped_ifds.clear();
+    }
+
+    /// Returns an iterator over the `n`-th IFD of the main IFD chain (0:
+    /// IFD0, the main image; 1: IFD1, usually the thumbnail) along with its
+    /// sub-IFDs (e.g. the Exif and GPS IFDs of IFD0), but not the IFDs
+    /// following it. The data is shared with this iterator, whose state
+    /// isn't affected.
+    ///
+    /// Returns `None` if there isn't such an IFD, or it can't be parsed.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let iter = parse_exif(f, None).unwrap().unwrap();
+    ///
+    /// let thumbnail = iter.nth_ifd(1).unwrap();
+    /// assert!(thumbnail
+    ///     .filter_map(|x| x.tag())
+    ///     .any(|x| x == ExifTag::ThumbnailOffset));
+    /// assert!(iter.nth_ifd(2).is_none());
+    /// ```
+    pub fn nth_ifd(&self, n: usize) -> Option<ExifIter<'a>> {
+        let mut ifd = self.ifd0.clone()?;
+        for idx in 1..=n {
+            let offset = ifd.next_ifd_offset()?;
+            ifd = self.new_ifd(idx, IfdKind::Ifd1, offset).ok()?;
+        }
+
+        let mut iter = ExifIter {
+            input: self.input.clone(),
+            endian: self.endian,
+            tz: self.tz.clone(),
+            ifd0: Some(ifd),
+            limits: self.limits.clone(),
+            file_offset: self.file_offset,
+            follow_next: false,
+            ifds: Vec::new(),
+            entries: 0,
+            chain: BTreeSet::new(),
+            skipped_ifds: Vec::new(),
+            diagnostics: self.diagnostics.clone(),
+        };
+        iter.rewind();
+        Some(iter)
     }
 
     /// Try to find and parse gps information.
@@ -91,28 +306,32 @@ impl<'a> ExifIter<'a> {
             Err(e) => return Err(e),
         };
 
-        let data = &iter.input[..];
-        let mut gps_subifd = match ImageFileDirectoryIter::try_new(
-            gps.ifd,
-            iter.input.make_associated(data),
-            offset,
-            iter.endian,
-            iter.tz.clone(),
-        ) {
-            Ok(ifd0) => ifd0,
-            Err(e) => return Err(e),
-        };
+        let mut gps_subifd = iter.new_ifd(gps.ifd, IfdKind::Gps, offset)?;
         Ok(gps_subifd.parse_gps_info())
     }
 
+    /// Returns the TIFF data, its byte order, and the position of the value
+    /// of the first `tag` entry within the data.
+    pub(crate) fn find_value_in_tiff(&self, tag: ExifTag) -> Option<(&[u8], Endianness, usize)> {
+        let entry = self.shallow_clone().find(|x| x.tag() == Some(tag))?;
+        let pos = entry
+            .raw
+            .value_offset
+            .checked_sub(self.file_offset + self.input.range.start)?;
+        Some((&self.input[..], self.endian, pos))
+    }
+
     // Make sure we won't clone the owned data.
     fn shallow_clone(&'a self) -> Self {
-        ExifIter::new(
+        let mut iter = ExifIter::new(
             &self.input[..],
             self.endian,
             self.tz.clone(),
             self.ifd0.clone(),
-        )
+        );
+        iter.set_limits(self.limits.clone());
+        iter.file_offset = self.file_offset;
+        iter
     }
 }
 
@@ -126,8 +345,24 @@ impl Default for ExifIter<'static> {
 pub struct ParsedExifEntry {
     // 0: ifd0, 1: ifd1
     ifd: usize,
+    kind: IfdKind,
     tag: ExifTagCode,
-    res: RefCell<Option<crate::Result<EntryValue>>>,
+    // A `Mutex` rather than a `RefCell`, so that entries are `Sync` (there
+    // are no threads without `std`)
+    res: Mutex<Option<crate::Result<EntryValue>>>,
+    raw: RawEntry,
+}
+
+/// An IFD entry as stored in the data, before its value is parsed.
+#[derive(Debug, Clone, Default)]
+struct RawEntry {
+    data_type: u16,
+    count: u32,
+    /// Position of the 12-byte entry, relative to the TIFF header.
+    entry_offset: usize,
+    /// Position of the value, relative to the TIFF header.
+    value_offset: usize,
+    bytes: Vec<u8>,
 }
 
 impl ParsedExifEntry {
@@ -138,6 +373,34 @@ impl ParsedExifEntry {
         self.ifd
     }
 
+    /// Get the IFD where this entry is located. Unlike [`Self::ifd_index`],
+    /// this tells entries of the Exif and GPS sub-IFDs apart from the ones of
+    /// IFD0, which is useful for tags whose codes are only unique within
+    /// their IFD (e.g. unrecognized or vendor-specific tags).
+    ///
+    /// Entries of IFD1 and any following IFDs are reported as
+    /// [`IfdKind::Ifd1`]. The codes of the Interoperability IFD overlap with
+    /// the ones of the GPS IFD, [`Self::tag`] names them after the IFD they
+    /// appear in (e.g. [`ExifTag::InteropIndex`] rather than
+    /// [`ExifTag::GPSLatitudeRef`]).
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let iter = parse_exif(f, None).unwrap().unwrap();
+    /// let thumbnail_tags = iter
+    ///     .filter(|x| x.ifd_kind() == IfdKind::Ifd1)
+    ///     .filter_map(|x| x.tag())
+    ///     .collect::<Vec<_>>();
+    /// assert!(thumbnail_tags.contains(&ExifTag::ThumbnailOffset));
+    /// ```
+    pub fn ifd_kind(&self) -> IfdKind {
+        self.kind
+    }
+
     /// Get recognized Exif tag of this entry, maybe return `None` if the tag
     /// is unrecognized.
     ///
@@ -162,6 +425,12 @@ impl ParsedExifEntry {
         self.tag.code()
     }
 
+    /// Returns the tag for messages, e.g. `"Make(0x010f)"`.
+    #[cfg(feature = "std")]
+    pub(crate) fn tag_name(&self) -> String {
+        format!("{:?}", self.tag)
+    }
+
     /// Returns true if there is an `EntryValue` in self.
     ///
     /// Both of the following situations may cause this method to return false:
@@ -169,11 +438,22 @@ impl ParsedExifEntry {
     /// - The value has been taken by calling [`Self::take_value`] or
     ///   [`Self::take_result`] methods.
     pub fn has_value(&self) -> bool {
-        self.res
-            .borrow()
-            .as_ref()
-            .map(|e| e.is_ok())
-            .is_some_and(|b| b)
+        self.res().as_ref().map(|e| e.is_ok()).is_some_and(|b| b)
+    }
+
+    /// Renders the value of this entry the way photographers expect, see
+    /// [`ExifTag::display_value`]. Values of unrecognized tags are rendered
+    /// with [`EntryValue`]'s `Display` implementation.
+    ///
+    /// Returns `None` if the value couldn't be parsed, or has been taken
+    /// (see [`Self::take_value`]).
+    pub fn display_value(&self) -> Option<String> {
+        let res = self.res();
+        let value = res.as_ref()?.as_ref().ok()?;
+        Some(match self.tag() {
+            Some(tag) => tag.display_value(value),
+            None => value.to_string(),
+        })
     }
 
     /// Takes out the parsed entry value of this entry.
@@ -182,7 +462,7 @@ impl ParsedExifEntry {
     /// calling it again always returns `None`. You may want to check it by
     /// calling [`Self::has_value`] before calling this method.
     pub fn take_value(&self) -> Option<EntryValue> {
-        match self.res.take() {
+        match self.res().take() {
             Some(v) => v.ok(),
             None => None,
         }
@@ -202,31 +482,102 @@ impl ParsedExifEntry {
     ///
     /// **Note**: This method can only be called once!
     pub fn take_result(&self) -> crate::Result<EntryValue> {
-        match self.res.take() {
+        match self.res().take() {
             Some(v) => v,
             None => Err(crate::Error::EntryHasBeenTaken),
         }
     }
 
-    fn make_ok(ifd: usize, tag: ExifTagCode, v: EntryValue) -> Self {
+    /// Get the raw data type code of this entry, e.g. 2 for ASCII or 5 for
+    /// RATIONAL. Invalid type codes are returned as is.
+    pub fn data_type(&self) -> u16 {
+        self.raw.data_type
+    }
+
+    /// Get the number of components (not bytes) of the value, as stored in
+    /// the entry.
+    pub fn count(&self) -> u32 {
+        self.raw.count
+    }
+
+    /// Get the position of the 12-byte IFD entry.
+    ///
+    /// Positions are absolute file offsets when the [`ExifIter`] is created
+    /// by [`parse_exif`](crate::parse_exif) from the start of a file.
+    /// Otherwise, they're relative to the data the parsing started with.
+    pub fn entry_offset(&self) -> u64 {
+        self.raw.entry_offset as u64
+    }
+
+    /// Get the position of the value, which is within the entry for values
+    /// of up to 4 bytes. See [`Self::entry_offset`] for what it's relative
+    /// to.
+    pub fn value_offset(&self) -> u64 {
+        self.raw.value_offset as u64
+    }
+
+    /// Get the bytes of the value as stored, i.e. `count` components in the
+    /// byte order of the Exif data, without any parsing. Useful for
+    /// inspecting malformed values.
+    ///
+    /// For unknown data types, whose size is unknown, the 4-byte value field
+    /// of the entry is returned. The bytes are empty if the value is out of
+    /// bounds.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut iter = parse_exif(f, None).unwrap().unwrap();
+    /// let model = iter.find(|x| x.tag() == Some(ExifTag::Model)).unwrap();
+    /// assert_eq!(model.data_type(), 2);
+    /// assert_eq!(model.raw_value(), b"vivo X90 Pro+\0");
+    ///
+    /// let data = std::fs::read("./testdata/exif.jpg").unwrap();
+    /// let start = model.value_offset() as usize;
+    /// assert_eq!(&data[start..start + 13], b"vivo X90 Pro+");
+    /// ```
+    pub fn raw_value(&self) -> &[u8] {
+        &self.raw.bytes
+    }
+
+    /// Locks the result. The lock is never held across a panic, poisoning is
+    /// ignored anyway.
+    #[cfg(feature = "std")]
+    fn res(&self) -> MutexGuard<'_, Option<crate::Result<EntryValue>>> {
+        self.res.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn res(&self) -> MutexGuard<'_, Option<crate::Result<EntryValue>>> {
+        self.res.borrow_mut()
+    }
+
+    fn make_ok(ifd: usize, kind: IfdKind, tag: ExifTagCode, v: EntryValue, raw: RawEntry) -> Self {
         Self {
             ifd,
+            kind,
             tag,
-            res: RefCell::new(Some(Ok(v))),
+            res: Mutex::new(Some(Ok(v))),
+            raw,
         }
     }
 
-    fn make_err(ifd: usize, tag: ExifTagCode, e: EntryError) -> Self {
+    fn make_err(ifd: usize, kind: IfdKind, tag: ExifTagCode, e: EntryError, raw: RawEntry) -> Self {
         Self {
             ifd,
+            kind,
             tag,
-            res: RefCell::new(Some(Err(crate::Error::InvalidEntry(e.into())))),
+            res: Mutex::new(Some(Err(e.into()))),
+            raw,
         }
     }
 }
 
 impl Debug for ParsedExifEntry {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let value = match self.take_result() {
             Ok(v) => format!("{v}"),
             Err(e) => format!("{e:?}"),
@@ -239,22 +590,61 @@ impl Debug for ParsedExifEntry {
     }
 }
 
-const MAX_IFD_DEPTH: usize = 8;
+/// Serialized as `{"ifd": 0, "tag": "Make(0x010f)", "value": "Apple",
+/// "error": null}`. Unlike [`ParsedExifEntry::take_value`], the value is not
+/// taken; both `value` and `error` are `null` if it has been taken.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ParsedExifEntry {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let res = self.res();
+        let (value, error) = match res.as_ref() {
+            Some(Ok(v)) => (Some(v), None),
+            Some(Err(e)) => (None, Some(e.to_string())),
+            None => (None, None),
+        };
+        let mut s = serializer.serialize_struct("ParsedExifEntry", 4)?;
+        s.serialize_field("ifd", &self.ifd)?;
+        s.serialize_field("tag", &format!("{:?}", self.tag))?;
+        s.serialize_field("value", &value)?;
+        s.serialize_field("error", &error)?;
+        s.end()
+    }
+}
+
+pub(crate) const MAX_IFD_DEPTH: usize = 8;
+
+/// Identifier which precedes the TIFF data in JPEG APP1 segments.
+const EXIF_IDENT: &[u8] = b"Exif\0\0";
 
 impl<'a> Iterator for ExifIter<'a> {
     type Item = ParsedExifEntry;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let endian = self.endian;
         loop {
-            if self.ifds.len() > MAX_IFD_DEPTH {
+            if self.ifds.len() > self.limits.max_ifd_depth {
                 self.ifds.pop();
             }
 
             let mut ifd = self.ifds.pop()?;
-            match ifd.next() {
-                Some((tag_code, entry)) => match entry {
+            let kind = ifd.kind;
+            let next = ifd.next();
+            if next.is_some() {
+                self.entries += 1;
+            }
+            let stop = next.as_ref().and_then(|_| self.limits.check(self.entries));
+            match (next, stop) {
+                (Some((tag_code, _)), Some(e)) => {
+                    let tag = interop_tag(kind, tag_code);
+                    let raw = self.raw_entry(&ifd);
+                    // Stop iterating
+                    self.ifds.clear();
+                    return Some(ParsedExifEntry::make_err(ifd.ifd_idx, kind, tag, e, raw));
+                }
+                (Some((tag_code, entry)), None) => match entry {
                     IfdEntry::Ifd { idx, offset } => {
+                        let raw = self.raw_entry(&ifd);
                         let is_subifd = if idx == ifd.ifd_idx {
                             // Push the current ifd before enter sub-ifd.
                             self.ifds.push(ifd);
@@ -266,13 +656,29 @@ impl<'a> Iterator for ExifIter<'a> {
                             false
                         };
 
-                        if let Ok(ifd) = ImageFileDirectoryIter::try_new(
-                            idx,
-                            self.input.make_associated(&self.input[..]),
-                            offset,
-                            endian,
-                            self.tz.clone(),
-                        ) {
+                        let sub_kind = match tag_code.tag() {
+                            _ if !is_subifd => IfdKind::Ifd1,
+                            Some(ExifTag::GPSInfo) => IfdKind::Gps,
+                            Some(ExifTag::InteropOffset) => IfdKind::Interop,
+                            _ => IfdKind::Exif,
+                        };
+                        if !is_subifd && !matches!(kind, IfdKind::Ifd0 | IfdKind::Ifd1) {
+                            // Sub-IFDs don't have following IFDs
+                            continue;
+                        }
+                        if !is_subifd && !self.follow_next {
+                            continue;
+                        }
+                        if !is_subifd && !self.chain.insert(offset) {
+                            tracing::warn!(offset, "IFD chain has a loop, stop.");
+                            self.skipped_ifds.push(Warning::SkippedIfd {
+                                kind: sub_kind,
+                                offset,
+                                reason: "IFD chain has a loop".to_owned(),
+                            });
+                            continue;
+                        }
+                        if let Some(ifd) = self.try_new_ifd(idx, sub_kind, offset) {
                             self.ifds.push(ifd);
                         }
 
@@ -280,40 +686,156 @@ impl<'a> Iterator for ExifIter<'a> {
                             // Return sub-ifd as an entry
                             return Some(ParsedExifEntry::make_ok(
                                 idx,
+                                kind,
                                 tag_code,
                                 EntryValue::U32(offset as u32),
+                                raw,
                             ));
                         }
                     }
                     IfdEntry::Entry(v) => {
-                        let res = Some(ParsedExifEntry::make_ok(ifd.ifd_idx, tag_code, v));
-                        self.ifds.push(ifd);
+                        let tag = interop_tag(kind, tag_code);
+                        let raw = self.raw_entry(&ifd);
+                        let res = Some(ParsedExifEntry::make_ok(ifd.ifd_idx, kind, tag, v, raw));
+                        self.push_ifd(ifd, tag_code);
                         return res;
                     }
                     IfdEntry::Err(e) => {
-                        let res = Some(ParsedExifEntry::make_err(ifd.ifd_idx, tag_code, e));
-                        self.ifds.push(ifd);
+                        let tag = interop_tag(kind, tag_code);
+                        let raw = self.raw_entry(&ifd);
+                        let res = Some(ParsedExifEntry::make_err(ifd.ifd_idx, kind, tag, e, raw));
+                        self.push_ifd(ifd, tag_code);
                         return res;
                     }
                 },
-                None => continue,
+                (None, _) => continue,
             }
         }
     }
 }
 
+impl ExifIter<'_> {
+    /// Creates an iterator over the IFD at `offset`, with the limits of this
+    /// iterator.
+    fn new_ifd(
+        &self,
+        idx: usize,
+        kind: IfdKind,
+        offset: usize,
+    ) -> crate::Result<ImageFileDirectoryIter> {
+        let mut ifd = ImageFileDirectoryIter::try_new(
+            idx,
+            kind,
+            self.input.make_associated(&self.input[..]),
+            offset,
+            self.endian,
+            self.tz.clone(),
+            &self.diagnostics,
+        )?;
+        ifd.max_string_length = self.limits.max_string_length;
+        Ok(ifd)
+    }
+
+    /// Like [`Self::new_ifd`], but an IFD which can't be parsed is only
+    /// recorded, so that iterating goes on with the other IFDs.
+    fn try_new_ifd(
+        &mut self,
+        idx: usize,
+        kind: IfdKind,
+        offset: usize,
+    ) -> Option<ImageFileDirectoryIter> {
+        match self.new_ifd(idx, kind, offset) {
+            Ok(ifd) => Some(ifd),
+            Err(e) => {
+                tracing::debug!(?kind, offset, error = ?e, "Skipped IFD.");
+                self.skipped_ifds.push(Warning::SkippedIfd {
+                    kind,
+                    offset,
+                    reason: e.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Returns the IFDs which have been skipped so far, since they couldn't
+    /// be parsed.
+    #[cfg(feature = "std")]
+    pub(crate) fn take_skipped_ifds(&mut self) -> Vec<Warning> {
+        core::mem::take(&mut self.skipped_ifds)
+    }
+
+    /// Returns a rewound copy of this iterator which records its warnings in
+    /// `diagnostics`, including the ones of IFD0, which is parsed again.
+    #[cfg(feature = "std")]
+    pub(crate) fn with_diagnostics(&self, diagnostics: Diagnostics) -> Self {
+        let mut iter = self.clone();
+        iter.diagnostics = diagnostics;
+        iter.ifd0 = self.ifd0.as_ref().and_then(|ifd0| {
+            // `pos` is past the entry count. IFD0 has been parsed already, so
+            // it doesn't fail.
+            iter.new_ifd(ifd0.ifd_idx, ifd0.kind, ifd0.pos - 2).ok()
+        });
+        iter.rewind();
+        iter
+    }
+
+    /// Returns the raw form of the last entry parsed from `ifd`, with
+    /// offsets relative to the start of the file (or of the input buffer).
+    fn raw_entry(&self, ifd: &ImageFileDirectoryIter) -> RawEntry {
+        let mut raw = ifd.last_entry_raw().unwrap_or_default();
+        raw.entry_offset += self.file_offset + self.input.range.start;
+        raw.value_offset += self.file_offset + self.input.range.start;
+        raw
+    }
+
+    /// Pushes `ifd` back, along with the SubIFDs which its last entry points
+    /// to (if it's a `SubIFDs` entry), so they're parsed before the rest of
+    /// `ifd`.
+    fn push_ifd(&mut self, ifd: ImageFileDirectoryIter, tag_code: ExifTagCode) {
+        let mut offsets = if tag_code.code() == SUBIFDS {
+            ifd.last_entry_offsets()
+        } else {
+            Vec::new()
+        };
+        let idx = ifd.ifd_idx;
+        self.ifds.push(ifd);
+
+        offsets.truncate(self.limits.max_ifd_depth.saturating_sub(self.ifds.len()));
+        for (n, offset) in offsets.into_iter().enumerate().rev() {
+            if let Some(ifd) = self.try_new_ifd(idx, IfdKind::SubIfd(n as u8), offset) {
+                self.ifds.push(ifd);
+            }
+        }
+    }
+}
+
+/// Recognizes the tags of the Interoperability IFD, whose codes overlap
+/// with the ones of the GPS IFD, see [`ExifTagCode::in_ifd`].
+fn interop_tag(kind: IfdKind, tag_code: ExifTagCode) -> ExifTagCode {
+    ExifTagCode::in_ifd(kind, tag_code.code())
+}
+
 #[derive(Debug)]
 pub(crate) struct ImageFileDirectoryIter {
     pub ifd_idx: usize,
+    pub kind: IfdKind,
     pub input: AssociatedInput,
     pub pos: usize,
     pub endian: Endianness,
     pub tz: Option<String>,
 
     pub num_entries: u16,
+    /// The entry count has been reduced to what fits in the data, so the
+    /// link to the next IFD is missing.
+    pub clamped: bool,
+    pub max_string_length: usize,
+    pub diagnostics: Diagnostics,
 
     // Iterating status
     pub index: u16,
+    /// The link to the next IFD has been returned, nothing is left.
+    pub finished: bool,
 }
 
 impl Clone for ImageFileDirectoryIter {
@@ -321,7 +843,9 @@ impl Clone for ImageFileDirectoryIter {
         Self {
             tz: self.tz.clone(),
             input: self.input.clone(),
+            diagnostics: self.diagnostics.clone(),
             index: 0,
+            finished: false,
             ..*self
         }
     }
@@ -330,39 +854,139 @@ impl Clone for ImageFileDirectoryIter {
 impl ImageFileDirectoryIter {
     pub fn try_new(
         ifd_idx: usize,
+        kind: IfdKind,
         input: AssociatedInput,
         pos: usize,
         endian: Endianness,
         tz: Option<String>,
+        diagnostics: &Diagnostics,
     ) -> crate::Result<Self> {
-        let num_entries = Self::parse_num_entries(endian, &input[pos..])?;
+        let (declared, num_entries) = Self::parse_num_entries(endian, &input[pos..])?;
+        if num_entries < declared {
+            diagnostics.push(Warning::EntryCount {
+                kind,
+                declared,
+                available: num_entries,
+            });
+        }
         Ok(Self {
             ifd_idx,
+            kind,
             endian,
             tz,
             num_entries,
+            clamped: num_entries < declared,
+            max_string_length: usize::MAX,
+            diagnostics: diagnostics.clone(),
             index: 0,
+            finished: false,
             input,
             pos: pos + 2,
         })
     }
 
-    fn parse_num_entries(endian: Endianness, data: &[u8]) -> crate::Result<u16> {
-        let (remain, num) = complete::u16(endian)(data)?; // Safe-slice
-        if num == 0 {
-            return Ok(num);
-        }
+    /// Returns the position of this IFD (of its entry count) in the data.
+    fn offset(&self) -> usize {
+        self.pos - self.index as usize * ENTRY_SIZE - 2
+    }
 
-        // 12 bytes per entry
-        let size = (num as usize).checked_mul(ENTRY_SIZE);
-        let Some(size) = size else {
-            return Err("ifd entry num is too big".into());
+    /// Returns the type, count, positions and value bytes of the last
+    /// parsed entry, as stored in the data.
+    fn last_entry_raw(&self) -> Option<RawEntry> {
+        let endian = self.endian;
+        let entry_offset = self.pos.checked_sub(ENTRY_SIZE)?;
+        let entry_data = self
+            .input
+            .slice_checked(entry_offset..entry_offset + ENTRY_SIZE)?;
+        let (_, (_, data_type, count, value_or_offset)) = tuple((
+            complete::u16::<_, nom::error::Error<_>>(endian),
+            complete::u16(endian),
+            complete::u32(endian),
+            complete::u32(endian),
+        ))(entry_data)
+        .ok()?;
+
+        let component_size = match DataFormat::try_from(data_type) {
+            Ok(df) => Some(df.component_size()),
+            // IFD
+            Err(_) if data_type == 13 => Some(4),
+            Err(_) => None,
         };
-        if size > remain.len() {
-            Err("ifd entry num is too big".into())
-        } else {
-            Ok(num)
+        let size = component_size.and_then(|x| x.checked_mul(count as usize));
+        let (value_offset, bytes) = match size {
+            Some(size) if size > 4 => {
+                let start = value_or_offset as usize;
+                let bytes = start
+                    .checked_add(size)
+                    .and_then(|end| self.input.slice_checked(start..end))
+                    .unwrap_or_default();
+                (start, bytes)
+            }
+            // The value is stored in the entry; the size of values of
+            // unknown types is unknown, so the whole value field is used.
+            size => (
+                entry_offset + 8,
+                &entry_data[8..8 + size.unwrap_or(4)], // Safe-slice
+            ),
+        };
+        Some(RawEntry {
+            data_type,
+            count,
+            entry_offset,
+            value_offset,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the offsets which the last parsed entry points to, assuming
+    /// it's a `SubIFDs` entry (of type LONG or IFD).
+    fn last_entry_offsets(&self) -> Vec<usize> {
+        let Some(raw) = self.last_entry_raw() else {
+            return Vec::new();
+        };
+        // LONG or IFD
+        if !matches!(raw.data_type, 4 | 13) {
+            return Vec::new();
         }
+        raw.bytes
+            .chunks_exact(4)
+            .map(|x| match self.endian {
+                Endianness::Little => u32::from_le_bytes(x.try_into().unwrap()),
+                _ => u32::from_be_bytes(x.try_into().unwrap()),
+            } as usize)
+            .filter(|&offset| {
+                if offset >= self.input.len() {
+                    self.diagnostics.push(Warning::OffsetOutOfRange {
+                        name: format!("SubIFDs(0x{SUBIFDS:04x})"),
+                        offset,
+                    });
+                }
+                offset != 0 && offset < self.input.len()
+            })
+            .collect()
+    }
+
+    /// Returns the offset of the IFD following this one, if there is a valid
+    /// link to it.
+    fn next_ifd_offset(&self) -> Option<usize> {
+        if self.clamped {
+            return None;
+        }
+        let pos = (self.offset() + 2).checked_add(self.num_entries as usize * ENTRY_SIZE)?;
+        let data = self.input.slice_checked(pos..pos + 4)?;
+        let (_, offset) = complete::u32::<_, nom::error::Error<_>>(self.endian)(data).ok()?;
+        let offset = offset as usize;
+        (offset != 0 && offset < self.input.len()).then_some(offset)
+    }
+
+    /// Returns the entry count of the IFD at the start of `data`, and the
+    /// number of entries which actually fit in `data`.
+    fn parse_num_entries(endian: Endianness, data: &[u8]) -> crate::Result<(u16, u16)> {
+        let (remain, num) = complete::u16(endian)(data)?; // Safe-slice
+
+        // 12 bytes per entry
+        let available = (remain.len() / ENTRY_SIZE).min(num as usize) as u16;
+        Ok((num, available))
     }
 
     fn parse_tag_entry(&self, entry_data: &[u8]) -> Option<(u16, IfdEntry)> {
@@ -391,6 +1015,11 @@ impl ImageFileDirectoryIter {
         entry_data: &[u8],
         value_or_offset: u32,
     ) -> (u16, IfdEntry) {
+        if data_format == DataFormat::Text && components_num as usize > self.max_string_length {
+            let limit = Limit::StringLength(self.max_string_length);
+            return (tag, IfdEntry::Err(EntryError::LimitExceeded(limit)));
+        }
+
         // get component_size according to data format
         let component_size = data_format.component_size();
 
@@ -424,6 +1053,12 @@ impl ImageFileDirectoryIter {
             }
         }
 
+        if data_format == DataFormat::Text && !data.is_ascii() {
+            self.diagnostics.push(Warning::NonAsciiText {
+                name: format!("{:?}", interop_tag(self.kind, tag.into())),
+            });
+        }
+
         let entry = EntryData {
             endian: self.endian,
             tag,
@@ -460,7 +1095,8 @@ impl ImageFileDirectoryIter {
     }
 
     fn find_tz_offset_in_exif_subifd(&self, offset: usize) -> Option<String> {
-        let num_entries = Self::parse_num_entries(self.endian, &self.input[offset..]).ok()?;
+        let data = self.input.get(offset..)?;
+        let (_, num_entries) = Self::parse_num_entries(self.endian, data).ok()?;
         let pos = offset + 2;
         for i in 0..num_entries {
             let pos = pos + i as usize * ENTRY_SIZE;
@@ -538,6 +1174,44 @@ impl ImageFileDirectoryIter {
                         gps.speed = (*v).into();
                     }
                 }
+                ExifTag::GPSTrackRef => {
+                    if let Some(c) = entry.as_char() {
+                        gps.track_ref = c;
+                    }
+                }
+                ExifTag::GPSTrack => {
+                    if let Some(v) = entry.as_urational() {
+                        gps.track = *v;
+                    } else if let Some(v) = entry.as_irational() {
+                        gps.track = (*v).into();
+                    }
+                }
+                ExifTag::GPSImgDirectionRef => {
+                    if let Some(c) = entry.as_char() {
+                        gps.img_direction_ref = c;
+                    }
+                }
+                ExifTag::GPSImgDirection => {
+                    if let Some(v) = entry.as_urational() {
+                        gps.img_direction = *v;
+                    } else if let Some(v) = entry.as_irational() {
+                        gps.img_direction = (*v).into();
+                    }
+                }
+                ExifTag::GPSDOP => {
+                    if let Some(v) = entry.as_urational() {
+                        gps.dop = *v;
+                    } else if let Some(v) = entry.as_irational() {
+                        gps.dop = (*v).into();
+                    }
+                }
+                ExifTag::GPSHPositioningError => {
+                    if let Some(v) = entry.as_urational() {
+                        gps.h_positioning_error = *v;
+                    } else if let Some(v) = entry.as_irational() {
+                        gps.h_positioning_error = (*v).into();
+                    }
+                }
                 _ => (),
             }
         }
@@ -608,7 +1282,13 @@ impl IfdEntry {
 }
 
 const ENTRY_SIZE: usize = 12;
-const SUBIFD_TAGS: &[u16] = &[ExifTag::ExifOffset.code(), ExifTag::GPSInfo.code()];
+const SUBIFD_TAGS: &[u16] = &[
+    ExifTag::ExifOffset.code(),
+    ExifTag::GPSInfo.code(),
+    ExifTag::InteropOffset.code(),
+];
+/// Tag code of `SubIFDs`, which points to the sub-images of TIFF/DNG files.
+const SUBIFDS: u16 = 0x014a;
 const TZ_OFFSET_TAGS: &[u16] = &[
     ExifTag::OffsetTimeOriginal.code(),
     ExifTag::OffsetTimeDigitized.code(),
@@ -621,6 +1301,9 @@ impl Iterator for ImageFileDirectoryIter {
     fn next(&mut self) -> Option<Self::Item> {
         let endian = self.endian;
         if self.index >= self.num_entries {
+            if self.clamped || self.finished {
+                return None;
+            }
             // next IFD
             let (_, offset) =
                 complete::u32::<_, nom::error::Error<_>>(endian)(&self.input[self.pos..]).ok()?;
@@ -631,8 +1314,13 @@ impl Iterator for ImageFileDirectoryIter {
                 return None;
             } else if offset >= self.input.len() {
                 // Ignore this error
+                self.diagnostics.push(Warning::OffsetOutOfRange {
+                    name: "next IFD".to_owned(),
+                    offset,
+                });
                 return None;
             } else {
+                self.finished = true;
                 return Some((
                     ExifTagCode::Code(0),
                     IfdEntry::Ifd {
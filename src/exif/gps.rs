@@ -1,7 +1,18 @@
-use crate::values::{IRational, URational};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use crate::mov::{ACCURACY_KEY, LOCATION_KEY};
+#[cfg(feature = "std")]
+use crate::EntryValue;
+use crate::{
+    prelude::*,
+    values::{IRational, URational},
+};
 
 /// Represents gps information stored in [`GPSInfo`](crate::ExifTag::GPSInfo)
 /// subIFD.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct GPSInfo {
     /// N, S
@@ -26,22 +37,227 @@ pub struct GPSInfo {
     /// - N: knots
     pub speed_ref: char,
     pub speed: URational,
+
+    /// Reference of the direction of movement
+    /// - T: true direction
+    /// - M: magnetic direction
+    pub track_ref: char,
+    /// Direction of movement, degrees from 0 to 359.99
+    pub track: URational,
+
+    /// Reference of the image direction
+    /// - T: true direction
+    /// - M: magnetic direction
+    pub img_direction_ref: char,
+    /// Direction of the image when it was captured, degrees from 0 to 359.99
+    pub img_direction: URational,
+
+    /// Degree of precision (DOP) of the measurement
+    pub dop: URational,
+
+    /// Horizontal positioning error in meters, the
+    /// `com.apple.quicktime.location.accuracy.horizontal` item of QuickTime
+    /// files
+    pub h_positioning_error: URational,
 }
 
 /// degree, minute, second,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct LatLng(pub URational, pub URational, pub URational);
 
+/// Denominator of the seconds value generated by [`GPSInfo::from_decimal`],
+/// which gives a precision of about 3 millimeters.
+const SECONDS_DENOMINATOR: u32 = 10_000;
+
+/// Denominator of the altitude generated by [`GPSInfo::from_decimal`].
+const ALTITUDE_DENOMINATOR: u32 = 1000;
+
+/// Denominator of the positioning error generated by
+/// [`GPSInfo::from_quicktime`].
+#[cfg(feature = "std")]
+const ACCURACY_DENOMINATOR: u32 = 1000;
+
 impl GPSInfo {
+    /// Creates a [`GPSInfo`] from decimal degrees, e.g. `22.5797, 113.938`.
+    ///
+    /// - `latitude`: positive for north, negative for south.
+    /// - `longitude`: positive for east, negative for west.
+    /// - `altitude`: meters, negative for below sea level.
+    ///
+    /// An `Err` is returned if a coordinate is out of range or not finite.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let gps = GPSInfo::from_decimal(-33.8568, 151.2153, 5.5).unwrap();
+    /// assert_eq!(gps.latitude_ref, 'S');
+    /// assert_eq!(gps.longitude_ref, 'E');
+    /// assert_eq!(gps.format_iso6709(), "-33.85680+151.21530+5.500/");
+    /// ```
+    pub fn from_decimal(latitude: f64, longitude: f64, altitude: f64) -> crate::Result<GPSInfo> {
+        if !(-90.0..=90.0).contains(&latitude) {
+            return Err(format!("invalid latitude: {latitude}").into());
+        }
+        if !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!("invalid longitude: {longitude}").into());
+        }
+        let alt = (altitude.abs() * ALTITUDE_DENOMINATOR as f64).round();
+        if !alt.is_finite() || alt > u32::MAX as f64 {
+            return Err(format!("invalid altitude: {altitude}").into());
+        }
+
+        Ok(GPSInfo {
+            latitude_ref: if latitude < 0.0 { 'S' } else { 'N' },
+            latitude: LatLng::from_degrees(latitude.abs()),
+            longitude_ref: if longitude < 0.0 { 'W' } else { 'E' },
+            longitude: LatLng::from_degrees(longitude.abs()),
+            altitude_ref: if altitude < 0.0 { 1 } else { 0 },
+            altitude: (alt as u32, ALTITUDE_DENOMINATOR).into(),
+            ..Default::default()
+        })
+    }
+
+    /// Parses an ISO 6709 geographic point location string such as
+    /// `+27.1281+100.2508+000.000/`, which is the format used by QuickTime
+    /// metadata. Angles can be in degrees (`±DD.DD`), degrees & minutes
+    /// (`±DDMM.MM`), or degrees, minutes & seconds (`±DDMMSS.SS`). The
+    /// altitude is optional, and a trailing CRS is ignored.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let gps = GPSInfo::from_iso6709("+48.8577+002.295/").unwrap();
+    /// assert_eq!(gps, GPSInfo::from_iso6709("+485127.72+0021742/").unwrap());
+    /// assert_eq!(gps.format_iso6709(), "+48.85770+002.29500/");
+    /// ```
+    pub fn from_iso6709(s: &str) -> crate::Result<GPSInfo> {
+        let err = || format!("invalid ISO 6709 location: {s}");
+        let body = s.trim().trim_end_matches('/');
+        let body = body.find("CRS").map_or(body, |i| &body[..i]);
+
+        // Split into signed components
+        let mut parts = Vec::new();
+        for (i, c) in body.char_indices() {
+            if c == '+' || c == '-' {
+                parts.push(i);
+            } else if parts.is_empty() {
+                return Err(err().into());
+            }
+        }
+        parts.push(body.len());
+        let parts = parts
+            .windows(2)
+            .map(|x| &body[x[0]..x[1]])
+            .collect::<Vec<_>>();
+
+        let (latitude, longitude, altitude) = match parts[..] {
+            [lat, lon] => (lat, lon, None),
+            [lat, lon, alt] => (lat, lon, Some(alt)),
+            _ => return Err(err().into()),
+        };
+        let latitude = parse_iso6709_angle(latitude, 2).ok_or_else(err)?;
+        let longitude = parse_iso6709_angle(longitude, 3).ok_or_else(err)?;
+        let Some(altitude) = altitude else {
+            let mut gps = GPSInfo::from_decimal(latitude, longitude, 0.0)?;
+            // Unknown altitude
+            gps.altitude = (0, 0).into();
+            return Ok(gps);
+        };
+        let altitude = altitude.parse::<f64>().map_err(|_| err())?;
+        GPSInfo::from_decimal(latitude, longitude, altitude)
+    }
+
+    /// Converts to decimal degrees `(latitude, longitude, altitude)`, the
+    /// inverse of [`GPSInfo::from_decimal`].
+    ///
+    /// - `latitude`: positive for north, negative for south.
+    /// - `longitude`: positive for east, negative for west.
+    /// - `altitude`: meters, negative for below sea level, `None` if unknown.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let gps = GPSInfo::from_iso6709("-33.8568+151.2153+5.5/").unwrap();
+    /// let (latitude, longitude, altitude) = gps.to_decimal();
+    /// assert!((latitude - -33.8568).abs() < 1e-6);
+    /// assert!((longitude - 151.2153).abs() < 1e-6);
+    /// assert_eq!(altitude, Some(5.5));
+    /// ```
+    pub fn to_decimal(&self) -> (f64, f64, Option<f64>) {
+        let latitude = self.latitude.to_degrees();
+        let longitude = self.longitude.to_degrees();
+        let altitude = self.altitude.to_f64();
+        (
+            if self.latitude_ref == 'S' {
+                -latitude
+            } else {
+                latitude
+            },
+            if self.longitude_ref == 'W' {
+                -longitude
+            } else {
+                longitude
+            },
+            altitude.map(|x| if self.altitude_ref == 1 { -x } else { x }),
+        )
+    }
+
+    /// Creates a [`GPSInfo`] from QuickTime metadata, as returned by
+    /// [`parse_metadata`](crate::parse_metadata): the location is read from
+    /// `com.apple.quicktime.location.ISO6709`, and the positioning error from
+    /// `com.apple.quicktime.location.accuracy.horizontal`.
+    ///
+    /// Returns `Ok(None)` if there is no location.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/embedded-in-heic.mov").unwrap();
+    /// let entries = parse_metadata(f).unwrap();
+    /// let gps = GPSInfo::from_quicktime(&entries).unwrap().unwrap();
+    /// assert_eq!(gps.format_iso6709(), "+22.57970+113.93800+28.396/");
+    /// assert_eq!(gps.h_positioning_error.as_float(), 14.236);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_quicktime(entries: &[(String, EntryValue)]) -> crate::Result<Option<GPSInfo>> {
+        let find = |key| entries.iter().find(|x| x.0 == key).map(|x| &x.1);
+        let Some(location) = find(LOCATION_KEY) else {
+            return Ok(None);
+        };
+        let location = location
+            .as_str()
+            .ok_or_else(|| format!("invalid ISO 6709 location: {location}"))?;
+        let mut gps = GPSInfo::from_iso6709(location)?;
+
+        let accuracy = find(ACCURACY_KEY).and_then(|x| match x {
+            EntryValue::Text(s) => s.trim().parse::<f64>().ok(),
+            x => x.to_f64(),
+        });
+        if let Some(accuracy) = accuracy {
+            let v = (accuracy * ACCURACY_DENOMINATOR as f64).round();
+            if (0.0..=u32::MAX as f64).contains(&v) {
+                gps.h_positioning_error = (v as u32, ACCURACY_DENOMINATOR).into();
+            } else {
+                tracing::warn!(accuracy, "Skip invalid horizontal accuracy.");
+            }
+        }
+        Ok(Some(gps))
+    }
+
     /// Returns an ISO 6709 geographic point location string such as
     /// `+48.8577+002.295/`.
     pub fn format_iso6709(&self) -> String {
-        let latitude = self.latitude.0.as_float()
-            + self.latitude.1.as_float() / 60.0
-            + self.latitude.2.as_float() / 3600.0;
-        let longitude = self.longitude.0.as_float()
-            + self.longitude.1.as_float() / 60.0
-            + self.longitude.2.as_float() / 3600.0;
+        let latitude = self.latitude.to_degrees();
+        let longitude = self.longitude.to_degrees();
         let altitude = self.altitude.as_float();
         format!(
             "{}{latitude:08.5}{}{longitude:09.5}{}/",
@@ -67,6 +283,75 @@ impl GPSInfo {
     }
 }
 
+/// Parses a signed ISO 6709 angle to decimal degrees. `degree_digits` is the
+/// number of integer digits of the degrees, 2 for latitude and 3 for
+/// longitude.
+fn parse_iso6709_angle(s: &str, degree_digits: usize) -> Option<f64> {
+    let digits = s.get(1..)?;
+    if !digits.bytes().all(|x| x.is_ascii_digit() || x == b'.') {
+        return None;
+    }
+    let value = digits.parse::<f64>().ok()?;
+    let int_len = digits.find('.').unwrap_or(digits.len());
+    let degrees = match int_len.checked_sub(degree_digits)? {
+        0 => value,
+        2 => {
+            let d = (value / 100.0).trunc();
+            d + (value - d * 100.0) / 60.0
+        }
+        4 => {
+            let d = (value / 10000.0).trunc();
+            let m = ((value - d * 10000.0) / 100.0).trunc();
+            d + m / 60.0 + (value - d * 10000.0 - m * 100.0) / 3600.0
+        }
+        _ => return None,
+    };
+    Some(if s.starts_with('-') {
+        -degrees
+    } else {
+        degrees
+    })
+}
+
+impl LatLng {
+    /// Converts non-negative decimal `degrees` to degree, minute, second.
+    fn from_degrees(degrees: f64) -> LatLng {
+        let total = (degrees * 3600.0 * SECONDS_DENOMINATOR as f64).round() as u64;
+        let per_degree = 3600 * SECONDS_DENOMINATOR as u64;
+        let per_minute = 60 * SECONDS_DENOMINATOR as u64;
+        let d = total / per_degree;
+        let m = total % per_degree / per_minute;
+        let s = total % per_minute;
+        LatLng(
+            (d as u32, 1).into(),
+            (m as u32, 1).into(),
+            (s as u32, SECONDS_DENOMINATOR).into(),
+        )
+    }
+
+    /// Converts degree, minute, second to decimal degrees.
+    fn to_degrees(&self) -> f64 {
+        self.0.as_float() + self.1.as_float() / 60.0 + self.2.as_float() / 3600.0
+    }
+}
+
+/// Converts to a `geo_types::Point`, with the longitude as `x` and the
+/// latitude as `y`. The altitude is dropped.
+#[cfg(feature = "geo-types")]
+impl From<&GPSInfo> for geo_types::Point<f64> {
+    fn from(value: &GPSInfo) -> Self {
+        let (latitude, longitude, _) = value.to_decimal();
+        geo_types::Point::new(longitude, latitude)
+    }
+}
+
+#[cfg(feature = "geo-types")]
+impl From<GPSInfo> for geo_types::Point<f64> {
+    fn from(value: GPSInfo) -> Self {
+        (&value).into()
+    }
+}
+
 impl From<[(u32, u32); 3]> for LatLng {
     fn from(value: [(u32, u32); 3]) -> Self {
         let res: [URational; 3] = value.map(|x| x.into());
@@ -158,6 +443,7 @@ impl<'a> FromIterator<&'a IRational> for LatLng {
 #[cfg(test)]
 mod tests {
     use crate::values::Rational;
+    use test_case::test_case;
 
     use super::*;
 
@@ -241,4 +527,100 @@ mod tests {
         };
         assert_eq!(below.format_iso6709(), "+40.68917-074.04444-123.000/");
     }
+
+    #[test]
+    fn gps_from_decimal() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let gps = GPSInfo::from_decimal(40.689167, -74.044444, -12.3456).unwrap();
+        assert_eq!(gps.latitude_ref, 'N');
+        assert_eq!(
+            gps.latitude,
+            LatLng(
+                Rational::<u32>(40, 1),
+                Rational::<u32>(41, 1),
+                Rational::<u32>(210_012, 10_000),
+            )
+        );
+        assert_eq!(gps.longitude_ref, 'W');
+        assert_eq!(
+            gps.longitude,
+            LatLng(
+                Rational::<u32>(74, 1),
+                Rational::<u32>(2, 1),
+                Rational::<u32>(399_984, 10_000),
+            )
+        );
+        assert_eq!(gps.altitude_ref, 1);
+        assert_eq!(gps.altitude, Rational::<u32>(12_346, 1000));
+        assert_eq!(gps.format_iso6709(), "+40.68917-074.04444-12.346/");
+
+        // Rounding carries over to minutes & degrees
+        let gps = GPSInfo::from_decimal(9.999_999_999, 0.0, 0.0).unwrap();
+        assert_eq!(gps.latitude, [(10, 1), (0, 1), (0, 10_000)].into());
+
+        GPSInfo::from_decimal(90.1, 0.0, 0.0).unwrap_err();
+        GPSInfo::from_decimal(0.0, -180.1, 0.0).unwrap_err();
+        GPSInfo::from_decimal(f64::NAN, 0.0, 0.0).unwrap_err();
+        GPSInfo::from_decimal(0.0, 0.0, f64::INFINITY).unwrap_err();
+    }
+
+    #[test]
+    fn gps_to_decimal() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        for (lat, lon, alt) in [
+            (40.689167, -74.044444, -12.346),
+            (-33.8568, 151.2153, 5.5),
+            (0.0, 0.0, 0.0),
+        ] {
+            let (latitude, longitude, altitude) =
+                GPSInfo::from_decimal(lat, lon, alt).unwrap().to_decimal();
+            assert!((latitude - lat).abs() < 1e-6, "{latitude}");
+            assert!((longitude - lon).abs() < 1e-6, "{longitude}");
+            assert_eq!(altitude, Some(alt));
+        }
+
+        // Unknown altitude
+        let gps = GPSInfo::from_iso6709("+4051-07402/").unwrap();
+        let (latitude, longitude, altitude) = gps.to_decimal();
+        assert!((latitude - 40.85).abs() < 1e-6, "{latitude}");
+        assert!((longitude - -74.033333).abs() < 1e-6, "{longitude}");
+        assert_eq!(altitude, None);
+    }
+
+    #[cfg(feature = "geo-types")]
+    #[test]
+    fn gps_geo_types() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let gps = GPSInfo::from_decimal(-33.8568, 151.2153, 5.5).unwrap();
+        let point: geo_types::Point<f64> = gps.into();
+        assert!((point.x() - 151.2153).abs() < 1e-6, "{point:?}");
+        assert!((point.y() - -33.8568).abs() < 1e-6, "{point:?}");
+    }
+
+    #[test_case("+27.1281+100.2508+000.000/", "+27.12810+100.25080/")]
+    #[test_case("-33.8568+151.2153+5.500/", "-33.85680+151.21530+5.500/")]
+    #[test_case("+40.68917-074.04444-12.5CRSWGS_84/", "+40.68917-074.04444-12.500/")]
+    #[test_case("+4051-07402/", "+40.85000-074.03333/")]
+    #[test_case("+405108.5-0740200/", "+40.85236-074.03333/")]
+    fn gps_from_iso6709(s: &str, formatted: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let gps = GPSInfo::from_iso6709(s).unwrap();
+        assert_eq!(gps.format_iso6709(), formatted);
+    }
+
+    #[test_case("")]
+    #[test_case("27.1281+100.2508/")]
+    #[test_case("+27.1281/")]
+    #[test_case("+127.1281+100.2508/")]
+    #[test_case("+27.12a1+100.2508/")]
+    #[test_case("+95.0+100.0/")]
+    fn gps_from_iso6709_invalid(s: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        GPSInfo::from_iso6709(s).unwrap_err();
+    }
 }
@@ -1,31 +1,190 @@
-use crate::EntryValue;
-use std::collections::HashMap;
+use crate::{prelude::*, EntryValue, ExifTag};
+use alloc::collections::{BTreeMap, BTreeSet};
 
 /// https://www.media.mit.edu/pia/Research/deepview/exif.html
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct ParsedImageFileDirectory {
-    pub entries: HashMap<u16, ParsedIdfEntry>,
+    /// Entries of this IFD and its sub-IFDs.
+    pub entries: BTreeMap<(IfdKind, u16), ParsedIdfEntry>,
 }
 
 impl ParsedImageFileDirectory {
     pub fn new() -> Self {
         Self {
-            entries: HashMap::new(),
+            entries: BTreeMap::new(),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct ParsedIdfEntry {
-    pub value: EntryValue,
+    /// Values of all occurrences of the tag, in parsing order. Only broken
+    /// files have more than one.
+    pub values: Vec<EntryValue>,
 }
 
 impl ParsedImageFileDirectory {
-    pub(crate) fn get(&self, tag: u16) -> Option<&EntryValue> {
-        self.entries.get(&tag).map(|x| &x.value)
+    /// Looks `tag` up in this IFD, then in its sub-IFDs, and picks one of
+    /// the occurrences according to `policy`.
+    pub(crate) fn get(&self, tag: u16, policy: DuplicatePolicy) -> Option<&EntryValue> {
+        policy.pick(self.get_all(tag))
     }
 
-    pub(crate) fn put(&mut self, code: u16, v: EntryValue) {
-        self.entries.insert(code, ParsedIdfEntry { value: v });
+    /// Like [`Self::get`], for a recognized `tag`, see [`Self::get_all_tag`].
+    pub(crate) fn get_tag(&self, tag: ExifTag, policy: DuplicatePolicy) -> Option<&EntryValue> {
+        policy.pick(self.get_all_tag(tag))
+    }
+
+    /// Returns all occurrences of `tag` in this IFD and its sub-IFDs.
+    pub(crate) fn get_all(&self, tag: u16) -> impl Iterator<Item = &EntryValue> {
+        self.get_all_in(
+            &[IfdKind::Ifd0, IfdKind::Ifd1, IfdKind::Exif, IfdKind::Gps],
+            tag,
+        )
+    }
+
+    /// Like [`Self::get_all`], for a recognized `tag`: the tags of the
+    /// Interoperability IFD are looked up in that IFD only, since their
+    /// codes overlap with the ones of the GPS IFD.
+    pub(crate) fn get_all_tag(&self, tag: ExifTag) -> impl Iterator<Item = &EntryValue> {
+        match IfdKind::of_tag(tag) {
+            IfdKind::Interop => self.get_all_in(&[IfdKind::Interop], tag.code()),
+            _ => self.get_all_in(
+                &[IfdKind::Ifd0, IfdKind::Ifd1, IfdKind::Exif, IfdKind::Gps],
+                tag.code(),
+            ),
+        }
+    }
+
+    fn get_all_in<'a>(
+        &'a self,
+        kinds: &'static [IfdKind],
+        tag: u16,
+    ) -> impl Iterator<Item = &'a EntryValue> {
+        kinds
+            .iter()
+            .filter_map(move |kind| self.entries.get(&(*kind, tag)))
+            .flat_map(|x| x.values.iter())
+    }
+
+    /// Returns the codes of the tags in this IFD and its sub-IFDs (the ones
+    /// looked up by [`Self::get`]), sorted.
+    pub(crate) fn tag_codes(&self) -> BTreeSet<u16> {
+        self.entries
+            .keys()
+            .filter(|(kind, _)| {
+                matches!(
+                    kind,
+                    IfdKind::Ifd0 | IfdKind::Ifd1 | IfdKind::Exif | IfdKind::Gps
+                )
+            })
+            .map(|(_, code)| *code)
+            .collect()
+    }
+
+    pub(crate) fn get_raw(
+        &self,
+        kind: IfdKind,
+        tag: u16,
+        policy: DuplicatePolicy,
+    ) -> Option<&EntryValue> {
+        policy.pick(self.entries.get(&(kind, tag))?.values.iter())
+    }
+
+    pub(crate) fn put(&mut self, kind: IfdKind, code: u16, v: EntryValue) {
+        self.entries
+            .entry((kind, code))
+            .or_insert_with(|| ParsedIdfEntry { values: Vec::new() })
+            .values
+            .push(v);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ParsedImageFileDirectory {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut keys = self.entries.keys().collect::<Vec<_>>();
+        keys.sort_by_key(|(kind, code)| (*code, *kind));
+        let mut map = serializer.serialize_map(Some(keys.len()))?;
+        for key in keys {
+            let tag = super::tags::ExifTagCode::in_ifd(key.0, key.1);
+            map.serialize_entry(&format!("{tag:?}"), &self.entries[key].values[0])?;
+        }
+        map.end()
+    }
+}
+
+/// Decides which value [`Exif::get`](crate::Exif::get) returns for a tag
+/// which occurs more than once, e.g. in both IFD0 and the Exif sub-IFD, or
+/// repeated within one IFD. Only broken files contain such duplicates, see
+/// [`Exif::get_all`](crate::Exif::get_all) to inspect them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Use the first occurrence (the default).
+    #[default]
+    First,
+    /// Use the last occurrence.
+    Last,
+    /// Treat the tag as missing, so that an ambiguous value is never used.
+    Error,
+}
+
+impl DuplicatePolicy {
+    fn pick<'a>(self, mut values: impl Iterator<Item = &'a EntryValue>) -> Option<&'a EntryValue> {
+        match self {
+            DuplicatePolicy::First => values.next(),
+            DuplicatePolicy::Last => values.last(),
+            DuplicatePolicy::Error => {
+                let first = values.next();
+                if values.next().is_some() {
+                    None
+                } else {
+                    first
+                }
+            }
+        }
+    }
+}
+
+/// Identifies an image file directory (IFD) within the Exif data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IfdKind {
+    /// IFD0, which describes the main image.
+    Ifd0,
+    /// IFD1, which usually describes the embedded thumbnail image.
+    Ifd1,
+    /// Exif sub-IFD, pointed to by [`ExifTag::ExifOffset`].
+    Exif,
+    /// GPS sub-IFD, pointed to by [`ExifTag::GPSInfo`].
+    Gps,
+    /// Interoperability sub-IFD, pointed to from the Exif sub-IFD.
+    Interop,
+    /// The n-th sub-image IFD of TIFF/DNG files, pointed to by the
+    /// `SubIFDs` (0x014a) tag, e.g. the full-resolution raw image of a DNG.
+    ///
+    /// These can only be read, the writing functions don't support them.
+    SubIfd(u8),
+}
+
+impl IfdKind {
+    /// Returns the IFD where `tag` is conventionally stored for the main
+    /// image.
+    pub(crate) fn of_tag(tag: ExifTag) -> IfdKind {
+        let code = tag.code();
+        if matches!(tag, ExifTag::InteropIndex | ExifTag::InteropVersion) {
+            IfdKind::Interop
+        } else if code <= ExifTag::GPSHPositioningError.code() {
+            // All recognized tags in this range are GPS tags.
+            IfdKind::Gps
+        } else if code >= ExifTag::ExposureTime.code()
+            && tag != ExifTag::ExifOffset
+            && tag != ExifTag::GPSInfo
+        {
+            IfdKind::Exif
+        } else {
+            IfdKind::Ifd0
+        }
     }
 }
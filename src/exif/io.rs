@@ -1,133 +1,186 @@
 use crate::slice::SubsliceRange;
-use crate::{error::convert_parse_error, input::Input, FileFormat};
+use crate::{error::convert_parse_error, prelude::*, Error::*, FileFormat};
+#[cfg(feature = "std")]
+use crate::{input::Input, media::MIN_GROW_SIZE, MediaParser, Warning};
+use core::ops::Range;
+#[cfg(feature = "std")]
 use nom::Needed;
-use std::{cmp, io::Read};
+#[cfg(feature = "std")]
+use std::io::Read;
+
+#[cfg(any(feature = "async", feature = "futures-io"))]
+use crate::async_io::AsyncSource;
 
 /// Read exif data from `reader`, if `format` is None, then guess the file
-/// format based on the read content.
+/// format based on the read content. The buffer sizes are limited by
+/// `parser`.
+#[cfg(feature = "std")]
 #[tracing::instrument(skip(reader))]
 pub(crate) fn read_exif<T: Read>(
-    mut reader: T,
+    reader: T,
+    format: Option<FileFormat>,
+    parser: &MediaParser,
+) -> crate::Result<Option<Input<'static>>> {
+    read_exif_impl(reader, format, parser, None)
+}
+
+/// Like [`read_exif`], but if the file ends within the exif data, the
+/// available part of it is returned (if it can be found), and a
+/// [`Warning::Truncated`] is pushed to `warnings`.
+#[cfg(feature = "std")]
+pub(crate) fn read_exif_lenient<T: Read>(
+    reader: T,
     format: Option<FileFormat>,
+    parser: &MediaParser,
+    warnings: &mut Vec<Warning>,
 ) -> crate::Result<Option<Input<'static>>> {
-    const INIT_BUF_SIZE: usize = 4096;
-    const MIN_GROW_SIZE: usize = 4096;
-    const MAX_GROW_SIZE: usize = 1000 * 4096;
+    read_exif_impl(reader, format, parser, Some(warnings))
+}
 
-    let mut buf = Vec::with_capacity(INIT_BUF_SIZE);
+#[cfg(feature = "std")]
+fn read_exif_impl<T: Read>(
+    mut reader: T,
+    format: Option<FileFormat>,
+    parser: &MediaParser,
+    warnings: Option<&mut Vec<Warning>>,
+) -> crate::Result<Option<Input<'static>>> {
+    let init_size = parser.init_read_size();
+    let mut buf = parser.alloc_buffer(init_size);
     let n = reader
         .by_ref()
-        .take(INIT_BUF_SIZE as u64)
+        .take(init_size as u64)
         .read_to_end(buf.as_mut())?;
+    parser.stats.read(n, buf.len());
     if n == 0 {
-        Err("file is empty")?;
+        Err(Truncated { needed: None })?;
     }
 
-    let ff = match format {
-        Some(ff) => {
-            ff.check(&buf)?;
-            ff
-        }
-        None => buf
-            .as_slice()
-            .try_into()
-            .map_err(|_| "unrecognized file format")?,
-    };
+    let ff = exif_format(&buf, format)?;
 
     let exif_data = loop {
-        let to_read = match ff.extract_exif_data(&buf[..]) {
+        let needed = match ff.extract_exif_data(&buf[..]) {
             Ok((_, data)) => break data,
             Err(nom::Err::Incomplete(needed)) => match needed {
-                Needed::Unknown => MIN_GROW_SIZE,
-                Needed::Size(n) => n.get(),
+                Needed::Unknown => None,
+                Needed::Size(n) => Some(n.get()),
             },
             Err(err) => return Err(convert_parse_error(err, "read exif failed")),
         };
+        let to_read = needed.unwrap_or(MIN_GROW_SIZE);
 
         tracing::debug!(bytes = ?to_read, "to_read");
         assert!(to_read > 0);
 
-        let to_read = cmp::max(MIN_GROW_SIZE, to_read);
-        let to_read = cmp::min(MAX_GROW_SIZE, to_read);
-        buf.reserve(to_read);
+        let to_read = parser.grow_size(buf.len(), to_read)?;
+        parser.reserve_buffer(&mut buf, to_read);
 
         let n = reader
             .by_ref()
             .take(to_read as u64)
             .read_to_end(buf.as_mut())?;
+        parser.stats.read(n, buf.len());
         if n == 0 {
-            return Err("read exif failed; not enough bytes".into());
+            let partial = ff.extract_partial_exif_data(&buf[..]);
+            match (warnings, partial) {
+                (Some(warnings), Some(data)) => {
+                    warnings.push(Warning::Truncated { needed });
+                    break Some(data);
+                }
+                _ => return Err(Truncated { needed }),
+            }
         }
     };
 
     Ok(exif_data
-        .and_then(|x| buf.subslice_range(x))
-        .map(|x| Input::from_vec_range(buf, x)))
+        .and_then(|x| SubsliceRange::subslice_range(buf.as_slice(), x))
+        .map(|x| parser.buffer_to_input(buf, x)))
 }
 
-#[cfg(feature = "async")]
-use tokio::io::AsyncRead;
-#[cfg(feature = "async")]
-use tokio::io::AsyncReadExt;
-
-/// Read exif data from `reader`, if `format` is None, then guess the file
-/// format based on the read content.
-#[cfg(feature = "async")]
-#[tracing::instrument(skip(reader))]
-pub(crate) async fn read_exif_async<T>(
-    mut reader: T,
+/// Find exif data in `data`, which holds the whole file (or at least all of
+/// it up to the end of the exif data), and return its range. If `format` is
+/// None, then guess the file format based on the content.
+pub(crate) fn exif_range(
+    data: &[u8],
     format: Option<FileFormat>,
-) -> crate::Result<Option<Input<'static>>>
-where
-    T: AsyncRead + std::marker::Unpin,
-{
-    use std::ops::Deref;
-    const INIT_BUF_SIZE: usize = 4096;
-    const MIN_GROW_SIZE: usize = 4096;
-    const MAX_GROW_SIZE: usize = 1000 * 4096;
-
-    let mut buf = Vec::with_capacity(INIT_BUF_SIZE);
-
-    let n = reader.read_buf(&mut buf).await?;
-    if n == 0 {
-        Err("file is empty")?;
+) -> crate::Result<Option<Range<usize>>> {
+    if data.is_empty() {
+        Err(Truncated { needed: None })?;
     }
 
+    let ff = exif_format(data, format)?;
+    match ff.extract_exif_data(data) {
+        Ok((_, exif_data)) => Ok(exif_data.and_then(|x| SubsliceRange::subslice_range(data, x))),
+        Err(err) => Err(convert_parse_error(err, "read exif failed")),
+    }
+}
+
+/// Detects the file format of `buf` (the beginning of a file), or checks it
+/// if `format` is given, and rejects formats which don't contain Exif data.
+fn exif_format(buf: &[u8], format: Option<FileFormat>) -> crate::Result<FileFormat> {
     let ff = match format {
         Some(ff) => {
-            ff.check(&buf)?;
+            ff.check(buf).map_err(|_| UnsupportedFormat {
+                detected: FileFormat::try_from(buf).ok().map(|x| x.to_string()),
+            })?;
             ff
         }
-        None => buf
-            .deref()
-            .try_into()
-            .map_err(|_| "unrecognized file format")?,
+        None => buf.try_into()?,
     };
+    match ff {
+        FileFormat::QuickTime | FileFormat::MP4 | FileFormat::Matroska => Err(UnsupportedFormat {
+            detected: Some(ff.to_string()),
+        }),
+        ff => Ok(ff),
+    }
+}
+
+/// `async` version of [`read_exif`]. `head` is the start of the file, which
+/// has already been read.
+#[cfg(any(feature = "async", feature = "futures-io"))]
+#[tracing::instrument(skip(reader, head))]
+pub(crate) async fn read_exif_async<T: AsyncSource>(
+    mut reader: T,
+    format: Option<FileFormat>,
+    parser: &MediaParser,
+    head: &[u8],
+) -> crate::Result<Option<Input<'static>>> {
+    let init_size = parser.init_read_size().max(head.len());
+    let mut buf = parser.alloc_buffer(init_size);
+    buf.extend_from_slice(head);
+
+    let n = reader.read_to_buf(&mut buf, init_size - head.len()).await?;
+    parser.stats.read(n + head.len(), buf.len());
+    if buf.is_empty() {
+        Err(Truncated { needed: None })?;
+    }
+
+    let ff = exif_format(&buf, format)?;
 
     let exif_data = loop {
-        let to_read = match ff.extract_exif_data(&buf[..]) {
+        let needed = match ff.extract_exif_data(&buf[..]) {
             Ok((_, data)) => break data,
             Err(nom::Err::Incomplete(needed)) => match needed {
-                Needed::Unknown => MIN_GROW_SIZE,
-                Needed::Size(n) => n.get(),
+                Needed::Unknown => None,
+                Needed::Size(n) => Some(n.get()),
             },
             Err(err) => return Err(convert_parse_error(err, "read exif failed")),
         };
+        let to_read = needed.unwrap_or(MIN_GROW_SIZE);
 
         tracing::debug!(bytes = ?to_read, "to_read");
         assert!(to_read > 0);
 
-        let to_read = cmp::max(MIN_GROW_SIZE, to_read);
-        let to_read = cmp::min(MAX_GROW_SIZE, to_read);
-        buf.reserve(to_read);
+        let to_read = parser.grow_size(buf.len(), to_read)?;
+        parser.reserve_buffer(&mut buf, to_read);
 
-        let n = reader.read_buf(&mut buf).await?;
+        let n = reader.read_to_buf(&mut buf, to_read).await?;
+        parser.stats.read(n, buf.len());
         if n == 0 {
-            return Err("read exif failed; not enough bytes".into());
+            return Err(Truncated { needed });
         }
     };
 
     Ok(exif_data
-        .and_then(|x| buf.subslice_range(x))
-        .map(|x| Input::from_vec_range(buf, x)))
+        .and_then(|x| SubsliceRange::subslice_range(buf.as_slice(), x))
+        .map(|x| parser.buffer_to_input(buf, x)))
 }
@@ -0,0 +1,381 @@
+//! Resolution of the lens information, which is spread over several standard
+//! tags and vendor-specific MakerNote data.
+
+use nom::number::{complete, Endianness};
+
+use crate::{prelude::*, EntryValue, ExifTag};
+
+use super::{Exif, ExifIter};
+
+/// Information about the lens an image was taken with, see
+/// [`Exif::lens_info`].
+///
+/// Focal lengths are in millimeters. Apertures are f-numbers, i.e. the
+/// maximum aperture (smallest f-number) of the lens at its minimum and
+/// maximum focal lengths.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LensInfo {
+    /// From `LensMake`.
+    pub make: Option<String>,
+
+    /// From `LensModel`, or the vendor lens ID (requires the `lens-db`
+    /// feature).
+    pub model: Option<String>,
+
+    /// From `LensSerialNumber`.
+    pub serial_number: Option<String>,
+
+    pub min_focal_length: Option<f64>,
+
+    pub max_focal_length: Option<f64>,
+
+    /// Maximum aperture at the minimum focal length.
+    pub min_focal_length_aperture: Option<f64>,
+
+    /// Maximum aperture at the maximum focal length.
+    pub max_focal_length_aperture: Option<f64>,
+
+    /// The vendor-specific lens ID found in the MakerNote, only Canon's
+    /// `LensType` is supported at present.
+    pub lens_id: Option<u16>,
+}
+
+/// Lens information found in a MakerNote.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MakerNoteLens {
+    id: Option<u16>,
+    focal_range: Option<(f64, f64)>,
+}
+
+impl Exif {
+    /// Resolves the lens information from `LensSpecification` (`LensInfo`
+    /// in exiftool), `LensMake`, `LensModel`, `LensSerialNumber` and the
+    /// lens ID of the MakerNote.
+    ///
+    /// Missing focal lengths and apertures are taken from the lens name
+    /// (e.g. `EF 24-70mm f/2.8L USM`) or the MakerNote if possible.
+    ///
+    /// With the `lens-db` feature, the name of the lens is looked up by its
+    /// MakerNote lens ID when `LensModel` is missing. Note that third-party
+    /// lenses may report the ID of another lens.
+    ///
+    /// Returns `None` if there isn't any lens information.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.heic").unwrap();
+    /// let exif: Exif = parse_exif(f, None).unwrap().unwrap().into();
+    ///
+    /// let lens = exif.lens_info().unwrap();
+    /// assert_eq!(lens.make.as_deref(), Some("Apple"));
+    /// assert_eq!(lens.max_focal_length, Some(6.0));
+    /// assert_eq!(lens.min_focal_length_aperture, Some(1.6));
+    /// ```
+    pub fn lens_info(&self) -> Option<LensInfo> {
+        let text = |tag| {
+            self.get(tag)
+                .and_then(EntryValue::as_str)
+                .map(|x| x.trim().to_owned())
+                .filter(|x| !x.is_empty())
+        };
+
+        let maker_note = self.maker_note_lens.as_ref();
+        let lens_id = maker_note.and_then(|x| x.id);
+        let mut info = LensInfo {
+            make: text(ExifTag::LensMake),
+            model: text(ExifTag::LensModel),
+            serial_number: text(ExifTag::LensSerialNumber),
+            lens_id,
+            ..LensInfo::default()
+        };
+
+        #[cfg(feature = "lens-db")]
+        if info.model.is_none() {
+            info.model = lens_id
+                .and_then(super::lens_db::canon_lens_name)
+                .map(|x| x.to_owned());
+        }
+
+        if let Some(spec) = self.get(ExifTag::LensSpecification) {
+            info.set_specification(spec);
+        }
+        if let Some(spec) = info.model.as_deref().and_then(parse_lens_name) {
+            info.fill([Some(spec.0), Some(spec.1), Some(spec.2), spec.3]);
+        }
+        if let Some((min, max)) = maker_note.and_then(|x| x.focal_range) {
+            info.fill([Some(min), Some(max), None, None]);
+        }
+
+        (info != LensInfo::default()).then_some(info)
+    }
+}
+
+impl LensInfo {
+    /// Sets the focal lengths and apertures from a `LensSpecification`
+    /// value, whose unknown components are 0/0.
+    fn set_specification(&mut self, spec: &EntryValue) {
+        let Some(values) = spec.as_urational_array() else {
+            return;
+        };
+        let mut values = values.iter().map(|x| x.to_f64().filter(|x| *x != 0.0));
+        let mut next = || values.next().flatten();
+        self.fill([next(), next(), next(), next()]);
+    }
+
+    /// Sets the fields which are still missing, from `[min focal length,
+    /// max focal length, min focal length aperture, max focal length
+    /// aperture]`.
+    fn fill(&mut self, values: [Option<f64>; 4]) {
+        let fields = [
+            &mut self.min_focal_length,
+            &mut self.max_focal_length,
+            &mut self.min_focal_length_aperture,
+            &mut self.max_focal_length_aperture,
+        ];
+        for (field, value) in fields.into_iter().zip(values) {
+            if field.is_none() {
+                *field = value;
+            }
+        }
+    }
+}
+
+/// Parses the focal lengths and apertures from a lens name, e.g. `EF-S
+/// 18-55mm f/3.5-5.6 IS` or `iPhone 12 Pro back camera 4.2mm f/1.6`.
+fn parse_lens_name(name: &str) -> Option<(f64, f64, f64, Option<f64>)> {
+    // The first match of `(\d+(\.\d+)?)(-\d+(\.\d+)?)?\s*mm\s*f/?(\d+(\.\d+)?)(-\d+(\.\d+)?)?`,
+    // ignoring case
+    name.char_indices()
+        .find_map(|(i, _)| parse_lens_spec(&name[i..]))
+}
+
+fn parse_lens_spec(s: &str) -> Option<(f64, f64, f64, Option<f64>)> {
+    let (min_focal_length, s) = parse_number(s)?;
+    let (max_focal_length, s) = match s.strip_prefix('-').and_then(parse_number) {
+        Some((n, rest)) => (Some(n), rest),
+        None => (None, s),
+    };
+    let s = s.trim_start();
+    let s = s
+        .get(..2)
+        .filter(|x| x.eq_ignore_ascii_case("mm"))
+        .map(|_| &s[2..])?;
+    let s = s.trim_start();
+    let s = s.strip_prefix(['f', 'F'])?;
+    let (aperture, s) = parse_number(s.strip_prefix('/').unwrap_or(s))?;
+    let max_aperture = s.strip_prefix('-').and_then(parse_number).map(|x| x.0);
+
+    match max_focal_length {
+        // A zoom lens, whose aperture may vary
+        Some(max_focal_length) => Some((
+            min_focal_length,
+            max_focal_length,
+            aperture,
+            Some(max_aperture.unwrap_or(aperture)),
+        )),
+        None => Some((min_focal_length, min_focal_length, aperture, Some(aperture))),
+    }
+}
+
+/// Parses a decimal number, e.g. `3.5`, at the start of `s`, and returns it
+/// with the rest of `s`.
+fn parse_number(s: &str) -> Option<(f64, &str)> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let mut len = digits(s);
+    if len == 0 {
+        return None;
+    }
+    if let Some(fraction) = s[len..].strip_prefix('.') {
+        let n = digits(fraction);
+        if n > 0 {
+            len += 1 + n;
+        }
+    }
+    Some((s[..len].parse().ok()?, &s[len..]))
+}
+
+/// Tag of Canon's CameraSettings MakerNote entry.
+const CANON_CAMERA_SETTINGS: u16 = 0x0001;
+
+/// Finds the lens information in the MakerNote of `iter`, only Canon's
+/// format is supported at present.
+pub(crate) fn parse_maker_note_lens(iter: &ExifIter) -> Option<MakerNoteLens> {
+    let make = iter.clone().find(|x| x.tag() == Some(ExifTag::Make))?;
+    if !make.take_value()?.as_str()?.starts_with("Canon") {
+        return None;
+    }
+
+    // Canon's MakerNote is an IFD without header, whose offsets are
+    // relative to the TIFF header, like the ones of the standard IFDs
+    let (data, endian, pos) = iter.find_value_in_tiff(ExifTag::MakerNote)?;
+    let settings = find_ifd_shorts(data, endian, pos, CANON_CAMERA_SETTINGS)?;
+
+    // LensType, MaxFocalLength, MinFocalLength and FocalUnits
+    let get = |i: usize| settings.get(i).copied();
+    let id = get(22).filter(|x| *x != 0 && *x != 0xffff);
+    let focal_units = get(25).filter(|x| *x != 0).unwrap_or(1) as f64;
+    let focal_range = match (get(24), get(23)) {
+        (Some(min), Some(max)) if min > 0 && max >= min => {
+            Some((min as f64 / focal_units, max as f64 / focal_units))
+        }
+        _ => None,
+    };
+    Some(MakerNoteLens { id, focal_range })
+}
+
+/// Returns the values of the SHORT entry `tag` of the IFD at `pos`.
+fn find_ifd_shorts(data: &[u8], endian: Endianness, pos: usize, tag: u16) -> Option<Vec<u16>> {
+    const SHORT: u16 = 3;
+    const ENTRY_SIZE: usize = 12;
+
+    let (_, num) = complete::u16::<_, ()>(endian)(data.get(pos..)?).ok()?;
+    let value = (0..num as usize).find_map(|i| {
+        let entry_pos = pos + 2 + i * ENTRY_SIZE;
+        let entry = data.get(entry_pos..entry_pos + ENTRY_SIZE)?;
+        let (_, (code, data_type, count, offset)) = nom::sequence::tuple((
+            complete::u16::<_, ()>(endian),
+            complete::u16(endian),
+            complete::u32(endian),
+            complete::u32(endian),
+        ))(entry)
+        .ok()?;
+        if code != tag || data_type != SHORT {
+            return None;
+        }
+
+        let size = count as usize * 2;
+        if size <= 4 {
+            // The value is stored in the entry
+            entry.get(8..8 + size)
+        } else {
+            let start = offset as usize;
+            data.get(start..start.checked_add(size)?)
+        }
+    })?;
+
+    Some(
+        value
+            .chunks_exact(2)
+            .map(|x| match endian {
+                Endianness::Little => u16::from_le_bytes([x[0], x[1]]),
+                _ => u16::from_be_bytes([x[0], x[1]]),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exif::input_to_exif, parse_exif, testkit::open_sample};
+    use test_case::test_case;
+
+    #[test_case("EF 24-70mm f/2.8L USM", (24.0, 70.0, 2.8, Some(2.8)))]
+    #[test_case("EF-S 18-55mm f/3.5-5.6 IS", (18.0, 55.0, 3.5, Some(5.6)))]
+    #[test_case("iPhone 12 Pro back triple camera 4.2mm f/1.6", (4.2, 4.2, 1.6, Some(1.6)))]
+    #[test_case("XF35mmF1.4 R", (35.0, 35.0, 1.4, Some(1.4)))]
+    #[test_case("Lens 70-200 MM F4-", (70.0, 200.0, 4.0, Some(4.0)))]
+    #[test_case("1.2.8mm f2", (2.8, 2.8, 2.0, Some(2.0)))]
+    fn lens_name(name: &str, expect: (f64, f64, f64, Option<f64>)) {
+        assert_eq!(parse_lens_name(name), Some(expect));
+    }
+
+    #[test_case("EF 50mm")]
+    #[test_case("f/1.8 50mm")]
+    #[test_case("50 f/1.8")]
+    fn lens_name_none(name: &str) {
+        assert_eq!(parse_lens_name(name), None);
+    }
+
+    #[test]
+    fn lens_info() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif: Exif = parse_exif(open_sample("exif.heic").unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        let lens = exif.lens_info().unwrap();
+        assert_eq!(
+            lens.model.as_deref(),
+            Some("iPhone 12 Pro back triple camera 4.2mm f/1.6")
+        );
+        // From LensSpecification rather than the name
+        assert_eq!(lens.min_focal_length, Some(807365.0 / 524263.0));
+        assert_eq!(lens.max_focal_length, Some(6.0));
+        assert_eq!(lens.max_focal_length_aperture, Some(2.4));
+        assert_eq!(lens.lens_id, None);
+
+        let exif: Exif = parse_exif(open_sample("exif.jpg").unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(exif.lens_info(), None);
+    }
+
+    /// Builds little-endian TIFF data with a `Make` of "Canon" and a Canon
+    /// MakerNote whose CameraSettings have the specified `lens_type` and
+    /// focal lengths.
+    fn canon_tiff(lens_type: u16, max_focal: u16, min_focal: u16) -> Vec<u8> {
+        fn entry(buf: &mut Vec<u8>, tag: u16, data_type: u16, count: u32, value: u32) {
+            buf.extend_from_slice(&tag.to_le_bytes());
+            buf.extend_from_slice(&data_type.to_le_bytes());
+            buf.extend_from_slice(&count.to_le_bytes());
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut settings = [0u16; 26];
+        settings[22] = lens_type;
+        settings[23] = max_focal;
+        settings[24] = min_focal;
+        settings[25] = 1;
+
+        // IFD0 at 8: Make, ExifOffset
+        let mut buf = b"II*\0\x08\0\0\0".to_vec();
+        buf.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut buf, 0x010f, 2, 6, 38);
+        entry(&mut buf, 0x8769, 4, 1, 44);
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(b"Canon\0");
+        // Exif IFD at 44: MakerNote
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut buf, 0x927c, 7, 18 + 52, 62);
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        // MakerNote at 62: CameraSettings
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut buf, 0x0001, 3, 26, 80);
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(buf.len(), 80);
+        for v in settings {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn canon_maker_note() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif = input_to_exif(canon_tiff(230, 70, 24)).unwrap();
+        let lens = exif.lens_info().unwrap();
+        assert_eq!(lens.lens_id, Some(230));
+        assert_eq!(lens.min_focal_length, Some(24.0));
+        assert_eq!(lens.max_focal_length, Some(70.0));
+
+        #[cfg(feature = "lens-db")]
+        {
+            assert_eq!(lens.model.as_deref(), Some("Canon EF 24-70mm f/2.8L USM"));
+            assert_eq!(lens.min_focal_length_aperture, Some(2.8));
+        }
+        #[cfg(not(feature = "lens-db"))]
+        assert_eq!(lens.model, None);
+
+        // Unknown lens type
+        let exif = input_to_exif(canon_tiff(0xffff, 0, 0)).unwrap();
+        assert_eq!(exif.lens_info(), None);
+    }
+}
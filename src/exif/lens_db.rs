@@ -0,0 +1,117 @@
+//! Names of vendor lens IDs found in MakerNotes, enabled by the `lens-db`
+//! feature.
+//!
+//! Several lenses (mostly third-party ones) may share an ID, the most common
+//! one is listed.
+
+/// Canon `LensType` IDs (CameraSettings index 22) of EF/EF-S lenses.
+const CANON_LENSES: &[(u16, &str)] = &[
+    (1, "Canon EF 50mm f/1.8"),
+    (2, "Canon EF 28mm f/2.8"),
+    (3, "Canon EF 135mm f/2.8 Soft"),
+    (10, "Canon EF 50mm f/2.5 Macro"),
+    (11, "Canon EF 35mm f/2"),
+    (13, "Canon EF 15mm f/2.8 Fisheye"),
+    (26, "Canon EF 100mm f/2.8 Macro"),
+    (29, "Canon EF 50mm f/1.8 II"),
+    (32, "Canon EF 24mm f/2.8"),
+    (45, "Canon EF-S 18-55mm f/3.5-5.6"),
+    (48, "Canon EF-S 18-55mm f/3.5-5.6 IS"),
+    (49, "Canon EF-S 55-250mm f/4-5.6 IS"),
+    (50, "Canon EF-S 18-200mm f/3.5-5.6 IS"),
+    (51, "Canon EF-S 18-135mm f/3.5-5.6 IS"),
+    (52, "Canon EF-S 18-55mm f/3.5-5.6 IS II"),
+    (124, "Canon MP-E 65mm f/2.8 1-5x Macro Photo"),
+    (125, "Canon TS-E 24mm f/3.5L"),
+    (126, "Canon TS-E 45mm f/2.8"),
+    (127, "Canon TS-E 90mm f/2.8"),
+    (129, "Canon EF 300mm f/2.8L USM"),
+    (130, "Canon EF 50mm f/1.0L USM"),
+    (131, "Canon EF 28-80mm f/2.8-4L USM"),
+    (132, "Canon EF 1200mm f/5.6L USM"),
+    (134, "Canon EF 600mm f/4L IS USM"),
+    (135, "Canon EF 200mm f/1.8L USM"),
+    (136, "Canon EF 300mm f/2.8L USM"),
+    (137, "Canon EF 85mm f/1.2L USM"),
+    (139, "Canon EF 400mm f/2.8L USM"),
+    (141, "Canon EF 500mm f/4.5L USM"),
+    (142, "Canon EF 300mm f/2.8L IS USM"),
+    (143, "Canon EF 500mm f/4L IS USM"),
+    (149, "Canon EF 100mm f/2 USM"),
+    (150, "Canon EF 14mm f/2.8L USM"),
+    (151, "Canon EF 200mm f/2.8L USM"),
+    (152, "Canon EF 300mm f/4L IS USM"),
+    (153, "Canon EF 35-350mm f/3.5-5.6L USM"),
+    (154, "Canon EF 20mm f/2.8 USM"),
+    (155, "Canon EF 85mm f/1.8 USM"),
+    (156, "Canon EF 28-105mm f/3.5-4.5 USM"),
+    (160, "Canon EF 20-35mm f/3.5-4.5 USM"),
+    (161, "Canon EF 28-70mm f/2.8L USM"),
+    (162, "Canon EF 200mm f/2.8L USM"),
+    (163, "Canon EF 300mm f/4L"),
+    (164, "Canon EF 400mm f/5.6L"),
+    (165, "Canon EF 70-200mm f/2.8L USM"),
+    (168, "Canon EF 28mm f/1.8 USM"),
+    (169, "Canon EF 17-35mm f/2.8L USM"),
+    (170, "Canon EF 200mm f/2.8L II USM"),
+    (172, "Canon EF 400mm f/5.6L USM"),
+    (173, "Canon EF 180mm Macro f/3.5L USM"),
+    (174, "Canon EF 135mm f/2L USM"),
+    (175, "Canon EF 400mm f/2.8L USM"),
+    (176, "Canon EF 24-85mm f/3.5-4.5 USM"),
+    (177, "Canon EF 300mm f/4L IS USM"),
+    (178, "Canon EF 28-135mm f/3.5-5.6 IS"),
+    (179, "Canon EF 24mm f/1.4L USM"),
+    (180, "Canon EF 35mm f/1.4L USM"),
+    (183, "Canon EF 100-400mm f/4.5-5.6L IS USM"),
+    (186, "Canon EF 70-200mm f/4L USM"),
+    (190, "Canon EF 100mm f/2.8 Macro USM"),
+    (191, "Canon EF 400mm f/4 DO IS USM"),
+    (197, "Canon EF 75-300mm f/4-5.6 IS USM"),
+    (198, "Canon EF 50mm f/1.4 USM"),
+    (224, "Canon EF 70-200mm f/2.8L IS USM"),
+    (229, "Canon EF 16-35mm f/2.8L USM"),
+    (230, "Canon EF 24-70mm f/2.8L USM"),
+    (231, "Canon EF 17-40mm f/4L USM"),
+    (232, "Canon EF 70-300mm f/4.5-5.6 DO IS USM"),
+    (234, "Canon EF-S 17-85mm f/4-5.6 IS USM"),
+    (235, "Canon EF-S 10-22mm f/3.5-4.5 USM"),
+    (236, "Canon EF-S 60mm f/2.8 Macro USM"),
+    (237, "Canon EF 24-105mm f/4L IS USM"),
+    (238, "Canon EF 70-300mm f/4-5.6 IS USM"),
+    (239, "Canon EF 85mm f/1.2L II USM"),
+    (240, "Canon EF-S 17-55mm f/2.8 IS USM"),
+    (241, "Canon EF 50mm f/1.2L USM"),
+    (242, "Canon EF 70-200mm f/4L IS USM"),
+    (246, "Canon EF 16-35mm f/2.8L II USM"),
+    (247, "Canon EF 14mm f/2.8L II USM"),
+    (248, "Canon EF 200mm f/2L IS USM"),
+    (249, "Canon EF 800mm f/5.6L IS USM"),
+    (250, "Canon EF 24mm f/1.4L II USM"),
+    (251, "Canon EF 70-200mm f/2.8L IS II USM"),
+    (254, "Canon EF 100mm f/2.8L Macro IS USM"),
+];
+
+/// Returns the name of the Canon lens with `LensType` `id`.
+pub(crate) fn canon_lens_name(id: u16) -> Option<&'static str> {
+    CANON_LENSES
+        .binary_search_by_key(&id, |x| x.0)
+        .ok()
+        .map(|i| CANON_LENSES[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canon_lenses() {
+        assert!(CANON_LENSES.windows(2).all(|x| x[0].0 < x[1].0));
+        assert_eq!(canon_lens_name(1), Some("Canon EF 50mm f/1.8"));
+        assert_eq!(
+            canon_lens_name(254),
+            Some("Canon EF 100mm f/2.8L Macro IS USM")
+        );
+        assert_eq!(canon_lens_name(0), None);
+    }
+}
@@ -10,26 +10,51 @@ use nom::{
     IResult,
 };
 
+use alloc::collections::BTreeMap;
+use chrono::{DateTime, FixedOffset, Timelike};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+#[cfg(feature = "std")]
+use crate::Warning;
 use crate::{
-    exif::{ExifTag, GPSInfo},
+    error::Diagnostics,
+    exif::{ExifTag, GPSInfo, IfdKind, Orientation},
     input::{self, Input},
+    prelude::*,
+    values::URational,
     EntryValue,
 };
 
+#[cfg(feature = "std")]
+use crate::visit::FilteredEntries;
+
 use super::{
     exif_iter::{ExifIter, ImageFileDirectoryIter, ParsedExifEntry},
-    ifd::ParsedImageFileDirectory,
+    ifd::{DuplicatePolicy, ParsedImageFileDirectory},
+    lens::{parse_maker_note_lens, MakerNoteLens},
 };
 
 /// Parses Exif information from the `input` TIFF data.
 pub(crate) fn input_to_iter<'a>(input: impl Into<input::Input<'a>>) -> crate::Result<ExifIter<'a>> {
+    input_to_iter_with_diagnostics(input, Diagnostics::default())
+}
+
+/// Like [`input_to_iter`], and records recoverable anomalies to
+/// `diagnostics`.
+pub(crate) fn input_to_iter_with_diagnostics<'a>(
+    input: impl Into<input::Input<'a>>,
+    diagnostics: Diagnostics,
+) -> crate::Result<ExifIter<'a>> {
     let input = input.into();
-    let parser = ExifParser::new(input);
+    let mut parser = ExifParser::new(input);
+    parser.inner.diagnostics = diagnostics;
     let iter: ExifIter<'a> = parser.parse_iter()?;
     Ok(iter)
 }
 
 /// Parses Exif information from the `input` TIFF data.
+#[cfg(feature = "std")]
 pub(crate) fn input_to_exif<'a>(input: impl Into<input::Input<'a>>) -> crate::Result<Exif> {
     Ok(input_to_iter(input)?.into())
 }
@@ -69,12 +94,14 @@ impl<'a> ExifParser<'a> {
 
 struct Inner<'a> {
     input: Input<'a>,
+    diagnostics: Diagnostics,
 }
 
 impl<'a> Inner<'a> {
     fn new(input: impl Into<input::Input<'a>>) -> Inner<'a> {
         Self {
             input: input.into(),
+            diagnostics: Diagnostics::default(),
         }
     }
 
@@ -90,20 +117,20 @@ impl<'a> Inner<'a> {
         }
 
         let pos = data.len() - remain.len();
-        let mut ifd0 = match ImageFileDirectoryIter::try_new(
+        let mut ifd0 = ImageFileDirectoryIter::try_new(
             0,
+            IfdKind::Ifd0,
             self.input.make_associated(data),
             pos,
             header.endian,
             None,
-        ) {
-            Ok(ifd0) => ifd0,
-            Err(e) => return Err(e),
-        };
+            &self.diagnostics,
+        )?;
 
         let tz = ifd0.find_tz_offset();
         ifd0.tz = tz.clone();
-        let iter: ExifIter<'a> = ExifIter::new(self.input, header.endian, tz, Some(ifd0));
+        let mut iter: ExifIter<'a> = ExifIter::new(self.input, header.endian, tz, Some(ifd0));
+        iter.diagnostics = self.diagnostics;
 
         Ok(iter)
     }
@@ -114,16 +141,43 @@ impl<'a> Inner<'a> {
 pub struct Exif {
     ifds: Vec<ParsedImageFileDirectory>,
     gps_info: Option<GPSInfo>,
+    duplicate_policy: DuplicatePolicy,
+    pub(crate) maker_note_lens: Option<MakerNoteLens>,
 }
 
 impl Exif {
-    fn new(gps_info: Option<GPSInfo>) -> Exif {
+    fn new(iter: &ExifIter<'_>, gps_info: Option<GPSInfo>) -> Exif {
         Exif {
             ifds: Vec::new(),
             gps_info,
+            duplicate_policy: DuplicatePolicy::default(),
+            maker_note_lens: parse_maker_note_lens(iter),
         }
     }
 
+    /// Sets which value the getters (e.g. [`Self::get`]) return for a tag
+    /// which occurs more than once, see [`DuplicatePolicy`].
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut exif: Exif = parse_exif(f, None).unwrap().unwrap().into();
+    /// exif.set_duplicate_policy(DuplicatePolicy::Error);
+    /// assert_eq!(exif.get(ExifTag::Make).unwrap().as_str(), Some("vivo"));
+    /// ```
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) -> &mut Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Returns the [`DuplicatePolicy`] of the getters.
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+
     /// Get entry value for the specified `tag` in ifd0 (the main image).
     ///
     /// *Note*:
@@ -138,8 +192,13 @@ impl Exif {
     /// - If you have any custom defined tag which does not exist in
     ///   [`ExifTag`], you can always get the entry value by a raw tag code,
     ///   see [`Self::get_by_tag_code`].
+    ///
+    /// - If the tag occurs more than once, which only happens in broken
+    ///   files, the value is picked according to
+    ///   [`Self::set_duplicate_policy`].
     pub fn get(&self, tag: ExifTag) -> Option<&EntryValue> {
-        self.get_by_tag_code(tag.code())
+        self.ifd0()
+            .and_then(|ifd0| ifd0.get_tag(tag, self.duplicate_policy))
     }
 
     /// Get entry value for the specified `tag` in ifd0 (the main image).
@@ -153,7 +212,131 @@ impl Exif {
     /// - If you want to handle parsing error, please consider to use
     ///   [`ExifIter`].
     pub fn get_by_tag_code(&self, tag: u16) -> Option<&EntryValue> {
-        self.ifd0().and_then(|ifd0| ifd0.get(tag))
+        self.ifd0()
+            .and_then(|ifd0| ifd0.get(tag, self.duplicate_policy))
+    }
+
+    /// Get all occurrences of `tag`, along with the index of the IFD they're
+    /// located in (0 for the main image, 1 for the thumbnail). Entries of
+    /// sub-IFDs belong to their parent IFD.
+    ///
+    /// Valid files have at most one occurrence in each IFD, but broken ones
+    /// may repeat a tag, e.g. in both IFD0 and the Exif sub-IFD. See
+    /// [`Self::set_duplicate_policy`] for which one [`Self::get`] returns.
+    pub fn get_all(&self, tag: ExifTag) -> Vec<(usize, &EntryValue)> {
+        self.ifds
+            .iter()
+            .enumerate()
+            .flat_map(|(index, ifd)| ifd.get_all_tag(tag).map(move |v| (index, v)))
+            .collect()
+    }
+
+    /// Get the value of `tag` as a `u32`, integers of other widths are
+    /// converted if they fit, see [`EntryValue::to_u32`].
+    pub fn get_u32(&self, tag: ExifTag) -> Option<u32> {
+        self.get(tag).and_then(EntryValue::to_u32)
+    }
+
+    /// Get the value of `tag` as a `f64`, any numeric value (including
+    /// rationals) is converted, see [`EntryValue::to_f64`].
+    pub fn get_f64(&self, tag: ExifTag) -> Option<f64> {
+        self.get(tag).and_then(EntryValue::to_f64)
+    }
+
+    /// Get the value of a date/time `tag`, e.g. [`ExifTag::DateTimeOriginal`].
+    pub fn get_datetime(&self, tag: ExifTag) -> Option<DateTime<FixedOffset>> {
+        self.get(tag).and_then(EntryValue::as_time)
+    }
+
+    /// Get the date/time when the image was taken, combined from
+    /// `DateTimeOriginal`, `SubSecTimeOriginal` (fractional seconds) and
+    /// `OffsetTimeOriginal` (time zone offset).
+    ///
+    /// Fallbacks:
+    ///
+    /// - If `DateTimeOriginal` is missing, `CreateDate` (DateTimeDigitized)
+    ///   and then `ModifyDate` are used, combined with their own sub-second
+    ///   and offset tags (e.g. `SubSecTimeDigitized` and
+    ///   `OffsetTimeDigitized`).
+    ///
+    /// - If the offset tag is missing or invalid, the offset which the time
+    ///   was parsed with is kept, i.e. the first offset tag found in the
+    ///   Exif data, or the local time zone if there is none.
+    ///
+    /// - If the sub-second tag is missing or invalid, the time has no
+    ///   fractional seconds.
+    pub fn datetime_original(&self) -> Option<DateTime<FixedOffset>> {
+        self.datetime_original_with_tag().map(|x| x.1)
+    }
+
+    /// Like [`Self::datetime_original`], along with the date/time tag which
+    /// has been used.
+    pub(crate) fn datetime_original_with_tag(&self) -> Option<(ExifTag, DateTime<FixedOffset>)> {
+        const TAGS: [(ExifTag, ExifTag, ExifTag); 3] = [
+            (
+                ExifTag::DateTimeOriginal,
+                ExifTag::SubSecTimeOriginal,
+                ExifTag::OffsetTimeOriginal,
+            ),
+            (
+                ExifTag::CreateDate,
+                ExifTag::SubSecTimeDigitized,
+                ExifTag::OffsetTimeDigitized,
+            ),
+            (
+                ExifTag::ModifyDate,
+                ExifTag::SubSecTime,
+                ExifTag::OffsetTime,
+            ),
+        ];
+
+        TAGS.iter().find_map(|&(time, subsec, offset)| {
+            let t = self.get_datetime(time)?;
+            let offset = self
+                .get(offset)
+                .and_then(EntryValue::as_str)
+                .and_then(|x| x.trim().parse::<FixedOffset>().ok())
+                .unwrap_or(*t.offset());
+            let nanos = self
+                .get(subsec)
+                .and_then(EntryValue::as_str)
+                .and_then(parse_subsec)
+                .unwrap_or(0);
+            let t = t
+                .naive_local()
+                .with_nanosecond(nanos)?
+                .and_local_timezone(offset)
+                .single()?;
+            Some((time, t))
+        })
+    }
+
+    /// Get the Orientation of the main image, `None` if the tag is missing or
+    /// has an invalid value.
+    pub fn orientation(&self) -> Option<Orientation> {
+        let v = self.get_u32(ExifTag::Orientation)?;
+        Orientation::try_from(u16::try_from(v).ok()?).ok()
+    }
+
+    /// Get the value of `tag` as an unsigned rational, see
+    /// [`EntryValue::to_urational`].
+    pub fn get_rational(&self, tag: ExifTag) -> Option<URational> {
+        self.get(tag).and_then(EntryValue::to_urational)
+    }
+
+    /// Get entry value by the IFD it's located in, and its raw tag code.
+    ///
+    /// Unlike [`Self::get_by_tag_code`], which looks through IFD0 and its
+    /// sub-IFDs, this is exact, so it's useful for unrecognized, private or
+    /// vendor-specific tags whose codes may be used in several IFDs.
+    ///
+    /// `Exif`, `Gps`, `Interop` and `SubIfd` refer to the sub-IFDs of IFD0
+    /// (the main image).
+    pub fn get_raw(&self, ifd: IfdKind, tag: u16) -> Option<&EntryValue> {
+        let index = if ifd == IfdKind::Ifd1 { 1 } else { 0 };
+        self.ifds
+            .get(index)?
+            .get_raw(ifd, tag, self.duplicate_policy)
     }
 
     /// Get entry values for the specified `tags` in ifd0 (the main image).
@@ -180,8 +363,7 @@ impl Exif {
     /// Get entry value for the specified `tag` in ifd0 (the main image).
     #[deprecated(since = "1.5.0", note = "please use [`Self::get`] instead")]
     pub fn get_value(&self, tag: &ExifTag) -> crate::Result<Option<EntryValue>> {
-        #[allow(deprecated)]
-        self.get_value_by_tag_code(tag.code())
+        Ok(self.get(*tag).map(|x| x.to_owned()))
     }
 
     /// Get entry value for the specified `tag` in ifd0 (the main image).
@@ -195,12 +377,52 @@ impl Exif {
         Ok(self.gps_info.clone())
     }
 
+    /// Returns the recognized entries of ifd0 (the main image) and its
+    /// sub-IFDs, sorted by tag code. These are the values [`Self::get`]
+    /// returns, so a tag which occurs more than once is resolved according
+    /// to [`Self::set_duplicate_policy`].
+    ///
+    /// Entries of the thumbnail IFD and unrecognized tags are left out, use
+    /// [`Self::get_raw`] or [`ExifIter`] to get them.
+    ///
+    /// `Exif` can also be converted into a `HashMap` or `BTreeMap` of these
+    /// entries, or iterated over.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let exif: Exif = parse_exif(f, None).unwrap().unwrap().into();
+    /// let entries = exif.entries();
+    /// assert!(entries.contains(&(ExifTag::Make, "vivo".into())));
+    ///
+    /// let map: BTreeMap<ExifTag, EntryValue> = exif.into();
+    /// assert_eq!(map[&ExifTag::Make].to_string(), "vivo");
+    /// assert_eq!(map.into_iter().collect::<Vec<_>>(), entries);
+    /// ```
+    pub fn entries(&self) -> Vec<(ExifTag, EntryValue)> {
+        let Some(ifd0) = self.ifd0() else {
+            return Vec::new();
+        };
+        ifd0.tag_codes()
+            .into_iter()
+            .filter_map(|code| {
+                let tag = ExifTag::try_from(code).ok()?;
+                let value = ifd0.get(code, self.duplicate_policy)?;
+                Some((tag, value.to_owned()))
+            })
+            .collect()
+    }
+
     fn put(&mut self, res: ParsedExifEntry) {
         while self.ifds.len() < res.ifd_index() + 1 {
             self.ifds.push(ParsedImageFileDirectory::new());
         }
         if let Some(v) = res.take_value() {
-            self.ifds[res.ifd_index()].put(res.tag_code(), v);
+            self.ifds[res.ifd_index()].put(res.ifd_kind(), res.tag_code(), v);
         }
     }
 
@@ -209,10 +431,35 @@ impl Exif {
     }
 }
 
+/// Parses the digits of a SubSecTime* tag (e.g. "092") into nanoseconds.
+fn parse_subsec(s: &str) -> Option<u32> {
+    let digits = s.trim();
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = &digits[..digits.len().min(9)]; // Safe-slice
+    let n: u32 = digits.parse().ok()?;
+    Some(n * 10_u32.pow(9 - digits.len() as u32))
+}
+
+/// Serialized as `{"ifds": [{"Make(0x010f)": "Apple", ...}, ...], "gps_info":
+/// {...}}`, entries of each IFD are sorted by tag code.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Exif {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("Exif", 2)?;
+        s.serialize_field("ifds", &self.ifds)?;
+        s.serialize_field("gps_info", &self.gps_info)?;
+        s.end()
+    }
+}
+
 impl From<ExifIter<'_>> for Exif {
     fn from(iter: ExifIter<'_>) -> Self {
         let gps_info = iter.parse_gps_info().ok().flatten();
-        let mut exif = Exif::new(gps_info);
+        let mut exif = Exif::new(&iter, gps_info);
 
         for it in iter {
             exif.put(it);
@@ -222,6 +469,80 @@ impl From<ExifIter<'_>> for Exif {
     }
 }
 
+/// Iterates over [`Exif::entries`].
+impl IntoIterator for Exif {
+    type Item = (ExifTag, EntryValue);
+    type IntoIter = alloc::vec::IntoIter<(ExifTag, EntryValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries().into_iter()
+    }
+}
+
+/// Collects [`Exif::entries`].
+#[cfg(feature = "std")]
+impl From<Exif> for HashMap<ExifTag, EntryValue> {
+    fn from(exif: Exif) -> Self {
+        exif.into_iter().collect()
+    }
+}
+
+/// Collects [`Exif::entries`].
+impl From<Exif> for BTreeMap<ExifTag, EntryValue> {
+    fn from(exif: Exif) -> Self {
+        exif.into_iter().collect()
+    }
+}
+
+impl Exif {
+    /// Like `From<ExifIter>`, but only the entries of `tags` (all of them
+    /// if `tags` is empty) are collected, and the iteration stops once they
+    /// have all been found. The GPS info is only parsed if
+    /// [`ExifTag::GPSInfo`] is one of `tags`.
+    #[cfg(feature = "std")]
+    pub(crate) fn from_iter_filtered(iter: ExifIter<'_>, tags: &[ExifTag]) -> Exif {
+        if tags.is_empty() {
+            return iter.into();
+        }
+        let gps_info = if tags.contains(&ExifTag::GPSInfo) {
+            iter.parse_gps_info().ok().flatten()
+        } else {
+            None
+        };
+        let mut exif = Exif {
+            ifds: Vec::new(),
+            gps_info,
+            duplicate_policy: DuplicatePolicy::default(),
+            maker_note_lens: None,
+        };
+        for it in FilteredEntries::new(iter, tags) {
+            exif.put(it);
+        }
+        exif
+    }
+
+    /// Like `From<ExifIter>`, but also returns what has been skipped: entries
+    /// which couldn't be parsed, and IFDs which couldn't be parsed at all.
+    #[cfg(feature = "std")]
+    pub(crate) fn from_iter_lenient(mut iter: ExifIter<'_>) -> (Exif, Vec<Warning>) {
+        // Errors of the GPS IFD are reported while iterating
+        let gps_info = iter.parse_gps_info().ok().flatten();
+        let mut exif = Exif::new(&iter, gps_info);
+        let mut warnings = Vec::new();
+
+        for it in iter.by_ref() {
+            if it.has_value() {
+                exif.put(it);
+            } else if let Err(e) = it.take_result() {
+                warnings.push(Warning::skipped(it.tag_name(), e));
+            }
+        }
+        warnings.extend(iter.take_skipped_ifds());
+
+        (exif, warnings)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Header {
     pub endian: Endianness,
@@ -343,17 +664,486 @@ mod tests {
                 altitude,
                 speed_ref,
                 speed,
+                ..Default::default()
             }
         )
     }
 
+    #[test]
+    fn gps_info_extended() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif: Exif = crate::parse_exif(open_sample("exif.heic").unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        let gps = exif.get_gps_info().unwrap().unwrap();
+        assert_eq!(gps.speed_ref, 'K');
+        assert_eq!(gps.speed, (0, 1).into());
+        assert_eq!(gps.track_ref, '\0');
+        assert_eq!(gps.img_direction_ref, 'T');
+        assert_eq!(gps.img_direction, (443187, 1672).into());
+        assert_eq!(gps.dop, URational::default());
+        assert_eq!(gps.h_positioning_error, (33708, 7163).into());
+    }
+
+    #[test]
+    fn typed_getters() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample("exif.jpg").unwrap();
+        let exif = input_to_exif(&buf[12..]).unwrap(); // Safe-slice in test
+
+        // ImageWidth is a LONG, ISOSpeedRatings a SHORT
+        assert_eq!(exif.get_u32(ExifTag::ImageWidth), Some(3072));
+        assert_eq!(exif.get_u32(ExifTag::ISOSpeedRatings), Some(454));
+        assert_eq!(exif.get_f64(ExifTag::ISOSpeedRatings), Some(454.0));
+        assert_eq!(exif.get_f64(ExifTag::FNumber), Some(1.75));
+        assert_eq!(exif.get_rational(ExifTag::FNumber), Some((175, 100).into()));
+        assert_eq!(
+            exif.get_rational(ExifTag::ExposureBiasValue),
+            Some((0, 1).into())
+        );
+        assert_eq!(
+            exif.get_datetime(ExifTag::DateTimeOriginal),
+            DateTime::parse_from_rfc3339("2023-07-09T20:36:33+08:00").ok()
+        );
+
+        // Wrong types
+        assert_eq!(exif.get_u32(ExifTag::Make), None);
+        assert_eq!(exif.get_rational(ExifTag::ImageWidth), None);
+        assert_eq!(exif.get_datetime(ExifTag::Make), None);
+        // Missing tags
+        assert_eq!(exif.get_u32(ExifTag::Orientation), None);
+    }
+
+    #[test]
+    fn duplicate_entries() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let make = ExifTag::Make.code();
+        let mut builder = crate::ExifBuilder::new();
+        builder
+            .dimensions(8, 8)
+            .set(ExifTag::Make, "first")
+            .set(ExifTag::Model, "second")
+            .set(ExifTag::ImageUniqueID, "third");
+        let mut data = builder.build().unwrap();
+
+        // Turn Model into a second Make entry of IFD0, and ImageUniqueID into
+        // a third one in the Exif IFD
+        for tag in [ExifTag::Model, ExifTag::ImageUniqueID] {
+            // Tag code followed by the ASCII type
+            let (pattern, patched) = if data.starts_with(b"MM") {
+                ([tag.code().to_be_bytes(), [0, 2]], make.to_be_bytes())
+            } else {
+                ([tag.code().to_le_bytes(), [2, 0]], make.to_le_bytes())
+            };
+            let pos = data.windows(4).position(|x| x == pattern.concat()).unwrap();
+            data[pos..pos + 2].copy_from_slice(&patched); // Safe-slice in test
+        }
+
+        let mut exif = input_to_exif(data).unwrap();
+        assert_eq!(
+            exif.get_all(ExifTag::Make),
+            [
+                (0, &"first".into()),
+                (0, &"second".into()),
+                (0, &"third".into()),
+            ]
+        );
+        assert_eq!(exif.get(ExifTag::Model), None);
+        assert!(exif.get_all(ExifTag::Model).is_empty());
+
+        assert_eq!(exif.duplicate_policy(), DuplicatePolicy::First);
+        assert_eq!(exif.get(ExifTag::Make), Some(&"first".into()));
+        assert_eq!(exif.get_raw(IfdKind::Exif, make), Some(&"third".into()));
+
+        exif.set_duplicate_policy(DuplicatePolicy::Last);
+        assert_eq!(exif.get(ExifTag::Make), Some(&"third".into()));
+        assert_eq!(exif.get_raw(IfdKind::Ifd0, make), Some(&"second".into()));
+
+        exif.set_duplicate_policy(DuplicatePolicy::Error);
+        assert_eq!(exif.get(ExifTag::Make), None);
+        assert_eq!(exif.get_raw(IfdKind::Ifd0, make), None);
+        assert_eq!(exif.get_raw(IfdKind::Exif, make), Some(&"third".into()));
+
+        // The thumbnail has its own resolution
+        let buf = read_sample("exif.jpg").unwrap();
+        let exif = input_to_exif(&buf[12..]).unwrap(); // Safe-slice in test
+        let resolution = EntryValue::URational((72, 1).into());
+        assert_eq!(
+            exif.get_all(ExifTag::XResolution),
+            [(0, &resolution), (1, &resolution)]
+        );
+    }
+
+    #[test]
+    fn ifd_kinds() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample("exif.jpg").unwrap();
+        let iter = input_to_iter(&buf[12..]).unwrap(); // Safe-slice in test
+        let kinds = iter
+            .map(|x| (x.tag_code(), (x.ifd_index(), x.ifd_kind(), x.tag())))
+            .collect::<std::collections::HashMap<_, _>>();
+        for (code, expected) in [
+            (0x010f, (0, IfdKind::Ifd0, Some(ExifTag::Make))),
+            (0x829a, (0, IfdKind::Exif, Some(ExifTag::ExposureTime))),
+            (0x0004, (0, IfdKind::Gps, Some(ExifTag::GPSLongitude))),
+            (0x001d, (0, IfdKind::Gps, Some(ExifTag::GPSDateStamp))),
+            (0x0201, (1, IfdKind::Ifd1, Some(ExifTag::ThumbnailOffset))),
+        ] {
+            assert_eq!(kinds[&code], expected, "0x{code:04x}");
+        }
+
+        let iter = input_to_iter(&buf[12..]).unwrap(); // Safe-slice in test
+        let interop = iter
+            .filter(|x| x.ifd_kind() == IfdKind::Interop)
+            .map(|x| (x.tag_code(), x.tag(), x.take_value().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            interop,
+            [
+                (0x0001, Some(ExifTag::InteropIndex), "R98".into()),
+                (
+                    0x0002,
+                    Some(ExifTag::InteropVersion),
+                    EntryValue::Undefined(b"0100".to_vec())
+                ),
+            ]
+        );
+
+        let exif = input_to_exif(&buf[12..]).unwrap(); // Safe-slice in test
+        assert_eq!(exif.get_raw(IfdKind::Interop, 0x0001), Some(&"R98".into()));
+        assert_eq!(exif.get(ExifTag::InteropIndex), Some(&"R98".into()));
+        assert_eq!(exif.get(ExifTag::GPSLatitudeRef), Some(&"N".into()));
+        assert_eq!(exif.get_all(ExifTag::InteropIndex), [(0, &"R98".into())]);
+    }
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn exif_collections(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif: Exif = crate::parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        let entries = exif.entries();
+        assert!(!entries.is_empty());
+        assert!(entries.windows(2).all(|x| x[0].0.code() < x[1].0.code()));
+        for (tag, value) in &entries {
+            assert_eq!(exif.get(*tag), Some(value), "{tag}");
+        }
+
+        let map: HashMap<ExifTag, EntryValue> = exif.clone().into();
+        assert_eq!(map.len(), entries.len());
+        assert_eq!(map.get(&ExifTag::Make), exif.get(ExifTag::Make));
+        // Thumbnail entries are left out
+        assert!(!map.contains_key(&ExifTag::ThumbnailOffset));
+
+        let map: BTreeMap<ExifTag, EntryValue> = exif.clone().into();
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), entries);
+        assert_eq!(exif.into_iter().collect::<Vec<_>>(), entries);
+    }
+
+    #[test]
+    fn rewind_nth_ifd() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample("exif.jpg").unwrap();
+        let mut iter = input_to_iter(&buf[12..]).unwrap(); // Safe-slice in test
+        let all = iter
+            .by_ref()
+            .map(|x| (x.ifd_index(), x.ifd_kind(), x.tag_code()))
+            .collect::<Vec<_>>();
+        assert!(iter.next().is_none());
+
+        iter.rewind();
+        let again = iter
+            .by_ref()
+            .map(|x| (x.ifd_index(), x.ifd_kind(), x.tag_code()))
+            .collect::<Vec<_>>();
+        assert_eq!(again, all);
+
+        // IFD0 with its sub-IFDs, then IFD1
+        let ifd0 = iter.nth_ifd(0).unwrap();
+        let ifd1 = iter.nth_ifd(1).unwrap();
+        let split = ifd0
+            .chain(ifd1.clone())
+            .map(|x| (x.ifd_index(), x.ifd_kind(), x.tag_code()))
+            .collect::<Vec<_>>();
+        assert_eq!(split, all);
+        assert!(ifd1.clone().all(|x| x.ifd_kind() == IfdKind::Ifd1));
+        assert_eq!(
+            iter.nth_ifd(0).unwrap().parse_gps_info().unwrap(),
+            iter.parse_gps_info().unwrap()
+        );
+        assert!(iter.nth_ifd(2).is_none());
+
+        // A clone starts from the first entry of the IFD
+        let mut ifd1 = ifd1;
+        ifd1.next().unwrap();
+        assert_eq!(ifd1.clone().count(), ifd1.count() + 1);
+    }
+
+    #[test]
+    fn sub_ifds() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // IFD0 with ImageWidth & SubIFDs, followed by the offsets of two
+        // SubIFDs, each of which has an ImageWidth
+        let entry = |tag: u16, format: u16, count: u32, value: u32| {
+            [
+                &tag.to_le_bytes()[..],
+                &format.to_le_bytes(),
+                &count.to_le_bytes(),
+                &value.to_le_bytes(),
+            ]
+            .concat()
+        };
+        let data = [
+            &b"II\x2a\x00\x08\x00\x00\x00"[..],
+            // IFD0
+            &2u16.to_le_bytes(),
+            &entry(0x0100, 3, 1, 100),
+            &entry(0x014a, 4, 2, 38),
+            &0u32.to_le_bytes(),
+            // SubIFDs offsets
+            &46u32.to_le_bytes(),
+            &64u32.to_le_bytes(),
+            // SubIFD 0
+            &1u16.to_le_bytes(),
+            &entry(0x0100, 3, 1, 200),
+            &0u32.to_le_bytes(),
+            // SubIFD 1, the (broken) next IFD pointer is ignored
+            &1u16.to_le_bytes(),
+            &entry(0x0100, 3, 1, 300),
+            &8u32.to_le_bytes(),
+        ]
+        .concat();
+
+        let iter = input_to_iter(data.clone()).unwrap();
+        let entries = iter
+            .filter(|x| x.tag_code() == ExifTag::ImageWidth.code())
+            .map(|x| (x.ifd_index(), x.ifd_kind(), x.take_value().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                (0, IfdKind::Ifd0, 100u16.into()),
+                (0, IfdKind::SubIfd(0), 200u16.into()),
+                (0, IfdKind::SubIfd(1), 300u16.into()),
+            ]
+        );
+
+        let exif = input_to_exif(data).unwrap();
+        assert_eq!(exif.get(ExifTag::ImageWidth), Some(&100u16.into()));
+        assert_eq!(
+            exif.get_raw(IfdKind::SubIfd(1), ExifTag::ImageWidth.code()),
+            Some(&300u16.into())
+        );
+    }
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn raw_values(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample(path).unwrap();
+        let iter = crate::parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let mut count = 0;
+        for entry in iter {
+            let start = entry.entry_offset() as usize;
+            let code = &data[start..start + 2]; // Safe-slice in test
+            assert!(
+                code == entry.tag_code().to_be_bytes() || code == entry.tag_code().to_le_bytes(),
+                "{entry:?}"
+            );
+
+            let start = entry.value_offset() as usize;
+            let raw = entry.raw_value();
+            assert_eq!(&data[start..start + raw.len()], raw); // Safe-slice in test
+            if entry.tag() == Some(ExifTag::Make) {
+                assert_eq!(entry.data_type(), 2);
+                assert_eq!(entry.count() as usize, raw.len());
+            }
+            count += 1;
+        }
+        assert!(count > 50);
+    }
+
+    #[test]
+    fn raw_values_malformed() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let entry = |tag: u16, format: u16, count: u32, value: u32| {
+            [
+                &tag.to_be_bytes()[..],
+                &format.to_be_bytes(),
+                &count.to_be_bytes(),
+                &value.to_be_bytes(),
+            ]
+            .concat()
+        };
+        let data = [
+            &b"MM\x00\x2a\x00\x00\x00\x08"[..],
+            &3u16.to_be_bytes(),
+            // Invalid data type
+            &entry(0x010f, 99, 1, 0x4142_4344),
+            // Out of bounds
+            &entry(0x0110, 2, 100, 1000),
+            // Two SHORTs, which don't make a valid Orientation
+            &entry(0x0112, 3, 2, 0x0001_0002),
+            &0u32.to_be_bytes(),
+        ]
+        .concat();
+
+        let iter = input_to_iter(data).unwrap();
+        let entries = iter
+            .map(|x| {
+                (
+                    x.tag_code(),
+                    x.has_value(),
+                    x.data_type(),
+                    x.count(),
+                    x.value_offset(),
+                    x.raw_value().to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                (0x010f, false, 99, 1, 18, b"ABCD".to_vec()),
+                (0x0110, false, 2, 100, 1000, vec![]),
+                (0x0112, false, 3, 2, 42, vec![0, 1, 0, 2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_entries() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // The same unrecognized tag code in IFD0, the Exif and the GPS IFD
+        let code = 0x0030;
+        let gps = GPSInfo::from_decimal(1.0, 2.0, 3.0).unwrap();
+        let mut builder = crate::ExifBuilder::new();
+        builder
+            .dimensions(8, 8)
+            .set_by_tag_code(IfdKind::Ifd0, code, "ifd0")
+            .set_by_tag_code(IfdKind::Exif, code, 7u16)
+            .set_by_tag_code(IfdKind::Gps, code, "gps")
+            .set_gps(&gps);
+        let data = builder.build().unwrap();
+
+        let iter = input_to_iter(data.clone()).unwrap();
+        let entries = iter
+            .filter(|x| x.tag_code() == code)
+            .map(|x| {
+                (
+                    x.ifd_index(),
+                    x.ifd_kind(),
+                    x.tag(),
+                    x.take_value().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.contains(&(0, IfdKind::Ifd0, None, "ifd0".into())));
+        assert!(entries.contains(&(0, IfdKind::Exif, None, 7u16.into())));
+        assert!(entries.contains(&(0, IfdKind::Gps, None, "gps".into())));
+
+        let exif = input_to_exif(data).unwrap();
+        assert_eq!(exif.get_raw(IfdKind::Ifd0, code), Some(&"ifd0".into()));
+        assert_eq!(exif.get_raw(IfdKind::Exif, code), Some(&7u16.into()));
+        assert_eq!(exif.get_raw(IfdKind::Gps, code), Some(&"gps".into()));
+        assert_eq!(exif.get_raw(IfdKind::Ifd1, code), None);
+        assert_eq!(exif.get_by_tag_code(code), Some(&"ifd0".into()));
+
+        let latitude_ref = ExifTag::GPSLatitudeRef.code();
+        assert_eq!(exif.get_raw(IfdKind::Gps, latitude_ref), Some(&"N".into()));
+        assert_eq!(exif.get_raw(IfdKind::Ifd0, latitude_ref), None);
+        assert_eq!(
+            exif.get_raw(IfdKind::Exif, ExifTag::ExifImageWidth.code()),
+            Some(&8u32.into())
+        );
+    }
+
+    #[test_case("exif.jpg", "2023-07-09T20:36:33.616+08:00")]
+    #[test_case("exif.heic", "2022-07-22T21:26:32.092+08:00")]
+    fn datetime_original(path: &str, expected: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = crate::parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let exif: Exif = iter.into();
+        assert_eq!(
+            exif.datetime_original(),
+            DateTime::parse_from_rfc3339(expected).ok()
+        );
+    }
+
+    #[test]
+    fn datetime_original_fallback() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let t = DateTime::parse_from_rfc3339("2024-03-01T10:30:00+01:00").unwrap();
+        let build = |f: &dyn Fn(&mut crate::ExifBuilder)| {
+            let mut builder = crate::ExifBuilder::new();
+            builder.dimensions(8, 8);
+            f(&mut builder);
+            input_to_exif(builder.build().unwrap()).unwrap()
+        };
+
+        // Falls back to CreateDate and its own sub-second & offset tags
+        let exif = build(&|b| {
+            b.set(ExifTag::CreateDate, t)
+                .set(ExifTag::SubSecTimeDigitized, "5")
+                .set(ExifTag::SubSecTimeOriginal, "7");
+        });
+        assert_eq!(
+            exif.datetime_original(),
+            DateTime::parse_from_rfc3339("2024-03-01T10:30:00.5+01:00").ok()
+        );
+
+        // The offset tag wins over the offset the time was parsed with
+        let exif = build(&|b| {
+            b.set(ExifTag::ModifyDate, t)
+                .set(ExifTag::OffsetTime, "-05:00")
+                .set(ExifTag::SubSecTime, "invalid");
+        });
+        assert_eq!(
+            exif.datetime_original(),
+            DateTime::parse_from_rfc3339("2024-03-01T10:30:00-05:00").ok()
+        );
+
+        let exif = build(&|_| {});
+        assert_eq!(exif.datetime_original(), None);
+    }
+
+    #[test_case("616", Some(616_000_000))]
+    #[test_case("092", Some(92_000_000))]
+    #[test_case(" 5 ", Some(500_000_000))]
+    #[test_case("1234567891", Some(123_456_789))]
+    #[test_case("", None)]
+    #[test_case("1a", None)]
+    fn subsec(s: &str, nanos: Option<u32>) {
+        assert_eq!(parse_subsec(s), nanos);
+    }
+
     #[test_case("exif.jpg")]
     fn exif_iter(path: &str) {
-        use std::fmt::Write;
+        use core::fmt::Write;
         let buf = read_sample(path).unwrap();
         let (_, data) = extract_exif_data(&buf).unwrap();
         let data = data
-            .and_then(|x| buf.subslice_range(x))
+            .and_then(|x| SubsliceRange::subslice_range(buf.as_slice(), x))
             .map(|x| Input::from_vec_range(buf, x))
             .unwrap();
         let parser = ExifParser::new(data);
@@ -383,7 +1173,7 @@ mod tests {
         let buf = read_sample(path).unwrap();
         let (_, data) = extract_exif_data(&buf).unwrap();
         let data = data
-            .and_then(|x| buf.subslice_range(x))
+            .and_then(|x| SubsliceRange::subslice_range(buf.as_slice(), x))
             .map(|x| Input::from_vec_range(buf, x))
             .unwrap();
         let parser = ExifParser::new(data);
@@ -394,11 +1184,11 @@ mod tests {
 
     #[test_case("exif.jpg")]
     fn clone_exif_iter_to_thread(path: &str) {
-        use std::fmt::Write;
+        use core::fmt::Write;
         let buf = read_sample(path).unwrap();
         let (_, data) = extract_exif_data(&buf).unwrap();
         let data = data
-            .and_then(|x| buf.subslice_range(x))
+            .and_then(|x| SubsliceRange::subslice_range(buf.as_slice(), x))
             .map(|x| Input::from_vec_range(buf, x))
             .unwrap();
         let parser = ExifParser::new(data);
@@ -0,0 +1,170 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+
+use super::{ExifIter, ParsedExifEntry};
+
+type ParseFuture = Pin<Box<dyn Future<Output = crate::Result<Option<ExifIter<'static>>>> + Send>>;
+
+/// The entries of an [`ExifIter`] as a [`Stream`], for async consumers.
+///
+/// When created with [`parse_exif_stream`](crate::parse_exif_stream), the
+/// Exif data is read when the stream is first polled, and a read error is
+/// returned as the only item. The errors of the entries themselves are kept
+/// in the [`ParsedExifEntry`]s, as with [`ExifIter`].
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let iter = parse_exif(std::fs::File::open("./testdata/exif.heic").unwrap(), None)
+///     .unwrap()
+///     .unwrap();
+/// let mut stream = ExifStream::from(iter);
+/// while let Some(entry) = stream.next().await {
+///     let entry = entry.unwrap();
+///     if entry.tag() == Some(ExifTag::Make) {
+///         assert_eq!(entry.take_value().unwrap().to_string(), "Apple");
+///     }
+/// }
+/// # });
+/// ```
+pub struct ExifStream {
+    state: State,
+}
+
+#[allow(clippy::large_enum_variant)]
+enum State {
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    Parsing(ParseFuture),
+    Iter(ExifIter<'static>),
+    Done,
+}
+
+impl ExifStream {
+    #[cfg(feature = "async")]
+    pub(crate) fn parsing(future: ParseFuture) -> Self {
+        Self {
+            state: State::Parsing(future),
+        }
+    }
+
+    /// Returns the next entry, or `None` once all the entries have been
+    /// returned. The same as `StreamExt::next`, without depending on
+    /// futures-util.
+    pub async fn next(&mut self) -> Option<crate::Result<ParsedExifEntry>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl From<ExifIter<'static>> for ExifStream {
+    fn from(iter: ExifIter<'static>) -> Self {
+        Self {
+            state: State::Iter(iter),
+        }
+    }
+}
+
+impl Stream for ExifStream {
+    type Item = crate::Result<ParsedExifEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match &mut self.state {
+                State::Parsing(future) => match future.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(Some(iter))) => self.state = State::Iter(iter),
+                    Poll::Ready(Ok(None)) => self.state = State::Done,
+                    Poll::Ready(Err(e)) => {
+                        self.state = State::Done;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                },
+                State::Iter(iter) => match iter.next() {
+                    Some(entry) => return Poll::Ready(Some(Ok(entry))),
+                    None => self.state = State::Done,
+                },
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.state {
+            State::Parsing(_) => (0, None),
+            State::Iter(iter) => iter.size_hint(),
+            State::Done => (0, Some(0)),
+        }
+    }
+}
+
+impl Debug for ExifStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self.state {
+            State::Parsing(_) => "Parsing",
+            State::Iter(_) => "Iter",
+            State::Done => "Done",
+        };
+        f.debug_struct("ExifStream").field("state", &state).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, testkit::open_sample};
+    use test_case::test_case;
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn exif_stream(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let expected = iter
+            .clone()
+            .map(|e| (e.tag_code(), e.take_result().ok()))
+            .collect::<Vec<_>>();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let entries = runtime.block_on(async {
+            let mut stream = ExifStream::from(iter);
+            let mut entries = Vec::new();
+            while let Some(e) = stream.next().await {
+                let e = e.unwrap();
+                entries.push((e.tag_code(), e.take_result().ok()));
+            }
+            assert!(stream.next().await.is_none());
+            entries
+        });
+        assert_eq!(entries, expected);
+
+        #[cfg(feature = "async")]
+        runtime.block_on(async {
+            let f = tokio::fs::File::open(format!("./testdata/{path}"))
+                .await
+                .unwrap();
+            let mut stream = crate::parse_exif_stream(f, None);
+            let mut count = 0;
+            while let Some(e) = stream.next().await {
+                e.unwrap();
+                count += 1;
+            }
+            assert_eq!(count, expected.len());
+
+            let f = tokio::fs::File::open("./testdata/meta.mov").await.unwrap();
+            let mut stream = crate::parse_exif_stream(f, Some(crate::FileFormat::Jpeg));
+            assert!(stream.next().await.unwrap().is_err());
+            assert!(stream.next().await.is_none());
+        });
+    }
+}
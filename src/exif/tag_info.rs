@@ -0,0 +1,701 @@
+//! Descriptions of the recognized Exif tags, for rendering metadata tables.
+
+use crate::{EntryValue, ExifTag};
+
+use super::IfdKind;
+
+/// Describes a recognized Exif tag, see [`ExifTag::info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TagInfo {
+    /// Name of the tag, e.g. `"DateTimeOriginal"`.
+    pub name: &'static str,
+
+    /// The IFD where the tag is stored.
+    pub ifd: IfdKind,
+
+    /// A short, human-readable description.
+    pub description: &'static str,
+
+    /// Value type defined by the Exif standard.
+    pub value_type: TagType,
+}
+
+/// Value types defined by the Exif standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagType {
+    Byte,
+    Ascii,
+    Short,
+    Long,
+    /// Either `Short` or `Long`, e.g. image dimensions.
+    ShortOrLong,
+    Rational,
+    SByte,
+    Undefined,
+    SShort,
+    SLong,
+    SRational,
+    Float,
+    Double,
+}
+
+impl TagType {
+    /// Returns true if a parsed `value` has this type.
+    ///
+    /// Note that date/time tags are `Ascii`, but are parsed as
+    /// [`EntryValue::Time`], and rationals with several components are
+    /// parsed as arrays.
+    pub fn matches(self, value: &EntryValue) -> bool {
+        use EntryValue as V;
+        match self {
+            TagType::Byte => matches!(value, V::U8(_)),
+            TagType::Ascii => matches!(value, V::Text(_) | V::Time(_)),
+            TagType::Short => matches!(value, V::U16(_)),
+            TagType::Long => matches!(value, V::U32(_)),
+            TagType::ShortOrLong => matches!(value, V::U16(_) | V::U32(_)),
+            TagType::Rational => matches!(value, V::URational(_) | V::URationalArray(_)),
+            TagType::SByte => matches!(value, V::I8(_)),
+            TagType::Undefined => matches!(value, V::Undefined(_)),
+            TagType::SShort => matches!(value, V::I16(_)),
+            TagType::SLong => matches!(value, V::I32(_)),
+            TagType::SRational => matches!(value, V::IRational(_) | V::IRationalArray(_)),
+            TagType::Float => matches!(value, V::F32(_)),
+            TagType::Double => matches!(value, V::F64(_)),
+        }
+    }
+}
+
+impl ExifTag {
+    /// Returns the name, IFD, description and expected value type of this
+    /// tag.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let info = ExifTag::LensModel.info();
+    /// assert_eq!(info.name, "LensModel");
+    /// assert_eq!(info.ifd, IfdKind::Exif);
+    /// assert_eq!(info.value_type, TagType::Ascii);
+    /// assert_eq!(info.description, "Model name of the lens");
+    ///
+    /// // Look up a raw tag code
+    /// let info = ExifTag::try_from(0x010f).ok().map(ExifTag::info);
+    /// assert_eq!(info.unwrap().name, "Make");
+    /// ```
+    pub fn info(self) -> TagInfo {
+        use TagType::*;
+
+        let (name, value_type, description) = match self {
+            #[allow(deprecated)]
+            ExifTag::Unknown => (
+                "Unknown",
+                Undefined,
+                "Deprecated placeholder for unrecognized tags",
+            ),
+            ExifTag::Make => ("Make", Ascii, "Manufacturer of the camera"),
+            ExifTag::Model => ("Model", Ascii, "Model name of the camera"),
+            ExifTag::Orientation => (
+                "Orientation",
+                Short,
+                "Orientation of the image relative to the stored pixels",
+            ),
+            ExifTag::ImageWidth => ("ImageWidth", ShortOrLong, "Image width in pixels"),
+            ExifTag::ImageHeight => ("ImageHeight", ShortOrLong, "Image height in pixels"),
+            ExifTag::ISOSpeedRatings => (
+                "ISOSpeedRatings",
+                Short,
+                "ISO sensitivity of the camera (PhotographicSensitivity)",
+            ),
+            ExifTag::ShutterSpeedValue => (
+                "ShutterSpeedValue",
+                SRational,
+                "Shutter speed in APEX units",
+            ),
+            ExifTag::ExposureTime => ("ExposureTime", Rational, "Exposure time in seconds"),
+            ExifTag::FNumber => ("FNumber", Rational, "F number of the lens"),
+            ExifTag::ExifImageWidth => (
+                "ExifImageWidth",
+                ShortOrLong,
+                "Width of the valid image data (PixelXDimension)",
+            ),
+            ExifTag::ExifImageHeight => (
+                "ExifImageHeight",
+                ShortOrLong,
+                "Height of the valid image data (PixelYDimension)",
+            ),
+            ExifTag::DateTimeOriginal => (
+                "DateTimeOriginal",
+                Ascii,
+                "Date and time when the image was taken",
+            ),
+            ExifTag::CreateDate => (
+                "CreateDate",
+                Ascii,
+                "Date and time when the image was digitized (DateTimeDigitized)",
+            ),
+            ExifTag::ModifyDate => (
+                "ModifyDate",
+                Ascii,
+                "Date and time when the file was last changed (DateTime)",
+            ),
+            ExifTag::OffsetTime => ("OffsetTime", Ascii, "Time zone offset of ModifyDate"),
+            ExifTag::OffsetTimeOriginal => (
+                "OffsetTimeOriginal",
+                Ascii,
+                "Time zone offset of DateTimeOriginal",
+            ),
+            ExifTag::OffsetTimeDigitized => (
+                "OffsetTimeDigitized",
+                Ascii,
+                "Time zone offset of CreateDate",
+            ),
+            ExifTag::GPSLatitudeRef => ("GPSLatitudeRef", Ascii, "North or south latitude"),
+            ExifTag::GPSLatitude => (
+                "GPSLatitude",
+                Rational,
+                "Latitude in degrees, minutes and seconds",
+            ),
+            ExifTag::GPSLongitudeRef => ("GPSLongitudeRef", Ascii, "East or west longitude"),
+            ExifTag::GPSLongitude => (
+                "GPSLongitude",
+                Rational,
+                "Longitude in degrees, minutes and seconds",
+            ),
+            ExifTag::GPSAltitudeRef => (
+                "GPSAltitudeRef",
+                Byte,
+                "Whether the altitude is above or below sea level",
+            ),
+            ExifTag::GPSAltitude => ("GPSAltitude", Rational, "Altitude in meters"),
+            ExifTag::GPSVersionID => ("GPSVersionID", Byte, "Version of the GPS IFD"),
+            ExifTag::ExifOffset => ("ExifOffset", Long, "Offset of the Exif sub-IFD"),
+            ExifTag::GPSInfo => ("GPSInfo", Long, "Offset of the GPS sub-IFD"),
+            ExifTag::ImageDescription => (
+                "ImageDescription",
+                Ascii,
+                "Title or description of the image",
+            ),
+            ExifTag::XResolution => (
+                "XResolution",
+                Rational,
+                "Horizontal resolution in pixels per ResolutionUnit",
+            ),
+            ExifTag::YResolution => (
+                "YResolution",
+                Rational,
+                "Vertical resolution in pixels per ResolutionUnit",
+            ),
+            ExifTag::ResolutionUnit => (
+                "ResolutionUnit",
+                Short,
+                "Unit of XResolution and YResolution",
+            ),
+            ExifTag::Software => ("Software", Ascii, "Software used to create the image"),
+            ExifTag::Artist => ("Artist", Ascii, "Person who created the image"),
+            ExifTag::HostComputer => ("HostComputer", Ascii, "Computer used to create the image"),
+            ExifTag::WhitePoint => ("WhitePoint", Rational, "Chromaticity of the white point"),
+            ExifTag::PrimaryChromaticities => (
+                "PrimaryChromaticities",
+                Rational,
+                "Chromaticities of the primary colors",
+            ),
+            ExifTag::YCbCrCoefficients => (
+                "YCbCrCoefficients",
+                Rational,
+                "Coefficients of the RGB to YCbCr transformation",
+            ),
+            ExifTag::ReferenceBlackWhite => (
+                "ReferenceBlackWhite",
+                Rational,
+                "Reference black and white point values",
+            ),
+            ExifTag::Copyright => ("Copyright", Ascii, "Copyright notice"),
+            ExifTag::NewSubfileType => ("NewSubfileType", Long, "Kind of the data in this IFD"),
+            ExifTag::SubfileType => (
+                "SubfileType",
+                Short,
+                "Kind of the data in this IFD (deprecated)",
+            ),
+            ExifTag::BitsPerSample => ("BitsPerSample", Short, "Number of bits per component"),
+            ExifTag::Compression => ("Compression", Short, "Compression scheme of the image data"),
+            ExifTag::PhotometricInterpretation => (
+                "PhotometricInterpretation",
+                Short,
+                "Color space of the image data",
+            ),
+            ExifTag::StripOffsets => (
+                "StripOffsets",
+                ShortOrLong,
+                "Offsets of the image data strips",
+            ),
+            ExifTag::SamplesPerPixel => {
+                ("SamplesPerPixel", Short, "Number of components per pixel")
+            }
+            ExifTag::RowsPerStrip => ("RowsPerStrip", ShortOrLong, "Number of rows per strip"),
+            ExifTag::StripByteCounts => (
+                "StripByteCounts",
+                ShortOrLong,
+                "Byte counts of the image data strips",
+            ),
+            ExifTag::PlanarConfiguration => (
+                "PlanarConfiguration",
+                Short,
+                "Whether components are stored chunky or planar",
+            ),
+            ExifTag::TransferFunction => {
+                ("TransferFunction", Short, "Transfer function of the image")
+            }
+            ExifTag::ThumbnailOffset => (
+                "ThumbnailOffset",
+                Long,
+                "Offset of the JPEG thumbnail (JPEGInterchangeFormat)",
+            ),
+            ExifTag::ThumbnailLength => (
+                "ThumbnailLength",
+                Long,
+                "Length of the JPEG thumbnail (JPEGInterchangeFormatLength)",
+            ),
+            ExifTag::YCbCrSubSampling => (
+                "YCbCrSubSampling",
+                Short,
+                "Subsampling ratio of chrominance components",
+            ),
+            ExifTag::YCbCrPositioning => (
+                "YCbCrPositioning",
+                Short,
+                "Position of chrominance components relative to luminance",
+            ),
+            ExifTag::ExposureProgram => {
+                ("ExposureProgram", Short, "Program used to set the exposure")
+            }
+            ExifTag::SpectralSensitivity => (
+                "SpectralSensitivity",
+                Ascii,
+                "Spectral sensitivity of each channel",
+            ),
+            ExifTag::OECF => ("OECF", Undefined, "Opto-electronic conversion function"),
+            ExifTag::SensitivityType => (
+                "SensitivityType",
+                Short,
+                "Which sensitivity value ISOSpeedRatings holds",
+            ),
+            ExifTag::ExifVersion => ("ExifVersion", Undefined, "Version of the Exif standard"),
+            ExifTag::ApertureValue => ("ApertureValue", Rational, "Lens aperture in APEX units"),
+            ExifTag::BrightnessValue => ("BrightnessValue", SRational, "Brightness in APEX units"),
+            ExifTag::ExposureBiasValue => (
+                "ExposureBiasValue",
+                SRational,
+                "Exposure bias in APEX units",
+            ),
+            ExifTag::MaxApertureValue => (
+                "MaxApertureValue",
+                Rational,
+                "Smallest F number of the lens in APEX units",
+            ),
+            ExifTag::SubjectDistance => (
+                "SubjectDistance",
+                Rational,
+                "Distance to the subject in meters",
+            ),
+            ExifTag::MeteringMode => ("MeteringMode", Short, "Metering mode"),
+            ExifTag::LightSource => ("LightSource", Short, "Kind of light source"),
+            ExifTag::Flash => ("Flash", Short, "Flash status"),
+            ExifTag::FocalLength => (
+                "FocalLength",
+                Rational,
+                "Focal length of the lens in millimeters",
+            ),
+            ExifTag::SubjectArea => (
+                "SubjectArea",
+                Short,
+                "Location and area of the main subject",
+            ),
+            ExifTag::MakerNote => ("MakerNote", Undefined, "Manufacturer specific data"),
+            ExifTag::UserComment => ("UserComment", Undefined, "User comments"),
+            ExifTag::FlashPixVersion => (
+                "FlashPixVersion",
+                Undefined,
+                "Supported Flashpix format version",
+            ),
+            ExifTag::ColorSpace => ("ColorSpace", Short, "Color space"),
+            ExifTag::RelatedSoundFile => {
+                ("RelatedSoundFile", Ascii, "Name of a related audio file")
+            }
+            ExifTag::FlashEnergy => ("FlashEnergy", Rational, "Strobe energy in BCPS"),
+            ExifTag::FocalPlaneXResolution => (
+                "FocalPlaneXResolution",
+                Rational,
+                "Horizontal pixels per FocalPlaneResolutionUnit on the sensor",
+            ),
+            ExifTag::FocalPlaneYResolution => (
+                "FocalPlaneYResolution",
+                Rational,
+                "Vertical pixels per FocalPlaneResolutionUnit on the sensor",
+            ),
+            ExifTag::FocalPlaneResolutionUnit => (
+                "FocalPlaneResolutionUnit",
+                Short,
+                "Unit of FocalPlaneXResolution and FocalPlaneYResolution",
+            ),
+            ExifTag::SubjectLocation => ("SubjectLocation", Short, "Location of the main subject"),
+            ExifTag::ExposureIndex => ("ExposureIndex", Rational, "Exposure index"),
+            ExifTag::SensingMethod => ("SensingMethod", Short, "Type of the image sensor"),
+            ExifTag::FileSource => (
+                "FileSource",
+                Undefined,
+                "Source of the image, e.g. a digital camera",
+            ),
+            ExifTag::SceneType => (
+                "SceneType",
+                Undefined,
+                "Type of the scene, e.g. directly photographed",
+            ),
+            ExifTag::CFAPattern => (
+                "CFAPattern",
+                Undefined,
+                "Color filter array pattern of the sensor",
+            ),
+            ExifTag::CustomRendered => (
+                "CustomRendered",
+                Short,
+                "Special processing applied to the image",
+            ),
+            ExifTag::ExposureMode => ("ExposureMode", Short, "Exposure mode"),
+            ExifTag::WhiteBalanceMode => ("WhiteBalanceMode", Short, "White balance mode"),
+            ExifTag::DigitalZoomRatio => ("DigitalZoomRatio", Rational, "Digital zoom ratio"),
+            ExifTag::FocalLengthIn35mmFilm => (
+                "FocalLengthIn35mmFilm",
+                Short,
+                "Equivalent focal length for 35mm film in millimeters",
+            ),
+            ExifTag::SceneCaptureType => ("SceneCaptureType", Short, "Type of the scene captured"),
+            ExifTag::GainControl => (
+                "GainControl",
+                Short,
+                "Degree of the overall image gain adjustment",
+            ),
+            ExifTag::Contrast => (
+                "Contrast",
+                Short,
+                "Contrast processing applied by the camera",
+            ),
+            ExifTag::Saturation => (
+                "Saturation",
+                Short,
+                "Saturation processing applied by the camera",
+            ),
+            ExifTag::Sharpness => (
+                "Sharpness",
+                Short,
+                "Sharpness processing applied by the camera",
+            ),
+            ExifTag::DeviceSettingDescription => (
+                "DeviceSettingDescription",
+                Undefined,
+                "Picture-taking conditions of the camera model",
+            ),
+            ExifTag::SubjectDistanceRange => (
+                "SubjectDistanceRange",
+                Short,
+                "Distance range to the subject",
+            ),
+            ExifTag::ImageUniqueID => ("ImageUniqueID", Ascii, "Unique identifier of the image"),
+            ExifTag::LensSpecification => (
+                "LensSpecification",
+                Rational,
+                "Minimum and maximum focal length and F number of the lens",
+            ),
+            ExifTag::LensMake => ("LensMake", Ascii, "Manufacturer of the lens"),
+            ExifTag::LensModel => ("LensModel", Ascii, "Model name of the lens"),
+            ExifTag::Gamma => ("Gamma", Rational, "Gamma coefficient"),
+            ExifTag::StandardOutputSensitivity => (
+                "StandardOutputSensitivity",
+                Long,
+                "Standard output sensitivity (ISO 12232)",
+            ),
+            ExifTag::RecommendedExposureIndex => (
+                "RecommendedExposureIndex",
+                Long,
+                "Recommended exposure index (ISO 12232)",
+            ),
+            ExifTag::ISOSpeed => ("ISOSpeed", Long, "ISO speed (ISO 12232)"),
+            ExifTag::ISOSpeedLatitudeyyy => (
+                "ISOSpeedLatitudeyyy",
+                Long,
+                "ISO speed latitude yyy (ISO 12232)",
+            ),
+            ExifTag::ISOSpeedLatitudezzz => (
+                "ISOSpeedLatitudezzz",
+                Long,
+                "ISO speed latitude zzz (ISO 12232)",
+            ),
+            ExifTag::ComponentsConfiguration => (
+                "ComponentsConfiguration",
+                Undefined,
+                "Order of the components, e.g. YCbCr",
+            ),
+            ExifTag::CompressedBitsPerPixel => (
+                "CompressedBitsPerPixel",
+                Rational,
+                "Compression ratio in bits per pixel",
+            ),
+            ExifTag::SubSecTime => ("SubSecTime", Ascii, "Fractions of seconds of ModifyDate"),
+            ExifTag::SubSecTimeOriginal => (
+                "SubSecTimeOriginal",
+                Ascii,
+                "Fractions of seconds of DateTimeOriginal",
+            ),
+            ExifTag::SubSecTimeDigitized => (
+                "SubSecTimeDigitized",
+                Ascii,
+                "Fractions of seconds of CreateDate",
+            ),
+            ExifTag::AmbientTemperature => (
+                "AmbientTemperature",
+                SRational,
+                "Ambient temperature in degrees Celsius",
+            ),
+            ExifTag::Humidity => ("Humidity", Rational, "Ambient relative humidity in percent"),
+            ExifTag::Pressure => ("Pressure", Rational, "Ambient air pressure in hPa"),
+            ExifTag::WaterDepth => (
+                "WaterDepth",
+                SRational,
+                "Water depth in meters (negative for above water)",
+            ),
+            ExifTag::Acceleration => (
+                "Acceleration",
+                Rational,
+                "Acceleration of the camera in mGal",
+            ),
+            ExifTag::CameraElevationAngle => (
+                "CameraElevationAngle",
+                SRational,
+                "Elevation angle of the camera in degrees",
+            ),
+            ExifTag::InteropOffset => (
+                "InteropOffset",
+                Long,
+                "Offset of the Interoperability sub-IFD",
+            ),
+            ExifTag::SpatialFrequencyResponse => (
+                "SpatialFrequencyResponse",
+                Undefined,
+                "Spatial frequency response of the camera",
+            ),
+            ExifTag::CameraOwnerName => ("CameraOwnerName", Ascii, "Owner of the camera"),
+            ExifTag::BodySerialNumber => (
+                "BodySerialNumber",
+                Ascii,
+                "Serial number of the camera body",
+            ),
+            ExifTag::LensSerialNumber => ("LensSerialNumber", Ascii, "Serial number of the lens"),
+            ExifTag::ImageTitle => ("ImageTitle", Ascii, "Title of the image"),
+            ExifTag::Photographer => ("Photographer", Ascii, "Name of the photographer"),
+            ExifTag::ImageEditor => (
+                "ImageEditor",
+                Ascii,
+                "Name of the person who edited the image",
+            ),
+            ExifTag::CameraFirmware => ("CameraFirmware", Ascii, "Firmware version of the camera"),
+            ExifTag::RAWDevelopingSoftware => (
+                "RAWDevelopingSoftware",
+                Ascii,
+                "Software used to develop the RAW image",
+            ),
+            ExifTag::ImageEditingSoftware => (
+                "ImageEditingSoftware",
+                Ascii,
+                "Software used to edit the image",
+            ),
+            ExifTag::MetadataEditingSoftware => (
+                "MetadataEditingSoftware",
+                Ascii,
+                "Software used to edit the metadata",
+            ),
+            ExifTag::CompositeImage => (
+                "CompositeImage",
+                Short,
+                "Whether the image is a composite of several images",
+            ),
+            ExifTag::SourceImageNumberOfCompositeImage => (
+                "SourceImageNumberOfCompositeImage",
+                Short,
+                "Number of source images of a composite image",
+            ),
+            ExifTag::SourceExposureTimesOfCompositeImage => (
+                "SourceExposureTimesOfCompositeImage",
+                Undefined,
+                "Exposure times of the source images of a composite image",
+            ),
+            ExifTag::GPSTimeStamp => ("GPSTimeStamp", Rational, "UTC time of the GPS fix"),
+            ExifTag::GPSSatellites => (
+                "GPSSatellites",
+                Ascii,
+                "Satellites used for the measurement",
+            ),
+            ExifTag::GPSStatus => ("GPSStatus", Ascii, "Status of the GPS receiver"),
+            ExifTag::GPSMeasureMode => ("GPSMeasureMode", Ascii, "2D or 3D measurement"),
+            ExifTag::GPSDOP => (
+                "GPSDOP",
+                Rational,
+                "Dilution of precision of the measurement",
+            ),
+            ExifTag::GPSSpeedRef => ("GPSSpeedRef", Ascii, "Unit of GPSSpeed"),
+            ExifTag::GPSSpeed => ("GPSSpeed", Rational, "Speed of the GPS receiver"),
+            ExifTag::GPSTrackRef => (
+                "GPSTrackRef",
+                Ascii,
+                "Reference of GPSTrack, true or magnetic north",
+            ),
+            ExifTag::GPSTrack => ("GPSTrack", Rational, "Direction of the movement in degrees"),
+            ExifTag::GPSImgDirectionRef => (
+                "GPSImgDirectionRef",
+                Ascii,
+                "Reference of GPSImgDirection, true or magnetic north",
+            ),
+            ExifTag::GPSImgDirection => (
+                "GPSImgDirection",
+                Rational,
+                "Direction of the image in degrees",
+            ),
+            ExifTag::GPSMapDatum => ("GPSMapDatum", Ascii, "Geodetic survey data, e.g. WGS-84"),
+            ExifTag::GPSDestLatitudeRef => (
+                "GPSDestLatitudeRef",
+                Ascii,
+                "North or south latitude of the destination",
+            ),
+            ExifTag::GPSDestLatitude => {
+                ("GPSDestLatitude", Rational, "Latitude of the destination")
+            }
+            ExifTag::GPSDestLongitudeRef => (
+                "GPSDestLongitudeRef",
+                Ascii,
+                "East or west longitude of the destination",
+            ),
+            ExifTag::GPSDestLongitude => {
+                ("GPSDestLongitude", Rational, "Longitude of the destination")
+            }
+            ExifTag::GPSDestBearingRef => (
+                "GPSDestBearingRef",
+                Ascii,
+                "Reference of GPSDestBearing, true or magnetic north",
+            ),
+            ExifTag::GPSDestBearing => (
+                "GPSDestBearing",
+                Rational,
+                "Bearing to the destination in degrees",
+            ),
+            ExifTag::GPSDestDistanceRef => ("GPSDestDistanceRef", Ascii, "Unit of GPSDestDistance"),
+            ExifTag::GPSDestDistance => {
+                ("GPSDestDistance", Rational, "Distance to the destination")
+            }
+            ExifTag::GPSProcessingMethod => (
+                "GPSProcessingMethod",
+                Undefined,
+                "Name of the method used for location finding",
+            ),
+            ExifTag::GPSAreaInformation => {
+                ("GPSAreaInformation", Undefined, "Name of the GPS area")
+            }
+            ExifTag::GPSDateStamp => ("GPSDateStamp", Ascii, "UTC date of the GPS fix"),
+            ExifTag::GPSDifferential => (
+                "GPSDifferential",
+                Short,
+                "Whether differential correction was applied",
+            ),
+            ExifTag::GPSHPositioningError => (
+                "GPSHPositioningError",
+                Rational,
+                "Horizontal positioning error in meters",
+            ),
+            ExifTag::InteropIndex => (
+                "InteropIndex",
+                Ascii,
+                "Interoperability rule, e.g. \"R98\" for DCF basic files",
+            ),
+            ExifTag::InteropVersion => (
+                "InteropVersion",
+                Undefined,
+                "Version of the interoperability rule",
+            ),
+        };
+        let ifd = match self {
+            // The thumbnail is referenced from IFD1
+            ExifTag::ThumbnailOffset | ExifTag::ThumbnailLength => IfdKind::Ifd1,
+            tag => IfdKind::of_tag(tag),
+        };
+        TagInfo {
+            name,
+            ifd,
+            description,
+            value_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, testkit::open_sample};
+    use test_case::test_case;
+
+    #[test_case(ExifTag::Make, "Make", IfdKind::Ifd0, TagType::Ascii)]
+    #[test_case(
+        ExifTag::ExifImageWidth,
+        "ExifImageWidth",
+        IfdKind::Exif,
+        TagType::ShortOrLong
+    )]
+    #[test_case(
+        ExifTag::BodySerialNumber,
+        "BodySerialNumber",
+        IfdKind::Exif,
+        TagType::Ascii
+    )]
+    #[test_case(ExifTag::GPSLatitude, "GPSLatitude", IfdKind::Gps, TagType::Rational)]
+    #[test_case(
+        ExifTag::InteropIndex,
+        "InteropIndex",
+        IfdKind::Interop,
+        TagType::Ascii
+    )]
+    #[test_case(
+        ExifTag::ThumbnailOffset,
+        "ThumbnailOffset",
+        IfdKind::Ifd1,
+        TagType::Long
+    )]
+    fn info(tag: ExifTag, name: &str, ifd: IfdKind, value_type: TagType) {
+        let info = tag.info();
+        assert_eq!(info.name, name);
+        assert_eq!(info.ifd, ifd);
+        assert_eq!(info.value_type, value_type);
+        assert!(!info.description.is_empty());
+
+        // Consistent with `Display`
+        assert_eq!(
+            format!("{}(0x{:04x})", info.name, tag.code()),
+            tag.to_string()
+        );
+    }
+
+    // Files don't always follow the standard, e.g. vivo phones write
+    // UserComment as ASCII, and GPS coordinates as signed rationals
+    #[test_case("exif.jpg", &[ExifTag::UserComment, ExifTag::GPSLatitude, ExifTag::GPSLongitude])]
+    #[test_case("exif.heic", &[])]
+    fn value_types(path: &str, mismatched: &[ExifTag]) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let tags = iter
+            .filter_map(|x| Some((x.tag()?, x.take_value()?)))
+            .filter(|(tag, value)| !tag.info().value_type.matches(value))
+            .map(|(tag, _)| tag)
+            .collect::<Vec<_>>();
+        assert_eq!(tags, mismatched);
+    }
+}
@@ -1,13 +1,16 @@
 //! Define exif tags and related enums, see
 //! https://exiftool.org/TagNames/EXIF.html
 
-use std::fmt::{Debug, Display};
+use core::fmt::{Debug, Display};
 
-#[cfg(feature = "json_dump")]
+use super::IfdKind;
+
+use crate::prelude::*;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 #[allow(unused)]
-#[cfg_attr(feature = "json_dump", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Eq, PartialEq, Hash, Clone, Copy)]
 pub(crate) enum ExifTagCode {
     /// Recognized Exif tag
@@ -37,6 +40,21 @@ impl ExifTagCode {
     }
 }
 
+impl ExifTagCode {
+    /// Returns the tag of `code` in an IFD of the given `kind`. Codes of the
+    /// Interoperability IFD overlap with the ones of the GPS IFD (e.g.
+    /// `InteropIndex` and `GPSLatitudeRef` are both 0x0001), so they're
+    /// only recognized in the IFD where they appear.
+    pub(crate) fn in_ifd(kind: IfdKind, code: u16) -> ExifTagCode {
+        match (kind, code) {
+            (IfdKind::Interop, 0x0001) => ExifTagCode::Tag(ExifTag::InteropIndex),
+            (IfdKind::Interop, 0x0002) => ExifTagCode::Tag(ExifTag::InteropVersion),
+            (IfdKind::Interop, _) => ExifTagCode::Code(code),
+            _ => code.into(),
+        }
+    }
+}
+
 impl From<u16> for ExifTagCode {
     fn from(v: u16) -> Self {
         let tag: crate::Result<ExifTag> = v.try_into();
@@ -49,7 +67,7 @@ impl From<u16> for ExifTagCode {
 }
 
 impl Debug for ExifTagCode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             ExifTagCode::Tag(t) => write!(f, "{t}"),
             ExifTagCode::Code(c) => write!(f, "Unrecognized(0x{c:04x})"),
@@ -61,11 +79,12 @@ impl Debug for ExifTagCode {
 /// defined here. This enum definition is just for ease of use.
 ///
 /// You can always get the entry value by raw tag code which is an `u16` value.
-/// See [`ParsedExifEntry::tag_code`](crate::ParsedExifEntry::tag_code) and
-/// [`Exif::get_by_tag_code`](crate::Exif::get_by_tag_code).
+/// See [`ParsedExifEntry::tag_code`](crate::ParsedExifEntry::tag_code),
+/// [`Exif::get_by_tag_code`](crate::Exif::get_by_tag_code) and
+/// [`Exif::get_raw`](crate::Exif::get_raw).
 #[allow(unused)]
-#[cfg_attr(feature = "json_dump", derive(Serialize, Deserialize))]
-#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, PartialOrd, Ord)]
 pub enum ExifTag {
     /// `Unknown` has been deprecated, please don't use this variant in your
     /// code (use "_" to ommit it if you are using match statement).
@@ -114,12 +133,28 @@ pub enum ExifTag {
     YResolution = 0x0000_011b,
     ResolutionUnit = 0x0000_0128,
     Software = 0x0000_0131,
+    Artist = 0x0000_013b,
     HostComputer = 0x0000_013c,
     WhitePoint = 0x0000_013e,
     PrimaryChromaticities = 0x0000_013f,
     YCbCrCoefficients = 0x0000_0211,
     ReferenceBlackWhite = 0x0000_0214,
     Copyright = 0x0000_8298,
+    NewSubfileType = 0x0000_00fe,
+    SubfileType = 0x0000_00ff,
+    BitsPerSample = 0x0000_0102,
+    Compression = 0x0000_0103,
+    PhotometricInterpretation = 0x0000_0106,
+    StripOffsets = 0x0000_0111,
+    SamplesPerPixel = 0x0000_0115,
+    RowsPerStrip = 0x0000_0116,
+    StripByteCounts = 0x0000_0117,
+    PlanarConfiguration = 0x0000_011c,
+    TransferFunction = 0x0000_012d,
+    ThumbnailOffset = 0x0000_0201,
+    ThumbnailLength = 0x0000_0202,
+    YCbCrSubSampling = 0x0000_0212,
+    YCbCrPositioning = 0x0000_0213,
 
     ExposureProgram = 0x0000_8822,
     SpectralSensitivity = 0x0000_8824,
@@ -168,6 +203,37 @@ pub enum ExifTag {
     LensMake = 0x0000_a433,
     LensModel = 0x0000_a434,
     Gamma = 0xa500,
+    StandardOutputSensitivity = 0x0000_8831,
+    RecommendedExposureIndex = 0x0000_8832,
+    ISOSpeed = 0x0000_8833,
+    ISOSpeedLatitudeyyy = 0x0000_8834,
+    ISOSpeedLatitudezzz = 0x0000_8835,
+    ComponentsConfiguration = 0x0000_9101,
+    CompressedBitsPerPixel = 0x0000_9102,
+    SubSecTime = 0x0000_9290,
+    SubSecTimeOriginal = 0x0000_9291,
+    SubSecTimeDigitized = 0x0000_9292,
+    AmbientTemperature = 0x0000_9400,
+    Humidity = 0x0000_9401,
+    Pressure = 0x0000_9402,
+    WaterDepth = 0x0000_9403,
+    Acceleration = 0x0000_9404,
+    CameraElevationAngle = 0x0000_9405,
+    InteropOffset = 0x0000_a005,
+    SpatialFrequencyResponse = 0x0000_a20c,
+    CameraOwnerName = 0x0000_a430,
+    BodySerialNumber = 0x0000_a431,
+    LensSerialNumber = 0x0000_a435,
+    ImageTitle = 0x0000_a436,
+    Photographer = 0x0000_a437,
+    ImageEditor = 0x0000_a438,
+    CameraFirmware = 0x0000_a439,
+    RAWDevelopingSoftware = 0x0000_a43a,
+    ImageEditingSoftware = 0x0000_a43b,
+    MetadataEditingSoftware = 0x0000_a43c,
+    CompositeImage = 0x0000_a460,
+    SourceImageNumberOfCompositeImage = 0x0000_a461,
+    SourceExposureTimesOfCompositeImage = 0x0000_a462,
 
     GPSTimeStamp = 0x00007,
     GPSSatellites = 0x00008,
@@ -193,16 +259,26 @@ pub enum ExifTag {
     GPSAreaInformation = 0x0000_001c,
     GPSDateStamp = 0x0000_001d,
     GPSDifferential = 0x0000_001e,
+    GPSHPositioningError = 0x0000_001f,
+
+    // Interoperability IFD. The codes (0x0001 and 0x0002) overlap with the
+    // ones of the GPS IFD, see `ExifTag::code`.
+    InteropIndex = 0x0001_0001,
+    InteropVersion = 0x0001_0002,
 }
 
 impl ExifTag {
     pub const fn code(self) -> u16 {
-        self as u16
+        match self {
+            ExifTag::InteropIndex => 0x0001,
+            ExifTag::InteropVersion => 0x0002,
+            _ => self as u16,
+        }
     }
 }
 
 impl Display for ExifTag {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             #[allow(deprecated)]
             ExifTag::Unknown => write!(f, "Unknown(0x{:04x})", self.code()),
@@ -239,6 +315,7 @@ impl Display for ExifTag {
             ExifTag::YResolution => write!(f, "YResolution(0x{:04x})", self.code()),
             ExifTag::ResolutionUnit => write!(f, "ResolutionUnit(0x{:04x})", self.code()),
             ExifTag::Software => write!(f, "Software(0x{:04x})", self.code()),
+            ExifTag::Artist => write!(f, "Artist(0x{:04x})", self.code()),
             ExifTag::HostComputer => write!(f, "HostComputer(0x{:04x})", self.code()),
             ExifTag::WhitePoint => write!(f, "WhitePoint(0x{:04x})", self.code()),
             ExifTag::PrimaryChromaticities => {
@@ -338,6 +415,85 @@ impl Display for ExifTag {
             ExifTag::GPSAreaInformation => write!(f, "GPSAreaInformation(0x{:04x})", self.code()),
             ExifTag::GPSDateStamp => write!(f, "GPSDateStamp(0x{:04x})", self.code()),
             ExifTag::GPSDifferential => write!(f, "GPSDifferential(0x{:04x})", self.code()),
+            ExifTag::NewSubfileType => write!(f, "NewSubfileType(0x{:04x})", self.code()),
+            ExifTag::SubfileType => write!(f, "SubfileType(0x{:04x})", self.code()),
+            ExifTag::BitsPerSample => write!(f, "BitsPerSample(0x{:04x})", self.code()),
+            ExifTag::Compression => write!(f, "Compression(0x{:04x})", self.code()),
+            ExifTag::PhotometricInterpretation => {
+                write!(f, "PhotometricInterpretation(0x{:04x})", self.code())
+            }
+            ExifTag::StripOffsets => write!(f, "StripOffsets(0x{:04x})", self.code()),
+            ExifTag::SamplesPerPixel => write!(f, "SamplesPerPixel(0x{:04x})", self.code()),
+            ExifTag::RowsPerStrip => write!(f, "RowsPerStrip(0x{:04x})", self.code()),
+            ExifTag::StripByteCounts => write!(f, "StripByteCounts(0x{:04x})", self.code()),
+            ExifTag::PlanarConfiguration => write!(f, "PlanarConfiguration(0x{:04x})", self.code()),
+            ExifTag::TransferFunction => write!(f, "TransferFunction(0x{:04x})", self.code()),
+            ExifTag::ThumbnailOffset => write!(f, "ThumbnailOffset(0x{:04x})", self.code()),
+            ExifTag::ThumbnailLength => write!(f, "ThumbnailLength(0x{:04x})", self.code()),
+            ExifTag::YCbCrSubSampling => write!(f, "YCbCrSubSampling(0x{:04x})", self.code()),
+            ExifTag::YCbCrPositioning => write!(f, "YCbCrPositioning(0x{:04x})", self.code()),
+            ExifTag::StandardOutputSensitivity => {
+                write!(f, "StandardOutputSensitivity(0x{:04x})", self.code())
+            }
+            ExifTag::RecommendedExposureIndex => {
+                write!(f, "RecommendedExposureIndex(0x{:04x})", self.code())
+            }
+            ExifTag::ISOSpeed => write!(f, "ISOSpeed(0x{:04x})", self.code()),
+            ExifTag::ISOSpeedLatitudeyyy => write!(f, "ISOSpeedLatitudeyyy(0x{:04x})", self.code()),
+            ExifTag::ISOSpeedLatitudezzz => write!(f, "ISOSpeedLatitudezzz(0x{:04x})", self.code()),
+            ExifTag::ComponentsConfiguration => {
+                write!(f, "ComponentsConfiguration(0x{:04x})", self.code())
+            }
+            ExifTag::CompressedBitsPerPixel => {
+                write!(f, "CompressedBitsPerPixel(0x{:04x})", self.code())
+            }
+            ExifTag::SubSecTime => write!(f, "SubSecTime(0x{:04x})", self.code()),
+            ExifTag::SubSecTimeOriginal => write!(f, "SubSecTimeOriginal(0x{:04x})", self.code()),
+            ExifTag::SubSecTimeDigitized => write!(f, "SubSecTimeDigitized(0x{:04x})", self.code()),
+            ExifTag::AmbientTemperature => write!(f, "AmbientTemperature(0x{:04x})", self.code()),
+            ExifTag::Humidity => write!(f, "Humidity(0x{:04x})", self.code()),
+            ExifTag::Pressure => write!(f, "Pressure(0x{:04x})", self.code()),
+            ExifTag::WaterDepth => write!(f, "WaterDepth(0x{:04x})", self.code()),
+            ExifTag::Acceleration => write!(f, "Acceleration(0x{:04x})", self.code()),
+            ExifTag::CameraElevationAngle => {
+                write!(f, "CameraElevationAngle(0x{:04x})", self.code())
+            }
+            ExifTag::InteropOffset => write!(f, "InteropOffset(0x{:04x})", self.code()),
+            ExifTag::SpatialFrequencyResponse => {
+                write!(f, "SpatialFrequencyResponse(0x{:04x})", self.code())
+            }
+            ExifTag::CameraOwnerName => write!(f, "CameraOwnerName(0x{:04x})", self.code()),
+            ExifTag::BodySerialNumber => write!(f, "BodySerialNumber(0x{:04x})", self.code()),
+            ExifTag::LensSerialNumber => write!(f, "LensSerialNumber(0x{:04x})", self.code()),
+            ExifTag::ImageTitle => write!(f, "ImageTitle(0x{:04x})", self.code()),
+            ExifTag::Photographer => write!(f, "Photographer(0x{:04x})", self.code()),
+            ExifTag::ImageEditor => write!(f, "ImageEditor(0x{:04x})", self.code()),
+            ExifTag::CameraFirmware => write!(f, "CameraFirmware(0x{:04x})", self.code()),
+            ExifTag::RAWDevelopingSoftware => {
+                write!(f, "RAWDevelopingSoftware(0x{:04x})", self.code())
+            }
+            ExifTag::ImageEditingSoftware => {
+                write!(f, "ImageEditingSoftware(0x{:04x})", self.code())
+            }
+            ExifTag::MetadataEditingSoftware => {
+                write!(f, "MetadataEditingSoftware(0x{:04x})", self.code())
+            }
+            ExifTag::CompositeImage => write!(f, "CompositeImage(0x{:04x})", self.code()),
+            ExifTag::SourceImageNumberOfCompositeImage => write!(
+                f,
+                "SourceImageNumberOfCompositeImage(0x{:04x})",
+                self.code()
+            ),
+            ExifTag::SourceExposureTimesOfCompositeImage => write!(
+                f,
+                "SourceExposureTimesOfCompositeImage(0x{:04x})",
+                self.code()
+            ),
+            ExifTag::GPSHPositioningError => {
+                write!(f, "GPSHPositioningError(0x{:04x})", self.code())
+            }
+            ExifTag::InteropIndex => write!(f, "InteropIndex(0x{:04x})", self.code()),
+            ExifTag::InteropVersion => write!(f, "InteropVersion(0x{:04x})", self.code()),
         }
     }
 }
@@ -378,6 +534,7 @@ impl TryFrom<u16> for ExifTag {
             x if x == ExifTag::YResolution.code() => Ok(ExifTag::YResolution),
             x if x == ExifTag::ResolutionUnit.code() => Ok(ExifTag::ResolutionUnit),
             x if x == ExifTag::Software.code() => Ok(ExifTag::Software),
+            x if x == ExifTag::Artist.code() => Ok(ExifTag::Artist),
             x if x == ExifTag::HostComputer.code() => Ok(ExifTag::HostComputer),
             x if x == ExifTag::WhitePoint.code() => Ok(ExifTag::WhitePoint),
             x if x == ExifTag::PrimaryChromaticities.code() => Ok(ExifTag::PrimaryChromaticities),
@@ -459,19 +616,208 @@ impl TryFrom<u16> for ExifTag {
             x if x == ExifTag::GPSAreaInformation.code() => Ok(ExifTag::GPSAreaInformation),
             x if x == ExifTag::GPSDateStamp.code() => Ok(ExifTag::GPSDateStamp),
             x if x == ExifTag::GPSDifferential.code() => Ok(ExifTag::GPSDifferential),
+            x if x == ExifTag::NewSubfileType.code() => Ok(ExifTag::NewSubfileType),
+            x if x == ExifTag::SubfileType.code() => Ok(ExifTag::SubfileType),
+            x if x == ExifTag::BitsPerSample.code() => Ok(ExifTag::BitsPerSample),
+            x if x == ExifTag::Compression.code() => Ok(ExifTag::Compression),
+            x if x == ExifTag::PhotometricInterpretation.code() => {
+                Ok(ExifTag::PhotometricInterpretation)
+            }
+            x if x == ExifTag::StripOffsets.code() => Ok(ExifTag::StripOffsets),
+            x if x == ExifTag::SamplesPerPixel.code() => Ok(ExifTag::SamplesPerPixel),
+            x if x == ExifTag::RowsPerStrip.code() => Ok(ExifTag::RowsPerStrip),
+            x if x == ExifTag::StripByteCounts.code() => Ok(ExifTag::StripByteCounts),
+            x if x == ExifTag::PlanarConfiguration.code() => Ok(ExifTag::PlanarConfiguration),
+            x if x == ExifTag::TransferFunction.code() => Ok(ExifTag::TransferFunction),
+            x if x == ExifTag::ThumbnailOffset.code() => Ok(ExifTag::ThumbnailOffset),
+            x if x == ExifTag::ThumbnailLength.code() => Ok(ExifTag::ThumbnailLength),
+            x if x == ExifTag::YCbCrSubSampling.code() => Ok(ExifTag::YCbCrSubSampling),
+            x if x == ExifTag::YCbCrPositioning.code() => Ok(ExifTag::YCbCrPositioning),
+            x if x == ExifTag::StandardOutputSensitivity.code() => {
+                Ok(ExifTag::StandardOutputSensitivity)
+            }
+            x if x == ExifTag::RecommendedExposureIndex.code() => {
+                Ok(ExifTag::RecommendedExposureIndex)
+            }
+            x if x == ExifTag::ISOSpeed.code() => Ok(ExifTag::ISOSpeed),
+            x if x == ExifTag::ISOSpeedLatitudeyyy.code() => Ok(ExifTag::ISOSpeedLatitudeyyy),
+            x if x == ExifTag::ISOSpeedLatitudezzz.code() => Ok(ExifTag::ISOSpeedLatitudezzz),
+            x if x == ExifTag::ComponentsConfiguration.code() => {
+                Ok(ExifTag::ComponentsConfiguration)
+            }
+            x if x == ExifTag::CompressedBitsPerPixel.code() => Ok(ExifTag::CompressedBitsPerPixel),
+            x if x == ExifTag::SubSecTime.code() => Ok(ExifTag::SubSecTime),
+            x if x == ExifTag::SubSecTimeOriginal.code() => Ok(ExifTag::SubSecTimeOriginal),
+            x if x == ExifTag::SubSecTimeDigitized.code() => Ok(ExifTag::SubSecTimeDigitized),
+            x if x == ExifTag::AmbientTemperature.code() => Ok(ExifTag::AmbientTemperature),
+            x if x == ExifTag::Humidity.code() => Ok(ExifTag::Humidity),
+            x if x == ExifTag::Pressure.code() => Ok(ExifTag::Pressure),
+            x if x == ExifTag::WaterDepth.code() => Ok(ExifTag::WaterDepth),
+            x if x == ExifTag::Acceleration.code() => Ok(ExifTag::Acceleration),
+            x if x == ExifTag::CameraElevationAngle.code() => Ok(ExifTag::CameraElevationAngle),
+            x if x == ExifTag::InteropOffset.code() => Ok(ExifTag::InteropOffset),
+            x if x == ExifTag::SpatialFrequencyResponse.code() => {
+                Ok(ExifTag::SpatialFrequencyResponse)
+            }
+            x if x == ExifTag::CameraOwnerName.code() => Ok(ExifTag::CameraOwnerName),
+            x if x == ExifTag::BodySerialNumber.code() => Ok(ExifTag::BodySerialNumber),
+            x if x == ExifTag::LensSerialNumber.code() => Ok(ExifTag::LensSerialNumber),
+            x if x == ExifTag::ImageTitle.code() => Ok(ExifTag::ImageTitle),
+            x if x == ExifTag::Photographer.code() => Ok(ExifTag::Photographer),
+            x if x == ExifTag::ImageEditor.code() => Ok(ExifTag::ImageEditor),
+            x if x == ExifTag::CameraFirmware.code() => Ok(ExifTag::CameraFirmware),
+            x if x == ExifTag::RAWDevelopingSoftware.code() => Ok(ExifTag::RAWDevelopingSoftware),
+            x if x == ExifTag::ImageEditingSoftware.code() => Ok(ExifTag::ImageEditingSoftware),
+            x if x == ExifTag::MetadataEditingSoftware.code() => {
+                Ok(ExifTag::MetadataEditingSoftware)
+            }
+            x if x == ExifTag::CompositeImage.code() => Ok(ExifTag::CompositeImage),
+            x if x == ExifTag::SourceImageNumberOfCompositeImage.code() => {
+                Ok(ExifTag::SourceImageNumberOfCompositeImage)
+            }
+            x if x == ExifTag::SourceExposureTimesOfCompositeImage.code() => {
+                Ok(ExifTag::SourceExposureTimesOfCompositeImage)
+            }
+            x if x == ExifTag::GPSHPositioningError.code() => Ok(ExifTag::GPSHPositioningError),
             v => Err(format!("Unrecognized ExifTag 0x{v:04x}").into()),
         }
     }
 }
 
-#[allow(unused)]
+/// Values of the [`ExifTag::Orientation`] tag, which tell how the stored
+/// pixels must be transformed to display the image upright.
+///
+/// To display the image, mirror it horizontally first if
+/// [`Self::is_mirrored`], then rotate it clockwise by [`Self::degrees`].
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let orientation = Orientation::try_from(6).unwrap();
+/// assert_eq!(orientation, Orientation::Rotate90);
+/// assert_eq!(orientation.degrees(), 90);
+/// assert!(!orientation.is_mirrored());
+/// assert_eq!(orientation.apply_to_dimensions(4032, 3024), (3024, 4032));
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u16)]
 pub enum Orientation {
-    Horizontal,
-    MirrorHorizontal,
-    Rotate,
-    MirrorVertical,
-    MirrorHorizontalRotate270,
-    Rotate90,
-    MirrorHorizontalRotate90,
-    Rotate270,
+    /// 1: No transformation needed.
+    #[default]
+    Horizontal = 1,
+    /// 2: Mirrored horizontally.
+    MirrorHorizontal = 2,
+    /// 3: Rotated by 180°.
+    Rotate180 = 3,
+    /// 4: Mirrored vertically, i.e. mirrored horizontally and rotated by 180°.
+    MirrorVertical = 4,
+    /// 5: Mirrored horizontally and rotated by 270° clockwise (transposed).
+    MirrorHorizontalRotate270 = 5,
+    /// 6: Rotated by 90° clockwise.
+    Rotate90 = 6,
+    /// 7: Mirrored horizontally and rotated by 90° clockwise (transversed).
+    MirrorHorizontalRotate90 = 7,
+    /// 8: Rotated by 270° clockwise.
+    Rotate270 = 8,
+}
+
+impl Orientation {
+    /// Returns the raw value (1-8) of the Orientation tag.
+    pub const fn value(self) -> u16 {
+        self as u16
+    }
+
+    /// Returns the clockwise rotation (0, 90, 180 or 270 degrees) needed to
+    /// display the image, after mirroring it if [`Self::is_mirrored`].
+    pub const fn degrees(self) -> u16 {
+        match self {
+            Orientation::Horizontal | Orientation::MirrorHorizontal => 0,
+            Orientation::Rotate90 | Orientation::MirrorHorizontalRotate90 => 90,
+            Orientation::Rotate180 | Orientation::MirrorVertical => 180,
+            Orientation::Rotate270 | Orientation::MirrorHorizontalRotate270 => 270,
+        }
+    }
+
+    /// Returns true if the image must be mirrored horizontally (before
+    /// rotating it) to be displayed.
+    pub const fn is_mirrored(self) -> bool {
+        matches!(
+            self,
+            Orientation::MirrorHorizontal
+                | Orientation::MirrorVertical
+                | Orientation::MirrorHorizontalRotate270
+                | Orientation::MirrorHorizontalRotate90
+        )
+    }
+
+    /// Returns the displayed size of an image whose stored pixels are
+    /// `width` x `height`, i.e. width and height are swapped for the
+    /// orientations rotated by 90 or 270 degrees.
+    pub const fn apply_to_dimensions(self, width: u32, height: u32) -> (u32, u32) {
+        match self.degrees() {
+            90 | 270 => (height, width),
+            _ => (width, height),
+        }
+    }
+}
+
+impl TryFrom<u16> for Orientation {
+    type Error = crate::Error;
+    fn try_from(v: u16) -> Result<Self, Self::Error> {
+        let orientation = match v {
+            1 => Orientation::Horizontal,
+            2 => Orientation::MirrorHorizontal,
+            3 => Orientation::Rotate180,
+            4 => Orientation::MirrorVertical,
+            5 => Orientation::MirrorHorizontalRotate270,
+            6 => Orientation::Rotate90,
+            7 => Orientation::MirrorHorizontalRotate90,
+            8 => Orientation::Rotate270,
+            v => return Err(format!("invalid orientation: {v}").into()),
+        };
+        Ok(orientation)
+    }
+}
+
+impl From<Orientation> for u16 {
+    fn from(v: Orientation) -> Self {
+        v.value()
+    }
+}
+
+impl From<Orientation> for crate::EntryValue {
+    fn from(v: Orientation) -> Self {
+        crate::EntryValue::U16(v.value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(1, 0, false, (4, 3))]
+    #[test_case(2, 0, true, (4, 3))]
+    #[test_case(3, 180, false, (4, 3))]
+    #[test_case(4, 180, true, (4, 3))]
+    #[test_case(5, 270, true, (3, 4))]
+    #[test_case(6, 90, false, (3, 4))]
+    #[test_case(7, 90, true, (3, 4))]
+    #[test_case(8, 270, false, (3, 4))]
+    fn orientation(value: u16, degrees: u16, mirrored: bool, dimensions: (u32, u32)) {
+        let orientation = Orientation::try_from(value).unwrap();
+        assert_eq!(orientation.value(), value);
+        assert_eq!(orientation.degrees(), degrees);
+        assert_eq!(orientation.is_mirrored(), mirrored);
+        assert_eq!(orientation.apply_to_dimensions(4, 3), dimensions);
+    }
+
+    #[test_case(0)]
+    #[test_case(9)]
+    fn invalid_orientation(value: u16) {
+        Orientation::try_from(value).unwrap_err();
+    }
 }
@@ -0,0 +1,326 @@
+use core::fmt::Display;
+
+use crate::{error::Diagnostics, prelude::*, values::EntryError, ExifTag, Warning};
+
+use super::{ExifIter, IfdKind, TagType};
+
+/// A deviation from the Exif standard, as found by [`ExifIter::validate`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// A tag which the standard requires is missing.
+    MissingTag { ifd: IfdKind, tag: ExifTag },
+
+    /// An entry is stored with another data type than the standard one,
+    /// e.g. `Orientation` as a LONG. `data_type` is the type code of the
+    /// entry.
+    WrongType {
+        tag: ExifTag,
+        expected: TagType,
+        data_type: u16,
+    },
+
+    /// An entry hasn't the number of components (not bytes) the standard
+    /// requires, e.g. `GPSLatitude` with 2 rationals instead of 3.
+    WrongCount {
+        tag: ExifTag,
+        expected: u32,
+        count: u32,
+    },
+
+    /// A tag of the main image is stored in another IFD than the standard
+    /// one, e.g. `DateTimeOriginal` in IFD0 rather than in the Exif IFD.
+    WrongIfd {
+        tag: ExifTag,
+        expected: IfdKind,
+        found: IfdKind,
+    },
+
+    /// The value of an entry couldn't be parsed, e.g. its data type is
+    /// invalid, or it points out of the data. `name` is the tag (e.g.
+    /// `"Make(0x010f)"`).
+    InvalidEntry { name: String, reason: String },
+
+    /// The structure of the data is broken, e.g. an offset points out of the
+    /// data, or an IFD declares more entries than it holds. These anomalies
+    /// are also reported as [`Warning`]s when parsing.
+    Structure(Warning),
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Finding::MissingTag { ifd, tag } => write!(f, "{tag} is missing in {ifd:?}"),
+            Finding::WrongType {
+                tag,
+                expected,
+                data_type,
+            } => write!(f, "{tag} has data type {data_type}, expected {expected:?}"),
+            Finding::WrongCount {
+                tag,
+                expected,
+                count,
+            } => write!(f, "{tag} has {count} components, expected {expected}"),
+            Finding::WrongIfd {
+                tag,
+                expected,
+                found,
+            } => write!(f, "{tag} is in {found:?}, expected {expected:?}"),
+            Finding::InvalidEntry { name, reason } => write!(f, "invalid {name}; {reason}"),
+            Finding::Structure(warning) => warning.fmt(f),
+        }
+    }
+}
+
+/// Tags which the Exif standard (2.32, section 4.6.8) requires for the
+/// main image, whether it's compressed or not.
+const MANDATORY: [(IfdKind, ExifTag); 6] = [
+    (IfdKind::Ifd0, ExifTag::XResolution),
+    (IfdKind::Ifd0, ExifTag::YResolution),
+    (IfdKind::Ifd0, ExifTag::ResolutionUnit),
+    (IfdKind::Ifd0, ExifTag::ExifOffset),
+    (IfdKind::Exif, ExifTag::ExifVersion),
+    (IfdKind::Exif, ExifTag::ColorSpace),
+];
+
+/// Numbers of components of the tags whose count is fixed by the standard.
+/// ASCII counts include the terminating NUL.
+const COUNTS: [(ExifTag, u32); 18] = [
+    (ExifTag::Orientation, 1),
+    (ExifTag::XResolution, 1),
+    (ExifTag::YResolution, 1),
+    (ExifTag::ResolutionUnit, 1),
+    (ExifTag::ModifyDate, 20),
+    (ExifTag::ExifVersion, 4),
+    (ExifTag::DateTimeOriginal, 20),
+    (ExifTag::CreateDate, 20),
+    (ExifTag::ComponentsConfiguration, 4),
+    (ExifTag::ColorSpace, 1),
+    (ExifTag::GPSVersionID, 4),
+    (ExifTag::GPSLatitudeRef, 2),
+    (ExifTag::GPSLatitude, 3),
+    (ExifTag::GPSLongitudeRef, 2),
+    (ExifTag::GPSLongitude, 3),
+    (ExifTag::GPSAltitudeRef, 1),
+    (ExifTag::GPSTimeStamp, 3),
+    (ExifTag::GPSDateStamp, 11),
+];
+
+impl ExifIter<'_> {
+    /// Checks the Exif data against the Exif standard, e.g. for camera
+    /// firmware QA or before ingesting files into an archive, and returns
+    /// the deviations found, in the order of the entries. Nothing is
+    /// returned for conforming data.
+    ///
+    /// It checks that:
+    ///
+    /// - the tags required for the main image are present (resolution,
+    ///   Exif version and color space; `GPSVersionID` if there is a GPS
+    ///   IFD),
+    /// - recognized tags have their standard data type and, for tags with a
+    ///   fixed size (e.g. `GPSLatitude`), count,
+    /// - recognized tags of the main image are in their standard IFD,
+    /// - all the entries can be parsed, and all the offsets are in bounds.
+    ///
+    /// The iterator's state isn't affected, and the warnings aren't passed
+    /// to its [`MediaParser`](crate::MediaParser).
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let iter = parse_exif(f, None).unwrap().unwrap();
+    /// for finding in iter.validate() {
+    ///     println!("{finding}");
+    /// }
+    /// ```
+    pub fn validate(&self) -> Vec<Finding> {
+        let diagnostics = Diagnostics::new();
+        let mut iter = self.with_diagnostics(diagnostics.clone());
+        let mut findings = Vec::new();
+        let mut present = Vec::new();
+
+        for entry in iter.by_ref() {
+            let kind = entry.ifd_kind();
+            match entry.take_result() {
+                // A limitation of this parser rather than of the data
+                Err(crate::Error::InvalidEntry(e))
+                    if matches!(e.downcast_ref(), Some(EntryError::Unsupported(_))) => {}
+                Err(e) => findings.push(Finding::InvalidEntry {
+                    name: entry.tag_name(),
+                    reason: e.to_string(),
+                }),
+                Ok(_) => (),
+            }
+            let Some(tag) = entry.tag() else {
+                continue;
+            };
+            if entry.ifd_index() == 0 {
+                present.push((kind, tag));
+            }
+
+            let info = tag.info();
+            if !accepts(info.value_type, entry.data_type()) {
+                findings.push(Finding::WrongType {
+                    tag,
+                    expected: info.value_type,
+                    data_type: entry.data_type(),
+                });
+            }
+            if let Some(&(_, expected)) = COUNTS.iter().find(|x| x.0 == tag) {
+                if entry.count() != expected {
+                    findings.push(Finding::WrongCount {
+                        tag,
+                        expected,
+                        count: entry.count(),
+                    });
+                }
+            }
+            let main = matches!(kind, IfdKind::Ifd0 | IfdKind::Exif | IfdKind::Gps);
+            if main && entry.ifd_index() == 0 && kind != info.ifd {
+                findings.push(Finding::WrongIfd {
+                    tag,
+                    expected: info.ifd,
+                    found: kind,
+                });
+            }
+        }
+
+        let has_gps = present.iter().any(|x| x.0 == IfdKind::Gps);
+        let gps = has_gps.then_some((IfdKind::Gps, ExifTag::GPSVersionID));
+        for (ifd, tag) in MANDATORY.into_iter().chain(gps) {
+            if !present.contains(&(ifd, tag)) {
+                findings.push(Finding::MissingTag { ifd, tag });
+            }
+        }
+
+        let warnings = iter
+            .take_skipped_ifds()
+            .into_iter()
+            .chain(diagnostics.take());
+        // Entries which couldn't be parsed have been reported already
+        let warnings = warnings.filter(|x| !matches!(x, Warning::SkippedEntry { .. }));
+        findings.extend(warnings.map(Finding::Structure));
+        findings
+    }
+}
+
+/// Returns true if the type code `data_type` is the one of `expected`.
+fn accepts(expected: TagType, data_type: u16) -> bool {
+    let code = match expected {
+        TagType::ShortOrLong => return matches!(data_type, 3 | 4),
+        TagType::Byte => 1,
+        TagType::Ascii => 2,
+        TagType::Short => 3,
+        TagType::Long => 4,
+        TagType::Rational => 5,
+        TagType::SByte => 6,
+        TagType::Undefined => 7,
+        TagType::SShort => 8,
+        TagType::SLong => 9,
+        TagType::SRational => 10,
+        TagType::Float => 11,
+        TagType::Double => 12,
+    };
+    data_type == code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exif::input_to_iter, parse_exif, testkit::*, write_metadata, MetadataEdits};
+    use test_case::test_case;
+
+    #[test_case("exif.jpg", &[
+        // Quirks of the camera
+        Finding::WrongType {
+            tag: ExifTag::UserComment,
+            expected: TagType::Undefined,
+            data_type: 2,
+        },
+        Finding::WrongType {
+            tag: ExifTag::GPSLatitude,
+            expected: TagType::Rational,
+            data_type: 10,
+        },
+        Finding::WrongType {
+            tag: ExifTag::GPSLongitude,
+            expected: TagType::Rational,
+            data_type: 10,
+        },
+        Finding::MissingTag { ifd: IfdKind::Gps, tag: ExifTag::GPSVersionID },
+    ])]
+    #[test_case("exif.heic", &[
+        Finding::MissingTag { ifd: IfdKind::Gps, tag: ExifTag::GPSVersionID },
+    ])]
+    fn validate_samples(path: &str, expected: &[Finding]) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(iter.validate(), expected);
+    }
+
+    #[test]
+    fn validate_edited() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample("exif.jpg").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits
+            .remove_exif(ExifTag::ColorSpace)
+            // A LONG rather than a SHORT
+            .set_exif(ExifTag::Orientation, 1u32);
+        let mut out = Vec::new();
+        write_metadata(&data[..], &mut out, &edits).unwrap();
+
+        let original = parse_exif(&data[..], None).unwrap().unwrap().validate();
+        let iter = parse_exif(&out[..], None).unwrap().unwrap();
+        let findings = iter.validate();
+        assert_eq!(
+            findings
+                .into_iter()
+                .filter(|x| !original.contains(x))
+                .collect::<Vec<_>>(),
+            [
+                Finding::WrongType {
+                    tag: ExifTag::Orientation,
+                    expected: TagType::Short,
+                    data_type: 4,
+                },
+                Finding::MissingTag {
+                    ifd: IfdKind::Exif,
+                    tag: ExifTag::ColorSpace,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_structure() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // IFD0 with 2 entries (the second one declared but missing): a
+        // GPSLatitude with 2 components, pointing out of the data
+        let mut data = b"II*\0\x08\0\0\0\x02\0".to_vec();
+        data.extend([0x02, 0x00, 0x05, 0x00, 0x02, 0, 0, 0, 0xff, 0, 0, 0]);
+        let iter = input_to_iter(&data[..]).unwrap();
+        let findings = iter.validate();
+        assert!(findings.contains(&Finding::WrongCount {
+            tag: ExifTag::GPSLatitude,
+            expected: 3,
+            count: 2,
+        }));
+        assert!(findings.contains(&Finding::Structure(Warning::EntryCount {
+            kind: IfdKind::Ifd0,
+            declared: 2,
+            available: 1,
+        })));
+        assert!(findings
+            .iter()
+            .any(|x| matches!(x, Finding::InvalidEntry { .. })));
+    }
+}
@@ -0,0 +1,445 @@
+//! Exports the metadata of many files as CSV or TSV, one row per file with
+//! a fixed set of columns (see [`COLUMNS`]), e.g. to load the results of
+//! [`batch::parse_dir`](crate::batch) in a spreadsheet or a dataframe.
+//!
+//! GPS tracks, i.e. [`TrackPoint`]s, can be written as GPX 1.1 with
+//! [`write_gpx`], or as KML with [`write_kml`].
+//!
+//! # Usage
+//!
+//! ```rust
+//! use nom_exif::{export::*, *};
+//!
+//! let mut w = CsvWriter::new(Vec::new());
+//! for path in ["./testdata/exif.jpg", "./testdata/meta.mov"] {
+//!     let info = parse_media(std::fs::File::open(path).unwrap()).unwrap();
+//!     w.write(&Row::new(path, info)).unwrap();
+//! }
+//!
+//! let csv = String::from_utf8(w.finish().unwrap()).unwrap();
+//! let mut lines = csv.lines();
+//! assert_eq!(
+//!     lines.next().unwrap(),
+//!     "path,make,model,datetime,lat,lon,width,height,duration"
+//! );
+//! assert!(lines.next().unwrap().starts_with("./testdata/exif.jpg,vivo,vivo X90 Pro+,"));
+//! assert!(lines.next().unwrap().ends_with(",720,1280,0.5"));
+//! ```
+
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+
+use crate::{
+    mov::{CREATIONDATE_KEY, LOCATION_KEY, MAKE_KEY, MODEL_KEY},
+    Exif, ExifTag, GPSInfo, MediaInfo, TrackPoint,
+};
+
+/// The columns of the exported rows, in order.
+pub const COLUMNS: [&str; 9] = [
+    "path", "make", "model", "datetime", "lat", "lon", "width", "height", "duration",
+];
+
+/// The exported metadata of a file. The fields are empty when the file
+/// doesn't have the metadata, or when it couldn't be parsed.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Row {
+    pub path: PathBuf,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// The capture time, see [`Exif::datetime_original`], or the
+    /// `com.apple.quicktime.creationdate` of videos. Exported as RFC 3339.
+    pub datetime: Option<DateTime<FixedOffset>>,
+    /// Latitude in degrees, negative in the southern hemisphere.
+    pub lat: Option<f64>,
+    /// Longitude in degrees, negative in the western hemisphere.
+    pub lon: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Duration of videos, in seconds.
+    pub duration: Option<f64>,
+}
+
+impl Row {
+    /// Extracts the columns from the metadata of the file at `path`, as
+    /// returned by [`parse_media`](crate::parse_media).
+    pub fn new(path: impl Into<PathBuf>, info: Option<MediaInfo>) -> Self {
+        let mut row = Row {
+            path: path.into(),
+            ..Default::default()
+        };
+        let gps = match info {
+            Some(MediaInfo::Exif(iter)) => {
+                let exif = Exif::from(iter);
+                let text = |tag| Some(exif.get(tag)?.as_str()?.trim().to_owned());
+                row.make = text(ExifTag::Make);
+                row.model = text(ExifTag::Model);
+                row.datetime = exif.datetime_original();
+                (row.width, row.height) = exif.image_size().unzip();
+                exif.get_gps_info().ok().flatten()
+            }
+            Some(MediaInfo::Track(iter)) => {
+                let get = |key: &str| iter.get(key).and_then(|x| x.ok());
+                let text = |key| Some(get(key)?.as_str()?.trim().to_owned());
+                row.make = text(MAKE_KEY);
+                row.model = text(MODEL_KEY);
+                row.datetime = get(CREATIONDATE_KEY).and_then(|x| x.as_time());
+                row.width = get("width").and_then(|x| x.to_u32());
+                row.height = get("height").and_then(|x| x.to_u32());
+                row.duration = get("duration")
+                    .and_then(|x| x.to_u32())
+                    .map(|ms| f64::from(ms) / 1000.0);
+                text(LOCATION_KEY).and_then(|x| GPSInfo::from_iso6709(&x).ok())
+            }
+            None => None,
+        };
+        (row.lat, row.lon) = gps.map(|x| (x.to_decimal().0, x.to_decimal().1)).unzip();
+        row
+    }
+
+    /// Returns the row of a file which couldn't be parsed: all the fields but
+    /// the path are empty.
+    pub fn failed(path: impl Into<PathBuf>) -> Self {
+        Self::new(path, None)
+    }
+
+    fn fields(&self) -> [String; COLUMNS.len()] {
+        fn opt<T: Display>(x: &Option<T>) -> String {
+            x.as_ref().map(|x| x.to_string()).unwrap_or_default()
+        }
+        [
+            self.path.display().to_string(),
+            opt(&self.make),
+            opt(&self.model),
+            opt(&self.datetime.map(|x| x.to_rfc3339())),
+            opt(&self.lat),
+            opt(&self.lon),
+            opt(&self.width),
+            opt(&self.height),
+            opt(&self.duration),
+        ]
+    }
+}
+
+/// Writes [`Row`]s as CSV (RFC 4180) or TSV, preceded by a header line with
+/// the [`COLUMNS`].
+#[derive(Debug)]
+pub struct CsvWriter<W> {
+    writer: W,
+    delimiter: char,
+    header: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Creates a CSV writer: fields are separated by commas, and quoted if
+    /// needed.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            delimiter: ',',
+            header: false,
+        }
+    }
+
+    /// Creates a TSV writer: fields are separated by tabs, and the tabs and
+    /// line breaks of the fields are replaced with spaces.
+    pub fn tsv(writer: W) -> Self {
+        Self {
+            delimiter: '\t',
+            ..Self::new(writer)
+        }
+    }
+
+    /// Writes `row`, after the header line if it's the first one.
+    pub fn write(&mut self, row: &Row) -> io::Result<()> {
+        if !self.header {
+            self.write_line(COLUMNS.map(String::from))?;
+            self.header = true;
+        }
+        self.write_line(row.fields())
+    }
+
+    /// Writes the results of many files, e.g. of
+    /// [`batch::parse_dir`](crate::batch): the files which couldn't be
+    /// parsed are written with empty fields (see [`Row::failed`]). Returns
+    /// the number of written rows.
+    pub fn write_all<I, P>(&mut self, results: I) -> io::Result<usize>
+    where
+        I: IntoIterator<Item = (P, crate::Result<Option<MediaInfo>>)>,
+        P: Into<PathBuf>,
+    {
+        let mut count = 0;
+        for (path, res) in results {
+            let row = match res {
+                Ok(info) => Row::new(path, info),
+                Err(_) => Row::failed(path),
+            };
+            self.write(&row)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Writes the header line if there were no rows, flushes and returns
+    /// the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.header {
+            self.write_line(COLUMNS.map(String::from))?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_line(&mut self, fields: [String; COLUMNS.len()]) -> io::Result<()> {
+        let line = fields
+            .iter()
+            .map(|x| self.escape(x))
+            .collect::<Vec<_>>()
+            .join(&self.delimiter.to_string());
+        writeln!(self.writer, "{line}")
+    }
+
+    fn escape(&self, field: &str) -> String {
+        if self.delimiter == '\t' {
+            field.replace(['\t', '\n', '\r'], " ")
+        } else if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_owned()
+        }
+    }
+}
+
+/// Writes `points` as a GPX 1.1 track. A new `<trkseg>` is started whenever
+/// the [`TrackPoint::segment`] changes, so the output can be read back with
+/// [`GpxTrack::parse`](crate::GpxTrack::parse).
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::{export::write_gpx, *};
+///
+/// let track = GpxTrack::parse(r#"<gpx><trk><trkseg>
+///     <trkpt lat="22.5" lon="114.0"><time>2023-07-09T12:30:00Z</time></trkpt>
+/// </trkseg></trk></gpx>"#).unwrap();
+///
+/// let mut gpx = Vec::new();
+/// write_gpx(&mut gpx, track.points()).unwrap();
+/// assert_eq!(GpxTrack::parse(std::str::from_utf8(&gpx).unwrap()).unwrap(), track);
+/// ```
+pub fn write_gpx<W: Write>(mut writer: W, points: &[TrackPoint]) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<gpx version="1.1" creator="nom-exif" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )?;
+    writeln!(writer, "<trk>")?;
+    for (i, segment) in segments(points).enumerate() {
+        if i > 0 {
+            writeln!(writer, "</trkseg>")?;
+        }
+        writeln!(writer, "<trkseg>")?;
+        for p in segment {
+            write!(
+                writer,
+                r#"<trkpt lat="{}" lon="{}">"#,
+                p.latitude, p.longitude
+            )?;
+            if let Some(ele) = p.elevation {
+                write!(writer, "<ele>{ele}</ele>")?;
+            }
+            writeln!(writer, "<time>{}</time></trkpt>", utc(p))?;
+        }
+    }
+    if !points.is_empty() {
+        writeln!(writer, "</trkseg>")?;
+    }
+    writeln!(writer, "</trk>")?;
+    writeln!(writer, "</gpx>")?;
+    writer.flush()
+}
+
+/// Writes `points` as a KML document, with a `<gx:Track>` per
+/// [`TrackPoint::segment`], so that the times are kept. The altitude is 0
+/// when the elevation is unknown.
+pub fn write_kml<W: Write>(mut writer: W, points: &[TrackPoint]) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2">"#
+    )?;
+    writeln!(writer, "<Document>")?;
+    writeln!(writer, "<Placemark>")?;
+    writeln!(writer, "<gx:MultiTrack>")?;
+    for segment in segments(points) {
+        writeln!(writer, "<gx:Track>")?;
+        for p in segment {
+            writeln!(writer, "<when>{}</when>", utc(p))?;
+        }
+        for p in segment {
+            let alt = p.elevation.unwrap_or_default();
+            writeln!(
+                writer,
+                "<gx:coord>{} {} {alt}</gx:coord>",
+                p.longitude, p.latitude
+            )?;
+        }
+        writeln!(writer, "</gx:Track>")?;
+    }
+    writeln!(writer, "</gx:MultiTrack>")?;
+    writeln!(writer, "</Placemark>")?;
+    writeln!(writer, "</Document>")?;
+    writeln!(writer, "</kml>")?;
+    writer.flush()
+}
+
+/// Splits `points` into runs with the same segment.
+fn segments(points: &[TrackPoint]) -> impl Iterator<Item = &[TrackPoint]> {
+    points.chunk_by(|a, b| a.segment == b.segment)
+}
+
+fn utc(p: &TrackPoint) -> String {
+    p.time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_media, testkit::open_sample, GpxTrack};
+    use test_case::test_case;
+
+    #[test_case(
+        "exif.jpg",
+        "vivo,vivo X90 Pro+,2023-07-09T20:36:33.616+08:00,22.5311",
+        ",3072,4096,"
+    )]
+    #[test_case(
+        "exif.heic",
+        "Apple,iPhone 12 Pro,2022-07-22T21:26:32.092+08:00,43.2901",
+        ",4032,3024,"
+    )]
+    #[test_case(
+        "meta.mov",
+        "Apple,iPhone X,2019-02-12T15:27:12+08:00,27.1281,100.2508",
+        ",720,1280,0.5"
+    )]
+    #[test_case("no-exif.jpg", ",,,,", ",,,")]
+    fn export_row(path: &str, start: &str, end: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let info = parse_media(open_sample(path).unwrap()).unwrap();
+        let mut w = CsvWriter::new(Vec::new());
+        w.write(&Row::new(path, info)).unwrap();
+        let csv = String::from_utf8(w.finish().unwrap()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), COLUMNS.join(","));
+        let line = lines.next().unwrap();
+        assert!(line.starts_with(&format!("{path},{start}")), "{line}");
+        assert!(line.ends_with(end), "{line}");
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn export_escape() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let row = Row {
+            make: Some("Acme, \"Inc\"".into()),
+            model: Some("X\t1".into()),
+            ..Row::failed("a.jpg")
+        };
+
+        let mut w = CsvWriter::new(Vec::new());
+        w.write(&row).unwrap();
+        let csv = String::from_utf8(w.finish().unwrap()).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "a.jpg,\"Acme, \"\"Inc\"\"\",X\t1,,,,,,"
+        );
+
+        let mut w = CsvWriter::tsv(Vec::new());
+        w.write(&row).unwrap();
+        let tsv = String::from_utf8(w.finish().unwrap()).unwrap();
+        assert_eq!(
+            tsv.lines().nth(1).unwrap(),
+            "a.jpg\tAcme, \"Inc\"\tX 1\t\t\t\t\t\t"
+        );
+
+        let tsv = String::from_utf8(CsvWriter::tsv(Vec::new()).finish().unwrap()).unwrap();
+        assert_eq!(tsv, format!("{}\n", COLUMNS.join("\t")));
+    }
+
+    #[test]
+    fn export_all() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let results = ["exif.jpg", "missing.jpg"].map(|path| {
+            let res = open_sample(path)
+                .map_err(crate::Error::from)
+                .and_then(parse_media);
+            (path, res)
+        });
+        let mut w = CsvWriter::new(Vec::new());
+        assert_eq!(w.write_all(results).unwrap(), 2);
+        let csv = String::from_utf8(w.finish().unwrap()).unwrap();
+        assert_eq!(csv.lines().nth(2).unwrap(), "missing.jpg,,,,,,,,");
+    }
+
+    fn track() -> Vec<TrackPoint> {
+        let point = |time: &str, latitude, longitude, elevation, segment| TrackPoint {
+            time: time.parse().unwrap(),
+            latitude,
+            longitude,
+            elevation,
+            segment,
+        };
+        vec![
+            point("2023-07-09T12:30:00Z", 22.5, 114.0, Some(12.5), 1),
+            point("2023-07-09T12:30:01.5Z", 22.5001, 114.0002, None, 1),
+            point("2023-07-09T13:00:00Z", -33.8568, 151.2153, Some(-3.0), 2),
+        ]
+    }
+
+    #[test]
+    fn export_gpx() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut gpx = Vec::new();
+        write_gpx(&mut gpx, &track()).unwrap();
+        let gpx = String::from_utf8(gpx).unwrap();
+        assert_eq!(gpx.matches("<trkseg>").count(), 2);
+        assert!(gpx.contains(
+            r#"<trkpt lat="22.5001" lon="114.0002"><time>2023-07-09T12:30:01.500Z</time></trkpt>"#
+        ));
+        assert_eq!(GpxTrack::parse(&gpx).unwrap().points(), track());
+
+        let mut gpx = Vec::new();
+        write_gpx(&mut gpx, &[]).unwrap();
+        let gpx = String::from_utf8(gpx).unwrap();
+        assert!(gpx.contains("<trk>\n</trk>"), "{gpx}");
+        assert!(GpxTrack::parse(&gpx).unwrap().points().is_empty());
+    }
+
+    #[test]
+    fn export_kml() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut kml = Vec::new();
+        write_kml(&mut kml, &track()).unwrap();
+        let kml = String::from_utf8(kml).unwrap();
+        assert_eq!(kml.matches("<gx:Track>").count(), 2);
+        assert!(kml.contains(
+            "<when>2023-07-09T12:30:00Z</when>\n\
+             <when>2023-07-09T12:30:01.500Z</when>\n\
+             <gx:coord>114 22.5 12.5</gx:coord>\n\
+             <gx:coord>114.0002 22.5001 0</gx:coord>\n"
+        ));
+        assert!(kml.contains("<gx:coord>151.2153 -33.8568 -3</gx:coord>"));
+        assert!(kml.trim_end().ends_with("</kml>"));
+    }
+}
@@ -1,11 +1,14 @@
+use core::fmt::Display;
 use nom::{bytes::complete, multi::many0, IResult};
-use std::{fmt::Display, io::Read};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek, SeekFrom};
 use FileFormat::*;
 
 use crate::{
     bbox::BoxHolder,
-    heif,
+    ebml, heif,
     jpeg::{self, check_jpeg},
+    prelude::*,
 };
 
 const HEIF_FTYPS: &[&[u8]] = &[
@@ -30,6 +33,7 @@ const QT_BRAND_NAMES: &[&str] = &["qt  ", "mqt "];
 
 #[allow(unused)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum FileFormat {
     Jpeg,
     Heif,
@@ -43,18 +47,31 @@ pub enum FileFormat {
     // in that atom.
     QuickTime,
     MP4,
+
+    /// Matroska (MKV) and WebM files, whose tags and tracks are read like
+    /// the metadata of QuickTime/MP4 files.
+    Matroska,
 }
 
+/// Number of bytes read to detect the format of a file.
+#[cfg(feature = "std")]
+const SNIFF_SIZE: usize = 4096;
+
 impl FileFormat {
+    #[cfg(feature = "std")]
     pub fn try_from_read<T: Read>(reader: T) -> crate::Result<Self> {
-        const BUF_SIZE: usize = 4096;
-        let mut buf = Vec::with_capacity(BUF_SIZE);
-        let n = reader.take(BUF_SIZE as u64).read_to_end(buf.as_mut())?;
-        if n == 0 {
-            Err("file is empty")?;
-        }
+        read_head(reader)?.as_slice().try_into()
+    }
 
-        buf.as_slice().try_into()
+    /// Detects the format of the file in `reader`, which must be at the
+    /// start of the file. The bytes which have been read are kept in the
+    /// returned reader, so the parsers don't read them again, nor rewind
+    /// `reader`.
+    #[cfg(feature = "std")]
+    pub(crate) fn sniff<R: Read>(mut reader: R) -> crate::Result<(Self, SniffedReader<R>)> {
+        let head = read_head(reader.by_ref())?;
+        let ff = head.as_slice().try_into()?;
+        Ok((ff, SniffedReader::new(head, reader)))
     }
 
     pub(crate) fn extract_exif_data<'a>(
@@ -68,6 +85,20 @@ impl FileFormat {
                 nom::error::context("no exif data in QuickTime file", nom::combinator::fail)(input)
             }
             MP4 => nom::error::context("no exif data in MP4 file", nom::combinator::fail)(input),
+            Matroska => {
+                nom::error::context("no exif data in Matroska file", nom::combinator::fail)(input)
+            }
+        }
+    }
+
+    /// Like [`Self::extract_exif_data`], for a file which ends within its
+    /// exif data: returns the available part of the exif data, if it can be
+    /// found. Only supported for JPEG files.
+    #[cfg(feature = "std")]
+    pub(crate) fn extract_partial_exif_data<'a>(&self, input: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Jpeg => jpeg::extract_partial_exif_data(input),
+            Heif | QuickTime | MP4 | Matroska => None,
         }
     }
 
@@ -91,11 +122,101 @@ impl FileFormat {
                     Err("not a MP4 file".into())
                 }
             }
+            Matroska => {
+                if is_matroska(input) {
+                    Ok(())
+                } else {
+                    Err("not a Matroska file".into())
+                }
+            }
         }
     }
 }
 
 // Parse the input buffer and detect its file type
+#[cfg(feature = "std")]
+fn read_head<R: Read>(reader: R) -> crate::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(SNIFF_SIZE);
+    let n = reader.take(SNIFF_SIZE as u64).read_to_end(buf.as_mut())?;
+    if n == 0 {
+        Err(crate::Error::Truncated { needed: None })?;
+    }
+    Ok(buf)
+}
+
+/// A reader whose first bytes have been read by [`FileFormat::sniff`]:
+/// they're read again from memory. Seeks are only forwarded to the inner
+/// reader when data after these bytes is read.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct SniffedReader<R> {
+    head: Vec<u8>,
+    inner: R,
+    /// Position in the file.
+    pos: u64,
+    /// Position of `inner` in the file.
+    inner_pos: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R> SniffedReader<R> {
+    fn new(head: Vec<u8>, inner: R) -> Self {
+        let len = head.len() as u64;
+        Self {
+            head,
+            inner,
+            pos: 0,
+            inner_pos: len,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Read for SniffedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(head) = usize::try_from(self.pos)
+            .ok()
+            .and_then(|pos| self.head.get(pos..))
+            .filter(|x| !x.is_empty())
+        {
+            let n = head.len().min(buf.len());
+            buf[..n].copy_from_slice(&head[..n]); // Safe-slice
+            self.pos += n as u64;
+            return Ok(n);
+        }
+
+        if self.inner_pos != self.pos {
+            self.inner_pos = self.inner.seek(SeekFrom::Start(self.pos))?;
+        }
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        self.inner_pos = self.pos;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Seek for SniffedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let offset = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+            SeekFrom::End(_) => {
+                self.inner_pos = self.inner.seek(pos)?;
+                Some(self.inner_pos)
+            }
+        };
+        let Some(offset) = offset else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ));
+        };
+        self.pos = offset;
+        Ok(offset)
+    }
+}
+
 impl TryFrom<&[u8]> for FileFormat {
     type Error = crate::Error;
 
@@ -104,23 +225,169 @@ impl TryFrom<&[u8]> for FileFormat {
             Ok(Self::Jpeg)
         } else if check_heif(input).is_ok() {
             Ok(Self::Heif)
+        } else if is_matroska(input) {
+            Ok(Self::Matroska)
         } else {
-            check_qt_mp4(input)
+            check_qt_mp4(input).map_err(|e| match e {
+                crate::Error::UnsupportedFormat { .. } => e,
+                _ => crate::Error::UnsupportedFormat { detected: None },
+            })
         }
     }
 }
 
 impl Display for FileFormat {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Jpeg => "JPEG".fmt(f),
             Heif => "HEIF/HEIC".fmt(f),
             QuickTime => "QuickTime".fmt(f),
             MP4 => "MP4".fmt(f),
+            Matroska => "Matroska".fmt(f),
+        }
+    }
+}
+
+fn is_matroska(input: &[u8]) -> bool {
+    matches!(ebml::doc_type(input), Some(b"matroska" | b"webm"))
+}
+
+/// The type of a media file, as detected by [`detect_format`].
+///
+/// Unlike [`FileFormat`], which only covers the formats that metadata can be
+/// parsed from, this covers every format the crate recognizes (e.g. PNG and
+/// WebP, which can be stripped by [`strip_metadata`](crate::strip_metadata)),
+/// and tells the MIME type and file extension of each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MediaType {
+    Jpeg,
+    /// HEIF/HEIC image
+    Heif,
+    Png,
+    WebP,
+    Tiff,
+    QuickTime,
+    Mp4,
+    Matroska,
+    WebM,
+}
+
+impl MediaType {
+    /// Returns the MIME type, e.g. `"image/jpeg"`.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            MediaType::Jpeg => "image/jpeg",
+            MediaType::Heif => "image/heic",
+            MediaType::Png => "image/png",
+            MediaType::WebP => "image/webp",
+            MediaType::Tiff => "image/tiff",
+            MediaType::QuickTime => "video/quicktime",
+            MediaType::Mp4 => "video/mp4",
+            MediaType::Matroska => "video/x-matroska",
+            MediaType::WebM => "video/webm",
+        }
+    }
+
+    /// Returns the canonical file extension (without the dot), e.g. `"jpg"`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MediaType::Jpeg => "jpg",
+            MediaType::Heif => "heic",
+            MediaType::Png => "png",
+            MediaType::WebP => "webp",
+            MediaType::Tiff => "tif",
+            MediaType::QuickTime => "mov",
+            MediaType::Mp4 => "mp4",
+            MediaType::Matroska => "mkv",
+            MediaType::WebM => "webm",
+        }
+    }
+
+    /// Returns the corresponding [`FileFormat`], if metadata can be parsed
+    /// from this type of files.
+    pub fn file_format(&self) -> Option<FileFormat> {
+        match self {
+            MediaType::Jpeg => Some(Jpeg),
+            MediaType::Heif => Some(Heif),
+            MediaType::QuickTime => Some(QuickTime),
+            MediaType::Mp4 => Some(MP4),
+            MediaType::Matroska | MediaType::WebM => Some(Matroska),
+            MediaType::Png | MediaType::WebP | MediaType::Tiff => None,
+        }
+    }
+
+    /// Detects the type of `input`, which is the beginning of a file.
+    #[cfg(feature = "std")]
+    fn detect(input: &[u8]) -> Option<MediaType> {
+        if input.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(MediaType::Png)
+        } else if input.len() >= 12 && &input[..4] == b"RIFF" && &input[8..12] == b"WEBP" {
+            Some(MediaType::WebP)
+        } else if input.starts_with(b"II*\0") || input.starts_with(b"MM\0*") {
+            Some(MediaType::Tiff)
+        } else if ebml::doc_type(input) == Some(b"webm") {
+            Some(MediaType::WebM)
+        } else {
+            FileFormat::try_from(input).ok().map(MediaType::from)
+        }
+    }
+}
+
+impl From<FileFormat> for MediaType {
+    fn from(ff: FileFormat) -> Self {
+        match ff {
+            Jpeg => MediaType::Jpeg,
+            Heif => MediaType::Heif,
+            QuickTime => MediaType::QuickTime,
+            MP4 => MediaType::Mp4,
+            Matroska => MediaType::Matroska,
+        }
+    }
+}
+
+impl Display for MediaType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MediaType::Jpeg => "JPEG".fmt(f),
+            MediaType::Heif => "HEIF/HEIC".fmt(f),
+            MediaType::Png => "PNG".fmt(f),
+            MediaType::WebP => "WebP".fmt(f),
+            MediaType::Tiff => "TIFF".fmt(f),
+            MediaType::QuickTime => "QuickTime".fmt(f),
+            MediaType::Mp4 => "MP4".fmt(f),
+            MediaType::Matroska => "Matroska".fmt(f),
+            MediaType::WebM => "WebM".fmt(f),
         }
     }
 }
 
+/// Detects the type of the file in `reader` from its first bytes (at most 4
+/// KiB are read), without parsing any metadata. Returns `None` if the type
+/// isn't recognized, or the file can't be read.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+/// let media_type = detect_format(f).unwrap();
+/// assert_eq!(media_type, MediaType::QuickTime);
+/// assert_eq!(media_type.mime_type(), "video/quicktime");
+/// assert_eq!(media_type.extension(), "mov");
+/// ```
+#[cfg(feature = "std")]
+pub fn detect_format<R: Read>(reader: R) -> Option<MediaType> {
+    const BUF_SIZE: usize = 4096;
+    let mut buf = Vec::with_capacity(BUF_SIZE);
+    reader
+        .take(BUF_SIZE as u64)
+        .read_to_end(buf.as_mut())
+        .ok()?;
+    MediaType::detect(&buf)
+}
+
 pub(crate) fn check_heif(input: &[u8]) -> crate::Result<()> {
     let (ftyp, Some(major_brand)) = get_ftyp_and_major_brand(input)? else {
         return Err("invalid ISOBMFF file; ftyp not found".into());
@@ -160,26 +427,27 @@ pub(crate) fn check_qt_mp4(input: &[u8]) -> crate::Result<FileFormat> {
 
     if QT_BRAND_NAMES
         .iter()
-        .any(|v| compatible_brands.iter().any(|x| v.as_bytes() == *x))
+        .any(|v| compatible_brands.contains(&v.as_bytes()))
     {
         return Ok(FileFormat::QuickTime);
     }
 
     if MP4_BRAND_NAMES
         .iter()
-        .any(|v| compatible_brands.iter().any(|x| v.as_bytes() == *x))
+        .any(|v| compatible_brands.contains(&v.as_bytes()))
     {
         return Ok(FileFormat::MP4);
     }
 
-    Err(format!(
-        "unsupported video file; major brand: '{}'",
-        major_brand.iter().map(|b| *b as char).collect::<String>()
-    )
-    .into())
+    Err(crate::Error::UnsupportedFormat {
+        detected: Some(format!(
+            "ISOBMFF with major brand '{}'",
+            major_brand.iter().map(|b| *b as char).collect::<String>()
+        )),
+    })
 }
 
-fn get_ftyp_and_major_brand(input: &[u8]) -> crate::Result<(BoxHolder, Option<&[u8]>)> {
+fn get_ftyp_and_major_brand(input: &[u8]) -> crate::Result<(BoxHolder<'_>, Option<&[u8]>)> {
     let (_, bbox) = BoxHolder::parse(input).map_err(|e| format!("parse ftyp failed: {e}"))?;
 
     if bbox.box_type() == "ftyp" {
@@ -208,3 +476,83 @@ fn get_compatible_brands(body: &[u8]) -> crate::Result<Vec<&[u8]>> {
     };
     Ok(brands)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    use test_case::test_case;
+
+    #[test_case("exif.jpg", Some(MediaType::Jpeg))]
+    #[test_case("exif.heic", Some(MediaType::Heif))]
+    #[test_case("exif.png", Some(MediaType::Png))]
+    #[test_case("exif.webp", Some(MediaType::WebP))]
+    #[test_case("exif.tif", Some(MediaType::Tiff))]
+    #[test_case("meta.mov", Some(MediaType::QuickTime))]
+    #[test_case("meta.mp4", Some(MediaType::Mp4))]
+    #[test_case("embedded-in-heic.mov", Some(MediaType::QuickTime))]
+    #[test_case("ramdisk.img", None)]
+    fn detect(path: &str, expected: Option<MediaType>) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let media_type = detect_format(open_sample(path).unwrap());
+        assert_eq!(media_type, expected);
+
+        if let Some(t) = media_type {
+            assert!(path.ends_with(t.extension()));
+            if let Some(ff) = t.file_format() {
+                assert_eq!(MediaType::from(ff), t);
+            }
+        }
+    }
+
+    #[test]
+    fn sniffed_reader() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample("exif.jpg").unwrap();
+        let counting = CountingReader::new(io::Cursor::new(&data));
+        let (ff, mut reader) = FileFormat::sniff(counting).unwrap();
+        assert_eq!(ff, Jpeg);
+
+        let mut buf = [0; 10];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[..10]);
+        reader.seek(SeekFrom::Current(-5)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[5..15]);
+        // The sniffed bytes are read from memory
+        assert_eq!(reader.inner.bytes_read, SNIFF_SIZE);
+        assert_eq!(reader.inner.seeks, 0);
+
+        assert_eq!(reader.seek(SeekFrom::Current(5000)).unwrap(), 5015);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[5015..5025]);
+        assert_eq!(reader.inner.seeks, 1);
+
+        // Across the end of the sniffed bytes
+        reader.seek(SeekFrom::Start(SNIFF_SIZE as u64 - 5)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[SNIFF_SIZE - 5..SNIFF_SIZE + 5]);
+
+        let end = reader.seek(SeekFrom::End(-10)).unwrap();
+        assert_eq!(end as usize, data.len() - 10);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[data.len() - 10..]);
+
+        reader.rewind().unwrap();
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, data);
+        reader
+            .seek(SeekFrom::Current(-1 - data.len() as i64))
+            .unwrap_err();
+    }
+
+    #[test]
+    fn detect_empty() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        assert_eq!(detect_format(&[][..]), None);
+    }
+}
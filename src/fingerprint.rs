@@ -0,0 +1,169 @@
+use chrono::{DateTime, FixedOffset, Timelike};
+
+use crate::{
+    mov::{CREATIONDATE_KEY, MAKE_KEY, MODEL_KEY},
+    Exif, ExifTag, MetadataIter,
+};
+
+impl Exif {
+    /// Returns a hash of the fields which identify a capture: make, model,
+    /// capture time (see [`Exif::datetime_original`], with its sub-second
+    /// part) and image size, e.g. to find duplicates among re-encoded or
+    /// resized copies of an image, whose other tags have been rewritten.
+    ///
+    /// The fields are normalized before being hashed:
+    ///
+    /// - make & model are trimmed and compared case-insensitively,
+    /// - the capture time is the local time of the camera, to milliseconds,
+    ///   since the `OffsetTime*` tags are often dropped by editors,
+    /// - the size ignores the orientation (a rotated copy has the same
+    ///   fingerprint).
+    ///
+    /// The hash is stable across versions and platforms, so it can be
+    /// stored. Returns `None` if there is no capture time, since the other
+    /// fields are shared by all the images of a camera.
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let exif: Exif = parse_exif(f, None).unwrap().unwrap().into();
+    /// assert!(exif.fingerprint().is_some());
+    /// ```
+    pub fn fingerprint(&self) -> Option<u64> {
+        let text = |tag| self.get(tag)?.as_str().map(str::to_owned);
+        let fields = Fields {
+            make: text(ExifTag::Make),
+            model: text(ExifTag::Model),
+            time: self.datetime_original()?,
+            dimensions: self.image_size(),
+        };
+        Some(fields.hash())
+    }
+}
+
+impl MetadataIter {
+    /// Like [`Exif::fingerprint`], for videos: hashes the make & model
+    /// (`com.apple.quicktime.make` & `model`), the creation date and the
+    /// size of the video track.
+    ///
+    /// Fingerprints of videos and images are never equal, even if a video
+    /// has the same metadata as an image.
+    pub fn fingerprint(&self) -> Option<u64> {
+        let get = |key: &str| self.get(key).and_then(|x| x.ok());
+        let text = |key| get(key)?.as_str().map(str::to_owned);
+        let dimensions = |w, h| get(w)?.to_u32().zip(get(h)?.to_u32());
+        let fields = Fields {
+            make: text(MAKE_KEY),
+            model: text(MODEL_KEY),
+            time: get(CREATIONDATE_KEY)?.as_time()?,
+            dimensions: dimensions("width", "height"),
+        };
+        // Keeps the fingerprints of images & videos apart
+        Some(fields.hash() ^ 1)
+    }
+}
+
+struct Fields {
+    make: Option<String>,
+    model: Option<String>,
+    time: DateTime<FixedOffset>,
+    dimensions: Option<(u32, u32)>,
+}
+
+impl Fields {
+    fn hash(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        for s in [&self.make, &self.model] {
+            let s = s.as_deref().unwrap_or_default();
+            let s = s.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            hasher.write(s.to_lowercase().as_bytes());
+        }
+
+        let time = self.time.naive_local();
+        let millis = time.nanosecond() / 1_000_000;
+        hasher.write(time.format("%Y%m%d%H%M%S").to_string().as_bytes());
+        hasher.write(&millis.to_le_bytes());
+
+        let (w, h) = self.dimensions.unwrap_or_default();
+        hasher.write(&w.max(h).to_le_bytes());
+        hasher.write(&w.min(h).to_le_bytes());
+        hasher.0
+    }
+}
+
+/// The 64-bit FNV-1a hash, which (unlike [`std::hash::DefaultHasher`]) is
+/// the same in all Rust versions.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    /// Hashes `bytes` followed by a separator, so that fields can't run into
+    /// each other.
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes.iter().chain([&0xff]) {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, parse_media, testkit::*, write_metadata, MetadataEdits};
+    use std::io::Cursor;
+
+    fn sample(make: &str, time: Option<&str>, offset: Option<&str>) -> Exif {
+        let mut edits = MetadataEdits::new();
+        edits.set_exif(ExifTag::Make, make);
+        if let Some(time) = time {
+            edits.set_exif(ExifTag::DateTimeOriginal, time);
+        }
+        if let Some(offset) = offset {
+            edits.set_exif(ExifTag::OffsetTimeOriginal, offset);
+        }
+        let mut out = Vec::new();
+        write_metadata(&read_sample("no-exif.jpg").unwrap()[..], &mut out, &edits).unwrap();
+        parse_exif(Cursor::new(out), None).unwrap().unwrap().into()
+    }
+
+    fn track(path: &str) -> MetadataIter {
+        let info = parse_media(open_sample(path).unwrap()).unwrap();
+        info.unwrap().into_track().unwrap()
+    }
+
+    #[test]
+    fn fingerprint() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let time = Some("2024:07:01 12:00:00");
+        let a = sample("Apple", time, Some("+08:00"));
+        assert_eq!(a.fingerprint(), Some(0xaac7_8414_8b2e_bf14));
+        // Normalized make, and no offset
+        let b = sample(" APPLE ", time, None);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        let c = sample("Apple", Some("2024:07:01 12:00:01"), Some("+08:00"));
+        assert_ne!(a.fingerprint(), c.fingerprint());
+        let d = sample("Canon", time, Some("+08:00"));
+        assert_ne!(a.fingerprint(), d.fingerprint());
+
+        assert_eq!(sample("Apple", None, None).fingerprint(), None);
+    }
+
+    #[test]
+    fn fingerprint_track() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let fingerprint = track("meta.mov").fingerprint();
+        assert!(fingerprint.is_some());
+        assert_eq!(track("meta.mov").fingerprint(), fingerprint);
+        assert_ne!(track("meta.mp4").fingerprint(), fingerprint);
+    }
+}
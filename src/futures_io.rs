@@ -0,0 +1,137 @@
+//! The async API over the `AsyncRead` and `AsyncSeek` traits of
+//! [futures-io](https://crates.io/crates/futures-io), for runtimes other
+//! than tokio, e.g. smol, async-std, or embedded executors. Enabled by the
+//! `futures-io` feature flag.
+//!
+//! The functions take the [`MediaParser`] whose limits are applied, use
+//! `&MediaParser::default()` for the default ones.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use futures_util::io::AllowStdIo;
+//! use nom_exif::{futures_io, *};
+//!
+//! # let runtime = tokio::runtime::Runtime::new().unwrap();
+//! # runtime.block_on(async {
+//! let parser = MediaParser::default();
+//! // Any futures-io reader, e.g. a `smol::fs::File`
+//! let f = AllowStdIo::new(std::fs::File::open("./testdata/meta.mov").unwrap());
+//! let info = futures_io::parse_media(&parser, f).await.unwrap();
+//! assert_eq!(info.unwrap().into_track().unwrap().tracks().len(), 4);
+//! # });
+//! ```
+
+use futures_util::io::{AsyncRead, AsyncSeek};
+
+use crate::{
+    async_io::FuturesSource, mov::valid_entries, EntryValue, ExifIter, FileFormat, MediaInfo,
+    MediaParser, MediaSummary, MetadataIter,
+};
+
+/// Like [`parse_exif_async`](crate::parse_exif_async).
+pub async fn parse_exif<R: AsyncRead + Unpin>(
+    parser: &MediaParser,
+    reader: R,
+    format: Option<FileFormat>,
+) -> crate::Result<Option<ExifIter<'static>>> {
+    parser
+        .parse_exif_source(FuturesSource(reader), format, &[])
+        .await
+}
+
+/// Like [`parse_metadata_iter_async`](crate::parse_metadata_iter_async):
+/// the `mdat` atom is skipped by seeking.
+pub async fn parse_metadata_iter<R>(parser: &MediaParser, reader: R) -> crate::Result<MetadataIter>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    crate::mov::read_metadata_iter_async(FuturesSource(reader), parser, &[]).await
+}
+
+/// Like [`parse_media_async`](crate::parse_media_async).
+pub async fn parse_media<R>(parser: &MediaParser, reader: R) -> crate::Result<Option<MediaInfo>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    Ok(parser.parse_media_source(FuturesSource(reader)).await?.1)
+}
+
+/// Like [`parse_metadata_async`](crate::parse_metadata_async): invalid
+/// entries are skipped.
+pub async fn parse_metadata<R>(
+    parser: &MediaParser,
+    reader: R,
+) -> crate::Result<Vec<(String, EntryValue)>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    Ok(valid_entries(parse_metadata_iter(parser, reader).await?))
+}
+
+/// Like [`MediaParser::probe_async`](crate::MediaParser::probe_async).
+pub async fn probe<R>(parser: &MediaParser, reader: R) -> crate::Result<MediaSummary>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let (format, info) = parser.parse_media_source(FuturesSource(reader)).await?;
+    Ok(parser.summarize(format, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::io::AllowStdIo;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{testkit::open_sample, Exif};
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn futures_parse_media(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::default();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let reader = AllowStdIo::new(open_sample(path).unwrap());
+        let info = runtime.block_on(parse_media(&parser, reader)).unwrap();
+
+        match (
+            info.unwrap(),
+            crate::parse_media(open_sample(path).unwrap())
+                .unwrap()
+                .unwrap(),
+        ) {
+            (MediaInfo::Exif(iter), MediaInfo::Exif(expected)) => {
+                let exif: Exif = iter.into();
+                let expected: Exif = expected.into();
+                assert_eq!(exif.entries(), expected.entries());
+
+                let reader = AllowStdIo::new(open_sample(path).unwrap());
+                let iter = runtime.block_on(parse_exif(&parser, reader, None));
+                assert!(iter.unwrap().is_some());
+            }
+            (MediaInfo::Track(iter), MediaInfo::Track(expected)) => {
+                assert_eq!(iter.tracks(), expected.tracks());
+                // The `mdat` atom is seeked over
+                assert_eq!(parser.take_stats().skips, 1);
+
+                let reader = AllowStdIo::new(open_sample(path).unwrap());
+                let iter = runtime.block_on(parse_metadata_iter(&parser, reader));
+                assert_eq!(iter.unwrap().count(), expected.count());
+
+                let reader = AllowStdIo::new(open_sample(path).unwrap());
+                let entries = runtime.block_on(parse_metadata(&parser, reader)).unwrap();
+                let expected = crate::parse_metadata(open_sample(path).unwrap()).unwrap();
+                assert_eq!(entries, expected);
+            }
+            (info, expected) => panic!("{info:?} != {expected:?}"),
+        }
+
+        let reader = AllowStdIo::new(open_sample(path).unwrap());
+        let summary = runtime.block_on(probe(&parser, reader)).unwrap();
+        assert_eq!(summary, parser.probe(open_sample(path).unwrap()).unwrap());
+    }
+}
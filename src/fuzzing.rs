@@ -0,0 +1,411 @@
+//! Structured fuzzing support, enabled by the `fuzzing` feature flag.
+//!
+//! The `fuzz_*` functions run the internal parsers on their raw input,
+//! without the file format detection and the container parsing before them,
+//! and consume all their results, so a fuzzer can target one subsystem.
+//!
+//! [`TiffLayout`] and [`BoxTree`] implement
+//! [`arbitrary::Arbitrary`](https://docs.rs/arbitrary), and serialize to
+//! well-formed TIFF data and ISOBMFF boxes: the fuzzer mutates the structure
+//! rather than the bytes, so most inputs get past the headers and offsets.
+//!
+//! # Usage
+//!
+//! With [cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz):
+//!
+//! ```rust,ignore
+//! #![no_main]
+//! use libfuzzer_sys::fuzz_target;
+//! use nom_exif::fuzzing::*;
+//!
+//! fuzz_target!(|layout: TiffLayout| fuzz_tiff(&layout.to_bytes()));
+//! ```
+
+use std::io::Cursor;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{
+    exif::input_to_iter, file::FileFormat, input::Input, mov::new_metadata_iter, parse_media, Exif,
+    MediaInfo, MediaParser,
+};
+
+/// Parses TIFF data (the Exif data without its container), as found in
+/// the `APP1` segment of JPEG files.
+pub fn fuzz_tiff(data: &[u8]) {
+    let Ok(iter) = input_to_iter(data) else {
+        return;
+    };
+    let _ = iter.parse_gps_info();
+    for entry in iter.clone() {
+        let _ = entry.take_result().map(|x| x.to_string());
+    }
+    let exif: Exif = iter.into();
+    let _ = exif.datetime_original();
+}
+
+/// Parses the body of a `moov` atom of a QuickTime/MP4 file.
+pub fn fuzz_moov(data: &[u8]) {
+    let input = Input::from_vec(data.to_vec());
+    for format in [FileFormat::QuickTime, FileFormat::MP4] {
+        let Ok(iter) = new_metadata_iter(format, input.clone(), 0, &MediaParser::default()) else {
+            continue;
+        };
+        let _ = iter.tracks();
+        iter.for_each(drop);
+    }
+}
+
+/// Parses a whole file, see [`parse_media`].
+pub fn fuzz_media(data: &[u8]) {
+    match parse_media(Cursor::new(data)) {
+        Ok(Some(MediaInfo::Exif(iter))) => iter.for_each(drop),
+        Ok(Some(MediaInfo::Track(iter))) => iter.for_each(drop),
+        _ => (),
+    }
+}
+
+/// The layout of TIFF data: a chain of IFDs, with sub-IFDs.
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub struct TiffLayout {
+    pub big_endian: bool,
+    pub ifds: Vec<IfdLayout>,
+}
+
+/// An Image File Directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Arbitrary)]
+pub struct IfdLayout {
+    pub entries: Vec<EntryLayout>,
+    /// Sub-IFDs, pointed to by an entry with the tag of their kind.
+    pub sub_ifds: Vec<(SubIfdKind, IfdLayout)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Arbitrary)]
+pub enum SubIfdKind {
+    Exif,
+    Gps,
+    Interop,
+}
+
+impl SubIfdKind {
+    fn tag(self) -> u16 {
+        match self {
+            SubIfdKind::Exif => 0x8769,
+            SubIfdKind::Gps => 0x8825,
+            SubIfdKind::Interop => 0xa005,
+        }
+    }
+}
+
+/// An IFD entry. The count of the entry is the number of components of
+/// `data`, rounded down, or its length if `format` is invalid.
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub struct EntryLayout {
+    pub tag: u16,
+    /// The format is `format % 13`: 1 to 12 are the valid TIFF formats
+    /// (byte, ASCII, short, long, rational, ...), 0 is invalid.
+    pub format: u8,
+    pub data: Vec<u8>,
+}
+
+/// Sizes of the components of the formats, by format.
+const FORMAT_SIZES: [usize; 13] = [0, 1, 1, 2, 4, 8, 1, 1, 2, 4, 8, 4, 8];
+
+impl TiffLayout {
+    /// Serializes the layout to TIFF data: the IFDs follow the header, and
+    /// the values which don't fit in the entries follow their IFD.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let w = Writer {
+            big_endian: self.big_endian,
+        };
+        out.extend_from_slice(if self.big_endian { b"MM" } else { b"II" });
+        w.u16(&mut out, 42);
+        w.u32(&mut out, 8);
+        let mut next = 4;
+        for ifd in &self.ifds {
+            let start = out.len() as u32;
+            w.patch(&mut out, next, start);
+            next = w.ifd(&mut out, ifd);
+        }
+        out
+    }
+}
+
+struct Writer {
+    big_endian: bool,
+}
+
+impl Writer {
+    fn u16(&self, out: &mut Vec<u8>, x: u16) {
+        let bytes = if self.big_endian {
+            x.to_be_bytes()
+        } else {
+            x.to_le_bytes()
+        };
+        out.extend_from_slice(&bytes);
+    }
+
+    fn u32(&self, out: &mut Vec<u8>, x: u32) {
+        out.extend_from_slice(&self.u32_bytes(x));
+    }
+
+    fn u32_bytes(&self, x: u32) -> [u8; 4] {
+        if self.big_endian {
+            x.to_be_bytes()
+        } else {
+            x.to_le_bytes()
+        }
+    }
+
+    fn patch(&self, out: &mut [u8], pos: usize, x: u32) {
+        out[pos..pos + 4].copy_from_slice(&self.u32_bytes(x));
+    }
+
+    /// Writes `ifd` and its sub-IFDs, and returns the position of its link
+    /// to the next IFD.
+    fn ifd(&self, out: &mut Vec<u8>, ifd: &IfdLayout) -> usize {
+        let count = ifd.entries.len() + ifd.sub_ifds.len();
+        self.u16(out, count.min(u16::MAX as usize) as u16);
+
+        // (position of the value or offset, data which doesn't fit)
+        let mut pending = Vec::new();
+        for entry in &ifd.entries {
+            let format = entry.format % 13;
+            let count = match FORMAT_SIZES[format as usize] {
+                0 => entry.data.len(),
+                size => entry.data.len() / size,
+            };
+            self.u16(out, entry.tag);
+            self.u16(out, format.into());
+            self.u32(out, count as u32);
+            let pos = out.len();
+            if entry.data.len() <= 4 {
+                let mut value = [0; 4];
+                value[..entry.data.len()].copy_from_slice(&entry.data);
+                out.extend_from_slice(&value);
+            } else {
+                out.extend_from_slice(&[0; 4]);
+                pending.push((pos, Some(&entry.data)));
+            }
+        }
+        for (kind, _) in &ifd.sub_ifds {
+            self.u16(out, kind.tag());
+            self.u16(out, 4);
+            self.u32(out, 1);
+            pending.push((out.len(), None));
+            out.extend_from_slice(&[0; 4]);
+        }
+        let next = out.len();
+        self.u32(out, 0);
+
+        let mut sub_ifds = ifd.sub_ifds.iter();
+        for (pos, data) in pending {
+            let offset = out.len() as u32;
+            self.patch(out, pos, offset);
+            match data {
+                Some(data) => out.extend_from_slice(data),
+                None => {
+                    let (_, sub_ifd) = sub_ifds.next().expect("one per sub-IFD entry");
+                    self.ifd(out, sub_ifd);
+                }
+            }
+        }
+        next
+    }
+}
+
+/// A tree of ISOBMFF boxes (atoms), as found in QuickTime, MP4 and HEIF
+/// files.
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub struct BoxTree {
+    pub boxes: Vec<BoxLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub struct BoxLayout {
+    pub box_type: BoxType,
+    pub body: BoxBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub enum BoxBody {
+    Data(Vec<u8>),
+    /// `version_flags` precedes the children of full boxes, e.g. the `meta`
+    /// box of HEIF files.
+    Container {
+        version_flags: Option<[u8; 4]>,
+        children: Vec<BoxLayout>,
+    },
+}
+
+/// The type of a box, usually one which the parsers look for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoxType(pub [u8; 4]);
+
+const BOX_TYPES: [&[u8; 4]; 24] = [
+    b"ftyp", b"moov", b"mdat", b"meta", b"keys", b"ilst", b"udta", b"trak", b"mdia", b"mvhd",
+    b"tkhd", b"mdhd", b"hdlr", b"minf", b"stbl", b"stsd", b"stts", b"stsz", b"iinf", b"infe",
+    b"iloc", b"iprp", b"idat", b"Exif",
+];
+
+impl<'a> Arbitrary<'a> for BoxType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.ratio(1, 8)? {
+            return Ok(BoxType(u.arbitrary()?));
+        }
+        Ok(BoxType(**u.choose(&BOX_TYPES)?))
+    }
+}
+
+impl BoxTree {
+    /// Serializes the boxes, with 32-bit sizes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.boxes.iter().for_each(|x| x.write(&mut out));
+        out
+    }
+}
+
+impl BoxLayout {
+    fn write(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&self.box_type.0);
+        match &self.body {
+            BoxBody::Data(data) => out.extend_from_slice(data),
+            BoxBody::Container {
+                version_flags,
+                children,
+            } => {
+                if let Some(x) = version_flags {
+                    out.extend_from_slice(x);
+                }
+                children.iter().for_each(|x| x.write(out));
+            }
+        }
+        let size = u32::try_from(out.len() - start).unwrap_or(u32::MAX);
+        out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EntryValue, ExifTag};
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn fuzzing_tiff_layout() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        for big_endian in [false, true] {
+            let entry = |tag: ExifTag, format, data: &[u8]| EntryLayout {
+                tag: tag.code(),
+                format,
+                data: data.to_vec(),
+            };
+            let layout = TiffLayout {
+                big_endian,
+                ifds: vec![IfdLayout {
+                    entries: vec![
+                        entry(ExifTag::Make, 2, b"Acme\0"),
+                        entry(ExifTag::Model, 2, b"X1\0"),
+                    ],
+                    sub_ifds: vec![(
+                        SubIfdKind::Exif,
+                        IfdLayout {
+                            entries: vec![entry(ExifTag::ExposureProgram, 3, &{
+                                let x = 2u16;
+                                if big_endian {
+                                    x.to_be_bytes()
+                                } else {
+                                    x.to_le_bytes()
+                                }
+                            })],
+                            sub_ifds: vec![],
+                        },
+                    )],
+                }],
+            };
+
+            let exif: Exif = input_to_iter(layout.to_bytes().as_slice()).unwrap().into();
+            assert_eq!(
+                exif.get(ExifTag::Make),
+                Some(&EntryValue::Text("Acme".into()))
+            );
+            assert_eq!(
+                exif.get(ExifTag::Model),
+                Some(&EntryValue::Text("X1".into()))
+            );
+            assert_eq!(
+                exif.get(ExifTag::ExposureProgram),
+                Some(&EntryValue::U16(2))
+            );
+            fuzz_tiff(&layout.to_bytes());
+        }
+    }
+
+    #[test]
+    fn fuzzing_tiff_regressions() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // An empty GPS IFD linking to itself
+        fuzz_tiff(
+            b"II*\0\x08\0\0\0\x01\0\x25\x88\x04\0\x01\0\0\0\x1a\0\0\0\0\0\0\0\
+              \0\0\x1a\0\0\0",
+        );
+        // IFD1 linking to itself
+        fuzz_tiff(
+            b"II*\0\x08\0\0\0\x01\0\0\x01\x03\0\x01\0\0\0\x01\0\0\0\x1a\0\0\0\
+              \x01\0\0\x01\x03\0\x01\0\0\0\x02\0\0\0\x1a\0\0\0",
+        );
+    }
+
+    #[test]
+    fn fuzzing_box_tree() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let tree = BoxTree {
+            boxes: vec![
+                BoxLayout {
+                    box_type: BoxType(*b"ftyp"),
+                    body: BoxBody::Data(b"qt  \0\0\0\0qt  ".to_vec()),
+                },
+                BoxLayout {
+                    box_type: BoxType(*b"moov"),
+                    body: BoxBody::Container {
+                        version_flags: None,
+                        children: vec![],
+                    },
+                },
+            ],
+        };
+        let data = tree.to_bytes();
+        assert_eq!(&data[..4], &20u32.to_be_bytes());
+        assert_eq!(&data[20..28], b"\0\0\0\x08moov");
+        fuzz_media(&data);
+        fuzz_moov(&data);
+    }
+
+    #[test]
+    fn fuzzing_random() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut buf = vec![0u8; 4096];
+        for _ in 0..200 {
+            rng.fill(&mut buf[..]);
+            let mut u = Unstructured::new(&buf);
+            if let Ok(layout) = TiffLayout::arbitrary(&mut u) {
+                fuzz_tiff(&layout.to_bytes());
+            }
+            if let Ok(tree) = BoxTree::arbitrary(&mut u) {
+                let data = tree.to_bytes();
+                fuzz_moov(&data);
+                fuzz_media(&data);
+            }
+            fuzz_tiff(&buf);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::GPSInfo;
+
+/// A place name, as returned by a [`Geocoder`]. All the fields are
+/// optional, since reverse-geocoding services don't know all of them
+/// everywhere.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Place {
+    /// Name of the place itself, e.g. a landmark or an address.
+    pub name: Option<String>,
+    /// City, town or village.
+    pub locality: Option<String>,
+    /// State, province or region.
+    pub region: Option<String>,
+    pub country: Option<String>,
+    /// ISO 3166-1 alpha-2 code, e.g. `"FR"`.
+    pub country_code: Option<String>,
+}
+
+/// Reverse-geocodes the GPS positions found by
+/// [`MediaParser::probe`](crate::MediaParser::probe), see
+/// [`MediaParserBuilder::geocoder`](crate::MediaParserBuilder::geocoder).
+///
+/// It's called synchronously while probing, so implementations which query
+/// a remote service should cache their results, e.g. by rounding the
+/// coordinates.
+pub trait Geocoder: Send + Sync {
+    /// Returns the place at `gps`, or `None` if it's unknown.
+    fn reverse_geocode(&self, gps: &GPSInfo) -> Option<Place>;
+}
+
+impl<F> Geocoder for F
+where
+    F: Fn(&GPSInfo) -> Option<Place> + Send + Sync,
+{
+    fn reverse_geocode(&self, gps: &GPSInfo) -> Option<Place> {
+        self(gps)
+    }
+}
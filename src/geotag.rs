@@ -0,0 +1,525 @@
+//! Geotagging media files from a GPX track.
+
+use std::io::Read;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, Utc};
+
+use crate::{EntryValue, Exif, GPSInfo};
+
+/// Key of the QuickTime creation date item.
+const CREATIONDATE_KEY: &str = "com.apple.quicktime.creationdate";
+
+/// A point of a [`GpxTrack`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPoint {
+    pub time: DateTime<Utc>,
+    /// Decimal degrees, positive for north.
+    pub latitude: f64,
+    /// Decimal degrees, positive for east.
+    pub longitude: f64,
+    /// Meters, if recorded.
+    pub elevation: Option<f64>,
+    /// Index of the track segment (`<trkseg>`) this point belongs to.
+    /// Positions are never interpolated between different segments.
+    pub segment: usize,
+}
+
+/// Track points (`<trkpt>`) read from a GPX file, sorted by time.
+///
+/// Points without a `<time>` are ignored, since they can't be matched with
+/// any media.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpxTrack {
+    points: Vec<TrackPoint>,
+}
+
+impl GpxTrack {
+    /// Parses a GPX document. All tracks and segments in it are merged.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let track = GpxTrack::parse(r#"<gpx><trk><trkseg>
+    ///     <trkpt lat="22.5" lon="114.0"><time>2023-07-09T12:30:00Z</time></trkpt>
+    /// </trkseg></trk></gpx>"#).unwrap();
+    /// assert_eq!(track.points().len(), 1);
+    /// assert_eq!(track.points()[0].latitude, 22.5);
+    /// ```
+    pub fn parse(gpx: &str) -> crate::Result<GpxTrack> {
+        let mut points = Vec::new();
+        let mut segment = 0;
+        let mut rest = gpx;
+        while let Some(pos) = rest.find("<trk") {
+            rest = &rest[pos + 4..];
+            if rest.starts_with("seg") && is_name_end(&rest[3..]) {
+                segment += 1;
+                continue;
+            }
+            if !(rest.starts_with("pt") && is_name_end(&rest[2..])) {
+                continue;
+            }
+
+            let tag_end = rest
+                .find('>')
+                .ok_or("invalid GPX data; unterminated <trkpt> tag")?;
+            let tag = &rest[2..tag_end];
+            let body = if tag.ends_with('/') {
+                rest = &rest[tag_end + 1..];
+                ""
+            } else {
+                let end = rest[tag_end..]
+                    .find("</trkpt>")
+                    .ok_or("invalid GPX data; missing </trkpt>")?
+                    + tag_end;
+                let body = &rest[tag_end + 1..end];
+                rest = &rest[end..];
+                body
+            };
+
+            let Some(time) = element(body, "time") else {
+                continue;
+            };
+            let latitude = parse_number(attribute(tag, "lat"), "latitude")?;
+            let longitude = parse_number(attribute(tag, "lon"), "longitude")?;
+            if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                return Err(
+                    format!("invalid GPX data; bad location: {latitude},{longitude}").into(),
+                );
+            }
+            let elevation = element(body, "ele")
+                .map(|x| parse_number(Some(x), "elevation"))
+                .transpose()?;
+
+            points.push(TrackPoint {
+                time: parse_time(time)?,
+                latitude,
+                longitude,
+                elevation,
+                segment,
+            });
+        }
+
+        points.sort_by_key(|x| x.time);
+        Ok(GpxTrack { points })
+    }
+
+    /// Reads a GPX document from `reader`, see [`GpxTrack::parse`].
+    pub fn from_reader<R: Read>(mut reader: R) -> crate::Result<GpxTrack> {
+        let mut gpx = String::new();
+        reader.read_to_string(&mut gpx)?;
+        Self::parse(&gpx)
+    }
+
+    /// Returns the track points, sorted by time.
+    pub fn points(&self) -> &[TrackPoint] {
+        &self.points
+    }
+}
+
+/// How to interpret the capture times of media, which are usually recorded
+/// in the camera's local time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeZoneStrategy {
+    /// Use the time zone recorded with the time, i.e. the Exif
+    /// `OffsetTimeOriginal` (or `OffsetTime`) entry, or the offset of
+    /// QuickTime `creationdate`. If there isn't one, the local time zone of
+    /// this machine is assumed, as [`parse_exif`](crate::parse_exif) does.
+    #[default]
+    Recorded,
+
+    /// Ignore any recorded time zone, and interpret the time shown on the
+    /// camera's clock as a time in the given time zone, e.g. when the camera
+    /// was left on home time while traveling.
+    Fixed(FixedOffset),
+}
+
+/// Options for [`Geotagger`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeotagOptions {
+    /// How far the camera clock was ahead of GPS time, which is subtracted
+    /// from the capture times. Default: zero.
+    pub clock_offset: Duration,
+
+    /// Default: [`TimeZoneStrategy::Recorded`].
+    pub time_zone: TimeZoneStrategy,
+
+    /// Maximum time between two track points for positions to be
+    /// interpolated between them, and maximum time between a capture and the
+    /// nearest track point otherwise. Default: 30 minutes.
+    pub max_gap: Duration,
+}
+
+impl Default for GeotagOptions {
+    fn default() -> Self {
+        Self {
+            clock_offset: Duration::zero(),
+            time_zone: TimeZoneStrategy::Recorded,
+            max_gap: Duration::minutes(30),
+        }
+    }
+}
+
+/// Media whose capture time can be matched with a [`GpxTrack`].
+pub trait CaptureTime {
+    /// Returns the capture time, or `None` if it's unknown.
+    fn capture_time(&self) -> Option<DateTime<FixedOffset>>;
+}
+
+impl CaptureTime for DateTime<FixedOffset> {
+    fn capture_time(&self) -> Option<DateTime<FixedOffset>> {
+        Some(*self)
+    }
+}
+
+/// See [`Exif::datetime_original`].
+impl CaptureTime for Exif {
+    fn capture_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.datetime_original()
+    }
+}
+
+/// `com.apple.quicktime.creationdate` of the entries returned by
+/// [`parse_metadata`](crate::parse_metadata).
+impl CaptureTime for [(String, EntryValue)] {
+    fn capture_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.iter()
+            .find(|(k, _)| k == CREATIONDATE_KEY)
+            .and_then(|(_, v)| v.as_time())
+    }
+}
+
+impl CaptureTime for Vec<(String, EntryValue)> {
+    fn capture_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.as_slice().capture_time()
+    }
+}
+
+/// Finds the locations of media in a [`GpxTrack`], by their capture times.
+///
+/// The returned [`GPSInfo`]s can be written with
+/// [`MetadataEdits::set_gps`](crate::MetadataEdits::set_gps).
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+/// use chrono::Duration;
+///
+/// let track = GpxTrack::parse(r#"<gpx><trk><trkseg>
+///     <trkpt lat="22.5" lon="114.0"><time>2023-07-09T12:40:00Z</time></trkpt>
+///     <trkpt lat="22.6" lon="114.1"><time>2023-07-09T12:50:00Z</time></trkpt>
+/// </trkseg></trk></gpx>"#).unwrap();
+///
+/// // The camera clock was 3 min 27 s slow
+/// let geotagger = Geotagger::new(track, GeotagOptions {
+///     clock_offset: Duration::seconds(-207),
+///     ..Default::default()
+/// });
+///
+/// // Taken at 2023-07-09T20:36:33.616+08:00
+/// let exif: Exif = parse_exif(std::fs::File::open("./testdata/exif.jpg").unwrap(), None)
+///     .unwrap()
+///     .unwrap()
+///     .into();
+/// let gps = geotagger.locate(&exif).unwrap();
+/// assert_eq!(gps.format_iso6709(), "+22.50010+114.00010/");
+///
+/// let mut edits = MetadataEdits::new();
+/// edits.set_gps(&gps);
+/// let mut output = Vec::new();
+/// let reader = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// write_metadata(reader, &mut output, &edits).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Geotagger {
+    track: GpxTrack,
+    options: GeotagOptions,
+}
+
+impl Geotagger {
+    pub fn new(track: GpxTrack, options: GeotagOptions) -> Self {
+        Self { track, options }
+    }
+
+    /// Returns the location of `media`, or `None` if its capture time is
+    /// unknown, or isn't covered by the track.
+    ///
+    /// The position is linearly interpolated between the track points just
+    /// before and after the capture time, if they are in the same segment
+    /// and no more than `max_gap` apart. Otherwise the nearest track point
+    /// within `max_gap` is used.
+    pub fn locate<T: CaptureTime + ?Sized>(&self, media: &T) -> Option<GPSInfo> {
+        let time = media.capture_time()?;
+        let time = match self.options.time_zone {
+            TimeZoneStrategy::Recorded => time,
+            TimeZoneStrategy::Fixed(tz) => local_time(time.naive_local(), tz)?,
+        };
+        self.locate_utc(time.with_timezone(&Utc) - self.options.clock_offset)
+    }
+
+    /// Calls [`Geotagger::locate`] for each of `media`.
+    pub fn locate_all<'a, T: CaptureTime + ?Sized + 'a>(
+        &self,
+        media: impl IntoIterator<Item = &'a T>,
+    ) -> Vec<Option<GPSInfo>> {
+        media.into_iter().map(|x| self.locate(x)).collect()
+    }
+
+    fn locate_utc(&self, time: DateTime<Utc>) -> Option<GPSInfo> {
+        let points = self.track.points();
+        let max_gap = self.options.max_gap;
+        let idx = points.partition_point(|x| x.time <= time);
+        let prev = idx.checked_sub(1).map(|i| &points[i]);
+        let next = points.get(idx);
+
+        let (p1, p2) = match (prev, next) {
+            (Some(p1), Some(p2)) if p1.segment == p2.segment && p2.time - p1.time <= max_gap => {
+                (p1, p2)
+            }
+            _ => {
+                let nearest = [prev, next]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|x| (x.time - time).abs())?;
+                if (nearest.time - time).abs() > max_gap {
+                    return None;
+                }
+                (nearest, nearest)
+            }
+        };
+        to_gps_info(&interpolate(p1, p2, time))
+    }
+}
+
+/// Interpolates between `p1` & `p2`, where `p1.time <= time <= p2.time`.
+fn interpolate(p1: &TrackPoint, p2: &TrackPoint, time: DateTime<Utc>) -> TrackPoint {
+    let total = (p2.time - p1.time).num_milliseconds();
+    if total == 0 {
+        return p1.clone();
+    }
+    let ratio = (time - p1.time).num_milliseconds() as f64 / total as f64;
+    let lerp = |a: f64, b: f64| a + (b - a) * ratio;
+
+    // Take the short way around when crossing the antimeridian
+    let mut lon2 = p2.longitude;
+    if lon2 - p1.longitude > 180.0 {
+        lon2 -= 360.0;
+    } else if p1.longitude - lon2 > 180.0 {
+        lon2 += 360.0;
+    }
+    let mut longitude = lerp(p1.longitude, lon2);
+    if longitude > 180.0 {
+        longitude -= 360.0;
+    } else if longitude < -180.0 {
+        longitude += 360.0;
+    }
+
+    TrackPoint {
+        time,
+        latitude: lerp(p1.latitude, p2.latitude),
+        longitude,
+        elevation: p1.elevation.zip(p2.elevation).map(|(a, b)| lerp(a, b)),
+        segment: p1.segment,
+    }
+}
+
+fn to_gps_info(point: &TrackPoint) -> Option<GPSInfo> {
+    let Some(elevation) = point.elevation else {
+        let mut gps = GPSInfo::from_decimal(point.latitude, point.longitude, 0.0).ok()?;
+        // Unknown altitude
+        gps.altitude = (0, 0).into();
+        return Some(gps);
+    };
+    GPSInfo::from_decimal(point.latitude, point.longitude, elevation).ok()
+}
+
+fn local_time(t: NaiveDateTime, tz: FixedOffset) -> Option<DateTime<FixedOffset>> {
+    t.and_local_timezone(tz).single()
+}
+
+/// Times without a time zone are in UTC, as required by GPX.
+fn parse_time(s: &str) -> crate::Result<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+        .map(|t| t.and_utc())
+        .map_err(|_| format!("invalid GPX data; bad time: {s}").into())
+}
+
+fn parse_number(s: Option<&str>, name: &str) -> crate::Result<f64> {
+    let s = s.ok_or_else(|| format!("invalid GPX data; missing {name}"))?;
+    s.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|x| x.is_finite())
+        .ok_or_else(|| format!("invalid GPX data; bad {name}: {s}").into())
+}
+
+fn is_name_end(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+}
+
+/// Returns the value of attribute `name` in the start tag `tag`.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(name) {
+        let before = rest[..pos].chars().next_back();
+        let after = rest[pos + name.len()..].trim_start();
+        rest = &rest[pos + name.len()..];
+        if !before.is_some_and(|c| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+    None
+}
+
+/// Returns the text of the first child element `name` in `body`.
+fn element<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{name}>");
+    let start = body.find(&open)? + open.len();
+    let end = body[start..].find(&format!("</{name}>"))? + start;
+    Some(&body[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk>
+    <name>test</name>
+    <trkseg>
+      <trkpt lat="10.0" lon="179.5"><ele>100</ele><time>2024-01-01T00:00:00Z</time></trkpt>
+      <trkpt lon='-179.5' lat='11.0'>
+        <ele>200</ele>
+        <time>2024-01-01T00:10:00Z</time>
+      </trkpt>
+      <trkpt lat="50" lon="50"/>
+    </trkseg>
+    <trkseg>
+      <trkpt lat="12.0" lon="-170.0"><time>2024-01-01T01:00:00Z</time></trkpt>
+      <trkpt lat="13.0" lon="-169.0"><time>2024-01-01T01:10:00</time></trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+    fn time(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    #[test]
+    fn parse_gpx() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let track = GpxTrack::parse(GPX).unwrap();
+        let points = track.points();
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[1].latitude, 11.0);
+        assert_eq!(points[1].longitude, -179.5);
+        assert_eq!(points[1].elevation, Some(200.0));
+        assert_eq!(points[2].elevation, None);
+        assert_eq!(
+            points.iter().map(|x| x.segment).collect::<Vec<_>>(),
+            [1, 1, 2, 2]
+        );
+        assert_eq!(points[3].time, time("2024-01-01T01:10:00Z"));
+    }
+
+    #[test_case(r#"<trkpt lat="x" lon="1"><time>2024-01-01T00:00:00Z</time></trkpt>"#)]
+    #[test_case(r#"<trkpt lat="91" lon="1"><time>2024-01-01T00:00:00Z</time></trkpt>"#)]
+    #[test_case(r#"<trkpt lon="1"><time>2024-01-01T00:00:00Z</time></trkpt>"#)]
+    #[test_case(r#"<trkpt lat="1" lon="1"><time>yesterday</time></trkpt>"#)]
+    #[test_case(r#"<trkpt lat="1" lon="1"><time>2024-01-01T00:00:00Z</time>"#)]
+    fn parse_invalid(gpx: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+        GpxTrack::parse(gpx).unwrap_err();
+    }
+
+    #[test_case("2024-01-01T00:00:00Z", Some("+10.00000+179.50000+100.000/"))]
+    #[test_case("2024-01-01T08:05:00+08:00", Some("+10.50000+180.00000+150.000/"))]
+    #[test_case("2024-01-01T00:07:30Z", Some("+10.75000-179.75000+175.000/"))]
+    #[test_case("2024-01-01T00:20:00Z", Some("+11.00000-179.50000+200.000/"); "nearest")]
+    #[test_case("2024-01-01T00:50:00Z", Some("+12.00000-170.00000/"); "other segment")]
+    #[test_case("2024-01-01T01:05:00Z", Some("+12.50000-169.50000/"))]
+    #[test_case("2024-01-01T01:45:00Z", None; "after")]
+    #[test_case("2023-12-31T23:29:00Z", None; "before")]
+    fn locate(t: &str, gps: Option<&str>) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let geotagger = Geotagger::new(GpxTrack::parse(GPX).unwrap(), GeotagOptions::default());
+        assert_eq!(
+            geotagger.locate(&time(t)).map(|x| x.format_iso6709()),
+            gps.map(|x| x.to_owned())
+        );
+    }
+
+    #[test]
+    fn locate_options() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let track = GpxTrack::parse(GPX).unwrap();
+
+        // Camera clock is 5 minutes fast, and was left on UTC+2
+        let geotagger = Geotagger::new(
+            track.clone(),
+            GeotagOptions {
+                clock_offset: Duration::minutes(5),
+                time_zone: TimeZoneStrategy::Fixed(FixedOffset::east_opt(2 * 3600).unwrap()),
+                ..Default::default()
+            },
+        );
+        let gps = geotagger
+            .locate(&time("2024-01-01T02:10:00+08:00"))
+            .unwrap();
+        assert_eq!(gps.format_iso6709(), "+10.50000+180.00000+150.000/");
+
+        let geotagger = Geotagger::new(
+            track,
+            GeotagOptions {
+                max_gap: Duration::minutes(3),
+                ..Default::default()
+            },
+        );
+        let gps = geotagger.locate_all([
+            &time("2024-01-01T00:05:00Z"),
+            &time("2024-01-01T00:12:00Z"),
+            &time("2024-01-01T00:14:00Z"),
+        ]);
+        assert_eq!(
+            gps.iter()
+                .map(|x| x.as_ref().map(|x| x.format_iso6709()))
+                .collect::<Vec<_>>(),
+            [None, Some("+11.00000-179.50000+200.000/".to_owned()), None]
+        );
+    }
+
+    #[test]
+    fn capture_time() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif: Exif = crate::parse_exif(crate::testkit::open_sample("exif.jpg").unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(
+            exif.capture_time(),
+            Some(time("2023-07-09T20:36:33.616+08:00"))
+        );
+
+        let meta = crate::parse_metadata(crate::testkit::open_sample("meta.mov").unwrap()).unwrap();
+        assert_eq!(meta.capture_time(), Some(time("2019-02-12T15:27:12+08:00")));
+    }
+}
@@ -1,11 +1,18 @@
+#[cfg(feature = "std")]
 use std::io::{Read, Seek};
 
 use nom::combinator::fail;
 use nom::{number::complete::be_u32, IResult};
 
 use crate::bbox::find_box;
+#[cfg(feature = "std")]
+use crate::capabilities::{exif_capabilities, Capabilities};
+#[cfg(feature = "std")]
 use crate::exif::{input_to_exif, read_exif, Exif};
+#[cfg(feature = "std")]
 use crate::file::FileFormat;
+#[cfg(feature = "std")]
+use crate::MediaParser;
 use crate::{
     bbox::{BoxHolder, MetaBox, ParseBox},
     exif::check_exif_header,
@@ -46,14 +53,52 @@ use crate::{
 ///     .collect::<Vec<_>>()
 /// );
 /// ```
+#[cfg(feature = "std")]
 pub fn parse_heif_exif<R: Read + Seek>(reader: R) -> crate::Result<Option<Exif>> {
-    read_exif(reader, Some(FileFormat::Heif))?
+    read_exif(reader, Some(FileFormat::Heif), &MediaParser::default())?
         .map(input_to_exif)
         .transpose()
 }
 
+/// Content type of the `mime` items which hold XMP packets.
+#[cfg(feature = "std")]
+pub(crate) const XMP_CONTENT_TYPE: &str = "application/rdf+xml";
+
 /// Extract Exif TIFF data from the bytes of a HEIF/HEIC file.
 pub(crate) fn extract_exif_data(input: &[u8]) -> IResult<&[u8], Option<&[u8]>> {
+    let (remain, meta) = parse_meta_box(input)?;
+    match meta {
+        Some(meta) => meta_exif_data(&meta, input),
+        None => Ok((remain, None)),
+    }
+}
+
+/// Parses the `meta` box, and returns the kinds of metadata found.
+#[cfg(feature = "std")]
+pub(crate) fn scan_capabilities(input: &[u8]) -> IResult<&[u8], Capabilities> {
+    let (remain, meta) = parse_meta_box(input)?;
+    let Some(meta) = meta else {
+        return Ok((remain, Capabilities::empty()));
+    };
+
+    let mut caps = Capabilities::empty();
+    if meta.has_mime_item(XMP_CONTENT_TYPE) {
+        caps |= Capabilities::XMP;
+    }
+    let (remain, data) = match meta_exif_data(&meta, input) {
+        Ok((remain, data)) => (remain, data),
+        Err(nom::Err::Incomplete(n)) => return Err(nom::Err::Incomplete(n)),
+        // The Exif item can't be read, but the other items are still there
+        Err(_) => (remain, None),
+    };
+    if let Some(data) = data {
+        caps |= Capabilities::EXIF | exif_capabilities(data);
+    }
+
+    Ok((remain, caps))
+}
+
+fn parse_meta_box(input: &[u8]) -> IResult<&[u8], Option<MetaBox>> {
     let remain = input;
     let (remain, bbox) = BoxHolder::parse(remain)?;
     if bbox.box_type() != "ftyp" {
@@ -63,8 +108,12 @@ pub(crate) fn extract_exif_data(input: &[u8]) -> IResult<&[u8], Option<&[u8]>> {
     let (_, Some(bbox)) = find_box(remain, "meta")? else {
         return Ok((remain, None));
     };
-    let (_, bbox) = MetaBox::parse_box(bbox.data)?;
-    let (out_remain, data) = bbox.exif_data(input)?;
+    let (_, meta) = MetaBox::parse_box(bbox.data)?;
+    Ok((remain, Some(meta)))
+}
+
+fn meta_exif_data<'a>(meta: &MetaBox, input: &'a [u8]) -> IResult<&'a [u8], Option<&'a [u8]>> {
+    let (out_remain, data) = meta.exif_data(input)?;
 
     if let Some(data) = data {
         let (remain, _) = be_u32(data)?;
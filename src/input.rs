@@ -1,17 +1,132 @@
-use crate::slice::SubsliceRange as _;
+use crate::slice::SubsliceRange;
 
-use std::borrow::Borrow;
-use std::borrow::Cow;
-use std::ops::Deref;
-use std::ops::Range;
-use std::slice;
+use core::borrow::Borrow;
+use core::ops::Deref;
+use core::ops::Range;
+use core::slice;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub(crate) struct Input<'a> {
-    pub(crate) data: Cow<'a, [u8]>,
+    pub(crate) data: InputData<'a>,
     pub(crate) range: Range<usize>,
 }
 
+/// The buffer of an [`Input`], either borrowed from the caller or owned.
+#[derive(Clone, Debug)]
+pub(crate) enum InputData<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Vec<u8>),
+    #[cfg(feature = "bytes")]
+    Bytes(bytes::Bytes),
+    #[cfg(feature = "mmap")]
+    Mmap(std::sync::Arc<memmap2::Mmap>),
+    #[cfg(feature = "std")]
+    Pooled(PooledBuffer),
+}
+
+impl PartialEq for InputData<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for InputData<'_> {}
+
+impl Default for InputData<'_> {
+    fn default() -> Self {
+        InputData::Borrowed(&[])
+    }
+}
+
+impl Deref for InputData<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        match self {
+            InputData::Borrowed(data) => data,
+            InputData::Owned(data) => data,
+            #[cfg(feature = "bytes")]
+            InputData::Bytes(data) => data,
+            #[cfg(feature = "mmap")]
+            InputData::Mmap(data) => data,
+            #[cfg(feature = "std")]
+            InputData::Pooled(data) => &data.data,
+        }
+    }
+}
+
+/// Buffers larger than this are freed rather than kept in a [`BufferPool`],
+/// so that a single large file doesn't pin its memory.
+#[cfg(feature = "std")]
+const MAX_POOLED_CAPACITY: usize = 4 * 1024 * 1024;
+
+/// Buffers of dropped [`Input`]s, which are reused to read the next files.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    pub(crate) max_buffers: usize,
+}
+
+#[cfg(feature = "std")]
+impl BufferPool {
+    pub(crate) fn new(max_buffers: usize) -> Arc<BufferPool> {
+        Arc::new(BufferPool {
+            buffers: Mutex::new(Vec::new()),
+            max_buffers,
+        })
+    }
+
+    /// Returns a pooled buffer with room for `capacity` bytes, if any.
+    pub(crate) fn take(&self, capacity: usize) -> Option<Vec<u8>> {
+        let mut buf = self.buffers().pop()?;
+        buf.reserve(capacity);
+        Some(buf)
+    }
+
+    /// Returns the number of buffers which are ready to be reused.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.buffers().len()
+    }
+
+    fn put(&self, mut buf: Vec<u8>) {
+        if buf.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        buf.clear();
+        let mut buffers = self.buffers();
+        if buffers.len() < self.max_buffers {
+            buffers.push(buf);
+        }
+    }
+
+    fn buffers(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A buffer which goes back to its [`BufferPool`] when dropped, unless the
+/// pool (i.e. the parser) has been dropped first.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub(crate) struct PooledBuffer {
+    data: Vec<u8>,
+    pool: Weak<BufferPool>,
+}
+
+#[cfg(feature = "std")]
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.upgrade() {
+            pool.put(core::mem::take(&mut self.data));
+        }
+    }
+}
+
 impl Input<'_> {
     pub(crate) fn from_vec(data: Vec<u8>) -> Input<'static> {
         let range = 0..data.len();
@@ -21,14 +136,55 @@ impl Input<'_> {
     pub(crate) fn from_vec_range(data: Vec<u8>, range: Range<usize>) -> Input<'static> {
         assert!(range.end <= data.len());
         Input {
-            data: Cow::Owned(data),
+            data: InputData::Owned(data),
+            range,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn from_pooled_range(
+        data: Vec<u8>,
+        range: Range<usize>,
+        pool: &Arc<BufferPool>,
+    ) -> Input<'static> {
+        assert!(range.end <= data.len());
+        Input {
+            data: InputData::Pooled(PooledBuffer {
+                data,
+                pool: Arc::downgrade(pool),
+            }),
+            range,
+        }
+    }
+
+    pub(crate) fn from_slice_range(data: &[u8], range: Range<usize>) -> Input<'_> {
+        assert!(range.end <= data.len());
+        Input {
+            data: InputData::Borrowed(data),
+            range,
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    pub(crate) fn from_bytes_range(data: bytes::Bytes, range: Range<usize>) -> Input<'static> {
+        assert!(range.end <= data.len());
+        Input {
+            data: InputData::Bytes(data),
+            range,
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    pub(crate) fn from_mmap_range(data: memmap2::Mmap, range: Range<usize>) -> Input<'static> {
+        assert!(range.end <= data.len());
+        Input {
+            data: InputData::Mmap(std::sync::Arc::new(data)),
             range,
         }
     }
 
     pub(crate) fn make_associated(&self, subslice: &[u8]) -> AssociatedInput {
-        let _ = self
-            .subslice_range(subslice)
+        let _ = SubsliceRange::subslice_range(&self[..], subslice)
             .expect("subslice should be a sub slice of self");
 
         AssociatedInput::new(subslice)
@@ -37,13 +193,7 @@ impl Input<'_> {
 
 impl<'a> From<&'a [u8]> for Input<'a> {
     fn from(data: &'a [u8]) -> Self {
-        Input {
-            data: Cow::Borrowed(data),
-            range: Range {
-                start: 0,
-                end: data.len(),
-            },
-        }
+        Input::from_slice_range(data, 0..data.len())
     }
 }
 
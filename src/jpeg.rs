@@ -1,9 +1,21 @@
-use crate::{exif::read_exif, file::FileFormat};
-use std::io::{Read, Seek};
+#[cfg(feature = "std")]
+use crate::{
+    capabilities::{exif_capabilities, Capabilities},
+    event::ParseEvent,
+    exif::read_exif,
+    file::FileFormat,
+    input::Input,
+    prelude::*,
+    MediaParser,
+};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek, SeekFrom};
 
 use nom::{bytes::streaming, combinator::fail, number, sequence::tuple, IResult};
 
-use crate::exif::{check_exif_header, input_to_exif, Exif};
+use crate::exif::check_exif_header;
+#[cfg(feature = "std")]
+use crate::exif::{input_to_exif, Exif};
 
 /// Analyze the byte stream in the `reader` as a JPEG file, attempting to
 /// extract Exif data it may contain.
@@ -40,8 +52,9 @@ use crate::exif::{check_exif_header, input_to_exif, Exif};
 ///     .collect::<Vec<_>>()
 /// );
 /// ```
+#[cfg(feature = "std")]
 pub fn parse_jpeg_exif<R: Read>(reader: R) -> crate::Result<Option<Exif>> {
-    read_exif(reader, Some(FileFormat::Jpeg))?
+    read_exif(reader, Some(FileFormat::Jpeg), &MediaParser::default())?
         .map(input_to_exif)
         .transpose()
 }
@@ -60,6 +73,167 @@ pub fn extract_exif_data(input: &[u8]) -> IResult<&[u8], Option<&[u8]>> {
     Ok((remain, data))
 }
 
+/// Reads the Exif data of a JPEG file which can seek: the segments which
+/// precede the Exif segment (e.g. multi-megabyte ICC profiles in APP2) are
+/// skipped by seeking, so only the Exif segment is read into the buffer.
+///
+/// Returns the Exif data, and the position of the buffer in the file.
+#[cfg(feature = "std")]
+pub(crate) fn read_exif_seekable<R: Read + Seek>(
+    mut reader: R,
+    parser: &MediaParser,
+) -> crate::Result<Option<(Input<'static>, usize)>> {
+    let mut buf = parser.alloc_buffer(0);
+    let mut header = [0u8; 4];
+    read_segment_data(&mut reader, &mut header[..2], 0, parser)?;
+    check_jpeg(&header)?;
+
+    let mut pos = 2;
+    loop {
+        read_segment_data(&mut reader, &mut header[..2], 0, parser)?;
+        let code = header[1];
+        if header[0] != 0xFF {
+            return Err("invalid JPEG segment; marker not found".into());
+        }
+        pos += 2;
+        if code == MarkerCode::Soi.code() {
+            continue;
+        }
+        if code == MarkerCode::Sos.code() || code == MarkerCode::Eoi.code() {
+            return Ok(None);
+        }
+
+        read_segment_data(&mut reader, &mut header[2..], 0, parser)?;
+        let size = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let Some(payload_len) = size.checked_sub(2) else {
+            return Err("invalid JPEG segment; size is too small".into());
+        };
+        pos += 2;
+
+        if code == MarkerCode::APP1.code() && payload_len > 6 {
+            parser.check_metadata_size(payload_len)?;
+            parser.reserve_buffer(&mut buf, payload_len);
+            buf.resize(EXIF_IDENT_LEN, 0);
+            read_segment_data(&mut reader, &mut buf, EXIF_IDENT_LEN, parser)?;
+            if check_exif_header(&buf) {
+                buf.resize(payload_len, 0);
+                read_segment_data(&mut reader, &mut buf[EXIF_IDENT_LEN..], payload_len, parser)?;
+                let input = parser.buffer_to_input(buf, EXIF_IDENT_LEN..payload_len);
+                return Ok(Some((input, pos)));
+            }
+            buf.clear();
+            skip_segment_data(&mut reader, payload_len - EXIF_IDENT_LEN, parser)?;
+        } else {
+            skip_segment_data(&mut reader, payload_len, parser)?;
+        }
+        parser.emit(|| ParseEvent::SegmentSkipped {
+            marker: code,
+            offset: pos as u64 - 4,
+            size: payload_len as u64,
+        });
+        pos += payload_len;
+    }
+}
+
+/// Length of the `Exif\0\0` identifier which precedes the TIFF data.
+#[cfg(feature = "std")]
+const EXIF_IDENT_LEN: usize = 6;
+
+/// Fills `buf`, or returns [`crate::Error::Truncated`] if the file ends
+/// first. `buffered` is the number of bytes in the buffer afterwards.
+#[cfg(feature = "std")]
+fn read_segment_data<R: Read>(
+    reader: &mut R,
+    buf: &mut [u8],
+    buffered: usize,
+    parser: &MediaParser,
+) -> crate::Result<()> {
+    match reader.read_exact(buf) {
+        Ok(()) => {
+            parser.stats.read(buf.len(), buffered);
+            Ok(())
+        }
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(crate::Error::Truncated { needed: None })
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(feature = "std")]
+fn skip_segment_data<R: Seek>(reader: &mut R, n: usize, parser: &MediaParser) -> crate::Result<()> {
+    parser.check_cancelled()?;
+    if n > 0 {
+        reader.seek(SeekFrom::Current(n as i64))?;
+        parser.stats.skip(n as u64);
+    }
+    Ok(())
+}
+
+/// Like [`extract_exif_data`], for a file which ends within its Exif
+/// segment: returns the available part of the Exif data.
+#[cfg(feature = "std")]
+pub(crate) fn extract_partial_exif_data(input: &[u8]) -> Option<&[u8]> {
+    let is_exif = |code: u8, payload: &[u8]| {
+        code == MarkerCode::APP1.code() && payload.len() > 6 && check_exif_header(payload)
+    };
+
+    let mut remain = input;
+    loop {
+        let (rem, (_, code)) =
+            tuple((streaming::tag::<_, _, ()>([0xFF]), number::streaming::u8))(remain).ok()?;
+        match parse_segment(code, rem) {
+            Ok((rem, segment)) => {
+                if code == MarkerCode::Sos.code() {
+                    return None;
+                }
+                if is_exif(code, segment.payload) {
+                    return Some(&segment.payload[6..]); // Safe-slice
+                }
+                remain = rem;
+            }
+            Err(nom::Err::Incomplete(_)) => {
+                // The file ends within this segment, skip the segment size
+                let payload = rem.get(2..)?;
+                return is_exif(code, payload).then(|| &payload[6..]); // Safe-slice
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Identifies an XMP packet in an APP1 segment.
+#[cfg(feature = "std")]
+pub(crate) const XMP_IDENT: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Identifies Photoshop image resources (which hold IPTC data) in an APP13
+/// segment.
+#[cfg(feature = "std")]
+pub(crate) const PHOTOSHOP_IDENT: &[u8] = b"Photoshop 3.0\0";
+
+/// Scans the segments before SOS, and returns the kinds of metadata found.
+#[cfg(feature = "std")]
+pub(crate) fn scan_capabilities(input: &[u8]) -> IResult<&[u8], Capabilities> {
+    let mut caps = Capabilities::empty();
+    let (remain, _) = travel_until(input, |s| {
+        let payload = s.payload;
+        if s.marker_code == MarkerCode::APP1.code() && check_exif_header(payload) {
+            caps |= Capabilities::EXIF;
+            if let Some(data) = payload.get(6..) {
+                caps |= exif_capabilities(data);
+            }
+        } else if s.marker_code == MarkerCode::APP1.code() && payload.starts_with(XMP_IDENT) {
+            caps |= Capabilities::XMP;
+        } else if s.marker_code == MarkerCode::APP13.code() && payload.starts_with(PHOTOSHOP_IDENT)
+        {
+            caps |= Capabilities::IPTC;
+        }
+        s.marker_code == MarkerCode::Sos.code()
+    })?;
+
+    Ok((remain, caps))
+}
+
 struct Segment<'a> {
     marker_code: u8,
     payload: &'a [u8],
@@ -105,10 +279,7 @@ where
     }
 }
 
-/// len of input should be >= 2
 pub fn check_jpeg(input: &[u8]) -> crate::Result<()> {
-    assert!(input.len() >= 2);
-
     // check SOI marker [0XFF, 0XD8]
     let (_, (_, code)) = tuple((nom::bytes::complete::tag([0xFF]), number::complete::u8))(input)?;
 
@@ -153,6 +324,7 @@ fn parse_segment(marker_code: u8, input: &[u8]) -> IResult<&[u8], Segment<'_>> {
 ///
 /// The returned data might include several other SOS markers if the image is a
 /// progressive JPEG.
+#[cfg(feature = "std")]
 #[allow(dead_code)]
 fn read_image_data<T: Read + Seek>(mut reader: T) -> crate::Result<Vec<u8>> {
     let mut header = [0u8; 2];
@@ -177,11 +349,7 @@ fn read_image_data<T: Read + Seek>(mut reader: T) -> crate::Result<Vec<u8>> {
             reader.read_to_end(&mut data)?;
 
             // remove tail data
-            loop {
-                let Some(tail) = data.pop() else {
-                    // empty
-                    break;
-                };
+            while let Some(tail) = data.pop() {
                 if tail == MarkerCode::Eoi.code() {
                     if let Some(tail) = data.pop() {
                         if tail == 0xFF {
@@ -202,13 +370,20 @@ fn read_image_data<T: Read + Seek>(mut reader: T) -> crate::Result<Vec<u8>> {
 }
 
 /// A marker code is a byte following 0xFF that indicates the kind of marker.
-enum MarkerCode {
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) enum MarkerCode {
     // Start of Image
     Soi = 0xD8,
 
+    // APP0 marker (JFIF)
+    APP0 = 0xE0,
+
     // APP1 marker
     APP1 = 0xE1,
 
+    // APP13 marker (Photoshop image resources)
+    APP13 = 0xED,
+
     // Start of Scan
     Sos = 0xDA,
 
@@ -217,7 +392,7 @@ enum MarkerCode {
 }
 
 impl MarkerCode {
-    fn code(self) -> u8 {
+    pub(crate) fn code(self) -> u8 {
         self as u8
     }
 }
@@ -323,6 +498,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn jpeg_seek_segments() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // 4 large APP2 segments before the Exif segment
+        let sample = read_sample("exif.jpg").unwrap();
+        let mut data = sample[..2].to_vec();
+        for _ in 0..4 {
+            data.extend_from_slice(&[0xFF, 0xE2, 0xFF, 0xFF]);
+            data.extend_from_slice(&[0; 0xFFFF - 2]);
+        }
+        data.extend_from_slice(&sample[2..]);
+
+        let parser = MediaParser::builder().max_metadata_size(0x8000).build();
+        let info = parser.parse_media(std::io::Cursor::new(&data)).unwrap();
+        let mut iter = info.unwrap().into_exif().unwrap();
+        let stats = parser.take_stats();
+        assert_eq!(stats.skips, 4);
+        assert_eq!(stats.skipped_bytes, 4 * (0xFFFF - 2));
+        assert!(stats.peak_buffer_size < 0x8000);
+
+        // Offsets are still file offsets
+        let model = iter.find(|x| x.tag() == Some(Model)).unwrap();
+        let start = model.value_offset() as usize;
+        assert_eq!(&data[start..start + 13], b"vivo X90 Pro+");
+
+        // Reading everything up to the Exif segment exceeds the limit
+        parser
+            .parse_exif(std::io::Cursor::new(&data), None)
+            .unwrap_err();
+
+        let f = open_sample("no-exif.jpg").unwrap();
+        assert!(parser.parse_media(f).unwrap().is_none());
+
+        let end = 4 * 0xFFFF + 0x1000;
+        let err = parser
+            .parse_media(std::io::Cursor::new(&data[..end]))
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Truncated { .. }), "{err}");
+    }
+
     #[test]
     fn broken_jpg() {
         let _ = tracing_subscriber::fmt().with_test_writer().try_init();
@@ -0,0 +1,227 @@
+//! Conversions from/to the types of
+//! [kamadak-exif](https://crates.io/crates/kamadak-exif), enabled by the
+//! `kamadak-exif` feature flag.
+//!
+//! The conversions which may lose information are `TryFrom`: kamadak-exif
+//! values are arrays, whereas [`EntryValue`]s only hold several components
+//! for rationals and undefined data, as when they're parsed.
+
+use ::exif::{Context, Field, In, SRational, Tag, Value};
+
+use crate::{exif::ExifTagCode, values::Rational, EntryValue, ExifTag, IfdKind, ParsedExifEntry};
+
+fn unsupported(what: &str, len: usize) -> crate::Error {
+    crate::Error::InvalidEntry(format!("{what} with {len} components").into())
+}
+
+/// Fails for the values which don't fit in the 32-bit integers of Exif.
+/// Times are converted to Exif's `"YYYY:MM:DD HH:MM:SS"` text, without their
+/// UTC offset.
+impl TryFrom<EntryValue> for Value {
+    type Error = crate::Error;
+
+    fn try_from(value: EntryValue) -> Result<Self, Self::Error> {
+        let out_of_range = || crate::Error::InvalidEntry(format!("{value:?} is too large").into());
+        let rational = |x: &Rational<u32>| ::exif::Rational {
+            num: x.0,
+            denom: x.1,
+        };
+        let srational = |x: &Rational<i32>| SRational {
+            num: x.0,
+            denom: x.1,
+        };
+        Ok(match value {
+            EntryValue::Text(ref s) => Value::Ascii(vec![s.as_bytes().to_vec()]),
+            EntryValue::URational(ref x) => Value::Rational(vec![rational(x)]),
+            EntryValue::IRational(ref x) => Value::SRational(vec![srational(x)]),
+            EntryValue::U8(x) => Value::Byte(vec![x]),
+            EntryValue::U16(x) => Value::Short(vec![x]),
+            EntryValue::U32(x) => Value::Long(vec![x]),
+            EntryValue::U64(x) => Value::Long(vec![x.try_into().map_err(|_| out_of_range())?]),
+            EntryValue::I8(x) => Value::SByte(vec![x]),
+            EntryValue::I16(x) => Value::SShort(vec![x]),
+            EntryValue::I32(x) => Value::SLong(vec![x]),
+            EntryValue::I64(x) => Value::SLong(vec![x.try_into().map_err(|_| out_of_range())?]),
+            EntryValue::F32(x) => Value::Float(vec![x]),
+            EntryValue::F64(x) => Value::Double(vec![x]),
+            EntryValue::Time(t) => {
+                let s = t.format("%Y:%m:%d %H:%M:%S").to_string();
+                Value::Ascii(vec![s.into_bytes()])
+            }
+            EntryValue::Undefined(data) => Value::Undefined(data, 0),
+            EntryValue::URationalArray(v) => Value::Rational(v.iter().map(rational).collect()),
+            EntryValue::IRationalArray(v) => Value::SRational(v.iter().map(srational).collect()),
+        })
+    }
+}
+
+/// Fails for the arrays of integers or floats which don't have exactly one
+/// component, and for the values of unknown types.
+impl TryFrom<&Value> for EntryValue {
+    type Error = crate::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        fn single<T: Copy>(what: &str, v: &[T]) -> crate::Result<T> {
+            match v {
+                [x] => Ok(*x),
+                _ => Err(unsupported(what, v.len())),
+            }
+        }
+        Ok(match value {
+            Value::Byte(v) if v.len() == 1 => EntryValue::U8(v[0]),
+            Value::Byte(v) => EntryValue::Undefined(v.clone()),
+            Value::Ascii(v) => {
+                let s = v.first().map(|x| String::from_utf8_lossy(x));
+                EntryValue::Text(s.unwrap_or_default().into_owned())
+            }
+            Value::Short(v) => EntryValue::U16(single("unsigned short", v)?),
+            Value::Long(v) => EntryValue::U32(single("unsigned long", v)?),
+            Value::Rational(v) => {
+                let mut v = v.iter().map(|x| Rational::new(x.num, x.denom));
+                match v.len() {
+                    1 => EntryValue::URational(v.next().expect("one component")),
+                    _ => EntryValue::URationalArray(v.collect()),
+                }
+            }
+            Value::SByte(v) => EntryValue::I8(single("signed byte", v)?),
+            Value::Undefined(v, _) => EntryValue::Undefined(v.clone()),
+            Value::SShort(v) => EntryValue::I16(single("signed short", v)?),
+            Value::SLong(v) => EntryValue::I32(single("signed long", v)?),
+            Value::SRational(v) => {
+                let mut v = v.iter().map(|x| Rational::new(x.num, x.denom));
+                match v.len() {
+                    1 => EntryValue::IRational(v.next().expect("one component")),
+                    _ => EntryValue::IRationalArray(v.collect()),
+                }
+            }
+            Value::Float(v) => EntryValue::F32(single("float", v)?),
+            Value::Double(v) => EntryValue::F64(single("double", v)?),
+            Value::Unknown(t, _, _) => {
+                let msg = format!("unknown value type {t}");
+                return Err(crate::Error::InvalidEntry(msg.into()));
+            }
+        })
+    }
+}
+
+impl From<IfdKind> for Context {
+    fn from(kind: IfdKind) -> Self {
+        match kind {
+            IfdKind::Ifd0 | IfdKind::Ifd1 | IfdKind::SubIfd(_) => Context::Tiff,
+            IfdKind::Exif => Context::Exif,
+            IfdKind::Gps => Context::Gps,
+            IfdKind::Interop => Context::Interop,
+        }
+    }
+}
+
+/// The context of the tag is the IFD where it's conventionally stored.
+impl From<ExifTag> for Tag {
+    fn from(tag: ExifTag) -> Self {
+        Tag(tag.info().ifd.into(), tag.code())
+    }
+}
+
+/// Fails for the tags which aren't recognized by nom-exif.
+impl TryFrom<Tag> for ExifTag {
+    type Error = crate::Error;
+
+    fn try_from(tag: Tag) -> Result<Self, Self::Error> {
+        match tag.context() {
+            Context::Interop => ExifTagCode::in_ifd(IfdKind::Interop, tag.number())
+                .tag()
+                .ok_or_else(|| format!("Unrecognized ExifTag 0x{:04x}", tag.number()).into()),
+            _ => tag.number().try_into(),
+        }
+    }
+}
+
+/// Takes the value of the entry (see [`ParsedExifEntry::take_result`]), so
+/// fails if it couldn't be parsed, or has already been taken.
+impl TryFrom<ParsedExifEntry> for Field {
+    type Error = crate::Error;
+
+    fn try_from(entry: ParsedExifEntry) -> Result<Self, Self::Error> {
+        Ok(Field {
+            tag: Tag(entry.ifd_kind().into(), entry.tag_code()),
+            ifd_num: In(entry.ifd_index().try_into().unwrap_or(u16::MAX)),
+            value: entry.take_result()?.try_into()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, testkit::*};
+    use test_case::test_case;
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn kamadak_fields(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let expected = ::exif::Reader::new()
+            .read_from_container(&mut std::io::BufReader::new(open_sample(path).unwrap()))
+            .unwrap();
+
+        let mut count = 0;
+        for entry in iter.clone() {
+            let Some(tag) = entry.tag() else { continue };
+            let in_ifd = In(entry.ifd_index() as u16);
+            let Ok(field) = Field::try_from(entry) else {
+                continue;
+            };
+            assert_eq!(Tag::from(tag).number(), field.tag.number());
+            assert_eq!(ExifTag::try_from(field.tag).unwrap(), tag);
+
+            let Some(other) = expected.get_field(field.tag, in_ifd) else {
+                continue;
+            };
+            // The times are parsed by nom-exif, and converted back to text
+            let value = EntryValue::try_from(&field.value).unwrap();
+            if !matches!(value, EntryValue::Text(_)) {
+                assert_eq!(value, EntryValue::try_from(&other.value).unwrap(), "{tag}");
+            }
+            count += 1;
+        }
+        assert!(count > 10, "{count}");
+    }
+
+    #[test]
+    fn kamadak_values() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let values = [
+            EntryValue::Text("Apple".into()),
+            EntryValue::U16(6),
+            EntryValue::U32(4032),
+            EntryValue::I32(-2),
+            EntryValue::F64(1.5),
+            EntryValue::URational(Rational::new(175, 100)),
+            EntryValue::IRationalArray(vec![Rational::new(-1, 3), Rational::new(2, 1)]),
+            EntryValue::Undefined(b"0231".to_vec()),
+        ];
+        for v in values {
+            let value = Value::try_from(v.clone()).unwrap();
+            assert_eq!(EntryValue::try_from(&value).unwrap(), v);
+        }
+
+        assert!(Value::try_from(EntryValue::U64(u64::MAX)).is_err());
+        assert!(EntryValue::try_from(&Value::Short(vec![1, 2])).is_err());
+        assert_eq!(
+            EntryValue::try_from(&Value::Byte(vec![1, 2])).unwrap(),
+            EntryValue::Undefined(vec![1, 2])
+        );
+
+        let t = EntryValue::Time("2023-07-09T20:36:33+08:00".parse().unwrap());
+        assert!(matches!(
+            Value::try_from(t).unwrap(),
+            Value::Ascii(v) if v == [b"2023:07:09 20:36:33"]
+        ));
+        assert_eq!(Tag::from(ExifTag::DateTimeOriginal), Tag::DateTimeOriginal);
+    }
+}
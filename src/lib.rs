@@ -162,32 +162,227 @@
 //! }
 //! ```
 //!
+//! ## `no_std`
+//!
+//! The `std` feature flag is enabled by default. Without it, the crate is
+//! `no_std` (it still needs `alloc`), and only parses data which is in
+//! memory, e.g. with [`parse_exif_slice`], [`ExifIter`], [`Exif`] and
+//! [`FileFormat`]. The readers, [`MediaParser`] and the other feature flags
+//! require `std`.
+//!
+//! ```toml
+//! [dependencies]
+//! nom-exif = { version = "1", default-features = false }
+//! ```
+//!
 //! For more usage details, please refer to the [API
 //! documentation](https://docs.rs/nom-exif/latest/nom_exif/).
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub use capabilities::{capabilities, Capabilities};
+#[cfg(feature = "std")]
+pub use capture_time::{best_capture_time, CaptureTimeInfo, CaptureTimeSource};
+#[cfg(feature = "std")]
+pub use codec::{ChromaSubsampling, CodecConfig};
+#[cfg(feature = "std")]
+pub use color::{ColorInfo, ContentLightLevel, DolbyVision, HdrFormat, MasteringDisplay};
+#[cfg(feature = "std")]
+pub use diff::{diff, diff_tracks, TagChange};
+#[cfg(feature = "std")]
 pub use heif::parse_heif_exif;
+#[cfg(feature = "std")]
 pub use jpeg::parse_jpeg_exif;
-pub use mov::{parse_metadata, parse_mov_metadata};
+#[cfg(feature = "std")]
+pub use mov::{
+    parse_metadata, parse_metadata_iter, parse_mov_metadata, MetadataIter, TrackKind, TrackMeta,
+};
 
+#[cfg(feature = "std")]
+pub use event::ParseEvent;
 #[cfg(feature = "async")]
 pub use exif::parse_exif_async;
-pub use exif::{parse_exif, Exif, ExifIter, ExifTag, GPSInfo, LatLng, ParsedExifEntry};
-pub use file::FileFormat;
-pub use values::EntryValue;
+#[cfg(feature = "bytes")]
+pub use exif::parse_exif_bytes;
+#[cfg(feature = "mmap")]
+pub use exif::parse_exif_mmap;
+#[cfg(all(feature = "async", feature = "stream"))]
+pub use exif::parse_exif_stream;
+#[cfg(feature = "stream")]
+pub use exif::ExifStream;
+#[cfg(feature = "std")]
+pub use exif::{parse_exif, Finding};
+pub use exif::{
+    parse_exif_slice, CompositeTag, DuplicatePolicy, Exif, ExifIter, ExifTag, GPSInfo, IfdKind,
+    LatLng, LensInfo, Orientation, ParsedExifEntry, TagInfo, TagType,
+};
+#[cfg(feature = "std")]
+pub use file::detect_format;
+pub use file::{FileFormat, MediaType};
+#[cfg(feature = "std")]
+pub use geocode::{Geocoder, Place};
+#[cfg(feature = "std")]
+pub use geotag::{CaptureTime, GeotagOptions, Geotagger, GpxTrack, TimeZoneStrategy, TrackPoint};
+#[cfg(feature = "mmap")]
+pub use media::parse_media_mmap;
+#[cfg(feature = "async")]
+pub use media::{parse_file_async, parse_media_async};
+#[cfg(feature = "std")]
+pub use media::{parse_media, MediaInfo, MediaParser, MediaParserBuilder, MediaSummary};
+#[cfg(feature = "std")]
+pub use mkv::{parse_matroska_metadata, parse_matroska_tags, parse_matroska_tracks};
+#[cfg(feature = "async")]
+pub use mov::{parse_metadata_async, parse_metadata_iter_async};
+#[cfg(feature = "std")]
+pub use push::{FeedStatus, PushParser};
+#[cfg(feature = "std")]
+pub use stats::ParseStats;
+pub use values::{EntryValue, IRational, Rational, URational};
+#[cfg(feature = "std")]
+pub use visit::{visit_exif, visit_metadata};
+#[cfg(feature = "std")]
+pub use writer::{
+    copy_metadata, read_exif_data, read_xmp, rewrite_file, strip_metadata, write_metadata,
+    write_metadata_in_place, write_orientation, CopyOptions, ExifBuilder, MediaWriter,
+    MetadataEdits, StripOptions,
+};
+
+pub use error::{Error, Limit, Warning};
+pub type Result<T> = core::result::Result<T, Error>;
+
+// Parsers and their results can be moved across threads and shared, e.g.
+// stored in the state of a web server.
+#[cfg(feature = "std")]
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<MediaParser>();
+    assert_send_sync::<ExifIter<'static>>();
+    assert_send_sync::<ParsedExifEntry>();
+    assert_send_sync::<Exif>();
+    assert_send_sync::<EntryValue>();
+    assert_send_sync::<GPSInfo>();
+    assert_send_sync::<MetadataIter>();
+    assert_send_sync::<TrackMeta>();
+    assert_send_sync::<MediaInfo>();
+    assert_send_sync::<MediaSummary>();
+    assert_send_sync::<Place>();
+    assert_send_sync::<Error>();
+};
+
+/// The `alloc` items of the std prelude, for the modules which are built
+/// without the `std` feature.
+mod prelude {
+    pub(crate) use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    /// The float functions of `std`, which `core` lacks.
+    #[cfg(not(feature = "std"))]
+    pub(crate) trait Float {
+        fn atan(self) -> Self;
+        fn floor(self) -> Self;
+        fn hypot(self, other: Self) -> Self;
+        fn log2(self) -> Self;
+        fn powf(self, n: Self) -> Self;
+        fn round(self) -> Self;
+        fn trunc(self) -> Self;
+    }
 
-pub use error::Error;
-pub type Result<T> = std::result::Result<T, Error>;
+    #[cfg(not(feature = "std"))]
+    impl Float for f64 {
+        fn atan(self) -> f64 {
+            libm::atan(self)
+        }
+        fn floor(self) -> f64 {
+            libm::floor(self)
+        }
+        fn hypot(self, other: f64) -> f64 {
+            libm::hypot(self, other)
+        }
+        fn log2(self) -> f64 {
+            libm::log2(self)
+        }
+        fn powf(self, n: f64) -> f64 {
+            libm::pow(self, n)
+        }
+        fn round(self) -> f64 {
+            libm::round(self)
+        }
+        fn trunc(self) -> f64 {
+            libm::trunc(self)
+        }
+    }
+}
 
+#[cfg(any(feature = "async", feature = "futures-io"))]
+mod async_io;
+#[cfg(feature = "rayon")]
+pub mod batch;
 mod bbox;
+#[cfg(feature = "std")]
+mod capabilities;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "std")]
+mod capture_time;
+#[cfg(feature = "std")]
+mod codec;
+#[cfg(feature = "std")]
+mod color;
+#[cfg(feature = "std")]
+mod diff;
+mod ebml;
 mod error;
+#[cfg(feature = "std")]
+mod event;
 mod exif;
+#[cfg(feature = "std")]
+pub mod export;
 mod file;
+#[cfg(feature = "std")]
+mod fingerprint;
+#[cfg(feature = "futures-io")]
+pub mod futures_io;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+#[cfg(feature = "std")]
+mod geocode;
+#[cfg(feature = "std")]
+mod geotag;
 mod heif;
 mod input;
 mod jpeg;
+#[cfg(feature = "kamadak-exif")]
+mod kamadak;
+#[cfg(feature = "std")]
+mod media;
+#[cfg(feature = "std")]
+mod mkv;
+#[cfg(feature = "std")]
 mod mov;
+#[cfg(feature = "std")]
+mod push;
 mod slice;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "tz")]
+pub mod tz;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
 mod values;
+#[cfg(feature = "std")]
+mod visit;
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub mod wasm;
+#[cfg(feature = "std")]
+mod writer;
 
 #[cfg(test)]
 mod testkit;
@@ -0,0 +1,2232 @@
+use std::{
+    fmt::Debug,
+    io::{Read, Seek},
+    ops::{ControlFlow, Range},
+    sync::Arc,
+    time::Instant,
+};
+
+use chrono::{DateTime, Duration, FixedOffset};
+#[cfg(all(feature = "async", feature = "stream"))]
+use futures_core::Stream;
+#[cfg(all(feature = "async", feature = "stream"))]
+use futures_util::StreamExt;
+
+#[cfg(feature = "async")]
+use crate::async_io::TokioSource;
+#[cfg(any(feature = "async", feature = "futures-io"))]
+use crate::async_io::{AsyncSeekSource, AsyncSource};
+use crate::{
+    capabilities::{read_capabilities, Capabilities},
+    capture_time::{read_capture_time, CaptureTimeInfo},
+    error::Diagnostics,
+    event::{EventSink, ParseEvent},
+    exif::{input_to_iter_with_diagnostics, read_exif, read_exif_lenient, ExifLimits},
+    file::FileFormat,
+    geocode::{Geocoder, Place},
+    input::{BufferPool, Input},
+    jpeg::read_exif_seekable,
+    mov::{
+        read_metadata_iter, read_metadata_lenient, read_metadata_unseekable, MetadataEntries,
+        CREATIONDATE_KEY, LOCATION_KEY,
+    },
+    stats::{ParseStats, StatsCounter},
+    visit::{read_visit_exif, read_visit_metadata},
+    EntryValue, Exif, ExifIter, ExifTag, GPSInfo, Limit, MetadataIter, ParsedExifEntry, Warning,
+};
+#[cfg(feature = "mmap")]
+use crate::{
+    exif::exif_range,
+    mov::{self, extract_moov_body_from_buf, new_metadata_iter},
+};
+
+/// Minimum number of bytes read at once when the buffer grows.
+pub(crate) const MIN_GROW_SIZE: usize = 4096;
+
+/// Minimum number of bytes read at first, which is needed to detect the file
+/// format.
+const MIN_BUFFER_CAPACITY: usize = 512;
+
+/// Minimum nesting depth, which is the depth of the deepest boxes which are
+/// parsed, e.g. `moov/trak/mdia/minf/stbl/stsd/avc1/avcC`.
+const MIN_NESTING_DEPTH: usize = 8;
+
+/// The metadata of a media file, as returned by [`parse_media`].
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub enum MediaInfo {
+    /// Exif data of an image (JPEG, HEIF/HEIC).
+    Exif(ExifIter<'static>),
+
+    /// Metadata of a video (QuickTime, MP4).
+    Track(MetadataIter),
+}
+
+impl MediaInfo {
+    /// Returns the Exif data, if this is an image.
+    pub fn into_exif(self) -> Option<ExifIter<'static>> {
+        match self {
+            MediaInfo::Exif(iter) => Some(iter),
+            MediaInfo::Track(_) => None,
+        }
+    }
+
+    /// Returns the video metadata, if this is a video.
+    pub fn into_track(self) -> Option<MetadataIter> {
+        match self {
+            MediaInfo::Exif(_) => None,
+            MediaInfo::Track(iter) => Some(iter),
+        }
+    }
+}
+
+/// A summary of a media file, as returned by [`MediaParser::probe`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaSummary {
+    pub format: FileFormat,
+
+    /// Width and height of the image (from the Exif data) or of the video
+    /// track.
+    pub dimensions: Option<(u32, u32)>,
+
+    /// Duration of a video.
+    pub duration: Option<Duration>,
+
+    /// Capture time, see [`Exif::datetime_original`] for images, and
+    /// `com.apple.quicktime.creationdate` for videos.
+    pub capture_time: Option<DateTime<FixedOffset>>,
+
+    /// Whether the file has GPS info (a GPS IFD, or a location entry).
+    pub has_gps: bool,
+
+    /// The place where the media was captured, as found by the
+    /// [`Geocoder`] of the parser (see [`MediaParserBuilder::geocoder`]).
+    pub place: Option<Place>,
+}
+
+impl MediaSummary {
+    pub(crate) fn new(
+        format: FileFormat,
+        info: Option<MediaInfo>,
+        geocoder: Option<&dyn Geocoder>,
+    ) -> Self {
+        let mut summary = MediaSummary {
+            format,
+            dimensions: None,
+            duration: None,
+            capture_time: None,
+            has_gps: false,
+            place: None,
+        };
+        let gps = match info {
+            Some(MediaInfo::Exif(iter)) => {
+                let exif: Exif = iter.into();
+                summary.dimensions = exif.image_size();
+                summary.capture_time = exif.datetime_original();
+                let gps = exif.get_gps_info().ok().flatten();
+                summary.has_gps = gps.is_some();
+                gps
+            }
+            Some(MediaInfo::Track(iter)) => {
+                let get = |key: &str| iter.get(key).and_then(|x| x.ok());
+                if let (Some(w), Some(h)) = (get("width"), get("height")) {
+                    summary.dimensions = w.to_u32().zip(h.to_u32());
+                }
+                summary.duration = get("duration")
+                    .and_then(|x| x.to_u32())
+                    .map(|ms| Duration::milliseconds(ms.into()));
+                summary.capture_time = get(CREATIONDATE_KEY).and_then(|x| x.as_time());
+                let location = get(LOCATION_KEY);
+                summary.has_gps = location.is_some();
+                location.and_then(|x| GPSInfo::from_iso6709(x.as_str()?).ok())
+            }
+            None => None,
+        };
+        summary.place = geocoder
+            .zip(gps)
+            .and_then(|(g, gps)| g.reverse_geocode(&gps));
+        summary
+    }
+}
+
+/// Detects the file format of `reader`, and parses whatever metadata it has:
+/// Exif data for images (see [`parse_exif`](crate::parse_exif)), and
+/// QuickTime/MP4 metadata for videos (see
+/// [`parse_metadata_iter`](crate::parse_metadata_iter)). The
+/// tags and tracks of Matroska (MKV) and WebM files are returned the same
+/// way, see [`parse_matroska_metadata`](crate::parse_matroska_metadata).
+///
+/// Returns `Ok(None)` if an image doesn't have any Exif data.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// for path in ["./testdata/exif.heic", "./testdata/meta.mov"] {
+///     let f = std::fs::File::open(path).unwrap();
+///     match parse_media(f).unwrap() {
+///         Some(MediaInfo::Exif(iter)) => {
+///             let exif: Exif = iter.into();
+///             assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "Apple");
+///         }
+///         Some(MediaInfo::Track(mut iter)) => {
+///             let (key, value) = iter.next().unwrap();
+///             assert_eq!(key, "com.apple.quicktime.make");
+///             assert_eq!(value.unwrap().to_string(), "Apple");
+///         }
+///         _ => unreachable!(),
+///     }
+/// }
+/// ```
+pub fn parse_media<R: Read + Seek>(reader: R) -> crate::Result<Option<MediaInfo>> {
+    MediaParser::default().parse_media(reader)
+}
+
+/// Like [`parse_media`], for the file at `path`, which is memory-mapped
+/// rather than read. Requires the `mmap` feature flag.
+///
+/// The Exif data of images, and the `moov` atom of QuickTime/MP4 files, are
+/// parsed directly from the mapping, and the returned iterators keep it
+/// alive: only the pages which hold the metadata are read from disk, and
+/// nothing is copied, e.g. for indexing large photo libraries. Matroska
+/// files are read from the mapping as from a file.
+///
+/// # Safety
+///
+/// The file must not be modified (e.g. truncated), by this or another
+/// process, while the returned [`MediaInfo`] is alive, see
+/// [`parse_exif_mmap`](crate::parse_exif_mmap).
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// // SAFETY: the test data isn't modified
+/// let info = unsafe { parse_media_mmap("./testdata/meta.mov") };
+/// let iter = info.unwrap().unwrap().into_track().unwrap();
+/// assert_eq!(iter.tracks().len(), 4);
+/// ```
+#[cfg(feature = "mmap")]
+pub unsafe fn parse_media_mmap<P: AsRef<std::path::Path>>(
+    path: P,
+) -> crate::Result<Option<MediaInfo>> {
+    // SAFETY: guaranteed by the caller
+    unsafe { MediaParser::default().parse_media_mmap(path) }
+}
+
+/// `async` version of [`parse_media`]: the unneeded data of videos (e.g.
+/// the `mdat` atom) is skipped by seeking, rather than read. A
+/// [`tokio::fs::File`] can be passed as is, see also [`parse_file_async`].
+///
+/// # Usage
+///
+/// ```rust
+/// # #[cfg(feature = "async")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use nom_exif::*;
+///
+/// let f = tokio::fs::File::open("./testdata/meta.mov").await.unwrap();
+/// let iter = parse_media_async(f).await.unwrap().unwrap().into_track().unwrap();
+/// assert_eq!(iter.tracks().len(), 4);
+/// # });
+/// ```
+#[cfg(feature = "async")]
+pub async fn parse_media_async<R>(reader: R) -> crate::Result<Option<MediaInfo>>
+where
+    R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+{
+    MediaParser::default().parse_media_async(reader).await
+}
+
+/// Like [`parse_media_async`], for the file at `path`, which is opened with
+/// [`tokio::fs::File`].
+///
+/// # Usage
+///
+/// ```rust
+/// # #[cfg(feature = "async")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use nom_exif::*;
+///
+/// let info = parse_file_async("./testdata/exif.heic").await.unwrap();
+/// let exif: Exif = info.unwrap().into_exif().unwrap().into();
+/// assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "Apple");
+/// # });
+/// ```
+#[cfg(feature = "async")]
+pub async fn parse_file_async<P: AsRef<std::path::Path>>(
+    path: P,
+) -> crate::Result<Option<MediaInfo>> {
+    MediaParser::default().parse_file_async(path).await
+}
+
+/// A parser with configurable buffer sizes and limits, created by
+/// [`MediaParser::builder`].
+///
+/// The free functions ([`parse_exif`](crate::parse_exif),
+/// [`parse_metadata_iter`](crate::parse_metadata_iter), [`parse_media`],
+/// etc.) use [`MediaParser::default`], which doesn't limit the metadata
+/// size. Services parsing untrusted uploads can use a parser with a maximum
+/// metadata size instead, so memory usage is bounded no matter what the
+/// files claim their sizes are.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let parser = MediaParser::builder()
+///     .buffer_capacity(8 * 1024)
+///     .max_metadata_size(1024 * 1024)
+///     .build();
+///
+/// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// let exif: Exif = parser.parse_exif(f, None).unwrap().unwrap().into();
+/// assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "vivo");
+///
+/// // The Exif data of this file is larger than the limit
+/// let parser = MediaParser::builder().max_metadata_size(1024).build();
+/// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// assert!(parser.parse_exif(f, None).is_err());
+/// ```
+#[derive(Debug)]
+pub struct MediaParser {
+    buffer_capacity: usize,
+    max_buffer_growth: usize,
+    max_metadata_size: Option<usize>,
+    max_ifd_depth: usize,
+    pub(crate) max_entries: Option<usize>,
+    pub(crate) max_string_length: Option<usize>,
+    max_skipped_bytes: Option<u64>,
+    max_nesting_depth: usize,
+    pub(crate) max_discarded_bytes: Option<u64>,
+    pub(crate) max_moov_size: Option<u64>,
+    buffer_pool: Option<Arc<BufferPool>>,
+    cancellation: Cancellation,
+    geocoder: Option<GeocoderHook>,
+    pub(crate) stats: StatsCounter,
+    diagnostics: Diagnostics,
+}
+
+/// When parsing stops early, see [`MediaParserBuilder::deadline`] and
+/// [`MediaParserBuilder::cancel_check`].
+#[derive(Clone, Default)]
+pub(crate) struct Cancellation {
+    deadline: Option<Instant>,
+    check: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl Debug for Cancellation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cancellation")
+            .field("deadline", &self.deadline)
+            .field("check", &self.check.is_some())
+            .finish()
+    }
+}
+
+impl Cancellation {
+    /// Returns `Some(timed_out)` if parsing has been cancelled: the deadline
+    /// has passed (`true`), or the cancel check returns `true` (`false`).
+    pub(crate) fn cancelled(&self) -> Option<bool> {
+        if self.deadline.is_some_and(|x| Instant::now() >= x) {
+            Some(true)
+        } else if self.check.as_ref().is_some_and(|f| f()) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GeocoderHook(Arc<dyn Geocoder>);
+
+impl Debug for GeocoderHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Geocoder")
+    }
+}
+
+/// A cloned parser has the same settings (including the event callback),
+/// but doesn't share the warnings
+/// (see [`MediaParser::take_warnings`]), the statistics (see
+/// [`MediaParser::take_stats`]) nor the buffer pool (see
+/// [`MediaParserBuilder::buffer_pool_size`]).
+impl Clone for MediaParser {
+    fn clone(&self) -> Self {
+        Self {
+            diagnostics: self.diagnostics.renew(),
+            stats: StatsCounter::default(),
+            buffer_pool: self
+                .buffer_pool
+                .as_ref()
+                .map(|x| BufferPool::new(x.max_buffers)),
+            cancellation: self.cancellation.clone(),
+            geocoder: self.geocoder.clone(),
+            ..*self
+        }
+    }
+}
+
+impl Default for MediaParser {
+    fn default() -> Self {
+        Self {
+            buffer_capacity: 4096,
+            max_buffer_growth: 1000 * 4096,
+            max_metadata_size: None,
+            max_ifd_depth: ExifLimits::default().max_ifd_depth,
+            max_entries: None,
+            max_string_length: None,
+            max_skipped_bytes: None,
+            max_nesting_depth: 32,
+            max_discarded_bytes: None,
+            max_moov_size: None,
+            buffer_pool: None,
+            cancellation: Cancellation::default(),
+            geocoder: None,
+            stats: StatsCounter::default(),
+            diagnostics: Diagnostics::new(),
+        }
+    }
+}
+
+impl MediaParser {
+    /// Creates a builder, which starts with the default settings.
+    pub fn builder() -> MediaParserBuilder {
+        MediaParserBuilder::default()
+    }
+
+    /// Like [`parse_exif`](crate::parse_exif), with the limits of this
+    /// parser.
+    pub fn parse_exif<R: Read>(
+        &self,
+        reader: R,
+        format: Option<FileFormat>,
+    ) -> crate::Result<Option<ExifIter<'static>>> {
+        read_exif(reader, format, self)?
+            .map(|input| self.input_to_iter(input))
+            .transpose()
+    }
+
+    /// Like [`parse_exif_async`](crate::parse_exif_async), with the limits
+    /// of this parser.
+    #[cfg(feature = "async")]
+    pub async fn parse_exif_async<R: tokio::io::AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        format: Option<FileFormat>,
+    ) -> crate::Result<Option<ExifIter<'static>>> {
+        self.parse_exif_source(TokioSource(reader), format, &[])
+            .await
+    }
+
+    /// Like [`parse_metadata_iter_async`](crate::parse_metadata_iter_async),
+    /// with the limits of this parser.
+    #[cfg(feature = "async")]
+    pub async fn parse_metadata_iter_async<R>(&self, reader: R) -> crate::Result<MetadataIter>
+    where
+        R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+    {
+        crate::mov::read_metadata_iter_async(TokioSource(reader), self, &[]).await
+    }
+
+    /// Like [`parse_media_async`](crate::parse_media_async), with the limits
+    /// of this parser.
+    #[cfg(feature = "async")]
+    pub async fn parse_media_async<R>(&self, reader: R) -> crate::Result<Option<MediaInfo>>
+    where
+        R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+    {
+        Ok(self.parse_media_source(TokioSource(reader)).await?.1)
+    }
+
+    #[cfg(any(feature = "async", feature = "futures-io"))]
+    pub(crate) async fn parse_exif_source<S: AsyncSource>(
+        &self,
+        reader: S,
+        format: Option<FileFormat>,
+        head: &[u8],
+    ) -> crate::Result<Option<ExifIter<'static>>> {
+        crate::exif::read_exif_async(reader, format, self, head)
+            .await?
+            .map(|input| self.input_to_iter(input))
+            .transpose()
+    }
+
+    /// Returns the format and the metadata of the file in `reader`.
+    #[cfg(any(feature = "async", feature = "futures-io"))]
+    pub(crate) async fn parse_media_source<S: AsyncSeekSource>(
+        &self,
+        mut reader: S,
+    ) -> crate::Result<(FileFormat, Option<MediaInfo>)> {
+        let init_size = self.init_read_size();
+        let mut head = Vec::with_capacity(init_size);
+        reader.read_to_buf(&mut head, init_size).await?;
+        if head.is_empty() {
+            Err(crate::Error::Truncated { needed: None })?;
+        }
+
+        // The head isn't read again
+        let ff = FileFormat::try_from(&head[..])?;
+        let info = match ff {
+            FileFormat::Jpeg | FileFormat::Heif => self
+                .parse_exif_source(reader, Some(ff), &head)
+                .await?
+                .map(MediaInfo::Exif),
+            FileFormat::QuickTime | FileFormat::MP4 => {
+                let iter = crate::mov::read_metadata_iter_async(reader, self, &head).await?;
+                Some(MediaInfo::Track(iter))
+            }
+            // Seeks back to the start of the file
+            FileFormat::Matroska => {
+                let iter = crate::mkv::read_metadata_iter_async(reader, self).await?;
+                Some(MediaInfo::Track(iter))
+            }
+        };
+        Ok((ff, info))
+    }
+
+    /// Like [`parse_file_async`](crate::parse_file_async), with the limits
+    /// of this parser.
+    #[cfg(feature = "async")]
+    pub async fn parse_file_async<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> crate::Result<Option<MediaInfo>> {
+        let f = tokio::fs::File::open(path).await?;
+        self.parse_media_async(f).await
+    }
+
+    /// Parses the readers of `sources` with up to `concurrency` parses in
+    /// flight, and returns their results in the order of `sources`. The
+    /// parses share the limits, the buffer pool and the statistics of this
+    /// parser. Requires the `async` and `stream` feature flags.
+    ///
+    /// See [`Self::parse_many_unordered`] to get the results as soon as
+    /// they're ready.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use futures_util::{stream, StreamExt};
+    /// use nom_exif::*;
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let parser = MediaParser::builder().buffer_pool_size(4).build();
+    /// let paths = ["./testdata/exif.jpg", "./testdata/meta.mov"];
+    /// let files = stream::iter(paths).then(|p| async move {
+    ///     tokio::fs::File::open(p).await.unwrap()
+    /// });
+    ///
+    /// let results = parser.parse_many(files, 4).collect::<Vec<_>>().await;
+    /// assert!(matches!(results[0], Ok(Some(MediaInfo::Exif(_)))));
+    /// assert!(matches!(results[1], Ok(Some(MediaInfo::Track(_)))));
+    /// # });
+    /// ```
+    #[cfg(all(feature = "async", feature = "stream"))]
+    pub fn parse_many<'a, S, R>(
+        &'a self,
+        sources: S,
+        concurrency: usize,
+    ) -> impl Stream<Item = crate::Result<Option<MediaInfo>>> + 'a
+    where
+        S: Stream<Item = R> + 'a,
+        R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin + 'a,
+    {
+        sources
+            .map(move |reader| self.parse_media_async(reader))
+            .buffered(concurrency.max(1))
+    }
+
+    /// Like [`Self::parse_many`], but the results are returned as soon as
+    /// they're ready, with the index of their source in `sources`.
+    #[cfg(all(feature = "async", feature = "stream"))]
+    pub fn parse_many_unordered<'a, S, R>(
+        &'a self,
+        sources: S,
+        concurrency: usize,
+    ) -> impl Stream<Item = (usize, crate::Result<Option<MediaInfo>>)> + 'a
+    where
+        S: Stream<Item = R> + 'a,
+        R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin + 'a,
+    {
+        sources
+            .enumerate()
+            .map(move |(i, reader)| async move { (i, self.parse_media_async(reader).await) })
+            .buffer_unordered(concurrency.max(1))
+    }
+
+    /// Like [`Self::parse_exif`], but only the entries of `tags` are
+    /// collected into an [`Exif`], and parsing stops as soon as they've all
+    /// been found: the following entries and IFDs (e.g. the thumbnail IFD)
+    /// are neither parsed nor decoded. The GPS info (see
+    /// [`Exif::get_gps_info`]) is only parsed if [`ExifTag::GPSInfo`] is one
+    /// of `tags`. All the entries are collected if `tags` is empty.
+    ///
+    /// Reading stops at the end of the Exif data, which is read at once:
+    /// it's a single JPEG segment or HEIF item, usually a few KiB.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let parser = MediaParser::default();
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let tags = [ExifTag::Make, ExifTag::DateTimeOriginal];
+    /// let exif = parser.parse_filtered(f, None, &tags).unwrap().unwrap();
+    ///
+    /// assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "vivo");
+    /// assert!(exif.get(ExifTag::DateTimeOriginal).is_some());
+    /// assert!(exif.get(ExifTag::Model).is_none());
+    /// ```
+    pub fn parse_filtered<R: Read>(
+        &self,
+        reader: R,
+        format: Option<FileFormat>,
+        tags: &[ExifTag],
+    ) -> crate::Result<Option<Exif>> {
+        let iter = self.parse_exif(reader, format)?;
+        Ok(iter.map(|iter| Exif::from_iter_filtered(iter, tags)))
+    }
+
+    /// Like [`parse_metadata_iter`](crate::parse_metadata_iter), with the
+    /// limits of this parser.
+    pub fn parse_metadata_iter<R: Read + Seek>(&self, reader: R) -> crate::Result<MetadataIter> {
+        read_metadata_iter(reader, self)
+    }
+
+    /// Like [`Self::parse_metadata_iter`], but for readers which can't seek,
+    /// e.g. network streams: the data which precedes the metadata (usually
+    /// the `mdat` atom) is read and discarded instead.
+    ///
+    /// Skips larger than
+    /// [`MediaParserBuilder::max_discarded_bytes`] fail with
+    /// [`Error::SkipRequired`](crate::Error::SkipRequired), which tells where
+    /// parsing can be resumed with [`Self::resume_metadata_unseekable`], after
+    /// seeking out-of-band.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    /// use std::io::{Seek, SeekFrom};
+    ///
+    /// let parser = MediaParser::builder().max_discarded_bytes(1024).build();
+    /// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    ///
+    /// let iter = match parser.parse_metadata_unseekable(f) {
+    ///     Err(Error::SkipRequired { format, offset, remaining }) => {
+    ///         // E.g. send an HTTP range request, here seek the file
+    ///         let mut f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    ///         f.seek(SeekFrom::Start(offset + remaining)).unwrap();
+    ///         parser.resume_metadata_unseekable(f, format, offset + remaining)
+    ///     }
+    ///     res => res,
+    /// };
+    /// let entries = iter.unwrap().collect::<Vec<_>>();
+    /// assert_eq!(entries[0].0, "com.apple.quicktime.make");
+    /// assert_eq!(entries[0].1.as_ref().unwrap().to_string(), "Apple");
+    /// ```
+    pub fn parse_metadata_unseekable<R: Read>(&self, reader: R) -> crate::Result<MetadataIter> {
+        read_metadata_unseekable(reader, self, None)
+    }
+
+    /// Resumes [`Self::parse_metadata_unseekable`] after an
+    /// [`Error::SkipRequired`](crate::Error::SkipRequired): `reader` starts
+    /// at `offset` of a file of the given `format`, which is usually
+    /// `offset + remaining` from the error.
+    pub fn resume_metadata_unseekable<R: Read>(
+        &self,
+        reader: R,
+        format: FileFormat,
+        offset: u64,
+    ) -> crate::Result<MetadataIter> {
+        read_metadata_unseekable(reader, self, Some((format, offset)))
+    }
+
+    /// Like [`parse_exif`](crate::parse_exif), but corruption in the middle
+    /// of the Exif data doesn't abort the parse: the entries which have
+    /// been parsed are returned along with [`Warning`]s describing what has
+    /// been skipped, e.g. entries with invalid values, IFDs which can't be
+    /// parsed, or the end of a truncated JPEG file.
+    ///
+    /// An `Err` is still returned if nothing could be parsed, e.g. the file
+    /// format isn't supported, or the Exif header is corrupt.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// // The file is cut off in the middle of its Exif data
+    /// let mut data = std::fs::read("./testdata/exif.jpg").unwrap();
+    /// data.truncate(0x400);
+    ///
+    /// assert!(parse_exif(&data[..], None).is_err());
+    ///
+    /// let parser = MediaParser::default();
+    /// let (exif, warnings) = parser.parse_exif_lenient(&data[..], None).unwrap().unwrap();
+    /// assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "vivo");
+    /// assert!(matches!(warnings[0], Warning::Truncated { .. }));
+    /// ```
+    pub fn parse_exif_lenient<R: Read>(
+        &self,
+        reader: R,
+        format: Option<FileFormat>,
+    ) -> crate::Result<Option<(Exif, Vec<Warning>)>> {
+        let mut warnings = Vec::new();
+        let Some(input) = read_exif_lenient(reader, format, self, &mut warnings)? else {
+            return Ok(None);
+        };
+        let (exif, skipped) = Exif::from_iter_lenient(self.input_to_iter(input)?);
+        warnings.extend(skipped);
+        Ok(Some((exif, warnings)))
+    }
+
+    /// Like [`parse_metadata`](crate::parse_metadata), but instead of being
+    /// dropped silently, the entries and atoms which can't be parsed are
+    /// reported as [`Warning`]s. A corrupt `moov/meta` atom doesn't abort
+    /// the parse either, the entries derived from other atoms (e.g.
+    /// `duration`) are still returned.
+    pub fn parse_metadata_lenient<R: Read + Seek>(
+        &self,
+        reader: R,
+    ) -> crate::Result<(MetadataEntries, Vec<Warning>)> {
+        read_metadata_lenient(reader, self)
+    }
+
+    /// Like [`parse_media`], with the limits of this parser.
+    #[tracing::instrument(skip_all)]
+    pub fn parse_media<R: Read + Seek>(&self, reader: R) -> crate::Result<Option<MediaInfo>> {
+        let (ff, reader) = FileFormat::sniff(reader)?;
+
+        match ff {
+            FileFormat::Jpeg | FileFormat::Heif => {
+                Ok(self.parse_exif_seekable(reader, ff)?.map(MediaInfo::Exif))
+            }
+            FileFormat::QuickTime | FileFormat::MP4 | FileFormat::Matroska => Ok(Some(
+                MediaInfo::Track(self.parse_track_seekable(reader, ff)?),
+            )),
+        }
+    }
+
+    /// Like [`parse_media_mmap`], with the limits of this parser.
+    ///
+    /// # Safety
+    ///
+    /// See [`parse_media_mmap`].
+    #[cfg(feature = "mmap")]
+    #[tracing::instrument(skip_all)]
+    pub unsafe fn parse_media_mmap<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> crate::Result<Option<MediaInfo>> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: guaranteed by the caller
+        let data = unsafe { memmap2::Mmap::map(&file)? };
+        let ff = FileFormat::try_from(&data[..])?;
+
+        match ff {
+            FileFormat::Jpeg | FileFormat::Heif => Ok(exif_range(&data, Some(ff))?
+                .map(|range| self.input_to_iter(Input::from_mmap_range(data, range)))
+                .transpose()?
+                .map(MediaInfo::Exif)),
+            FileFormat::QuickTime | FileFormat::MP4 => {
+                let range = match extract_moov_body_from_buf(&data, None) {
+                    Ok(range) => range,
+                    // The moov atom is missing, or incomplete
+                    Err(mov::Error::Need(n)) => Err(crate::Error::Truncated { needed: Some(n) })?,
+                    Err(mov::Error::Skip(n)) => Err(crate::Error::Truncated {
+                        needed: Some(usize::try_from(n).unwrap_or(usize::MAX)),
+                    })?,
+                    Err(mov::Error::ParseFailed(e)) => return Err(e),
+                    Err(mov::Error::LargeMoov { .. }) => unreachable!("no maximum moov size"),
+                };
+                self.check_metadata_size(range.len())?;
+                let input = Input::from_mmap_range(data, range);
+                Ok(Some(MediaInfo::Track(new_metadata_iter(
+                    ff, input, 0, self,
+                )?)))
+            }
+            FileFormat::Matroska => Ok(Some(MediaInfo::Track(crate::mkv::read_metadata_iter(
+                std::io::Cursor::new(&data[..]),
+                self,
+            )?))),
+        }
+    }
+
+    /// Returns a summary of the media in `reader`: its format, dimensions,
+    /// duration, capture time and whether it has GPS info, e.g. for
+    /// directory listings which don't need all the tags.
+    ///
+    /// Only the Exif data of images, and the `moov` atom of videos, are
+    /// read; images without Exif data (or without size tags) have no
+    /// dimensions.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let parser = MediaParser::default();
+    ///
+    /// let summary = parser.probe(std::fs::File::open("./testdata/exif.jpg").unwrap()).unwrap();
+    /// assert_eq!(summary.format, FileFormat::Jpeg);
+    /// assert_eq!(summary.dimensions, Some((3072, 4096)));
+    /// assert!(summary.has_gps);
+    ///
+    /// let summary = parser.probe(std::fs::File::open("./testdata/meta.mov").unwrap()).unwrap();
+    /// assert_eq!(summary.dimensions, Some((720, 1280)));
+    /// assert_eq!(summary.duration.unwrap().num_milliseconds(), 500);
+    /// assert_eq!(
+    ///     summary.capture_time.unwrap().to_rfc3339(),
+    ///     "2019-02-12T15:27:12+08:00"
+    /// );
+    /// ```
+    pub fn probe<R: Read + Seek>(&self, reader: R) -> crate::Result<MediaSummary> {
+        let (format, reader) = FileFormat::sniff(reader)?;
+        let info = match format {
+            FileFormat::Jpeg | FileFormat::Heif => self
+                .parse_exif_seekable(reader, format)?
+                .map(MediaInfo::Exif),
+            FileFormat::QuickTime | FileFormat::MP4 | FileFormat::Matroska => {
+                Some(MediaInfo::Track(self.parse_track_seekable(reader, format)?))
+            }
+        };
+        Ok(self.summarize(format, info))
+    }
+
+    /// `async` version of [`Self::probe`].
+    #[cfg(feature = "async")]
+    pub async fn probe_async<R>(&self, reader: R) -> crate::Result<MediaSummary>
+    where
+        R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+    {
+        let (format, info) = self.parse_media_source(TokioSource(reader)).await?;
+        Ok(self.summarize(format, info))
+    }
+
+    /// Like [`capabilities`](crate::capabilities), with the limits of this
+    /// parser.
+    pub fn capabilities<R: Read + Seek>(&self, reader: R) -> crate::Result<Capabilities> {
+        read_capabilities(reader, self)
+    }
+
+    /// Like [`best_capture_time`](crate::best_capture_time), with the limits
+    /// of this parser.
+    pub fn best_capture_time<R: Read + Seek>(
+        &self,
+        reader: R,
+    ) -> crate::Result<Option<CaptureTimeInfo>> {
+        read_capture_time(reader, self)
+    }
+
+    /// Like [`visit_exif`](crate::visit_exif), with the limits of this
+    /// parser.
+    pub fn visit_exif<R, F>(
+        &self,
+        reader: R,
+        format: Option<FileFormat>,
+        tags: &[ExifTag],
+        visitor: F,
+    ) -> crate::Result<()>
+    where
+        R: Read,
+        F: FnMut(&ParsedExifEntry) -> ControlFlow<()>,
+    {
+        read_visit_exif(reader, format, self, tags, visitor)
+    }
+
+    /// Like [`visit_metadata`](crate::visit_metadata), with the limits of
+    /// this parser.
+    pub fn visit_metadata<R, F>(&self, reader: R, keys: &[&str], visitor: F) -> crate::Result<()>
+    where
+        R: Read + Seek,
+        F: FnMut(&str, crate::Result<EntryValue>) -> ControlFlow<()>,
+    {
+        read_visit_metadata(reader, self, keys, visitor)
+    }
+
+    /// Returns the recoverable anomalies which have been found (and worked
+    /// around) since the last call, e.g. IFD entry counts which are larger
+    /// than the data, offsets which are out of range, or non-ASCII
+    /// characters in ASCII entries. Unlike the warnings of
+    /// [`Self::parse_exif_lenient`], these don't cause anything to be
+    /// skipped, so they're collected in strict mode as well.
+    ///
+    /// Since entries are parsed lazily, the anomalies of an [`ExifIter`]
+    /// are found while iterating over it, so call this method after the
+    /// iteration is done.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let parser = MediaParser::default();
+    /// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let exif: Exif = parser.parse_exif(f, None).unwrap().unwrap().into();
+    /// assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "vivo");
+    /// assert_eq!(parser.take_warnings(), []);
+    /// ```
+    pub fn take_warnings(&self) -> Vec<Warning> {
+        self.diagnostics.take()
+    }
+
+    /// Returns the statistics of the reads done since the last call, e.g.
+    /// the number of bytes read, or how many buffers have been reused.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let parser = MediaParser::builder().buffer_pool_size(1).build();
+    /// for _ in 0..2 {
+    ///     let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    ///     let _: Exif = parser.parse_exif(f, None).unwrap().unwrap().into();
+    /// }
+    ///
+    /// let stats = parser.take_stats();
+    /// assert_eq!(stats.files, 2);
+    /// assert_eq!(stats.pooled_buffers, 1);
+    /// assert!(stats.bytes_read > 0);
+    /// assert_eq!(parser.take_stats().files, 0);
+    /// ```
+    pub fn take_stats(&self) -> ParseStats {
+        self.stats.take()
+    }
+
+    /// Like [`Self::parse_exif`], for a reader which can seek to skip the
+    /// JPEG segments which precede the Exif segment (see
+    /// [`read_exif_seekable`]).
+    fn parse_exif_seekable<R: Read + Seek>(
+        &self,
+        reader: R,
+        format: FileFormat,
+    ) -> crate::Result<Option<ExifIter<'static>>> {
+        if format != FileFormat::Jpeg {
+            return self.parse_exif(reader, Some(format));
+        }
+        let Some((input, offset)) = read_exif_seekable(reader, self)? else {
+            return Ok(None);
+        };
+        let mut iter = self.input_to_iter(input)?;
+        iter.set_file_offset(offset);
+        Ok(Some(iter))
+    }
+
+    /// Parses the metadata of a video, whose format is `format`: the `moov`
+    /// atom of QuickTime/MP4 files, or the tags and tracks of Matroska files.
+    pub(crate) fn parse_track_seekable<R: Read + Seek>(
+        &self,
+        reader: R,
+        format: FileFormat,
+    ) -> crate::Result<MetadataIter> {
+        match format {
+            FileFormat::Matroska => crate::mkv::read_metadata_iter(reader, self),
+            _ => self.parse_metadata_iter(reader),
+        }
+    }
+
+    pub(crate) fn input_to_iter(&self, input: Input<'static>) -> crate::Result<ExifIter<'static>> {
+        let mut iter = input_to_iter_with_diagnostics(input, self.diagnostics.clone())?;
+        iter.set_limits(ExifLimits {
+            max_ifd_depth: self.max_ifd_depth,
+            max_entries: self.max_entries.unwrap_or(usize::MAX),
+            max_string_length: self.max_string_length.unwrap_or(usize::MAX),
+            cancellation: self.cancellation.clone(),
+        });
+        Ok(iter)
+    }
+
+    /// Returns an empty buffer to read `size` bytes into, which is taken
+    /// from the buffer pool if there is one.
+    pub(crate) fn alloc_buffer(&self, size: usize) -> Vec<u8> {
+        let pooled = self.buffer_pool.as_ref().and_then(|x| x.take(size));
+        self.stats.start(pooled.is_some());
+        self.emit(|| ParseEvent::Started);
+        pooled.unwrap_or_else(|| Vec::with_capacity(size))
+    }
+
+    /// Reserves room for `size` more bytes in `buf`, counting the
+    /// reallocations.
+    pub(crate) fn reserve_buffer(&self, buf: &mut Vec<u8>, size: usize) {
+        let capacity = buf.capacity();
+        buf.reserve(size);
+        if buf.capacity() != capacity {
+            self.stats.reallocation();
+        }
+    }
+
+    /// Wraps a buffer returned by [`Self::alloc_buffer`], so that it goes
+    /// back to the buffer pool (if any) once it's dropped.
+    pub(crate) fn buffer_to_input(&self, buf: Vec<u8>, range: Range<usize>) -> Input<'static> {
+        match &self.buffer_pool {
+            Some(pool) => Input::from_pooled_range(buf, range, pool),
+            None => Input::from_vec_range(buf, range),
+        }
+    }
+
+    /// Returns the number of bytes to read first.
+    pub(crate) fn init_read_size(&self) -> usize {
+        match self.max_metadata_size {
+            Some(max) => self.buffer_capacity.min(max),
+            None => self.buffer_capacity,
+        }
+    }
+
+    /// Returns the number of bytes to read next, when `needed` more bytes
+    /// are needed and `buffered` bytes have been read, or an error if the
+    /// maximum metadata size has been reached.
+    pub(crate) fn grow_size(&self, buffered: usize, needed: usize) -> crate::Result<usize> {
+        self.check_cancelled()?;
+        let size = needed.max(MIN_GROW_SIZE).min(self.max_buffer_growth);
+        match self.max_metadata_size {
+            Some(max) if buffered >= max => {
+                let limit = Limit::MetadataSize(max);
+                Err(crate::Error::LimitExceeded { limit })
+            }
+            Some(max) => Ok(size.min(max - buffered)),
+            None => Ok(size),
+        }
+    }
+
+    /// Returns an error if metadata of `size` bytes is larger than the
+    /// maximum metadata size.
+    pub(crate) fn check_metadata_size(&self, size: usize) -> crate::Result<()> {
+        self.check_cancelled()?;
+        match self.max_metadata_size {
+            Some(max) if size > max => {
+                let limit = Limit::MetadataSize(max);
+                Err(crate::Error::LimitExceeded { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if `skipped` bytes of a file are more than the
+    /// maximum which may be skipped.
+    pub(crate) fn check_skipped(&self, skipped: u64) -> crate::Result<()> {
+        self.check_cancelled()?;
+        match self.max_skipped_bytes {
+            Some(max) if skipped > max => {
+                let limit = Limit::SkippedBytes(max);
+                Err(crate::Error::LimitExceeded { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns [`Error::LimitExceeded`](crate::Error::LimitExceeded) if a
+    /// string of `len` bytes is longer than allowed.
+    pub(crate) fn check_string_length(&self, len: usize) -> crate::Result<()> {
+        match self.max_string_length {
+            Some(max) if len > max => {
+                let limit = Limit::StringLength(max);
+                Err(crate::Error::LimitExceeded { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns [`Error::LimitExceeded`](crate::Error::LimitExceeded) if
+    /// `depth` levels of boxes or EBML elements are more than allowed.
+    pub(crate) fn check_nesting_depth(&self, depth: usize) -> crate::Result<()> {
+        if depth > self.max_nesting_depth {
+            let limit = Limit::NestingDepth(self.max_nesting_depth);
+            return Err(crate::Error::LimitExceeded { limit });
+        }
+        Ok(())
+    }
+
+    /// Returns the [`MediaSummary`] of `info`, the metadata of a `format`
+    /// file, with its place found by the geocoder of this parser, if any.
+    pub(crate) fn summarize(&self, format: FileFormat, info: Option<MediaInfo>) -> MediaSummary {
+        let geocoder = self.geocoder.as_ref().map(|x| &*x.0);
+        MediaSummary::new(format, info, geocoder)
+    }
+
+    /// Passes an event to the callback set with
+    /// [`MediaParserBuilder::on_event`], if any. `event` is only called when
+    /// there is one.
+    pub(crate) fn emit(&self, event: impl FnOnce() -> ParseEvent) {
+        self.diagnostics.emit(event);
+    }
+
+    /// Returns [`Error::Cancelled`](crate::Error::Cancelled) if the deadline
+    /// has passed, or if the cancel check returns `true`. Called before the
+    /// reads and skips which follow the first read.
+    pub(crate) fn check_cancelled(&self) -> crate::Result<()> {
+        match self.cancellation.cancelled() {
+            Some(timed_out) => Err(crate::Error::Cancelled { timed_out }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Builder of a [`MediaParser`].
+#[derive(Debug, Clone, Default)]
+pub struct MediaParserBuilder {
+    parser: MediaParser,
+}
+
+impl MediaParserBuilder {
+    /// Sets the number of bytes read at first, which is enough to find the
+    /// metadata of most files. Default: 4 KiB, minimum: 512 bytes.
+    pub fn buffer_capacity(&mut self, size: usize) -> &mut Self {
+        self.parser.buffer_capacity = size.max(MIN_BUFFER_CAPACITY);
+        self
+    }
+
+    /// Sets the maximum number of bytes read at once when the buffer grows,
+    /// e.g. when a large Exif segment needs to be read. Default: 4000 KiB.
+    pub fn max_buffer_growth(&mut self, size: usize) -> &mut Self {
+        self.parser.max_buffer_growth = size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of bytes which are buffered to find and read
+    /// the metadata (the Exif data with everything before it, or the `moov`
+    /// atom of videos), larger metadata fails with
+    /// [`Error::LimitExceeded`](crate::Error::LimitExceeded). Default: no
+    /// limit, minimum: 512 bytes.
+    pub fn max_metadata_size(&mut self, size: usize) -> &mut Self {
+        self.parser.max_metadata_size = Some(size.max(MIN_BUFFER_CAPACITY));
+        self
+    }
+
+    /// Sets how deeply nested IFDs (e.g. Exif IFD → Interop IFD) are
+    /// followed, deeper IFDs are skipped. Default: 8.
+    pub fn max_ifd_depth(&mut self, depth: usize) -> &mut Self {
+        self.parser.max_ifd_depth = depth.max(1);
+        self
+    }
+
+    /// Sets the maximum number of entries: Exif entries (in all IFDs), or
+    /// QuickTime metadata items. The entry after the last allowed one fails
+    /// with [`Error::LimitExceeded`](crate::Error::LimitExceeded), and the
+    /// iteration stops. Default: no limit.
+    pub fn max_entries(&mut self, count: usize) -> &mut Self {
+        self.parser.max_entries = Some(count);
+        self
+    }
+
+    /// Sets the maximum length of string values (Exif ASCII values including
+    /// the terminating NUL, QuickTime text items, and Matroska tag names and
+    /// values) in bytes, longer values fail with
+    /// [`Error::LimitExceeded`](crate::Error::LimitExceeded). Default: no
+    /// limit.
+    pub fn max_string_length(&mut self, length: usize) -> &mut Self {
+        self.parser.max_string_length = Some(length);
+        self
+    }
+
+    /// Sets the maximum number of bytes which are skipped (by seeking) to
+    /// find the metadata of videos, e.g. the `mdat` atom which precedes the
+    /// `moov` atom. Files which need to skip more fail with
+    /// [`Error::LimitExceeded`](crate::Error::LimitExceeded). Default: no
+    /// limit.
+    pub fn max_skipped_bytes(&mut self, size: u64) -> &mut Self {
+        self.parser.max_skipped_bytes = Some(size);
+        self
+    }
+
+    /// Sets how deeply boxes (of QuickTime/MP4 files) and EBML elements (of
+    /// Matroska files, e.g. nested `SimpleTag`s) may be nested, deeper ones
+    /// fail with [`Error::LimitExceeded`](crate::Error::LimitExceeded).
+    /// Default: 32, minimum: 8.
+    pub fn max_nesting_depth(&mut self, depth: usize) -> &mut Self {
+        self.parser.max_nesting_depth = depth.max(MIN_NESTING_DEPTH);
+        self
+    }
+
+    /// Sets the maximum number of bytes which are read and discarded at
+    /// once, when a reader can't seek (see
+    /// [`MediaParser::parse_metadata_unseekable`]). Larger skips fail with
+    /// [`Error::SkipRequired`](crate::Error::SkipRequired). Default: no
+    /// limit.
+    pub fn max_discarded_bytes(&mut self, size: u64) -> &mut Self {
+        self.parser.max_discarded_bytes = Some(size);
+        self
+    }
+
+    /// Sets the maximum size of a `moov` atom which is buffered as a whole.
+    /// Larger ones (e.g. the 100+ MB `moov` atoms of some screen recordings)
+    /// are read atom by atom, seeking over the atoms which aren't parsed,
+    /// so that the buffer stays small: atoms larger than `size` are skipped,
+    /// except the sample tables which give the frame rate and the bitrate,
+    /// which are summarized as they're read. Default: no limit.
+    ///
+    /// Only applies to readers which can seek.
+    pub fn max_moov_size(&mut self, size: u64) -> &mut Self {
+        self.parser.max_moov_size = Some(size);
+        self
+    }
+
+    /// Keeps up to `count` buffers of dropped results (e.g. [`ExifIter`]s),
+    /// and reuses them to read the next files instead of allocating new
+    /// ones. The parser is `Send + Sync`, so the pool is shared by all the
+    /// threads parsing with a `&MediaParser`, e.g. rayon or tokio workers.
+    /// Buffers larger than 4 MiB are freed. Default: 0 (no pool).
+    pub fn buffer_pool_size(&mut self, count: usize) -> &mut Self {
+        self.parser.buffer_pool = (count > 0).then(|| BufferPool::new(count));
+        self
+    }
+
+    /// Sets when parsing gives up: the parser checks the deadline between
+    /// its reads and skips, and fails with
+    /// [`Error::Cancelled`](crate::Error::Cancelled) once it has passed, so
+    /// that a pathological file can't stall a request handler. The returned
+    /// [`ExifIter`]s check it before each entry too, and end with an entry
+    /// holding the error. The deadline applies to every parse, build a
+    /// parser per request (the builder can be kept and cloned). Default:
+    /// none.
+    ///
+    /// A read which never returns can't be interrupted: with the `async`
+    /// API, drop the future instead, e.g. with `tokio::time::timeout`.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let parser = MediaParser::builder()
+    ///     .deadline(Instant::now() + Duration::from_secs(5))
+    ///     .build();
+    /// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    /// assert!(parser.parse_metadata_iter(f).is_ok());
+    ///
+    /// let parser = MediaParser::builder().deadline(Instant::now()).build();
+    /// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    /// let err = parser.parse_metadata_iter(f).unwrap_err();
+    /// assert!(matches!(err, Error::Cancelled { timed_out: true }));
+    /// ```
+    pub fn deadline(&mut self, deadline: Instant) -> &mut Self {
+        self.parser.cancellation.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets a callback which is called between the reads and skips of the
+    /// parser, and before each entry of the returned [`ExifIter`]s, parsing
+    /// fails with [`Error::Cancelled`](crate::Error::Cancelled) once it
+    /// returns `true`, e.g. when the client of a request handler has
+    /// disconnected. Default: none.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    /// use std::sync::{
+    ///     atomic::{AtomicBool, Ordering},
+    ///     Arc,
+    /// };
+    ///
+    /// let cancelled = Arc::new(AtomicBool::new(false));
+    /// let flag = cancelled.clone();
+    /// let parser = MediaParser::builder()
+    ///     .cancel_check(move || flag.load(Ordering::Relaxed))
+    ///     .build();
+    ///
+    /// cancelled.store(true, Ordering::Relaxed);
+    /// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    /// let err = parser.parse_metadata_iter(f).unwrap_err();
+    /// assert!(matches!(err, Error::Cancelled { timed_out: false }));
+    /// ```
+    pub fn cancel_check<F>(&mut self, check: F) -> &mut Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.parser.cancellation.check = Some(Arc::new(check));
+        self
+    }
+
+    /// Sets a [`Geocoder`], which [`MediaParser::probe`] calls with the GPS
+    /// position of the media, to fill in [`MediaSummary::place`]. Default:
+    /// none.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let parser = MediaParser::builder()
+    ///     .geocoder(|gps: &GPSInfo| {
+    ///         let (lat, lon, _) = gps.to_decimal();
+    ///         // Look up the place, e.g. in an offline database
+    ///         ((lat - 22.53).abs() < 0.1 && (lon - 114.02).abs() < 0.1).then(|| Place {
+    ///             locality: Some("Shenzhen".into()),
+    ///             country_code: Some("CN".into()),
+    ///             ..Default::default()
+    ///         })
+    ///     })
+    ///     .build();
+    ///
+    /// let summary = parser.probe(std::fs::File::open("./testdata/exif.jpg").unwrap()).unwrap();
+    /// assert_eq!(summary.place.unwrap().locality.unwrap(), "Shenzhen");
+    /// ```
+    pub fn geocoder(&mut self, geocoder: impl Geocoder + 'static) -> &mut Self {
+        self.parser.geocoder = Some(GeocoderHook(Arc::new(geocoder)));
+        self
+    }
+
+    /// Sets a callback which is called with the [`ParseEvent`]s of the
+    /// parser: when a file starts to be parsed, when a box or a segment is
+    /// visited or skipped, and when a fallback is used, along with the
+    /// [`Warning`]. It's called from the thread which parses, while parsing,
+    /// so it should be quick. Default: none.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let events = Arc::new(Mutex::new(Vec::new()));
+    /// let log = events.clone();
+    /// let parser = MediaParser::builder()
+    ///     .on_event(move |event| log.lock().unwrap().push(event.clone()))
+    ///     .build();
+    ///
+    /// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    /// parser.parse_metadata_iter(f).unwrap();
+    ///
+    /// let events = events.lock().unwrap();
+    /// assert_eq!(events[0], ParseEvent::Started);
+    /// assert!(events.iter().any(|x| matches!(
+    ///     x,
+    ///     ParseEvent::BoxVisited { box_type, .. } if box_type == "moov"
+    /// )));
+    /// ```
+    pub fn on_event<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&ParseEvent) + Send + Sync + 'static,
+    {
+        self.parser.diagnostics = Diagnostics::with_events(EventSink::new(callback));
+        self
+    }
+
+    /// Creates the parser.
+    pub fn build(&self) -> MediaParser {
+        self.parser.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, parse_metadata, parse_metadata_iter, testkit::*, ExifTag, IfdKind};
+    use test_case::test_case;
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn media_exif(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let info = parse_media(open_sample(path).unwrap()).unwrap().unwrap();
+        let iter = info.clone().into_exif().unwrap();
+        assert!(info.into_track().is_none());
+
+        let expected = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            iter.map(|x| (x.ifd_index(), x.tag_code(), x.take_value()))
+                .collect::<Vec<_>>(),
+            expected
+                .map(|x| (x.ifd_index(), x.tag_code(), x.take_value()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn media_track(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let info = parse_media(open_sample(path).unwrap()).unwrap().unwrap();
+        let iter = info.into_track().unwrap();
+        assert_eq!(
+            iter.map(|(k, v)| (k, v.unwrap())).collect::<Vec<_>>(),
+            parse_metadata(open_sample(path).unwrap()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    #[test_case("no-exif.jpg")]
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn media_mmap(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let entries = |info: Option<MediaInfo>| match info {
+            Some(MediaInfo::Exif(iter)) => format!("{:?}", Exif::from(iter).entries()),
+            Some(MediaInfo::Track(iter)) => {
+                let tracks = iter.tracks();
+                format!("{:?} {tracks:?}", iter.collect::<Vec<_>>())
+            }
+            None => String::new(),
+        };
+        // SAFETY: the test data isn't modified
+        let info = unsafe { parse_media_mmap(format!("./testdata/{path}")) }.unwrap();
+        let expected = parse_media(open_sample(path).unwrap()).unwrap();
+        assert_eq!(entries(info), entries(expected));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn media_mmap_error() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // SAFETY: the test data isn't modified
+        unsafe {
+            parse_media_mmap("./testdata/missing.jpg").unwrap_err();
+            parse_media_mmap("./testdata/exif.png").unwrap_err();
+        }
+
+        // Truncated in the middle of the moov atom
+        let data = read_sample("meta.mov").unwrap();
+        let path = std::env::temp_dir().join("nom-exif-media-mmap-error.mov");
+        std::fs::write(&path, &data[..data.len() - 100]).unwrap();
+        // SAFETY: the file isn't modified while it's mapped
+        let res = unsafe { parse_media_mmap(&path) };
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(res, Err(crate::Error::Truncated { .. })));
+    }
+
+    #[test_case("exif.jpg", Some((3072, 4096)), None, true)]
+    #[test_case("exif.heic", Some((4032, 3024)), None, true)]
+    #[test_case("no-exif.jpg", None, None, false)]
+    #[test_case("meta.mov", Some((720, 1280)), Some(500), true)]
+    #[test_case("meta.mp4", Some((1920, 1080)), Some(1063), true)]
+    fn probe(path: &str, dimensions: Option<(u32, u32)>, ms: Option<i64>, has_gps: bool) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let summary = MediaParser::default()
+            .probe(open_sample(path).unwrap())
+            .unwrap();
+        assert_eq!(summary.dimensions, dimensions);
+        assert_eq!(summary.duration.map(|x| x.num_milliseconds()), ms);
+        assert_eq!(summary.has_gps, has_gps);
+        assert_eq!(summary.capture_time.is_some(), path != "no-exif.jpg");
+    }
+
+    #[test_case("exif.jpg", Some("+22.53113+114.02148/"))]
+    #[test_case("meta.mov", Some("+27.12810+100.25080/"))]
+    #[test_case("no-exif.jpg", None)]
+    fn probe_geocoder(path: &str, name: Option<&str>) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::builder()
+            .geocoder(|gps: &GPSInfo| {
+                Some(Place {
+                    name: Some(gps.format_iso6709()),
+                    ..Default::default()
+                })
+            })
+            .build();
+        let summary = parser.clone().probe(open_sample(path).unwrap()).unwrap();
+        assert_eq!(summary.place.and_then(|x| x.name).as_deref(), name);
+
+        let summary = MediaParser::default()
+            .probe(open_sample(path).unwrap())
+            .unwrap();
+        assert_eq!(summary.place, None);
+    }
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn parser_limits(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let entries = |parser: MediaParser| match parser
+            .parse_media(open_sample(path).unwrap())
+            .unwrap()
+            .unwrap()
+        {
+            MediaInfo::Exif(iter) => iter
+                .map(|x| (x.tag_code(), x.take_value().map(|v| v.to_string())))
+                .collect::<Vec<_>>(),
+            MediaInfo::Track(iter) => iter
+                .map(|(_, v)| (0, v.ok().map(|v| v.to_string())))
+                .collect::<Vec<_>>(),
+        };
+        let expected = entries(MediaParser::default());
+        assert!(!expected.is_empty());
+
+        // Tiny reads give the same result
+        let parser = MediaParser::builder()
+            .buffer_capacity(1)
+            .max_buffer_growth(1)
+            .build();
+        assert_eq!(entries(parser), expected);
+
+        let size = read_sample(path).unwrap().len();
+        let parser = MediaParser::builder().max_metadata_size(size).build();
+        assert_eq!(entries(parser), expected);
+
+        let parser = MediaParser::builder().max_metadata_size(1024).build();
+        let err = parser.parse_media(open_sample(path).unwrap()).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("limit exceeded; metadata is larger than 1024 bytes"));
+    }
+
+    #[test]
+    fn parser_ifd_depth() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let kinds = |parser: MediaParser| {
+            let iter = parser
+                .parse_exif(open_sample("exif.jpg").unwrap(), None)
+                .unwrap()
+                .unwrap();
+            iter.clone()
+                .chain(iter)
+                .map(|x| x.ifd_kind())
+                .collect::<Vec<_>>()
+        };
+        let kinds_all = kinds(MediaParser::default());
+        assert!(kinds_all.contains(&crate::IfdKind::Exif));
+
+        let kinds = kinds(MediaParser::builder().max_ifd_depth(1).build());
+        assert!(!kinds.contains(&crate::IfdKind::Exif));
+        assert!(kinds.contains(&crate::IfdKind::Ifd0));
+        assert!(kinds.contains(&crate::IfdKind::Ifd1));
+    }
+
+    #[test]
+    fn parser_exif_limits() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parse = |parser: MediaParser| {
+            parser
+                .parse_exif(open_sample("exif.jpg").unwrap(), None)
+                .unwrap()
+                .unwrap()
+                .map(|x| (x.tag(), x.take_result()))
+                .collect::<Vec<_>>()
+        };
+
+        let results = parse(MediaParser::builder().max_entries(5).build());
+        assert_eq!(results.len(), 6);
+        assert!(results[..5].iter().all(|(_, v)| v.is_ok()));
+        assert_eq!(
+            results[5].1.as_ref().unwrap_err().to_string(),
+            "limit exceeded; more than 5 entries"
+        );
+
+        let results = parse(MediaParser::builder().max_string_length(5).build());
+        let get = |tag| &results.iter().find(|x| x.0 == Some(tag)).unwrap().1;
+        // "vivo\0"
+        assert_eq!(get(ExifTag::Make).as_ref().unwrap().as_str(), Some("vivo"));
+        // "vivo X90 Pro+\0"
+        assert!(matches!(
+            get(ExifTag::Model),
+            Err(crate::Error::LimitExceeded {
+                limit: Limit::StringLength(5)
+            })
+        ));
+    }
+
+    #[test_case("meta.mov")]
+    fn parser_track_limits(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::builder().max_entries(2).build();
+        let entries = parser
+            .parse_metadata_iter(open_sample(path).unwrap())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 3);
+        assert!(entries[..2].iter().all(|(_, v)| v.is_ok()));
+        assert!(matches!(
+            entries[2].1,
+            Err(crate::Error::LimitExceeded {
+                limit: Limit::Entries(2)
+            })
+        ));
+
+        let parser = MediaParser::builder().max_string_length(5).build();
+        let entries = parser
+            .parse_metadata_iter(open_sample(path).unwrap())
+            .unwrap()
+            .collect::<Vec<_>>();
+        let (key, value) = entries
+            .iter()
+            .find(|(k, _)| k == "com.apple.quicktime.model")
+            .unwrap();
+        assert!(
+            matches!(value, Err(crate::Error::LimitExceeded { .. })),
+            "{key}"
+        );
+    }
+
+    #[test]
+    fn parser_skipped_bytes() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // The `mdat` atoms of both files precede the `moov` atom
+        for path in ["meta.mov", "meta.mp4"] {
+            let parser = MediaParser::builder().max_skipped_bytes(0).build();
+            let err = parser
+                .parse_metadata_iter(open_sample(path).unwrap())
+                .unwrap_err();
+            assert_eq!(err.to_string(), "limit exceeded; more than 0 bytes skipped");
+
+            let size = read_sample(path).unwrap().len() as u64;
+            let parser = MediaParser::builder().max_skipped_bytes(size).build();
+            let iter = parser
+                .parse_metadata_iter(open_sample(path).unwrap())
+                .unwrap();
+            assert_eq!(
+                iter.count(),
+                parse_metadata(open_sample(path).unwrap()).unwrap().len()
+            );
+        }
+    }
+
+    #[cfg(all(feature = "async", feature = "stream"))]
+    #[test_case(0)]
+    #[test_case(1)]
+    #[test_case(3)]
+    fn parse_many(concurrency: usize) {
+        use futures_util::stream;
+
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let paths = [
+            "exif.jpg",
+            "meta.mov",
+            "no-exif.jpg",
+            "exif.heic",
+            "meta.mp4",
+        ];
+        let files = || {
+            stream::iter(paths).then(|p| async move {
+                tokio::fs::File::open(format!("./testdata/{p}"))
+                    .await
+                    .unwrap()
+            })
+        };
+        let kind = |res: &crate::Result<Option<MediaInfo>>| match res {
+            Ok(Some(MediaInfo::Exif(_))) => "exif",
+            Ok(Some(MediaInfo::Track(_))) => "track",
+            Ok(None) => "none",
+            Err(_) => "error",
+        };
+        let expected = ["exif", "track", "none", "exif", "track"];
+
+        let parser = MediaParser::builder().buffer_pool_size(2).build();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let results = runtime.block_on(parser.parse_many(files(), concurrency).collect::<Vec<_>>());
+        assert_eq!(results.iter().map(kind).collect::<Vec<_>>(), expected);
+        assert_eq!(parser.take_stats().files, paths.len() as u64);
+
+        let mut results = runtime.block_on(
+            parser
+                .parse_many_unordered(files(), concurrency)
+                .collect::<Vec<_>>(),
+        );
+        results.sort_by_key(|(i, _)| *i);
+        assert_eq!(
+            results.iter().map(|(_, x)| kind(x)).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            results.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+    }
+
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn parser_cancellation(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // Cancels at the `n`th check
+        let cancelling = |n: usize| {
+            let checks = std::sync::atomic::AtomicUsize::new(0);
+            MediaParser::builder()
+                .buffer_capacity(512)
+                .max_buffer_growth(512)
+                .cancel_check(move || {
+                    checks.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1 == n
+                })
+                .build()
+        };
+
+        let entries = |info: Option<MediaInfo>| match info.unwrap() {
+            MediaInfo::Exif(iter) => format!("{:?}", Exif::from(iter).entries()),
+            MediaInfo::Track(iter) => format!("{:?}", iter.collect::<Vec<_>>()),
+        };
+        let expected = entries(parse_media(open_sample(path).unwrap()).unwrap());
+        let info = cancelling(0)
+            .parse_media(open_sample(path).unwrap())
+            .unwrap();
+        assert_eq!(entries(info), expected);
+
+        for n in 1.. {
+            match cancelling(n).parse_media(open_sample(path).unwrap()) {
+                Err(crate::Error::Cancelled { timed_out: false }) => (),
+                Ok(info) => {
+                    assert!(n > 1, "never checked");
+                    // Decoding the Exif entries is checked as well, and
+                    // stops early
+                    if entries(info) == expected {
+                        break;
+                    }
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
+
+        let parser = MediaParser::builder()
+            .deadline(std::time::Instant::now())
+            .build();
+        let err = parser.parse_media(open_sample(path).unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "parsing timed out");
+
+        #[cfg(feature = "async")]
+        {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let res = runtime.block_on(async {
+                let f = tokio::fs::File::open(format!("./testdata/{path}"))
+                    .await
+                    .unwrap();
+                cancelling(1).parse_media_async(f).await
+            });
+            assert!(matches!(res, Err(crate::Error::Cancelled { .. })));
+        }
+    }
+
+    #[test]
+    fn exif_cancellation() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // A JPEG file with `tiff` in its Exif segment
+        let jpeg = |tiff: &[u8]| {
+            let mut data = b"\xff\xd8\xff\xe1".to_vec();
+            data.extend_from_slice(&(tiff.len() as u16 + 8).to_be_bytes());
+            data.extend_from_slice(b"Exif\0\0");
+            data.extend_from_slice(tiff);
+            data.extend_from_slice(b"\xff\xd9");
+            data
+        };
+        // An empty GPS IFD linking to itself
+        let gps_loop = jpeg(
+            b"II*\0\x08\0\0\0\x01\0\x25\x88\x04\0\x01\0\0\0\x1a\0\0\0\0\0\0\0\
+              \0\0\x1a\0\0\0",
+        );
+        // IFD1 linking to itself
+        let ifd1_loop = jpeg(
+            b"II*\0\x08\0\0\0\x01\0\0\x01\x03\0\x01\0\0\0\x01\0\0\0\x1a\0\0\0\
+              \x01\0\0\x01\x03\0\x01\0\0\0\x02\0\0\0\x1a\0\0\0",
+        );
+
+        let parser = MediaParser::builder()
+            .deadline(Instant::now() + std::time::Duration::from_secs(60))
+            .build();
+        let summary = parser.probe(std::io::Cursor::new(&gps_loop)).unwrap();
+        assert!(!summary.has_gps);
+        let iter = parser
+            .parse_exif(std::io::Cursor::new(&ifd1_loop), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Exif::from(iter).get_all(ExifTag::ImageWidth).len(), 2);
+
+        // Cancelled once the Exif data has been read
+        let cancelled = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = cancelled.clone();
+        let parser = MediaParser::builder()
+            .cancel_check(move || flag.load(std::sync::atomic::Ordering::Relaxed))
+            .build();
+        let iter = parser
+            .parse_exif(std::io::Cursor::new(&ifd1_loop), None)
+            .unwrap()
+            .unwrap();
+        cancelled.store(true, std::sync::atomic::Ordering::Relaxed);
+        let entries = iter.collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(
+            entries[0].take_result(),
+            Err(crate::Error::Cancelled { timed_out: false })
+        ));
+    }
+
+    #[test]
+    fn parse_media_reads_head_once() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // The Exif segment follows the SOI marker, and is 0x4569 bytes long
+        // with its marker
+        let mut reader = CountingReader::new(open_sample("exif.jpg").unwrap());
+        let parser = MediaParser::default();
+        assert!(parser.parse_media(&mut reader).unwrap().is_some());
+        assert_eq!(reader.bytes_read, 2 + 2 + 0x4569);
+        assert_eq!(reader.seeks, 0);
+
+        let mut reader = CountingReader::new(open_sample("meta.mov").unwrap());
+        assert!(parser.probe(&mut reader).unwrap().duration.is_some());
+        // Only the seek over the `mdat` atom
+        assert_eq!(reader.seeks, 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    #[test_case("embedded-in-heic.mov")]
+    fn parse_media_async(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::default();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let info = runtime.block_on(async {
+            let f = tokio::fs::File::open(format!("./testdata/{path}"))
+                .await
+                .unwrap();
+            parser.parse_media_async(f).await.unwrap().unwrap()
+        });
+        let stats = parser.take_stats();
+
+        let by_path = runtime.block_on(parser.parse_file_async(format!("./testdata/{path}")));
+        assert!(by_path.unwrap().is_some());
+        let missing = runtime.block_on(parser.parse_file_async("./testdata/missing.jpg"));
+        assert!(missing.is_err());
+
+        let summary = runtime.block_on(async {
+            let f = tokio::fs::File::open(format!("./testdata/{path}"))
+                .await
+                .unwrap();
+            parser.probe_async(f).await.unwrap()
+        });
+        assert_eq!(summary, parser.probe(open_sample(path).unwrap()).unwrap());
+
+        match (
+            info,
+            parse_media(open_sample(path).unwrap()).unwrap().unwrap(),
+        ) {
+            (MediaInfo::Exif(iter), MediaInfo::Exif(expected)) => {
+                let exif: Exif = iter.into();
+                let expected: Exif = expected.into();
+                assert_eq!(exif.entries(), expected.entries());
+            }
+            (MediaInfo::Track(iter), MediaInfo::Track(expected)) => {
+                assert_eq!(iter.tracks(), expected.tracks());
+                assert_eq!(
+                    format!("{:?}", iter.collect::<Vec<_>>()),
+                    format!("{:?}", expected.collect::<Vec<_>>())
+                );
+                // The `mdat` atom is seeked over
+                let size = read_sample(path).unwrap().len() as u64;
+                assert!(stats.bytes_read < size / 2, "{stats:?}");
+            }
+            (info, expected) => panic!("{info:?} != {expected:?}"),
+        }
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn parse_unseekable(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample(path).unwrap();
+        let expected = format!("{:?}", parse_metadata(open_sample(path).unwrap()).unwrap());
+        let entries = |iter: MetadataIter| {
+            let entries = iter.map(|(k, v)| (k, v.unwrap())).collect::<Vec<_>>();
+            format!("{entries:?}")
+        };
+
+        let parser = MediaParser::default();
+        let iter = parser.parse_metadata_unseekable(&data[..]).unwrap();
+        assert_eq!(entries(iter), expected);
+
+        let parser = MediaParser::builder().max_discarded_bytes(0).build();
+        let err = parser.parse_metadata_unseekable(&data[..]).unwrap_err();
+        let crate::Error::SkipRequired {
+            format,
+            offset,
+            remaining,
+        } = err
+        else {
+            panic!("unexpected error: {err}");
+        };
+        assert!(remaining > 0);
+        let start = (offset + remaining) as usize;
+        let iter = parser
+            .resume_metadata_unseekable(&data[start..], format, start as u64)
+            .unwrap();
+        assert_eq!(entries(iter), expected);
+
+        // The file ends within the skipped data
+        let parser = MediaParser::default();
+        let err = parser
+            .parse_metadata_unseekable(&data[..start - 1])
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::Error::Truncated { needed: Some(1) }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn structured_errors() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let err = parse_exif(open_sample("ramdisk.img").unwrap(), None).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::UnsupportedFormat { detected: None }
+        ));
+
+        let err = parse_exif(open_sample("meta.mov").unwrap(), None).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported file format: QuickTime");
+
+        let err = parse_exif(&[][..], None).unwrap_err();
+        assert!(matches!(err, crate::Error::Truncated { needed: None }));
+
+        let buf = read_sample("exif.jpg").unwrap();
+        let err = parse_exif(&buf[..0x100], None).unwrap_err();
+        assert!(matches!(err, crate::Error::Truncated { .. }), "{err}");
+
+        let err = parse_metadata_iter(std::io::Cursor::new([])).unwrap_err();
+        assert!(matches!(err, crate::Error::Truncated { needed: None }));
+
+        let err = parse_metadata_iter(open_sample("exif.jpg").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported file format: JPEG");
+    }
+
+    #[test_case("exif.jpg", 0)]
+    #[test_case("exif.heic", 1; "exif.heic has an invalid SubjectArea")]
+    fn lenient_exif(path: &str, skipped: usize) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::default();
+        let (exif, warnings) = parser
+            .parse_exif_lenient(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(warnings.len(), skipped, "{warnings:?}");
+        let expected: Exif = parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(exif.get(ExifTag::Make), expected.get(ExifTag::Make));
+        assert_eq!(
+            exif.get_gps_info().unwrap(),
+            expected.get_gps_info().unwrap()
+        );
+    }
+
+    #[test]
+    fn lenient_truncated() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample("exif.jpg").unwrap();
+        let parser = MediaParser::default();
+        for size in [0x200, 0x300, 0x1000] {
+            let data = &buf[..size];
+            parse_exif(data, None).unwrap_err();
+
+            let (exif, warnings) = parser.parse_exif_lenient(data, None).unwrap().unwrap();
+            assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "vivo");
+            assert!(
+                matches!(warnings[0], Warning::Truncated { needed: Some(_) }),
+                "{warnings:?}"
+            );
+        }
+
+        // The Exif IFD is cut off, the entries which are there are parsed
+        parser.take_warnings();
+        let (exif, _) = parser
+            .parse_exif_lenient(&buf[..0x200], None)
+            .unwrap()
+            .unwrap();
+        assert!(exif.get(ExifTag::DateTimeOriginal).is_none());
+        assert!(exif.get(ExifTag::ExposureProgram).is_some());
+        let warnings = parser.take_warnings();
+        assert!(
+            warnings.iter().any(|w| matches!(
+                w,
+                Warning::EntryCount {
+                    kind: IfdKind::Exif,
+                    ..
+                }
+            )),
+            "{warnings:?}"
+        );
+
+        // Values of the Exif IFD are cut off
+        let (exif, warnings) = parser
+            .parse_exif_lenient(&buf[..0x300], None)
+            .unwrap()
+            .unwrap();
+        assert!(exif.get(ExifTag::DateTimeOriginal).is_none());
+        assert!(exif.get(ExifTag::ExifImageWidth).is_some());
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            Warning::SkippedEntry { name, .. } if name == "DateTimeOriginal(0x9003)"
+        )));
+
+        // Truncated before the Exif data
+        parser.parse_exif_lenient(&buf[..0x10], None).unwrap_err();
+    }
+
+    #[test]
+    fn lenient_track() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::default();
+        let (entries, warnings) = parser
+            .parse_metadata_lenient(open_sample("meta.mov").unwrap())
+            .unwrap();
+        assert_eq!(warnings, []);
+        assert_eq!(
+            entries,
+            parse_metadata(open_sample("meta.mov").unwrap()).unwrap()
+        );
+
+        // Break the data atom of "Apple", the first ilst item
+        let mut buf = read_sample("meta.mov").unwrap();
+        let item = b"data\x00\x00\x00\x01\x00\x00\x00\x00Apple";
+        let pos = buf.windows(item.len()).position(|x| x == item).unwrap();
+        buf[pos] = b'x';
+
+        let (entries, warnings) = parser
+            .parse_metadata_lenient(std::io::Cursor::new(&buf))
+            .unwrap();
+        assert_eq!(
+            warnings,
+            [Warning::SkippedBox {
+                path: "moov/meta/ilst".to_owned(),
+                offset: pos as u64 - 12
+            }]
+        );
+        assert!(entries.iter().any(|(k, _)| k == "duration"));
+    }
+
+    #[test]
+    fn parser_warnings() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample("exif.jpg").unwrap();
+        let tiff = buf.windows(6).position(|x| x == b"Exif\0\0").unwrap() + 6;
+        assert_eq!(&buf[tiff..tiff + 2], b"MM");
+        let ifd0 = tiff + u32::from_be_bytes(buf[tiff + 4..tiff + 8].try_into().unwrap()) as usize;
+
+        // Non-ASCII model
+        let mut data = buf.clone();
+        let pos = data
+            .windows(13)
+            .position(|x| x == b"vivo X90 Pro+")
+            .unwrap();
+        data[pos + 11..pos + 13].copy_from_slice("é".as_bytes());
+
+        let parser = MediaParser::default();
+        let exif: Exif = parser.parse_exif(&data[..], None).unwrap().unwrap().into();
+        assert_eq!(
+            exif.get(ExifTag::Model).unwrap().to_string(),
+            "vivo X90 Pré"
+        );
+        assert_eq!(
+            parser.take_warnings(),
+            [Warning::NonAsciiText {
+                name: "Model(0x0110)".to_owned()
+            }]
+        );
+        assert_eq!(parser.take_warnings(), []);
+
+        // Too many entries in IFD0
+        let mut data = buf.clone();
+        data[ifd0..ifd0 + 2].copy_from_slice(&[0xff, 0xff]);
+
+        let exif: Exif = parser.parse_exif(&data[..], None).unwrap().unwrap().into();
+        assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "vivo");
+        let warnings = parser.take_warnings();
+        assert!(
+            matches!(
+                warnings[0],
+                Warning::EntryCount {
+                    kind: IfdKind::Ifd0,
+                    declared: 0xffff,
+                    ..
+                }
+            ),
+            "{warnings:?}"
+        );
+
+        // Warnings aren't shared with clones
+        let cloned = parser.clone();
+        let _: Exif = parser.parse_exif(&data[..], None).unwrap().unwrap().into();
+        assert_eq!(cloned.take_warnings(), []);
+        assert!(!parser.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn parser_events() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = events.clone();
+        let parser = MediaParser::builder()
+            .on_event(move |x| log.lock().unwrap().push(x.clone()))
+            .build();
+        let take = || std::mem::take(&mut *events.lock().unwrap());
+
+        parser
+            .parse_media(open_sample("meta.mov").unwrap())
+            .unwrap();
+        let boxes = [("ftyp", 0, 20), ("wide", 20, 8), ("mdat", 28, 765675)]
+            .into_iter()
+            .chain([("moov", 765703, 4133)])
+            .map(|(t, offset, size)| ParseEvent::BoxVisited {
+                box_type: t.to_owned(),
+                offset,
+                size,
+            });
+        let expected: Vec<_> = [ParseEvent::Started].into_iter().chain(boxes).collect();
+        assert_eq!(take(), expected);
+
+        // An ICC profile before the Exif segment
+        let mut data = read_sample("exif.jpg").unwrap();
+        let mut app2 = vec![0xFF, 0xE2, 0, 102];
+        app2.resize(104, b'x');
+        data.splice(2..2, app2);
+        parser.parse_media(std::io::Cursor::new(&data)).unwrap();
+        assert_eq!(
+            take(),
+            [
+                ParseEvent::Started,
+                ParseEvent::SegmentSkipped {
+                    marker: 0xE2,
+                    offset: 2,
+                    size: 100
+                }
+            ]
+        );
+
+        // Non-ASCII model
+        let pos = data
+            .windows(13)
+            .position(|x| x == b"vivo X90 Pro+")
+            .unwrap();
+        data[pos + 11..pos + 13].copy_from_slice("é".as_bytes());
+        let _: Exif = parser.parse_exif(&data[..], None).unwrap().unwrap().into();
+        let warning = Warning::NonAsciiText {
+            name: "Model(0x0110)".to_owned(),
+        };
+        assert_eq!(take(), [ParseEvent::Started, ParseEvent::Fallback(warning)]);
+
+        // Clones have the same callback
+        parser
+            .clone()
+            .probe(open_sample("exif.heic").unwrap())
+            .unwrap();
+        assert_eq!(take(), [ParseEvent::Started]);
+    }
+
+    #[test_case("no-exif.jpg")]
+    fn media_none(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        assert!(parse_media(open_sample(path).unwrap()).unwrap().is_none());
+    }
+
+    #[test]
+    fn buffer_pool() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::builder().buffer_pool_size(1).build();
+        let pool = parser.buffer_pool.as_ref().unwrap();
+
+        let iter = parser.parse_exif(open_sample("exif.jpg").unwrap(), None);
+        let iter2 = parser.parse_exif(open_sample("exif.heic").unwrap(), None);
+        assert_eq!(pool.len(), 0);
+        drop((iter, iter2));
+        // Only one buffer is kept
+        assert_eq!(pool.len(), 1);
+
+        // The buffer is reused, and released again with the metadata
+        let iter = parser.parse_metadata_iter(open_sample("meta.mov").unwrap());
+        assert_eq!(pool.len(), 0);
+        let entries = iter.unwrap().collect::<Vec<_>>();
+        assert_eq!(entries[0].0, "com.apple.quicktime.make");
+        assert_eq!(pool.len(), 1);
+
+        let exif: Exif = parser
+            .parse_exif(open_sample("exif.jpg").unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(exif.get(ExifTag::Make).unwrap().to_string(), "vivo");
+        assert_eq!(pool.len(), 1);
+
+        // Clones have their own pool
+        assert_eq!(parser.clone().buffer_pool.unwrap().len(), 0);
+    }
+
+    #[test_case("exif.jpg", 0)]
+    #[test_case("meta.mp4", 1)]
+    fn parse_stats(path: &str, skips: u64) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::builder().buffer_capacity(512).build();
+        assert!(parser.parse_media(open_sample(path).unwrap()).is_ok());
+
+        let stats = parser.take_stats();
+        assert_eq!(stats.files, 1);
+        assert_eq!(stats.skips, skips);
+        assert_eq!(stats.skipped_bytes > 0, skips > 0);
+        assert!(stats.bytes_read >= stats.peak_buffer_size as u64);
+        assert!(stats.peak_buffer_size > 512);
+        assert!(stats.reallocations > 0);
+        assert_eq!(stats.pooled_buffers, 0);
+        assert_eq!(parser.take_stats(), ParseStats::default());
+    }
+
+    #[test_case("exif.jpg", &[ExifTag::Make, ExifTag::Model])]
+    #[test_case("exif.heic", &[ExifTag::DateTimeOriginal, ExifTag::GPSInfo])]
+    #[test_case("exif.jpg", &[ExifTag::ImageDescription])]
+    #[test_case("exif.jpg", &[])]
+    fn parse_filtered(path: &str, tags: &[ExifTag]) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let parser = MediaParser::default();
+        let exif = parser
+            .parse_filtered(open_sample(path).unwrap(), None, tags)
+            .unwrap()
+            .unwrap();
+        let full: Exif = parser
+            .parse_exif(open_sample(path).unwrap(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+
+        if tags.is_empty() {
+            assert_eq!(exif.entries(), full.entries());
+            return;
+        }
+        assert!(exif.entries().iter().all(|(tag, _)| tags.contains(tag)));
+        for &tag in tags {
+            assert_eq!(exif.get(tag), full.get(tag), "{tag}");
+        }
+        assert_eq!(
+            exif.get_gps_info().unwrap().is_some(),
+            tags.contains(&ExifTag::GPSInfo)
+        );
+    }
+}
@@ -0,0 +1,1253 @@
+use std::{
+    collections::HashSet,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+#[cfg(any(feature = "async", feature = "futures-io"))]
+use crate::async_io::AsyncSeekSource;
+use crate::{
+    ebml::{children, float, text, uint, vint, DOC_TYPE_ID, EBML_ID},
+    mov::LOCATION_KEY,
+    CodecConfig, ColorInfo, ContentLightLevel, DolbyVision, EntryValue, MasteringDisplay,
+    MediaParser, MetadataIter, TrackKind, TrackMeta,
+};
+
+const SEGMENT_ID: u32 = 0x1853_8067;
+const CLUSTER_ID: u32 = 0x1F43_B675;
+const CUES_ID: u32 = 0x1C53_BB6B;
+
+const SEEK_HEAD_ID: u32 = 0x114D_9B74;
+const SEEK_ID: u32 = 0x4DBB;
+const SEEK_ID_ID: u32 = 0x53AB;
+const SEEK_POSITION_ID: u32 = 0x53AC;
+
+const INFO_ID: u32 = 0x1549_A966;
+const TIMESTAMP_SCALE_ID: u32 = 0x2A_D7B1;
+const DURATION_ID: u32 = 0x4489;
+
+const TRACKS_ID: u32 = 0x1654_AE6B;
+const TRACK_ENTRY_ID: u32 = 0xAE;
+const TRACK_NUMBER_ID: u32 = 0xD7;
+const LANGUAGE_ID: u32 = 0x22_B59C;
+const BLOCK_ADDITION_MAPPING_ID: u32 = 0x41E4;
+const BLOCK_ADD_ID_TYPE_ID: u32 = 0x41E7;
+const BLOCK_ADD_ID_EXTRA_DATA_ID: u32 = 0x41ED;
+const TRACK_TYPE_ID: u32 = 0x83;
+const CODEC_ID_ID: u32 = 0x86;
+const CODEC_PRIVATE_ID: u32 = 0x63A2;
+const DEFAULT_DURATION_ID: u32 = 0x23_E383;
+const VIDEO_ID: u32 = 0xE0;
+const PIXEL_WIDTH_ID: u32 = 0xB0;
+const PIXEL_HEIGHT_ID: u32 = 0xBA;
+const COLOUR_ID: u32 = 0x55B0;
+const MATRIX_COEFFICIENTS_ID: u32 = 0x55B1;
+const RANGE_ID: u32 = 0x55B9;
+const TRANSFER_CHARACTERISTICS_ID: u32 = 0x55BA;
+const PRIMARIES_ID: u32 = 0x55BB;
+const MAX_CLL_ID: u32 = 0x55BC;
+const MAX_FALL_ID: u32 = 0x55BD;
+const MASTERING_METADATA_ID: u32 = 0x55D0;
+const AUDIO_ID: u32 = 0xE1;
+const SAMPLING_FREQUENCY_ID: u32 = 0xB5;
+const OUTPUT_SAMPLING_FREQUENCY_ID: u32 = 0x78B5;
+const CHANNELS_ID: u32 = 0x9F;
+const BIT_DEPTH_ID: u32 = 0x6264;
+
+const TAGS_ID: u32 = 0x1254_C367;
+const TAG_ID: u32 = 0x7373;
+const SIMPLE_TAG_ID: u32 = 0x67C8;
+const TAG_NAME_ID: u32 = 0x45A3;
+const TAG_STRING_ID: u32 = 0x4487;
+
+/// `SeekHead`, `Info`, `Tracks` and `Tags` elements larger than this are
+/// skipped.
+const MAX_ELEMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Maximum length of an element header: a 4-byte ID and an 8-byte size.
+const MAX_HEADER_LEN: u64 = 12;
+
+/// Reads the tags (`Segment/Tags/Tag/SimpleTag` elements with a string
+/// value) of a Matroska (MKV) or WebM file, as `(TagName, Text)` pairs in
+/// file order. Nested tags are named after their parents, e.g.
+/// `"ARTIST.URL"`.
+///
+/// Phones and drones record the GPS position as an ISO 6709 string in the
+/// `RECORDING_LOCATION` tag, like the `com.apple.quicktime.location.ISO6709`
+/// entry of QuickTime/MP4 files, which [`GPSInfo::from_iso6709`] parses.
+///
+/// Only the elements which precede the first cluster are read, followed by
+/// the ones the `SeekHead` index of the file points to, until the `Info`,
+/// `Tracks` and `Tags` elements have been read: the clusters (the media data)
+/// and the `Cues` are never read. Files without a `SeekHead` are scanned by
+/// seeking over their clusters, so the elements following a cluster of
+/// unknown size (written by live recorders) aren't found.
+///
+/// Returns [`Error::UnsupportedFormat`](crate::Error::UnsupportedFormat) if
+/// `reader` isn't a Matroska or WebM file, and
+/// [`Error::LimitExceeded`](crate::Error::LimitExceeded) if tags are nested
+/// more deeply than the default
+/// [`MediaParserBuilder::max_nesting_depth`](crate::MediaParserBuilder::max_nesting_depth).
+///
+/// # Usage
+///
+/// ```rust,no_run
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./drone.mkv").unwrap();
+/// let tags = parse_matroska_tags(f).unwrap();
+/// let gps = tags
+///     .iter()
+///     .find(|x| x.0 == "RECORDING_LOCATION")
+///     .and_then(|x| GPSInfo::from_iso6709(x.1.as_str()?).ok());
+/// ```
+///
+/// [`GPSInfo::from_iso6709`]: crate::GPSInfo::from_iso6709
+#[tracing::instrument(skip_all)]
+pub fn parse_matroska_tags<R: Read + Seek>(reader: R) -> crate::Result<Vec<(String, EntryValue)>> {
+    Ok(read_matroska(reader, &MediaParser::default())?.tags)
+}
+
+/// Like [`parse_matroska_tags`], followed by the properties of the file,
+/// with the keys of [`parse_metadata`](crate::parse_metadata):
+///
+/// - `duration` (milliseconds), the segment duration scaled by its
+///   `TimestampScale`,
+/// - `width`, `height`, `video_codec` and `frame_rate` of the first video
+///   track, where the frame rate is derived from the `DefaultDuration` of
+///   the track,
+/// - `audio_codec`, `audio_sample_rate`, `audio_channels` and
+///   `audio_bit_depth` (bits per sample) of the first audio track, e.g. of
+///   MKA files, which only have audio tracks. The sample rate is the output
+///   one, which is twice the stored one for HE-AAC (SBR).
+///
+/// Codecs are Matroska codec IDs, e.g. `"V_VP9"` or `"A_OPUS"`.
+///
+/// # Usage
+///
+/// ```rust,no_run
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./video.webm").unwrap();
+/// let entries = parse_matroska_metadata(f).unwrap();
+/// let codec = entries.iter().find(|x| x.0 == "video_codec");
+/// ```
+#[tracing::instrument(skip_all)]
+pub fn parse_matroska_metadata<R: Read + Seek>(
+    reader: R,
+) -> crate::Result<Vec<(String, EntryValue)>> {
+    Ok(read_matroska(reader, &MediaParser::default())?.take_entries())
+}
+
+/// Returns the tracks of a Matroska (MKV) or WebM file, in file order, like
+/// [`MetadataIter::tracks`](crate::MetadataIter::tracks) does for
+/// QuickTime/MP4 files, e.g. to find the color description of HDR videos.
+///
+/// Codecs are Matroska codec IDs, and the ID of a track is its
+/// `TrackNumber`. Tracks have no duration or creation time of their own.
+///
+/// # Usage
+///
+/// ```rust,no_run
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./video.mkv").unwrap();
+/// let tracks = parse_matroska_tracks(f).unwrap();
+/// let hdr = tracks
+///     .iter()
+///     .filter_map(|x| x.color.as_ref())
+///     .any(|x| x.hdr_format().is_some());
+/// ```
+#[tracing::instrument(skip_all)]
+pub fn parse_matroska_tracks<R: Read + Seek>(reader: R) -> crate::Result<Vec<TrackMeta>> {
+    Ok(read_matroska(reader, &MediaParser::default())?.into_tracks())
+}
+
+/// Reads the entries of [`parse_matroska_metadata`] and the tracks of a
+/// Matroska file, for [`MediaParser::parse_media`](crate::MediaParser::parse_media).
+///
+/// The `RECORDING_LOCATION` tag is also yielded as the
+/// `com.apple.quicktime.location.ISO6709` entry, so that the GPS position is
+/// found the same way as in QuickTime/MP4 files.
+pub(crate) fn read_metadata_iter<R: Read + Seek>(
+    reader: R,
+    parser: &MediaParser,
+) -> crate::Result<MetadataIter> {
+    Ok(read_matroska(reader, parser)?.into_metadata_iter())
+}
+
+/// `async` version of [`read_metadata_iter`].
+#[cfg(any(feature = "async", feature = "futures-io"))]
+pub(crate) async fn read_metadata_iter_async<S: AsyncSeekSource>(
+    mut reader: S,
+    parser: &MediaParser,
+) -> crate::Result<MetadataIter> {
+    let mut scanner = Scanner::new(parser);
+    let mut buf = Vec::new();
+    while let Some((pos, len)) = scanner.next_read() {
+        buf.clear();
+        reader.seek_to(pos).await?;
+        reader.read_to_buf(&mut buf, len as usize).await?;
+        scanner.feed(&buf)?;
+    }
+    Ok(scanner.mkv.into_metadata_iter())
+}
+
+/// The elements of a Matroska file which are reported.
+#[derive(Debug, Default)]
+struct Matroska {
+    tags: Vec<(String, EntryValue)>,
+    timestamp_scale: Option<u64>,
+    /// In `timestamp_scale` units.
+    duration: Option<f64>,
+    tracks: Vec<Track>,
+}
+
+impl Matroska {
+    /// Takes the tags, followed by the properties of the file, see
+    /// [`parse_matroska_metadata`].
+    fn take_entries(&mut self) -> Vec<(String, EntryValue)> {
+        let mut entries = std::mem::take(&mut self.tags);
+        let mut push = |k: &str, v: Option<EntryValue>| {
+            if let Some(v) = v {
+                entries.push((k.to_owned(), v));
+            }
+        };
+
+        // In nanoseconds
+        let scale = self.timestamp_scale.unwrap_or(1_000_000) as f64;
+        let duration = self
+            .duration
+            .map(|x| (x * scale / 1_000_000.0).round() as u32);
+        push("duration", duration.map(Into::into));
+
+        let video = self.tracks.iter().find(|x| x.kind == Some(1));
+        if let Some(track) = video {
+            push("width", track.width.map(Into::into));
+            push("height", track.height.map(Into::into));
+            push("video_codec", track.codec.clone().map(Into::into));
+            let frame_rate = track.default_duration.filter(|x| *x > 0);
+            push("frame_rate", frame_rate.map(|x| (1e9 / x as f64).into()));
+        }
+        let audio = self.tracks.iter().find(|x| x.kind == Some(2));
+        if let Some(track) = audio {
+            push("audio_codec", track.codec.clone().map(Into::into));
+            let rate = track.output_sample_rate.or(track.sample_rate);
+            push("audio_sample_rate", rate.map(|x| (x.round() as u32).into()));
+            push("audio_channels", track.channels.map(Into::into));
+            push("audio_bit_depth", track.bit_depth.map(Into::into));
+        }
+        entries
+    }
+
+    /// See [`read_metadata_iter`].
+    fn into_metadata_iter(mut self) -> MetadataIter {
+        let mut entries = self.take_entries();
+        let location = entries.iter().find(|x| x.0 == "RECORDING_LOCATION");
+        if let Some((_, location)) = location {
+            entries.push((LOCATION_KEY.to_owned(), location.clone()));
+        }
+        MetadataIter::from_matroska(entries, self.into_tracks())
+    }
+
+    fn into_tracks(self) -> Vec<TrackMeta> {
+        let tracks = self.tracks.into_iter().map(|track| TrackMeta {
+            id: track.number,
+            kind: match track.kind {
+                Some(1) => TrackKind::Video,
+                Some(2) => TrackKind::Audio,
+                Some(0x11) => TrackKind::Subtitle,
+                Some(0x21) => TrackKind::Metadata,
+                kind => TrackKind::Other(kind.unwrap_or_default().to_string()),
+            },
+            codec: track.codec,
+            duration: None,
+            presentation_duration: None,
+            language: track.language,
+            creation_time: None,
+            modification_time: None,
+            color: track.color,
+            codec_config: track.codec_config,
+        });
+        tracks.collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Track {
+    number: Option<u32>,
+    /// 1 for video, 2 for audio.
+    kind: Option<u64>,
+    codec: Option<String>,
+    codec_config: Option<CodecConfig>,
+    language: Option<String>,
+    /// Duration of a frame, in nanoseconds.
+    default_duration: Option<u64>,
+    width: Option<u32>,
+    height: Option<u32>,
+    color: Option<ColorInfo>,
+    sample_rate: Option<f64>,
+    output_sample_rate: Option<f64>,
+    channels: Option<u16>,
+    bit_depth: Option<u16>,
+}
+
+fn read_matroska<R: Read + Seek>(mut reader: R, parser: &MediaParser) -> crate::Result<Matroska> {
+    let mut scanner = Scanner::new(parser);
+    let mut buf = Vec::new();
+    while let Some((pos, len)) = scanner.next_read() {
+        buf.clear();
+        reader.seek(SeekFrom::Start(pos))?;
+        (&mut reader).take(len).read_to_end(&mut buf)?;
+        scanner.feed(&buf)?;
+    }
+    Ok(scanner.mkv)
+}
+
+/// Finds and parses the elements of a Matroska file which are reported,
+/// telling which range of the file to read next.
+///
+/// The top-level elements are read in order up to the first cluster, then
+/// the ones indexed by the `SeekHead` which haven't been read, until `Info`,
+/// `Tracks` and `Tags` have been read. The clusters (the media data) and the
+/// `Cues` (which only index the clusters) are never read: without a
+/// `SeekHead`, the top-level elements which follow the clusters are found by
+/// seeking over them.
+struct Scanner<'a> {
+    parser: &'a MediaParser,
+    mkv: Matroska,
+    step: Step,
+    /// Position of the segment's body, which seek positions are relative to.
+    segment: u64,
+    /// IDs of the elements which have been parsed.
+    parsed: Vec<u32>,
+    /// Positions of the elements whose header has been read.
+    visited: HashSet<u64>,
+    /// Positions of the elements indexed by the seek heads, and how many of
+    /// them have been visited.
+    indexed: Vec<u64>,
+    next_indexed: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// The ID and the size of the EBML header, at the start of the file.
+    Start,
+    /// The body of the EBML header at `pos`, followed by the segment header.
+    EbmlHeader {
+        pos: u64,
+        size: u64,
+    },
+    /// The header of the element at `pos`, which follows the previous
+    /// top-level element if `scan` is set, or is indexed by a seek head.
+    Header {
+        pos: u64,
+        scan: bool,
+    },
+    /// The body of an element for which [`is_wanted`] is true.
+    Body {
+        id: u32,
+        pos: u64,
+        size: u64,
+        scan: bool,
+    },
+    Done,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(parser: &'a MediaParser) -> Self {
+        Self {
+            parser,
+            mkv: Matroska::default(),
+            step: Step::Start,
+            segment: 0,
+            parsed: Vec::new(),
+            visited: HashSet::new(),
+            indexed: Vec::new(),
+            next_indexed: 0,
+        }
+    }
+
+    /// Returns the position and the length of the range of the file to read
+    /// next, or `None` once done.
+    fn next_read(&self) -> Option<(u64, u64)> {
+        match self.step {
+            Step::Start => Some((0, MAX_HEADER_LEN)),
+            Step::EbmlHeader { pos, size } => Some((pos, size + MAX_HEADER_LEN)),
+            Step::Header { pos, .. } => Some((pos, MAX_HEADER_LEN)),
+            Step::Body { pos, size, .. } => Some((pos, size)),
+            Step::Done => None,
+        }
+    }
+
+    /// Parses `data`, which has been read from the range returned by
+    /// [`Self::next_read`], and is only shorter at the end of the file.
+    fn feed(&mut self, data: &[u8]) -> crate::Result<()> {
+        self.step = match self.step {
+            Step::Start => self.start(data)?,
+            Step::EbmlHeader { pos, size } => self.ebml_header(data, pos, size)?,
+            Step::Header { pos, scan } => {
+                let mut reader = data;
+                let header = read_header(&mut reader)?;
+                let body = pos + (data.len() - reader.len()) as u64;
+                match header {
+                    Some((id, Some(size))) if is_wanted(id) && size <= MAX_ELEMENT_SIZE => {
+                        Step::Body {
+                            id,
+                            pos: body,
+                            size,
+                            scan,
+                        }
+                    }
+                    Some((id, Some(size))) if scan && !self.stops_scan(id) => {
+                        match body.checked_add(size) {
+                            Some(pos) => self.visit(pos),
+                            None => self.next_indexed(),
+                        }
+                    }
+                    Some((id, None)) if scan => {
+                        tracing::debug!(id = format!("{id:x}"), "Element of unknown size.");
+                        self.next_indexed()
+                    }
+                    _ => {
+                        if !scan {
+                            tracing::debug!(pos, "Invalid seek position.");
+                        }
+                        self.next_indexed()
+                    }
+                }
+            }
+            Step::Body {
+                id,
+                pos,
+                size,
+                scan,
+            } => {
+                if (data.len() as u64) < size {
+                    return Err(crate::Error::Truncated { needed: None });
+                }
+                parse_element(
+                    id,
+                    data,
+                    self.segment,
+                    &mut self.mkv,
+                    &mut self.indexed,
+                    self.parser,
+                )?;
+                self.parsed.push(id);
+                if scan && !self.is_complete() {
+                    self.visit(pos + size)
+                } else {
+                    self.next_indexed()
+                }
+            }
+            Step::Done => Step::Done,
+        };
+        Ok(())
+    }
+
+    /// Checks the ID and the size of the EBML header at the start of the
+    /// file.
+    fn start(&mut self, data: &[u8]) -> crate::Result<Step> {
+        let unsupported = || crate::Error::UnsupportedFormat { detected: None };
+        let mut reader = data;
+
+        let mut magic = [0; 4];
+        match reader.read_exact(&mut magic) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(unsupported()),
+            res => res?,
+        }
+        if u32::from_be_bytes(magic) != EBML_ID {
+            return Err(unsupported());
+        }
+        match read_size(&mut reader)? {
+            Some(size) if size <= 4096 => Ok(Step::EbmlHeader {
+                pos: (data.len() - reader.len()) as u64,
+                size,
+            }),
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Checks the `DocType` of the EBML header, and finds the segment.
+    fn ebml_header(&mut self, data: &[u8], pos: u64, size: u64) -> crate::Result<Step> {
+        let mut reader = data;
+        let header = read_body(&mut reader, size)?;
+        let doc_type = children(&header).find(|x| x.0 == DOC_TYPE_ID);
+        if !doc_type.is_some_and(|x| matches!(x.1, b"matroska" | b"webm")) {
+            return Err(crate::Error::UnsupportedFormat { detected: None });
+        }
+
+        let Some((SEGMENT_ID, _)) = read_header(&mut reader)? else {
+            return Ok(Step::Done);
+        };
+        self.segment = pos + (data.len() - reader.len()) as u64;
+        Ok(self.visit(self.segment))
+    }
+
+    /// Reads the header of the top-level element at `pos`, unless it has
+    /// already been read.
+    fn visit(&mut self, pos: u64) -> Step {
+        if self.visited.insert(pos) {
+            Step::Header { pos, scan: true }
+        } else {
+            self.next_indexed()
+        }
+    }
+
+    /// Reads the next element indexed by the seek heads which hasn't been
+    /// read, if any is still needed.
+    fn next_indexed(&mut self) -> Step {
+        if self.is_complete() {
+            return Step::Done;
+        }
+        while let Some(&pos) = self.indexed.get(self.next_indexed) {
+            self.next_indexed += 1;
+            if self.visited.insert(pos) {
+                return Step::Header { pos, scan: false };
+            }
+        }
+        Step::Done
+    }
+
+    /// Whether the top-level elements following `id` are found through the
+    /// seek head rather than by seeking over the clusters.
+    fn stops_scan(&self, id: u32) -> bool {
+        matches!(id, CLUSTER_ID | CUES_ID) && self.parsed.contains(&SEEK_HEAD_ID)
+    }
+
+    fn is_complete(&self) -> bool {
+        [INFO_ID, TRACKS_ID, TAGS_ID]
+            .iter()
+            .all(|x| self.parsed.contains(x))
+    }
+}
+
+fn is_wanted(id: u32) -> bool {
+    matches!(id, SEEK_HEAD_ID | INFO_ID | TRACKS_ID | TAGS_ID)
+}
+
+/// Parses a top-level element for which [`is_wanted`] is true. `segment` is
+/// the position of the segment's body, which seek positions are relative to.
+fn parse_element(
+    id: u32,
+    body: &[u8],
+    segment: u64,
+    mkv: &mut Matroska,
+    indexed: &mut Vec<u64>,
+    parser: &MediaParser,
+) -> crate::Result<()> {
+    match id {
+        SEEK_HEAD_ID => {
+            for (_, seek) in children(body).filter(|x| x.0 == SEEK_ID) {
+                let field = |id| children(seek).find(|x| x.0 == id).and_then(|x| uint(x.1));
+                let Some(id) = field(SEEK_ID_ID).and_then(|x| u32::try_from(x).ok()) else {
+                    continue;
+                };
+                let pos = field(SEEK_POSITION_ID).and_then(|x| segment.checked_add(x));
+                if let Some(pos) = pos.filter(|_| is_wanted(id)) {
+                    indexed.push(pos);
+                }
+            }
+        }
+        INFO_ID => parse_info(body, mkv),
+        TRACKS_ID => parse_tracks(body, mkv),
+        _ => {
+            for (_, tag) in children(body).filter(|x| x.0 == TAG_ID) {
+                // Segment/Tags/Tag/SimpleTag
+                simple_tags(tag, 4, &mut mkv.tags, parser)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_info(body: &[u8], mkv: &mut Matroska) {
+    for (id, data) in children(body) {
+        match id {
+            TIMESTAMP_SCALE_ID => mkv.timestamp_scale = uint(data),
+            DURATION_ID => mkv.duration = float(data),
+            _ => (),
+        }
+    }
+}
+
+fn parse_tracks(body: &[u8], mkv: &mut Matroska) {
+    for (_, entry) in children(body).filter(|x| x.0 == TRACK_ENTRY_ID) {
+        let mut track = Track::default();
+        for (id, data) in children(entry) {
+            match id {
+                TRACK_NUMBER_ID => track.number = uint(data).and_then(|x| x.try_into().ok()),
+                TRACK_TYPE_ID => track.kind = uint(data),
+                CODEC_ID_ID => track.codec = Some(text(data)),
+                LANGUAGE_ID => track.language = Some(text(data)),
+                DEFAULT_DURATION_ID => track.default_duration = uint(data),
+                VIDEO_ID => {
+                    for (id, data) in children(data) {
+                        let value = uint(data).and_then(|x| u32::try_from(x).ok());
+                        match id {
+                            PIXEL_WIDTH_ID => track.width = value,
+                            PIXEL_HEIGHT_ID => track.height = value,
+                            COLOUR_ID => track.color = Some(parse_colour(data)),
+                            _ => (),
+                        }
+                    }
+                }
+                AUDIO_ID => {
+                    for (id, data) in children(data) {
+                        let value = uint(data).and_then(|x| u16::try_from(x).ok());
+                        match id {
+                            SAMPLING_FREQUENCY_ID => track.sample_rate = float(data),
+                            OUTPUT_SAMPLING_FREQUENCY_ID => track.output_sample_rate = float(data),
+                            CHANNELS_ID => track.channels = value,
+                            BIT_DEPTH_ID => track.bit_depth = value,
+                            _ => (),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        // The codec private data of these codecs is the record of the
+        // QuickTime/MP4 configuration box
+        let box_type = match track.codec.as_deref() {
+            Some("V_MPEG4/ISO/AVC") => "avcC",
+            Some("V_MPEGH/ISO/HEVC") => "hvcC",
+            Some("V_AV1") => "av1C",
+            _ => "",
+        };
+        let private = children(entry).find(|x| x.0 == CODEC_PRIVATE_ID);
+        track.codec_config = private.and_then(|x| CodecConfig::parse(box_type, x.1));
+
+        // Block additions declared by the track, whose Dolby Vision & HDR10+
+        // signaling supplements the `Colour` element
+        for (_, mapping) in children(entry).filter(|x| x.0 == BLOCK_ADDITION_MAPPING_ID) {
+            let field = |id| children(mapping).find(|x| x.0 == id).map(|x| x.1);
+            let dolby_vision = match field(BLOCK_ADD_ID_TYPE_ID).and_then(uint) {
+                // ITU-T T.35 metadata
+                Some(4) => {
+                    track.color.get_or_insert_with(Default::default).hdr10_plus = true;
+                    continue;
+                }
+                // `dvcC`, `dvvC` and `dvwC`, whose extra data is the
+                // configuration record
+                Some(0x6476_6343 | 0x6476_7643 | 0x6476_7743) => {
+                    field(BLOCK_ADD_ID_EXTRA_DATA_ID).and_then(DolbyVision::parse)
+                }
+                _ => None,
+            };
+            if dolby_vision.is_some() {
+                track
+                    .color
+                    .get_or_insert_with(Default::default)
+                    .dolby_vision = dolby_vision;
+            }
+        }
+        mkv.tracks.push(track);
+    }
+}
+
+/// Parses a `Colour` element, whose code points are the ones of the
+/// `colr` boxes of QuickTime/MP4 files.
+fn parse_colour(body: &[u8]) -> ColorInfo {
+    let mut color = ColorInfo::default();
+    let mut light = None::<ContentLightLevel>;
+    for (id, data) in children(body) {
+        let value = uint(data).and_then(|x| u16::try_from(x).ok());
+        match id {
+            MATRIX_COEFFICIENTS_ID => color.matrix = value,
+            TRANSFER_CHARACTERISTICS_ID => color.transfer = value,
+            PRIMARIES_ID => color.primaries = value,
+            RANGE_ID => {
+                color.full_range = match value {
+                    Some(1) => Some(false),
+                    Some(2) => Some(true),
+                    _ => None,
+                }
+            }
+            MAX_CLL_ID => light.get_or_insert_with(Default::default).max_cll = value.unwrap_or(0),
+            MAX_FALL_ID => light.get_or_insert_with(Default::default).max_fall = value.unwrap_or(0),
+            MASTERING_METADATA_ID => color.mastering_display = Some(parse_mastering(data)),
+            _ => (),
+        }
+    }
+    color.content_light = light;
+    color
+}
+
+/// Parses a `MasteringMetadata` element, converting its values to the
+/// units of `mdcv` boxes.
+fn parse_mastering(body: &[u8]) -> MasteringDisplay {
+    let mut values = [0.0; 10];
+    for (id, data) in children(body) {
+        // PrimaryRChromaticityX (0x55D1) to LuminanceMin (0x55DA)
+        if let (0x55D1..=0x55DA, Some(x)) = (id, float(data)) {
+            values[(id - 0x55D1) as usize] = x;
+        }
+    }
+    let xy = |i: usize| {
+        let c = |x: f64| (x * 50_000.0).round() as u16;
+        (c(values[i]), c(values[i + 1]))
+    };
+    let luminance = |x: f64| (x * 10_000.0).round() as u32;
+    MasteringDisplay {
+        red: xy(0),
+        green: xy(2),
+        blue: xy(4),
+        white_point: xy(6),
+        max_luminance: luminance(values[8]),
+        min_luminance: luminance(values[9]),
+    }
+}
+
+/// Adds the `SimpleTag`s in the body of `parent` to `entries`, nested ones
+/// being named after their parents. `depth` is the nesting depth of the
+/// outermost tags.
+///
+/// The nested tags are walked with a stack rather than recursively, so that
+/// a deeply nested file fails with the nesting depth limit of `parser`
+/// instead of overflowing the stack.
+fn simple_tags(
+    parent: &[u8],
+    depth: usize,
+    entries: &mut Vec<(String, EntryValue)>,
+    parser: &MediaParser,
+) -> crate::Result<()> {
+    // The tags being iterated over, with the prefix of their names
+    let mut stack = vec![(children(parent), String::new())];
+    while let Some(level) = stack.len().checked_sub(1) {
+        let (tags, prefix) = &mut stack[level];
+        let Some((_, tag)) = tags.find(|x| x.0 == SIMPLE_TAG_ID) else {
+            stack.pop();
+            continue;
+        };
+        parser.check_nesting_depth(depth + level)?;
+        let string = |id| children(tag).find(|x| x.0 == id).map(|x| text(x.1));
+        let Some(name) = string(TAG_NAME_ID) else {
+            continue;
+        };
+        let name = format!("{prefix}{name}");
+        parser.check_string_length(name.len())?;
+        if let Some(value) = string(TAG_STRING_ID) {
+            parser.check_string_length(value.len())?;
+            entries.push((name.clone(), value.into()));
+        }
+        stack.push((children(tag), name + "."));
+    }
+    Ok(())
+}
+
+/// Reads the ID and size of the element at the position of `reader`.
+/// Returns `None` at the end of the file.
+fn read_header<R: Read>(reader: &mut R) -> crate::Result<Option<(u32, Option<u64>)>> {
+    let mut buf = [0; 4];
+    if read_vint(reader, &mut buf)?.is_none() {
+        return Ok(None);
+    }
+    let (id, _) = vint(&buf, true).ok_or("invalid EBML element ID")?;
+    Ok(Some((id as u32, read_size(reader)?)))
+}
+
+/// Reads the size of an element, `None` for an unknown size.
+fn read_size<R: Read>(reader: &mut R) -> crate::Result<Option<u64>> {
+    let mut buf = [0; 8];
+    let Some(len) = read_vint(reader, &mut buf)? else {
+        return Err(crate::Error::Truncated { needed: None });
+    };
+    let (size, _) = vint(&buf, false).ok_or("invalid EBML element size")?;
+    // All the bits set means an unknown size
+    let unknown = size == (1 << (7 * len)) - 1;
+    Ok((!unknown).then_some(size))
+}
+
+/// Reads a variable-length integer of up to `buf.len()` bytes into `buf`,
+/// and returns its length, or `None` at the end of the file.
+fn read_vint<R: Read>(reader: &mut R, buf: &mut [u8]) -> crate::Result<Option<usize>> {
+    match reader.read_exact(&mut buf[..1]) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        res => res?,
+    }
+    let len = buf[0].leading_zeros() as usize + 1;
+    if len > buf.len() {
+        return Err("invalid EBML variable-length integer".into());
+    }
+    reader.read_exact(&mut buf[1..len])?;
+    Ok(Some(len))
+}
+
+fn read_body<R: Read>(reader: &mut R, size: u64) -> crate::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    reader.take(size).read_to_end(&mut body)?;
+    if (body.len() as u64) < size {
+        return Err(crate::Error::Truncated { needed: None });
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        detect_format, testkit::*, Capabilities, FileFormat, GPSInfo, MediaInfo, MediaParser,
+        MediaType,
+    };
+    use std::io::Cursor;
+
+    /// Returns an element with an 8-byte size, or an unknown size.
+    fn element(id: u32, body: &[u8], known: bool) -> Vec<u8> {
+        let mut data = id
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|x| *x == 0)
+            .collect::<Vec<_>>();
+        if known {
+            data.push(0x01);
+            data.extend(&(body.len() as u64).to_be_bytes()[1..]);
+        } else {
+            data.push(0xff);
+        }
+        data.extend(body);
+        data
+    }
+
+    fn simple_tag(name: &str, value: &str, nested: &[u8]) -> Vec<u8> {
+        let mut body = element(TAG_NAME_ID, name.as_bytes(), true);
+        body.extend(element(TAG_STRING_ID, value.as_bytes(), true));
+        body.extend(nested);
+        element(SIMPLE_TAG_ID, &body, true)
+    }
+
+    /// Returns a file whose tags follow a cluster, and are indexed by a seek
+    /// head if `seek_head` is set.
+    fn sample(doc_type: &str, unknown_cluster: bool, seek_head: bool) -> Vec<u8> {
+        let mut data = element(
+            EBML_ID,
+            &element(DOC_TYPE_ID, doc_type.as_bytes(), true),
+            true,
+        );
+        let mut tag = simple_tag("RECORDING_LOCATION", "+37.3349-122.0090+010.000/", &[]);
+        tag.extend(simple_tag(
+            "ARTIST",
+            "Jane",
+            &simple_tag("URL", "https://x", &[]),
+        ));
+        let tags = element(TAGS_ID, &element(TAG_ID, &tag, true), true);
+
+        let mut info = element(TIMESTAMP_SCALE_ID, &[0x01, 0x86, 0xa0], true);
+        info.extend(element(DURATION_ID, &12340f32.to_be_bytes(), true));
+
+        let mut video = element(TRACK_NUMBER_ID, &[1], true);
+        video.extend(element(TRACK_TYPE_ID, &[1], true));
+        video.extend(element(CODEC_ID_ID, b"V_VP9", true));
+        video.extend(element(
+            DEFAULT_DURATION_ID,
+            &40_000_000u32.to_be_bytes(),
+            true,
+        ));
+        let mut size = element(PIXEL_WIDTH_ID, &[0x07, 0x80], true);
+        size.extend(element(PIXEL_HEIGHT_ID, &[0x04, 0x38], true));
+        // HLG, with a content light level
+        let mut colour = element(PRIMARIES_ID, &[9], true);
+        colour.extend(element(TRANSFER_CHARACTERISTICS_ID, &[18], true));
+        colour.extend(element(MATRIX_COEFFICIENTS_ID, &[9], true));
+        colour.extend(element(RANGE_ID, &[1], true));
+        colour.extend(element(MAX_CLL_ID, &[0x03, 0xe8], true));
+        let mut mastering = element(0x55D1, &0.708f64.to_be_bytes(), true);
+        mastering.extend(element(0x55D2, &0.292f64.to_be_bytes(), true));
+        mastering.extend(element(0x55D9, &1000f32.to_be_bytes(), true));
+        mastering.extend(element(0x55DA, &0.005f64.to_be_bytes(), true));
+        colour.extend(element(MASTERING_METADATA_ID, &mastering, true));
+        size.extend(element(COLOUR_ID, &colour, true));
+        video.extend(element(VIDEO_ID, &size, true));
+        let hdr10_plus = element(BLOCK_ADD_ID_TYPE_ID, &[4], true);
+        video.extend(element(BLOCK_ADDITION_MAPPING_ID, &hdr10_plus, true));
+        let mut audio = element(TRACK_NUMBER_ID, &[2], true);
+        audio.extend(element(TRACK_TYPE_ID, &[2], true));
+        audio.extend(element(CODEC_ID_ID, b"A_OPUS\0", true));
+        audio.extend(element(LANGUAGE_ID, b"fre", true));
+        let mut format = element(SAMPLING_FREQUENCY_ID, &48000f64.to_be_bytes(), true);
+        format.extend(element(CHANNELS_ID, &[2], true));
+        format.extend(element(BIT_DEPTH_ID, &[24], true));
+        audio.extend(element(AUDIO_ID, &format, true));
+        let mut tracks = element(TRACK_ENTRY_ID, &video, true);
+        tracks.extend(element(TRACK_ENTRY_ID, &audio, true));
+
+        let mut segment = element(INFO_ID, &info, true);
+        segment.extend(element(TRACKS_ID, &tracks, true));
+        segment.extend(element(CLUSTER_ID, &[0; 100], !unknown_cluster));
+        if seek_head {
+            // The seek head has the same size whatever the position
+            let seek_head = |pos: u64| {
+                let mut seek = element(SEEK_ID_ID, &TAGS_ID.to_be_bytes(), true);
+                seek.extend(element(SEEK_POSITION_ID, &pos.to_be_bytes(), true));
+                element(SEEK_HEAD_ID, &element(SEEK_ID, &seek, true), true)
+            };
+            let pos = (seek_head(0).len() + segment.len()) as u64;
+            segment.splice(0..0, seek_head(pos));
+        }
+        segment.extend(tags);
+        data.extend(element(SEGMENT_ID, &segment, false));
+        data
+    }
+
+    #[test]
+    fn matroska_tags() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let tags = parse_matroska_tags(Cursor::new(sample("webm", false, true))).unwrap();
+        assert_eq!(
+            tags,
+            [
+                (
+                    "RECORDING_LOCATION".to_owned(),
+                    "+37.3349-122.0090+010.000/".into()
+                ),
+                ("ARTIST".to_owned(), "Jane".into()),
+                ("ARTIST.URL".to_owned(), "https://x".into()),
+            ]
+        );
+        let gps = GPSInfo::from_iso6709(tags[0].1.as_str().unwrap()).unwrap();
+        assert_eq!(gps, GPSInfo::from_iso6709("+37.3349-122.009+10/").unwrap());
+
+        // The tags after a cluster of unknown size are found through the seek
+        // head, and only through it
+        let expected = tags;
+        let tags = parse_matroska_tags(Cursor::new(sample("matroska", true, true))).unwrap();
+        assert_eq!(tags, expected);
+        let tags = parse_matroska_tags(Cursor::new(sample("matroska", true, false))).unwrap();
+        assert_eq!(tags, []);
+    }
+
+    /// Records the ranges which are read.
+    struct RangeReader {
+        inner: Cursor<Vec<u8>>,
+        reads: Vec<std::ops::Range<u64>>,
+    }
+
+    impl Read for RangeReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let pos = self.inner.position();
+            let n = self.inner.read(buf)?;
+            self.reads.push(pos..pos + n as u64);
+            Ok(n)
+        }
+    }
+
+    impl Seek for RangeReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn matroska_clusters_not_read() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        for seek_head in [true, false] {
+            let data = sample("webm", false, seek_head);
+            let cluster = data
+                .windows(4)
+                .position(|x| x == CLUSTER_ID.to_be_bytes())
+                .unwrap() as u64;
+            // The body of the cluster, after its ID and size
+            let body = cluster + 12..cluster + 112;
+
+            let mut reader = RangeReader {
+                inner: Cursor::new(data),
+                reads: Vec::new(),
+            };
+            let tags = parse_matroska_tags(&mut reader).unwrap();
+            assert_eq!(tags.len(), 3);
+            let read = reader.reads.iter().filter(|x| !x.is_empty());
+            assert!(
+                read.clone()
+                    .all(|x| x.end <= body.start || x.start >= body.end),
+                "{:?}",
+                reader.reads
+            );
+            // Only the header of the cluster is read, once
+            let header = read.filter(|x| x.start < body.start && x.end > cluster);
+            assert_eq!(header.count(), 1);
+        }
+    }
+
+    #[test]
+    fn matroska_metadata() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let entries = parse_matroska_metadata(Cursor::new(sample("webm", false, true))).unwrap();
+        let entries = entries
+            .iter()
+            .skip(3)
+            .map(|x| format!("{x:?}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            entries,
+            r#"("duration", U32(1234))
+("width", U32(1920))
+("height", U32(1080))
+("video_codec", Text("V_VP9"))
+("frame_rate", F64(25.0))
+("audio_codec", Text("A_OPUS"))
+("audio_sample_rate", U32(48000))
+("audio_channels", U16(2))
+("audio_bit_depth", U16(24))"#
+        );
+    }
+
+    #[test]
+    fn matroska_tracks() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let tracks = parse_matroska_tracks(Cursor::new(sample("webm", false, true))).unwrap();
+        assert_eq!(tracks.len(), 2);
+        let (video, audio) = (&tracks[0], &tracks[1]);
+        assert_eq!((video.id, &video.kind), (Some(1), &TrackKind::Video));
+        assert_eq!(video.codec.as_deref(), Some("V_VP9"));
+        assert_eq!(
+            video.color,
+            Some(ColorInfo {
+                primaries: Some(9),
+                transfer: Some(18),
+                matrix: Some(9),
+                full_range: Some(false),
+                mastering_display: Some(MasteringDisplay {
+                    red: (35400, 14600),
+                    max_luminance: 10_000_000,
+                    min_luminance: 50,
+                    ..Default::default()
+                }),
+                content_light: Some(ContentLightLevel {
+                    max_cll: 1000,
+                    max_fall: 0,
+                }),
+                hdr10_plus: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            video.color.as_ref().unwrap().hdr_format(),
+            Some(crate::HdrFormat::Hlg)
+        );
+
+        assert_eq!((audio.id, &audio.kind), (Some(2), &TrackKind::Audio));
+        assert_eq!(audio.language.as_deref(), Some("fre"));
+        assert_eq!(audio.color, None);
+    }
+
+    #[test]
+    fn matroska_dolby_vision() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // Profile 5, without any `Colour` element
+        let mut mapping = element(BLOCK_ADD_ID_TYPE_ID, b"dvcC", true);
+        mapping.extend(element(
+            BLOCK_ADD_ID_EXTRA_DATA_ID,
+            &[1, 0, 0x0a, 0x3d, 0, 0, 0, 0],
+            true,
+        ));
+        let mut video = element(TRACK_TYPE_ID, &[1], true);
+        video.extend(element(CODEC_ID_ID, b"V_MPEGH/ISO/HEVC", true));
+        // Main 10, level 5.1, 4:2:0
+        let mut hvcc = [0; 23];
+        hvcc[..2].copy_from_slice(&[1, 2]);
+        hvcc[12..18].copy_from_slice(&[153, 0xf0, 0, 0xfc, 0xfd, 0xfa]);
+        video.extend(element(CODEC_PRIVATE_ID, &hvcc, true));
+        video.extend(element(BLOCK_ADDITION_MAPPING_ID, &mapping, true));
+        let tracks = element(TRACKS_ID, &element(TRACK_ENTRY_ID, &video, true), true);
+        let mut data = element(EBML_ID, &element(DOC_TYPE_ID, b"matroska", true), true);
+        data.extend(element(SEGMENT_ID, &tracks, true));
+
+        let tracks = parse_matroska_tracks(Cursor::new(data)).unwrap();
+        let color = tracks[0].color.as_ref().unwrap();
+        assert_eq!(
+            color.dolby_vision,
+            Some(DolbyVision {
+                profile: 5,
+                level: 7,
+                enhancement_layer: false,
+                compatibility_id: 0,
+            })
+        );
+        assert_eq!(color.hdr_format(), Some(crate::HdrFormat::DolbyVision));
+
+        let config = tracks[0].codec_config.as_ref().unwrap();
+        assert_eq!((config.profile, config.level), (2, 153));
+        assert_eq!(config.bit_depth, Some(10));
+    }
+
+    #[test]
+    fn matroska_audio_only() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // HE-AAC, whose output sample rate is twice the stored one
+        let mut format = element(SAMPLING_FREQUENCY_ID, &24000f32.to_be_bytes(), true);
+        format.extend(element(
+            OUTPUT_SAMPLING_FREQUENCY_ID,
+            &48000f32.to_be_bytes(),
+            true,
+        ));
+        let mut audio = element(TRACK_TYPE_ID, &[2], true);
+        audio.extend(element(CODEC_ID_ID, b"A_AAC", true));
+        audio.extend(element(AUDIO_ID, &format, true));
+        let tracks = element(TRACKS_ID, &element(TRACK_ENTRY_ID, &audio, true), true);
+        let mut data = element(EBML_ID, &element(DOC_TYPE_ID, b"matroska", true), true);
+        data.extend(element(SEGMENT_ID, &tracks, true));
+
+        let entries = parse_matroska_metadata(Cursor::new(data)).unwrap();
+        assert_eq!(
+            entries,
+            [
+                ("audio_codec".to_owned(), "A_AAC".into()),
+                ("audio_sample_rate".to_owned(), 48000u32.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn matroska_media() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = sample("webm", true, true);
+        assert_eq!(detect_format(&data[..]), Some(MediaType::WebM));
+        let mkv = sample("matroska", false, false);
+        assert_eq!(detect_format(&mkv[..]), Some(MediaType::Matroska));
+        assert_eq!(detect_format(&sample("other", false, false)[..]), None);
+
+        let parser = MediaParser::default();
+        let Some(MediaInfo::Track(iter)) = parser.parse_media(Cursor::new(&data)).unwrap() else {
+            panic!("not a track");
+        };
+        assert_eq!(iter.tracks().len(), 2);
+        assert_eq!(iter.get("width").unwrap().unwrap(), 1920u32.into());
+        let entries = iter.map(|(k, v)| (k, v.unwrap())).collect::<Vec<_>>();
+        let gps = GPSInfo::from_quicktime(&entries).unwrap().unwrap();
+        assert_eq!(gps, GPSInfo::from_iso6709("+37.3349-122.009+10/").unwrap());
+
+        let summary = parser.probe(Cursor::new(&data)).unwrap();
+        assert_eq!(summary.format, FileFormat::Matroska);
+        assert_eq!(summary.dimensions, Some((1920, 1080)));
+        assert_eq!(summary.duration.unwrap().num_milliseconds(), 1234);
+        assert!(summary.has_gps);
+
+        let caps = parser.capabilities(Cursor::new(&data)).unwrap();
+        assert_eq!(caps, Capabilities::TRACK | Capabilities::GPS);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn matroska_media_async() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = sample("webm", false, true);
+        let parser = MediaParser::default();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let info = runtime.block_on(parser.parse_media_async(Cursor::new(&data)));
+        let Some(MediaInfo::Track(iter)) = info.unwrap() else {
+            panic!("not a track");
+        };
+        let Some(MediaInfo::Track(expected)) = parser.parse_media(Cursor::new(&data)).unwrap()
+        else {
+            panic!("not a track");
+        };
+        assert_eq!(iter.tracks(), expected.tracks());
+        assert_eq!(
+            format!("{:?}", iter.collect::<Vec<_>>()),
+            format!("{:?}", expected.collect::<Vec<_>>())
+        );
+
+        let summary = runtime.block_on(parser.probe_async(Cursor::new(&data)));
+        assert_eq!(summary.unwrap(), parser.probe(Cursor::new(&data)).unwrap());
+
+        // The tags after a cluster of unknown size are found through the seek
+        // head as well
+        let data = sample("matroska", true, true);
+        let summary = runtime.block_on(parser.probe_async(Cursor::new(&data)));
+        assert!(summary.unwrap().has_gps);
+    }
+
+    #[test]
+    fn matroska_nesting_depth() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // `levels` nested tags, the first one being 4 levels deep
+        let nested = |levels: usize| {
+            let tag = (0..levels).fold(Vec::new(), |tag, _| simple_tag("A", "x", &tag));
+            let tags = element(TAGS_ID, &element(TAG_ID, &tag, true), true);
+            let mut data = element(EBML_ID, &element(DOC_TYPE_ID, b"matroska", true), true);
+            data.extend(element(SEGMENT_ID, &tags, true));
+            data
+        };
+        let parser = MediaParser::builder().max_nesting_depth(8).build();
+
+        let data = nested(5);
+        let Some(MediaInfo::Track(iter)) = parser.parse_media(Cursor::new(&data)).unwrap() else {
+            panic!("not a track");
+        };
+        assert_eq!(iter.last().unwrap().0, "A.A.A.A.A");
+
+        let data = nested(6);
+        let res = parser.parse_media(Cursor::new(&data));
+        assert!(matches!(
+            res,
+            Err(crate::Error::LimitExceeded {
+                limit: crate::Limit::NestingDepth(8)
+            })
+        ));
+        // Within the default limit
+        assert_eq!(parse_matroska_tags(Cursor::new(&data)).unwrap().len(), 6);
+
+        // The names of nested tags are bounded as well
+        let parser = MediaParser::builder().max_string_length(4).build();
+        let res = parser.parse_media(Cursor::new(nested(3)));
+        assert!(matches!(
+            res,
+            Err(crate::Error::LimitExceeded {
+                limit: crate::Limit::StringLength(4)
+            })
+        ));
+    }
+
+    #[test]
+    fn matroska_deeply_nested_tags() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // 200,000 nested tags (4 MB), written from the outermost one, whose
+        // bodies end with the next one
+        const LEVELS: u64 = 200_000;
+        let mut fields = element(TAG_NAME_ID, b"A", true);
+        fields.extend(element(TAG_STRING_ID, b"x", true));
+        let level = 2 + 8 + fields.len() as u64;
+        let mut tag = Vec::with_capacity((LEVELS * level) as usize);
+        for i in 1..=LEVELS {
+            tag.extend(SIMPLE_TAG_ID.to_be_bytes()[2..].iter());
+            tag.push(0x01);
+            let size = fields.len() as u64 + (LEVELS - i) * level;
+            tag.extend(&size.to_be_bytes()[1..]);
+            tag.extend(&fields);
+        }
+        let tags = element(TAGS_ID, &element(TAG_ID, &tag, true), true);
+        let mut data = element(EBML_ID, &element(DOC_TYPE_ID, b"webm", true), true);
+        data.extend(element(SEGMENT_ID, &tags, true));
+
+        let res = parse_matroska_tags(Cursor::new(&data));
+        assert!(matches!(
+            res,
+            Err(crate::Error::LimitExceeded {
+                limit: crate::Limit::NestingDepth(32)
+            })
+        ));
+        let res = MediaParser::default().parse_media(Cursor::new(&data));
+        assert!(matches!(res, Err(crate::Error::LimitExceeded { .. })));
+    }
+
+    #[test]
+    fn matroska_unsupported() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let res = parse_matroska_tags(Cursor::new(sample("other", false, false)));
+        assert!(matches!(res, Err(crate::Error::UnsupportedFormat { .. })));
+        let res = parse_matroska_tags(open_sample("meta.mp4").unwrap());
+        assert!(matches!(res, Err(crate::Error::UnsupportedFormat { .. })));
+    }
+}
@@ -1,22 +1,33 @@
 use std::{
-    cmp,
     io::{Read, Seek},
     ops::Range,
 };
 
-use chrono::DateTime;
+#[cfg(any(feature = "async", feature = "futures-io"))]
+use crate::async_io::AsyncSeekSource;
+use chrono::{DateTime, Datelike, Duration, FixedOffset};
 use nom::{bytes::streaming, IResult};
 use thiserror::Error;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncRead, AsyncSeek};
 
 use crate::{
     bbox::{
-        find_box, parse_video_tkhd_in_moov, travel_header, IlstBox, KeysBox, MvhdBox, ParseBox,
+        find_box, parse_traks_in_moov, travel_header, BoxHeader, KeysBox, MvhdBox, ParseBox,
+        RawIlstItem, TrakBox,
     },
+    event::ParseEvent,
     file::{check_qt_mp4, FileFormat},
     input::Input,
-    EntryValue,
+    media::MIN_GROW_SIZE,
+    slice::SubsliceRange,
+    CaptureTimeSource, CodecConfig, ColorInfo, EntryValue, Limit, MediaParser, Warning,
 };
 
+use compact::MoovReader;
+
+mod compact;
+
 /// Analyze the byte stream in the `reader` as a MOV/MP4 file, attempting to
 /// extract any possible metadata it may contain, and return it in the form of
 /// key-value pairs.
@@ -47,30 +58,543 @@ use crate::{
 /// ("com.apple.quicktime.location.ISO6709", Text("+27.1281+100.2508+000.000/"))
 /// ("com.apple.quicktime.creationdate", Time(2019-02-12T15:27:12+08:00))
 /// ("duration", U32(500))
+/// ("media_duration", U32(510))
 /// ("width", U32(720))
-/// ("height", U32(1280))"#,
+/// ("height", U32(1280))
+/// ("rotation", U32(0))
+/// ("video_codec", Text("avc1"))
+/// ("frame_rate", F64(60.0))
+/// ("bitrate", U64(12250672))
+/// ("audio_codec", Text("mp4a"))
+/// ("audio_sample_rate", U32(44100))
+/// ("audio_channels", U16(1))"#,
 /// );
 /// ```
 #[tracing::instrument(skip_all)]
 pub fn parse_metadata<R: Read + Seek>(reader: R) -> crate::Result<Vec<(String, EntryValue)>> {
-    let (ft, moov_body) = extract_moov_body(reader)?;
+    Ok(valid_entries(parse_metadata_iter(reader)?))
+}
 
-    let (_, mut entries) = match parse_moov_body(&moov_body) {
-        Ok((remain, Some(entries))) => (remain, entries),
-        Ok((remain, None)) => (remain, Vec::new()),
-        Err(_) => {
-            return Err("invalid moov body".into());
+/// `async` version of [`parse_metadata`]: the `mdat` atom (and the other
+/// atoms before `moov`) is skipped by seeking, rather than read.
+///
+/// # Usage
+///
+/// ```rust
+/// # #[cfg(feature = "async")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use nom_exif::*;
+///
+/// let f = tokio::fs::File::open("./testdata/meta.mp4").await.unwrap();
+/// let entries = parse_metadata_async(f).await.unwrap();
+/// assert_eq!(entries[0].0, "com.apple.quicktime.location.ISO6709");
+/// # });
+/// ```
+#[cfg(feature = "async")]
+pub async fn parse_metadata_async<R>(reader: R) -> crate::Result<Vec<(String, EntryValue)>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    Ok(valid_entries(parse_metadata_iter_async(reader).await?))
+}
+
+/// Collects the entries of `iter`, invalid ones are skipped.
+pub(crate) fn valid_entries(iter: MetadataIter) -> Vec<(String, EntryValue)> {
+    iter.filter_map(|(key, res)| match res {
+        Ok(value) => Some((key, value)),
+        Err(e) => {
+            tracing::warn!(?key, ?e, "Skip invalid metadata entry.");
+            None
         }
-    };
+    })
+    .collect()
+}
+
+/// Like [`parse_metadata`], but returns a [`MetadataIter`], which decodes the
+/// entries lazily, and reports errors per entry (like [`ExifIter`](crate::ExifIter)
+/// does) instead of skipping the invalid ones.
+///
+/// Only the `moov` atom is read, as with [`parse_metadata`].
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+/// let iter = parse_metadata_iter(f).unwrap();
+///
+/// for (key, res) in iter {
+///     match res {
+///         Ok(value) => println!("{key} => {value}"),
+///         Err(e) => println!("{key}: {e}"),
+///     }
+/// }
+/// ```
+#[tracing::instrument(skip_all)]
+pub fn parse_metadata_iter<R: Read + Seek>(reader: R) -> crate::Result<MetadataIter> {
+    MediaParser::default().parse_metadata_iter(reader)
+}
+
+/// `async` version of [`parse_metadata_iter`]: the `mdat` atom (and the
+/// other atoms before `moov`) is skipped by seeking, rather than read.
+#[cfg(feature = "async")]
+pub async fn parse_metadata_iter_async<R>(reader: R) -> crate::Result<MetadataIter>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    MediaParser::default()
+        .parse_metadata_iter_async(reader)
+        .await
+}
+
+/// [`parse_metadata_iter`], with the buffer sizes limited by `parser`.
+pub(crate) fn read_metadata_iter<R: Read + Seek>(
+    reader: R,
+    parser: &MediaParser,
+) -> crate::Result<MetadataIter> {
+    let (ft, moov_body, skipped) = extract_moov_body(reader, parser)?;
+    new_metadata_iter(ft, moov_body, skipped, parser)
+}
+
+/// Like [`read_metadata_iter`], for readers which can't seek, see
+/// [`MediaParser::parse_metadata_unseekable`]. If `resume` is set, `reader`
+/// starts at the given offset of a file of the given format, see
+/// [`MediaParser::resume_metadata_unseekable`].
+pub(crate) fn read_metadata_unseekable<R: Read>(
+    reader: R,
+    parser: &MediaParser,
+    resume: Option<(FileFormat, u64)>,
+) -> crate::Result<MetadataIter> {
+    let (ft, moov_body, skipped) =
+        extract_moov_body_impl(reader, parser, resume, None, |reader, format, offset, n| {
+            match parser.max_discarded_bytes {
+                Some(max) if n > max => Err(crate::Error::SkipRequired {
+                    format,
+                    offset,
+                    remaining: n,
+                }),
+                _ => {
+                    let discarded = std::io::copy(&mut reader.take(n), &mut std::io::sink())?;
+                    if discarded < n {
+                        // usize::MAX is larger than any box we'd read anyway
+                        let needed = usize::try_from(n - discarded).unwrap_or(usize::MAX);
+                        Err(crate::Error::Truncated {
+                            needed: Some(needed),
+                        })?;
+                    }
+                    Ok(())
+                }
+            }
+        })?;
+    new_metadata_iter(ft, moov_body, skipped, parser)
+}
+
+pub(crate) fn new_metadata_iter(
+    ft: FileFormat,
+    moov_body: Input<'static>,
+    skipped: u64,
+    parser: &MediaParser,
+) -> crate::Result<MetadataIter> {
+    let mut iter = MetadataIter::new(ft, moov_body, skipped)?;
+    iter.max_entries = parser.max_entries.unwrap_or(usize::MAX);
+    iter.max_string_length = parser.max_string_length.unwrap_or(usize::MAX);
+    Ok(iter)
+}
+
+/// Like [`parse_metadata`], with the limits of `parser`, but entries and
+/// atoms which can't be parsed are skipped and reported as warnings.
+pub(crate) fn read_metadata_lenient<R: Read + Seek>(
+    reader: R,
+    parser: &MediaParser,
+) -> crate::Result<(MetadataEntries, Vec<Warning>)> {
+    let (ft, moov_body, skipped) = extract_moov_body(reader, parser)?;
+    let (mut iter, err) = MetadataIter::new_lenient(ft, moov_body, skipped);
+    iter.max_entries = parser.max_entries.unwrap_or(usize::MAX);
+    iter.max_string_length = parser.max_string_length.unwrap_or(usize::MAX);
+
+    let mut warnings = Vec::new();
+    if let Some(e) = err {
+        warnings.push(Warning::skipped("meta".to_owned(), e));
+    }
+    let mut entries = Vec::new();
+    for (name, value) in iter {
+        match value {
+            Ok(v) => entries.push((name, v)),
+            Err(e) => warnings.push(Warning::skipped(name, e)),
+        }
+    }
+    Ok((entries, warnings))
+}
+
+pub(crate) const LOCATION_KEY: &str = "com.apple.quicktime.location.ISO6709";
+pub(crate) const ACCURACY_KEY: &str = "com.apple.quicktime.location.accuracy.horizontal";
+pub(crate) const CREATIONDATE_KEY: &str = "com.apple.quicktime.creationdate";
+pub(crate) const MAKE_KEY: &str = "com.apple.quicktime.make";
+pub(crate) const MODEL_KEY: &str = "com.apple.quicktime.model";
+
+/// Keys of the entries derived from atoms other than `moov/meta`, which
+/// describe the tracks rather than the metadata items.
+pub(crate) const TRACK_KEYS: [&str; 12] = [
+    "duration",
+    "media_duration",
+    "width",
+    "height",
+    "rotation",
+    "flip",
+    "video_codec",
+    "frame_rate",
+    "bitrate",
+    "audio_codec",
+    "audio_sample_rate",
+    "audio_channels",
+];
+
+type MetaItems = (Vec<String>, Range<usize>);
+
+/// Metadata entries, as returned by [`parse_metadata`].
+pub(crate) type MetadataEntries = Vec<(String, EntryValue)>;
+
+/// An iterator over the metadata entries of a QuickTime/MP4 file, created by
+/// [`parse_metadata_iter`], or of a Matroska file, created by
+/// [`parse_media`](crate::parse_media) (the entries of
+/// [`parse_matroska_metadata`](crate::parse_matroska_metadata)).
+///
+/// All keys of the `moov/meta` atom are yielded, including vendor-specific
+/// ones, followed by the entries derived from other atoms (e.g. `duration`,
+/// `width` and `height`). See [`parse_metadata`] for details.
+///
+/// `duration` is the duration of the movie once the edit lists of its tracks
+/// are applied, e.g. of a clip trimmed on a phone, and `media_duration` is
+/// the duration of the longest track's media, which includes the parts
+/// edited out.
+///
+/// `rotation` is the clockwise rotation of the video track in degrees, which
+/// players apply when displaying it, e.g. 90 for videos recorded in portrait
+/// by phones. Mirrored videos also have a `flip` entry (`"horizontal"`),
+/// the flip being applied before the rotation.
+///
+/// Items are `(key, Result<EntryValue>)` pairs. A value which can't be
+/// decoded (e.g. an unsupported data type) only fails its own entry.
+#[derive(Debug, Clone)]
+pub struct MetadataIter {
+    format: FileFormat,
+    moov_body: Input<'static>,
+    /// Position of `moov_body` in the file.
+    offset: u64,
+    keys: Vec<String>,
+    /// Range of all the ilst items in `moov_body`.
+    ilst: Range<usize>,
+    /// Range of the remaining ilst items in `moov_body`.
+    items: Range<usize>,
+    has_location: bool,
+    has_creationdate: bool,
+    entries: usize,
+    max_entries: usize,
+    max_string_length: usize,
+    /// Entries derived from other atoms, which are yielded after the ilst
+    /// items.
+    tail: Option<std::vec::IntoIter<(String, EntryValue)>>,
+    /// The entries and tracks of a Matroska file, which has no `moov` atom.
+    matroska: Option<(MetadataEntries, Vec<TrackMeta>)>,
+}
+
+impl MetadataIter {
+    /// Returns a description of each track (`moov/trak` atom) of the file,
+    /// in file order, e.g. to tell apart the audio tracks of a multi-language
+    /// video, or to find subtitles.
+    ///
+    /// Unlike the entries, which only describe the first video and audio
+    /// tracks, every track is returned.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    /// let tracks = parse_metadata_iter(f).unwrap().tracks();
+    ///
+    /// assert_eq!(tracks.len(), 4);
+    /// assert_eq!(tracks[0].kind, TrackKind::Video);
+    /// assert_eq!(tracks[1].kind, TrackKind::Audio);
+    /// assert_eq!(tracks[0].codec.as_deref(), Some("avc1"));
+    /// assert_eq!(tracks[1].duration.unwrap().num_milliseconds(), 510);
+    /// ```
+    pub fn tracks(&self) -> Vec<TrackMeta> {
+        if let Some((_, tracks)) = &self.matroska {
+            return tracks.clone();
+        }
+        let time_scale = find_box(&self.moov_body[..], "mvhd")
+            .ok()
+            .and_then(|(_, b)| MvhdBox::parse_box(b?.data).ok())
+            .map(|(_, mvhd)| mvhd.time_scale())
+            .filter(|x| *x > 0);
+        parse_traks_in_moov(&self.moov_body[..])
+            .into_iter()
+            .map(|trak| TrackMeta {
+                id: trak.tkhd.as_ref().map(|x| x.track_id),
+                kind: TrackKind::from_handler_type(&trak.handler_type),
+                codec: trak.codec,
+                duration: trak.mdhd.as_ref().and_then(|x| x.duration()),
+                presentation_duration: trak.edit_duration.zip(time_scale).and_then(|(d, scale)| {
+                    let ms = d as u128 * 1000 / scale as u128;
+                    Some(Duration::milliseconds(ms.try_into().ok()?))
+                }),
+                language: trak.mdhd.as_ref().and_then(|x| x.language()),
+                // The media times, or the track ones if they aren't set
+                creation_time: trak
+                    .mdhd
+                    .as_ref()
+                    .and_then(|x| x.creation_time())
+                    .or_else(|| trak.tkhd.as_ref()?.creation_time()),
+                modification_time: trak
+                    .mdhd
+                    .as_ref()
+                    .and_then(|x| x.modification_time())
+                    .or_else(|| trak.tkhd.as_ref()?.modification_time()),
+                color: trak.color,
+                codec_config: trak.codec_config,
+            })
+            .collect()
+    }
+
+    /// Returns the value of the entry of `key`, e.g. `"duration"` or
+    /// `"com.apple.quicktime.make"`, or `None` if there isn't any.
+    ///
+    /// Only what's needed to get this value is parsed: the other items of
+    /// the `moov/meta` atom aren't decoded, so it's cheaper than iterating
+    /// over all the entries when only a few of them are needed. The iterator is left as is, and
+    /// the maximum number of entries (see
+    /// [`MediaParserBuilder::max_entries`](crate::MediaParserBuilder::max_entries))
+    /// doesn't apply.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+    /// let iter = parse_metadata_iter(f).unwrap();
+    ///
+    /// let duration = iter.get("duration").unwrap().unwrap();
+    /// assert_eq!(duration.to_u32(), Some(500));
+    /// let model = iter.get("com.apple.quicktime.model").unwrap().unwrap();
+    /// assert_eq!(model.to_string(), "iPhone X");
+    /// assert!(iter.get("com.apple.quicktime.author").is_none());
+    /// ```
+    pub fn get(&self, key: &str) -> Option<crate::Result<EntryValue>> {
+        if let Some(res) = self.find_item(key) {
+            return Some(res);
+        }
+
+        let mvhd = || {
+            let (_, bbox) = find_box(&self.moov_body[..], "mvhd").ok()?;
+            MvhdBox::parse_box(bbox?.data).ok().map(|(_, mvhd)| mvhd)
+        };
+        let value = match key {
+            CREATIONDATE_KEY if self.matroska.is_none() => {
+                mvhd().map(|x| EntryValue::Time(x.creation_time()))
+            }
+            _ => self
+                .tail_entries()
+                .into_iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v),
+        };
+        value.map(Ok)
+    }
+
+    /// Returns the creation date of the file: the
+    /// `com.apple.quicktime.creationdate` item, or the creation time of the
+    /// movie header if there isn't one.
+    pub(crate) fn into_capture_time(
+        mut self,
+    ) -> Option<(DateTime<FixedOffset>, CaptureTimeSource)> {
+        while !self.items.is_empty() {
+            if let (key, Ok(EntryValue::Time(t))) = self.next_item() {
+                if key == CREATIONDATE_KEY {
+                    return Some((t, CaptureTimeSource::QuickTimeCreationDate));
+                }
+            }
+        }
+
+        let (_, bbox) = find_box(&self.moov_body[..], "mvhd").ok()?;
+        let (_, mvhd) = MvhdBox::parse_box(bbox?.data).ok()?;
+        let t = mvhd.creation_time();
+        // 0 means the time isn't set
+        (t.year() > 1904).then_some((t, CaptureTimeSource::MovieHeader))
+    }
+
+    /// `skipped` is the number of bytes of the file which precede
+    /// `moov_body`, but are not in its buffer.
+    fn new(
+        format: FileFormat,
+        moov_body: Input<'static>,
+        skipped: u64,
+    ) -> crate::Result<MetadataIter> {
+        match Self::new_lenient(format, moov_body, skipped) {
+            (iter, None) => Ok(iter),
+            (_, Some(e)) => Err(e),
+        }
+    }
+
+    /// Like [`Self::new`], but a corrupt `moov/meta` atom is skipped (only
+    /// the entries derived from other atoms are yielded), and its error is
+    /// returned along with the iterator.
+    fn new_lenient(
+        format: FileFormat,
+        moov_body: Input<'static>,
+        skipped: u64,
+    ) -> (MetadataIter, Option<crate::Error>) {
+        let offset = skipped + moov_body.range.start as u64;
+        let (keys, items, err) = match Self::parse_meta(&moov_body) {
+            Ok((_, Some((keys, items)))) => (keys, items, None),
+            Ok((_, None)) => (Vec::new(), 0..0, None),
+            Err(_) => {
+                let err = crate::Error::CorruptBox {
+                    path: "moov/meta".to_owned(),
+                    offset,
+                };
+                (Vec::new(), 0..0, Some(err))
+            }
+        };
+        let iter = MetadataIter {
+            format,
+            moov_body,
+            offset,
+            keys,
+            ilst: items.clone(),
+            items,
+            has_location: false,
+            has_creationdate: false,
+            entries: 0,
+            max_entries: usize::MAX,
+            max_string_length: usize::MAX,
+            tail: None,
+            matroska: None,
+        };
+        (iter, err)
+    }
+
+    /// Returns an iterator over the `entries` of a Matroska file, whose
+    /// tracks are `tracks`.
+    pub(crate) fn from_matroska(entries: MetadataEntries, tracks: Vec<TrackMeta>) -> MetadataIter {
+        let (mut iter, _) = Self::new_lenient(FileFormat::Matroska, Input::from_vec(Vec::new()), 0);
+        iter.matroska = Some((entries, tracks));
+        iter
+    }
+
+    /// Returns the keys, and the range of the ilst items in `moov_body`.
+    fn parse_meta(moov_body: &[u8]) -> IResult<&[u8], Option<MetaItems>> {
+        let (remain, Some(meta)) = find_box(moov_body, "meta")? else {
+            return Ok((moov_body, None));
+        };
+        let (_, Some(keys)) = find_box(meta.body_data(), "keys")? else {
+            return Ok((remain, None));
+        };
+        let (_, Some(ilst)) = find_box(meta.body_data(), "ilst")? else {
+            return Ok((remain, None));
+        };
+
+        let (_, keys) = KeysBox::parse_box(keys.data)?;
+        let keys = keys.entries.into_iter().map(|k| k.key).collect();
+        let items = SubsliceRange::subslice_range(moov_body, ilst.body_data()).unwrap();
+        Ok((remain, Some((keys, items))))
+    }
+
+    fn next_item(&mut self) -> (String, crate::Result<EntryValue>) {
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            // Stop iterating
+            self.items = 0..0;
+            self.tail = Some(Vec::new().into_iter());
+            let limit = Limit::Entries(self.max_entries);
+            return (
+                "ilst".to_owned(),
+                Err(crate::Error::LimitExceeded { limit }),
+            );
+        }
+
+        let input = &self.moov_body[self.items.clone()];
+        let Ok((remain, item)) = RawIlstItem::parse(input) else {
+            let err = crate::Error::CorruptBox {
+                path: "moov/meta/ilst".to_owned(),
+                offset: self.offset + self.items.start as u64,
+            };
+            self.items = 0..0;
+            return ("ilst".to_owned(), Err(err));
+        };
+        self.items.start = self.items.end - remain.len();
+
+        let Some(key) = item
+            .index
+            .checked_sub(1)
+            .and_then(|i| self.keys.get(i as usize))
+        else {
+            let msg = format!(
+                "invalid ilst item; key index {} is out of range",
+                item.index
+            );
+            return (format!("#{}", item.index), Err(msg.into()));
+        };
+        let key = key.to_owned();
+
+        let value = match self.item_value(&key, &item) {
+            Ok(v) => v,
+            Err(e) => return (key, Err(e)),
+        };
+        self.has_location |= key == LOCATION_KEY;
+        self.has_creationdate |= key == CREATIONDATE_KEY;
+        (key, Ok(value))
+    }
+
+    /// Decodes the value of an ilst item.
+    fn item_value(&self, key: &str, item: &RawIlstItem) -> crate::Result<EntryValue> {
+        // UTF-8 text
+        if item.type_code == 1 && item.data.len() > self.max_string_length {
+            let limit = Limit::StringLength(self.max_string_length);
+            return Err(crate::Error::LimitExceeded { limit });
+        }
+
+        match item.value()? {
+            EntryValue::Text(s) if key == CREATIONDATE_KEY => {
+                match DateTime::parse_from_str(&s, "%+") {
+                    Ok(t) => Ok(EntryValue::Time(t)),
+                    Err(_) => Ok(EntryValue::Text(s)),
+                }
+            }
+            v => Ok(v),
+        }
+    }
+
+    /// Returns the value of the ilst item of `key`, without decoding the
+    /// other items.
+    fn find_item(&self, key: &str) -> Option<crate::Result<EntryValue>> {
+        let index = self.keys.iter().position(|x| x == key)? as u32 + 1;
+        let mut input = &self.moov_body[self.ilst.clone()];
+        while !input.is_empty() {
+            let (remain, item) = RawIlstItem::parse(input).ok()?;
+            if item.index == index {
+                return Some(self.item_value(key, &item));
+            }
+            input = remain;
+        }
+        None
+    }
 
-    if ft == FileFormat::MP4 {
-        const LOCATION_KEY: &str = "com.apple.quicktime.location.ISO6709";
+    /// Entries derived from atoms other than `moov/meta`.
+    fn tail_entries(&self) -> Vec<(String, EntryValue)> {
+        if let Some((entries, _)) = &self.matroska {
+            return entries.clone();
+        }
+        let moov_body = &self.moov_body[..];
+        let mut entries = Vec::new();
 
-        if !entries.iter().any(|x| x.0 == LOCATION_KEY) {
+        if self.format == FileFormat::MP4 && !self.has_location {
             // Try to parse GPS location for MP4 files. For mp4 files, Android
             // phones store GPS info in the `moov/udta/©xyz` atom.
-            let (_, bbox) = find_box(&moov_body, "udta/©xyz")?;
-            if let Some(bbox) = bbox {
+            if let Ok((_, Some(bbox))) = find_box(moov_body, "udta/©xyz") {
                 if bbox.body_data().len() <= 4 {
                     tracing::error!("Box body is too small.");
                 } else {
@@ -82,40 +606,175 @@ pub fn parse_metadata<R: Read + Seek>(reader: R) -> crate::Result<Vec<(String, E
                 }
             }
         }
-    }
 
-    const CREATIONDATE_KEY: &str = "com.apple.quicktime.creationdate";
-    if let Some(pos) = entries.iter().position(|x| x.0 == CREATIONDATE_KEY) {
-        if let EntryValue::Text(ref s) = entries[pos].1 {
-            if let Ok(t) = DateTime::parse_from_str(s, "%+") {
-                let _ = std::mem::replace(
-                    &mut entries[pos],
-                    (CREATIONDATE_KEY.to_string(), EntryValue::Time(t)),
-                );
+        let traks = parse_traks_in_moov(moov_body);
+        let media_ms = traks
+            .iter()
+            .filter_map(|x| x.mdhd.as_ref()?.duration())
+            .map(|x| x.num_milliseconds() as u32)
+            .max();
+        let mut duration_ms = None;
+        if let Ok((_, Some(bbox))) = find_box(moov_body, "mvhd") {
+            if let Ok((_, mvhd)) = MvhdBox::parse_box(bbox.data) {
+                let duration = presentation_ms(&mvhd, &traks);
+                duration_ms = Some(duration);
+                entries.push(("duration".to_owned(), duration.into()));
+                if let Some(ms) = media_ms {
+                    entries.push(("media_duration".to_owned(), ms.into()));
+                }
+
+                if !self.has_creationdate {
+                    entries.push((
+                        CREATIONDATE_KEY.to_owned(),
+                        EntryValue::Time(mvhd.creation_time()),
+                    ));
+                }
             }
         }
-    }
 
-    let (_, bbox) = find_box(&moov_body, "mvhd")?;
-    if let Some(bbox) = bbox {
-        if let Ok((_, mvhd)) = MvhdBox::parse_box(bbox.data) {
-            entries.push(("duration".to_owned(), mvhd.duration_ms().into()));
+        let video = traks
+            .iter()
+            .find(|x| x.handler_type == "vide" && x.tkhd.is_some());
+        if let Some(trak) = video {
+            if let Some(tkhd) = &trak.tkhd {
+                entries.push(("width".to_owned(), tkhd.width.into()));
+                entries.push(("height".to_owned(), tkhd.height.into()));
+                entries.push(("rotation".to_owned(), tkhd.rotation().into()));
+                if tkhd.is_flipped() {
+                    entries.push(("flip".to_owned(), "horizontal".into()));
+                }
+            }
+            if let Some(codec) = &trak.codec {
+                entries.push(("video_codec".to_owned(), codec.into()));
+            }
+            if let Some(frame_rate) = trak.frame_rate() {
+                entries.push(("frame_rate".to_owned(), frame_rate.into()));
+            }
+        }
+
+        // Average bitrate of all tracks, in bits per second
+        let bytes: u64 = traks.iter().map(|x| x.sample_bytes).sum();
+        if let Some(ms) = duration_ms.filter(|ms| *ms > 0 && bytes > 0) {
+            let bitrate = (bytes as f64 * 8.0 * 1000.0 / ms as f64).round() as u64;
+            entries.push(("bitrate".to_owned(), bitrate.into()));
+        }
 
-            if !entries.iter().any(|x| x.0 == CREATIONDATE_KEY) {
-                entries.push((
-                    CREATIONDATE_KEY.to_owned(),
-                    EntryValue::Time(mvhd.creation_time()),
-                ));
+        if let Some(trak) = traks.iter().find(|x| x.handler_type == "soun") {
+            if let Some(codec) = &trak.codec {
+                entries.push(("audio_codec".to_owned(), codec.into()));
+            }
+            if let Some(rate) = trak.audio_sample_rate {
+                entries.push(("audio_sample_rate".to_owned(), rate.into()));
+            }
+            if let Some(channels) = trak.audio_channels {
+                entries.push(("audio_channels".to_owned(), channels.into()));
             }
         }
+
+        entries
     }
+}
 
-    if let Ok(Some(tkhd)) = parse_video_tkhd_in_moov(&moov_body) {
-        entries.push(("width".to_owned(), tkhd.width.into()));
-        entries.push(("height".to_owned(), tkhd.height.into()));
+/// Returns the duration of the movie in milliseconds once the edit lists of
+/// its tracks are applied, or the `mvhd` duration if there is none.
+fn presentation_ms(mvhd: &MvhdBox, traks: &[TrakBox]) -> u32 {
+    let scale = mvhd.time_scale() as u128;
+    if scale == 0 || traks.iter().all(|x| x.edit_duration.is_none()) {
+        return mvhd.duration_ms();
+    }
+    // Tracks without edit list are presented in full
+    let durations = traks.iter().filter_map(|trak| match trak.edit_duration {
+        Some(d) => Some(d as u128 * 1000 / scale),
+        None => Some(trak.mdhd.as_ref()?.duration()?.num_milliseconds() as u128),
+    });
+    let ms = durations.max().unwrap_or_default();
+    ms.try_into().unwrap_or(u32::MAX)
+}
+
+impl Iterator for MetadataIter {
+    type Item = (String, crate::Result<EntryValue>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.items.is_empty() {
+            return Some(self.next_item());
+        }
+        if self.tail.is_none() {
+            self.tail = Some(self.tail_entries().into_iter());
+        }
+        self.tail.as_mut()?.next().map(|(k, v)| (k, Ok(v)))
     }
+}
+
+/// A track of a QuickTime/MP4 file, as returned by [`MetadataIter::tracks`],
+/// or of a Matroska file, as returned by
+/// [`parse_matroska_tracks`](crate::parse_matroska_tracks).
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackMeta {
+    /// Track ID (from the track header).
+    pub id: Option<u32>,
+
+    pub kind: TrackKind,
+
+    /// Data format of the samples, e.g. `"avc1"`, `"hvc1"`, `"mp4a"` or
+    /// `"tx3g"` (or Matroska codec IDs, e.g. `"V_VP9"`).
+    pub codec: Option<String>,
+
+    /// Duration of the track's media.
+    pub duration: Option<Duration>,
+
+    /// Duration of the track once its edit list is applied, which is shorter
+    /// than `duration` if parts of the media are edited out (e.g. trimmed
+    /// clips, or the priming samples of AAC tracks), `None` if there is no
+    /// edit list.
+    pub presentation_duration: Option<Duration>,
+
+    /// ISO 639-2/T language code, e.g. `"eng"`, `None` if undetermined.
+    pub language: Option<String>,
+
+    /// Creation time of the track's media (`mdhd`), or of the track
+    /// (`tkhd`) if it's not set, in UTC. Track times are often valid in
+    /// files whose movie header time is zero.
+    pub creation_time: Option<DateTime<FixedOffset>>,
 
-    Ok(entries)
+    /// Modification time of the track's media, or of the track, like
+    /// `creation_time`.
+    pub modification_time: Option<DateTime<FixedOffset>>,
+
+    /// Color description of video tracks, e.g. to tell HDR videos apart.
+    pub color: Option<ColorInfo>,
+
+    /// Profile, level, chroma subsampling and bit depth of H.264, HEVC and
+    /// AV1 video tracks.
+    pub codec_config: Option<CodecConfig>,
+}
+
+/// The kind of a [`TrackMeta`], from the handler type of the track.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TrackKind {
+    Video,
+    Audio,
+    /// Subtitles or closed captions.
+    Subtitle,
+    Timecode,
+    /// Timed metadata, e.g. the live photo data of iPhone videos.
+    Metadata,
+    /// Other handler types, e.g. `"hint"`.
+    Other(String),
+}
+
+impl TrackKind {
+    fn from_handler_type(handler_type: &str) -> TrackKind {
+        match handler_type {
+            "vide" => TrackKind::Video,
+            "soun" => TrackKind::Audio,
+            "sbtl" | "subt" | "text" | "clcp" => TrackKind::Subtitle,
+            "tmcd" => TrackKind::Timecode,
+            "meta" => TrackKind::Metadata,
+            _ => TrackKind::Other(handler_type.to_owned()),
+        }
+    }
 }
 
 /// Analyze the byte stream in the `reader` as a MOV file, attempting to extract
@@ -148,45 +807,115 @@ pub fn parse_metadata<R: Read + Seek>(reader: R) -> crate::Result<Vec<(String, E
 /// ("com.apple.quicktime.location.ISO6709", Text("+27.1281+100.2508+000.000/"))
 /// ("com.apple.quicktime.creationdate", Time(2019-02-12T15:27:12+08:00))
 /// ("duration", U32(500))
+/// ("media_duration", U32(510))
 /// ("width", U32(720))
-/// ("height", U32(1280))"#,
+/// ("height", U32(1280))
+/// ("rotation", U32(0))
+/// ("video_codec", Text("avc1"))
+/// ("frame_rate", F64(60.0))
+/// ("bitrate", U64(12250672))
+/// ("audio_codec", Text("mp4a"))
+/// ("audio_sample_rate", U32(44100))
+/// ("audio_channels", U16(1))"#,
 /// );
 /// ```
 pub fn parse_mov_metadata<R: Read + Seek>(reader: R) -> crate::Result<Vec<(String, EntryValue)>> {
     parse_metadata(reader)
 }
 
-#[tracing::instrument(skip_all)]
+/// Returns the format, the body of the `moov` atom, and the number of bytes
+/// which were skipped (by seeking) before it.
 fn extract_moov_body<R: Read + Seek>(
+    reader: R,
+    parser: &MediaParser,
+) -> Result<(FileFormat, Input<'static>, u64), crate::Error> {
+    extract_moov_body_impl(
+        reader,
+        parser,
+        None,
+        parser.max_moov_size,
+        |reader, _, _, n| {
+            reader.seek(std::io::SeekFrom::Current(n as i64))?;
+            Ok(())
+        },
+    )
+}
+
+/// Like [`extract_moov_body`], but the unneeded data is skipped by `skip`,
+/// which is called with the reader, the file format, the offset of the data
+/// in the file, and its size.
+///
+/// If `resume` is set, the format isn't checked, and `reader` starts at the
+/// given offset of a file of the given format.
+///
+/// If the body of the `moov` atom is larger than `max_moov_size`, only the
+/// atoms which are parsed are read, see
+/// [`MediaParserBuilder::max_moov_size`](crate::MediaParserBuilder::max_moov_size).
+#[tracing::instrument(skip_all)]
+fn extract_moov_body_impl<R, F>(
     mut reader: R,
-) -> Result<(FileFormat, Input<'static>), crate::Error> {
-    const INIT_BUF_SIZE: usize = 4096;
-    const GROW_BUF_SIZE: usize = 4096;
-    let mut buf = Vec::with_capacity(INIT_BUF_SIZE);
+    parser: &MediaParser,
+    resume: Option<(FileFormat, u64)>,
+    max_moov_size: Option<u64>,
+    mut skip: F,
+) -> Result<(FileFormat, Input<'static>, u64), crate::Error>
+where
+    R: Read,
+    F: FnMut(&mut R, FileFormat, u64, u64) -> crate::Result<()>,
+{
+    let init_size = parser.init_read_size();
+    let mut buf = parser.alloc_buffer(init_size);
 
-    buf.reserve(INIT_BUF_SIZE);
     let n = reader
         .by_ref()
-        .take(INIT_BUF_SIZE as u64)
+        .take(init_size as u64)
         .read_to_end(buf.as_mut())?;
+    parser.stats.read(n, buf.len());
     if n == 0 {
-        Err("file is empty")?;
+        Err(crate::Error::Truncated { needed: None })?;
     }
 
-    let ft = check_qt_mp4(&buf)?;
+    let (ft, start) = match resume {
+        Some(x) => x,
+        None => (check_format(&buf)?, 0),
+    };
 
     let mut offset = 0;
+    let mut skipped = 0;
+    let mut visited = BoxesVisited::default();
     let moov_body_range = loop {
         let input = if offset > 0 { &buf[offset..] } else { &buf[..] }; // Safe-slice
 
-        let to_read = match extract_moov_body_from_buf(input) {
+        let base = start + skipped + offset as u64;
+        let on_box = |pos, h: &BoxHeader| visited.visit(parser, base + pos as u64, h);
+        let to_read = match extract_moov_body_with(input, max_moov_size, on_box) {
             Ok(range) => break range.start + offset..range.end + offset,
             Err(Error::Need(n)) => n,
+            Err(Error::LargeMoov {
+                start: body_start,
+                size,
+            }) => {
+                tracing::debug!(?size, "read large moov");
+                let body_start = offset + body_start;
+                let position = start + skipped + body_start as u64;
+                // The buffer is reused for the copy of the moov body
+                let head = buf.split_off(body_start);
+                buf.clear();
+                // Safe unwrap: checked by extract_moov_body_from_buf
+                let max_size = max_moov_size.unwrap();
+                MoovReader::new(&head, &mut reader, skip, ft, position, parser, max_size)
+                    .read_moov(size, &mut buf)?;
+                let len = buf.len();
+                return Ok((ft, parser.buffer_to_input(buf, 0..len), position));
+            }
             Err(Error::Skip(n)) => {
-                tracing::debug!(?n, "skip");
-                reader.seek(std::io::SeekFrom::Current(n as i64))?;
+                let position = start + skipped + buf.len() as u64;
+                skipped += n;
+                parser.check_skipped(skipped)?;
+                skip(&mut reader, ft, position, n)?;
+                parser.stats.skip(n);
                 offset = buf.len();
-                GROW_BUF_SIZE
+                MIN_GROW_SIZE
             }
             Err(Error::ParseFailed(e)) => return Err(e),
         };
@@ -194,19 +923,121 @@ fn extract_moov_body<R: Read + Seek>(
         tracing::debug!(?to_read, "to_read");
         assert!(to_read > 0);
 
-        let to_read = cmp::max(GROW_BUF_SIZE, to_read);
-        buf.reserve(to_read);
+        let to_read = parser.grow_size(buf.len(), to_read)?;
+        parser.reserve_buffer(&mut buf, to_read);
 
         let n = reader
             .by_ref()
             .take(to_read as u64)
             .read_to_end(buf.as_mut())?;
+        parser.stats.read(n, buf.len());
+        if n == 0 {
+            // The moov atom is missing, or incomplete
+            Err(crate::Error::Truncated {
+                needed: Some(to_read),
+            })?;
+        }
+    };
+
+    let input = parser.buffer_to_input(buf, moov_body_range);
+    Ok((ft, input, start + skipped))
+}
+
+/// Emits a [`ParseEvent::BoxVisited`] for each top-level box, once: the
+/// boxes are parsed again from the start of the buffer when it grows.
+#[derive(Default)]
+struct BoxesVisited {
+    /// Offset of the end of the last box visited.
+    end: u64,
+}
+
+impl BoxesVisited {
+    fn visit(&mut self, parser: &MediaParser, offset: u64, h: &BoxHeader) {
+        if offset < self.end {
+            return;
+        }
+        self.end = offset + h.box_size;
+        parser.emit(|| ParseEvent::BoxVisited {
+            box_type: h.box_type.clone(),
+            offset,
+            size: h.box_size,
+        });
+    }
+}
+
+/// Returns the format of a QuickTime/MP4 file starting with `buf`, or an
+/// error which tells the detected format.
+fn check_format(buf: &[u8]) -> crate::Result<FileFormat> {
+    check_qt_mp4(buf).map_err(|e| match e {
+        crate::Error::UnsupportedFormat { .. } => e,
+        _ => crate::Error::UnsupportedFormat {
+            detected: FileFormat::try_from(buf).ok().map(|x| x.to_string()),
+        },
+    })
+}
+
+/// `async` version of [`read_metadata_iter`]: the unneeded data (e.g. the
+/// `mdat` atom) is skipped by seeking. `head` is the start of the file,
+/// which has already been read.
+#[cfg(any(feature = "async", feature = "futures-io"))]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn read_metadata_iter_async<R: AsyncSeekSource>(
+    mut reader: R,
+    parser: &MediaParser,
+    head: &[u8],
+) -> crate::Result<MetadataIter> {
+    let init_size = parser.init_read_size().max(head.len());
+    let mut buf = parser.alloc_buffer(init_size);
+    buf.extend_from_slice(head);
+
+    let n = reader.read_to_buf(&mut buf, init_size - head.len()).await?;
+    parser.stats.read(n + head.len(), buf.len());
+    if buf.is_empty() {
+        Err(crate::Error::Truncated { needed: None })?;
+    }
+    let ft = check_format(&buf)?;
+
+    let mut offset = 0;
+    let mut skipped = 0;
+    let mut visited = BoxesVisited::default();
+    let moov_body_range = loop {
+        let input = if offset > 0 { &buf[offset..] } else { &buf[..] }; // Safe-slice
+
+        let base = skipped + offset as u64;
+        let on_box = |pos, h: &BoxHeader| visited.visit(parser, base + pos as u64, h);
+        let to_read = match extract_moov_body_with(input, None, on_box) {
+            Ok(range) => break range.start + offset..range.end + offset,
+            Err(Error::Need(n)) => n,
+            Err(Error::Skip(n)) => {
+                skipped += n;
+                parser.check_skipped(skipped)?;
+                reader.skip(n).await?;
+                parser.stats.skip(n);
+                offset = buf.len();
+                MIN_GROW_SIZE
+            }
+            Err(Error::ParseFailed(e)) => return Err(e),
+            Err(Error::LargeMoov { .. }) => unreachable!("no maximum moov size"),
+        };
+
+        tracing::debug!(?to_read, "to_read");
+        assert!(to_read > 0);
+
+        let to_read = parser.grow_size(buf.len(), to_read)?;
+        parser.reserve_buffer(&mut buf, to_read);
+
+        let n = reader.read_to_buf(&mut buf, to_read).await?;
+        parser.stats.read(n, buf.len());
         if n == 0 {
-            Err("metadata not found")?;
+            // The moov atom is missing, or incomplete
+            Err(crate::Error::Truncated {
+                needed: Some(to_read),
+            })?;
         }
     };
 
-    Ok((ft, Input::from_vec_range(buf, moov_body_range)))
+    let input = parser.buffer_to_input(buf, moov_body_range);
+    new_metadata_iter(ft, input, skipped, parser)
 }
 
 /// Due to the fact that metadata in MOV files is typically located at the end
@@ -256,6 +1087,11 @@ pub enum Error {
 
     #[error("{0}")]
     ParseFailed(crate::Error),
+
+    /// The body of the `moov` atom is larger than the maximum size which is
+    /// buffered, it starts at index `start` of the input.
+    #[error("moov atom of {size} bytes is too large")]
+    LargeMoov { start: usize, size: u64 },
 }
 
 /// Parse the byte data of an ISOBMFF file and return the potential body data of
@@ -263,7 +1099,23 @@ pub enum Error {
 ///
 /// Regarding error handling, please refer to [Error] for more information.
 #[tracing::instrument(skip_all)]
-fn extract_moov_body_from_buf(input: &[u8]) -> Result<Range<usize>, Error> {
+pub(crate) fn extract_moov_body_from_buf(
+    input: &[u8],
+    max_moov_size: Option<u64>,
+) -> Result<Range<usize>, Error> {
+    extract_moov_body_with(input, max_moov_size, |_, _| ())
+}
+
+/// Like [`extract_moov_body_from_buf`], `on_box` is called with the
+/// position in `input` and the header of each top-level box found.
+fn extract_moov_body_with<F>(
+    input: &[u8],
+    max_moov_size: Option<u64>,
+    mut on_box: F,
+) -> Result<Range<usize>, Error>
+where
+    F: FnMut(usize, &BoxHeader),
+{
     // parse metadata from moov/meta/keys & moov/meta/ilst
     let remain = input;
 
@@ -280,6 +1132,7 @@ fn extract_moov_body_from_buf(input: &[u8]) -> Result<Range<usize>, Error> {
     let mut skipped = 0;
     let (remain, header) = travel_header(remain, |h, remain| {
         tracing::debug!(?h.box_type, ?h.box_size, "Got");
+        on_box(input.len() - remain.len() - h.header_size, h);
         if h.box_type == "moov" {
             // stop travelling
             skipped += h.header_size;
@@ -298,6 +1151,15 @@ fn extract_moov_body_from_buf(input: &[u8]) -> Result<Range<usize>, Error> {
     if to_skip > 0 {
         return Err(Error::Skip(to_skip));
     }
+    match max_moov_size {
+        Some(max) if header.body_size() > max => {
+            return Err(Error::LargeMoov {
+                start: skipped,
+                size: header.body_size(),
+            })
+        }
+        _ => (),
+    }
 
     let (_, body) = streaming::take(header.body_size())(remain)
         .map_err(|e| convert_error(e, "moov is too small"))?;
@@ -305,34 +1167,6 @@ fn extract_moov_body_from_buf(input: &[u8]) -> Result<Range<usize>, Error> {
     Ok(skipped..skipped + body.len())
 }
 
-type EntriesResult<'a> = IResult<&'a [u8], Option<Vec<(String, EntryValue)>>>;
-
-fn parse_moov_body(input: &[u8]) -> EntriesResult {
-    let (remain, Some(meta)) = find_box(input, "meta")? else {
-        return Ok((input, None));
-    };
-
-    let (_, Some(keys)) = find_box(meta.body_data(), "keys")? else {
-        return Ok((remain, None));
-    };
-
-    let (_, Some(ilst)) = find_box(meta.body_data(), "ilst")? else {
-        return Ok((remain, None));
-    };
-
-    let (_, keys) = KeysBox::parse_box(keys.data)?;
-    let (_, ilst) = IlstBox::parse_box(ilst.data)?;
-
-    let entries = keys
-        .entries
-        .into_iter()
-        .map(|k| k.key)
-        .zip(ilst.items.into_iter().map(|v| v.value))
-        .collect::<Vec<_>>();
-
-    Ok((input, Some(entries)))
-}
-
 /// Change timezone format from iso 8601 to rfc3339, e.g.:
 ///
 /// - `2023-11-02T19:58:34+08` -> `2023-11-02T19:58:34+08:00`
@@ -368,6 +1202,7 @@ fn tz_iso_8601_to_rfc3339(s: String) -> String {
 mod tests {
     use super::*;
     use crate::testkit::*;
+    use std::io::Cursor;
     use test_case::test_case;
 
     #[test_case("meta.mov")]
@@ -388,8 +1223,16 @@ mod tests {
 (\"com.apple.quicktime.location.ISO6709\", Text(\"+27.1281+100.2508+000.000/\"))
 (\"com.apple.quicktime.creationdate\", Time(2019-02-12T15:27:12+08:00))
 (\"duration\", U32(500))
+(\"media_duration\", U32(510))
 (\"width\", U32(720))
-(\"height\", U32(1280))"
+(\"height\", U32(1280))
+(\"rotation\", U32(0))
+(\"video_codec\", Text(\"avc1\"))
+(\"frame_rate\", F64(60.0))
+(\"bitrate\", U64(12250672))
+(\"audio_codec\", Text(\"mp4a\"))
+(\"audio_sample_rate\", U32(44100))
+(\"audio_channels\", U16(1))"
         );
     }
 
@@ -399,12 +1242,12 @@ mod tests {
 
         let buf = read_sample(path).unwrap();
         tracing::info!(bytes = buf.len(), "File size.");
-        let range = extract_moov_body_from_buf(&buf).unwrap();
-        let (_, entries) = parse_moov_body(&buf[range]).unwrap();
+        let range = extract_moov_body_from_buf(&buf, None).unwrap();
+        let moov_body = Input::from_vec(buf[range].to_vec());
+        let iter = MetadataIter::new(FileFormat::QuickTime, moov_body, 0).unwrap();
         assert_eq!(
-            entries
-                .unwrap()
-                .iter()
+            iter.take(5)
+                .map(|(k, v)| (k, v.unwrap()))
                 .map(|x| format!("{x:?}"))
                 .collect::<Vec<_>>()
                 .join("\n"),
@@ -412,8 +1255,126 @@ mod tests {
 (\"com.apple.quicktime.model\", Text(\"iPhone X\"))
 (\"com.apple.quicktime.software\", Text(\"12.1.2\"))
 (\"com.apple.quicktime.location.ISO6709\", Text(\"+27.1281+100.2508+000.000/\"))
-(\"com.apple.quicktime.creationdate\", Text(\"2019-02-12T15:27:12+08:00\"))"
+(\"com.apple.quicktime.creationdate\", Time(2019-02-12T15:27:12+08:00))"
+        );
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    #[test_case("embedded-in-heic.mov")]
+    fn metadata_iter(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_metadata_iter(open_sample(path).unwrap()).unwrap();
+        let entries = iter.map(|(k, v)| (k, v.unwrap())).collect::<Vec<_>>();
+        assert_eq!(entries, parse_metadata(open_sample(path).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn track_times() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut buf = read_sample("meta.mov").unwrap();
+        let tracks = parse_metadata_iter(Cursor::new(&buf)).unwrap().tracks();
+        let created = tracks[0].creation_time.unwrap();
+        assert_eq!(created.to_rfc3339(), "2024-02-02T08:09:57+00:00");
+        assert!(tracks[0].modification_time.unwrap() >= created);
+
+        // Clear the times of the media headers, the track ones are used
+        let positions = buf
+            .windows(4)
+            .enumerate()
+            .filter(|(_, x)| x == b"mdhd")
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        for pos in positions {
+            buf[pos + 8..pos + 16].fill(0);
+        }
+        let cleared = parse_metadata_iter(Cursor::new(&buf)).unwrap().tracks();
+        assert!(cleared.iter().all(|x| x.creation_time.is_some()));
+        assert!(cleared.iter().all(|x| x.modification_time.is_some()));
+    }
+
+    #[test]
+    fn edit_list_duration() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // Trim all the tracks to 150 units (250 ms) with their edit lists
+        let mut buf = read_sample("meta.mov").unwrap();
+        let positions = buf
+            .windows(4)
+            .enumerate()
+            .filter(|(_, x)| x == b"elst")
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        assert_eq!(positions.len(), 4);
+        for pos in positions {
+            buf[pos + 12..pos + 16].copy_from_slice(&150u32.to_be_bytes());
+        }
+
+        let iter = parse_metadata_iter(Cursor::new(&buf)).unwrap();
+        assert_eq!(iter.get("duration").unwrap().unwrap(), 250u32.into());
+        assert_eq!(iter.get("media_duration").unwrap().unwrap(), 510u32.into());
+        let tracks = iter.tracks();
+        assert_eq!(
+            tracks[1].presentation_duration,
+            Some(Duration::milliseconds(250))
         );
+        assert_eq!(tracks[1].duration, Some(Duration::milliseconds(510)));
+
+        // Durations that don't fit in milliseconds are clamped
+        let mut buf = read_sample("meta.mov").unwrap();
+        let pos = buf.windows(4).position(|x| x == b"elst").unwrap();
+        buf[pos + 12..pos + 16].copy_from_slice(&u32::MAX.to_be_bytes());
+        let iter = parse_metadata_iter(Cursor::new(&buf)).unwrap();
+        assert_eq!(iter.get("duration").unwrap().unwrap(), u32::MAX.into());
+    }
+
+    #[test]
+    fn metadata_iter_invalid_entry() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // Change the data type of "Apple" to an unsupported one
+        let mut buf = read_sample("meta.mov").unwrap();
+        let item = b"data\x00\x00\x00\x01\x00\x00\x00\x00Apple";
+        let pos = buf.windows(item.len()).position(|x| x == item).unwrap();
+        buf[pos + 7] = 99;
+
+        let entries = parse_metadata_iter(Cursor::new(&buf))
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 16);
+        assert_eq!(entries[0].0, "com.apple.quicktime.make");
+        entries[0].1.as_ref().unwrap_err();
+        assert_eq!(entries[1].0, "com.apple.quicktime.model");
+        assert_eq!(*entries[1].1.as_ref().unwrap(), "iPhone X".into());
+
+        // Invalid entries are skipped
+        let entries = parse_metadata(Cursor::new(&buf)).unwrap();
+        assert_eq!(entries.len(), 15);
+        assert_eq!(entries[0].0, "com.apple.quicktime.model");
+    }
+
+    #[test]
+    fn metadata_iter_corrupt_box() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // Break the data atom of "Apple", the first ilst item
+        let mut buf = read_sample("meta.mov").unwrap();
+        let item = b"data\x00\x00\x00\x01\x00\x00\x00\x00Apple";
+        let pos = buf.windows(item.len()).position(|x| x == item).unwrap();
+        buf[pos] = b'x';
+
+        let entries = parse_metadata_iter(Cursor::new(&buf))
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(entries[0].0, "ilst");
+        let Err(crate::Error::CorruptBox { path, offset }) = &entries[0].1 else {
+            panic!("{entries:?}");
+        };
+        assert_eq!(path, "moov/meta/ilst");
+        // size, key index and data size precede the data atom type
+        assert_eq!(*offset, pos as u64 - 12);
     }
 
     #[test_case("compatible-brands.mov")]
@@ -448,9 +1409,17 @@ mod tests {
                 .join("\n"),
             "(\"com.apple.quicktime.location.ISO6709\", Text(\"+27.2939+112.6932/\"))
 (\"duration\", U32(1063))
+(\"media_duration\", U32(1109))
 (\"com.apple.quicktime.creationdate\", Time(2024-02-03T07:05:38+00:00))
 (\"width\", U32(1920))
-(\"height\", U32(1080))"
+(\"height\", U32(1080))
+(\"rotation\", U32(90))
+(\"video_codec\", Text(\"avc1\"))
+(\"frame_rate\", F64(30.0))
+(\"bitrate\", U64(16322401))
+(\"audio_codec\", Text(\"mp4a\"))
+(\"audio_sample_rate\", U32(48000))
+(\"audio_channels\", U16(2))"
         );
     }
 
@@ -476,9 +1445,81 @@ mod tests {
 (\"com.apple.quicktime.software\", Text(\"17.1\"))
 (\"com.apple.quicktime.creationdate\", Time(2023-11-02T19:58:34+08:00))
 (\"duration\", U32(2795))
+(\"media_duration\", U32(2795))
 (\"width\", U32(1920))
-(\"height\", U32(1440))"
+(\"height\", U32(1440))
+(\"rotation\", U32(90))
+(\"video_codec\", Text(\"hvc1\"))
+(\"frame_rate\", F64(21.46690518783542))
+(\"bitrate\", U64(21321171))
+(\"audio_codec\", Text(\"lpcm\"))
+(\"audio_sample_rate\", U32(44100))
+(\"audio_channels\", U16(1))"
+        );
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    #[test_case("embedded-in-heic.mov")]
+    fn metadata_get(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let iter = parse_metadata_iter(open_sample(path).unwrap()).unwrap();
+        let entries = parse_metadata(open_sample(path).unwrap()).unwrap();
+        for (i, (key, value)) in entries.iter().enumerate() {
+            // The first entry of a key wins
+            if entries[..i].iter().all(|x| &x.0 != key) {
+                assert_eq!(iter.get(key).unwrap().unwrap(), *value, "{key}");
+            }
+        }
+        assert!(iter.get("no.such.key").is_none());
+
+        // The iterator isn't consumed
+        assert_eq!(iter.count(), entries.len());
+    }
+
+    #[test_case(
+        "meta.mov",
+        &[TrackKind::Video, TrackKind::Audio, TrackKind::Metadata, TrackKind::Metadata]
+    )]
+    #[test_case("meta.mp4", &[TrackKind::Video, TrackKind::Audio])]
+    fn tracks(path: &str, kinds: &[TrackKind]) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let tracks = parse_metadata_iter(open_sample(path).unwrap())
+            .unwrap()
+            .tracks();
+        assert_eq!(
+            tracks.iter().map(|x| &x.kind).collect::<Vec<_>>(),
+            kinds.iter().collect::<Vec<_>>()
         );
+        assert_eq!(tracks[0].id, Some(1));
+        assert!(tracks.iter().all(|x| x.duration.is_some()));
+        assert!(tracks.iter().all(|x| x.presentation_duration.is_some()));
+        assert!(tracks.iter().all(|x| x.creation_time.is_some()));
+        assert_eq!(tracks[1].codec.as_deref(), Some("mp4a"));
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    #[test_case("embedded-in-heic.mov")]
+    fn large_moov(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // The minimum nesting depth is enough for the atoms which are parsed
+        let parser = MediaParser::builder()
+            .max_moov_size(1024)
+            .max_nesting_depth(0)
+            .build();
+        let iter = parser
+            .parse_metadata_iter(open_sample(path).unwrap())
+            .unwrap();
+        let expected = parse_metadata_iter(open_sample(path).unwrap()).unwrap();
+        // Only the atoms which are parsed are kept
+        assert!(iter.moov_body.len() < expected.moov_body.len());
+        assert_eq!(iter.tracks(), expected.tracks());
+        let entries = iter.map(|(k, v)| (k, v.unwrap())).collect::<Vec<_>>();
+        assert_eq!(entries, parse_metadata(open_sample(path).unwrap()).unwrap());
     }
 
     #[test]
@@ -0,0 +1,410 @@
+//! Reads a `moov` atom which is larger than
+//! [`MediaParserBuilder::max_moov_size`](crate::MediaParserBuilder::max_moov_size)
+//! atom by atom: only a copy of the atoms which are parsed is kept, and the
+//! rest (e.g. the chunk offset tables) is skipped.
+
+use std::io::{self, Read};
+
+use crate::{file::FileFormat, MediaParser};
+
+/// Size of the chunks in which large sample tables are read.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// What is kept of an atom.
+enum Keep {
+    /// The atom, with only the kept children.
+    Container,
+    /// A copy of the atom, if it isn't larger than the maximum size.
+    Copy,
+    /// A time-to-sample atom (`stts`), summarized if it's too large.
+    SampleTimes,
+    /// A sample size atom (`stsz`), summarized if it's too large.
+    SampleSizes,
+    Skip,
+}
+
+impl Keep {
+    fn of(parent: &[u8; 4], box_type: &[u8; 4]) -> Keep {
+        match (parent, box_type) {
+            (b"moov", b"trak") | (b"trak", b"mdia") | (b"mdia", b"minf") | (b"minf", b"stbl") => {
+                Keep::Container
+            }
+            // mvhd, meta, udta...
+            (b"moov", _) => Keep::Copy,
+            (b"trak", b"tkhd" | b"edts") | (b"mdia", b"mdhd" | b"hdlr") | (b"stbl", b"stsd") => {
+                Keep::Copy
+            }
+            (b"stbl", b"stts") => Keep::SampleTimes,
+            (b"stbl", b"stsz") => Keep::SampleSizes,
+            _ => Keep::Skip,
+        }
+    }
+}
+
+/// Reads the body of a `moov` atom, whose start has already been read.
+pub(crate) struct MoovReader<'a, R, F> {
+    /// The start of the body, which has already been read.
+    buffered: &'a [u8],
+    reader: &'a mut R,
+    /// Skips data of the reader, see `extract_moov_body_impl`.
+    skip: F,
+    format: FileFormat,
+    /// Offset of the next byte in the file.
+    position: u64,
+    parser: &'a MediaParser,
+    max_size: u64,
+}
+
+impl<'a, R, F> MoovReader<'a, R, F>
+where
+    R: Read,
+    F: FnMut(&mut R, FileFormat, u64, u64) -> crate::Result<()>,
+{
+    pub fn new(
+        buffered: &'a [u8],
+        reader: &'a mut R,
+        skip: F,
+        format: FileFormat,
+        position: u64,
+        parser: &'a MediaParser,
+        max_size: u64,
+    ) -> Self {
+        Self {
+            buffered,
+            reader,
+            skip,
+            format,
+            position,
+            parser,
+            max_size,
+        }
+    }
+
+    /// Appends to `out` the kept children of a `moov` body of `size` bytes.
+    pub fn read_moov(mut self, size: u64, out: &mut Vec<u8>) -> crate::Result<()> {
+        self.read_children(b"moov", 1, size, out)
+    }
+
+    /// Appends to `out` the kept children of a `parent` body of `size`
+    /// bytes, `depth` being the nesting depth of the parent.
+    fn read_children(
+        &mut self,
+        parent: &[u8; 4],
+        depth: usize,
+        size: u64,
+        out: &mut Vec<u8>,
+    ) -> crate::Result<()> {
+        let mut remaining = size;
+        while remaining >= 8 {
+            let mut header = [0; 8];
+            self.read_exact(&mut header)?;
+            let (box_size, box_type) = header.split_at(4);
+            let box_type: [u8; 4] = box_type.try_into().expect("4 bytes");
+            let box_size = u32::from_be_bytes(box_size.try_into().expect("4 bytes"));
+            let (box_size, header_size) = match box_size {
+                1 if remaining >= 16 => {
+                    self.read_exact(&mut header)?;
+                    (u64::from_be_bytes(header), 16)
+                }
+                n => (n as u64, 8),
+            };
+            if box_size < header_size || box_size > remaining {
+                // Malformed, the rest of the parent is ignored
+                tracing::debug!(?box_type, box_size, remaining, "invalid box size");
+                remaining -= header_size;
+                break;
+            }
+            remaining -= box_size;
+            let body_size = box_size - header_size;
+
+            match Keep::of(parent, &box_type) {
+                Keep::Container => {
+                    self.parser.check_nesting_depth(depth + 1)?;
+                    let start = out.len();
+                    self.reserve(out, 8)?;
+                    out.extend_from_slice(&[0; 4]);
+                    out.extend_from_slice(&box_type);
+                    self.read_children(&box_type, depth + 1, body_size, out)?;
+                    let size = u32::try_from(out.len() - start)
+                        .map_err(|_| crate::Error::from("moov atom is too large"))?;
+                    out[start..start + 4].copy_from_slice(&size.to_be_bytes()); // Safe-slice
+                }
+                Keep::Copy | Keep::SampleTimes | Keep::SampleSizes
+                    if body_size <= self.max_size =>
+                {
+                    self.copy_box(&box_type, body_size, out)?;
+                }
+                Keep::SampleTimes => {
+                    let body = self.summarize_stts(body_size)?;
+                    self.write_box(&box_type, &body, out)?;
+                }
+                Keep::SampleSizes => {
+                    let body = self.summarize_stsz(body_size)?;
+                    self.write_box(&box_type, &body, out)?;
+                }
+                Keep::Copy | Keep::Skip => {
+                    tracing::debug!(?box_type, body_size, "skip");
+                    self.skip(body_size)?;
+                }
+            }
+        }
+        self.skip(remaining)
+    }
+
+    fn copy_box(
+        &mut self,
+        box_type: &[u8; 4],
+        body_size: u64,
+        out: &mut Vec<u8>,
+    ) -> crate::Result<()> {
+        // Not larger than the maximum size, which is buffered anyway
+        let len = body_size as usize;
+        self.write_header(box_type, len, out)?;
+        self.reserve(out, len)?;
+        let start = out.len();
+        out.resize(start + len, 0);
+        self.read_exact(&mut out[start..]) // Safe-slice
+    }
+
+    fn write_box(
+        &mut self,
+        box_type: &[u8; 4],
+        body: &[u8],
+        out: &mut Vec<u8>,
+    ) -> crate::Result<()> {
+        self.write_header(box_type, body.len(), out)?;
+        self.reserve(out, body.len())?;
+        out.extend_from_slice(body);
+        Ok(())
+    }
+
+    fn write_header(
+        &mut self,
+        box_type: &[u8; 4],
+        body_size: usize,
+        out: &mut Vec<u8>,
+    ) -> crate::Result<()> {
+        self.reserve(out, 16)?;
+        match u32::try_from(body_size + 8) {
+            Ok(size) => out.extend_from_slice(&size.to_be_bytes()),
+            Err(_) => {
+                out.extend_from_slice(&1u32.to_be_bytes());
+                out.extend_from_slice(box_type);
+                out.extend_from_slice(&(body_size as u64 + 16).to_be_bytes());
+                return Ok(());
+            }
+        }
+        out.extend_from_slice(box_type);
+        Ok(())
+    }
+
+    /// Reads a time-to-sample atom (`stts`) body of `size` bytes, and
+    /// returns the body of an equivalent atom with at most a few entries:
+    /// with the same number of samples and total duration.
+    fn summarize_stts(&mut self, size: u64) -> crate::Result<Vec<u8>> {
+        let (mut count, mut duration) = (0u64, 0u64);
+        // version & flags, entry count
+        let mut header = [0; 8];
+        let remaining = self.read_table(size, &mut header, 8, |entry| {
+            let n = u32::from_be_bytes(entry[..4].try_into().expect("4 bytes")) as u64; // Safe-slice
+            let delta = u32::from_be_bytes(entry[4..].try_into().expect("4 bytes")) as u64; // Safe-slice
+            count += n;
+            duration += n * delta;
+        })?;
+        self.skip(remaining)?;
+
+        // `count - rem` samples of `delta` and `rem` samples of `delta + 1`
+        // have the same total duration. `delta + 1` doesn't overflow, since
+        // `rem` is 0 if all the samples have the maximum duration.
+        let mut runs = Vec::new();
+        if let Some(delta) = duration.checked_div(count) {
+            let rem = duration % count;
+            push_runs(&mut runs, count - rem, delta as u32);
+            push_runs(&mut runs, rem, delta as u32 + 1);
+        }
+        // version & flags, entry count, entries
+        let mut body = vec![0; 4];
+        body.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+        for (n, delta) in runs {
+            body.extend_from_slice(&n.to_be_bytes());
+            body.extend_from_slice(&delta.to_be_bytes());
+        }
+        Ok(body)
+    }
+
+    /// Reads a sample size atom (`stsz`) body of `size` bytes, and returns
+    /// the body of an atom with the same total size of the samples.
+    fn summarize_stsz(&mut self, size: u64) -> crate::Result<Vec<u8>> {
+        let mut total = 0u64;
+        // version & flags, sample size, sample count
+        let mut header = [0; 12];
+        let remaining = self.read_table(size, &mut header, 4, |entry| {
+            total += u32::from_be_bytes(entry.try_into().expect("4 bytes")) as u64;
+        })?;
+        self.skip(remaining)?;
+
+        let [sample_size, sample_count] =
+            [&header[4..8], &header[8..]] // Safe-slice
+                .map(|x| u32::from_be_bytes(x.try_into().expect("4 bytes")) as u64);
+        if sample_size != 0 {
+            // All the samples have the same size, there's no table
+            total = sample_size * sample_count;
+        }
+
+        let mut sizes = Vec::new();
+        while total > 0 {
+            let n = total.min(u32::MAX as u64);
+            sizes.push(n as u32);
+            total -= n;
+        }
+        // version & flags, sample size (0: the sizes are in the table),
+        // sample count, sizes
+        let mut body = vec![0; 8];
+        body.extend_from_slice(&(sizes.len() as u32).to_be_bytes());
+        for size in sizes {
+            body.extend_from_slice(&size.to_be_bytes());
+        }
+        Ok(body)
+    }
+
+    /// Reads the table of a body of `size` bytes: fills `header`, whose
+    /// last 4 bytes are the entry count, and calls `f` with each entry.
+    /// Returns the number of bytes left in the body.
+    fn read_table(
+        &mut self,
+        size: u64,
+        header: &mut [u8],
+        entry_size: usize,
+        mut f: impl FnMut(&[u8]),
+    ) -> crate::Result<u64> {
+        if size < header.len() as u64 {
+            return Ok(size);
+        }
+        self.read_exact(header)?;
+        let count = &header[header.len() - 4..]; // Safe-slice
+        let count = u32::from_be_bytes(count.try_into().expect("4 bytes")) as u64;
+        let mut remaining = size - header.len() as u64;
+        let mut entries = count.min(remaining / entry_size as u64);
+
+        let mut chunk = vec![0; CHUNK_SIZE - CHUNK_SIZE % entry_size];
+        while entries > 0 {
+            let n = entries.min((chunk.len() / entry_size) as u64) as usize;
+            let data = &mut chunk[..n * entry_size]; // Safe-slice
+            self.read_exact(data)?;
+            data.chunks_exact(entry_size).for_each(&mut f);
+            entries -= n as u64;
+            remaining -= (n * entry_size) as u64;
+        }
+        Ok(remaining)
+    }
+
+    /// Returns an error if `out` can't grow by `n` bytes without exceeding
+    /// the maximum metadata size.
+    fn reserve(&self, out: &mut Vec<u8>, n: usize) -> crate::Result<()> {
+        self.parser.check_metadata_size(out.len() + n)?;
+        self.parser.reserve_buffer(out, n);
+        self.parser.stats.read(0, out.len() + n);
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> crate::Result<()> {
+        let n = buf.len().min(self.buffered.len());
+        let (head, rest) = self.buffered.split_at(n);
+        buf[..n].copy_from_slice(head); // Safe-slice
+        self.buffered = rest;
+
+        let unbuffered = &mut buf[n..]; // Safe-slice
+        if !unbuffered.is_empty() {
+            self.parser.check_cancelled()?;
+            match self.reader.read_exact(unbuffered) {
+                Ok(()) => self.parser.stats.read(unbuffered.len(), 0),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Err(crate::Error::Truncated { needed: None });
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.position += buf.len() as u64;
+        Ok(())
+    }
+
+    fn skip(&mut self, n: u64) -> crate::Result<()> {
+        let buffered = n.min(self.buffered.len() as u64) as usize;
+        self.buffered = &self.buffered[buffered..]; // Safe-slice
+        let unbuffered = n - buffered as u64;
+        if unbuffered > 0 {
+            self.parser.check_cancelled()?;
+            let position = self.position + buffered as u64;
+            (self.skip)(self.reader, self.format, position, unbuffered)?;
+            self.parser.stats.skip(unbuffered);
+        }
+        self.position += n;
+        Ok(())
+    }
+}
+
+/// Appends time-to-sample entries of `n` samples of `delta`, each of at
+/// most `u32::MAX` samples.
+fn push_runs(runs: &mut Vec<(u32, u32)>, mut n: u64, delta: u32) {
+    while n > 0 {
+        let count = n.min(u32::MAX as u64);
+        runs.push((count as u32, delta));
+        n -= count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    use super::*;
+    use crate::{
+        bbox::{parse_traks_in_moov, travel_while},
+        testkit::read_sample,
+    };
+    use test_case::test_case;
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    #[test_case("embedded-in-heic.mov")]
+    fn summarize_sample_tables(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let buf = read_sample(path).unwrap();
+        let (_, moov) = travel_while(&buf, |b| b.box_type() != "moov").unwrap();
+        let body = moov.unwrap().body_data();
+
+        // Half of the body has already been read
+        let (buffered, rest) = body.split_at(body.len() / 2);
+        let mut reader = Cursor::new(rest);
+        let skip = |reader: &mut Cursor<&[u8]>, _, _, n| {
+            reader.seek(SeekFrom::Current(n as i64))?;
+            Ok(())
+        };
+        let parser = MediaParser::default();
+        let mut out = Vec::new();
+        // Every sample table is larger than 8 bytes
+        MoovReader::new(
+            buffered,
+            &mut reader,
+            skip,
+            FileFormat::QuickTime,
+            0,
+            &parser,
+            8,
+        )
+        .read_moov(body.len() as u64, &mut out)
+        .unwrap();
+        assert!(out.len() < body.len() / 2);
+
+        let traks = parse_traks_in_moov(&out);
+        let expected = parse_traks_in_moov(body);
+        assert_eq!(traks.len(), expected.len());
+        for (trak, expected) in traks.iter().zip(expected.iter()) {
+            assert_eq!(trak.sample_count, expected.sample_count);
+            assert_eq!(trak.sample_duration, expected.sample_duration);
+            assert_eq!(trak.sample_bytes, expected.sample_bytes);
+        }
+        assert!(traks.iter().any(|x| x.sample_bytes > 0));
+    }
+}
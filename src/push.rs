@@ -0,0 +1,267 @@
+use nom::Needed;
+
+use crate::{
+    error::convert_parse_error,
+    file::FileFormat,
+    media::MIN_GROW_SIZE,
+    mov::{self, extract_moov_body_from_buf, new_metadata_iter},
+    slice::SubsliceRange,
+    MediaInfo, MediaParser,
+};
+
+/// What a [`PushParser`] needs after being fed.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum FeedStatus {
+    /// About this many more bytes are needed, which can be fed in any
+    /// number of chunks.
+    NeedMore(usize),
+
+    /// The next bytes of this size aren't needed: they should be skipped,
+    /// and the bytes which follow them fed next.
+    Skip(u64),
+
+    /// The parsing is done, see [`parse_media`](crate::parse_media).
+    Done(Option<MediaInfo>),
+}
+
+/// Parses the metadata of a media file from bytes pushed by the caller,
+/// rather than pulled from a [`Read`](std::io::Read)er, e.g. for custom
+/// transports (QUIC streams, message queues...).
+///
+/// The file is fed from its start with [`PushParser::feed`], until
+/// [`FeedStatus::Done`] is returned. If the file ends before, e.g. when it's
+/// smaller than the buffer capacity, [`PushParser::finish`] parses what has
+/// been fed.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let data = std::fs::read("./testdata/meta.mov").unwrap();
+/// let mut parser = PushParser::default();
+/// let mut pos = 0;
+/// let info = loop {
+///     let chunk = &data[pos..(pos + 1024).min(data.len())];
+///     pos += chunk.len();
+///     match parser.feed(chunk).unwrap() {
+///         FeedStatus::NeedMore(_) if pos == data.len() => break parser.finish().unwrap(),
+///         FeedStatus::NeedMore(_) => (),
+///         // e.g. the `mdat` atom of a video
+///         FeedStatus::Skip(n) => pos += n as usize,
+///         FeedStatus::Done(info) => break info,
+///     }
+/// };
+///
+/// let iter = info.unwrap().into_track().unwrap();
+/// assert_eq!(iter.tracks().len(), 4);
+/// ```
+#[derive(Debug, Default)]
+pub struct PushParser {
+    parser: MediaParser,
+    /// The data fed since the last skip, `None` once the parsing is done.
+    buf: Option<Vec<u8>>,
+    format: Option<FileFormat>,
+    /// Position of the buffer in the file.
+    position: u64,
+    skipped: u64,
+}
+
+impl PushParser {
+    /// Creates a push parser with the limits, buffer pool and statistics of
+    /// `parser`.
+    pub fn new(parser: MediaParser) -> Self {
+        Self {
+            parser,
+            buf: None,
+            format: None,
+            position: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Returns the parser, e.g. to call
+    /// [`MediaParser::take_stats`](crate::MediaParser::take_stats).
+    pub fn parser(&self) -> &MediaParser {
+        &self.parser
+    }
+
+    /// Feeds the next bytes of the file, and returns what is needed next.
+    ///
+    /// Fails if the file is malformed or exceeds the limits of the parser,
+    /// or if the parsing is already done.
+    pub fn feed(&mut self, data: &[u8]) -> crate::Result<FeedStatus> {
+        let parser = &self.parser;
+        if self.buf.is_none() {
+            if self.format.is_some() {
+                return Err("the parsing is already done".into());
+            }
+            self.buf = Some(parser.alloc_buffer(parser.init_read_size().max(data.len())));
+        }
+        let buf = self.buf.as_mut().expect("the buffer is allocated");
+        parser.reserve_buffer(buf, data.len());
+        buf.extend_from_slice(data);
+        parser.stats.read(data.len(), buf.len());
+
+        self.advance(false)
+    }
+
+    /// Parses the data which has been fed, once the file has ended.
+    ///
+    /// Fails with [`Error::Truncated`](crate::Error::Truncated) if the
+    /// metadata is incomplete.
+    pub fn finish(&mut self) -> crate::Result<Option<MediaInfo>> {
+        if self.buf.is_none() && self.format.is_none() {
+            Err(crate::Error::Truncated { needed: None })?;
+        }
+        match self.advance(true)? {
+            FeedStatus::Done(info) => Ok(info),
+            FeedStatus::NeedMore(n) => Err(crate::Error::Truncated { needed: Some(n) }),
+            FeedStatus::Skip(n) => Err(crate::Error::Truncated {
+                needed: Some(usize::try_from(n).unwrap_or(usize::MAX)),
+            }),
+        }
+    }
+
+    /// Parses the buffer, `eof` is set once the file has ended.
+    fn advance(&mut self, eof: bool) -> crate::Result<FeedStatus> {
+        let parser = &self.parser;
+        let Some(buf) = self.buf.as_mut() else {
+            return Err("the parsing is already done".into());
+        };
+
+        let ff = match self.format {
+            Some(ff) => ff,
+            None if buf.len() < parser.init_read_size() && !eof => {
+                return Ok(FeedStatus::NeedMore(parser.init_read_size() - buf.len()));
+            }
+            None => {
+                let ff = FileFormat::try_from(&buf[..])?;
+                self.format = Some(ff);
+                ff
+            }
+        };
+
+        let needed = match ff {
+            FileFormat::Jpeg | FileFormat::Heif => match ff.extract_exif_data(&buf[..]) {
+                Ok((_, data)) => {
+                    let range = data.and_then(|x| SubsliceRange::subslice_range(&buf[..], x));
+                    let buf = self.buf.take().expect("the buffer is allocated");
+                    let iter = range
+                        .map(|range| parser.input_to_iter(parser.buffer_to_input(buf, range)))
+                        .transpose()?;
+                    return Ok(FeedStatus::Done(iter.map(MediaInfo::Exif)));
+                }
+                Err(nom::Err::Incomplete(Needed::Size(n))) => n.get(),
+                Err(nom::Err::Incomplete(Needed::Unknown)) => MIN_GROW_SIZE,
+                Err(e) => return Err(convert_parse_error(e, "read exif failed")),
+            },
+            FileFormat::QuickTime | FileFormat::MP4 => {
+                match extract_moov_body_from_buf(&buf[..], None) {
+                    Ok(range) => {
+                        let buf = self.buf.take().expect("the buffer is allocated");
+                        let input = parser.buffer_to_input(buf, range);
+                        let iter = new_metadata_iter(ff, input, self.position, parser)?;
+                        return Ok(FeedStatus::Done(Some(MediaInfo::Track(iter))));
+                    }
+                    Err(mov::Error::Need(n)) => n,
+                    Err(mov::Error::Skip(n)) => {
+                        self.skipped += n;
+                        parser.check_skipped(self.skipped)?;
+                        parser.stats.skip(n);
+                        // The buffered data has been consumed
+                        self.position += buf.len() as u64 + n;
+                        buf.clear();
+                        return Ok(FeedStatus::Skip(n));
+                    }
+                    Err(mov::Error::ParseFailed(e)) => return Err(e),
+                    Err(mov::Error::LargeMoov { .. }) => unreachable!("no maximum moov size"),
+                }
+            }
+            // Matroska files are parsed by seeking from element to element
+            FileFormat::Matroska => {
+                return Err(crate::Error::UnsupportedFormat {
+                    detected: Some(ff.to_string()),
+                })
+            }
+        };
+        Ok(FeedStatus::NeedMore(parser.grow_size(buf.len(), needed)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testkit::*, Exif};
+    use test_case::test_case;
+
+    #[test_case("exif.jpg", 1)]
+    #[test_case("exif.jpg", 4096)]
+    #[test_case("exif.heic", 100)]
+    #[test_case("no-exif.jpg", 512)]
+    #[test_case("meta.mov", 1)]
+    #[test_case("meta.mov", 1000)]
+    #[test_case("meta.mp4", 4096)]
+    #[test_case("embedded-in-heic.mov", 333)]
+    fn push_parse(path: &str, chunk_size: usize) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample(path).unwrap();
+        let mut parser = PushParser::default();
+        let mut pos = 0;
+        let mut skips = 0;
+        let info = loop {
+            let chunk = &data[pos..(pos + chunk_size).min(data.len())];
+            pos += chunk.len();
+            match parser.feed(chunk).unwrap() {
+                FeedStatus::NeedMore(n) => {
+                    assert!(n > 0);
+                    if pos == data.len() {
+                        break parser.finish().unwrap();
+                    }
+                }
+                FeedStatus::Skip(n) => {
+                    skips += 1;
+                    pos += n as usize;
+                }
+                FeedStatus::Done(info) => break info,
+            }
+        };
+        assert!(parser.feed(&[0]).is_err());
+
+        let expected = crate::parse_media(open_sample(path).unwrap()).unwrap();
+        match (info, expected) {
+            (Some(MediaInfo::Exif(iter)), Some(MediaInfo::Exif(expected))) => {
+                let exif: Exif = iter.into();
+                let expected: Exif = expected.into();
+                assert_eq!(exif.entries(), expected.entries());
+            }
+            (Some(MediaInfo::Track(iter)), Some(MediaInfo::Track(expected))) => {
+                assert!(skips > 0 || path == "embedded-in-heic.mov");
+                assert_eq!(iter.tracks(), expected.tracks());
+                let entries = iter.map(|(k, v)| (k, v.unwrap())).collect::<Vec<_>>();
+                let expected = expected.map(|(k, v)| (k, v.unwrap())).collect::<Vec<_>>();
+                assert_eq!(entries, expected);
+            }
+            (None, None) => (),
+            (info, expected) => panic!("{info:?} != {expected:?}"),
+        }
+    }
+
+    #[test]
+    fn push_truncated() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_sample("exif.jpg").unwrap();
+        let mut parser = PushParser::default();
+        assert!(matches!(
+            parser.feed(&data[..100]).unwrap(),
+            FeedStatus::NeedMore(_)
+        ));
+        let err = parser.finish().unwrap_err();
+        assert!(matches!(err, crate::Error::Truncated { .. }), "{err}");
+
+        assert!(PushParser::default().finish().is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Counters of the reads done by a [`MediaParser`](crate::MediaParser)
+/// since the last call to
+/// [`MediaParser::take_stats`](crate::MediaParser::take_stats), e.g. to
+/// tune the buffer sizes of a batch job, or to check that its buffer pool
+/// is effective.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// Number of files whose metadata has been read.
+    pub files: u64,
+
+    /// Number of bytes read from the files.
+    pub bytes_read: u64,
+
+    /// Size of the largest buffer needed for a single file, in bytes.
+    pub peak_buffer_size: usize,
+
+    /// Number of seeks done to skip data which doesn't contain metadata,
+    /// e.g. the `mdat` atom of videos, and the number of bytes skipped.
+    pub skips: u64,
+    pub skipped_bytes: u64,
+
+    /// Number of times a buffer has been reallocated to grow, see
+    /// [`MediaParserBuilder::buffer_capacity`](crate::MediaParserBuilder::buffer_capacity).
+    pub reallocations: u64,
+
+    /// Number of buffers which have been taken from the buffer pool rather
+    /// than allocated, see
+    /// [`MediaParserBuilder::buffer_pool_size`](crate::MediaParserBuilder::buffer_pool_size).
+    pub pooled_buffers: u64,
+}
+
+/// The shared counters behind [`ParseStats`].
+#[derive(Debug, Default)]
+pub(crate) struct StatsCounter {
+    files: AtomicU64,
+    bytes_read: AtomicU64,
+    peak_buffer_size: AtomicUsize,
+    skips: AtomicU64,
+    skipped_bytes: AtomicU64,
+    reallocations: AtomicU64,
+    pooled_buffers: AtomicU64,
+}
+
+impl StatsCounter {
+    /// Records the buffer of a new file.
+    pub fn start(&self, pooled: bool) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        if pooled {
+            self.pooled_buffers.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a read of `n` bytes, after which `buffered` bytes are in the
+    /// buffer.
+    pub fn read(&self, n: usize, buffered: usize) {
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        self.peak_buffer_size.fetch_max(buffered, Ordering::Relaxed);
+    }
+
+    pub fn reallocation(&self) {
+        self.reallocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn skip(&self, n: u64) {
+        self.skips.fetch_add(1, Ordering::Relaxed);
+        self.skipped_bytes.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the counters, and resets them.
+    pub fn take(&self) -> ParseStats {
+        ParseStats {
+            files: self.files.swap(0, Ordering::Relaxed),
+            bytes_read: self.bytes_read.swap(0, Ordering::Relaxed),
+            peak_buffer_size: self.peak_buffer_size.swap(0, Ordering::Relaxed),
+            skips: self.skips.swap(0, Ordering::Relaxed),
+            skipped_bytes: self.skipped_bytes.swap(0, Ordering::Relaxed),
+            reallocations: self.reallocations.swap(0, Ordering::Relaxed),
+            pooled_buffers: self.pooled_buffers.swap(0, Ordering::Relaxed),
+        }
+    }
+}
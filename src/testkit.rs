@@ -1,4 +1,8 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
 
 use crate::exif::Exif;
 use crate::exif::ExifTag::*;
@@ -66,6 +70,7 @@ pub fn sorted_exif_entries(exif: &Exif) -> Vec<String> {
             YResolution,
             ResolutionUnit,
             Software,
+            Artist,
             HostComputer,
             WhitePoint,
             PrimaryChromaticities,
@@ -143,6 +148,55 @@ pub fn sorted_exif_entries(exif: &Exif) -> Vec<String> {
             GPSAreaInformation,
             GPSDateStamp,
             GPSDifferential,
+            NewSubfileType,
+            SubfileType,
+            BitsPerSample,
+            Compression,
+            PhotometricInterpretation,
+            StripOffsets,
+            SamplesPerPixel,
+            RowsPerStrip,
+            StripByteCounts,
+            PlanarConfiguration,
+            TransferFunction,
+            ThumbnailOffset,
+            ThumbnailLength,
+            YCbCrSubSampling,
+            YCbCrPositioning,
+            StandardOutputSensitivity,
+            RecommendedExposureIndex,
+            ISOSpeed,
+            ISOSpeedLatitudeyyy,
+            ISOSpeedLatitudezzz,
+            ComponentsConfiguration,
+            CompressedBitsPerPixel,
+            SubSecTime,
+            SubSecTimeOriginal,
+            SubSecTimeDigitized,
+            AmbientTemperature,
+            Humidity,
+            Pressure,
+            WaterDepth,
+            Acceleration,
+            CameraElevationAngle,
+            InteropOffset,
+            SpatialFrequencyResponse,
+            CameraOwnerName,
+            BodySerialNumber,
+            LensSerialNumber,
+            ImageTitle,
+            Photographer,
+            ImageEditor,
+            CameraFirmware,
+            RAWDevelopingSoftware,
+            ImageEditingSoftware,
+            MetadataEditingSoftware,
+            CompositeImage,
+            SourceImageNumberOfCompositeImage,
+            SourceExposureTimesOfCompositeImage,
+            GPSHPositioningError,
+            InteropIndex,
+            InteropVersion,
         ])
         .into_iter()
         .map(|x| format!("{} » {}", x.0, x.1))
@@ -151,3 +205,36 @@ pub fn sorted_exif_entries(exif: &Exif) -> Vec<String> {
 
     entries
 }
+
+/// Wraps a reader, counting the bytes read and the seeks.
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    pub inner: R,
+    pub bytes_read: usize,
+    pub seeks: usize,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bytes_read: 0,
+            seeks: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.seeks += 1;
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,178 @@
+//! Infers the time zone of capture times from GPS positions, enabled by the
+//! `tz` feature flag.
+//!
+//! Exif times are recorded in the camera's local time, and many cameras
+//! don't write the `OffsetTime*` tags, in which case
+//! [`Exif::datetime_original`] assumes the local time zone of this machine.
+//! [`Exif::datetime_original_tz`] resolves the time zone of the place where
+//! the image was taken instead, with a [`TimeZoneLookup`].
+//!
+//! No time zone boundaries are bundled: [`NauticalTimeZone`] only derives
+//! the offset from the longitude. Precise lookups can be plugged in by
+//! implementing [`TimeZoneLookup`], e.g. with the
+//! [tzf-rs](https://crates.io/crates/tzf-rs) crate:
+//!
+//! ```rust,ignore
+//! let finder = tzf_rs::DefaultFinder::new();
+//! let lookup = |lat: f64, lon: f64| finder.get_tz_name(lon, lat).parse().ok();
+//! let time = exif.datetime_original_tz(&lookup);
+//! ```
+
+use chrono::{DateTime, FixedOffset, TimeZone as _};
+pub use chrono_tz::Tz;
+
+use crate::{Exif, ExifTag};
+
+/// Finds the time zone of a position.
+pub trait TimeZoneLookup {
+    /// Returns the time zone at `latitude` & `longitude` (decimal degrees,
+    /// positive for north and east), or `None` if it's unknown.
+    fn lookup(&self, latitude: f64, longitude: f64) -> Option<Tz>;
+}
+
+impl<F: Fn(f64, f64) -> Option<Tz>> TimeZoneLookup for F {
+    fn lookup(&self, latitude: f64, longitude: f64) -> Option<Tz> {
+        self(latitude, longitude)
+    }
+}
+
+/// The nautical time zone of a position, i.e. `Etc/GMT±N` where `N` is the
+/// longitude divided by 15°, rounded.
+///
+/// It ignores political boundaries and daylight saving time, so it can be an
+/// hour or more off on land (e.g. all of China is UTC+8), but it needs no
+/// data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NauticalTimeZone;
+
+impl TimeZoneLookup for NauticalTimeZone {
+    fn lookup(&self, _latitude: f64, longitude: f64) -> Option<Tz> {
+        if !(-180.0..=180.0).contains(&longitude) {
+            return None;
+        }
+        let hours = (longitude / 15.0).round() as i32;
+        // The signs of the `Etc` zones are inverted, e.g. `Etc/GMT-8` is UTC+8
+        let name = match hours {
+            0 => "Etc/GMT".to_owned(),
+            _ => format!("Etc/GMT{:+}", -hours),
+        };
+        name.parse().ok()
+    }
+}
+
+const OFFSET_TAGS: [ExifTag; 3] = [
+    ExifTag::OffsetTimeOriginal,
+    ExifTag::OffsetTimeDigitized,
+    ExifTag::OffsetTime,
+];
+
+impl Exif {
+    /// Like [`Self::datetime_original`], but when the image has no
+    /// `OffsetTime*` tags, the time is interpreted in the time zone where the
+    /// image was taken, as found by `lookup` from the GPS position.
+    ///
+    /// Falls back to [`Self::datetime_original`] if the image has no GPS
+    /// position, or if `lookup` doesn't find its time zone.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::{tz::*, *};
+    ///
+    /// // Taken in Madrid, without any `OffsetTime*` tag
+    /// let mut edits = MetadataEdits::new();
+    /// edits
+    ///     .set_exif(ExifTag::DateTimeOriginal, "2024:01:15 09:30:00")
+    ///     .set_gps(&GPSInfo::from_decimal(40.4168, -3.7038, 650.0).unwrap());
+    /// let mut jpeg = Vec::new();
+    /// let reader = std::fs::File::open("./testdata/no-exif.jpg").unwrap();
+    /// write_metadata(reader, &mut jpeg, &edits).unwrap();
+    ///
+    /// let exif: Exif = parse_exif(std::io::Cursor::new(jpeg), None)
+    ///     .unwrap()
+    ///     .unwrap()
+    ///     .into();
+    /// let madrid = |_: f64, _: f64| Some(Tz::Europe__Madrid);
+    /// let time = exif.datetime_original_tz(&madrid).unwrap();
+    /// assert_eq!(time.to_rfc3339(), "2024-01-15T09:30:00+01:00");
+    ///
+    /// let time = exif.datetime_original_tz(&NauticalTimeZone).unwrap();
+    /// assert_eq!(time.to_rfc3339(), "2024-01-15T09:30:00+00:00");
+    /// ```
+    pub fn datetime_original_tz(
+        &self,
+        lookup: &impl TimeZoneLookup,
+    ) -> Option<DateTime<FixedOffset>> {
+        let time = self.datetime_original()?;
+        if OFFSET_TAGS.iter().any(|&tag| self.get(tag).is_some()) {
+            return Some(time);
+        }
+        let inferred = || {
+            let (lat, lon, _) = self.get_gps_info().ok()??.to_decimal();
+            let tz = lookup.lookup(lat, lon)?;
+            // The earlier time for ambiguous times at the end of DST
+            let local = tz.from_local_datetime(&time.naive_local()).earliest()?;
+            Some(local.fixed_offset())
+        };
+        inferred().or(Some(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_exif, testkit::*, write_metadata, GPSInfo, MetadataEdits};
+    use std::io::Cursor;
+    use test_case::test_case;
+
+    fn sample(offset: Option<&str>, gps: Option<(f64, f64)>) -> Exif {
+        let mut edits = MetadataEdits::new();
+        edits.set_exif(ExifTag::DateTimeOriginal, "2024:07:01 12:00:00");
+        if let Some(offset) = offset {
+            edits.set_exif(ExifTag::OffsetTimeOriginal, offset);
+        }
+        if let Some((lat, lon)) = gps {
+            edits.set_gps(&GPSInfo::from_decimal(lat, lon, 0.0).unwrap());
+        }
+        let mut out = Vec::new();
+        write_metadata(&read_sample("no-exif.jpg").unwrap()[..], &mut out, &edits).unwrap();
+        parse_exif(Cursor::new(out), None).unwrap().unwrap().into()
+    }
+
+    #[test_case(0.0, Some("Etc/GMT"))]
+    #[test_case(114.05, Some("Etc/GMT-8"))]
+    #[test_case(-73.98, Some("Etc/GMT+5"))]
+    #[test_case(-180.0, Some("Etc/GMT+12"))]
+    #[test_case(180.0, Some("Etc/GMT-12"))]
+    #[test_case(181.0, None)]
+    fn nautical_time_zone(lon: f64, expected: Option<&str>) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let tz = NauticalTimeZone.lookup(10.0, lon);
+        assert_eq!(tz.map(|x| x.name()), expected);
+    }
+
+    #[test_case(None, Some((40.7128, -74.006)), "2024-07-01T12:00:00-04:00")]
+    #[test_case(Some("+02:00"), Some((40.7128, -74.006)), "2024-07-01T12:00:00+02:00")]
+    fn datetime_original_tz(offset: Option<&str>, gps: Option<(f64, f64)>, expected: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif = sample(offset, gps);
+        let new_york = |_: f64, _: f64| Some(Tz::America__New_York);
+        let time = exif.datetime_original_tz(&new_york).unwrap();
+        assert_eq!(time.to_rfc3339(), expected);
+    }
+
+    #[test]
+    fn datetime_original_tz_fallback() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let exif = sample(None, None);
+        let time = exif.datetime_original_tz(&NauticalTimeZone);
+        assert_eq!(time, exif.datetime_original());
+
+        let exif = sample(None, Some((40.7128, -74.006)));
+        let time = exif.datetime_original_tz(&|_: f64, _: f64| None);
+        assert_eq!(time, exif.datetime_original());
+    }
+}
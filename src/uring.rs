@@ -0,0 +1,377 @@
+//! Parses the metadata of many files using
+//! [io_uring](https://man7.org/linux/man-pages/man7/io_uring.7.html), Linux
+//! only. Enabled by the `io-uring` feature flag.
+//!
+//! The reads of a batch of files (their headers first, then the rest of
+//! their Exif data) are submitted to the kernel together, so indexing many
+//! small files costs a few syscalls per batch rather than several per file.
+
+use std::{fmt::Debug, fs::File, io, os::fd::AsRawFd, path::Path};
+
+use io_uring::{opcode, squeue, types, IoUring};
+use nom::Needed;
+
+use crate::{
+    error::convert_parse_error, file::FileFormat, media::MIN_GROW_SIZE, slice::SubsliceRange,
+    MediaInfo, MediaParser,
+};
+
+/// Number of files whose reads are submitted together.
+const QUEUE_DEPTH: u32 = 64;
+
+/// Parses files with the reads batched through an io_uring instance.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::{uring::UringLoader, *};
+///
+/// let Ok(mut loader) = UringLoader::new(MediaParser::default()) else {
+///     return; // io_uring isn't available, e.g. in a container
+/// };
+/// let paths = ["./testdata/exif.jpg", "./testdata/meta.mov"];
+/// let mut results = loader.parse_files(&paths).into_iter();
+///
+/// let iter = results.next().unwrap().unwrap().unwrap().into_exif().unwrap();
+/// assert_eq!(Exif::from(iter).get(ExifTag::Make).unwrap().to_string(), "vivo");
+/// let track = results.next().unwrap().unwrap().unwrap().into_track();
+/// assert!(track.is_some());
+/// ```
+pub struct UringLoader {
+    /// `None` once the ring has failed and couldn't be recreated.
+    ring: Option<IoUring>,
+    parser: MediaParser,
+}
+
+impl Debug for UringLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UringLoader")
+            .field("parser", &self.parser)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UringLoader {
+    /// Creates a loader which parses files with the limits, buffer pool and
+    /// statistics of `parser`.
+    ///
+    /// Fails if io_uring isn't available, e.g. on kernels older than 5.6, or
+    /// when it's disabled by a seccomp policy.
+    pub fn new(parser: MediaParser) -> crate::Result<Self> {
+        let ring = IoUring::new(QUEUE_DEPTH)?;
+        Ok(Self {
+            ring: Some(ring),
+            parser,
+        })
+    }
+
+    /// Returns the parser, e.g. to call
+    /// [`MediaParser::take_stats`](crate::MediaParser::take_stats).
+    pub fn parser(&self) -> &MediaParser {
+        &self.parser
+    }
+
+    /// Parses the files at `paths` like
+    /// [`parse_media`](crate::parse_media), and returns the results in the
+    /// same order.
+    ///
+    /// The Exif data of images is read through io_uring. The metadata of
+    /// videos is located by seeking from atom to atom, so videos are read
+    /// with regular reads once their format has been detected.
+    ///
+    /// If the ring fails, the files of the current batch fail with its
+    /// error, and the following files are parsed with regular reads if the
+    /// ring can't be recreated.
+    pub fn parse_files<P: AsRef<Path>>(
+        &mut self,
+        paths: &[P],
+    ) -> Vec<crate::Result<Option<MediaInfo>>> {
+        let mut results = Vec::with_capacity(paths.len());
+        for chunk in paths.chunks(QUEUE_DEPTH as usize) {
+            results.extend(self.parse_chunk(chunk));
+        }
+        results
+    }
+
+    fn parse_chunk<P: AsRef<Path>>(
+        &mut self,
+        paths: &[P],
+    ) -> Vec<crate::Result<Option<MediaInfo>>> {
+        let parser = &self.parser;
+        let Some(ring) = self.ring.as_mut() else {
+            return paths
+                .iter()
+                .map(|path| {
+                    File::open(path)
+                        .map_err(crate::Error::from)
+                        .and_then(|f| parser.parse_media(f))
+                })
+                .collect();
+        };
+        let mut loads = paths
+            .iter()
+            .map(|path| match File::open(path) {
+                Ok(file) => {
+                    let size = parser.init_read_size();
+                    let mut buf = parser.alloc_buffer(size);
+                    parser.reserve_buffer(&mut buf, size);
+                    Load::Reading(Reading {
+                        file,
+                        buf,
+                        format: None,
+                        to_read: size,
+                    })
+                }
+                Err(e) => Load::Done(Err(e.into())),
+            })
+            .collect::<Vec<_>>();
+
+        loop {
+            let mut submitted = 0;
+            for (idx, load) in loads.iter_mut().enumerate() {
+                let Load::Reading(reading) = load else {
+                    continue;
+                };
+                let entry = reading.read_entry().user_data(idx as u64);
+                // SAFETY: the buffer has room for the read, and it's neither
+                // accessed nor moved until the read has completed, since all
+                // the submitted reads are waited for below. There are at
+                // most `QUEUE_DEPTH` loads, so the queue can't be full.
+                unsafe { ring.submission().push(&entry) }
+                    .expect("submission queue is large enough");
+                submitted += 1;
+            }
+            if submitted == 0 {
+                break;
+            }
+
+            let mut completed = 0;
+            while completed < submitted {
+                match ring.submit_and_wait(submitted - completed) {
+                    Ok(_) => (),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        abort(&mut self.ring, &mut loads, submitted - completed, e);
+                        return finish(loads, parser);
+                    }
+                }
+                for cqe in ring.completion() {
+                    completed += 1;
+                    let load = &mut loads[cqe.user_data() as usize]; // Safe-slice
+                    if let Load::Reading(reading) = std::mem::replace(load, Load::Pending) {
+                        *load = reading.complete(cqe.result(), parser);
+                    }
+                }
+            }
+        }
+
+        finish(loads, parser)
+    }
+}
+
+/// Fails the files of a batch whose reads are still pending after the ring
+/// failed with `e`.
+///
+/// The reads in flight may write to their buffers at any time, so their
+/// completions are waited for. If the ring fails again, the buffers and files
+/// of the pending reads are leaked, and the ring is recreated so that their
+/// completions aren't mistaken for the ones of the next batch.
+fn abort(slot: &mut Option<IoUring>, loads: &mut [Load], mut in_flight: usize, e: io::Error) {
+    let error = || crate::Error::from(io::Error::new(e.kind(), e.to_string()));
+    if let Some(ring) = slot.as_mut() {
+        loop {
+            for cqe in ring.completion() {
+                in_flight -= 1;
+                loads[cqe.user_data() as usize] = Load::Done(Err(error())); // Safe-slice
+            }
+            if in_flight == 0 {
+                break;
+            }
+            match ring.submit_and_wait(in_flight) {
+                Ok(_) => (),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(_) => break,
+            }
+        }
+    }
+
+    // The loads which are still reading are either in flight, or waiting
+    // for their next read to be submitted
+    for load in loads.iter_mut() {
+        *load = match std::mem::replace(load, Load::Pending) {
+            Load::Reading(reading) => {
+                if in_flight > 0 {
+                    std::mem::forget(reading);
+                }
+                Load::Done(Err(error()))
+            }
+            load => load,
+        };
+    }
+    if in_flight > 0 {
+        *slot = IoUring::new(QUEUE_DEPTH).ok();
+    }
+}
+
+fn finish(loads: Vec<Load>, parser: &MediaParser) -> Vec<crate::Result<Option<MediaInfo>>> {
+    loads
+        .into_iter()
+        .map(|load| match load {
+            Load::Done(res) => res,
+            // The file position is still at the start, since io_uring reads
+            // at explicit offsets
+            Load::Video(file) => parser.parse_media(file),
+            Load::Reading(_) | Load::Pending => unreachable!("all reads have completed"),
+        })
+        .collect()
+}
+
+/// The state of a file of a batch.
+#[allow(clippy::large_enum_variant)]
+enum Load {
+    Reading(Reading),
+    Done(crate::Result<Option<MediaInfo>>),
+    /// Videos are parsed with regular reads.
+    Video(File),
+    /// Placeholder while a read completes.
+    Pending,
+}
+
+struct Reading {
+    file: File,
+    buf: Vec<u8>,
+    format: Option<FileFormat>,
+    to_read: usize,
+}
+
+impl Reading {
+    /// Returns a read of `to_read` more bytes into the buffer.
+    fn read_entry(&mut self) -> squeue::Entry {
+        let len = self.buf.len();
+        let to_read = self.to_read.min(self.buf.capacity() - len);
+        let to_read = u32::try_from(to_read).unwrap_or(u32::MAX);
+        // SAFETY: `len` is within the allocation of the buffer
+        let ptr = unsafe { self.buf.as_mut_ptr().add(len) };
+        opcode::Read::new(types::Fd(self.file.as_raw_fd()), ptr, to_read)
+            .offset(len as u64)
+            .build()
+    }
+
+    /// Handles the result of the read returned by [`Self::read_entry`].
+    fn complete(mut self, res: i32, parser: &MediaParser) -> Load {
+        let n = match usize::try_from(res) {
+            Ok(n) => n,
+            Err(_) => return Load::Done(Err(io::Error::from_raw_os_error(-res).into())),
+        };
+        // SAFETY: the kernel has initialized `n` bytes after the buffer's
+        // length, within the capacity (see `read_entry`).
+        unsafe { self.buf.set_len(self.buf.len() + n) };
+        parser.stats.read(n, self.buf.len());
+
+        if n == 0 {
+            let needed = if self.buf.is_empty() {
+                None
+            } else {
+                Some(self.to_read)
+            };
+            return Load::Done(Err(crate::Error::Truncated { needed }));
+        }
+
+        let ff = match self.format {
+            Some(ff) => ff,
+            None => match FileFormat::try_from(&self.buf[..]) {
+                Ok(FileFormat::QuickTime | FileFormat::MP4 | FileFormat::Matroska) => {
+                    return Load::Video(self.file)
+                }
+                Ok(ff) => {
+                    self.format = Some(ff);
+                    ff
+                }
+                Err(e) => return Load::Done(Err(e)),
+            },
+        };
+
+        let needed = match ff.extract_exif_data(&self.buf[..]) {
+            Ok((_, data)) => {
+                let range = data.and_then(|x| SubsliceRange::subslice_range(&self.buf[..], x));
+                let res = range
+                    .map(|range| parser.input_to_iter(parser.buffer_to_input(self.buf, range)))
+                    .transpose();
+                return Load::Done(res.map(|x| x.map(MediaInfo::Exif)));
+            }
+            Err(nom::Err::Incomplete(Needed::Size(n))) => n.get(),
+            Err(nom::Err::Incomplete(Needed::Unknown)) => MIN_GROW_SIZE,
+            Err(e) => return Load::Done(Err(convert_parse_error(e, "read exif failed"))),
+        };
+        match parser.grow_size(self.buf.len(), needed) {
+            Ok(size) => {
+                parser.reserve_buffer(&mut self.buf, size);
+                self.to_read = size;
+                Load::Reading(self)
+            }
+            Err(e) => Load::Done(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Exif;
+
+    #[test]
+    fn uring_parse_files() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let Ok(mut loader) = UringLoader::new(MediaParser::default()) else {
+            tracing::warn!("io_uring isn't available, skipped");
+            return;
+        };
+
+        let names = [
+            "exif.jpg",
+            "exif.heic",
+            "meta.mov",
+            "meta.mp4",
+            "no-exif.jpg",
+            "broken.jpg",
+            "exif.png",
+            "missing.jpg",
+        ];
+        let paths = names.map(|x| format!("./testdata/{x}"));
+        let results = loader.parse_files(&paths);
+        assert_eq!(results.len(), names.len());
+
+        let parser = MediaParser::default();
+        for (path, res) in paths.iter().zip(results) {
+            let expected = File::open(path)
+                .map_err(crate::Error::from)
+                .and_then(|f| parser.parse_media(f));
+            match (res, expected) {
+                (Ok(Some(MediaInfo::Exif(iter))), Ok(Some(MediaInfo::Exif(expected)))) => {
+                    let exif: Exif = iter.into();
+                    let expected: Exif = expected.into();
+                    assert_eq!(exif.entries(), expected.entries(), "{path}");
+                }
+                (Ok(Some(MediaInfo::Track(iter))), Ok(Some(MediaInfo::Track(expected)))) => {
+                    assert_eq!(iter.count(), expected.count(), "{path}");
+                }
+                (Ok(None), Ok(None)) | (Err(_), Err(_)) => (),
+                (res, expected) => panic!("{path}: {res:?} != {expected:?}"),
+            }
+        }
+
+        // Several batches
+        loader.parser().take_stats();
+        let paths = vec!["./testdata/exif.heic"; QUEUE_DEPTH as usize * 2 + 1];
+        let results = loader.parse_files(&paths);
+        assert_eq!(results.len(), paths.len());
+        assert!(results.iter().all(|x| matches!(x, Ok(Some(_)))));
+        assert_eq!(loader.parser().take_stats().files, paths.len() as u64);
+
+        // Regular reads once the ring is gone
+        loader.ring = None;
+        let results = loader.parse_files(&paths[..2]);
+        assert!(results.iter().all(|x| matches!(x, Ok(Some(_)))));
+    }
+}
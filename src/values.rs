@@ -1,13 +1,16 @@
-use std::{fmt::Display, string::FromUtf8Error};
+use alloc::string::FromUtf8Error;
+use core::fmt::Display;
 
-use chrono::{offset::LocalResult, DateTime, FixedOffset, Local, NaiveDateTime, TimeZone as _};
+#[cfg(feature = "std")]
+use chrono::{offset::LocalResult, Local, TimeZone as _};
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
 
 use nom::number::Endianness;
-#[cfg(feature = "json_dump")]
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize, Serializer};
 use thiserror::Error;
 
-use crate::{ExifTag};
+use crate::{prelude::*, ExifTag};
 
 /// Represent a parsed entry value.
 #[derive(Debug, Clone, PartialEq)]
@@ -55,11 +58,23 @@ pub(crate) enum EntryError {
 
     #[error("Failed to parse IFD entry; unsupported: {0}")]
     Unsupported(String),
+
+    #[error("{0}")]
+    LimitExceeded(crate::Limit),
+
+    #[cfg(feature = "std")]
+    #[error("{}", if *timed_out { "parsing timed out" } else { "parsing cancelled" })]
+    Cancelled { timed_out: bool },
 }
 
 impl From<EntryError> for crate::Error {
     fn from(value: EntryError) -> Self {
-        Self::InvalidEntry(value.into())
+        match value {
+            EntryError::LimitExceeded(limit) => Self::LimitExceeded { limit },
+            #[cfg(feature = "std")]
+            EntryError::Cancelled { timed_out } => Self::Cancelled { timed_out },
+            value => Self::InvalidEntry(value.into()),
+        }
     }
 }
 
@@ -145,14 +160,8 @@ impl EntryValue {
                     DateTime::parse_from_str(&s, "%Y:%m:%d %H:%M:%S %z")?
                 } else {
                     let t = NaiveDateTime::parse_from_str(&s, "%Y:%m:%d %H:%M:%S")?;
-                    let t = Local.from_local_datetime(&t);
-                    let t = if let LocalResult::Single(t) = t {
-                        Ok(t)
-                    } else {
-                        Err(Error::InvalidData(format!("parse time failed: {s}")))
-                    }?;
-
-                    t.with_timezone(t.offset())
+                    local_time(t)
+                        .ok_or_else(|| Error::InvalidData(format!("parse time failed: {s}")))?
                 };
 
                 return Ok(EntryValue::Time(t));
@@ -264,6 +273,12 @@ impl EntryValue {
         }
     }
 
+    /// Same as [`Self::as_time`], but returns a `time::OffsetDateTime`.
+    #[cfg(feature = "time")]
+    pub fn as_offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        self.as_time().as_ref().and_then(to_offset_datetime)
+    }
+
     pub fn as_u8(&self) -> Option<u8> {
         match self {
             EntryValue::U8(v) => Some(*v),
@@ -337,6 +352,87 @@ impl EntryValue {
             None
         }
     }
+
+    /// Converts an integer value of any width or sign to `u32`, if it fits.
+    ///
+    /// Unlike [`Self::as_u32`], this works for tags whose data format varies
+    /// between files, e.g. `ImageWidth` may be a `SHORT` or a `LONG`.
+    pub fn to_u32(&self) -> Option<u32> {
+        match *self {
+            EntryValue::U8(v) => Some(v.into()),
+            EntryValue::U16(v) => Some(v.into()),
+            EntryValue::U32(v) => Some(v),
+            EntryValue::U64(v) => v.try_into().ok(),
+            EntryValue::I8(v) => v.try_into().ok(),
+            EntryValue::I16(v) => v.try_into().ok(),
+            EntryValue::I32(v) => v.try_into().ok(),
+            EntryValue::I64(v) => v.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Converts any numeric value, including rationals, to `f64`.
+    ///
+    /// Returns `None` for rationals with a zero denominator.
+    pub fn to_f64(&self) -> Option<f64> {
+        match *self {
+            EntryValue::U8(v) => Some(v.into()),
+            EntryValue::U16(v) => Some(v.into()),
+            EntryValue::U32(v) => Some(v.into()),
+            EntryValue::U64(v) => Some(v as f64),
+            EntryValue::I8(v) => Some(v.into()),
+            EntryValue::I16(v) => Some(v.into()),
+            EntryValue::I32(v) => Some(v.into()),
+            EntryValue::I64(v) => Some(v as f64),
+            EntryValue::F32(v) => Some(v.into()),
+            EntryValue::F64(v) => Some(v),
+            EntryValue::URational(v) if v.1 != 0 => Some(v.as_float()),
+            EntryValue::IRational(v) if v.1 != 0 => Some(v.as_float()),
+            _ => None,
+        }
+    }
+
+    /// Returns an unsigned rational value, single element arrays and
+    /// non-negative signed rationals are converted as well.
+    pub fn to_urational(&self) -> Option<URational> {
+        match self {
+            EntryValue::URational(v) => Some(*v),
+            EntryValue::URationalArray(v) if v.len() == 1 => Some(v[0]),
+            EntryValue::IRational(v) if v.0 >= 0 && v.1 >= 0 => Some((*v).into()),
+            _ => None,
+        }
+    }
+
+    /// Converts an integer value of any width or sign to `i64`, if it fits
+    /// (only `U64` values may not).
+    pub fn to_i64(&self) -> Option<i64> {
+        match *self {
+            EntryValue::U8(v) => Some(v.into()),
+            EntryValue::U16(v) => Some(v.into()),
+            EntryValue::U32(v) => Some(v.into()),
+            EntryValue::U64(v) => v.try_into().ok(),
+            EntryValue::I8(v) => Some(v.into()),
+            EntryValue::I16(v) => Some(v.into()),
+            EntryValue::I32(v) => Some(v.into()),
+            EntryValue::I64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the `(numerator, denominator)` of a rational value, signed or
+    /// unsigned (single element arrays included). Integers are returned as
+    /// `(v, 1)`.
+    ///
+    /// The denominator may be zero, it's up to the caller to handle it.
+    pub fn to_rational(&self) -> Option<(i64, i64)> {
+        match self {
+            EntryValue::URational(v) => Some((v.0.into(), v.1.into())),
+            EntryValue::IRational(v) => Some((v.0.into(), v.1.into())),
+            EntryValue::URationalArray(v) if v.len() == 1 => Some((v[0].0.into(), v[0].1.into())),
+            EntryValue::IRationalArray(v) if v.len() == 1 => Some((v[0].0.into(), v[0].1.into())),
+            _ => self.to_i64().map(|v| (v, 1)),
+        }
+    }
 }
 
 /// # Exif Data format
@@ -387,14 +483,14 @@ impl TryFrom<u16> for DataFormat {
     type Error = Error;
     fn try_from(v: u16) -> Result<Self, Self::Error> {
         if v >= Self::U8 as u16 && v <= Self::F64 as u16 {
-            Ok(unsafe { std::mem::transmute::<u16, Self>(v) })
+            Ok(unsafe { core::mem::transmute::<u16, Self>(v) })
         } else {
             Err(Error::InvalidData(format!("data format {v}")))
         }
     }
 }
 
-#[cfg(feature = "json_dump")]
+#[cfg(feature = "serde")]
 impl Serialize for EntryValue {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -405,14 +501,14 @@ impl Serialize for EntryValue {
 }
 
 impl Display for EntryValue {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             EntryValue::Text(v) => f.write_str(v),
             EntryValue::URational(v) => {
-                write!(f, "{}/{} ({:.04})", v.0, v.1, v.0 as f64 / v.1 as f64)
+                write!(f, "{}/{} ({:.04})", v.0, v.1, v.as_float())
             }
             EntryValue::IRational(v) => {
-                write!(f, "{}/{} ({:.04})", v.0, v.1, v.0 as f64 / v.1 as f64)
+                write!(f, "{}/{} ({:.04})", v.0, v.1, v.as_float())
             }
             EntryValue::U32(v) => Display::fmt(&v, f),
             EntryValue::U16(v) => Display::fmt(&v, f),
@@ -547,6 +643,41 @@ impl From<&str> for EntryValue {
     }
 }
 
+impl From<DateTime<FixedOffset>> for EntryValue {
+    fn from(value: DateTime<FixedOffset>) -> Self {
+        EntryValue::Time(value)
+    }
+}
+
+/// Converts to `EntryValue::Time`, keeping the UTC offset (sub-second
+/// offsets are dropped).
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for EntryValue {
+    fn from(value: time::OffsetDateTime) -> Self {
+        let offset = FixedOffset::east_opt(value.offset().whole_seconds())
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        // Safe unwrap: any `OffsetDateTime` is in the range of `chrono`
+        let t = DateTime::from_timestamp(value.unix_timestamp(), value.nanosecond())
+            .unwrap()
+            .with_timezone(&offset);
+        EntryValue::Time(t)
+    }
+}
+
+/// Converts a `chrono` date/time to a `time::OffsetDateTime`, keeping the
+/// UTC offset. `None` if it's out of the range of `time` (years beyond
+/// ±9999).
+#[cfg(feature = "time")]
+pub(crate) fn to_offset_datetime(t: &DateTime<FixedOffset>) -> Option<time::OffsetDateTime> {
+    let offset = time::UtcOffset::from_whole_seconds(t.offset().local_minus_utc()).ok()?;
+    let nanos = t.timestamp() as i128 * 1_000_000_000 + t.timestamp_subsec_nanos() as i128;
+    Some(
+        time::OffsetDateTime::from_unix_timestamp_nanos(nanos)
+            .ok()?
+            .to_offset(offset),
+    )
+}
+
 impl From<(u32, u32)> for EntryValue {
     fn from(value: (u32, u32)) -> Self {
         Self::URational(value.into())
@@ -559,24 +690,260 @@ impl From<(i32, i32)> for EntryValue {
     }
 }
 
-// #[cfg_attr(feature = "json_dump", derive(Serialize, Deserialize))]
+fn conversion_error(value: &EntryValue, ty: &str) -> crate::Error {
+    crate::Error::InvalidEntry(format!("can't convert {value:?} to {ty}").into())
+}
+
+/// Integers of any width or sign are converted if the value fits, e.g.
+/// `U16(3)` converts to `3u8`, `I32(-1)` doesn't convert to `u32`.
+macro_rules! impl_try_from_integer {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<&EntryValue> for $t {
+                type Error = crate::Error;
+
+                fn try_from(value: &EntryValue) -> Result<Self, Self::Error> {
+                    let v = match *value {
+                        EntryValue::U64(v) => v.try_into().ok(),
+                        _ => value.to_i64().and_then(|v| v.try_into().ok()),
+                    };
+                    v.ok_or_else(|| conversion_error(value, stringify!($t)))
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// Any numeric value, including rationals, is converted, see
+/// [`EntryValue::to_f64`].
+impl TryFrom<&EntryValue> for f64 {
+    type Error = crate::Error;
+
+    fn try_from(value: &EntryValue) -> Result<Self, Self::Error> {
+        value.to_f64().ok_or_else(|| conversion_error(value, "f64"))
+    }
+}
+
+/// Any numeric value, including rationals, is converted (possibly losing
+/// precision), see [`EntryValue::to_f64`].
+impl TryFrom<&EntryValue> for f32 {
+    type Error = crate::Error;
+
+    fn try_from(value: &EntryValue) -> Result<Self, Self::Error> {
+        match *value {
+            EntryValue::F32(v) => Ok(v),
+            _ => value
+                .to_f64()
+                .map(|v| v as f32)
+                .ok_or_else(|| conversion_error(value, "f32")),
+        }
+    }
+}
+
+// #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 // #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 // pub struct URational(pub u32, pub u32);
 
 pub type URational = Rational<u32>;
 pub type IRational = Rational<i32>;
 
-#[cfg_attr(feature = "json_dump", derive(Serialize, Deserialize))]
+/// A rational number `numerator / denominator`, as stored by Exif:
+/// [`URational`] (TIFF `RATIONAL`) or [`IRational`] (TIFF `SRATIONAL`).
+///
+/// The value is kept exactly as it was read, e.g. `10/20` isn't reduced and
+/// the denominator may be zero, see [`Rational::normalized`].
+///
+/// Arithmetic operators are computed exactly, then the result is reduced;
+/// results which still don't fit are approximated (see
+/// [`Rational::from_f64`]) instead of overflowing.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let r = URational::new(10, 20);
+/// assert_eq!(r.normalized(), URational::new(1, 2));
+/// assert_eq!(r.to_f64(), Some(0.5));
+/// assert_eq!(URational::new(1, 0).to_f64(), None);
+///
+/// // Some cameras write giant numerators and denominators
+/// let r = URational::new(4_000_000_000, 3_000_000_000) * URational::new(3, 1);
+/// assert_eq!(r, URational::new(4, 1));
+///
+/// assert_eq!(IRational::from_f64(-0.75), Some(IRational::new(-3, 4)));
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub struct Rational<T>(pub T, pub T);
 
+impl<T> Rational<T> {
+    /// Creates the rational number `numerator / denominator`.
+    pub const fn new(numerator: T, denominator: T) -> Self {
+        Self(numerator, denominator)
+    }
+}
+
 impl<T> Rational<T>
 where
     T: Copy + Into<f64>,
 {
+    /// Converts to `f64`, a zero denominator yields an infinite value or NaN.
     pub fn as_float(&self) -> f64 {
-        std::convert::Into::<f64>::into(self.0) / std::convert::Into::<f64>::into(self.1)
+        core::convert::Into::<f64>::into(self.0) / core::convert::Into::<f64>::into(self.1)
+    }
+
+    /// Converts to `f64`, `None` if the denominator is zero.
+    pub fn to_f64(&self) -> Option<f64> {
+        (self.1.into() != 0.0).then(|| self.as_float())
+    }
+}
+
+macro_rules! impl_rational {
+    ($t:ty) => {
+        impl Rational<$t> {
+            /// Reduces to lowest terms with a positive denominator, e.g.
+            /// `10/-4` becomes `-5/2` and `0/7` becomes `0/1`. Values with a
+            /// zero denominator are returned as is.
+            pub fn normalized(&self) -> Self {
+                if self.1 == 0 {
+                    return *self;
+                }
+                Self::from_ratio(self.0.into(), self.1.into())
+            }
+
+            /// Returns the closest rational number to `value` whose numerator
+            /// and denominator fit, computed with continued fractions.
+            ///
+            /// Returns `None` if `value` is NaN, infinite, or out of range.
+            pub fn from_f64(value: f64) -> Option<Self> {
+                if !value.is_finite() || value < <$t>::MIN as f64 || value > <$t>::MAX as f64 {
+                    return None;
+                }
+
+                let max = <$t>::MAX as u64;
+                // The numerator of a negative value may be `-MIN`, which is
+                // `MAX + 1` for signed types
+                let max_numerator = if value < 0.0 {
+                    (<$t>::MIN as i128).unsigned_abs() as u64
+                } else {
+                    max
+                };
+                let mut x = value.abs();
+                // The two last convergents
+                let (mut h0, mut h1) = (0_u64, 1_u64);
+                let (mut k0, mut k1) = (1_u64, 0_u64);
+                loop {
+                    let a = x.floor() as u64;
+                    let h2 = a.checked_mul(h1).and_then(|h| h.checked_add(h0));
+                    let k2 = a.checked_mul(k1).and_then(|k| k.checked_add(k0));
+                    match (h2, k2) {
+                        (Some(h2), Some(k2)) if h2 <= max_numerator && k2 <= max => {
+                            (h0, h1) = (h1, h2);
+                            (k0, k1) = (k1, k2);
+                        }
+                        _ => break,
+                    }
+
+                    let frac = x - x.floor();
+                    if frac < f64::EPSILON || h1 as f64 / k1 as f64 == value.abs() {
+                        break;
+                    }
+                    x = 1.0 / frac;
+                }
+
+                // Negated before the cast, since `-MIN` doesn't fit
+                let numerator = if value < 0.0 {
+                    -(h1 as i128)
+                } else {
+                    h1 as i128
+                };
+                Some(Self(numerator as $t, k1 as $t))
+            }
+
+            /// Creates a normalized rational number from `n / d`, which is
+            /// approximated if it doesn't fit.
+            fn from_ratio(mut n: i128, mut d: i128) -> Self {
+                const MIN: i128 = <$t>::MIN as i128;
+                const MAX: i128 = <$t>::MAX as i128;
+
+                if d < 0 {
+                    (n, d) = (-n, -d);
+                }
+                if n < 0 && MIN == 0 {
+                    return Self(0, 1);
+                }
+                if d == 0 {
+                    return Self(n.signum().clamp(MIN, MAX) as $t, 0);
+                }
+
+                let gcd = gcd(n.unsigned_abs(), d.unsigned_abs()) as i128;
+                let (n, d) = (n / gcd, d / gcd);
+                if (MIN..=MAX).contains(&n) && d <= MAX {
+                    return Self(n as $t, d as $t);
+                }
+                Self::from_f64(n as f64 / d as f64)
+                    .unwrap_or(Self(if n < 0 { <$t>::MIN } else { <$t>::MAX }, 1))
+            }
+        }
+
+        impl core::ops::Mul for Rational<$t> {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self {
+                Self::from_ratio(
+                    i128::from(self.0) * i128::from(rhs.0),
+                    i128::from(self.1) * i128::from(rhs.1),
+                )
+            }
+        }
+
+        impl core::ops::Div for Rational<$t> {
+            type Output = Self;
+
+            fn div(self, rhs: Self) -> Self {
+                Self::from_ratio(
+                    i128::from(self.0) * i128::from(rhs.1),
+                    i128::from(self.1) * i128::from(rhs.0),
+                )
+            }
+        }
+
+        impl core::ops::Add for Rational<$t> {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self::from_ratio(
+                    i128::from(self.0) * i128::from(rhs.1) + i128::from(rhs.0) * i128::from(self.1),
+                    i128::from(self.1) * i128::from(rhs.1),
+                )
+            }
+        }
+
+        /// Results below zero saturate to `0/1` for [`URational`].
+        impl core::ops::Sub for Rational<$t> {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self::from_ratio(
+                    i128::from(self.0) * i128::from(rhs.1) - i128::from(rhs.0) * i128::from(self.1),
+                    i128::from(self.1) * i128::from(rhs.1),
+                )
+            }
+        }
+    };
+}
+
+impl_rational!(u32);
+impl_rational!(i32);
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
     }
+    a
 }
 
 impl<T> From<(T, T)> for Rational<T>
@@ -603,7 +970,19 @@ impl From<IRational> for URational {
     }
 }
 
-fn get_cstr(data: &[u8]) -> std::result::Result<String, FromUtf8Error> {
+/// Converts a time without an offset, which is a local time of the system.
+/// Without `std` there is no local time zone, and it's taken as UTC.
+fn local_time(t: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    #[cfg(feature = "std")]
+    match Local.from_local_datetime(&t) {
+        LocalResult::Single(t) => Some(t.fixed_offset()),
+        _ => None,
+    }
+    #[cfg(not(feature = "std"))]
+    Some(t.and_utc().fixed_offset())
+}
+
+fn get_cstr(data: &[u8]) -> core::result::Result<String, FromUtf8Error> {
     String::from_utf8(
         data.iter()
             .take_while(|b| **b != 0)
@@ -622,13 +1001,13 @@ impl TryFromBytes for u32 {
         fn make_err<T>() -> Error {
             Error::InvalidData(format!(
                 "data is too small to convert to {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
             ))
         }
         match endian {
             Endianness::Big => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_be_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -636,7 +1015,7 @@ impl TryFromBytes for u32 {
             }
             Endianness::Little => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_le_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -652,13 +1031,13 @@ impl TryFromBytes for i32 {
         fn make_err<T>() -> Error {
             Error::InvalidData(format!(
                 "data is too small to convert to {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
             ))
         }
         match endian {
             Endianness::Big => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_be_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -666,7 +1045,7 @@ impl TryFromBytes for i32 {
             }
             Endianness::Little => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_le_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -682,13 +1061,13 @@ impl TryFromBytes for u16 {
         fn make_err<T>() -> Error {
             Error::InvalidData(format!(
                 "data is too small to convert to {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
             ))
         }
         match endian {
             Endianness::Big => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_be_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -696,7 +1075,7 @@ impl TryFromBytes for u16 {
             }
             Endianness::Little => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_le_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -712,13 +1091,13 @@ impl TryFromBytes for i16 {
         fn make_err<T>() -> Error {
             Error::InvalidData(format!(
                 "data is too small to convert to {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
             ))
         }
         match endian {
             Endianness::Big => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_be_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -726,7 +1105,7 @@ impl TryFromBytes for i16 {
             }
             Endianness::Little => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_le_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -742,13 +1121,13 @@ impl TryFromBytes for f32 {
         fn make_err<T>() -> Error {
             Error::InvalidData(format!(
                 "data is too small to convert to {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
             ))
         }
         match endian {
             Endianness::Big => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_be_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -756,7 +1135,7 @@ impl TryFromBytes for f32 {
             }
             Endianness::Little => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_le_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -772,13 +1151,13 @@ impl TryFromBytes for f64 {
         fn make_err<T>() -> Error {
             Error::InvalidData(format!(
                 "data is too small to convert to {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
             ))
         }
         match endian {
             Endianness::Big => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_be_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -786,7 +1165,7 @@ impl TryFromBytes for f64 {
             }
             Endianness::Little => {
                 let (int_bytes, _) = bs
-                    .split_at_checked(std::mem::size_of::<Self>())
+                    .split_at_checked(core::mem::size_of::<Self>())
                     .ok_or_else(make_err::<Self>)?;
                 Ok(Self::from_le_bytes(
                     int_bytes.try_into().map_err(|_| make_err::<Self>())?,
@@ -818,6 +1197,123 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn conversions() {
+        assert_eq!(EntryValue::U16(3).to_u32(), Some(3));
+        assert_eq!(EntryValue::U64(u64::MAX).to_u32(), None);
+        assert_eq!(EntryValue::I32(-1).to_u32(), None);
+        assert_eq!(EntryValue::I8(7).to_u32(), Some(7));
+        assert_eq!(EntryValue::F32(1.0).to_u32(), None);
+
+        assert_eq!(EntryValue::I16(-2).to_f64(), Some(-2.0));
+        assert_eq!(EntryValue::URational((1, 4).into()).to_f64(), Some(0.25));
+        assert_eq!(EntryValue::IRational((-1, 2).into()).to_f64(), Some(-0.5));
+        assert_eq!(EntryValue::URational((1, 0).into()).to_f64(), None);
+        assert_eq!(EntryValue::Text("1".into()).to_f64(), None);
+
+        let r = URational::from((1, 3));
+        assert_eq!(EntryValue::URational(r).to_urational(), Some(r));
+        assert_eq!(EntryValue::URationalArray(vec![r]).to_urational(), Some(r));
+        assert_eq!(EntryValue::URationalArray(vec![r, r]).to_urational(), None);
+        assert_eq!(EntryValue::IRational((1, 3).into()).to_urational(), Some(r));
+        assert_eq!(EntryValue::IRational((-1, 3).into()).to_urational(), None);
+    }
+
+    #[test]
+    fn numeric_conversions() {
+        assert_eq!(EntryValue::U8(3).to_i64(), Some(3));
+        assert_eq!(EntryValue::I16(-3).to_i64(), Some(-3));
+        assert_eq!(EntryValue::U64(u64::MAX).to_i64(), None);
+        assert_eq!(EntryValue::F64(1.0).to_i64(), None);
+        assert_eq!(EntryValue::U32(2).to_f64(), Some(2.0));
+
+        assert_eq!(u8::try_from(&EntryValue::U32(255)).unwrap(), 255);
+        assert!(u8::try_from(&EntryValue::U32(256)).is_err());
+        assert!(u32::try_from(&EntryValue::I32(-1)).is_err());
+        assert_eq!(i32::try_from(&EntryValue::I8(-1)).unwrap(), -1);
+        assert_eq!(u64::try_from(&EntryValue::U64(u64::MAX)).unwrap(), u64::MAX);
+        assert!(i64::try_from(&EntryValue::Text("1".into())).is_err());
+        assert!(u16::try_from(&EntryValue::URational((1, 1).into())).is_err());
+        assert_eq!(
+            f64::try_from(&EntryValue::URational((1, 4).into())).unwrap(),
+            0.25
+        );
+        assert_eq!(f32::try_from(&EntryValue::I16(-2)).unwrap(), -2.0);
+        assert!(f64::try_from(&EntryValue::URational((1, 0).into())).is_err());
+
+        assert_eq!(
+            EntryValue::URational((1, 3).into()).to_rational(),
+            Some((1, 3))
+        );
+        assert_eq!(
+            EntryValue::IRational((-1, 3).into()).to_rational(),
+            Some((-1, 3))
+        );
+        assert_eq!(
+            EntryValue::URationalArray(vec![(5, 2).into()]).to_rational(),
+            Some((5, 2))
+        );
+        assert_eq!(EntryValue::U16(7).to_rational(), Some((7, 1)));
+        assert_eq!(EntryValue::F64(0.5).to_rational(), None);
+    }
+
+    #[test]
+    fn rationals() {
+        assert_eq!(URational::new(10, 20).normalized(), URational::new(1, 2));
+        assert_eq!(IRational::new(10, -4).normalized(), IRational::new(-5, 2));
+        assert_eq!(IRational::new(0, 7).normalized(), IRational::new(0, 1));
+        assert_eq!(URational::new(3, 0).normalized(), URational::new(3, 0));
+
+        assert_eq!(URational::new(1, 4).to_f64(), Some(0.25));
+        assert_eq!(IRational::new(-1, 0).to_f64(), None);
+
+        assert_eq!(URational::from_f64(0.5), Some(URational::new(1, 2)));
+        assert_eq!(URational::from_f64(-0.5), None);
+        assert_eq!(URational::from_f64(f64::NAN), None);
+        assert_eq!(URational::from_f64(5e9), None);
+        assert_eq!(IRational::from_f64(-2.25), Some(IRational::new(-9, 4)));
+        assert_eq!(
+            IRational::from_f64(i32::MIN as f64),
+            Some(IRational::new(i32::MIN, 1))
+        );
+        assert_eq!(
+            IRational::from_f64(i32::MAX as f64),
+            Some(IRational::new(i32::MAX, 1))
+        );
+        assert_eq!(
+            URational::from_f64(u32::MAX as f64),
+            Some(URational::new(u32::MAX, 1))
+        );
+        let r = URational::from_f64(core::f64::consts::PI).unwrap();
+        assert!((r.as_float() - core::f64::consts::PI).abs() < 1e-15);
+
+        let a = URational::new(1, 3);
+        let b = URational::new(1, 6);
+        assert_eq!(a + b, URational::new(1, 2));
+        assert_eq!(a - b, URational::new(1, 6));
+        assert_eq!(b - a, URational::new(0, 1));
+        assert_eq!(a * b, URational::new(1, 18));
+        assert_eq!(a / b, URational::new(2, 1));
+        assert_eq!(a / URational::new(0, 1), URational::new(1, 0));
+        assert_eq!(
+            IRational::new(-1, 3) * IRational::new(3, -2),
+            IRational::new(1, 2)
+        );
+
+        // Giant numerators and denominators
+        let big = URational::new(u32::MAX, u32::MAX - 1);
+        let r = big * big;
+        assert!((r.as_float() - big.as_float().powi(2)).abs() < 1e-12);
+        assert_eq!(
+            URational::new(u32::MAX, 1) * URational::new(2, 1),
+            URational::new(u32::MAX, 1)
+        );
+        assert_eq!(
+            IRational::new(i32::MIN, 1) * IRational::new(2, 1),
+            IRational::new(i32::MIN, 1)
+        );
+    }
+
     #[test]
     fn test_parse_time() {
         let tz = Local::now().format("%:z").to_string();
@@ -849,4 +1345,21 @@ mod tests {
         assert_eq!(t1, t2);
         assert!(t3 > t2);
     }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn offset_datetime() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let t = DateTime::parse_from_rfc3339("2023-07-09T20:36:33.616+08:00").unwrap();
+        let v = EntryValue::Time(t);
+        let ot = v.as_offset_datetime().unwrap();
+        assert_eq!(ot.unix_timestamp(), t.timestamp());
+        assert_eq!(ot.millisecond(), 616);
+        assert_eq!(ot.offset().whole_hours(), 8);
+        assert_eq!(ot.hour(), 20);
+
+        assert_eq!(EntryValue::from(ot), v);
+        assert_eq!(EntryValue::U8(1).as_offset_datetime(), None);
+    }
 }
@@ -0,0 +1,247 @@
+use std::{
+    borrow::Borrow,
+    io::{Read, Seek},
+    ops::ControlFlow,
+};
+
+use crate::{EntryValue, ExifIter, ExifTag, FileFormat, MediaParser, ParsedExifEntry};
+
+/// Calls `visitor` with the Exif entries of `reader` whose tags are in
+/// `tags` (all entries if `tags` is empty), as they are parsed.
+///
+/// Parsing stops as soon as `visitor` returns [`ControlFlow::Break`], or
+/// once every tag of `tags` has been visited, so the rest of the entries
+/// aren't parsed. E.g. getting `DateTimeOriginal` from many files doesn't
+/// parse their GPS or thumbnail IFDs.
+///
+/// Nothing is visited if the image has no Exif data.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+/// use std::ops::ControlFlow;
+///
+/// let f = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// let mut time = None;
+/// visit_exif(f, &[ExifTag::DateTimeOriginal], |entry| {
+///     time = entry.take_value();
+///     ControlFlow::Break(())
+/// })
+/// .unwrap();
+/// assert_eq!(time.unwrap().to_string(), "2023-07-09T20:36:33+08:00");
+/// ```
+pub fn visit_exif<R, F>(reader: R, tags: &[ExifTag], visitor: F) -> crate::Result<()>
+where
+    R: Read,
+    F: FnMut(&ParsedExifEntry) -> ControlFlow<()>,
+{
+    MediaParser::default().visit_exif(reader, None, tags, visitor)
+}
+
+/// Like [`visit_exif`], but for the metadata entries of a QuickTime/MP4
+/// file (see [`parse_metadata_iter`](crate::parse_metadata_iter)), whose
+/// keys are in `keys` (all entries if `keys` is empty).
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+/// use std::ops::ControlFlow;
+///
+/// let f = std::fs::File::open("./testdata/meta.mov").unwrap();
+/// let mut visited = Vec::new();
+/// visit_metadata(f, &["com.apple.quicktime.model", "duration"], |key, value| {
+///     visited.push((key.to_owned(), value.unwrap().to_string()));
+///     ControlFlow::Continue(())
+/// })
+/// .unwrap();
+/// assert_eq!(
+///     visited,
+///     [
+///         ("com.apple.quicktime.model".to_owned(), "iPhone X".to_owned()),
+///         ("duration".to_owned(), "500".to_owned()),
+///     ]
+/// );
+/// ```
+pub fn visit_metadata<R, F>(reader: R, keys: &[&str], visitor: F) -> crate::Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&str, crate::Result<EntryValue>) -> ControlFlow<()>,
+{
+    MediaParser::default().visit_metadata(reader, keys, visitor)
+}
+
+pub(crate) fn read_visit_exif<R, F>(
+    reader: R,
+    format: Option<FileFormat>,
+    parser: &MediaParser,
+    tags: &[ExifTag],
+    mut visitor: F,
+) -> crate::Result<()>
+where
+    R: Read,
+    F: FnMut(&ParsedExifEntry) -> ControlFlow<()>,
+{
+    let Some(iter) = parser.parse_exif(reader, format)? else {
+        return Ok(());
+    };
+    for entry in FilteredEntries::new(iter, tags) {
+        if visitor(&entry).is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_visit_metadata<R, F>(
+    reader: R,
+    parser: &MediaParser,
+    keys: &[&str],
+    mut visitor: F,
+) -> crate::Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&str, crate::Result<EntryValue>) -> ControlFlow<()>,
+{
+    let mut pending = Pending::new(keys);
+    for (key, value) in parser.parse_metadata_iter(reader)? {
+        if !pending.accepts(Some(key.as_str())) {
+            continue;
+        }
+        if visitor(&key, value).is_break() || pending.is_done() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Yields the entries of an [`ExifIter`] whose tags are in `tags` (all
+/// entries if `tags` is empty), and stops once every tag of `tags` has been
+/// yielded, without parsing the rest of the entries.
+pub(crate) struct FilteredEntries<'a> {
+    iter: ExifIter<'a>,
+    pending: Pending<ExifTag>,
+    done: bool,
+}
+
+impl<'a> FilteredEntries<'a> {
+    pub fn new(iter: ExifIter<'a>, tags: &[ExifTag]) -> Self {
+        Self {
+            iter,
+            pending: Pending::new(tags),
+            done: false,
+        }
+    }
+}
+
+impl Iterator for FilteredEntries<'_> {
+    type Item = ParsedExifEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let entry = self.iter.next()?;
+            if self.pending.accepts(entry.tag().as_ref()) {
+                self.done = self.pending.is_done();
+                return Some(entry);
+            }
+        }
+    }
+}
+
+/// The tags (or keys) a visitor is interested in, and which of them haven't
+/// been visited yet.
+struct Pending<T> {
+    wanted: Vec<T>,
+    remaining: Vec<T>,
+}
+
+impl<T: Copy> Pending<T> {
+    fn new(wanted: &[T]) -> Self {
+        Self {
+            wanted: wanted.to_vec(),
+            remaining: wanted.to_vec(),
+        }
+    }
+
+    /// Returns whether an entry should be visited, and marks it as visited.
+    fn accepts<Q>(&mut self, tag: Option<&Q>) -> bool
+    where
+        T: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        if self.wanted.is_empty() {
+            return true;
+        }
+        let Some(tag) = tag else {
+            return false;
+        };
+        self.remaining.retain(|x| x.borrow() != tag);
+        self.wanted.iter().any(|x| x.borrow() == tag)
+    }
+
+    /// Returns whether every wanted tag has been visited.
+    fn is_done(&self) -> bool {
+        !self.wanted.is_empty() && self.remaining.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    use test_case::test_case;
+
+    #[test_case("exif.jpg", &[], 0)]
+    #[test_case("exif.jpg", &[ExifTag::Make, ExifTag::Model], 2)]
+    #[test_case("exif.heic", &[ExifTag::Make, ExifTag::GPSLatitude], 2)]
+    #[test_case("exif.jpg", &[ExifTag::ImageDescription], 0)]
+    #[test_case("no-exif.jpg", &[ExifTag::Make], 0)]
+    fn visit_exif_tags(path: &str, tags: &[ExifTag], visits: usize) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut visited = Vec::new();
+        visit_exif(open_sample(path).unwrap(), tags, |entry| {
+            visited.push(entry.tag());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        if tags.is_empty() {
+            // All the entries
+            let iter = crate::parse_exif(open_sample(path).unwrap(), None).unwrap();
+            assert_eq!(visited.len(), iter.unwrap().count());
+        } else {
+            assert_eq!(visited.len(), visits);
+            assert!(visited.iter().all(|x| x.is_some_and(|x| tags.contains(&x))));
+        }
+    }
+
+    #[test]
+    fn visit_break() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut count = 0;
+        visit_exif(open_sample("exif.jpg").unwrap(), &[], |_| {
+            count += 1;
+            if count == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+        assert_eq!(count, 3);
+
+        let mut keys = Vec::new();
+        visit_metadata(open_sample("meta.mov").unwrap(), &[], |key, _| {
+            keys.push(key.to_owned());
+            ControlFlow::Break(())
+        })
+        .unwrap();
+        assert_eq!(keys, ["com.apple.quicktime.make"]);
+    }
+}
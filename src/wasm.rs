@@ -0,0 +1,100 @@
+//! Parses files held by JavaScript, e.g. the `File` picked for an upload,
+//! read into an `ArrayBuffer`, so that a browser can show its capture time
+//! or GPS info before the upload. `wasm32` only, enabled by the `wasm`
+//! feature flag.
+//!
+//! The data is copied out of the JavaScript memory as it's read: only the
+//! metadata is copied, not the whole file (e.g. the `mdat` atom of a video
+//! is skipped).
+//!
+//! Note that [`Instant::now`](std::time::Instant::now) isn't available on
+//! `wasm32-unknown-unknown`, use
+//! [`MediaParserBuilder::cancel_check`](crate::MediaParserBuilder::cancel_check)
+//! rather than a deadline.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use nom_exif::{wasm, *};
+//!
+//! fn capture_time(buffer: &js_sys::ArrayBuffer) -> Option<String> {
+//!     let data = js_sys::Uint8Array::new(buffer);
+//!     let summary = wasm::probe(&MediaParser::default(), &data).ok()?;
+//!     summary.capture_time.map(|x| x.to_rfc3339())
+//! }
+//! ```
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use js_sys::{ArrayBuffer, Uint8Array};
+
+use crate::{MediaInfo, MediaParser, MediaSummary};
+
+/// A [`Read`] + [`Seek`] reader over a `Uint8Array`, which can be passed to
+/// any of the parsing functions.
+#[derive(Debug, Clone)]
+pub struct Uint8ArrayReader {
+    array: Uint8Array,
+    position: u64,
+}
+
+impl Uint8ArrayReader {
+    pub fn new(array: Uint8Array) -> Self {
+        Self { array, position: 0 }
+    }
+}
+
+impl From<Uint8Array> for Uint8ArrayReader {
+    fn from(array: Uint8Array) -> Self {
+        Self::new(array)
+    }
+}
+
+impl From<&ArrayBuffer> for Uint8ArrayReader {
+    fn from(buffer: &ArrayBuffer) -> Self {
+        Self::new(Uint8Array::new(buffer))
+    }
+}
+
+impl Read for Uint8ArrayReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = u64::from(self.array.length());
+        let start = self.position.min(len);
+        let end = (start + buf.len() as u64).min(len);
+        let n = (end - start) as usize;
+        if n > 0 {
+            // Both are at most `len`, which is a `u32`
+            self.array
+                .subarray(start as u32, end as u32)
+                .copy_to(&mut buf[..n]); // Safe-slice
+        }
+        self.position = end;
+        Ok(n)
+    }
+}
+
+impl Seek for Uint8ArrayReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => u64::from(self.array.length()).checked_add_signed(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position")
+        })?;
+        Ok(self.position)
+    }
+}
+
+/// Like [`parse_media`](crate::parse_media), for a file held by a
+/// `Uint8Array`.
+pub fn parse_media(parser: &MediaParser, data: &Uint8Array) -> crate::Result<Option<MediaInfo>> {
+    parser.parse_media(Uint8ArrayReader::new(data.clone()))
+}
+
+/// Like [`MediaParser::probe`], for a file held by a `Uint8Array`: its
+/// format, dimensions, capture time, and whether it has GPS info.
+pub fn probe(parser: &MediaParser, data: &Uint8Array) -> crate::Result<MediaSummary> {
+    parser.probe(Uint8ArrayReader::new(data.clone()))
+}
@@ -0,0 +1,1718 @@
+//! Exif writing support.
+
+use std::{
+    borrow::Cow,
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+use chrono::Duration;
+use nom::number::Endianness;
+
+use crate::{
+    exif::IfdKind,
+    file::{check_heif, check_qt_mp4},
+    EntryValue, ExifTag, GPSInfo, LatLng, Orientation,
+};
+
+pub use self::atomic::rewrite_file;
+pub use self::batch::MediaWriter;
+pub use self::builder::ExifBuilder;
+pub use self::copy::{copy_metadata, CopyOptions};
+use self::tiff::{Entry, Tiff};
+
+mod atomic;
+mod batch;
+mod builder;
+mod copy;
+mod heif;
+mod jpeg;
+mod mov;
+mod png;
+mod tiff;
+mod webp;
+mod xmp;
+
+/// TIFF tag which holds the XMP packet.
+const XML_PACKET: u16 = 0x02bc;
+
+/// A set of metadata modifications, which can be applied to a file by
+/// [`write_metadata`].
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+/// use chrono::DateTime;
+///
+/// let time = DateTime::parse_from_rfc3339("1987-05-21T10:30:00+08:00").unwrap();
+/// let mut edits = MetadataEdits::new();
+/// edits
+///     .set_exif(ExifTag::Model, "scanner-x1")
+///     .set_exif(ExifTag::DateTimeOriginal, time)
+///     .remove_exif(ExifTag::Software);
+///
+/// let reader = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// let mut output = Vec::new();
+/// write_metadata(reader, &mut output, &edits).unwrap();
+///
+/// let iter = parse_exif(output.as_slice(), None).unwrap().unwrap();
+/// let exif: Exif = iter.into();
+/// assert_eq!(exif.get(ExifTag::Model).unwrap().as_str(), Some("scanner-x1"));
+/// assert_eq!(exif.get(ExifTag::DateTimeOriginal).unwrap().as_time(), Some(time));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataEdits {
+    exif: Vec<ExifEdit>,
+    time_shift: Option<Duration>,
+    location: Option<GPSInfo>,
+    remove_location: bool,
+    xmp: Option<String>,
+    quicktime: Vec<(String, EntryValue)>,
+    thumbnail: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExifEdit {
+    Set {
+        ifd: IfdKind,
+        code: u16,
+        value: EntryValue,
+    },
+    Remove {
+        ifd: IfdKind,
+        code: u16,
+    },
+    /// Sets an entry only if the file doesn't have it.
+    SetIfAbsent {
+        ifd: IfdKind,
+        code: u16,
+        value: EntryValue,
+    },
+    /// Sets an entry copied from another file, whose byte order is `endian`.
+    Copy {
+        ifd: IfdKind,
+        code: u16,
+        entry: Entry,
+        endian: Endianness,
+    },
+}
+
+impl MetadataEdits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if there is no modification at all.
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_empty()
+            && self.time_shift.is_none()
+            && self.location.is_none()
+            && !self.remove_location
+            && self.xmp.is_none()
+            && self.quicktime.is_empty()
+            && self.thumbnail.is_none()
+    }
+
+    /// Returns true if there is any modification to the Exif data.
+    pub(crate) fn has_exif_edits(&self) -> bool {
+        !self.exif.is_empty()
+            || self.time_shift.is_some()
+            || self.location.is_some()
+            || self.remove_location
+            || self.thumbnail.is_some()
+    }
+
+    /// Sets the Exif entry `tag` to `value`. The entry is written to the IFD
+    /// where `tag` conventionally belongs, e.g. [`ExifTag::DateTimeOriginal`]
+    /// goes to the Exif sub-IFD, and [`ExifTag::GPSLatitude`] goes to the GPS
+    /// sub-IFD.
+    ///
+    /// If `value` is an [`EntryValue::Time`] for `DateTimeOriginal`,
+    /// `CreateDate` or `ModifyDate`, the corresponding offset time entry will
+    /// be written as well.
+    pub fn set_exif(&mut self, tag: ExifTag, value: impl Into<EntryValue>) -> &mut Self {
+        self.set_exif_by_tag_code(IfdKind::of_tag(tag), tag.code(), value)
+    }
+
+    /// Sets the Exif entry with raw tag `code` in the specified `ifd`.
+    pub fn set_exif_by_tag_code(
+        &mut self,
+        ifd: IfdKind,
+        code: u16,
+        value: impl Into<EntryValue>,
+    ) -> &mut Self {
+        let value = value.into();
+        if let EntryValue::Time(t) = &value {
+            if let Some(offset_tag) = offset_time_tag(ifd, code) {
+                self.exif.push(ExifEdit::Set {
+                    ifd: IfdKind::Exif,
+                    code: offset_tag.code(),
+                    value: EntryValue::Text(t.format("%:z").to_string()),
+                });
+            }
+        }
+        self.exif.push(ExifEdit::Set { ifd, code, value });
+        self
+    }
+
+    /// Like [`MetadataEdits::set_exif`], but the entry is only set if the file
+    /// doesn't have it yet, so existing values are never overwritten.
+    ///
+    /// This is useful for templates applied to many files, e.g. to fill in
+    /// the copyright and artist of a photo library.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let mut template = MetadataEdits::new();
+    /// template
+    ///     .set_exif(ExifTag::Copyright, "(c) 2024 Jane Doe")
+    ///     .set_exif_if_absent(ExifTag::Artist, "Jane Doe")
+    ///     .set_exif_if_absent(ExifTag::Make, "unknown");
+    /// let mut writer = MediaWriter::new(template);
+    ///
+    /// let reader = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut output = Vec::new();
+    /// writer.write(reader, &mut output).unwrap();
+    ///
+    /// let exif: Exif = parse_exif(output.as_slice(), None).unwrap().unwrap().into();
+    /// assert_eq!(exif.get(ExifTag::Artist).unwrap().as_str(), Some("Jane Doe"));
+    /// assert_eq!(exif.get(ExifTag::Make).unwrap().as_str(), Some("vivo"));
+    /// ```
+    pub fn set_exif_if_absent(&mut self, tag: ExifTag, value: impl Into<EntryValue>) -> &mut Self {
+        self.set_exif_if_absent_by_tag_code(IfdKind::of_tag(tag), tag.code(), value)
+    }
+
+    /// Sets the Exif entry with raw tag `code` in the specified `ifd`, if the
+    /// file doesn't have it yet.
+    pub fn set_exif_if_absent_by_tag_code(
+        &mut self,
+        ifd: IfdKind,
+        code: u16,
+        value: impl Into<EntryValue>,
+    ) -> &mut Self {
+        let value = value.into();
+        self.exif.push(ExifEdit::SetIfAbsent { ifd, code, value });
+        self
+    }
+
+    /// Removes the Exif entry `tag` from the IFD where it conventionally
+    /// belongs.
+    pub fn remove_exif(&mut self, tag: ExifTag) -> &mut Self {
+        self.remove_exif_by_tag_code(IfdKind::of_tag(tag), tag.code())
+    }
+
+    /// Removes the Exif entry with raw tag `code` from the specified `ifd`.
+    ///
+    /// Removing a sub-IFD pointer (e.g. [`ExifTag::GPSInfo`]) removes the
+    /// whole sub-IFD.
+    pub fn remove_exif_by_tag_code(&mut self, ifd: IfdKind, code: u16) -> &mut Self {
+        self.exif.push(ExifEdit::Remove { ifd, code });
+        self
+    }
+
+    /// Removes location info only, all other metadata is kept as is. This is
+    /// useful for "share without location" features.
+    ///
+    /// - For images, the whole GPS IFD is removed from the Exif data.
+    ///
+    /// - For QuickTime/MP4 videos, the `com.apple.quicktime.location.*` keys
+    ///   and the `©xyz` atom are removed.
+    ///
+    /// The removal is applied after all other Exif edits, so GPS entries set by
+    /// [`MetadataEdits::set_exif`] are removed as well. It cancels a previous
+    /// [`MetadataEdits::set_gps`].
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let mut edits = MetadataEdits::new();
+    /// edits.remove_gps();
+    ///
+    /// let reader = std::fs::File::open("./testdata/exif.heic").unwrap();
+    /// let mut output = Vec::new();
+    /// write_metadata(reader, &mut output, &edits).unwrap();
+    ///
+    /// let iter = parse_exif(output.as_slice(), None).unwrap().unwrap();
+    /// let exif: Exif = iter.into();
+    /// assert!(exif.get_gps_info().unwrap().is_none());
+    /// assert_eq!(exif.get(ExifTag::Make).unwrap().as_str(), Some("Apple"));
+    /// ```
+    pub fn remove_gps(&mut self) -> &mut Self {
+        self.location = None;
+        self.remove_location = true;
+        self
+    }
+
+    /// Stamps the file with the location `gps`, which is usually created by
+    /// [`GPSInfo::from_decimal`].
+    ///
+    /// - For images, latitude, longitude and altitude (if any) are written to
+    ///   the GPS IFD, along with their refs. Speed, track, image direction,
+    ///   DOP and positioning error are written if set. Other GPS entries,
+    ///   e.g. time stamps, are kept.
+    ///
+    /// - For QuickTime/MP4 videos, `gps` is written to the
+    ///   `com.apple.quicktime.location.ISO6709` item (and the `©xyz` atom if
+    ///   there is one) as an ISO 6709 string, and the positioning error (if
+    ///   set) to `com.apple.quicktime.location.accuracy.horizontal`.
+    ///
+    /// It is applied after other Exif edits, and cancels a previous
+    /// [`MetadataEdits::remove_gps`].
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let mut edits = MetadataEdits::new();
+    /// edits.set_gps(&GPSInfo::from_decimal(48.8577, 2.295, 35.0).unwrap());
+    ///
+    /// let reader = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut output = Vec::new();
+    /// write_metadata(reader, &mut output, &edits).unwrap();
+    ///
+    /// let iter = parse_exif(output.as_slice(), None).unwrap().unwrap();
+    /// let exif: Exif = iter.into();
+    /// let gps = exif.get_gps_info().unwrap().unwrap();
+    /// assert_eq!(gps.format_iso6709(), "+48.85770+002.29500+35.000/");
+    /// ```
+    pub fn set_gps(&mut self, gps: &GPSInfo) -> &mut Self {
+        self.location = Some(gps.clone());
+        self.remove_location = false;
+        self
+    }
+
+    /// Shifts all date/time values by `delta`, e.g. to fix a camera clock
+    /// which was off by a few hours. Calling it multiple times accumulates
+    /// the shifts.
+    ///
+    /// - For images, `ModifyDate`, `DateTimeOriginal`, `CreateDate`, and the
+    ///   GPS time stamp (`GPSDateStamp` & `GPSTimeStamp`) are shifted. Offset
+    ///   times are kept as is.
+    ///
+    /// - For QuickTime/MP4 videos, the creation & modification times of the
+    ///   movie, tracks and media are shifted (in whole seconds), as well as
+    ///   `com.apple.quicktime.creationdate`.
+    ///
+    /// The shift is applied to the original values, before other Exif edits,
+    /// so values set by [`MetadataEdits::set_exif`] are written as is.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    /// use chrono::Duration;
+    ///
+    /// let mut edits = MetadataEdits::new();
+    /// edits.shift_times(Duration::hours(-3));
+    ///
+    /// let reader = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut output = Vec::new();
+    /// write_metadata(reader, &mut output, &edits).unwrap();
+    ///
+    /// let time = |data: &[u8]| {
+    ///     let exif: Exif = parse_exif(data, None).unwrap().unwrap().into();
+    ///     exif.get(ExifTag::DateTimeOriginal).unwrap().as_time().unwrap()
+    /// };
+    /// let orig = time(&std::fs::read("./testdata/exif.jpg").unwrap());
+    /// assert_eq!(time(&output), orig - Duration::hours(3));
+    /// ```
+    pub fn shift_times(&mut self, delta: Duration) -> &mut Self {
+        self.time_shift = Some(self.time_shift.unwrap_or_default() + delta);
+        self
+    }
+
+    /// Replaces the embedded thumbnail (referenced by IFD1) with `jpeg`, or
+    /// adds one if there isn't. `jpeg` must be a JPEG image, which is usually
+    /// no larger than 160x120, since the whole Exif block must fit in a JPEG
+    /// APP1 segment (64 KB).
+    ///
+    /// The `Compression` entry of IFD1 is set to JPEG, and the resolution
+    /// entries are added if they don't exist.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let thumbnail = [0xff, 0xd8, 0xff, 0xd9]; // SOI & EOI only, for brevity
+    /// let mut edits = MetadataEdits::new();
+    /// edits.set_thumbnail(thumbnail);
+    ///
+    /// let reader = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut output = Vec::new();
+    /// write_metadata(reader, &mut output, &edits).unwrap();
+    /// ```
+    pub fn set_thumbnail(&mut self, jpeg: impl Into<Vec<u8>>) -> &mut Self {
+        self.thumbnail = Some(jpeg.into());
+        self
+    }
+
+    /// Replaces the XMP packet with `packet`, which is usually read by
+    /// [`read_xmp`] and then modified, e.g. to change the rating or keywords.
+    ///
+    /// The packet is wrapped with `<?xpacket ...?>` if it isn't, and padded
+    /// with whitespace, so it can be rewritten in place next time. If the new
+    /// packet fits in the space of the old one, it is written in place.
+    ///
+    /// - JPEG: the XMP APP1 segment is replaced (or created), and extended XMP
+    ///   segments are removed. Packets larger than a segment are not
+    ///   supported.
+    ///
+    /// - HEIF/HEIC: the XMP item of the primary image is replaced. The image
+    ///   must already have an XMP item.
+    ///
+    /// - TIFF: the XMP packet is written to the `XMLPacket` (0x02bc) entry of
+    ///   IFD0.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let f = std::fs::File::open("./testdata/exif.heic").unwrap();
+    /// let packet = read_xmp(f).unwrap().unwrap();
+    /// let packet = packet.replace("<rdf:Description rdf:about=\"\"", "<rdf:Description rdf:about=\"\" xmp:Rating=\"5\"");
+    ///
+    /// let mut edits = MetadataEdits::new();
+    /// edits.set_xmp(packet);
+    /// let reader = std::fs::File::open("./testdata/exif.heic").unwrap();
+    /// let mut output = Vec::new();
+    /// write_metadata(reader, &mut output, &edits).unwrap();
+    ///
+    /// let packet = read_xmp(output.as_slice()).unwrap().unwrap();
+    /// assert!(packet.contains("xmp:Rating=\"5\""));
+    /// ```
+    pub fn set_xmp(&mut self, packet: impl Into<String>) -> &mut Self {
+        self.xmp = Some(packet.into());
+        self
+    }
+
+    /// Sets the QuickTime metadata item `key` (in `moov/meta`) to `value`,
+    /// for QuickTime/MP4 files only. Common keys are:
+    ///
+    /// - `com.apple.quicktime.creationdate`
+    /// - `com.apple.quicktime.make`
+    /// - `com.apple.quicktime.model`
+    /// - `com.apple.quicktime.software`
+    /// - `com.apple.quicktime.location.ISO6709`
+    ///
+    /// Texts and times are stored as UTF-8 strings (times in ISO 8601
+    /// format), integers & floats as big-endian numbers. Other value types
+    /// are not supported, and `write_metadata` will fail for them.
+    ///
+    /// The `moov` atom is rebuilt and written in place if it's the last atom
+    /// or the new one fits, otherwise it's moved to the end of the file.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let mut edits = MetadataEdits::new();
+    /// edits
+    ///     .set_quicktime("com.apple.quicktime.make", "Apple")
+    ///     .set_quicktime("com.apple.quicktime.model", "iPhone 15");
+    ///
+    /// let reader = std::fs::File::open("./testdata/meta.mp4").unwrap();
+    /// let mut output = Vec::new();
+    /// write_metadata(reader, &mut output, &edits).unwrap();
+    ///
+    /// let entries = parse_metadata(std::io::Cursor::new(output)).unwrap();
+    /// assert!(entries.contains(&("com.apple.quicktime.make".to_string(), "Apple".into())));
+    /// ```
+    pub fn set_quicktime(&mut self, key: &str, value: impl Into<EntryValue>) -> &mut Self {
+        self.quicktime.retain(|x| x.0 != key);
+        self.quicktime.push((key.to_owned(), value.into()));
+        self
+    }
+
+    /// Adds the edits of `other` to these ones, e.g. to combine a template
+    /// shared by many files with edits of a single file. The edits of
+    /// `other` are applied later, so its values take precedence.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use nom_exif::*;
+    ///
+    /// let mut template = MetadataEdits::new();
+    /// template.set_exif(ExifTag::Copyright, "(c) 2024 Jane Doe");
+    ///
+    /// let mut edits = MetadataEdits::new();
+    /// edits.set_exif(ExifTag::ImageDescription, "sunset").merge(&template);
+    ///
+    /// let reader = std::fs::File::open("./testdata/exif.jpg").unwrap();
+    /// let mut output = Vec::new();
+    /// write_metadata(reader, &mut output, &edits).unwrap();
+    ///
+    /// let exif: Exif = parse_exif(output.as_slice(), None).unwrap().unwrap().into();
+    /// assert_eq!(exif.get(ExifTag::ImageDescription).unwrap().as_str(), Some("sunset"));
+    /// assert_eq!(exif.get(ExifTag::Copyright).unwrap().as_str(), Some("(c) 2024 Jane Doe"));
+    /// ```
+    pub fn merge(&mut self, other: &MetadataEdits) -> &mut Self {
+        self.exif.extend(other.exif.iter().cloned());
+        if let Some(delta) = other.time_shift {
+            self.shift_times(delta);
+        }
+        if let Some(gps) = &other.location {
+            self.set_gps(gps);
+        } else if other.remove_location {
+            self.remove_gps();
+        }
+        if let Some(packet) = &other.xmp {
+            self.xmp = Some(packet.clone());
+        }
+        for (key, value) in other.quicktime.iter() {
+            self.set_quicktime(key, value.clone());
+        }
+        if let Some(jpeg) = &other.thumbnail {
+            self.thumbnail = Some(jpeg.clone());
+        }
+        self
+    }
+
+    /// Applies the Exif edits to `tiff`.
+    pub(crate) fn apply_exif(&self, tiff: &mut Tiff) -> crate::Result<()> {
+        if let Some(delta) = self.time_shift {
+            tiff.shift_times(delta)?;
+        }
+        for edit in self.exif.iter() {
+            if let ExifEdit::Set { ifd, .. } | ExifEdit::SetIfAbsent { ifd, .. } = edit {
+                if let IfdKind::SubIfd(_) = ifd {
+                    return Err(format!("entries can't be written to {ifd:?}").into());
+                }
+            }
+            match edit {
+                ExifEdit::Set { ifd, code, value } => {
+                    let entry = Entry::encode(value, tiff.endian)?;
+                    tiff.ifd_or_insert(*ifd).set(*code, entry)?;
+                }
+                ExifEdit::Remove { ifd, code } => {
+                    if let Some(ifd) = tiff.ifd_mut(*ifd) {
+                        ifd.remove(*code);
+                    }
+                }
+                ExifEdit::SetIfAbsent { ifd, code, value } => {
+                    if tiff.ifd(*ifd).is_some_and(|x| x.entries.contains_key(code)) {
+                        continue;
+                    }
+                    if let (EntryValue::Time(t), Some(offset_tag)) =
+                        (value, offset_time_tag(*ifd, *code))
+                    {
+                        let offset = EntryValue::Text(t.format("%:z").to_string());
+                        let entry = Entry::encode(&offset, tiff.endian)?;
+                        tiff.ifd_or_insert(IfdKind::Exif)
+                            .set(offset_tag.code(), entry)?;
+                    }
+                    let entry = Entry::encode(value, tiff.endian)?;
+                    tiff.ifd_or_insert(*ifd).set(*code, entry)?;
+                }
+                ExifEdit::Copy {
+                    ifd,
+                    code,
+                    entry,
+                    endian,
+                } => {
+                    let entry = entry.to_endian(*endian, tiff.endian);
+                    tiff.ifd_or_insert(*ifd).set(*code, entry)?;
+                }
+            }
+        }
+        if let Some(gps) = &self.location {
+            set_gps_entries(tiff, gps)?;
+        }
+        if self.remove_location {
+            if let Some(ifd) = tiff.ifd_mut(IfdKind::Ifd0) {
+                ifd.remove(ExifTag::GPSInfo.code());
+            }
+        }
+        if let Some(jpeg) = &self.thumbnail {
+            tiff.set_thumbnail(jpeg)?;
+        }
+        tiff.prune();
+        Ok(())
+    }
+}
+
+/// Writes the location of `gps` to the GPS IFD.
+fn set_gps_entries(tiff: &mut Tiff, gps: &GPSInfo) -> crate::Result<()> {
+    use ExifTag::*;
+
+    let latlng = |x: &LatLng| EntryValue::URationalArray(vec![x.0, x.1, x.2]);
+    let mut values = vec![
+        (
+            GPSLatitudeRef,
+            EntryValue::Text(gps.latitude_ref.to_string()),
+        ),
+        (GPSLatitude, latlng(&gps.latitude)),
+        (
+            GPSLongitudeRef,
+            EntryValue::Text(gps.longitude_ref.to_string()),
+        ),
+        (GPSLongitude, latlng(&gps.longitude)),
+    ];
+    let has_altitude = gps.altitude.1 != 0;
+    if has_altitude {
+        values.push((GPSAltitudeRef, EntryValue::U8(gps.altitude_ref)));
+        values.push((GPSAltitude, EntryValue::URational(gps.altitude)));
+    }
+    if gps.speed_ref != '\0' {
+        values.push((GPSSpeedRef, EntryValue::Text(gps.speed_ref.to_string())));
+        values.push((GPSSpeed, EntryValue::URational(gps.speed)));
+    }
+    if gps.track_ref != '\0' {
+        values.push((GPSTrackRef, EntryValue::Text(gps.track_ref.to_string())));
+        values.push((GPSTrack, EntryValue::URational(gps.track)));
+    }
+    if gps.img_direction_ref != '\0' {
+        values.push((
+            GPSImgDirectionRef,
+            EntryValue::Text(gps.img_direction_ref.to_string()),
+        ));
+        values.push((GPSImgDirection, EntryValue::URational(gps.img_direction)));
+    }
+    if gps.dop.1 != 0 {
+        values.push((GPSDOP, EntryValue::URational(gps.dop)));
+    }
+    if gps.h_positioning_error.1 != 0 {
+        values.push((
+            GPSHPositioningError,
+            EntryValue::URational(gps.h_positioning_error),
+        ));
+    }
+
+    let endian = tiff.endian;
+    let ifd = tiff.ifd_or_insert(IfdKind::Gps);
+    if !ifd.entries.contains_key(&GPSVersionID.code()) {
+        // Version 2.3.0.0
+        ifd.set(GPSVersionID.code(), Entry::bytes(&[2, 3, 0, 0]))?;
+    }
+    if !has_altitude {
+        // Don't leave a stale altitude
+        ifd.remove(GPSAltitudeRef.code());
+        ifd.remove(GPSAltitude.code());
+    }
+    for (tag, value) in values {
+        ifd.set(tag.code(), Entry::encode(&value, endian)?)?;
+    }
+    Ok(())
+}
+
+fn offset_time_tag(ifd: IfdKind, code: u16) -> Option<ExifTag> {
+    match (ifd, code.try_into().ok()?) {
+        (IfdKind::Exif, ExifTag::DateTimeOriginal) => Some(ExifTag::OffsetTimeOriginal),
+        (IfdKind::Exif, ExifTag::CreateDate) => Some(ExifTag::OffsetTimeDigitized),
+        (IfdKind::Ifd0, ExifTag::ModifyDate) => Some(ExifTag::OffsetTime),
+        _ => None,
+    }
+}
+
+/// Reads a file from `reader`, applies `edits` to its metadata, and writes the
+/// result to `writer`.
+///
+/// Currently supported file formats:
+///
+/// - TIFF: the IFDs are rewritten and appended to the end of the file, image
+///   data and unchanged values stay where they are. Stale IFD data is zeroed.
+///
+/// - JPEG: the Exif APP1 segment is rebuilt (or created if it doesn't exist).
+///
+/// - HEIF/HEIC: the Exif item payload is replaced, and the `iloc` box is
+///   updated accordingly. The image must already have an Exif item.
+///
+/// - QuickTime/MP4: only [`MetadataEdits::shift_times`],
+///   [`MetadataEdits::set_gps`], [`MetadataEdits::remove_gps`] and
+///   [`MetadataEdits::set_quicktime`] are supported. Media data always stays
+///   where it is.
+///
+/// An `Err` is returned if the file format is not supported, or the edits
+/// can't be applied, e.g. the new Exif data is too large for a JPEG APP1
+/// segment. Nothing is written to `writer` in this case.
+pub fn write_metadata<R: Read, W: Write>(
+    mut reader: R,
+    writer: W,
+    edits: &MetadataEdits,
+) -> crate::Result<()> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+    write_buf(&input, writer, edits)
+}
+
+/// Applies `edits` to the file in `input`, see [`write_metadata`].
+fn write_buf<W: Write>(input: &[u8], mut writer: W, edits: &MetadataEdits) -> crate::Result<()> {
+    let is_qt_mp4 = check_qt_mp4(input).is_ok();
+    if !edits.quicktime.is_empty() && !is_qt_mp4 {
+        return Err("QuickTime edits are only supported for QuickTime/MP4 files".into());
+    }
+
+    let output = if input.starts_with(b"II*\0") || input.starts_with(b"MM\0*") {
+        Cow::Owned(write_tiff(input, edits)?)
+    } else if input.starts_with(&[0xff, 0xd8]) {
+        Cow::Owned(jpeg::write(input, edits)?)
+    } else if check_heif(input).is_ok() {
+        Cow::Owned(heif::write(input, edits)?)
+    } else if is_qt_mp4 {
+        write_mov(input, edits)?
+    } else {
+        return Err("unsupported file format for writing".into());
+    };
+
+    writer.write_all(&output)?;
+    Ok(())
+}
+
+/// Like [`write_metadata`], but modifies `file` in place. The space of the
+/// existing metadata is reused, so that big files (e.g. videos) don't have to
+/// be copied:
+///
+/// - TIFF: only the changed bytes are written, new IFDs are appended to the
+///   end of the file.
+///
+/// - JPEG: the header segments must keep their total size. The Exif APP1
+///   segment is padded with zeros if the new one is smaller, and XMP packets
+///   are padded to the size of the old ones. Only the header is read.
+///
+/// - HEIF/HEIC: item payloads are replaced in place if they fit, otherwise
+///   they are appended to the end of the file, but the `meta` box must keep
+///   its size.
+///
+/// - QuickTime/MP4: the new `moov` atom uses the space of the old one and the
+///   `free` atoms right after it (or grows if it's the last atom). The media
+///   data is neither read nor moved.
+///
+/// Returns `Ok(false)` if the edits don't fit in the existing space. `file`
+/// is untouched in this case, and [`write_metadata`] can be used instead.
+///
+/// The file is modified directly, so it may be corrupted if the process
+/// crashes in the middle. Use [`rewrite_file`] with [`write_metadata`] if
+/// that's not acceptable.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let mut file = std::io::Cursor::new(std::fs::read("./testdata/meta.mov").unwrap());
+/// let len = file.get_ref().len();
+///
+/// let mut edits = MetadataEdits::new();
+/// edits.remove_gps();
+/// assert!(write_metadata_in_place(&mut file, &edits).unwrap());
+/// assert_eq!(file.get_ref().len(), len);
+/// ```
+pub fn write_metadata_in_place<F: Read + Write + Seek>(
+    mut file: F,
+    edits: &MetadataEdits,
+) -> crate::Result<bool> {
+    let mut head = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    (&mut file).take(4096).read_to_end(&mut head)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let is_qt_mp4 = check_qt_mp4(&head).is_ok();
+    if !edits.quicktime.is_empty() && !is_qt_mp4 {
+        return Err("QuickTime edits are only supported for QuickTime/MP4 files".into());
+    }
+
+    let (input, output) = if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+        let mut input = Vec::new();
+        file.read_to_end(&mut input)?;
+        let output = write_tiff(&input, edits)?;
+        (input, output)
+    } else if head.starts_with(&[0xff, 0xd8]) {
+        let header = jpeg::read_header(&mut file)?;
+        match jpeg::write_in_place(&header, edits)? {
+            Some(output) => (header, output),
+            None => return Ok(false),
+        }
+    } else if check_heif(&head).is_ok() {
+        let mut input = Vec::new();
+        file.read_to_end(&mut input)?;
+        match heif::write_in_place(&input, edits)? {
+            Some(output) => (input, output),
+            None => return Ok(false),
+        }
+    } else if is_qt_mp4 {
+        return mov::write_in_place(&mut file, |moov| {
+            write_mov(&moov, edits).map(Cow::into_owned)
+        });
+    } else {
+        return Err("unsupported file format for writing".into());
+    };
+
+    patch_file(&mut file, &input, &output)?;
+    Ok(true)
+}
+
+/// Sets the Orientation of the main image to `orientation`, e.g. after
+/// a lossless rotation. Only the two bytes of the value are written, no other
+/// byte of `file` is touched.
+///
+/// Supported file formats are TIFF, JPEG and HEIF/HEIC. The Orientation
+/// entry must already exist in IFD0, use [`MetadataEdits::set_exif`] to add
+/// one. Note that HEIF/HEIC readers usually apply the `irot` & `imir` item
+/// properties rather than the Exif Orientation, they are not changed.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let mut file = std::io::Cursor::new(std::fs::read("./testdata/exif.heic").unwrap());
+/// write_orientation(&mut file, Orientation::Rotate180).unwrap();
+///
+/// let exif: Exif = parse_exif(file.get_ref().as_slice(), None).unwrap().unwrap().into();
+/// assert_eq!(exif.orientation(), Some(Orientation::Rotate180));
+/// ```
+pub fn write_orientation<F: Read + Write + Seek>(
+    mut file: F,
+    orientation: Orientation,
+) -> crate::Result<()> {
+    let mut head = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    (&mut file).take(4096).read_to_end(&mut head)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let exif = read_exif_payload(&mut file, &head)?.ok_or("Exif data not found")?;
+    let (offset, endian) = tiff::find_orientation(&exif.data[exif.tiff_start..])?;
+    let pos = exif
+        .file_pos(exif.tiff_start + offset, 2)
+        .ok_or("Orientation value spans multiple extents")?;
+
+    let value = match endian {
+        Endianness::Big => orientation.value().to_be_bytes(),
+        _ => orientation.value().to_le_bytes(),
+    };
+    file.seek(SeekFrom::Start(pos as u64))?;
+    file.write_all(&value)?;
+    Ok(())
+}
+
+/// Reads the Exif data of an image from `reader`, `head` is the beginning of
+/// the file, which is used to detect the file format. For JPEG files, only
+/// the header segments are read.
+fn read_exif_payload<R: Read>(reader: &mut R, head: &[u8]) -> crate::Result<Option<ExifPayload>> {
+    if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let range = 0..data.len();
+        Ok(Some(ExifPayload {
+            extents: Vec::from([range]),
+            tiff_start: 0,
+            data,
+        }))
+    } else if head.starts_with(&[0xff, 0xd8]) {
+        let header = jpeg::read_header(reader)?;
+        Ok(jpeg::exif_range(&header)?.map(|range| ExifPayload {
+            data: header[range.clone()].to_vec(),
+            tiff_start: 0,
+            extents: Vec::from([range]),
+        }))
+    } else if check_heif(head).is_ok() {
+        let mut input = Vec::new();
+        reader.read_to_end(&mut input)?;
+        heif::exif_item(&input)
+    } else {
+        Err("unsupported image format".into())
+    }
+}
+
+/// Exif data read from a file.
+struct ExifPayload {
+    data: Vec<u8>,
+    /// Position of the TIFF header in `data`.
+    tiff_start: usize,
+    /// Where `data` is stored in the file.
+    extents: Vec<Range<usize>>,
+}
+
+impl ExifPayload {
+    /// Returns the file position of `data[offset..offset + len]`, or `None` if
+    /// it is not stored contiguously.
+    fn file_pos(&self, mut offset: usize, len: usize) -> Option<usize> {
+        for extent in self.extents.iter() {
+            if offset + len <= extent.len() {
+                return Some(extent.start + offset);
+            }
+            offset = offset.checked_sub(extent.len())?;
+        }
+        None
+    }
+}
+
+/// Writes the bytes of `output` which differ from `input` to `file`, where
+/// `input` is the original content of `file` (or the beginning of it), and
+/// `output` is not shorter than `input`.
+fn patch_file<F: Write + Seek>(file: &mut F, input: &[u8], output: &[u8]) -> crate::Result<()> {
+    debug_assert!(output.len() >= input.len());
+    let mut pos = 0;
+    while pos < input.len() {
+        if input[pos] == output[pos] {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        while pos < input.len() && input[pos] != output[pos] {
+            pos += 1;
+        }
+        file.seek(SeekFrom::Start(start as u64))?;
+        file.write_all(&output[start..pos])?;
+    }
+    if output.len() > input.len() {
+        file.seek(SeekFrom::Start(input.len() as u64))?;
+        file.write_all(&output[input.len()..])?;
+    }
+    Ok(())
+}
+
+fn write_mov<'a>(input: &'a [u8], edits: &MetadataEdits) -> crate::Result<Cow<'a, [u8]>> {
+    if !edits.exif.is_empty() || edits.thumbnail.is_some() {
+        return Err("Exif edits are not supported for QuickTime/MP4 files".into());
+    }
+    if edits.xmp.is_some() {
+        return Err("XMP edits are not supported for QuickTime/MP4 files".into());
+    }
+
+    let mut output = Cow::Borrowed(input);
+    if let Some(delta) = edits.time_shift {
+        output = Cow::Owned(mov::shift_times(&output, delta)?);
+    }
+
+    let mut items = edits
+        .quicktime
+        .iter()
+        .map(|(key, value)| mov::MetaItem::encode(key, value))
+        .collect::<crate::Result<Vec<_>>>()?;
+    if let Some(gps) = &edits.location {
+        let value = EntryValue::Text(gps.format_iso6709());
+        items.push(mov::MetaItem::encode(mov::LOCATION_KEY, &value)?);
+        if gps.h_positioning_error.1 != 0 {
+            let value = EntryValue::Text(gps.h_positioning_error.as_float().to_string());
+            items.push(mov::MetaItem::encode(mov::ACCURACY_KEY, &value)?);
+        }
+    }
+    if !items.is_empty() {
+        output = Cow::Owned(mov::set_items(&output, &items)?);
+    }
+
+    if edits.remove_location {
+        output = Cow::Owned(mov::remove_location(&output)?);
+    }
+    Ok(output)
+}
+
+fn write_tiff(input: &[u8], edits: &MetadataEdits) -> crate::Result<Vec<u8>> {
+    let mut tiff = Tiff::parse(input)?;
+    edits.apply_exif(&mut tiff)?;
+    if tiff.ifds.is_empty() {
+        return Err("a TIFF file must contain at least one IFD".into());
+    }
+    if let Some(packet) = edits.xmp.as_deref() {
+        let ifd = tiff.ifd_or_insert(IfdKind::Ifd0);
+        let old_len = ifd.entries.get(&XML_PACKET).map(|x| x.data.len());
+        let packet = xmp::pad_packet(packet, old_len, u32::MAX as usize)?;
+        ifd.set(XML_PACKET, Entry::bytes(&packet))?;
+    }
+    tiff.append_to(input)
+}
+
+/// Reads a file from `reader`, and returns its raw XMP packet (without
+/// trailing NULs), or `None` if there isn't one.
+///
+/// Supported file formats are the same as [`write_metadata`], except for
+/// QuickTime/MP4. The packet is returned as is, no XMP parsing is done.
+pub fn read_xmp<R: Read>(mut reader: R) -> crate::Result<Option<String>> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+
+    if input.starts_with(b"II*\0") || input.starts_with(b"MM\0*") {
+        let mut tiff = Tiff::parse(&input)?;
+        tiff.ifd_mut(IfdKind::Ifd0)
+            .and_then(|ifd| ifd.entries.get(&XML_PACKET))
+            .map(|x| xmp::packet_str(&x.data))
+            .transpose()
+    } else if input.starts_with(&[0xff, 0xd8]) {
+        jpeg::read_xmp(&input)
+    } else if check_heif(&input).is_ok() {
+        heif::read_xmp(&input)
+    } else {
+        Err("unsupported file format for reading XMP".into())
+    }
+}
+
+/// Reads a file from `reader`, and returns its Exif data as stored in the
+/// file: the complete TIFF structure, starting with the `II`/`MM` byte order
+/// mark, or `None` if there isn't any.
+///
+/// The bytes are not parsed or rebuilt, so they can be handed to other
+/// libraries or archived verbatim. Supported file formats are the same as
+/// [`write_metadata`], except for QuickTime/MP4, which don't store Exif data.
+/// For TIFF files, this is the whole file.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let f = std::fs::File::open("./testdata/exif.heic").unwrap();
+/// let data = read_exif_data(f).unwrap().unwrap();
+/// assert_eq!(&data[..4], b"MM\0*");
+///
+/// let f = std::fs::File::open("./testdata/no-exif.jpg").unwrap();
+/// assert!(read_exif_data(f).unwrap().is_none());
+/// ```
+pub fn read_exif_data<R: Read>(mut reader: R) -> crate::Result<Option<Vec<u8>>> {
+    let mut head = Vec::new();
+    (&mut reader).take(4096).read_to_end(&mut head)?;
+    if check_qt_mp4(&head).is_ok() {
+        return Err("Exif data is not supported for QuickTime/MP4 files".into());
+    }
+
+    let mut reader = head.as_slice().chain(reader);
+    let Some(mut exif) = read_exif_payload(&mut reader, &head)? else {
+        return Ok(None);
+    };
+    exif.data.drain(..exif.tiff_start);
+    Ok(Some(exif.data))
+}
+
+/// Options for [`strip_metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StripOptions {
+    /// Keep ICC color profiles, so colors are still rendered correctly.
+    /// Default: `true`.
+    pub keep_icc: bool,
+
+    /// Keep the Exif Orientation tag (in a minimal Exif block), so images are
+    /// still displayed upright. Default: `true`.
+    pub keep_orientation: bool,
+}
+
+impl Default for StripOptions {
+    fn default() -> Self {
+        Self {
+            keep_icc: true,
+            keep_orientation: true,
+        }
+    }
+}
+
+/// Reads a file from `reader`, removes its Exif, XMP and IPTC metadata, and
+/// writes the result to `writer`. Image data is copied as is.
+///
+/// Currently supported file formats:
+///
+/// - JPEG: Exif/XMP APP1 segments, IPTC (Photoshop) APP13 segments and
+///   optionally ICC profile APP2 segments are removed.
+///
+/// - PNG: `eXIf` chunks, XMP and raw profile text chunks, and optionally
+///   `iCCP` chunks are removed.
+///
+/// - WebP: `EXIF`, `XMP ` and optionally `ICCP` chunks are removed, and the
+///   `VP8X` flags are updated accordingly.
+///
+/// - HEIF/HEIC: the payload of the Exif item is replaced with an empty (or
+///   orientation only) Exif block, and XMP items are replaced with an empty
+///   XMP packet. Color profiles are image properties in HEIF, so they are
+///   always kept.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let reader = std::fs::File::open("./testdata/exif.heic").unwrap();
+/// let mut output = Vec::new();
+/// strip_metadata(reader, &mut output, StripOptions::default()).unwrap();
+///
+/// let iter = parse_exif(output.as_slice(), None).unwrap().unwrap();
+/// let exif: Exif = iter.into();
+/// assert_eq!(exif.get(ExifTag::Orientation).unwrap().as_u16(), Some(6));
+/// assert!(exif.get(ExifTag::Make).is_none());
+/// assert!(exif.get_gps_info().unwrap().is_none());
+/// ```
+pub fn strip_metadata<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    options: StripOptions,
+) -> crate::Result<()> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+
+    let output = if input.starts_with(&[0xff, 0xd8]) {
+        jpeg::strip(&input, &options)?
+    } else if input.starts_with(png::SIGNATURE) {
+        png::strip(&input, &options)?
+    } else if webp::check_webp(&input) {
+        webp::strip(&input, &options)?
+    } else if check_heif(&input).is_ok() {
+        heif::strip(&input, &options)?
+    } else {
+        return Err("unsupported file format for stripping".into());
+    };
+
+    writer.write_all(&output)?;
+    Ok(())
+}
+
+/// Strips the Exif TIFF `data` according to `options`, returns `None` if
+/// nothing is left.
+fn stripped_exif(data: &[u8], options: &StripOptions) -> crate::Result<Option<Vec<u8>>> {
+    if !options.keep_orientation {
+        return Ok(None);
+    }
+    let Ok(mut tiff) = Tiff::parse(data) else {
+        return Ok(None);
+    };
+    tiff.strip(options.keep_orientation);
+    if tiff.is_empty() {
+        Ok(None)
+    } else {
+        tiff.to_bytes(data).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration};
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{exif::input_to_exif, testkit::*, values::URational, Exif};
+
+    fn write(path: &str, edits: &MetadataEdits) -> Vec<u8> {
+        let mut output = Vec::new();
+        write_metadata(open_sample(path).unwrap(), &mut output, edits).unwrap();
+        output
+    }
+
+    fn parse(data: &[u8]) -> Exif {
+        if data.starts_with(b"II") || data.starts_with(b"MM") {
+            // Bare TIFF data
+            return input_to_exif(data.to_vec()).unwrap();
+        }
+        let iter = crate::parse_exif(data, None).unwrap().unwrap();
+        iter.into()
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn set_date_and_gps(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let t = DateTime::parse_from_rfc3339("1987-05-21T10:30:00-05:00").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits
+            .set_exif(ExifTag::DateTimeOriginal, t)
+            .set_exif(ExifTag::Make, "a much longer make value")
+            .set_exif(ExifTag::GPSLatitudeRef, "N")
+            .set_exif(
+                ExifTag::GPSLatitude,
+                EntryValue::URationalArray(vec![
+                    (22, 1).into(),
+                    (31, 1).into(),
+                    (3841, 100).into(),
+                ]),
+            )
+            .set_exif(ExifTag::GPSLongitudeRef, "E")
+            .set_exif(
+                ExifTag::GPSLongitude,
+                EntryValue::URationalArray(vec![
+                    (114, 1).into(),
+                    (1, 1).into(),
+                    (1732, 100).into(),
+                ]),
+            );
+
+        let output = write(path, &edits);
+        let exif = parse(&output);
+        // The parser applies the first offset time it finds to all time
+        // entries, so only compare the local time here.
+        assert_eq!(
+            exif.get(ExifTag::DateTimeOriginal)
+                .and_then(|x| x.as_time())
+                .map(|x| x.naive_local()),
+            Some(t.naive_local())
+        );
+        assert_eq!(
+            exif.get(ExifTag::OffsetTimeOriginal).unwrap().as_str(),
+            Some("-05:00")
+        );
+        assert_eq!(
+            exif.get(ExifTag::Make).unwrap().as_str(),
+            Some("a much longer make value")
+        );
+        let gps = exif.get_gps_info().unwrap().unwrap();
+        assert_eq!(gps.latitude_ref, 'N');
+        assert_eq!(gps.longitude.0, URational::from((114, 1)));
+
+        // Untouched entries are preserved.
+        let orig = parse(&read_sample(path).unwrap());
+        assert_eq!(exif.get(ExifTag::Model), orig.get(ExifTag::Model));
+        assert_eq!(
+            exif.get(ExifTag::ExposureTime),
+            orig.get(ExifTag::ExposureTime)
+        );
+    }
+
+    #[test]
+    fn tiff_image_data_stays_in_place() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample("exif.tif").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits
+            .set_exif(ExifTag::Model, "a model name which is longer")
+            .remove_exif(ExifTag::Make);
+        let output = write("exif.tif", &edits);
+
+        // Original file content is kept as a prefix, except for stale IFD
+        // data which has been zeroed out.
+        assert!(output.len() > input.len());
+        assert_eq!(output[8..24], input[8..24]);
+        assert!(!output.windows(8).any(|x| x == b"nom-exif"));
+
+        let tiff = Tiff::parse(&output).unwrap();
+        let exif = input_to_exif(output).unwrap();
+        assert_eq!(exif.get(ExifTag::Make), None);
+        assert_eq!(
+            exif.get(ExifTag::Model).unwrap().as_str(),
+            Some("a model name which is longer")
+        );
+        assert_eq!(
+            exif.get_by_tag_code(0x0111).and_then(|x| x.as_u32()),
+            Some(8)
+        );
+        assert_eq!(tiff.ifds.len(), 1);
+    }
+
+    #[test]
+    fn jpeg_remove_all() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample("exif.jpg").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits
+            .remove_exif_by_tag_code(IfdKind::Ifd0, ExifTag::ExifOffset.code())
+            .remove_exif(ExifTag::GPSInfo);
+        let output = write("exif.jpg", &edits);
+        assert!(output.len() < input.len());
+        let exif = parse(&output);
+        assert_eq!(exif.get(ExifTag::DateTimeOriginal), None);
+        assert_eq!(exif.get_gps_info().unwrap(), None);
+        assert!(exif.get(ExifTag::Make).is_some());
+    }
+
+    #[test]
+    fn jpeg_insert_exif() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // A minimal JPEG without Exif: SOI, APP0, SOS, EOI
+        let mut input = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00];
+        input.extend_from_slice(&[0xff, 0xda, 0x00, 0x02, 0xff, 0xd9]);
+
+        let mut edits = MetadataEdits::new();
+        edits.set_exif(ExifTag::Make, "nom-exif");
+        let mut output = Vec::new();
+        write_metadata(input.as_slice(), &mut output, &edits).unwrap();
+
+        assert_eq!(output[..8], input[..8]);
+        assert_eq!(output[8..10], [0xff, 0xe1]);
+        let exif = parse(&output);
+        assert_eq!(exif.get(ExifTag::Make).unwrap().as_str(), Some("nom-exif"));
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn remove_gps(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut edits = MetadataEdits::new();
+        edits
+            .set_exif(ExifTag::GPSLatitudeRef, "N")
+            .set_exif(ExifTag::Model, "x1")
+            .remove_gps();
+        let output = write(path, &edits);
+
+        let exif = parse(&output);
+        assert_eq!(exif.get_gps_info().unwrap(), None);
+        assert_eq!(exif.get(ExifTag::GPSInfo), None);
+        assert_eq!(exif.get(ExifTag::Model).unwrap().as_str(), Some("x1"));
+
+        let orig = parse(&read_sample(path).unwrap());
+        assert_eq!(exif.get(ExifTag::Make), orig.get(ExifTag::Make));
+        assert_eq!(
+            exif.get(ExifTag::DateTimeOriginal),
+            orig.get(ExifTag::DateTimeOriginal)
+        );
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    #[test_case("no-exif.jpg")]
+    fn set_if_absent(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let t = DateTime::parse_from_rfc3339("1987-05-21T10:30:00-05:00").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits
+            .set_exif_if_absent(ExifTag::Make, "maker")
+            .set_exif_if_absent(ExifTag::Artist, "artist")
+            .set_exif_if_absent(ExifTag::DateTimeOriginal, t);
+        let output = write(path, &edits);
+
+        let exif = parse(&output);
+        let orig = match path {
+            "no-exif.jpg" => None,
+            _ => Some(parse(&read_sample(path).unwrap())),
+        };
+        let orig = |tag| orig.as_ref().and_then(|x| x.get(tag)).cloned();
+        let values = [
+            (ExifTag::Make, EntryValue::from("maker")),
+            (ExifTag::Artist, EntryValue::from("artist")),
+            (ExifTag::DateTimeOriginal, EntryValue::from(t)),
+        ];
+        for (tag, value) in values {
+            assert_eq!(exif.get(tag), Some(&orig(tag).unwrap_or(value)), "{tag}");
+        }
+        assert_eq!(
+            exif.get(ExifTag::OffsetTimeOriginal),
+            Some(&orig(ExifTag::OffsetTimeOriginal).unwrap_or("-05:00".into()))
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let gps = GPSInfo::from_decimal(1.0, 2.0, 3.0).unwrap();
+        let key = "com.apple.quicktime.model";
+        let mut edits = MetadataEdits::new();
+        edits
+            .set_exif(ExifTag::Make, "a")
+            .set_gps(&gps)
+            .shift_times(Duration::hours(1))
+            .set_quicktime(key, "a");
+        let mut other = MetadataEdits::new();
+        other
+            .set_exif_if_absent(ExifTag::Model, "b")
+            .remove_gps()
+            .shift_times(Duration::hours(2))
+            .set_quicktime(key, "b")
+            .set_xmp("<x:xmpmeta/>");
+        edits.merge(&other);
+
+        let mut expected = MetadataEdits::new();
+        expected
+            .set_exif(ExifTag::Make, "a")
+            .set_exif_if_absent(ExifTag::Model, "b")
+            .remove_gps()
+            .shift_times(Duration::hours(3))
+            .set_quicktime(key, "b")
+            .set_xmp("<x:xmpmeta/>");
+        assert_eq!(edits, expected);
+
+        // Merging empty edits changes nothing
+        assert_eq!(expected.clone().merge(&MetadataEdits::new()), &expected);
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn shift_times(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let delta = Duration::hours(-13) + Duration::seconds(30);
+        let mut edits = MetadataEdits::new();
+        edits
+            .shift_times(Duration::hours(-13))
+            .shift_times(Duration::seconds(30));
+        let output = write(path, &edits);
+
+        let exif = parse(&output);
+        let orig = parse(&read_sample(path).unwrap());
+        for tag in [ExifTag::DateTimeOriginal, ExifTag::CreateDate] {
+            let time = |exif: &Exif| exif.get(tag).and_then(|x| x.as_time());
+            assert_eq!(time(&exif), time(&orig).map(|t| t + delta));
+        }
+        assert_eq!(
+            exif.get(ExifTag::OffsetTimeOriginal),
+            orig.get(ExifTag::OffsetTimeOriginal)
+        );
+
+        if path == "exif.jpg" {
+            // 2023:07:09 12:36:33 => 2023:07:08 23:37:03
+            assert_eq!(
+                exif.get(ExifTag::GPSDateStamp).unwrap().as_str(),
+                Some("2023:07:08")
+            );
+            assert_eq!(
+                exif.get(ExifTag::GPSTimeStamp),
+                Some(&EntryValue::URationalArray(vec![
+                    (23, 1).into(),
+                    (37, 1).into(),
+                    (3, 1).into(),
+                ]))
+            );
+        }
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn set_gps(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let gps = GPSInfo::from_decimal(-33.8568, 151.2153, -5.5).unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.remove_gps().set_gps(&gps);
+        let output = write(path, &edits);
+
+        // Other GPS entries, e.g. speed & time stamp, are kept.
+        let exif = parse(&output);
+        let info = exif.get_gps_info().unwrap().unwrap();
+        let orig = parse(&read_sample(path).unwrap());
+        let orig_info = orig.get_gps_info().unwrap().unwrap_or_default();
+        assert_eq!(
+            info,
+            GPSInfo {
+                speed_ref: orig_info.speed_ref,
+                speed: orig_info.speed,
+                track_ref: orig_info.track_ref,
+                track: orig_info.track,
+                img_direction_ref: orig_info.img_direction_ref,
+                img_direction: orig_info.img_direction,
+                dop: orig_info.dop,
+                h_positioning_error: orig_info.h_positioning_error,
+                ..gps
+            }
+        );
+        assert_eq!(
+            exif.get(ExifTag::GPSTimeStamp),
+            orig.get(ExifTag::GPSTimeStamp)
+        );
+
+        // Without altitude
+        let gps = GPSInfo {
+            altitude: Default::default(),
+            ..GPSInfo::from_decimal(10.0, 20.0, 0.0).unwrap()
+        };
+        let output = write(path, MetadataEdits::new().set_gps(&gps));
+        let exif = parse(&output);
+        assert_eq!(exif.get(ExifTag::GPSAltitude), None);
+        assert_eq!(exif.get_gps_info().unwrap().unwrap().latitude, gps.latitude);
+
+        // With direction & accuracy
+        let gps = GPSInfo {
+            track_ref: 'M',
+            track: (9015, 100).into(),
+            img_direction_ref: 'T',
+            img_direction: (270, 1).into(),
+            dop: (15, 10).into(),
+            h_positioning_error: (4706, 1000).into(),
+            ..GPSInfo::from_decimal(10.0, 20.0, 0.0).unwrap()
+        };
+        let output = write(path, MetadataEdits::new().remove_gps().set_gps(&gps));
+        let info = parse(&output).get_gps_info().unwrap().unwrap();
+        assert_eq!(
+            info,
+            GPSInfo {
+                speed_ref: orig_info.speed_ref,
+                speed: orig_info.speed,
+                ..gps
+            }
+        );
+    }
+
+    const XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="3"/></rdf:RDF></x:xmpmeta>"#;
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn xmp_round_trip(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.set_xmp(XMP);
+        let output = write(path, &edits);
+        if path == "exif.heic" {
+            // The new packet fits in the old item
+            assert_eq!(output.len(), input.len());
+        }
+
+        let packet = read_xmp(output.as_slice()).unwrap().unwrap();
+        assert!(packet.contains(XMP));
+        assert!(packet.ends_with(r#"<?xpacket end="w"?>"#));
+        assert_eq!(
+            parse(&output).get(ExifTag::Make),
+            parse(&input).get(ExifTag::Make)
+        );
+
+        // Edit the packet again, it is rewritten in place thanks to the
+        // padding.
+        let mut edits = MetadataEdits::new();
+        edits.set_xmp(packet.replace(r#"xmp:Rating="3""#, r#"xmp:Rating="5" xmp:Label="Red""#));
+        let mut again = Vec::new();
+        write_metadata(output.as_slice(), &mut again, &edits).unwrap();
+        let packet = read_xmp(again.as_slice()).unwrap().unwrap();
+        assert!(packet.contains(r#"xmp:Rating="5" xmp:Label="Red""#));
+        if path != "exif.tif" {
+            assert_eq!(again.len(), output.len());
+        }
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn exif_data(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let data = read_exif_data(open_sample(path).unwrap()).unwrap().unwrap();
+        assert!(data.starts_with(b"II*\0") || data.starts_with(b"MM\0*"));
+        if path == "exif.tif" {
+            assert_eq!(data, read_sample(path).unwrap());
+        } else {
+            let input =
+                crate::exif::read_exif(open_sample(path).unwrap(), None, &Default::default())
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(data, &input[..]);
+        }
+        assert_eq!(
+            parse(&data).get(ExifTag::Make),
+            parse(&read_sample(path).unwrap()).get(ExifTag::Make)
+        );
+    }
+
+    #[test_case("no-exif.jpg")]
+    fn exif_data_none(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        assert!(read_exif_data(open_sample(path).unwrap())
+            .unwrap()
+            .is_none());
+        read_exif_data(open_sample("meta.mov").unwrap()).unwrap_err();
+        read_exif_data(open_sample("exif.png").unwrap()).unwrap_err();
+    }
+
+    #[test]
+    fn remove_gps_from_video() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample("meta.mp4").unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.remove_gps();
+        let output = write("meta.mp4", &edits);
+        assert_eq!(output.len(), input.len());
+        assert!(!output.windows(4).any(|x| x == b"\xa9xyz"));
+    }
+
+    #[test_case(true)]
+    #[test_case(false)]
+    fn strip_jpeg(keep_icc: bool) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample("exif.jpg").unwrap();
+        let options = StripOptions {
+            keep_icc,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        strip_metadata(input.as_slice(), &mut output, options).unwrap();
+
+        // exif.jpg has no Orientation tag, so the whole Exif segment is gone.
+        assert!(crate::parse_exif(output.as_slice(), None)
+            .unwrap()
+            .is_none());
+        assert_eq!(output.windows(12).any(|x| x == b"ICC_PROFILE\0"), keep_icc);
+        // Image data is untouched
+        assert!(input.ends_with(&output[output.len() - 100_000..]));
+    }
+
+    #[test]
+    fn unsupported() {
+        let mut output = Vec::new();
+        let edits = MetadataEdits::new();
+        write_metadata(open_sample("exif.png").unwrap(), &mut output, &edits).unwrap_err();
+        assert!(output.is_empty());
+
+        let mut edits = MetadataEdits::new();
+        edits.set_exif(ExifTag::Make, EntryValue::U64(1));
+        write_metadata(open_sample("exif.jpg").unwrap(), &mut output, &edits).unwrap_err();
+        write_metadata(open_sample("meta.mov").unwrap(), &mut output, &edits).unwrap_err();
+
+        let mut edits = MetadataEdits::new();
+        edits.set_quicktime("com.apple.quicktime.make", "Apple");
+        write_metadata(open_sample("exif.jpg").unwrap(), &mut output, &edits).unwrap_err();
+
+        let mut edits = MetadataEdits::new();
+        edits.set_exif_by_tag_code(IfdKind::SubIfd(0), 0x0100, 8u16);
+        write_metadata(open_sample("exif.tif").unwrap(), &mut output, &edits).unwrap_err();
+
+        let options = StripOptions::default();
+        strip_metadata(open_sample("exif.tif").unwrap(), &mut output, options).unwrap_err();
+    }
+
+    /// Writes `edits` in place to a copy of `path`.
+    fn write_in_place(path: &str, edits: &MetadataEdits) -> (bool, Vec<u8>) {
+        let mut file = std::io::Cursor::new(read_sample(path).unwrap());
+        let done = write_metadata_in_place(&mut file, edits).unwrap();
+        (done, file.into_inner())
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn in_place_remove_gps(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.remove_gps();
+        let (done, output) = write_in_place(path, &edits);
+        assert!(done);
+
+        if path == "exif.jpg" {
+            // The smaller Exif segment is padded.
+            assert_eq!(output.len(), input.len());
+            let exif = parse(&output);
+            assert!(exif.get_gps_info().unwrap().is_none());
+            assert_eq!(exif.get(ExifTag::Make), parse(&input).get(ExifTag::Make));
+            assert_eq!(output[output.len() - 1000..], input[input.len() - 1000..]);
+        } else {
+            assert_eq!(output, write(path, &edits));
+        }
+    }
+
+    #[test]
+    fn in_place_no_room() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut edits = MetadataEdits::new();
+        edits.set_exif(ExifTag::ImageDescription, "x".repeat(1000));
+        let (done, output) = write_in_place("exif.jpg", &edits);
+        assert!(!done);
+        assert_eq!(output, read_sample("exif.jpg").unwrap());
+
+        let mut edits = MetadataEdits::new();
+        edits.set_quicktime("com.apple.quicktime.make", "Apple");
+        let mut file = std::io::Cursor::new(read_sample("exif.jpg").unwrap());
+        write_metadata_in_place(&mut file, &edits).unwrap_err();
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn orientation_only(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // Make sure there is an Orientation entry
+        let mut edits = MetadataEdits::new();
+        edits.set_exif(ExifTag::Orientation, Orientation::Horizontal);
+        let input = write(path, &edits);
+
+        let mut file = std::io::Cursor::new(input.clone());
+        write_orientation(&mut file, Orientation::Rotate270).unwrap();
+        let output = file.into_inner();
+        assert_eq!(parse(&output).orientation(), Some(Orientation::Rotate270));
+
+        // Only the value is changed
+        assert_eq!(output.len(), input.len());
+        let diff = input.iter().zip(output.iter()).filter(|(a, b)| a != b);
+        assert_eq!(diff.count(), 1);
+    }
+
+    #[test_case("exif.jpg"; "no orientation")]
+    #[test_case("meta.mov"; "unsupported")]
+    fn orientation_error(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let mut file = std::io::Cursor::new(input.clone());
+        write_orientation(&mut file, Orientation::Horizontal).unwrap_err();
+        assert_eq!(file.into_inner(), input);
+    }
+
+    /// A fake JPEG thumbnail: SOI, a COM segment and EOI.
+    const THUMBNAIL: &[u8] = b"\xff\xd8\xff\xfe\x00\x0bthumbnail\xff\xd9";
+
+    /// Returns the TIFF data of the Exif block in `data`.
+    fn tiff_data(path: &str, data: &[u8]) -> Vec<u8> {
+        if path.ends_with(".tif") {
+            data.to_vec()
+        } else if path.ends_with(".jpg") {
+            let header = jpeg::read_header(&mut &data[..]).unwrap();
+            header[jpeg::exif_range(&header).unwrap().unwrap()].to_vec()
+        } else {
+            let exif = heif::exif_item(data).unwrap().unwrap();
+            exif.data[exif.tiff_start..].to_vec()
+        }
+    }
+
+    #[test_case("exif.tif")]
+    #[test_case("exif.jpg")]
+    #[test_case("exif.heic")]
+    fn replace_thumbnail(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.set_thumbnail(THUMBNAIL);
+        let output = write(path, &edits);
+
+        let data = tiff_data(path, &output);
+        let tiff = Tiff::parse(&data).unwrap();
+        let ifd1 = &tiff.ifds[1];
+        let value = |tag: u16| {
+            let bytes = ifd1.entries[&tag].data[..4].try_into().unwrap();
+            match tiff.endian {
+                Endianness::Big => u32::from_be_bytes(bytes),
+                _ => u32::from_le_bytes(bytes),
+            }
+        };
+        let offset = value(0x0201) as usize;
+        let len = value(0x0202) as usize;
+        assert_eq!(&data[offset..offset + len], THUMBNAIL);
+        assert_eq!(
+            ifd1.entries[&0x0103],
+            Entry::encode(&EntryValue::U16(6), tiff.endian).unwrap()
+        );
+        assert_eq!(
+            parse(&output).get(ExifTag::Make),
+            parse(&input).get(ExifTag::Make)
+        );
+
+        // Remove it
+        let mut edits = MetadataEdits::new();
+        edits.remove_exif_by_tag_code(IfdKind::Ifd1, 0x0201);
+        let mut again = Vec::new();
+        write_metadata(output.as_slice(), &mut again, &edits).unwrap();
+        let tiff = Tiff::parse(&tiff_data(path, &again)).unwrap();
+        assert!(!tiff.ifds[1].entries.contains_key(&0x0201));
+        assert!(!tiff.ifds[1].entries.contains_key(&0x0202));
+    }
+
+    #[test]
+    fn invalid_thumbnail() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut edits = MetadataEdits::new();
+        edits.set_thumbnail(b"not a jpeg".to_vec());
+        let mut output = Vec::new();
+        write_metadata(open_sample("exif.jpg").unwrap(), &mut output, &edits).unwrap_err();
+        write_metadata(open_sample("meta.mov").unwrap(), &mut output, &edits).unwrap_err();
+    }
+}
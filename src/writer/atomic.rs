@@ -0,0 +1,231 @@
+//! Replacing files atomically.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Rewrites the file at `path` with any of the writing functions, without
+/// ever leaving a partially written file behind, which is important for
+/// archival use.
+///
+/// `write` is called with the original file as the reader, and a temporary
+/// file in the same directory as the writer. If it succeeds, the temporary
+/// file is synced to disk (`fsync`), given the permissions of the original
+/// file, and renamed over it. Otherwise the temporary file is removed, and
+/// the original is untouched.
+///
+/// The rename is atomic on POSIX systems, so after a crash the file has
+/// either the old or the new content. Once the file has been replaced, the
+/// directory is synced as well so that the rename survives a crash; if that
+/// fails, the new content is kept and `Ok` is still returned, with a logged
+/// warning.
+///
+/// If `path` is a symbolic link, the file it points to is rewritten, and the
+/// link is kept.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// # let dir = std::env::temp_dir().join("nom-exif-rewrite-file-doctest");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// let path = dir.join("exif.jpg");
+/// std::fs::copy("./testdata/exif.jpg", &path).unwrap();
+///
+/// let mut edits = MetadataEdits::new();
+/// edits.remove_gps();
+/// rewrite_file(&path, |reader, writer| write_metadata(reader, writer, &edits)).unwrap();
+///
+/// // Works with the other writers as well
+/// let mut writer = MediaWriter::new(edits);
+/// rewrite_file(&path, |reader, output| writer.write(reader, output)).unwrap();
+/// rewrite_file(&path, |reader, writer| {
+///     strip_metadata(reader, writer, StripOptions::default())
+/// })
+/// .unwrap();
+///
+/// let exif = parse_exif(std::fs::File::open(&path).unwrap(), None).unwrap();
+/// assert!(exif.is_none());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn rewrite_file<P, F>(path: P, write: F) -> crate::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File, &mut BufWriter<File>) -> crate::Result<()>,
+{
+    let path = path.as_ref();
+    // Renaming over a link would replace the link itself
+    let path = if fs::symlink_metadata(path)?.file_type().is_symlink() {
+        fs::canonicalize(path)?
+    } else {
+        path.to_path_buf()
+    };
+    let mut reader = File::open(&path)?;
+    let permissions = reader.metadata()?.permissions();
+
+    let (tmp_path, tmp_file) = create_temp_file(&path)?;
+    let result = (|| {
+        let mut writer = BufWriter::new(tmp_file);
+        write(&mut reader, &mut writer)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.set_permissions(permissions)?;
+        file.sync_all()?;
+        drop(file);
+
+        // Windows can't rename a file over an open one
+        drop(reader);
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    // The file has been replaced, and the temporary file is gone
+    if let Err(e) = sync_dir(&path) {
+        tracing::warn!(path = %path.display(), ?e, "Failed to sync the directory after the rename.");
+    }
+    Ok(())
+}
+
+/// Creates a new temporary file next to `path`.
+fn create_temp_file(path: &Path) -> crate::Result<(PathBuf, File)> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("invalid file path: {}", path.display()))?
+        .to_string_lossy();
+    loop {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp_path = path.with_file_name(format!(".{name}.{}.{n}.tmp", std::process::id()));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+        {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Makes the rename durable, by syncing the directory of `path`.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> crate::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> crate::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testkit::*, write_metadata, MetadataEdits};
+    use std::io::Write;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nom-exif-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn rewrite() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let dir = temp_dir("rewrite");
+        let path = dir.join("exif.jpg");
+        fs::copy("./testdata/exif.jpg", &path).unwrap();
+
+        let mut edits = MetadataEdits::new();
+        edits.remove_gps();
+        rewrite_file(&path, |r, w| write_metadata(r, w, &edits)).unwrap();
+
+        let mut expected = Vec::new();
+        write_metadata(open_sample("exif.jpg").unwrap(), &mut expected, &edits).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        assert_eq!(file_names(&dir), ["exif.jpg"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rewrite_error() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let dir = temp_dir("rewrite-error");
+        let path = dir.join("exif.png");
+        fs::copy("./testdata/exif.png", &path).unwrap();
+
+        let mut edits = MetadataEdits::new();
+        edits.remove_gps();
+        rewrite_file(&path, |r, w| {
+            // Fails after writing something
+            w.write_all(b"garbage")?;
+            write_metadata(r, w, &edits)
+        })
+        .unwrap_err();
+
+        assert_eq!(fs::read(&path).unwrap(), read_sample("exif.png").unwrap());
+        assert_eq!(file_names(&dir), ["exif.png"]);
+
+        rewrite_file(dir.join("missing.jpg"), |_, _| Ok(())).unwrap_err();
+        assert_eq!(file_names(&dir), ["exif.png"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rewrite_symlink() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let dir = temp_dir("rewrite-symlink");
+        let path = dir.join("exif.jpg");
+        fs::copy("./testdata/exif.jpg", &path).unwrap();
+        let link = dir.join("link.jpg");
+        std::os::unix::fs::symlink("exif.jpg", &link).unwrap();
+
+        let mut edits = MetadataEdits::new();
+        edits.remove_gps();
+        rewrite_file(&link, |r, w| write_metadata(r, w, &edits)).unwrap();
+
+        // The target has been rewritten, and the link still points to it
+        let mut expected = Vec::new();
+        write_metadata(open_sample("exif.jpg").unwrap(), &mut expected, &edits).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("exif.jpg"));
+        let mut names = file_names(&dir);
+        names.sort();
+        assert_eq!(names, ["exif.jpg", "link.jpg"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
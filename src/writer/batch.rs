@@ -0,0 +1,170 @@
+//! Applying the same edits to many files.
+
+use std::io::{Read, Write};
+
+use super::{write_buf, MetadataEdits};
+
+/// Applies a set of [`MetadataEdits`] to many files, e.g. to set the
+/// copyright of a whole photo library.
+///
+/// Unlike [`write_metadata`](crate::write_metadata), which allocates a new
+/// buffer for each file, `MediaWriter` keeps the buffer which files are read
+/// into between calls, so processing thousands of files doesn't allocate a
+/// buffer of the file size every time. After a file larger than
+/// [`MediaWriter::max_retained_capacity`] (default: 64 MiB, e.g. a video),
+/// the buffer is shrunk back to it. The output of each file is still built
+/// in a new buffer, except for videos whose metadata isn't changed, which
+/// are written from the read buffer.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let mut edits = MetadataEdits::new();
+/// edits.set_exif(ExifTag::Copyright, "(c) 2024 Jane Doe").remove_gps();
+/// let mut writer = MediaWriter::new(edits);
+///
+/// for path in ["./testdata/exif.jpg", "./testdata/exif.heic"] {
+///     let reader = std::fs::File::open(path).unwrap();
+///     let mut output = Vec::new();
+///     writer.write(reader, &mut output).unwrap();
+///
+///     let exif: Exif = parse_exif(output.as_slice(), None).unwrap().unwrap().into();
+///     assert!(exif.get_gps_info().unwrap().is_none());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MediaWriter {
+    edits: MetadataEdits,
+    buf: Vec<u8>,
+    max_retained_capacity: usize,
+}
+
+/// Default of [`MediaWriter::max_retained_capacity`].
+const MAX_RETAINED_CAPACITY: usize = 64 * 1024 * 1024;
+
+impl Default for MediaWriter {
+    fn default() -> Self {
+        Self::new(MetadataEdits::default())
+    }
+}
+
+impl MediaWriter {
+    /// Creates a writer which applies `edits` to each file.
+    pub fn new(edits: MetadataEdits) -> Self {
+        Self {
+            edits,
+            buf: Vec::new(),
+            max_retained_capacity: MAX_RETAINED_CAPACITY,
+        }
+    }
+
+    /// Sets the capacity which the read buffer is shrunk to after a larger
+    /// file, so that a single large file doesn't pin its memory until the
+    /// writer is dropped. Default: 64 MiB.
+    pub fn max_retained_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.max_retained_capacity = capacity;
+        self
+    }
+
+    /// Returns the edits applied to each file.
+    pub fn edits(&self) -> &MetadataEdits {
+        &self.edits
+    }
+
+    /// Returns the edits applied to each file, so they can be changed
+    /// between files.
+    pub fn edits_mut(&mut self) -> &mut MetadataEdits {
+        &mut self.edits
+    }
+
+    /// Reads a file from `reader`, applies the edits to its metadata, and
+    /// writes the result to `writer`. See
+    /// [`write_metadata`](crate::write_metadata) for the supported file
+    /// formats.
+    ///
+    /// Errors only affect the current file, the writer can be used for the
+    /// next one.
+    pub fn write<R: Read, W: Write>(&mut self, mut reader: R, writer: W) -> crate::Result<()> {
+        self.buf.clear();
+        let res = reader
+            .read_to_end(&mut self.buf)
+            .map_err(Into::into)
+            .and_then(|_| write_buf(&self.buf, writer, &self.edits));
+        if self.buf.capacity() > self.max_retained_capacity {
+            self.buf.clear();
+            self.buf.shrink_to(self.max_retained_capacity);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testkit::*, write_metadata, ExifTag};
+
+    #[test]
+    fn write_many() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut edits = MetadataEdits::new();
+        edits.remove_gps();
+        let mut writer = MediaWriter::new(edits.clone());
+
+        let paths = ["exif.jpg", "exif.heic", "meta.mov", "exif.tif", "meta.mp4"];
+        let mut capacity = 0;
+        for path in paths {
+            let mut output = Vec::new();
+            writer
+                .write(open_sample(path).unwrap(), &mut output)
+                .unwrap();
+
+            let mut expected = Vec::new();
+            write_metadata(open_sample(path).unwrap(), &mut expected, &edits).unwrap();
+            assert_eq!(output, expected, "{path}");
+
+            // The buffer is kept
+            assert!(writer.buf.capacity() >= capacity);
+            capacity = writer.buf.capacity();
+        }
+
+        // Errors don't affect following files
+        let mut output = Vec::new();
+        writer
+            .write(open_sample("exif.png").unwrap(), &mut output)
+            .unwrap_err();
+        writer.edits_mut().set_exif(ExifTag::Copyright, "nobody");
+        writer
+            .write(open_sample("meta.mov").unwrap(), &mut output)
+            .unwrap_err();
+        writer
+            .write(open_sample("exif.jpg").unwrap(), &mut output)
+            .unwrap();
+        assert!(!output.is_empty());
+    }
+
+    #[test]
+    fn retained_capacity() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut writer = MediaWriter::new(MetadataEdits::new());
+        writer.max_retained_capacity(1000);
+        let mut output = Vec::new();
+        writer
+            .write(open_sample("exif.jpg").unwrap(), &mut output)
+            .unwrap();
+        assert!(writer.buf.capacity() <= 1000);
+        writer
+            .write(open_sample("exif.png").unwrap(), &mut output)
+            .unwrap_err();
+        assert!(writer.buf.capacity() <= 1000);
+
+        writer.max_retained_capacity(usize::MAX);
+        writer
+            .write(open_sample("exif.jpg").unwrap(), &mut output)
+            .unwrap();
+        assert!(writer.buf.capacity() > 1000);
+    }
+}
@@ -0,0 +1,376 @@
+//! Building Exif data from scratch.
+
+use std::collections::BTreeMap;
+
+use nom::number::Endianness;
+
+use crate::{exif::IfdKind, EntryValue, ExifTag, GPSInfo};
+
+use super::{
+    tiff::{Entry, Tiff, DATA_TAGS, SUB_IFD_TAGS},
+    ExifEdit, MetadataEdits,
+};
+
+/// Builds an Exif block for images which have none, e.g. renders and scans.
+///
+/// Entries required by the Exif standard are filled in with defaults if they
+/// aren't set:
+///
+/// - IFD0: `XResolution` & `YResolution` (72), `ResolutionUnit` (inches) and
+///   `YCbCrPositioning` (centered).
+///
+/// - Exif sub-IFD: `ExifVersion` (2.32), `ComponentsConfiguration` (YCbCr),
+///   `FlashPixVersion` (1.0) and `ColorSpace` (sRGB).
+///
+/// The image size (`ExifImageWidth` & `ExifImageHeight`) has no default, and
+/// must be set with [`ExifBuilder::dimensions`].
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let mut builder = ExifBuilder::new();
+/// builder
+///     .dimensions(1920, 1080)
+///     .set(ExifTag::Software, "renderer 2.0")
+///     .set(ExifTag::Copyright, "(c) 2024 Jane Doe");
+///
+/// // A bare Exif block, e.g. for a PNG `eXIf` chunk
+/// let data = builder.build().unwrap();
+/// assert!(data.starts_with(b"MM\0*"));
+///
+/// // Or write it to an image
+/// let reader = std::fs::File::open("./testdata/no-exif.jpg").unwrap();
+/// let mut output = Vec::new();
+/// write_metadata(reader, &mut output, &builder.to_edits().unwrap()).unwrap();
+///
+/// let exif: Exif = parse_exif(output.as_slice(), None).unwrap().unwrap().into();
+/// assert_eq!(exif.get(ExifTag::Software).unwrap().as_str(), Some("renderer 2.0"));
+/// assert_eq!(exif.get(ExifTag::ExifImageWidth).unwrap().as_u32(), Some(1920));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExifBuilder {
+    little_endian: bool,
+    entries: BTreeMap<(IfdKind, u16), EntryValue>,
+    gps: Option<GPSInfo>,
+}
+
+impl ExifBuilder {
+    /// Creates a builder for big-endian (`MM`) Exif data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses little-endian (`II`) byte order instead of big-endian.
+    pub fn little_endian(&mut self, little_endian: bool) -> &mut Self {
+        self.little_endian = little_endian;
+        self
+    }
+
+    /// Sets the entry `tag` to `value`, in the IFD where `tag` conventionally
+    /// belongs. See [`MetadataEdits::set_exif`].
+    pub fn set(&mut self, tag: ExifTag, value: impl Into<EntryValue>) -> &mut Self {
+        self.set_by_tag_code(IfdKind::of_tag(tag), tag.code(), value)
+    }
+
+    /// Sets the entry with raw tag `code` in the specified `ifd`.
+    pub fn set_by_tag_code(
+        &mut self,
+        ifd: IfdKind,
+        code: u16,
+        value: impl Into<EntryValue>,
+    ) -> &mut Self {
+        self.entries.insert((ifd, code), value.into());
+        self
+    }
+
+    /// Sets `ExifImageWidth` & `ExifImageHeight`.
+    pub fn dimensions(&mut self, width: u32, height: u32) -> &mut Self {
+        self.set(ExifTag::ExifImageWidth, width)
+            .set(ExifTag::ExifImageHeight, height)
+    }
+
+    /// Sets the location, see [`MetadataEdits::set_gps`].
+    pub fn set_gps(&mut self, gps: &GPSInfo) -> &mut Self {
+        self.gps = Some(gps.clone());
+        self
+    }
+
+    /// Checks that the entries make up a valid Exif structure:
+    ///
+    /// - Entries can only be set in IFD0, and the Exif & GPS sub-IFDs.
+    ///
+    /// - Known tags must be in the IFD where they belong, e.g. `ExposureTime`
+    ///   can't be set in IFD0.
+    ///
+    /// - Sub-IFD pointers and tags referencing image data (e.g.
+    ///   `StripOffsets`) are generated when writing, so they can't be set.
+    ///
+    /// - Required entries must exist, and have the value types required by
+    ///   the standard.
+    pub fn validate(&self) -> crate::Result<()> {
+        for (&(ifd, code), value) in self.entries.iter() {
+            if !matches!(ifd, IfdKind::Ifd0 | IfdKind::Exif | IfdKind::Gps) {
+                return Err(format!("entries can't be set in {ifd:?}").into());
+            }
+            if SUB_IFD_TAGS.contains(&code) || DATA_TAGS.iter().any(|x| x.0 == code || x.1 == code)
+            {
+                return Err(format!("tag 0x{code:04x} is generated, it can't be set").into());
+            }
+            if let Ok(tag) = ExifTag::try_from(code) {
+                let expected = IfdKind::of_tag(tag);
+                if expected != ifd {
+                    return Err(format!("{tag} belongs to {expected:?}, not {ifd:?}").into());
+                }
+            }
+            if !has_valid_type(ifd, code, value) {
+                return Err(format!("invalid value for tag 0x{code:04x}: {value:?}").into());
+            }
+        }
+
+        for tag in [ExifTag::ExifImageWidth, ExifTag::ExifImageHeight] {
+            if !self.entries.contains_key(&(IfdKind::Exif, tag.code())) {
+                return Err(format!("missing required entry {tag}").into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the entries, and returns the edits which add them (and the
+    /// defaults) to a file with [`write_metadata`](crate::write_metadata).
+    /// Existing Exif entries of the file are kept, unless they are set in the
+    /// builder. Defaults never overwrite existing entries.
+    pub fn to_edits(&self) -> crate::Result<MetadataEdits> {
+        self.validate()?;
+
+        let mut edits = MetadataEdits::new();
+        for (ifd, code, value) in default_entries() {
+            if !self.entries.contains_key(&(ifd, code)) {
+                edits.set_exif_if_absent_by_tag_code(ifd, code, value);
+            }
+        }
+        for (&(ifd, code), value) in self.entries.iter() {
+            edits.set_exif_by_tag_code(ifd, code, value.clone());
+        }
+
+        let version = (IfdKind::Gps, ExifTag::GPSVersionID.code());
+        let has_gps_entries = self.entries.keys().any(|x| x.0 == IfdKind::Gps);
+        if has_gps_entries && !self.entries.contains_key(&version) {
+            // Version 2.3.0.0, which is a BYTE array
+            edits.exif.push(ExifEdit::Copy {
+                ifd: version.0,
+                code: version.1,
+                entry: Entry::bytes(&[2, 3, 0, 0]),
+                endian: Endianness::Big,
+            });
+        }
+        if let Some(gps) = &self.gps {
+            edits.set_gps(gps);
+        }
+        Ok(edits)
+    }
+
+    /// Validates the entries, and serializes them into a self-contained Exif
+    /// block (TIFF structure, starting with `MM` or `II`), as stored in JPEG
+    /// APP1 segments (after `Exif\0\0`), PNG `eXIf` chunks or HEIF Exif items
+    /// (after the offset header).
+    pub fn build(&self) -> crate::Result<Vec<u8>> {
+        let endian = if self.little_endian {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        };
+        let mut tiff = Tiff::new(endian);
+        self.to_edits()?.apply_exif(&mut tiff)?;
+        tiff.to_bytes(&[])
+    }
+}
+
+/// Entries required by the Exif standard for compressed images.
+fn default_entries() -> [(IfdKind, u16, EntryValue); 8] {
+    [
+        (
+            IfdKind::Ifd0,
+            ExifTag::XResolution.code(),
+            EntryValue::URational((72, 1).into()),
+        ),
+        (
+            IfdKind::Ifd0,
+            ExifTag::YResolution.code(),
+            EntryValue::URational((72, 1).into()),
+        ),
+        // Inches
+        (
+            IfdKind::Ifd0,
+            ExifTag::ResolutionUnit.code(),
+            EntryValue::U16(2),
+        ),
+        // Centered
+        (
+            IfdKind::Ifd0,
+            ExifTag::YCbCrPositioning.code(),
+            EntryValue::U16(1),
+        ),
+        (
+            IfdKind::Exif,
+            ExifTag::ExifVersion.code(),
+            EntryValue::Undefined(b"0232".to_vec()),
+        ),
+        // Y, Cb, Cr, -
+        (
+            IfdKind::Exif,
+            ExifTag::ComponentsConfiguration.code(),
+            EntryValue::Undefined(vec![1, 2, 3, 0]),
+        ),
+        (
+            IfdKind::Exif,
+            ExifTag::FlashPixVersion.code(),
+            EntryValue::Undefined(b"0100".to_vec()),
+        ),
+        // sRGB
+        (
+            IfdKind::Exif,
+            ExifTag::ColorSpace.code(),
+            EntryValue::U16(1),
+        ),
+    ]
+}
+
+/// Checks the value types of the required entries.
+fn has_valid_type(ifd: IfdKind, code: u16, value: &EntryValue) -> bool {
+    use EntryValue::*;
+
+    let tag = ExifTag::try_from(code).ok();
+    match (ifd, tag, value) {
+        (IfdKind::Ifd0, Some(ExifTag::XResolution | ExifTag::YResolution), v) => {
+            matches!(v, URational(_))
+        }
+        (IfdKind::Ifd0, Some(ExifTag::ResolutionUnit), v) => matches!(v, U16(1..=3)),
+        (IfdKind::Ifd0, Some(ExifTag::YCbCrPositioning), v) => matches!(v, U16(1..=2)),
+        (
+            IfdKind::Exif,
+            Some(
+                ExifTag::ExifVersion | ExifTag::FlashPixVersion | ExifTag::ComponentsConfiguration,
+            ),
+            v,
+        ) => matches!(v, Undefined(x) if x.len() == 4),
+        (IfdKind::Exif, Some(ExifTag::ColorSpace), v) => matches!(v, U16(_)),
+        (IfdKind::Exif, Some(ExifTag::ExifImageWidth | ExifTag::ExifImageHeight), v) => {
+            matches!(v, U16(1..) | U32(1..))
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exif::input_to_exif, testkit::*, write_metadata, Exif};
+    use chrono::DateTime;
+    use test_case::test_case;
+
+    fn parse(data: &[u8]) -> Exif {
+        input_to_exif(data.to_vec()).unwrap()
+    }
+
+    #[test_case(false)]
+    #[test_case(true)]
+    fn build(little_endian: bool) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let t = DateTime::parse_from_rfc3339("2024-03-01T10:30:00+01:00").unwrap();
+        let gps = GPSInfo::from_decimal(48.8577, 2.295, 35.0).unwrap();
+        let mut builder = ExifBuilder::new();
+        builder
+            .little_endian(little_endian)
+            .dimensions(640, 480)
+            .set(ExifTag::Make, "scanner")
+            .set(ExifTag::DateTimeOriginal, t)
+            .set(ExifTag::ColorSpace, 0xffffu16)
+            .set_gps(&gps);
+        let data = builder.build().unwrap();
+        assert!(data.starts_with(if little_endian { b"II*\0" } else { b"MM\0*" }));
+
+        let exif = parse(&data);
+        let get = |tag| exif.get(tag).cloned();
+        assert_eq!(get(ExifTag::Make), Some("scanner".into()));
+        assert_eq!(get(ExifTag::DateTimeOriginal), Some(t.into()));
+        assert_eq!(get(ExifTag::OffsetTimeOriginal), Some("+01:00".into()));
+        assert_eq!(get(ExifTag::ExifImageHeight), Some(480u32.into()));
+        assert_eq!(get(ExifTag::ColorSpace), Some(0xffffu16.into()));
+        assert_eq!(get(ExifTag::ResolutionUnit), Some(2u16.into()));
+        assert_eq!(
+            get(ExifTag::ExifVersion),
+            Some(EntryValue::Undefined(b"0232".to_vec()))
+        );
+        assert_eq!(exif.get_gps_info().unwrap(), Some(gps));
+    }
+
+    #[test]
+    fn gps_entries() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut builder = ExifBuilder::new();
+        builder
+            .dimensions(1, 1)
+            .set(ExifTag::GPSLatitudeRef, "N")
+            .set(ExifTag::GPSAltitude, EntryValue::URational((10, 1).into()));
+        let data = builder.build().unwrap();
+
+        let tiff = Tiff::parse(&data).unwrap();
+        let gps = tiff.ifd(IfdKind::Gps).unwrap();
+        let version = &gps.entries[&ExifTag::GPSVersionID.code()];
+        assert_eq!(version.data, [2, 3, 0, 0]);
+    }
+
+    #[test]
+    fn to_edits() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut builder = ExifBuilder::new();
+        builder.dimensions(100, 200).set(ExifTag::Artist, "me");
+        let edits = builder.to_edits().unwrap();
+
+        let mut output = Vec::new();
+        write_metadata(open_sample("no-exif.jpg").unwrap(), &mut output, &edits).unwrap();
+        let exif: Exif = crate::parse_exif(output.as_slice(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(exif.get(ExifTag::Artist), Some(&"me".into()));
+        assert_eq!(exif.get(ExifTag::ExifImageWidth), Some(&100u32.into()));
+        assert_eq!(
+            exif.get(ExifTag::XResolution),
+            Some(&EntryValue::URational((72, 1).into()))
+        );
+    }
+
+    #[test_case(IfdKind::Ifd1, 0x010f, "x".into(); "ifd1")]
+    #[test_case(IfdKind::Ifd0, ExifTag::ExifOffset.code(), 8u32.into(); "pointer")]
+    #[test_case(IfdKind::Ifd0, 0x0111, 8u32.into(); "strip offsets")]
+    #[test_case(IfdKind::Ifd0, ExifTag::ExposureTime.code(), EntryValue::URational((1, 100).into()); "wrong ifd")]
+    #[test_case(IfdKind::Exif, ExifTag::ExifVersion.code(), "0232".into(); "version type")]
+    #[test_case(IfdKind::Exif, ExifTag::ExifImageWidth.code(), 0u32.into(); "zero width")]
+    #[test_case(IfdKind::Ifd0, ExifTag::ResolutionUnit.code(), 2u32.into(); "unit type")]
+    fn invalid(ifd: IfdKind, code: u16, value: EntryValue) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut builder = ExifBuilder::new();
+        builder.dimensions(1, 1).set_by_tag_code(ifd, code, value);
+        builder.validate().unwrap_err();
+        builder.build().unwrap_err();
+        builder.to_edits().unwrap_err();
+    }
+
+    #[test]
+    fn missing_dimensions() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut builder = ExifBuilder::new();
+        builder.set(ExifTag::Make, "x");
+        builder.build().unwrap_err();
+        builder.dimensions(1, 1);
+        builder.build().unwrap();
+    }
+}
@@ -0,0 +1,393 @@
+//! Copying metadata from one file to another.
+
+use std::io::{Cursor, Read, Write};
+
+use crate::{
+    exif::input_to_exif, file::check_qt_mp4, mov::TRACK_KEYS, parse_metadata, EntryValue, ExifTag,
+    GPSInfo,
+};
+
+use super::{
+    check_heif, heif,
+    mov::{ACCURACY_KEY, CREATIONDATE_KEY, LOCATION_KEY},
+    read_exif_payload, read_xmp,
+    tiff::Tiff,
+    write_metadata, ExifEdit, IfdKind, MetadataEdits, XML_PACKET,
+};
+
+/// QuickTime items and the corresponding Exif entries.
+const QUICKTIME_TAGS: [(&str, ExifTag); 3] = [
+    ("com.apple.quicktime.make", ExifTag::Make),
+    ("com.apple.quicktime.model", ExifTag::Model),
+    ("com.apple.quicktime.software", ExifTag::Software),
+];
+
+/// Options for [`copy_metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyOptions {
+    /// Copy Exif entries (QuickTime metadata items for videos), except for
+    /// location info. Default: `true`.
+    pub exif: bool,
+
+    /// Copy location info. Default: `true`.
+    pub gps: bool,
+
+    /// Copy the XMP packet (between images only). Default: `true`.
+    pub xmp: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            exif: true,
+            gps: true,
+            xmp: true,
+        }
+    }
+}
+
+/// Reads metadata from `src`, copies it to the file read from `dst`, and
+/// writes the result to `writer`, e.g. to copy metadata from a RAW file to an
+/// exported JPEG, or to restore metadata which a transcoder dropped.
+///
+/// Metadata of `dst` which doesn't exist in `src` is kept. Format differences
+/// are handled as follows:
+///
+/// - Between images (TIFF, JPEG, HEIF/HEIC): entries of IFD0 and the Exif,
+///   GPS & Interop sub-IFDs are copied as is, except for the ones describing
+///   the layout of the image data (e.g. `ImageWidth`, `StripOffsets`), which
+///   belong to `dst`. The GPS sub-IFD is replaced as a whole, and the
+///   thumbnail (IFD1) is not copied. The XMP packet is
+///   copied if `dst` can store it, i.e. HEIF/HEIC files must already have an
+///   XMP item.
+///
+/// - Between videos (QuickTime/MP4): the `moov/meta` items are copied.
+///
+/// - Between an image and a video: `Make`, `Model`, `Software`,
+///   `DateTimeOriginal` and the GPS location are converted to the
+///   corresponding QuickTime items, and vice versa.
+///
+/// See [`write_metadata`] for the limitations of each file format.
+///
+/// # Usage
+///
+/// ```rust
+/// use nom_exif::*;
+///
+/// let src = std::fs::File::open("./testdata/meta.mov").unwrap();
+/// let dst = std::fs::File::open("./testdata/exif.jpg").unwrap();
+/// let mut output = Vec::new();
+/// copy_metadata(src, dst, &mut output, CopyOptions::default()).unwrap();
+///
+/// let exif: Exif = parse_exif(output.as_slice(), None).unwrap().unwrap().into();
+/// assert_eq!(exif.get(ExifTag::Model).and_then(|x| x.as_str()), Some("iPhone X"));
+/// let gps = exif.get_gps_info().unwrap().unwrap();
+/// assert_eq!(gps.format_iso6709(), "+27.12810+100.25080/");
+/// ```
+pub fn copy_metadata<S: Read, R: Read, W: Write>(
+    mut src: S,
+    mut dst: R,
+    writer: W,
+    options: CopyOptions,
+) -> crate::Result<()> {
+    let mut src_data = Vec::new();
+    src.read_to_end(&mut src_data)?;
+    let mut dst_data = Vec::new();
+    dst.read_to_end(&mut dst_data)?;
+
+    let to_video = is_video(&dst_data);
+    let edits = if is_video(&src_data) {
+        let entries = parse_metadata(Cursor::new(&src_data))?;
+        edits_from_video(entries, to_video, &options)
+    } else {
+        let mut edits = edits_from_image(&src_data, to_video, &options)?;
+        if options.xmp && !to_video && can_write_xmp(&dst_data)? {
+            if let Some(packet) = read_xmp(src_data.as_slice())? {
+                edits.set_xmp(packet);
+            }
+        }
+        edits
+    };
+
+    write_metadata(dst_data.as_slice(), writer, &edits)
+}
+
+fn is_video(data: &[u8]) -> bool {
+    !data.starts_with(b"II*\0")
+        && !data.starts_with(b"MM\0*")
+        && !data.starts_with(&[0xff, 0xd8])
+        && check_heif(data).is_err()
+        && check_qt_mp4(data).is_ok()
+}
+
+/// HEIF/HEIC files must already have an XMP item.
+fn can_write_xmp(dst: &[u8]) -> crate::Result<bool> {
+    if check_heif(dst).is_ok() {
+        Ok(heif::read_xmp(dst)?.is_some())
+    } else {
+        Ok(true)
+    }
+}
+
+fn edits_from_image(
+    src: &[u8],
+    to_video: bool,
+    options: &CopyOptions,
+) -> crate::Result<MetadataEdits> {
+    let mut edits = MetadataEdits::new();
+    let Some(payload) = read_exif_payload(&mut &src[..], src)? else {
+        return Ok(edits);
+    };
+    let data = &payload.data[payload.tiff_start..];
+
+    if to_video {
+        let exif = input_to_exif(data.to_vec())?;
+        if options.exif {
+            for (key, tag) in QUICKTIME_TAGS {
+                if let Some(s) = exif.get(tag).and_then(|x| x.as_str()) {
+                    edits.set_quicktime(key, s);
+                }
+            }
+            if let Some(t) = exif
+                .get(ExifTag::DateTimeOriginal)
+                .and_then(|x| x.as_time())
+            {
+                edits.set_quicktime(CREATIONDATE_KEY, t);
+            }
+        }
+        if options.gps {
+            if let Some(gps) = exif.get_gps_info()? {
+                edits.set_gps(&gps);
+            }
+        }
+        return Ok(edits);
+    }
+
+    let tiff = Tiff::parse(data)?;
+    let entries = tiff.metadata_entries();
+    if options.gps && entries.iter().any(|x| x.0 == IfdKind::Gps) {
+        // A location is copied as a whole, stale entries (e.g. the altitude)
+        // of `dst` are removed.
+        edits.remove_exif(ExifTag::GPSInfo);
+    }
+    for (ifd, code, entry) in entries {
+        let wanted = match ifd {
+            IfdKind::Gps => options.gps,
+            // XMP is copied separately
+            _ => options.exif && code != XML_PACKET,
+        };
+        if wanted {
+            edits.exif.push(ExifEdit::Copy {
+                ifd,
+                code,
+                entry: entry.clone(),
+                endian: tiff.endian,
+            });
+        }
+    }
+    Ok(edits)
+}
+
+fn edits_from_video(
+    entries: Vec<(String, EntryValue)>,
+    to_video: bool,
+    options: &CopyOptions,
+) -> MetadataEdits {
+    let mut edits = MetadataEdits::new();
+    if options.gps && !to_video {
+        match GPSInfo::from_quicktime(&entries) {
+            Ok(Some(gps)) => {
+                edits.set_gps(&gps);
+            }
+            Ok(None) => (),
+            Err(e) => tracing::warn!(?e, "Skip invalid location."),
+        }
+    }
+    for (key, value) in entries {
+        if TRACK_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let is_location = key == LOCATION_KEY || key == ACCURACY_KEY;
+        if (is_location && !options.gps) || (!is_location && !options.exif) {
+            continue;
+        }
+
+        if to_video {
+            edits.set_quicktime(&key, value);
+        } else if key == CREATIONDATE_KEY {
+            if let Some(t) = value.as_time() {
+                edits.set_exif(ExifTag::DateTimeOriginal, t);
+            }
+        } else if let Some((_, tag)) = QUICKTIME_TAGS.iter().find(|x| x.0 == key) {
+            if let Some(s) = value.as_str() {
+                edits.set_exif(*tag, s);
+            }
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{parse_exif, testkit::*, Exif};
+
+    fn copy(src: &str, dst: &str, options: CopyOptions) -> Vec<u8> {
+        let mut output = Vec::new();
+        copy_metadata(
+            open_sample(src).unwrap(),
+            open_sample(dst).unwrap(),
+            &mut output,
+            options,
+        )
+        .unwrap();
+        output
+    }
+
+    fn exif(data: &[u8]) -> Exif {
+        if data.starts_with(b"II") || data.starts_with(b"MM") {
+            return input_to_exif(data.to_vec()).unwrap();
+        }
+        parse_exif(data, None).unwrap().unwrap().into()
+    }
+
+    #[test_case("exif.jpg", "exif.heic")]
+    #[test_case("exif.heic", "exif.jpg")]
+    #[test_case("exif.tif", "exif.jpg")]
+    #[test_case("exif.jpg", "exif.tif")]
+    fn image_to_image(src: &str, dst: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let src_exif = exif(&read_sample(src).unwrap());
+        let dst_exif = exif(&read_sample(dst).unwrap());
+        let output = exif(&copy(src, dst, CopyOptions::default()));
+
+        for tag in [
+            ExifTag::Make,
+            ExifTag::Model,
+            ExifTag::DateTimeOriginal,
+            ExifTag::XResolution,
+            ExifTag::ExposureTime,
+        ] {
+            match src_exif.get(tag) {
+                Some(value) => assert_eq!(output.get(tag), Some(value), "{tag}"),
+                None => assert_eq!(output.get(tag), dst_exif.get(tag), "{tag}"),
+            }
+        }
+        // Image layout belongs to dst
+        for tag in [ExifTag::ImageWidth, ExifTag::ExifImageWidth] {
+            assert_eq!(output.get(tag), dst_exif.get(tag), "{tag}");
+        }
+        assert_eq!(
+            output.get_gps_info().unwrap(),
+            src_exif
+                .get_gps_info()
+                .unwrap()
+                .or(dst_exif.get_gps_info().unwrap())
+        );
+    }
+
+    #[test]
+    fn image_options() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let options = CopyOptions {
+            exif: false,
+            gps: true,
+            xmp: false,
+        };
+        let output = exif(&copy("exif.jpg", "exif.heic", options));
+        let src = exif(&read_sample("exif.jpg").unwrap());
+        let dst = exif(&read_sample("exif.heic").unwrap());
+        assert_eq!(output.get(ExifTag::Make), dst.get(ExifTag::Make));
+        assert_eq!(output.get_gps_info().unwrap(), src.get_gps_info().unwrap());
+
+        let options = CopyOptions {
+            gps: false,
+            ..Default::default()
+        };
+        let output = exif(&copy("exif.jpg", "exif.heic", options));
+        assert_eq!(output.get(ExifTag::Make), src.get(ExifTag::Make));
+        assert_eq!(output.get_gps_info().unwrap(), dst.get_gps_info().unwrap());
+    }
+
+    #[test]
+    fn xmp() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let packet = read_xmp(open_sample("exif.heic").unwrap())
+            .unwrap()
+            .unwrap();
+        let output = copy("exif.heic", "exif.jpg", CopyOptions::default());
+        let copied = read_xmp(output.as_slice()).unwrap().unwrap();
+
+        // Padding may differ
+        let start = packet.find("<x:xmpmeta").unwrap();
+        let end = packet.find("</x:xmpmeta>").unwrap();
+        assert!(copied.contains(&packet[start..end]));
+    }
+
+    #[test_case("exif.jpg", "meta.mov")]
+    #[test_case("exif.heic", "meta.mp4")]
+    fn image_to_video(src: &str, dst: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let src_exif = exif(&read_sample(src).unwrap());
+        let output = copy(src, dst, CopyOptions::default());
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        let get = |key: &str| entries.iter().find(|x| x.0 == key).map(|x| &x.1);
+
+        assert_eq!(get("com.apple.quicktime.make"), src_exif.get(ExifTag::Make));
+        assert_eq!(
+            get(CREATIONDATE_KEY),
+            src_exif.get(ExifTag::DateTimeOriginal)
+        );
+        assert_eq!(
+            get(LOCATION_KEY).and_then(|x| x.as_str()),
+            Some(
+                src_exif
+                    .get_gps_info()
+                    .unwrap()
+                    .unwrap()
+                    .format_iso6709()
+                    .as_str()
+            )
+        );
+        assert_eq!(
+            GPSInfo::from_quicktime(&entries)
+                .unwrap()
+                .unwrap()
+                .h_positioning_error
+                .1
+                != 0,
+            src == "exif.heic"
+        );
+    }
+
+    #[test_case("embedded-in-heic.mov", "exif.jpg")]
+    #[test_case("meta.mov", "exif.jpg")]
+    fn video_to_image(src: &str, dst: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let entries = parse_metadata(Cursor::new(read_sample(src).unwrap())).unwrap();
+        let expected = GPSInfo::from_quicktime(&entries).unwrap().unwrap();
+        let output = copy(src, dst, CopyOptions::default());
+        let gps = exif(&output).get_gps_info().unwrap().unwrap();
+        assert_eq!(gps.format_iso6709(), expected.format_iso6709());
+        assert_eq!(gps.h_positioning_error, expected.h_positioning_error);
+    }
+
+    #[test]
+    fn video_to_video() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let src = parse_metadata(Cursor::new(read_sample("meta.mov").unwrap())).unwrap();
+        let output = copy("meta.mov", "meta.mp4", CopyOptions::default());
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        for entry in src.iter().filter(|x| !TRACK_KEYS.contains(&x.0.as_str())) {
+            assert!(entries.contains(entry), "{entry:?}");
+        }
+    }
+}
@@ -0,0 +1,534 @@
+use std::ops::Range;
+
+use crate::{
+    bbox::{BoxHolder, IinfBox, IlocBox, ParseBox},
+    error::convert_parse_error,
+    heif::XMP_CONTENT_TYPE,
+};
+
+use super::{tiff::Tiff, xmp, ExifPayload, MetadataEdits, StripOptions};
+
+const FILE_OFFSET: u8 = 0;
+const IDAT_OFFSET: u8 = 1;
+
+const EMPTY_XMP: &[u8] = br#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?><x:xmpmeta xmlns:x="adobe:ns:meta/"/><?xpacket end="w"?>"#;
+
+/// Replaces the Exif and XMP item payloads in a HEIF/HEIC file.
+pub(crate) fn write(input: &[u8], edits: &MetadataEdits) -> crate::Result<Vec<u8>> {
+    let heif = Heif::parse(input)?;
+
+    let mut items = Vec::new();
+    if edits.has_exif_edits() {
+        let Some(exif_id) = heif.meta.exif_id else {
+            return Err("no Exif item found; adding a new one is not supported".into());
+        };
+        let payload = heif.item_data(input, exif_id)?;
+        let payload = rebuild_exif_payload(&payload, |tiff| edits.apply_exif(tiff))?;
+        items.push((exif_id, payload));
+    }
+    if let Some(packet) = edits.xmp.as_deref() {
+        let Some(xmp_id) = heif.meta.xmp_id else {
+            return Err("no XMP item found; adding a new one is not supported".into());
+        };
+        let old_len = heif.item_data(input, xmp_id)?.len();
+        items.push((xmp_id, xmp::pad_packet(packet, Some(old_len), usize::MAX)?));
+    }
+
+    heif.replace_items(input, items)
+}
+
+/// Like [`write`], but returns `None` if any byte of the original file would
+/// be moved, i.e. the `meta` box changes its size.
+pub(crate) fn write_in_place(
+    input: &[u8],
+    edits: &MetadataEdits,
+) -> crate::Result<Option<Vec<u8>>> {
+    let output = write(input, edits)?;
+    let boxes = top_level_boxes(input)?;
+    Ok(top_level_boxes(&output)?
+        .starts_with(&boxes)
+        .then_some(output))
+}
+
+/// Returns the payload of the Exif item, and where it is stored in `input`.
+pub(crate) fn exif_item(input: &[u8]) -> crate::Result<Option<ExifPayload>> {
+    let heif = Heif::parse(input)?;
+    let Some(exif_id) = heif.meta.exif_id else {
+        return Ok(None);
+    };
+    let (_, _, extents) = heif.item_extents(input, exif_id)?;
+    let data = heif.item_data(input, exif_id)?;
+    Ok(Some(ExifPayload {
+        tiff_start: tiff_start(&data)?,
+        data,
+        extents,
+    }))
+}
+
+/// Returns the XMP packet of the primary image.
+pub(crate) fn read_xmp(input: &[u8]) -> crate::Result<Option<String>> {
+    let heif = Heif::parse(input)?;
+    heif.meta
+        .xmp_id
+        .map(|id| xmp::packet_str(&heif.item_data(input, id)?))
+        .transpose()
+}
+
+/// Replaces the Exif item payload with an empty (or orientation only) Exif
+/// block, and XMP items with an empty XMP packet.
+pub(crate) fn strip(input: &[u8], options: &StripOptions) -> crate::Result<Vec<u8>> {
+    let heif = Heif::parse(input)?;
+
+    let mut items = Vec::new();
+    if let Some(exif_id) = heif.meta.exif_id {
+        let payload = heif.item_data(input, exif_id)?;
+        let payload = rebuild_exif_payload(&payload, |tiff| {
+            tiff.strip(options.keep_orientation);
+            Ok(())
+        })?;
+        items.push((exif_id, payload));
+    }
+    for id in heif.meta.xmp_ids.iter() {
+        items.push((*id, EMPTY_XMP.to_vec()));
+    }
+
+    heif.replace_items(input, items)
+}
+
+/// The Exif item payload starts with a 4-byte offset to the TIFF header,
+/// which is usually preceded by "Exif\0\0".
+fn rebuild_exif_payload<F>(payload: &[u8], f: F) -> crate::Result<Vec<u8>>
+where
+    F: FnOnce(&mut Tiff) -> crate::Result<()>,
+{
+    let tiff_start = tiff_start(payload)?;
+    let data = &payload[tiff_start..];
+
+    let mut tiff = Tiff::parse(data)?;
+    f(&mut tiff)?;
+    if tiff.ifds.is_empty() {
+        // Keep an empty IFD0, so the Exif item is still valid.
+        tiff.ifd_or_insert(crate::IfdKind::Ifd0);
+    }
+
+    let mut result = payload[..tiff_start].to_vec();
+    result.extend_from_slice(&tiff.to_bytes(data)?);
+    Ok(result)
+}
+
+/// Returns the position of the TIFF header in an Exif item payload.
+fn tiff_start(payload: &[u8]) -> crate::Result<usize> {
+    payload
+        .get(..4)
+        .and_then(|x| (u32::from_be_bytes([x[0], x[1], x[2], x[3]]) as usize).checked_add(4))
+        .filter(|x| *x <= payload.len())
+        .ok_or_else(|| "invalid HEIF Exif item; bad TIFF header offset".into())
+}
+
+struct Heif {
+    /// Type and range of the top level boxes.
+    boxes: Vec<(String, Range<usize>)>,
+    meta_range: Range<usize>,
+    meta: Meta,
+}
+
+impl Heif {
+    fn parse(input: &[u8]) -> crate::Result<Heif> {
+        let boxes = top_level_boxes(input)?;
+        let Some(meta_range) = boxes
+            .iter()
+            .find(|(t, _)| t == "meta")
+            .map(|(_, r)| r.clone())
+        else {
+            return Err("invalid HEIF file; meta box not found".into());
+        };
+        let meta = Meta::parse(input, meta_range.clone())?;
+
+        Ok(Heif {
+            boxes,
+            meta_range,
+            meta,
+        })
+    }
+
+    /// Returns construction method, base offset and absolute extent ranges of
+    /// item `id`.
+    fn item_extents(&self, input: &[u8], id: u32) -> crate::Result<(u8, usize, Vec<Range<usize>>)> {
+        let item = self
+            .meta
+            .iloc
+            .item_location(id)
+            .ok_or("invalid HEIF file; item location not found")?;
+        let method = item.construction_method();
+        let base = match method {
+            FILE_OFFSET => 0,
+            IDAT_OFFSET => {
+                self.meta
+                    .idat_body
+                    .as_ref()
+                    .ok_or("invalid HEIF file; idat box not found")?
+                    .start
+            }
+            m => return Err(format!("unsupported construction method ({m})").into()),
+        };
+
+        let mut extents = Vec::new();
+        for (offset, length) in item.extents() {
+            let start = usize::try_from(offset)
+                .ok()
+                .and_then(|x| x.checked_add(base))
+                .ok_or("invalid HEIF file; item offset is out of range")?;
+            let end = start
+                .checked_add(length as usize)
+                .filter(|x| *x <= input.len() && length > 0)
+                .ok_or("invalid HEIF file; item is truncated")?;
+            extents.push(start..end);
+        }
+        Ok((method, base, extents))
+    }
+
+    fn item_data(&self, input: &[u8], id: u32) -> crate::Result<Vec<u8>> {
+        let (_, _, extents) = self.item_extents(input, id)?;
+        Ok(extents
+            .iter()
+            .flat_map(|x| input[x.clone()].iter().copied())
+            .collect())
+    }
+
+    /// Replaces the payloads of `items`.
+    ///
+    /// If a new payload fits in the original location, it is written in
+    /// place. Otherwise it is appended to the end of the file in a new `mdat`
+    /// box. The `iloc` box is updated accordingly (all the other file offsets
+    /// are fixed up if the `iloc` box changes its size).
+    ///
+    /// The original payloads are zeroed out, so removed data won't leak.
+    fn replace_items(&self, input: &[u8], items: Vec<(u32, Vec<u8>)>) -> crate::Result<Vec<u8>> {
+        let meta = &self.meta;
+        let mut output = input.to_vec();
+        let mut iloc = meta.iloc.clone();
+        let mut appended = Vec::new();
+
+        for (id, payload) in items {
+            let (method, base, extents) = self.item_extents(input, id)?;
+            for extent in extents.iter() {
+                output[extent.clone()].fill(0);
+            }
+            match extents.as_slice() {
+                [extent] if payload.len() <= extent.len() => {
+                    output[extent.start..extent.start + payload.len()].copy_from_slice(&payload);
+                    let offset = (extent.start - base) as u64;
+                    iloc.set_item_location(id, method, offset, payload.len() as u64);
+                }
+                _ => appended.push((id, payload)),
+            }
+        }
+
+        // The position of the new `mdat` box depends on the size of `iloc`,
+        // and the size of `iloc` depends on the offsets, so iterate until it's
+        // stable.
+        let mut delta = 0;
+        let iloc_bytes = loop {
+            let mut iloc = iloc.clone();
+            if delta != 0 {
+                if self
+                    .boxes
+                    .iter()
+                    .any(|(t, r)| t == "moov" && r.start >= meta.iloc_range.end)
+                {
+                    return Err("HEIF files with tracks are not supported yet".into());
+                }
+                iloc.shift_file_offsets(meta.iloc_range.end as u64, delta);
+            }
+            let mut offset = (input.len() as i64 + delta) as u64 + 8;
+            for (id, payload) in appended.iter() {
+                iloc.set_item_location(*id, FILE_OFFSET, offset, payload.len() as u64);
+                offset += payload.len() as u64;
+            }
+
+            let bytes = iloc.to_bytes();
+            let new_delta = bytes.len() as i64 - meta.iloc_range.len() as i64;
+            if new_delta == delta {
+                break bytes;
+            }
+            delta = new_delta;
+        };
+
+        let meta_range = &self.meta_range;
+        let mut result = Vec::with_capacity(output.len() + iloc_bytes.len());
+        result.extend_from_slice(&output[..meta_range.start]);
+        let meta_size = meta_range.len() as i64 + delta;
+        if meta.header_size == 16 {
+            result.extend_from_slice(&1u32.to_be_bytes());
+            result.extend_from_slice(b"meta");
+            result.extend_from_slice(&(meta_size as u64).to_be_bytes());
+        } else {
+            let size = u32::try_from(meta_size).map_err(|_| "meta box is too large")?;
+            result.extend_from_slice(&size.to_be_bytes());
+            result.extend_from_slice(b"meta");
+        }
+        result
+            .extend_from_slice(&output[meta_range.start + meta.header_size..meta.iloc_range.start]);
+        result.extend_from_slice(&iloc_bytes);
+        result.extend_from_slice(&output[meta.iloc_range.end..]);
+
+        if !appended.is_empty() {
+            let len = appended.iter().map(|x| x.1.len()).sum::<usize>() + 8;
+            let size = u32::try_from(len).map_err(|_| "item data is too large")?;
+            result.extend_from_slice(&size.to_be_bytes());
+            result.extend_from_slice(b"mdat");
+            for (_, payload) in appended {
+                result.extend_from_slice(&payload);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+struct Meta {
+    header_size: usize,
+    exif_id: Option<u32>,
+    xmp_ids: Vec<u32>,
+    /// The XMP item which describes the primary image.
+    xmp_id: Option<u32>,
+    iloc: IlocBox,
+    /// Absolute range of the `iloc` box.
+    iloc_range: Range<usize>,
+    /// Absolute range of the `idat` box body.
+    idat_body: Option<Range<usize>>,
+}
+
+impl Meta {
+    fn parse(input: &[u8], range: Range<usize>) -> crate::Result<Meta> {
+        let (_, bbox) = BoxHolder::parse(&input[range.clone()])
+            .map_err(|e| convert_parse_error(e, "parse meta box failed"))?;
+        // meta is a full box
+        let header_size = bbox.header_size();
+        let children_start = range.start + header_size + 4;
+
+        let mut exif_id = None;
+        let mut xmp_ids = Vec::new();
+        let mut iloc = None;
+        let mut idat_body = None;
+        let mut primary_id = None;
+        let mut cdsc_refs = Vec::new();
+        let mut pos = children_start;
+        while pos < range.end {
+            let (_, child) = BoxHolder::parse(&input[pos..range.end])
+                .map_err(|e| convert_parse_error(e, "parse meta children failed"))?;
+            let child_range = pos..pos + child.data.len();
+            match child.box_type() {
+                "iinf" => {
+                    let (_, iinf) = IinfBox::parse_box(child.data)
+                        .map_err(|e| convert_parse_error(e, "parse iinf failed"))?;
+                    exif_id = iinf.get_infe("Exif").map(|x| x.id);
+                    xmp_ids = iinf.mime_items(XMP_CONTENT_TYPE).map(|x| x.id).collect();
+                }
+                "iloc" => {
+                    let (_, b) = IlocBox::parse_box(child.data)
+                        .map_err(|e| convert_parse_error(e, "parse iloc failed"))?;
+                    iloc = Some((b, child_range.clone()));
+                }
+                "idat" => {
+                    idat_body = Some(pos + child.header_size()..child_range.end);
+                }
+                "pitm" => primary_id = parse_pitm(child.body_data()),
+                "iref" => cdsc_refs = parse_cdsc_refs(child.body_data()),
+                _ => (),
+            }
+            pos = child_range.end;
+        }
+
+        let (iloc, iloc_range) = iloc.ok_or("invalid HEIF file; iloc box not found")?;
+
+        // Prefer the XMP item which references the primary image, since
+        // auxiliary images (e.g. HDR gain maps) may have their own XMP items.
+        let xmp_id = xmp_ids
+            .iter()
+            .copied()
+            .find(|id| {
+                cdsc_refs
+                    .iter()
+                    .any(|(from, to)| from == id && Some(*to) == primary_id)
+            })
+            .or(xmp_ids.first().copied());
+
+        Ok(Meta {
+            header_size,
+            exif_id,
+            xmp_ids,
+            xmp_id,
+            iloc,
+            iloc_range,
+            idat_body,
+        })
+    }
+}
+
+/// Parses the body of a `pitm` box, returns the primary item id.
+fn parse_pitm(body: &[u8]) -> Option<u32> {
+    match body.first()? {
+        0 => body
+            .get(4..6)
+            .map(|x| u16::from_be_bytes([x[0], x[1]]) as u32),
+        _ => body
+            .get(4..8)
+            .map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]])),
+    }
+}
+
+/// Parses the body of an `iref` box, returns `(from, to)` pairs of the
+/// `cdsc` (content describes) references.
+fn parse_cdsc_refs(body: &[u8]) -> Vec<(u32, u32)> {
+    let id_size = if body.first() == Some(&0) { 2 } else { 4 };
+    let read_id = |data: &[u8]| match id_size {
+        2 => u16::from_be_bytes([data[0], data[1]]) as u32,
+        _ => u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+    };
+
+    let mut refs = Vec::new();
+    let mut remain = body.get(4..).unwrap_or_default();
+    while let Ok((rem, bbox)) = BoxHolder::parse(remain) {
+        let data = bbox.body_data();
+        if bbox.box_type() == "cdsc" && data.len() >= id_size + 2 {
+            let from = read_id(data);
+            let count = u16::from_be_bytes([data[id_size], data[id_size + 1]]) as usize;
+            let ids = &data[id_size + 2..];
+            refs.extend(
+                ids.chunks_exact(id_size)
+                    .take(count)
+                    .map(|x| (from, read_id(x))),
+            );
+        }
+        remain = rem;
+    }
+    refs
+}
+
+fn top_level_boxes(input: &[u8]) -> crate::Result<Vec<(String, Range<usize>)>> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos < input.len() {
+        let (_, bbox) = BoxHolder::parse(&input[pos..])
+            .map_err(|e| convert_parse_error(e, "parse box failed"))?;
+        let end = pos + bbox.data.len();
+        boxes.push((bbox.box_type().to_owned(), pos..end));
+        pos = end;
+    }
+    Ok(boxes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testkit::*, ExifTag};
+    use test_case::test_case;
+
+    /// Returns data of all items except the Exif and XMP items.
+    fn items_data(input: &[u8]) -> Vec<Vec<u8>> {
+        let boxes = top_level_boxes(input).unwrap();
+        let (_, range) = boxes.iter().find(|(t, _)| t == "meta").unwrap();
+        let meta = Meta::parse(input, range.clone()).unwrap();
+        (0..1000)
+            .filter(|id| Some(*id) != meta.exif_id && !meta.xmp_ids.contains(id))
+            .filter_map(|id| meta.iloc.item_location(id))
+            .filter(|item| item.construction_method() == FILE_OFFSET)
+            .map(|item| {
+                item.extents()
+                    .flat_map(|(offset, len)| &input[offset as usize..(offset + len) as usize])
+                    .copied()
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test_case("exif.heic", false)]
+    #[test_case("exif.heic", true)]
+    fn replace_exif_item(path: &str, grow: bool) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let mut edits = MetadataEdits::new();
+        if grow {
+            edits.set_exif(ExifTag::ImageDescription, "x".repeat(1000));
+        } else {
+            edits.remove_exif(ExifTag::GPSInfo);
+        }
+        let output = write(&input, &edits).unwrap();
+
+        if grow {
+            assert!(output.len() > input.len() + 1000);
+        } else {
+            assert_eq!(output.len(), input.len());
+        }
+        assert_eq!(items_data(&output), items_data(&input));
+
+        let exif: crate::Exif = crate::parse_exif(output.as_slice(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(exif.get(ExifTag::Make).unwrap().as_str(), Some("Apple"));
+        assert_eq!(exif.get_gps_info().unwrap().is_some(), grow);
+    }
+
+    #[test]
+    fn tiff_header_offset() {
+        assert_eq!(tiff_start(b"\0\0\0\x06Exif\0\0II").unwrap(), 10);
+        assert_eq!(tiff_start(b"\0\0\0\0II").unwrap(), 4);
+        tiff_start(b"\0\0\0\x09Exif\0\0II").unwrap_err();
+        tiff_start(b"\xff\xff\xff\xffII").unwrap_err();
+        tiff_start(b"\0\0").unwrap_err();
+    }
+
+    #[test_case("no-exif.heic")]
+    fn grow_exif_item(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let mut edits = MetadataEdits::new();
+        edits.set_exif(ExifTag::Make, "nom-exif");
+        let output = write(&input, &edits).unwrap();
+        assert_eq!(items_data(&output), items_data(&input));
+
+        let exif: crate::Exif = crate::parse_exif(output.as_slice(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert_eq!(exif.get(ExifTag::Make).unwrap().as_str(), Some("nom-exif"));
+    }
+
+    #[test_case("exif.heic", true)]
+    #[test_case("exif.heic", false)]
+    fn strip_heif(path: &str, keep_orientation: bool) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let options = StripOptions {
+            keep_orientation,
+            ..Default::default()
+        };
+        let output = strip(&input, &options).unwrap();
+        assert_eq!(output.len(), input.len());
+        assert_eq!(items_data(&output), items_data(&input));
+
+        let heif = Heif::parse(&output).unwrap();
+        assert_eq!(heif.meta.xmp_ids.len(), 2);
+        // The other one belongs to the HDR gain map.
+        assert_eq!(heif.meta.xmp_id, Some(51));
+        for id in heif.meta.xmp_ids.iter() {
+            assert_eq!(heif.item_data(&output, *id).unwrap(), EMPTY_XMP);
+        }
+
+        let exif: crate::Exif = crate::parse_exif(output.as_slice(), None)
+            .unwrap()
+            .unwrap()
+            .into();
+        assert!(exif.get(ExifTag::Make).is_none());
+        assert!(exif.get_gps_info().unwrap().is_none());
+        assert_eq!(
+            exif.get(ExifTag::Orientation).and_then(|x| x.as_u16()),
+            keep_orientation.then_some(6)
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use std::{io::Read, ops::Range};
+
+use nom::number::Endianness;
+
+use crate::{
+    exif::check_exif_header,
+    jpeg::{MarkerCode, PHOTOSHOP_IDENT, XMP_IDENT},
+};
+
+use super::{stripped_exif, tiff::Tiff, xmp, MetadataEdits, StripOptions};
+
+/// Max payload size of a JPEG segment (the length field itself takes 2 bytes).
+const MAX_SEGMENT_PAYLOAD: usize = 0xffff - 2;
+
+const EXIF_IDENT: &[u8] = b"Exif\0\0";
+const XMP_EXT_IDENT: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+const ICC_IDENT: &[u8] = b"ICC_PROFILE\0";
+
+const APP2: u8 = 0xe2;
+
+/// A JPEG segment located before SOS.
+struct Segment {
+    marker_code: u8,
+    /// Range of the whole segment, including the marker.
+    range: Range<usize>,
+}
+
+impl Segment {
+    fn payload<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        if self.range.len() > 4 {
+            &input[self.range.start + 4..self.range.end]
+        } else {
+            &[]
+        }
+    }
+
+    fn is_xmp(&self, input: &[u8]) -> bool {
+        self.marker_code == MarkerCode::APP1.code() && self.payload(input).starts_with(XMP_IDENT)
+    }
+
+    fn is_xmp_ext(&self, input: &[u8]) -> bool {
+        self.marker_code == MarkerCode::APP1.code()
+            && self.payload(input).starts_with(XMP_EXT_IDENT)
+    }
+
+    fn is_exif(&self, input: &[u8]) -> bool {
+        let payload = self.payload(input);
+        self.marker_code == MarkerCode::APP1.code()
+            && payload.len() >= EXIF_IDENT.len()
+            && check_exif_header(payload)
+    }
+}
+
+/// Layout of the JPEG header segments (the ones before SOS).
+struct JpegLayout {
+    segments: Vec<Segment>,
+
+    /// Where to insert a new APP1 segment if there isn't one.
+    insert_pos: usize,
+}
+
+impl JpegLayout {
+    fn parse(input: &[u8]) -> crate::Result<JpegLayout> {
+        if !input.starts_with(&[0xff, MarkerCode::Soi.code()]) {
+            return Err("invalid JPEG file; SOI marker not found".into());
+        }
+
+        let mut layout = JpegLayout {
+            segments: Vec::new(),
+            insert_pos: 2,
+        };
+        let mut seen_other = false;
+        let mut pos = 2;
+        loop {
+            if input.get(pos) != Some(&0xff) {
+                return Err("invalid JPEG file; marker expected".into());
+            }
+            // Skip fill bytes
+            while input.get(pos + 1) == Some(&0xff) {
+                pos += 1;
+            }
+            let Some(&code) = input.get(pos + 1) else {
+                return Err("invalid JPEG file; unexpected EOF".into());
+            };
+            if code == MarkerCode::Sos.code() || code == MarkerCode::Eoi.code() {
+                break;
+            }
+            // Standalone markers without payload: TEM, RSTn
+            if code == 0x01 || (0xd0..=0xd7).contains(&code) {
+                layout.segments.push(Segment {
+                    marker_code: code,
+                    range: pos..pos + 2,
+                });
+                pos += 2;
+                continue;
+            }
+
+            let len = input
+                .get(pos + 2..pos + 4)
+                .map(|x| u16::from_be_bytes([x[0], x[1]]) as usize)
+                .ok_or("invalid JPEG file; unexpected EOF")?;
+            let end = pos + 2 + len;
+            if len < 2 || end > input.len() {
+                return Err("invalid JPEG file; segment is truncated".into());
+            }
+
+            // A new Exif segment goes right after SOI, or after the JFIF APP0
+            // segments which must come first.
+            if code == MarkerCode::APP0.code() && !seen_other {
+                layout.insert_pos = end;
+            } else {
+                seen_other = true;
+            }
+
+            layout.segments.push(Segment {
+                marker_code: code,
+                range: pos..end,
+            });
+            pos = end;
+        }
+
+        Ok(layout)
+    }
+
+    /// Returns the first Exif APP1 segment.
+    fn exif_segment(&self, input: &[u8]) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.is_exif(input))
+    }
+}
+
+/// Rebuilds the Exif APP1 segment (or creates one if it doesn't exist), and
+/// replaces the XMP APP1 segment if a new XMP packet is set.
+pub(crate) fn write(input: &[u8], edits: &MetadataEdits) -> crate::Result<Vec<u8>> {
+    let layout = JpegLayout::parse(input)?;
+    let exif = layout.exif_segment(input);
+
+    // (range to replace, new data), sorted by position
+    let mut splices = Vec::new();
+
+    if edits.has_exif_edits() {
+        let (mut tiff, tiff_data) = match exif {
+            Some(segment) => {
+                let data = &segment.payload(input)[EXIF_IDENT.len()..];
+                (Tiff::parse(data)?, data)
+            }
+            None => (Tiff::new(Endianness::Big), &[][..]),
+        };
+        edits.apply_exif(&mut tiff)?;
+
+        let segment = if tiff.is_empty() {
+            Vec::new()
+        } else {
+            exif_segment(&tiff.to_bytes(tiff_data)?)?
+        };
+        let range = match exif {
+            Some(s) => s.range.clone(),
+            None => layout.insert_pos..layout.insert_pos,
+        };
+        splices.push((range, segment));
+    }
+
+    if let Some(packet) = edits.xmp.as_deref() {
+        let mut xmp_segments = layout.segments.iter().filter(|s| s.is_xmp(input));
+        let old = xmp_segments.next();
+        let old_len = old.map(|s| s.payload(input).len() - XMP_IDENT.len());
+        let packet = xmp::pad_packet(packet, old_len, MAX_SEGMENT_PAYLOAD - XMP_IDENT.len())?;
+        let segment = app1_segment(XMP_IDENT, &packet)?;
+
+        // The new XMP segment goes right after the Exif segment.
+        let range = match (old, exif) {
+            (Some(s), _) => s.range.clone(),
+            (None, Some(s)) => s.range.end..s.range.end,
+            (None, None) => layout.insert_pos..layout.insert_pos,
+        };
+        splices.push((range, segment));
+
+        // Extended XMP belongs to the replaced packet.
+        for s in layout.segments.iter().filter(|s| s.is_xmp_ext(input)) {
+            splices.push((s.range.clone(), Vec::new()));
+        }
+    }
+    splices.sort_by_key(|(range, _)| range.start);
+
+    let mut output = Vec::with_capacity(input.len() + 1024);
+    let mut pos = 0;
+    for (range, data) in splices {
+        output.extend_from_slice(&input[pos..range.start]);
+        output.extend_from_slice(&data);
+        pos = range.end;
+    }
+    output.extend_from_slice(&input[pos..]);
+    Ok(output)
+}
+
+/// Like [`write`], but the header segments keep their total size, so the
+/// image data doesn't move.
+///
+/// `header` must contain the segments before SOS, and the SOS marker (see
+/// [`read_header`]). If the new header is smaller, the Exif APP1 segment is
+/// padded with zeros. Returns `None` if it's larger, or there is no Exif
+/// segment to pad.
+pub(crate) fn write_in_place(
+    header: &[u8],
+    edits: &MetadataEdits,
+) -> crate::Result<Option<Vec<u8>>> {
+    let output = write(header, edits)?;
+    if output.len() > header.len() {
+        return Ok(None);
+    }
+    let padding = header.len() - output.len();
+    if padding == 0 {
+        return Ok(Some(output));
+    }
+
+    let layout = JpegLayout::parse(&output)?;
+    let Some(exif) = layout.exif_segment(&output) else {
+        return Ok(None);
+    };
+    let mut payload = exif.payload(&output).to_vec();
+    payload.resize(payload.len() + padding, 0);
+    if payload.len() > MAX_SEGMENT_PAYLOAD {
+        return Ok(None);
+    }
+
+    let mut result = Vec::with_capacity(header.len());
+    result.extend_from_slice(&output[..exif.range.start]);
+    result.extend_from_slice(&[0xff, MarkerCode::APP1.code()]);
+    result.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    result.extend_from_slice(&payload);
+    result.extend_from_slice(&output[exif.range.end..]);
+    Ok(Some(result))
+}
+
+/// Reads the segments before SOS (including the SOS marker, or EOI if there
+/// is no image data), without reading the image data.
+pub(crate) fn read_header<R: Read>(reader: &mut R) -> crate::Result<Vec<u8>> {
+    let mut header = vec![0; 2];
+    reader.read_exact(&mut header)?;
+    loop {
+        let mut marker = [0; 2];
+        reader.read_exact(&mut marker)?;
+        header.extend_from_slice(&marker);
+        if marker[0] != 0xff {
+            return Err("invalid JPEG file; marker expected".into());
+        }
+        // Skip fill bytes
+        let mut code = marker[1];
+        while code == 0xff {
+            let mut byte = [0];
+            reader.read_exact(&mut byte)?;
+            header.push(byte[0]);
+            code = byte[0];
+        }
+        if code == MarkerCode::Sos.code() || code == MarkerCode::Eoi.code() {
+            break;
+        }
+        if code == 0x01 || (0xd0..=0xd7).contains(&code) {
+            continue;
+        }
+
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        header.extend_from_slice(&len);
+        let len = u16::from_be_bytes(len) as usize;
+        if len < 2 {
+            return Err("invalid JPEG file; segment is truncated".into());
+        }
+        let start = header.len();
+        header.resize(start + len - 2, 0);
+        reader.read_exact(&mut header[start..])?;
+    }
+    Ok(header)
+}
+
+/// Returns the range of the TIFF data in the first Exif APP1 segment.
+pub(crate) fn exif_range(header: &[u8]) -> crate::Result<Option<Range<usize>>> {
+    let layout = JpegLayout::parse(header)?;
+    Ok(layout
+        .exif_segment(header)
+        .map(|s| s.range.start + 4 + EXIF_IDENT.len()..s.range.end))
+}
+
+/// Returns the XMP packet in the first XMP APP1 segment.
+pub(crate) fn read_xmp(input: &[u8]) -> crate::Result<Option<String>> {
+    let layout = JpegLayout::parse(input)?;
+    layout
+        .segments
+        .iter()
+        .find(|s| s.is_xmp(input))
+        .map(|s| xmp::packet_str(&s.payload(input)[XMP_IDENT.len()..]))
+        .transpose()
+}
+
+/// Removes Exif (APP1), XMP (APP1), IPTC (APP13) and optionally ICC profile
+/// (APP2) segments.
+pub(crate) fn strip(input: &[u8], options: &StripOptions) -> crate::Result<Vec<u8>> {
+    let layout = JpegLayout::parse(input)?;
+
+    let exif = layout
+        .exif_segment(input)
+        .map(|s| stripped_exif(&s.payload(input)[EXIF_IDENT.len()..], options))
+        .transpose()?
+        .flatten()
+        .map(|x| exif_segment(&x))
+        .transpose()?;
+
+    let removed = layout
+        .segments
+        .iter()
+        .filter(|segment| {
+            let payload = segment.payload(input);
+            match segment.marker_code {
+                x if x == MarkerCode::APP1.code() => {
+                    segment.is_exif(input) || segment.is_xmp(input) || segment.is_xmp_ext(input)
+                }
+                APP2 => !options.keep_icc && payload.starts_with(ICC_IDENT),
+                x if x == MarkerCode::APP13.code() => payload.starts_with(PHOTOSHOP_IDENT),
+                _ => false,
+            }
+        })
+        .map(|segment| segment.range.clone())
+        .collect::<Vec<_>>();
+
+    // APP0 segments are never removed, so the insert position can't be in a
+    // removed segment.
+    let mut output = Vec::with_capacity(input.len());
+    let mut pos = 0;
+    for range in removed.iter() {
+        if pos <= layout.insert_pos && layout.insert_pos <= range.start {
+            output.extend_from_slice(&input[pos..layout.insert_pos]);
+            output.extend_from_slice(exif.as_deref().unwrap_or_default());
+            pos = layout.insert_pos;
+        }
+        output.extend_from_slice(&input[pos..range.start]);
+        pos = range.end;
+    }
+    if pos <= layout.insert_pos {
+        output.extend_from_slice(&input[pos..layout.insert_pos]);
+        output.extend_from_slice(exif.as_deref().unwrap_or_default());
+        pos = layout.insert_pos;
+    }
+    output.extend_from_slice(&input[pos..]);
+
+    Ok(output)
+}
+
+/// Builds an Exif APP1 segment (including the marker) from TIFF `data`.
+fn exif_segment(data: &[u8]) -> crate::Result<Vec<u8>> {
+    app1_segment(EXIF_IDENT, data)
+        .map_err(|_| "Exif data is too large for a JPEG APP1 segment".into())
+}
+
+/// Builds an APP1 segment (including the marker) with `ident` and `data`.
+fn app1_segment(ident: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    let len = 2 + ident.len() + data.len();
+    if len - 2 > MAX_SEGMENT_PAYLOAD {
+        return Err("data is too large for a JPEG APP1 segment".into());
+    }
+
+    let mut segment = Vec::with_capacity(len + 2);
+    segment.extend_from_slice(&[0xff, MarkerCode::APP1.code()]);
+    segment.extend_from_slice(&(len as u16).to_be_bytes());
+    segment.extend_from_slice(ident);
+    segment.extend_from_slice(data);
+    Ok(segment)
+}
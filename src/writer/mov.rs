@@ -0,0 +1,847 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+use chrono::{DateTime, Duration};
+
+use crate::{
+    bbox::{find_box, travel_while, BoxHolder},
+    slice::SubsliceOffset,
+    EntryValue,
+};
+
+/// Prefix of the QuickTime metadata keys which carry location info, e.g.
+/// `com.apple.quicktime.location.ISO6709`.
+const LOCATION_KEY_PREFIX: &str = "com.apple.quicktime.location";
+
+pub(crate) const LOCATION_KEY: &str = "com.apple.quicktime.location.ISO6709";
+pub(crate) const ACCURACY_KEY: &str = "com.apple.quicktime.location.accuracy.horizontal";
+
+pub(crate) const CREATIONDATE_KEY: &str = "com.apple.quicktime.creationdate";
+
+/// `©xyz` atom in `moov/udta`, which is used by Android phones.
+const XYZ_TYPE: &[u8] = b"\xa9xyz";
+
+/// Removes location info from a QuickTime/MP4 file.
+///
+/// All modifications are done in place, so the file size, and the offsets of
+/// the media data, stay the same:
+///
+/// - Location keys & items are removed from `moov/meta/keys` &
+///   `moov/meta/ilst`, and the freed space is filled with a `free` atom.
+///
+/// - `moov/udta/©xyz` atoms are turned into zeroed `free` atoms.
+pub(crate) fn remove_location(input: &[u8]) -> crate::Result<Vec<u8>> {
+    let mut output = input.to_vec();
+    let moov_body = find_moov(input)?.body_data();
+
+    if let Ok((_, Some(meta))) = find_box(moov_body, "meta") {
+        if let Some(body) = scrub_meta_body(meta.body_data())? {
+            let start = input.subslice_offset(meta.body_data()).unwrap();
+            output[start..start + body.len()].copy_from_slice(&body);
+        }
+    }
+
+    if let Ok((_, Some(udta))) = find_box(moov_body, "udta") {
+        for bbox in children(udta.body_data()) {
+            if bbox.data.get(4..8) == Some(XYZ_TYPE) {
+                let start = input.subslice_offset(bbox.data).unwrap();
+                let atom = &mut output[start..start + bbox.data.len()];
+                atom[4..8].copy_from_slice(b"free");
+                atom[bbox.header_size()..].fill(0);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Shifts the creation & modification times of the movie, tracks and media
+/// headers (`mvhd`, `tkhd` & `mdhd`), as well as the
+/// `com.apple.quicktime.creationdate` item, by `delta`.
+///
+/// All modifications are done in place. Header times have a resolution of
+/// one second, so `delta` is truncated to whole seconds for them.
+pub(crate) fn shift_times(input: &[u8], delta: Duration) -> crate::Result<Vec<u8>> {
+    let mut output = input.to_vec();
+    let moov_body = find_moov(input)?.body_data();
+
+    let mut headers = Vec::new();
+    for bbox in children(moov_body) {
+        match bbox.box_type() {
+            "mvhd" => headers.push(bbox),
+            "trak" => {
+                for b in children(bbox.body_data()) {
+                    match b.box_type() {
+                        "tkhd" => headers.push(b),
+                        "mdia" => headers
+                            .extend(children(b.body_data()).filter(|x| x.box_type() == "mdhd")),
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    for bbox in headers {
+        let start = input.subslice_offset(bbox.body_data()).unwrap();
+        shift_header_times(&mut output[start..start + bbox.body_data().len()], delta)?;
+    }
+
+    if let Some(range) = find_meta_value(input, moov_body, CREATIONDATE_KEY) {
+        let s = std::str::from_utf8(&input[range.clone()])
+            .map_err(|_| "invalid creation date; not a UTF-8 string")?;
+        if let Ok(t) = DateTime::parse_from_str(s, "%+") {
+            let t = t
+                .checked_add_signed(delta)
+                .ok_or("shifted time is out of range")?;
+            // Keep the original offset style, i.e. "+0800" or "+08:00".
+            let offset = if s.as_bytes().get(s.len().wrapping_sub(3)) == Some(&b':') {
+                "%:z"
+            } else {
+                "%z"
+            };
+            let shifted = t.format(&format!("%Y-%m-%dT%H:%M:%S{offset}")).to_string();
+            if shifted.len() != s.len() {
+                return Err("can't shift the creation date in place".into());
+            }
+            output[range].copy_from_slice(shifted.as_bytes());
+        }
+    }
+
+    Ok(output)
+}
+
+/// Shifts the creation & modification times in the body of a `mvhd`, `tkhd`
+/// or `mdhd` atom. Zero values mean "not set", and are left untouched.
+fn shift_header_times(body: &mut [u8], delta: Duration) -> crate::Result<()> {
+    let out_of_range = || "shifted time is out of range";
+    let secs = delta.num_seconds();
+    match body.first() {
+        Some(0) if body.len() >= 12 => {
+            for pos in [4, 8] {
+                let t = u32::from_be_bytes(body[pos..pos + 4].try_into().unwrap());
+                if t != 0 {
+                    let t = u32::try_from(t as i64 + secs).map_err(|_| out_of_range())?;
+                    body[pos..pos + 4].copy_from_slice(&t.to_be_bytes());
+                }
+            }
+        }
+        Some(1) if body.len() >= 20 => {
+            for pos in [4, 12] {
+                let t = u64::from_be_bytes(body[pos..pos + 8].try_into().unwrap());
+                if t != 0 {
+                    let t = t.checked_add_signed(secs).ok_or_else(out_of_range)?;
+                    body[pos..pos + 8].copy_from_slice(&t.to_be_bytes());
+                }
+            }
+        }
+        _ => return Err("invalid QuickTime/MP4 file; broken header atom".into()),
+    }
+    Ok(())
+}
+
+/// Returns the byte range of the value of the `moov/meta` item `key`.
+fn find_meta_value(input: &[u8], moov_body: &[u8], key: &str) -> Option<Range<usize>> {
+    let (_, Some(meta)) = find_box(moov_body, "meta").ok()? else {
+        return None;
+    };
+    let (_, Some(keys)) = find_box(meta.body_data(), "keys").ok()? else {
+        return None;
+    };
+    let (_, Some(ilst)) = find_box(meta.body_data(), "ilst").ok()? else {
+        return None;
+    };
+
+    let keys_body = keys.body_data();
+    let mut pos = 8;
+    let mut index = 1_u32;
+    loop {
+        let size = u32::from_be_bytes(keys_body.get(pos..pos + 4)?.try_into().unwrap()) as usize;
+        let entry = keys_body.get(pos..pos + size)?;
+        if entry.get(8..) == Some(key.as_bytes()) {
+            break;
+        }
+        pos += size.max(8);
+        index += 1;
+    }
+
+    // An ilst item is: size, index, then a `data` atom which is: size, type,
+    // type indicator, locale and the value.
+    let item =
+        children(ilst.body_data()).find(|x| x.data.get(4..8) == Some(&index.to_be_bytes()[..]))?;
+    let value = item.data.get(24..)?;
+    let start = input.subslice_offset(value)?;
+    Some(start..start + value.len())
+}
+
+/// Applies `edit` to the `moov` atom of `file` in place, without reading or
+/// moving the media data. `edit` is given a buffer which contains only the
+/// `moov` atom, and returns the new one.
+///
+/// The new `moov` atom may use the space of the old one, and the `free` atoms
+/// right after it. Leftover space is turned into a `free` atom. If `moov` is
+/// the last atom, the file grows as needed.
+///
+/// Returns `false` if there is not enough space, `file` is untouched in this
+/// case.
+pub(crate) fn write_in_place<F, E>(file: &mut F, edit: E) -> crate::Result<bool>
+where
+    F: Read + Write + Seek,
+    E: FnOnce(Vec<u8>) -> crate::Result<Vec<u8>>,
+{
+    let atoms = top_level_atoms(file)?;
+    let pos = atoms
+        .iter()
+        .position(|(t, _)| t == b"moov")
+        .ok_or("invalid QuickTime/MP4 file; moov not found")?;
+    let moov_range = atoms[pos].1.clone();
+    let moov_len =
+        usize::try_from(moov_range.end - moov_range.start).map_err(|_| "moov atom is too large")?;
+
+    let mut moov = vec![0; moov_len];
+    file.seek(SeekFrom::Start(moov_range.start))?;
+    file.read_exact(&mut moov)?;
+    let new_moov = edit(moov)?;
+
+    let free = atoms[pos + 1..]
+        .iter()
+        .take_while(|(t, _)| t == b"free" || t == b"skip")
+        .collect::<Vec<_>>();
+    let is_last = free.len() == atoms.len() - pos - 1;
+    let room = free.last().map(|x| x.1.end).unwrap_or(moov_range.end) - moov_range.start;
+
+    let new_len = new_moov.len() as u64;
+    let padding = match room.checked_sub(new_len) {
+        Some(n) if n == 0 || n >= 8 => n,
+        // Only the last atom can grow, or have a free atom beyond the end.
+        Some(_) if is_last => 8,
+        None if is_last => 0,
+        _ => return Ok(false),
+    };
+
+    file.seek(SeekFrom::Start(moov_range.start))?;
+    file.write_all(&new_moov)?;
+    if padding > 0 {
+        let mut header = Vec::new();
+        put_box_header(&mut header, b"free", padding as usize - 8);
+        file.write_all(&header)?;
+        // Zero out the rest of the old moov, so removed data won't leak.
+        let stale = moov_range
+            .end
+            .saturating_sub(moov_range.start + new_len + 8);
+        std::io::copy(&mut std::io::repeat(0).take(stale), file)?;
+    }
+    Ok(true)
+}
+
+/// Returns the types and ranges of the top level atoms in `reader`, by
+/// seeking from header to header.
+fn top_level_atoms<R: Read + Seek>(reader: &mut R) -> crate::Result<Vec<([u8; 4], Range<u64>)>> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let mut atoms = Vec::new();
+    let mut pos = 0;
+    while pos < file_len {
+        let mut header = [0; 8];
+        reader.seek(SeekFrom::Start(pos))?;
+        reader.read_exact(&mut header)?;
+        let atom_type: [u8; 4] = header[4..].try_into().unwrap();
+        let size = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            // The atom extends to the end of the file
+            0 => file_len - pos,
+            1 => {
+                let mut size = [0; 8];
+                reader.read_exact(&mut size)?;
+                u64::from_be_bytes(size)
+            }
+            n => n as u64,
+        };
+        let end = pos
+            .checked_add(size)
+            .filter(|x| size >= 8 && *x <= file_len)
+            .ok_or("invalid QuickTime/MP4 file; atom is truncated")?;
+        atoms.push((atom_type, pos..end));
+        pos = end;
+    }
+    Ok(atoms)
+}
+
+fn find_moov(input: &[u8]) -> crate::Result<BoxHolder<'_>> {
+    match travel_while(input, |b| b.box_type() != "moov") {
+        Ok((_, Some(moov))) if moov.box_type() == "moov" => Ok(moov),
+        _ => Err("invalid QuickTime/MP4 file; moov not found".into()),
+    }
+}
+
+/// Iterates over the child atoms in `body`, stops at the first broken one.
+fn children(body: &[u8]) -> impl Iterator<Item = BoxHolder<'_>> {
+    let mut remain = body;
+    std::iter::from_fn(move || {
+        let (rem, bbox) = BoxHolder::parse(remain).ok()?;
+        remain = rem;
+        Some(bbox)
+    })
+}
+
+/// Rebuilds the body of `moov/meta` without location keys & items. Returns
+/// `None` if there is nothing to remove.
+///
+/// The returned body always has the same size as the original one.
+fn scrub_meta_body(body: &[u8]) -> crate::Result<Option<Vec<u8>>> {
+    let boxes = parse_children(body)?;
+    let Some(keys) = boxes.iter().find(|b| b.box_type() == "keys") else {
+        return Ok(None);
+    };
+
+    let keys_body = keys.body_data();
+    let mut removed = Vec::new();
+    let mut kept_keys = Vec::new();
+    for (entry, index) in key_entries(keys_body)?.into_iter().zip(1..) {
+        if entry[8..].starts_with(LOCATION_KEY_PREFIX.as_bytes()) {
+            removed.push(index);
+        } else {
+            kept_keys.push(entry);
+        }
+    }
+    if removed.is_empty() {
+        return Ok(None);
+    }
+
+    let mut output = Vec::with_capacity(body.len());
+    for bbox in boxes.iter() {
+        match bbox.box_type() {
+            "keys" => {
+                let body_len = 8 + kept_keys.iter().map(|x| x.len()).sum::<usize>();
+                put_box_header(&mut output, b"keys", body_len);
+                output.extend_from_slice(&keys_body[..4]); // version & flags
+                output.extend_from_slice(&(kept_keys.len() as u32).to_be_bytes());
+                kept_keys.iter().for_each(|x| output.extend_from_slice(x));
+            }
+            "ilst" => {
+                let mut items = Vec::new();
+                for item in parse_children(bbox.body_data())? {
+                    let index = item_index(&item);
+                    if !removed.contains(&index) {
+                        // Item indices are 1-based indices of keys, which
+                        // have been shifted by the removal.
+                        let shift = removed.iter().filter(|x| **x < index).count() as u32;
+                        let mut item = item.data.to_vec();
+                        item[4..8].copy_from_slice(&(index - shift).to_be_bytes());
+                        items.push(item);
+                    }
+                }
+                let body_len = items.iter().map(|x| x.len()).sum();
+                put_box_header(&mut output, b"ilst", body_len);
+                items.iter().for_each(|x| output.extend_from_slice(x));
+            }
+            _ => output.extend_from_slice(bbox.data),
+        }
+    }
+
+    // A removed key entry takes at least 8 bytes, so there is always enough
+    // room for a free atom.
+    let free_len = body.len() - output.len() - 8;
+    put_box_header(&mut output, b"free", free_len);
+    output.resize(body.len(), 0);
+
+    Ok(Some(output))
+}
+
+/// A `moov/meta` item to be written.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MetaItem {
+    key: String,
+    /// [Type indicator](https://developer.apple.com/documentation/quicktime-file-format/type_indicator)
+    type_code: u32,
+    value: Vec<u8>,
+}
+
+impl MetaItem {
+    /// Encodes `value` as a [well-known type][1]. Times are stored as ISO 8601
+    /// strings, e.g. `2019-02-12T15:27:12+08:00`.
+    ///
+    /// [1]: https://developer.apple.com/documentation/quicktime-file-format/well-known_types
+    pub fn encode(key: &str, value: &EntryValue) -> crate::Result<MetaItem> {
+        let (type_code, value) = match value {
+            EntryValue::Text(s) => (1, s.as_bytes().to_vec()),
+            EntryValue::Time(t) => (1, t.format("%Y-%m-%dT%H:%M:%S%:z").to_string().into()),
+            EntryValue::I8(v) => (21, v.to_be_bytes().to_vec()),
+            EntryValue::I16(v) => (21, v.to_be_bytes().to_vec()),
+            EntryValue::I32(v) => (21, v.to_be_bytes().to_vec()),
+            EntryValue::I64(v) => (21, v.to_be_bytes().to_vec()),
+            EntryValue::U8(v) => (22, v.to_be_bytes().to_vec()),
+            EntryValue::U16(v) => (22, v.to_be_bytes().to_vec()),
+            EntryValue::U32(v) => (22, v.to_be_bytes().to_vec()),
+            EntryValue::U64(v) => (22, v.to_be_bytes().to_vec()),
+            EntryValue::F32(v) => (23, v.to_be_bytes().to_vec()),
+            EntryValue::F64(v) => (24, v.to_be_bytes().to_vec()),
+            v => return Err(format!("unsupported QuickTime metadata value: {v:?}").into()),
+        };
+        Ok(MetaItem {
+            key: key.to_owned(),
+            type_code,
+            value,
+        })
+    }
+}
+
+/// Sets `moov/meta` items. The `moov/udta/©xyz` atom is updated as well if
+/// the location is set and the atom exists.
+///
+/// The `moov` atom is rebuilt. The media data stays where it is, so chunk
+/// offsets are still valid:
+///
+/// - If `moov` is the last atom, or the new one fits in the old place (with
+///   room for a `free` atom), it is replaced in place.
+///
+/// - Otherwise the old `moov` is turned into a zeroed `free` atom, and the new
+///   one is appended to the end of the file.
+pub(crate) fn set_items(input: &[u8], items: &[MetaItem]) -> crate::Result<Vec<u8>> {
+    let moov = find_moov(input)?;
+    let location = items
+        .iter()
+        .rev()
+        .find(|x| x.key == LOCATION_KEY && x.type_code == 1);
+
+    let mut body = Vec::with_capacity(moov.data.len() + 256);
+    let mut has_meta = false;
+    for bbox in parse_children(moov.body_data())? {
+        match bbox.box_type() {
+            "meta" => {
+                has_meta = true;
+                let meta = set_meta_items(bbox.body_data(), items)?;
+                put_box_header(&mut body, b"meta", meta.len());
+                body.extend_from_slice(&meta);
+            }
+            "udta" if location.is_some() => {
+                let atoms = parse_children(bbox.body_data())?;
+                let mut udta = Vec::with_capacity(bbox.data.len());
+                for atom in atoms {
+                    match location {
+                        Some(item) if atom.data.get(4..8) == Some(XYZ_TYPE) => {
+                            put_xyz_atom(&mut udta, &item.value);
+                        }
+                        _ => udta.extend_from_slice(atom.data),
+                    }
+                }
+                put_box_header(&mut body, b"udta", udta.len());
+                body.extend_from_slice(&udta);
+            }
+            _ => body.extend_from_slice(bbox.data),
+        }
+    }
+    if !has_meta {
+        let mut meta = Vec::new();
+        // hdlr: version & flags, predefined, handler type, reserved, empty name
+        put_box_header(&mut meta, b"hdlr", 25);
+        meta.extend_from_slice(&[0; 8]);
+        meta.extend_from_slice(b"mdta");
+        meta.extend_from_slice(&[0; 13]);
+        meta.extend_from_slice(&set_meta_items(&[], items)?);
+
+        put_box_header(&mut body, b"meta", meta.len());
+        body.extend_from_slice(&meta);
+    }
+
+    replace_moov(input, &moov, &body)
+}
+
+/// Replaces the `moov` atom with a new one which has `body`, see
+/// [`set_items`] for how the new atom is placed.
+fn replace_moov(input: &[u8], moov: &BoxHolder, body: &[u8]) -> crate::Result<Vec<u8>> {
+    if body.len() + 8 > u32::MAX as usize {
+        return Err("moov atom is too large".into());
+    }
+    let mut atom = Vec::with_capacity(body.len() + 8);
+    put_box_header(&mut atom, b"moov", body.len());
+    atom.extend_from_slice(body);
+
+    let start = input.subslice_offset(moov.data).unwrap();
+    let end = start + moov.data.len();
+    let old_len = moov.data.len();
+
+    let mut output = Vec::with_capacity(input.len() + atom.len());
+    if end == input.len() {
+        output.extend_from_slice(&input[..start]);
+        output.extend_from_slice(&atom);
+    } else if atom.len() == old_len || atom.len() + 8 <= old_len {
+        output.extend_from_slice(&input[..start]);
+        output.extend_from_slice(&atom);
+        if atom.len() < old_len {
+            put_box_header(&mut output, b"free", old_len - atom.len() - 8);
+            output.resize(end, 0);
+        }
+        output.extend_from_slice(&input[end..]);
+    } else {
+        // Make sure the atoms after moov are well-formed, so the new moov
+        // won't be swallowed by a broken atom.
+        parse_children(&input[end..])?;
+
+        output.extend_from_slice(input);
+        output[start + 4..start + 8].copy_from_slice(b"free");
+        output[start + moov.header_size()..end].fill(0);
+        output.extend_from_slice(&atom);
+    }
+    Ok(output)
+}
+
+/// Rebuilds the body of `moov/meta` with `items` set. `keys` & `ilst` are
+/// created if they don't exist.
+fn set_meta_items(body: &[u8], new_items: &[MetaItem]) -> crate::Result<Vec<u8>> {
+    let boxes = parse_children(body)?;
+    let keys = boxes.iter().find(|b| b.box_type() == "keys");
+    let ilst = boxes.iter().find(|b| b.box_type() == "ilst");
+    let (mut entries, mut items) = match (keys, ilst) {
+        (Some(keys), Some(ilst)) => (
+            key_entries(keys.body_data())?
+                .into_iter()
+                .map(|x| x.to_vec())
+                .collect::<Vec<_>>(),
+            parse_children(ilst.body_data())?
+                .into_iter()
+                .map(|x| x.data.to_vec())
+                .collect::<Vec<_>>(),
+        ),
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    for item in new_items {
+        match entries.iter().position(|x| &x[8..] == item.key.as_bytes()) {
+            Some(pos) => {
+                let index = pos as u32 + 1;
+                items.retain(|x| x[4..8] != index.to_be_bytes());
+                items.push(ilst_item(index, item));
+            }
+            None => {
+                entries.push(key_entry(&item.key));
+                items.push(ilst_item(entries.len() as u32, item));
+            }
+        }
+    }
+    // The parser pairs keys & items by their order.
+    items.sort_by_key(|x| u32::from_be_bytes(x[4..8].try_into().unwrap()));
+
+    let mut output = Vec::with_capacity(body.len() + 256);
+    for bbox in boxes.iter() {
+        match bbox.box_type() {
+            // Free space is reclaimed.
+            "keys" | "ilst" | "free" => (),
+            _ => output.extend_from_slice(bbox.data),
+        }
+    }
+    put_keys_and_ilst(&mut output, &entries, &items);
+    Ok(output)
+}
+
+/// Writes a `keys` atom and an `ilst` atom.
+fn put_keys_and_ilst(output: &mut Vec<u8>, entries: &[Vec<u8>], items: &[Vec<u8>]) {
+    let body_len = 8 + entries.iter().map(|x| x.len()).sum::<usize>();
+    put_box_header(output, b"keys", body_len);
+    output.extend_from_slice(&[0; 4]); // version & flags
+    output.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    entries.iter().for_each(|x| output.extend_from_slice(x));
+
+    let body_len = items.iter().map(|x| x.len()).sum();
+    put_box_header(output, b"ilst", body_len);
+    items.iter().for_each(|x| output.extend_from_slice(x));
+}
+
+fn key_entry(key: &str) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(key.len() + 8);
+    entry.extend_from_slice(&(key.len() as u32 + 8).to_be_bytes());
+    entry.extend_from_slice(b"mdta");
+    entry.extend_from_slice(key.as_bytes());
+    entry
+}
+
+/// Builds an `ilst` item for the key at `index` (1-based).
+fn ilst_item(index: u32, item: &MetaItem) -> Vec<u8> {
+    let value = &item.value;
+    let mut data = Vec::with_capacity(value.len() + 24);
+    data.extend_from_slice(&(value.len() as u32 + 24).to_be_bytes());
+    data.extend_from_slice(&index.to_be_bytes());
+    // data atom: size, type, type indicator, locale
+    data.extend_from_slice(&(value.len() as u32 + 16).to_be_bytes());
+    data.extend_from_slice(b"data");
+    data.extend_from_slice(&item.type_code.to_be_bytes());
+    data.extend_from_slice(&0_u32.to_be_bytes());
+    data.extend_from_slice(value);
+    data
+}
+
+/// Writes a `©xyz` atom: string length, language code (English), then the
+/// string.
+fn put_xyz_atom(output: &mut Vec<u8>, iso6709: &[u8]) {
+    put_box_header(output, XYZ_TYPE.try_into().unwrap(), iso6709.len() + 4);
+    output.extend_from_slice(&(iso6709.len() as u16).to_be_bytes());
+    output.extend_from_slice(&0x15c7_u16.to_be_bytes());
+    output.extend_from_slice(iso6709);
+}
+
+/// Returns the raw entries (including the size & namespace) of a `keys` atom.
+fn key_entries(keys_body: &[u8]) -> crate::Result<Vec<&[u8]>> {
+    let invalid = || "invalid QuickTime/MP4 file; broken keys atom";
+
+    // keys is a full box: version & flags, entry_count, then the entries.
+    let mut entries = Vec::new();
+    let mut pos = 8;
+    while pos < keys_body.len() {
+        let size = keys_body
+            .get(pos..pos + 4)
+            .map(|x| u32::from_be_bytes(x.try_into().unwrap()) as usize)
+            .ok_or_else(invalid)?;
+        if size < 8 || pos + size > keys_body.len() {
+            return Err(invalid().into());
+        }
+        entries.push(&keys_body[pos..pos + size]);
+        pos += size;
+    }
+    Ok(entries)
+}
+
+/// Returns the 1-based key index of an `ilst` item.
+fn item_index(item: &BoxHolder) -> u32 {
+    u32::from_be_bytes(item.data[4..8].try_into().unwrap())
+}
+
+/// Parses all the child atoms in `body`.
+fn parse_children(body: &[u8]) -> crate::Result<Vec<BoxHolder<'_>>> {
+    let mut boxes = Vec::new();
+    let mut remain = body;
+    while !remain.is_empty() {
+        let (rem, bbox) =
+            BoxHolder::parse(remain).map_err(|_| "invalid QuickTime/MP4 file; broken atom")?;
+        boxes.push(bbox);
+        remain = rem;
+    }
+    Ok(boxes)
+}
+
+fn put_box_header(output: &mut Vec<u8>, box_type: &[u8; 4], body_len: usize) {
+    output.extend_from_slice(&(body_len as u32 + 8).to_be_bytes());
+    output.extend_from_slice(box_type);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        bbox::{MvhdBox, ParseBox},
+        parse_metadata,
+        testkit::*,
+    };
+    use chrono::DateTime;
+    use test_case::test_case;
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn remove_location_in_place(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let orig = parse_metadata(Cursor::new(&input)).unwrap();
+        assert!(orig.iter().any(|x| x.0.starts_with(LOCATION_KEY_PREFIX)));
+
+        let output = remove_location(&input).unwrap();
+        assert_eq!(output.len(), input.len());
+
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        let expected = orig
+            .into_iter()
+            .filter(|x| !x.0.starts_with(LOCATION_KEY_PREFIX))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, expected);
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn shift_times_in_place(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let orig = parse_metadata(Cursor::new(&input)).unwrap();
+
+        let delta = Duration::days(-1) + Duration::hours(3);
+        let output = shift_times(&input, delta).unwrap();
+        assert_eq!(output.len(), input.len());
+
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        let creation_date = |entries: &[(String, EntryValue)]| {
+            entries
+                .iter()
+                .find(|x| x.0 == CREATIONDATE_KEY)
+                .and_then(|x| x.1.as_time())
+                .unwrap()
+        };
+        assert_eq!(creation_date(&entries), creation_date(&orig) + delta);
+        assert_eq!(entries.len(), orig.len());
+
+        let (_, mvhd) = find_box(find_moov(&output).unwrap().body_data(), "mvhd").unwrap();
+        let (_, mvhd) = MvhdBox::parse_box(mvhd.unwrap().data).unwrap();
+        let (_, orig_mvhd) = find_box(find_moov(&input).unwrap().body_data(), "mvhd").unwrap();
+        let (_, orig_mvhd) = MvhdBox::parse_box(orig_mvhd.unwrap().data).unwrap();
+        assert_eq!(mvhd.creation_time(), orig_mvhd.creation_time() + delta);
+    }
+
+    fn location(entries: &[(String, EntryValue)]) -> Option<&str> {
+        entries
+            .iter()
+            .find(|x| x.0 == LOCATION_KEY)
+            .and_then(|x| x.1.as_str())
+    }
+
+    fn set_location(input: &[u8], iso6709: &str) -> crate::Result<Vec<u8>> {
+        set_items(input, &[MetaItem::encode(LOCATION_KEY, &iso6709.into())?])
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn set_location_at_end(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let orig = parse_metadata(Cursor::new(&input)).unwrap();
+
+        let iso6709 = "+48.85770+002.29500+35.000/";
+        let output = set_location(&input, iso6709).unwrap();
+
+        // moov is the last atom, so everything before it is untouched.
+        let moov_start = input
+            .subslice_offset(find_moov(&input).unwrap().data)
+            .unwrap();
+        assert_eq!(output[..moov_start], input[..moov_start]);
+
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        assert_eq!(location(&entries), Some(iso6709));
+        assert_eq!(entries.len(), orig.len());
+        for entry in orig.iter().filter(|x| x.0 != LOCATION_KEY) {
+            assert!(entries.contains(entry), "{entry:?}");
+        }
+
+        // Set it again, the item is replaced rather than added.
+        let output = set_location(&output, "-33.85680+151.21530/").unwrap();
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        assert_eq!(location(&entries), Some("-33.85680+151.21530/"));
+        assert_eq!(entries.len(), orig.len());
+    }
+
+    #[test]
+    fn set_location_moov_first() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // Move moov before mdat: ftyp, wide, moov, mdat
+        let data = read_sample("meta.mov").unwrap();
+        let moov = find_moov(&data).unwrap().data;
+        let moov_start = data.subslice_offset(moov).unwrap();
+        let mut input = data[..28].to_vec();
+        input.extend_from_slice(moov);
+        input.extend_from_slice(&data[28..moov_start]);
+
+        let output = set_location(&input, "+48.85770+002.29500+35.000/").unwrap();
+        // The old moov becomes a free atom, and media data stays in place.
+        assert_eq!(output[..28], input[..28]);
+        assert_eq!(&output[32..36], b"free");
+        assert_eq!(
+            output[28 + moov.len()..input.len()],
+            input[28 + moov.len()..]
+        );
+
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        assert_eq!(location(&entries), Some("+48.85770+002.29500+35.000/"));
+    }
+
+    #[test_case("meta.mov")]
+    #[test_case("meta.mp4")]
+    fn set_items_values(path: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        let orig = parse_metadata(Cursor::new(&input)).unwrap();
+
+        let time = DateTime::parse_from_rfc3339("2024-05-06T07:08:09+02:00").unwrap();
+        let values: Vec<(&str, EntryValue)> = vec![
+            ("com.apple.quicktime.make", "Apple".into()),
+            ("com.apple.quicktime.model", "iPhone 15".into()),
+            (CREATIONDATE_KEY, time.into()),
+            ("com.example.count", 42_u32.into()),
+            ("com.example.offset", (-2_i16).into()),
+            ("com.example.ratio", 1.5_f64.into()),
+        ];
+        let items = values
+            .iter()
+            .map(|(k, v)| MetaItem::encode(k, v).unwrap())
+            .collect::<Vec<_>>();
+        let output = set_items(&input, &items).unwrap();
+
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        for (key, value) in values.iter() {
+            let found = entries.iter().filter(|x| x.0 == *key).collect::<Vec<_>>();
+            assert_eq!(found, vec![&(key.to_string(), value.clone())]);
+        }
+        // Other items are kept.
+        for entry in orig.iter().filter(|x| values.iter().all(|v| v.0 != x.0)) {
+            assert!(entries.contains(entry), "{entry:?}");
+        }
+    }
+
+    #[test]
+    fn encode_unsupported() {
+        MetaItem::encode("key", &EntryValue::Undefined(vec![1])).unwrap_err();
+    }
+
+    #[test_case(false)]
+    #[test_case(true)]
+    fn write_in_place_free_after_moov(moov_first: bool) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        // ftyp, wide, [moov, free,] mdat, [moov, free]
+        let data = read_sample("meta.mov").unwrap();
+        let moov = find_moov(&data).unwrap().data;
+        let moov_start = data.subslice_offset(moov).unwrap();
+        let mut free = Vec::new();
+        put_box_header(&mut free, b"free", 1000);
+        free.resize(1008, 0);
+        let mut input = data[..28].to_vec();
+        if moov_first {
+            input.extend_from_slice(moov);
+            input.extend_from_slice(&free);
+        }
+        input.extend_from_slice(&data[28..moov_start]);
+        if !moov_first {
+            input.extend_from_slice(moov);
+            input.extend_from_slice(&free);
+        }
+
+        let items = [MetaItem::encode(LOCATION_KEY, &"-33.85680+151.21530/".into()).unwrap()];
+        let mut file = Cursor::new(input.clone());
+        assert!(write_in_place(&mut file, |moov| set_items(&moov, &items)).unwrap());
+        let output = file.into_inner();
+
+        // The free atom is used, so nothing else moves.
+        assert_eq!(output.len(), input.len());
+        let mdat = 28
+            + if moov_first {
+                moov.len() + free.len()
+            } else {
+                0
+            };
+        let mdat_len = moov_start - 28;
+        assert_eq!(output[mdat..mdat + mdat_len], input[mdat..mdat + mdat_len]);
+
+        let entries = parse_metadata(Cursor::new(&output)).unwrap();
+        assert_eq!(location(&entries), Some("-33.85680+151.21530/"));
+
+        // No room without the free atom
+        if moov_first {
+            let mut input = data[..28].to_vec();
+            input.extend_from_slice(moov);
+            input.extend_from_slice(&data[28..moov_start]);
+            let mut file = Cursor::new(input.clone());
+            assert!(!write_in_place(&mut file, |moov| set_items(&moov, &items)).unwrap());
+            assert_eq!(file.into_inner(), input);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use super::{stripped_exif, StripOptions};
+
+pub(crate) const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Keywords of text chunks which carry metadata, see
+/// https://exiftool.org/TagNames/PNG.html#TextualData
+const XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+const RAW_PROFILE_KEYWORD: &[u8] = b"Raw profile type ";
+
+struct Chunk<'a> {
+    chunk_type: &'a [u8],
+    data: &'a [u8],
+    /// The whole chunk, including length, type and CRC.
+    raw: &'a [u8],
+}
+
+fn parse_chunks(input: &[u8]) -> crate::Result<Vec<Chunk<'_>>> {
+    if !input.starts_with(SIGNATURE) {
+        return Err("invalid PNG file; bad signature".into());
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos = SIGNATURE.len();
+    while pos < input.len() {
+        let len = input
+            .get(pos..pos + 4)
+            .map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]) as usize)
+            .ok_or("invalid PNG file; chunk is truncated")?;
+        // `len` comes from the file, and may overflow on 32-bit targets
+        let end = (pos + 12)
+            .checked_add(len)
+            .filter(|end| *end <= input.len())
+            .ok_or("invalid PNG file; chunk is truncated")?;
+        let chunk = Chunk {
+            chunk_type: &input[pos + 4..pos + 8],
+            data: &input[pos + 8..pos + 8 + len],
+            raw: &input[pos..end],
+        };
+        let is_end = chunk.chunk_type == b"IEND";
+        chunks.push(chunk);
+        pos = end;
+        if is_end {
+            break;
+        }
+    }
+
+    Ok(chunks)
+}
+
+/// Removes `eXIf`, XMP/raw profile text chunks, and optionally `iCCP`.
+pub(crate) fn strip(input: &[u8], options: &StripOptions) -> crate::Result<Vec<u8>> {
+    let chunks = parse_chunks(input)?;
+
+    let mut output = Vec::with_capacity(input.len());
+    output.extend_from_slice(SIGNATURE);
+    for chunk in chunks {
+        match chunk.chunk_type {
+            b"eXIf" => {
+                if let Some(data) = stripped_exif(chunk.data, options)? {
+                    write_chunk(&mut output, b"eXIf", &data);
+                }
+            }
+            b"tEXt" | b"zTXt" | b"iTXt" if is_metadata_text(chunk.data) => (),
+            b"iCCP" if !options.keep_icc => (),
+            _ => output.extend_from_slice(chunk.raw),
+        }
+    }
+
+    Ok(output)
+}
+
+fn is_metadata_text(data: &[u8]) -> bool {
+    let keyword = data.split(|b| *b == 0).next().unwrap_or_default();
+    keyword == XMP_KEYWORD || keyword.starts_with(RAW_PROFILE_KEYWORD)
+}
+
+fn write_chunk(output: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = output.len();
+    output.extend_from_slice(chunk_type);
+    output.extend_from_slice(data);
+    let crc = crc32(&output[start..]);
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// CRC-32 (ISO 3309) which is used by PNG chunks.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    use test_case::test_case;
+
+    fn chunk_types(data: &[u8]) -> Vec<String> {
+        parse_chunks(data)
+            .unwrap()
+            .iter()
+            .map(|x| String::from_utf8_lossy(x.chunk_type).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn truncated_chunk() {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut data = SIGNATURE.to_vec();
+        data.extend(u32::MAX.to_be_bytes());
+        data.extend(b"IDAT");
+        assert!(parse_chunks(&data).is_err());
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test_case("exif.png", StripOptions::default(), "IHDR,iCCP,eXIf,IDAT,IEND")]
+    #[test_case(
+        "exif.png",
+        StripOptions { keep_icc: false, keep_orientation: false },
+        "IHDR,IDAT,IEND"
+    )]
+    fn strip_png(path: &str, options: StripOptions, chunks: &str) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        assert_eq!(
+            chunk_types(&input).join(","),
+            "IHDR,iCCP,eXIf,tEXt,iTXt,zTXt,IDAT,IEND"
+        );
+
+        let output = strip(&input, &options).unwrap();
+        assert_eq!(chunk_types(&output).join(","), chunks);
+
+        for chunk in parse_chunks(&output).unwrap() {
+            let crc = crc32(&chunk.raw[4..chunk.raw.len() - 4]);
+            assert_eq!(chunk.raw[chunk.raw.len() - 4..], crc.to_be_bytes());
+            if chunk.chunk_type == b"eXIf" {
+                let exif = crate::exif::input_to_exif(chunk.data).unwrap();
+                assert_eq!(
+                    exif.get(crate::ExifTag::Orientation)
+                        .and_then(|x| x.as_u16()),
+                    Some(6)
+                );
+                assert!(exif.get(crate::ExifTag::Make).is_none());
+            }
+        }
+    }
+}
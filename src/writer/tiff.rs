@@ -0,0 +1,998 @@
+//! An editable, byte-level model of TIFF structured data (the payload of Exif
+//! blocks as well as standalone TIFF files), and the routines to serialize it
+//! back into bytes.
+
+use std::{
+    collections::{btree_map, BTreeMap, HashSet},
+    ops::Range,
+};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use nom::number::Endianness;
+
+use crate::{exif::IfdKind, values::DataFormat, EntryValue, ExifTag};
+
+/// Sub-IFD pointer tags we know how to follow.
+pub(crate) const SUB_IFD_TAGS: [u16; 3] = [
+    ExifTag::ExifOffset.code(),
+    ExifTag::GPSInfo.code(),
+    INTEROP_OFFSET,
+];
+const INTEROP_OFFSET: u16 = ExifTag::InteropOffset.code();
+
+/// `(offsets tag, byte counts tag)` pairs which reference image data, e.g.
+/// StripOffsets/StripByteCounts, TileOffsets/TileByteCounts and
+/// JPEGInterchangeFormat/JPEGInterchangeFormatLength (thumbnail).
+pub(crate) const DATA_TAGS: [(u16, u16); 3] = [
+    (
+        ExifTag::StripOffsets.code(),
+        ExifTag::StripByteCounts.code(),
+    ),
+    (0x0144, 0x0145),
+    (THUMBNAIL_OFFSET, THUMBNAIL_LENGTH),
+];
+const THUMBNAIL_OFFSET: u16 = ExifTag::ThumbnailOffset.code();
+const THUMBNAIL_LENGTH: u16 = ExifTag::ThumbnailLength.code();
+
+const COMPRESSION: u16 = ExifTag::Compression.code();
+/// Compression value of JPEG thumbnails.
+const COMPRESSION_JPEG: u16 = 6;
+
+/// IFD0 tags which describe the layout of the image data (rather than the
+/// image itself), e.g. ImageWidth, BitsPerSample, Compression and SubIFDs.
+const IMAGE_TAGS: [u16; 28] = [
+    0x00fe, 0x00ff, 0x0100, 0x0101, 0x0102, 0x0103, 0x0106, 0x0107, 0x0108, 0x0109, 0x010a, 0x0115,
+    0x0116, 0x0118, 0x0119, 0x011c, 0x013d, 0x0140, 0x0142, 0x0143, 0x014a, 0x0152, 0x0153, 0x015b,
+    0x0211, 0x0212, 0x0213, 0x0214,
+];
+
+/// PixelXDimension & PixelYDimension of the Exif sub-IFD.
+const PIXEL_DIMENSION_TAGS: [u16; 2] = [0xa002, 0xa003];
+
+/// IFD type (13) is an alias of LONG which is used by some writers for
+/// pointer tags.
+const FORMAT_IFD: u16 = 13;
+
+/// Date/time entries which are shifted by [`Tiff::shift_times`].
+const TIME_TAGS: [(IfdKind, ExifTag); 3] = [
+    (IfdKind::Ifd0, ExifTag::ModifyDate),
+    (IfdKind::Exif, ExifTag::DateTimeOriginal),
+    (IfdKind::Exif, ExifTag::CreateDate),
+];
+const TIME_FORMAT: &str = "%Y:%m:%d %H:%M:%S";
+const GPS_DATE_FORMAT: &str = "%Y:%m:%d";
+
+const MAX_IFD_DEPTH: usize = 4;
+const MAX_IFD_CHAIN: usize = 8;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Tiff {
+    pub endian: Endianness,
+
+    /// The IFD chain: IFD0, IFD1, ...
+    pub ifds: Vec<Ifd>,
+
+    /// Byte ranges of the original IFD tables and out-of-line values.
+    orig_regions: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Ifd {
+    pub entries: BTreeMap<u16, Entry>,
+    pub sub_ifds: BTreeMap<u16, Ifd>,
+
+    /// Image data referenced by [`DATA_TAGS`], keyed by the offsets tag.
+    chunks: BTreeMap<u16, Vec<Range<usize>>>,
+
+    /// A new JPEG thumbnail, which replaces the original one.
+    thumbnail: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    pub format: u16,
+    pub count: u32,
+    /// Raw value bytes, stored in the byte order of the [`Tiff`].
+    pub data: Vec<u8>,
+
+    /// Where the value was stored originally, if it is an unchanged
+    /// out-of-line value.
+    orig_offset: Option<u32>,
+}
+
+impl Entry {
+    /// Encodes `value` into an entry in the given byte order.
+    pub fn encode(value: &EntryValue, endian: Endianness) -> crate::Result<Entry> {
+        let mut data = Vec::new();
+        let (format, count) = match value {
+            EntryValue::Text(s) => {
+                data.extend_from_slice(s.as_bytes());
+                data.push(0);
+                (DataFormat::Text, data.len())
+            }
+            EntryValue::Time(t) => {
+                data.extend_from_slice(t.format(TIME_FORMAT).to_string().as_bytes());
+                data.push(0);
+                (DataFormat::Text, data.len())
+            }
+            EntryValue::URational(v) => {
+                put_u32(&mut data, endian, v.0);
+                put_u32(&mut data, endian, v.1);
+                (DataFormat::URational, 1)
+            }
+            EntryValue::IRational(v) => {
+                put_u32(&mut data, endian, v.0 as u32);
+                put_u32(&mut data, endian, v.1 as u32);
+                (DataFormat::IRational, 1)
+            }
+            EntryValue::URationalArray(vs) => {
+                for v in vs {
+                    put_u32(&mut data, endian, v.0);
+                    put_u32(&mut data, endian, v.1);
+                }
+                (DataFormat::URational, vs.len())
+            }
+            EntryValue::IRationalArray(vs) => {
+                for v in vs {
+                    put_u32(&mut data, endian, v.0 as u32);
+                    put_u32(&mut data, endian, v.1 as u32);
+                }
+                (DataFormat::IRational, vs.len())
+            }
+            EntryValue::U8(v) => {
+                data.push(*v);
+                (DataFormat::U8, 1)
+            }
+            EntryValue::I8(v) => {
+                data.push(*v as u8);
+                (DataFormat::I8, 1)
+            }
+            EntryValue::U16(v) => {
+                put_u16(&mut data, endian, *v);
+                (DataFormat::U16, 1)
+            }
+            EntryValue::I16(v) => {
+                put_u16(&mut data, endian, *v as u16);
+                (DataFormat::I16, 1)
+            }
+            EntryValue::U32(v) => {
+                put_u32(&mut data, endian, *v);
+                (DataFormat::U32, 1)
+            }
+            EntryValue::I32(v) => {
+                put_u32(&mut data, endian, *v as u32);
+                (DataFormat::I32, 1)
+            }
+            EntryValue::F32(v) => {
+                put_u32(&mut data, endian, v.to_bits());
+                (DataFormat::F32, 1)
+            }
+            EntryValue::F64(v) => {
+                let bits = v.to_bits();
+                match endian {
+                    Endianness::Little => data.extend_from_slice(&bits.to_le_bytes()),
+                    _ => data.extend_from_slice(&bits.to_be_bytes()),
+                }
+                (DataFormat::F64, 1)
+            }
+            EntryValue::Undefined(v) => {
+                data.extend_from_slice(v);
+                (DataFormat::Undefined, v.len())
+            }
+            EntryValue::U64(_) | EntryValue::I64(_) => {
+                return Err("64-bit integers can't be stored in Exif".into());
+            }
+        };
+
+        let count = u32::try_from(count).map_err(|_| "entry value is too large")?;
+        Ok(Entry {
+            format: format as u16,
+            count,
+            data,
+            orig_offset: None,
+        })
+    }
+
+    /// Creates a BYTE entry, which can't be expressed with [`EntryValue`].
+    pub fn bytes(data: &[u8]) -> Entry {
+        Entry {
+            format: DataFormat::U8 as u16,
+            count: data.len() as u32,
+            data: data.to_vec(),
+            orig_offset: None,
+        }
+    }
+
+    /// Returns the value of an ASCII entry, without the trailing NULs.
+    fn text(&self) -> Option<&str> {
+        if self.format != DataFormat::Text as u16 {
+            return None;
+        }
+        std::str::from_utf8(&self.data)
+            .ok()
+            .map(|s| s.trim_end_matches('\0'))
+    }
+
+    fn urationals(&self, endian: Endianness) -> Option<Vec<(u32, u32)>> {
+        if self.format != DataFormat::URational as u16 {
+            return None;
+        }
+        let values = self
+            .data
+            .chunks_exact(8)
+            .map(|x| (get_u32(x, endian), get_u32(&x[4..], endian)))
+            .collect();
+        Some(values)
+    }
+
+    fn u32_values(&self, endian: Endianness) -> Option<Vec<u32>> {
+        let size = match self.format {
+            3 => 2,
+            4 | FORMAT_IFD => 4,
+            _ => return None,
+        };
+        let values = self
+            .data
+            .chunks_exact(size)
+            .map(|x| {
+                if size == 2 {
+                    get_u16(x, endian) as u32
+                } else {
+                    get_u32(x, endian)
+                }
+            })
+            .collect();
+        Some(values)
+    }
+
+    /// Converts the value from the `from` byte order to `to`, e.g. to copy it
+    /// to another TIFF structure.
+    pub fn to_endian(&self, from: Endianness, to: Endianness) -> Entry {
+        let mut entry = Entry {
+            orig_offset: None,
+            ..self.clone()
+        };
+        if from == to {
+            return entry;
+        }
+        use DataFormat::*;
+        let word = match DataFormat::try_from(self.format) {
+            Ok(U16 | I16) => 2,
+            // Rationals are pairs of 32-bit integers
+            Ok(U32 | I32 | URational | IRational | F32) => 4,
+            Ok(F64) => 8,
+            _ if self.format == FORMAT_IFD => 4,
+            _ => 1,
+        };
+        entry.data.chunks_exact_mut(word).for_each(|x| x.reverse());
+        entry
+    }
+}
+
+impl Tiff {
+    pub fn new(endian: Endianness) -> Tiff {
+        Tiff {
+            endian,
+            ifds: Vec::new(),
+            orig_regions: Vec::new(),
+        }
+    }
+
+    /// Parses TIFF structured `input`, which must start with the TIFF header.
+    pub fn parse(input: &[u8]) -> crate::Result<Tiff> {
+        let (endian, mut offset) = parse_header(input)?;
+        let mut tiff = Tiff::new(endian);
+        let mut visited = HashSet::new();
+        while offset != 0 {
+            if tiff.ifds.len() >= MAX_IFD_CHAIN || !visited.insert(offset) {
+                tracing::warn!(offset, "IFD chain is too long or has a loop, stop.");
+                break;
+            }
+            let (ifd, next) = tiff.parse_ifd(input, offset, 0, &mut visited)?;
+            tiff.ifds.push(ifd);
+            offset = next;
+        }
+
+        Ok(tiff)
+    }
+
+    fn parse_ifd(
+        &mut self,
+        input: &[u8],
+        offset: usize,
+        depth: usize,
+        visited: &mut HashSet<usize>,
+    ) -> crate::Result<(Ifd, usize)> {
+        let endian = self.endian;
+        let num = offset
+            .checked_add(2)
+            .and_then(|end| input.get(offset..end))
+            .map(|x| get_u16(x, endian) as usize)
+            .ok_or("invalid TIFF data; IFD offset is out of range")?;
+        let table_end = offset
+            .checked_add(2 + num * 12 + 4)
+            .filter(|end| *end <= input.len())
+            .ok_or("invalid TIFF data; IFD table is truncated")?;
+        self.orig_regions.push(offset..table_end);
+
+        let mut ifd = Ifd::default();
+        for pos in (offset + 2..table_end - 4).step_by(12) {
+            let raw = &input[pos..pos + 12];
+            let tag = get_u16(raw, endian);
+            let format = get_u16(&raw[2..], endian);
+            let count = get_u32(&raw[4..], endian);
+
+            let size = if format == FORMAT_IFD {
+                Some(4)
+            } else {
+                DataFormat::try_from(format)
+                    .ok()
+                    .map(|f| f.component_size())
+            };
+            let Some(size) = size.and_then(|x| (count as usize).checked_mul(x)) else {
+                tracing::warn!(tag, format, "Skip IFD entry with unknown format.");
+                continue;
+            };
+
+            let (data, orig_offset) = if size <= 4 {
+                (raw[8..8 + size].to_vec(), None)
+            } else {
+                let value_offset = get_u32(&raw[8..], endian);
+                let start = value_offset as usize;
+                let Some(data) = start
+                    .checked_add(size)
+                    .and_then(|end| input.get(start..end))
+                else {
+                    tracing::warn!(tag, "Skip IFD entry whose value is out of range.");
+                    continue;
+                };
+                self.orig_regions.push(start..start + size);
+                (data.to_vec(), Some(value_offset))
+            };
+
+            let entry = Entry {
+                format,
+                count,
+                data,
+                orig_offset,
+            };
+
+            if SUB_IFD_TAGS.contains(&tag) && count == 1 {
+                if let Some(&[sub_offset]) = entry.u32_values(endian).as_deref() {
+                    let sub_offset = sub_offset as usize;
+                    if depth >= MAX_IFD_DEPTH || !visited.insert(sub_offset) {
+                        tracing::warn!(tag, "Sub-IFD is nested too deep or has a loop.");
+                        continue;
+                    }
+                    match self.parse_ifd(input, sub_offset, depth + 1, visited) {
+                        Ok((sub_ifd, _)) => {
+                            ifd.sub_ifds.insert(tag, sub_ifd);
+                        }
+                        Err(e) => tracing::warn!(tag, ?e, "Drop invalid sub-IFD."),
+                    }
+                    continue;
+                }
+            }
+
+            ifd.entries.insert(tag, entry);
+        }
+
+        for (offsets_tag, counts_tag) in DATA_TAGS {
+            let (Some(offsets), Some(counts)) =
+                (ifd.entries.get(&offsets_tag), ifd.entries.get(&counts_tag))
+            else {
+                continue;
+            };
+
+            let chunks = match (offsets.u32_values(endian), counts.u32_values(endian)) {
+                (Some(offsets), Some(counts)) if offsets.len() == counts.len() => offsets
+                    .iter()
+                    .zip(counts.iter())
+                    .map(|(&start, &len)| {
+                        let end = (start as usize).checked_add(len as usize)?;
+                        (end <= input.len()).then_some(start as usize..end)
+                    })
+                    .collect::<Option<Vec<_>>>(),
+                _ => None,
+            };
+
+            if let Some(chunks) = chunks {
+                ifd.chunks.insert(offsets_tag, chunks);
+            } else {
+                tracing::warn!(offsets_tag, "Drop invalid image data references.");
+                ifd.entries.remove(&offsets_tag);
+                ifd.entries.remove(&counts_tag);
+            }
+        }
+
+        let next = get_u32(&input[table_end - 4..], endian) as usize;
+        Ok((ifd, next))
+    }
+
+    /// Returns true if there is no entry at all.
+    pub fn is_empty(&self) -> bool {
+        self.ifds.iter().all(Ifd::is_empty)
+    }
+
+    /// Gets the IFD specified by `kind`, returns `None` if it doesn't exist.
+    pub(crate) fn ifd(&self, kind: IfdKind) -> Option<&Ifd> {
+        match kind {
+            IfdKind::Ifd0 => self.ifds.first(),
+            IfdKind::Ifd1 => self.ifds.get(1),
+            IfdKind::Exif => self
+                .ifd(IfdKind::Ifd0)?
+                .sub_ifds
+                .get(&ExifTag::ExifOffset.code()),
+            IfdKind::Gps => self
+                .ifd(IfdKind::Ifd0)?
+                .sub_ifds
+                .get(&ExifTag::GPSInfo.code()),
+            IfdKind::Interop => self.ifd(IfdKind::Exif)?.sub_ifds.get(&INTEROP_OFFSET),
+            IfdKind::SubIfd(_) => None,
+        }
+    }
+
+    /// Returns the entries which describe the main image, rather than the
+    /// layout of its data, i.e. the ones which can be copied to another
+    /// image: entries of IFD0 and the Exif, GPS & Interop sub-IFDs, except for
+    /// [`IMAGE_TAGS`] and image data references.
+    pub fn metadata_entries(&self) -> Vec<(IfdKind, u16, &Entry)> {
+        let mut entries = Vec::new();
+        for kind in [IfdKind::Ifd0, IfdKind::Exif, IfdKind::Gps, IfdKind::Interop] {
+            let Some(ifd) = self.ifd(kind) else {
+                continue;
+            };
+            for (&tag, entry) in ifd.entries.iter() {
+                let skip = match kind {
+                    IfdKind::Ifd0 => {
+                        IMAGE_TAGS.contains(&tag)
+                            || DATA_TAGS.iter().any(|(o, c)| *o == tag || *c == tag)
+                    }
+                    IfdKind::Exif => PIXEL_DIMENSION_TAGS.contains(&tag),
+                    _ => false,
+                };
+                if !skip {
+                    entries.push((kind, tag, entry));
+                }
+            }
+        }
+        entries
+    }
+
+    /// Gets the IFD specified by `kind`, returns `None` if it doesn't exist.
+    pub fn ifd_mut(&mut self, kind: IfdKind) -> Option<&mut Ifd> {
+        match kind {
+            IfdKind::Ifd0 => self.ifds.get_mut(0),
+            IfdKind::Ifd1 => self.ifds.get_mut(1),
+            IfdKind::Exif => self
+                .ifd_mut(IfdKind::Ifd0)?
+                .sub_ifd_mut(ExifTag::ExifOffset.code()),
+            IfdKind::Gps => self
+                .ifd_mut(IfdKind::Ifd0)?
+                .sub_ifd_mut(ExifTag::GPSInfo.code()),
+            IfdKind::Interop => self.ifd_mut(IfdKind::Exif)?.sub_ifd_mut(INTEROP_OFFSET),
+            IfdKind::SubIfd(_) => None,
+        }
+    }
+
+    /// Gets the IFD specified by `kind`, creates it (and its parents) if
+    /// it doesn't exist.
+    ///
+    /// # Panics
+    ///
+    /// Panics for [`IfdKind::SubIfd`], which can't be written. Edits of
+    /// SubIFDs are rejected before getting here.
+    pub fn ifd_or_insert(&mut self, kind: IfdKind) -> &mut Ifd {
+        match kind {
+            IfdKind::Ifd0 | IfdKind::Ifd1 => {
+                let idx = if kind == IfdKind::Ifd0 { 0 } else { 1 };
+                while self.ifds.len() <= idx {
+                    self.ifds.push(Ifd::default());
+                }
+                &mut self.ifds[idx]
+            }
+            IfdKind::Exif => self
+                .ifd_or_insert(IfdKind::Ifd0)
+                .sub_ifds
+                .entry(ExifTag::ExifOffset.code())
+                .or_default(),
+            IfdKind::Gps => self
+                .ifd_or_insert(IfdKind::Ifd0)
+                .sub_ifds
+                .entry(ExifTag::GPSInfo.code())
+                .or_default(),
+            IfdKind::Interop => self
+                .ifd_or_insert(IfdKind::Exif)
+                .sub_ifds
+                .entry(INTEROP_OFFSET)
+                .or_default(),
+            IfdKind::SubIfd(_) => panic!("writing SubIFDs is not supported"),
+        }
+    }
+
+    /// Replaces the thumbnail (the JPEG image referenced by IFD1) with `jpeg`,
+    /// or adds one. Uncompressed thumbnail strips are removed, and the
+    /// original thumbnail is zeroed out by [`Tiff::append_to`].
+    pub fn set_thumbnail(&mut self, jpeg: &[u8]) -> crate::Result<()> {
+        if !jpeg.starts_with(&[0xff, 0xd8]) {
+            return Err("invalid thumbnail; not a JPEG image".into());
+        }
+        let endian = self.endian;
+        let ifd = self.ifd_or_insert(IfdKind::Ifd1);
+        let mut old = Vec::new();
+        for (offsets_tag, counts_tag) in DATA_TAGS {
+            ifd.entries.remove(&offsets_tag);
+            ifd.entries.remove(&counts_tag);
+            old.extend(ifd.chunks.remove(&offsets_tag).unwrap_or_default());
+        }
+        ifd.thumbnail = Some(jpeg.to_vec());
+
+        ifd.entries.insert(
+            COMPRESSION,
+            Entry::encode(&EntryValue::U16(COMPRESSION_JPEG), endian)?,
+        );
+        // Required by the Exif spec
+        let resolution = EntryValue::URational((72, 1).into());
+        for (tag, value) in [
+            (ExifTag::XResolution, resolution.clone()),
+            (ExifTag::YResolution, resolution),
+            (ExifTag::ResolutionUnit, EntryValue::U16(2)),
+        ] {
+            if let btree_map::Entry::Vacant(e) = ifd.entries.entry(tag.code()) {
+                e.insert(Entry::encode(&value, endian)?);
+            }
+        }
+
+        self.orig_regions.extend(old);
+        Ok(())
+    }
+
+    /// Removes all entries, except for the Orientation of the main image if
+    /// `keep_orientation` is true.
+    pub fn strip(&mut self, keep_orientation: bool) {
+        let orientation = self
+            .ifds
+            .first()
+            .and_then(|ifd| ifd.entries.get(&ExifTag::Orientation.code()))
+            .filter(|_| keep_orientation)
+            .cloned();
+        self.ifds.clear();
+        if let Some(entry) = orientation {
+            self.ifd_or_insert(IfdKind::Ifd0)
+                .entries
+                .insert(ExifTag::Orientation.code(), entry);
+        }
+    }
+
+    /// Shifts ModifyDate, DateTimeOriginal, CreateDate and the GPS time stamp
+    /// by `delta`. Offset times are kept as is. Entries which can't be parsed
+    /// (e.g. blank dates) are left untouched.
+    pub fn shift_times(&mut self, delta: Duration) -> crate::Result<()> {
+        let endian = self.endian;
+        for (kind, tag) in TIME_TAGS {
+            let Some(entry) = self
+                .ifd_mut(kind)
+                .and_then(|ifd| ifd.entries.get_mut(&tag.code()))
+            else {
+                continue;
+            };
+            let Some(t) = entry
+                .text()
+                .and_then(|s| NaiveDateTime::parse_from_str(s, TIME_FORMAT).ok())
+            else {
+                continue;
+            };
+            let t = t
+                .checked_add_signed(delta)
+                .ok_or("shifted time is out of range")?;
+            let value = EntryValue::Text(t.format(TIME_FORMAT).to_string());
+            *entry = Entry::encode(&value, endian)?;
+        }
+
+        if let Some(gps) = self.ifd_mut(IfdKind::Gps) {
+            gps.shift_gps_time(delta, endian)?;
+        }
+        Ok(())
+    }
+
+    /// Removes empty sub-IFDs and trailing empty IFDs.
+    pub fn prune(&mut self) {
+        for ifd in self.ifds.iter_mut() {
+            ifd.prune();
+        }
+        while self.ifds.last().is_some_and(Ifd::is_empty) {
+            self.ifds.pop();
+        }
+    }
+
+    /// Serializes into a self-contained TIFF block, all values and image data
+    /// will be relocated. This is used for Exif blocks embedded in other
+    /// containers.
+    pub fn to_bytes(&self, input: &[u8]) -> crate::Result<Vec<u8>> {
+        let mut w = TiffWriter::new(self.endian, input, Vec::new(), false);
+        w.out.extend_from_slice(match self.endian {
+            Endianness::Little => b"II",
+            _ => b"MM",
+        });
+        w.put_u16(0x2a);
+        w.put_u32(0);
+        w.write_chain(&self.ifds)?;
+        Ok(w.out)
+    }
+
+    /// Serializes by appending the IFDs to the end of `input` (the original
+    /// TIFF file), so image data and unchanged values stay where they are.
+    ///
+    /// Bytes of the original IFD tables and values which are no longer
+    /// referenced are zeroed out, so removed entries won't leak.
+    pub fn append_to(&self, input: &[u8]) -> crate::Result<Vec<u8>> {
+        let mut w = TiffWriter::new(self.endian, input, input.to_vec(), true);
+        w.write_chain(&self.ifds)?;
+
+        let mut kept = w.kept;
+        kept.sort_by_key(|x| x.start);
+        for region in self.orig_regions.iter() {
+            let mut start = region.start;
+            for k in kept
+                .iter()
+                .filter(|k| k.end > region.start && k.start < region.end)
+            {
+                if k.start > start {
+                    w.out[start..k.start].fill(0);
+                }
+                start = start.max(k.end);
+            }
+            if start < region.end {
+                w.out[start..region.end].fill(0);
+            }
+        }
+        Ok(w.out)
+    }
+}
+
+impl Ifd {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.sub_ifds.values().all(Ifd::is_empty)
+    }
+
+    fn sub_ifd_mut(&mut self, tag: u16) -> Option<&mut Ifd> {
+        self.sub_ifds.get_mut(&tag)
+    }
+
+    pub fn set(&mut self, tag: u16, entry: Entry) -> crate::Result<()> {
+        if SUB_IFD_TAGS.contains(&tag) {
+            return Err(format!("sub-IFD pointer 0x{tag:04x} can't be set directly").into());
+        }
+        if DATA_TAGS.iter().any(|(o, c)| *o == tag || *c == tag) {
+            return Err(format!("image data reference 0x{tag:04x} can't be set directly").into());
+        }
+        self.entries.insert(tag, entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, tag: u16) {
+        self.entries.remove(&tag);
+        self.sub_ifds.remove(&tag);
+        for (offsets_tag, counts_tag) in DATA_TAGS {
+            if tag == offsets_tag || tag == counts_tag {
+                self.entries.remove(&offsets_tag);
+                self.entries.remove(&counts_tag);
+                self.chunks.remove(&offsets_tag);
+                if offsets_tag == THUMBNAIL_OFFSET {
+                    self.thumbnail = None;
+                }
+            }
+        }
+    }
+
+    /// Shifts GPSTimeStamp (and GPSDateStamp if present) of a GPS IFD. The
+    /// time stamp wraps around midnight if there is no date stamp.
+    fn shift_gps_time(&mut self, delta: Duration, endian: Endianness) -> crate::Result<()> {
+        let time_code = ExifTag::GPSTimeStamp.code();
+        let date_code = ExifTag::GPSDateStamp.code();
+
+        let Some(hms) = self
+            .entries
+            .get(&time_code)
+            .and_then(|x| x.urationals(endian))
+            .filter(|x| x.len() == 3 && x.iter().all(|r| r.1 != 0))
+        else {
+            return Ok(());
+        };
+        let nanos = hms
+            .iter()
+            .zip([3600, 60, 1])
+            .map(|(r, unit)| r.0 as u128 * unit * 1_000_000_000 / r.1 as u128)
+            .sum::<u128>();
+        let Some(time) = u32::try_from(nanos / 1_000_000_000).ok().and_then(|secs| {
+            NaiveTime::from_num_seconds_from_midnight_opt(secs, (nanos % 1_000_000_000) as u32)
+        }) else {
+            return Ok(());
+        };
+        let date = self
+            .entries
+            .get(&date_code)
+            .and_then(Entry::text)
+            .and_then(|s| NaiveDate::parse_from_str(s, GPS_DATE_FORMAT).ok());
+
+        let time = match date {
+            Some(date) => {
+                let t = date
+                    .and_time(time)
+                    .checked_add_signed(delta)
+                    .ok_or("shifted time is out of range")?;
+                let value = EntryValue::Text(t.format(GPS_DATE_FORMAT).to_string());
+                self.entries
+                    .insert(date_code, Entry::encode(&value, endian)?);
+                t.time()
+            }
+            None => time.overflowing_add_signed(delta).0,
+        };
+
+        // Keep the precision of the original seconds value.
+        let den = hms[2].1;
+        let sec_nanos = time.second() as u64 * 1_000_000_000 + time.nanosecond() as u64;
+        let sec = ((sec_nanos * den as u64 + 500_000_000) / 1_000_000_000) as u32;
+        let value = EntryValue::URationalArray(vec![
+            (time.hour(), 1).into(),
+            (time.minute(), 1).into(),
+            (sec, den).into(),
+        ]);
+        self.entries
+            .insert(time_code, Entry::encode(&value, endian)?);
+        Ok(())
+    }
+
+    fn prune(&mut self) {
+        for ifd in self.sub_ifds.values_mut() {
+            ifd.prune();
+        }
+        self.sub_ifds.retain(|_, ifd| !ifd.is_empty());
+    }
+}
+
+struct TiffWriter<'a> {
+    endian: Endianness,
+    input: &'a [u8],
+    out: Vec<u8>,
+
+    /// Keep unchanged out-of-line values and image data in place.
+    in_place: bool,
+    kept: Vec<Range<usize>>,
+}
+
+impl<'a> TiffWriter<'a> {
+    fn new(endian: Endianness, input: &'a [u8], out: Vec<u8>, in_place: bool) -> Self {
+        Self {
+            endian,
+            input,
+            out,
+            in_place,
+            kept: Vec::new(),
+        }
+    }
+
+    fn write_chain(&mut self, ifds: &[Ifd]) -> crate::Result<()> {
+        // The first "next IFD" pointer is the IFD0 offset in the header.
+        let mut next_pos = 4;
+        for ifd in ifds {
+            let (pos, next) = self.write_ifd(ifd)?;
+            self.patch_u32(next_pos, pos);
+            next_pos = next;
+        }
+        Ok(())
+    }
+
+    /// Writes `ifd` and everything it references, returns the offset of the
+    /// IFD table and the position of its "next IFD" pointer, which is left as
+    /// zero.
+    fn write_ifd(&mut self, ifd: &Ifd) -> crate::Result<(u32, usize)> {
+        let mut entries = ifd.entries.clone();
+
+        // Write image data first, since the references need to be updated.
+        if let Some(data) = &ifd.thumbnail {
+            self.align();
+            let offset = self.pos()?;
+            self.out.extend_from_slice(data);
+            let len = u32::try_from(data.len()).map_err(|_| "thumbnail is too large")?;
+            entries.insert(THUMBNAIL_OFFSET, self.u32_array_entry(&[offset]));
+            entries.insert(THUMBNAIL_LENGTH, self.u32_array_entry(&[len]));
+        }
+        for (offsets_tag, counts_tag) in DATA_TAGS {
+            let Some(chunks) = ifd.chunks.get(&offsets_tag) else {
+                continue;
+            };
+            if self.in_place {
+                self.kept.extend(chunks.iter().cloned());
+                continue;
+            }
+
+            let mut offsets = Vec::new();
+            let mut counts = Vec::new();
+            for range in chunks {
+                self.align();
+                offsets.push(self.pos()?);
+                counts.push(range.len() as u32);
+                self.out.extend_from_slice(&self.input[range.clone()]);
+            }
+            entries.insert(offsets_tag, self.u32_array_entry(&offsets));
+            entries.insert(counts_tag, self.u32_array_entry(&counts));
+        }
+
+        let num = entries.len() + ifd.sub_ifds.len();
+        let num = u16::try_from(num).map_err(|_| "too many entries in IFD")?;
+
+        self.align();
+        let table_pos = self.pos()?;
+        self.put_u16(num);
+
+        let mut entry_pos = table_pos as usize + 2;
+        let mut deferred = Vec::new();
+        let mut sub_ifds = Vec::new();
+
+        // Entries must be sorted by tag in ascending order.
+        let mut tags: Vec<u16> = entries.keys().chain(ifd.sub_ifds.keys()).copied().collect();
+        tags.sort_unstable();
+        for tag in tags {
+            self.put_u16(tag);
+            if let Some(sub_ifd) = ifd.sub_ifds.get(&tag) {
+                self.put_u16(DataFormat::U32 as u16);
+                self.put_u32(1);
+                self.put_u32(0);
+                sub_ifds.push((entry_pos + 8, sub_ifd));
+            } else {
+                let entry = &entries[&tag];
+                self.put_u16(entry.format);
+                self.put_u32(entry.count);
+                if entry.data.len() <= 4 {
+                    self.out.extend_from_slice(&entry.data);
+                    self.out.resize(self.out.len() + 4 - entry.data.len(), 0);
+                } else {
+                    self.put_u32(0);
+                    deferred.push((entry_pos + 8, entry));
+                }
+            }
+            entry_pos += 12;
+        }
+        let next_pos = self.out.len();
+        self.put_u32(0);
+
+        for (pos, entry) in deferred {
+            let offset = match entry.orig_offset {
+                Some(offset) if self.in_place => {
+                    let start = offset as usize;
+                    self.kept.push(start..start + entry.data.len());
+                    offset
+                }
+                _ => {
+                    self.align();
+                    let offset = self.pos()?;
+                    self.out.extend_from_slice(&entry.data);
+                    offset
+                }
+            };
+            self.patch_u32(pos, offset);
+        }
+
+        for (pos, sub_ifd) in sub_ifds {
+            let (offset, _) = self.write_ifd(sub_ifd)?;
+            self.patch_u32(pos, offset);
+        }
+
+        Ok((table_pos, next_pos))
+    }
+
+    fn u32_array_entry(&self, values: &[u32]) -> Entry {
+        let mut data = Vec::with_capacity(values.len() * 4);
+        for v in values {
+            put_u32(&mut data, self.endian, *v);
+        }
+        Entry {
+            format: DataFormat::U32 as u16,
+            count: values.len() as u32,
+            data,
+            orig_offset: None,
+        }
+    }
+
+    fn pos(&self) -> crate::Result<u32> {
+        u32::try_from(self.out.len()).map_err(|_| "TIFF data exceeds 4 GB".into())
+    }
+
+    /// Values and IFDs must begin on a word boundary.
+    fn align(&mut self) {
+        if !self.out.len().is_multiple_of(2) {
+            self.out.push(0);
+        }
+    }
+
+    fn put_u16(&mut self, v: u16) {
+        put_u16(&mut self.out, self.endian, v);
+    }
+
+    fn put_u32(&mut self, v: u32) {
+        put_u32(&mut self.out, self.endian, v);
+    }
+
+    fn patch_u32(&mut self, pos: usize, v: u32) {
+        let bytes = match self.endian {
+            Endianness::Little => v.to_le_bytes(),
+            _ => v.to_be_bytes(),
+        };
+        self.out[pos..pos + 4].copy_from_slice(&bytes);
+    }
+}
+
+/// Parses the TIFF header, returns the byte order and the offset of IFD0.
+fn parse_header(input: &[u8]) -> crate::Result<(Endianness, usize)> {
+    if input.len() < 8 {
+        return Err("invalid TIFF data; header is too short".into());
+    }
+    let endian = match &input[..2] {
+        b"II" => Endianness::Little,
+        b"MM" => Endianness::Big,
+        _ => return Err("invalid TIFF data; unknown byte order".into()),
+    };
+    match get_u16(&input[2..], endian) {
+        0x2a => (),
+        0x2b => return Err("BigTIFF is not supported".into()),
+        _ => return Err("invalid TIFF data; bad magic number".into()),
+    }
+    Ok((endian, get_u32(&input[4..], endian) as usize))
+}
+
+/// Returns the position of the Orientation value in IFD0 of `input`, and the
+/// byte order. The entry must be a single SHORT, which is stored inline.
+pub(crate) fn find_orientation(input: &[u8]) -> crate::Result<(usize, Endianness)> {
+    let (endian, offset) = parse_header(input)?;
+    let num = input
+        .get(offset..offset + 2)
+        .map(|x| get_u16(x, endian) as usize)
+        .ok_or("invalid TIFF data; IFD offset is out of range")?;
+    let table_end = offset + 2 + num * 12;
+    if table_end > input.len() {
+        return Err("invalid TIFF data; IFD table is truncated".into());
+    }
+
+    for pos in (offset + 2..table_end).step_by(12) {
+        let raw = &input[pos..pos + 12];
+        if get_u16(raw, endian) != ExifTag::Orientation.code() {
+            continue;
+        }
+        if get_u16(&raw[2..], endian) != DataFormat::U16 as u16 || get_u32(&raw[4..], endian) != 1 {
+            return Err("invalid Orientation entry; it must be a single SHORT".into());
+        }
+        return Ok((pos + 8, endian));
+    }
+    Err("Orientation entry not found".into())
+}
+
+fn put_u16(out: &mut Vec<u8>, endian: Endianness, v: u16) {
+    match endian {
+        Endianness::Little => out.extend_from_slice(&v.to_le_bytes()),
+        _ => out.extend_from_slice(&v.to_be_bytes()),
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, endian: Endianness, v: u32) {
+    match endian {
+        Endianness::Little => out.extend_from_slice(&v.to_le_bytes()),
+        _ => out.extend_from_slice(&v.to_be_bytes()),
+    }
+}
+
+fn get_u16(data: &[u8], endian: Endianness) -> u16 {
+    let bytes = [data[0], data[1]];
+    match endian {
+        Endianness::Little => u16::from_le_bytes(bytes),
+        _ => u16::from_be_bytes(bytes),
+    }
+}
+
+fn get_u32(data: &[u8], endian: Endianness) -> u32 {
+    let bytes = [data[0], data[1], data[2], data[3]];
+    match endian {
+        Endianness::Little => u32::from_le_bytes(bytes),
+        _ => u32::from_be_bytes(bytes),
+    }
+}
@@ -0,0 +1,138 @@
+use super::{stripped_exif, StripOptions};
+
+// VP8X flags
+const ICC_FLAG: u8 = 0x20;
+const EXIF_FLAG: u8 = 0x08;
+const XMP_FLAG: u8 = 0x04;
+
+pub(crate) fn check_webp(input: &[u8]) -> bool {
+    input.len() >= 12 && &input[..4] == b"RIFF" && &input[8..12] == b"WEBP"
+}
+
+struct Chunk<'a> {
+    fourcc: &'a [u8],
+    data: &'a [u8],
+    /// The whole chunk, including header and padding.
+    raw: &'a [u8],
+}
+
+fn parse_chunks(input: &[u8]) -> crate::Result<Vec<Chunk<'_>>> {
+    if !check_webp(input) {
+        return Err("invalid WebP file; bad RIFF header".into());
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+    while pos + 8 <= input.len() {
+        let len = u32::from_le_bytes([
+            input[pos + 4],
+            input[pos + 5],
+            input[pos + 6],
+            input[pos + 7],
+        ]) as usize;
+        let data_end = pos + 8 + len;
+        // Chunks are padded to even sizes.
+        let end = data_end + (len & 1);
+        if data_end > input.len() {
+            return Err("invalid WebP file; chunk is truncated".into());
+        }
+        let end = end.min(input.len());
+        chunks.push(Chunk {
+            fourcc: &input[pos..pos + 4],
+            data: &input[pos + 8..data_end],
+            raw: &input[pos..end],
+        });
+        pos = end;
+    }
+
+    Ok(chunks)
+}
+
+/// Removes `EXIF`, `XMP ` and optionally `ICCP` chunks, then fixes up the
+/// `VP8X` flags and the RIFF size.
+pub(crate) fn strip(input: &[u8], options: &StripOptions) -> crate::Result<Vec<u8>> {
+    let chunks = parse_chunks(input)?;
+
+    let mut output = Vec::with_capacity(input.len());
+    output.extend_from_slice(&input[..12]);
+    let mut vp8x_flags_pos = None;
+    let mut flags = 0;
+    for chunk in chunks {
+        match chunk.fourcc {
+            b"EXIF" => {
+                // Some writers prepend "Exif\0\0" to the TIFF data.
+                let data = chunk.data.strip_prefix(b"Exif\0\0").unwrap_or(chunk.data);
+                if let Some(data) = stripped_exif(data, options)? {
+                    write_chunk(&mut output, b"EXIF", &data)?;
+                    flags |= EXIF_FLAG;
+                }
+            }
+            b"XMP " => (),
+            b"ICCP" if !options.keep_icc => (),
+            fourcc => {
+                if fourcc == b"VP8X" && !chunk.data.is_empty() {
+                    vp8x_flags_pos = Some(output.len() + 8);
+                } else if fourcc == b"ICCP" {
+                    flags |= ICC_FLAG;
+                }
+                output.extend_from_slice(chunk.raw);
+            }
+        }
+    }
+
+    if let Some(pos) = vp8x_flags_pos {
+        output[pos] = (output[pos] & !(ICC_FLAG | EXIF_FLAG | XMP_FLAG)) | flags;
+    }
+    let riff_size = u32::try_from(output.len() - 8).map_err(|_| "WebP file is too large")?;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+    Ok(output)
+}
+
+fn write_chunk(output: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) -> crate::Result<()> {
+    let len = u32::try_from(data.len()).map_err(|_| "WebP chunk is too large")?;
+    output.extend_from_slice(fourcc);
+    output.extend_from_slice(&len.to_le_bytes());
+    output.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        output.push(0);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::*;
+    use test_case::test_case;
+
+    fn chunk_types(data: &[u8]) -> Vec<String> {
+        parse_chunks(data)
+            .unwrap()
+            .iter()
+            .map(|x| String::from_utf8_lossy(x.fourcc).to_string())
+            .collect()
+    }
+
+    #[test_case("exif.webp", StripOptions::default(), "VP8X,ICCP,VP8L,EXIF", 0x28)]
+    #[test_case(
+        "exif.webp",
+        StripOptions { keep_icc: false, keep_orientation: false },
+        "VP8X,VP8L",
+        0
+    )]
+    fn strip_webp(path: &str, options: StripOptions, chunks: &str, flags: u8) {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let input = read_sample(path).unwrap();
+        assert_eq!(chunk_types(&input).join(","), "VP8X,ICCP,VP8L,EXIF,XMP ");
+
+        let output = strip(&input, &options).unwrap();
+        assert_eq!(chunk_types(&output).join(","), chunks);
+        assert_eq!(output[20], flags);
+        assert_eq!(
+            u32::from_le_bytes(output[4..8].try_into().unwrap()) as usize,
+            output.len() - 8
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Helpers for writing raw XMP packets.
+
+const XPACKET_HEADER: &str = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n";
+const XPACKET_TRAILER: &str = "<?xpacket end=\"w\"?>";
+const XPACKET_END: &str = "<?xpacket end=";
+
+/// Padding added when a packet grows, so following edits can usually be done
+/// in place, as recommended by the XMP specification.
+const DEFAULT_PADDING: usize = 2048;
+
+/// Prepares `packet` for writing:
+///
+/// - The packet is wrapped with `<?xpacket ...?>` processing instructions if
+///   it isn't.
+///
+/// - Whitespace padding is inserted before the trailer. If the packet fits in
+///   `fit_len` bytes (the size of the packet being replaced), it is padded to
+///   exactly `fit_len` bytes, so it can be written in place. Otherwise
+///   [`DEFAULT_PADDING`] bytes are added, but the result never exceeds
+///   `max_len`.
+pub(crate) fn pad_packet(
+    packet: &str,
+    fit_len: Option<usize>,
+    max_len: usize,
+) -> crate::Result<Vec<u8>> {
+    let packet = packet.trim_end_matches(['\0', ' ', '\t', '\r', '\n']);
+    let mut output = Vec::with_capacity(packet.len() + DEFAULT_PADDING);
+    let trailer = match packet.rfind(XPACKET_END) {
+        Some(pos) => {
+            output.extend_from_slice(packet[..pos].trim_end().as_bytes());
+            &packet[pos..]
+        }
+        None => {
+            output.extend_from_slice(XPACKET_HEADER.as_bytes());
+            output.extend_from_slice(packet.trim().as_bytes());
+            XPACKET_TRAILER
+        }
+    };
+    output.push(b'\n');
+
+    let len = output.len() + trailer.len();
+    if len > max_len {
+        return Err(format!("XMP packet is too large ({len} bytes)").into());
+    }
+    let target = match fit_len {
+        Some(n) if len <= n && n <= max_len => n,
+        _ => (len + DEFAULT_PADDING).min(max_len),
+    };
+
+    // Padding lines are 100 bytes each, including the newline.
+    for i in 0..target - len {
+        let remain = target - len - i;
+        output.push(if i % 100 == 99 && remain > 1 {
+            b'\n'
+        } else {
+            b' '
+        });
+    }
+    output.extend_from_slice(trailer.as_bytes());
+    Ok(output)
+}
+
+/// Returns the XMP packet in `data`, without trailing NULs.
+pub(crate) fn packet_str(data: &[u8]) -> crate::Result<String> {
+    let packet = std::str::from_utf8(data).map_err(|_| "invalid XMP packet; not UTF-8")?;
+    Ok(packet.trim_end_matches('\0').to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"/></x:xmpmeta>"#;
+
+    /// Length of the wrapped packet, without padding.
+    const WRAPPED_LEN: usize = XPACKET_HEADER.len() + XMP.len() + 1 + XPACKET_TRAILER.len();
+
+    #[test_case(None, usize::MAX, WRAPPED_LEN + DEFAULT_PADDING)]
+    #[test_case(Some(1000), usize::MAX, 1000)]
+    #[test_case(Some(100), usize::MAX, WRAPPED_LEN + DEFAULT_PADDING)]
+    #[test_case(None, 1000, 1000)]
+    fn pad(fit_len: Option<usize>, max_len: usize, len: usize) {
+        let output = pad_packet(XMP, fit_len, max_len).unwrap();
+        assert_eq!(output.len(), len);
+
+        let s = std::str::from_utf8(&output).unwrap();
+        assert!(s.starts_with(XPACKET_HEADER));
+        assert!(s.ends_with(XPACKET_TRAILER));
+        assert!(s.contains(XMP));
+
+        // Padding is not accumulated
+        assert_eq!(
+            pad_packet(s, None, max_len).unwrap(),
+            pad_packet(XMP, None, max_len).unwrap()
+        );
+        assert_eq!(pad_packet(s, fit_len, max_len).unwrap(), output);
+    }
+
+    #[test]
+    fn too_large() {
+        pad_packet(XMP, None, 100).unwrap_err();
+    }
+}